@@ -0,0 +1,97 @@
+//! Scrape cost of the text encoder at 50k series: a fresh buffer per scrape
+//! (the old path), a size-hinted buffer, and one reused buffer. Prints
+//! allocations per scrape before timing.
+//!
+//! The buffer strategies differ in bytes allocated; most allocations and
+//! most of the time are spent in `Registry::gather`, which every path pays.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use fintek::metrics::{encode_into, ScrapeEncoder};
+use prometheus::{GaugeVec, Opts, Registry};
+
+const SERIES: usize = 50_000;
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn registry() -> Registry {
+    let registry = Registry::new();
+    let prices = GaugeVec::new(
+        Opts::new("stock_price", "Current stock price"),
+        &["symbol", "asset_class", "currency"],
+    )
+    .unwrap();
+    for i in 0..SERIES {
+        prices
+            .with_label_values(&[&format!("SYM{:05}", i), "stock", "USD"])
+            .set(i as f64);
+    }
+    registry.register(Box::new(prices)).unwrap();
+    registry
+}
+
+fn report(name: &str, mut scrape: impl FnMut()) {
+    scrape();
+    let (count, bytes) = (
+        ALLOCATIONS.load(Ordering::Relaxed),
+        ALLOCATED.load(Ordering::Relaxed),
+    );
+    scrape();
+    println!(
+        "{:>14}: {} allocations, {} KiB per scrape",
+        name,
+        ALLOCATIONS.load(Ordering::Relaxed) - count,
+        (ALLOCATED.load(Ordering::Relaxed) - bytes) / 1024
+    );
+}
+
+fn scrape(c: &mut Criterion) {
+    let registry = registry();
+    let fresh = || {
+        let mut buffer = vec![];
+        encode_into(&registry, &mut buffer);
+        String::from_utf8(buffer).unwrap()
+    };
+    let encoder = ScrapeEncoder::default();
+    let mut reused = Vec::new();
+
+    report("fresh", || drop(fresh()));
+    report("size_hinted", || drop(encoder.encode(&registry)));
+    report("reused", || encode_into(&registry, &mut reused));
+
+    let mut group = c.benchmark_group("scrape_50k");
+    group.sample_size(20);
+    group.bench_function("fresh", |b| b.iter(fresh));
+    group.bench_function("size_hinted", |b| b.iter(|| encoder.encode(&registry)));
+    group.bench_function("reused", |b| b.iter(|| encode_into(&registry, &mut reused)));
+    group.finish();
+}
+
+criterion_group!(benches, scrape);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Stamps the binary with what it was built from, for `build_info` and
+// `/api/v1/version`.
+fn main() {
+    let sha = output("git", &["rev-parse", "--short=12", "HEAD"]);
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".into());
+    let rustc = output(&rustc, &["--version"]);
+    println!(
+        "cargo:rustc-env=FINTEK_GIT_SHA={}",
+        sha.as_deref().unwrap_or("unknown")
+    );
+    println!(
+        "cargo:rustc-env=FINTEK_RUSTC={}",
+        rustc.as_deref().unwrap_or("unknown")
+    );
+    println!("cargo:rustc-env=FINTEK_BUILD_DATE={}", build_date());
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
+
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let out = Command::new(program).args(args).output().ok()?;
+    let text = String::from_utf8(out.stdout).ok()?;
+    let text = text.trim();
+    (out.status.success() && !text.is_empty()).then(|| text.to_string())
+}
+
+// UTC day, from SOURCE_DATE_EPOCH when set for reproducible builds.
+fn build_date() -> String {
+    let secs = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .unwrap_or_else(|| {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH);
+            now.map_or(0, |d| d.as_secs() as i64)
+        });
+    // Days since the epoch to a civil date, after Howard Hinnant.
+    let z = secs.div_euclid(86_400) + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
@@ -0,0 +1,91 @@
+//! A small alert bot: price rules from the config's `[alerts]` format,
+//! checked on every price, and a [`Notifier`] of your own for where they
+//! go, here standard output. Alerts are sent from a task, so the bot
+//! waits a moment for the last ones before exiting.
+//!
+//! ```text
+//! cargo run --example alert_bot
+//! ```
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{TimeZone, Utc};
+use fintek::alerts::{Alerts, AlertsConfig};
+use fintek::clock::{Clock, VirtualClock};
+use fintek::config::Config;
+use fintek::engine::Engine;
+use fintek::notify::{Notification, Notifier, Notifiers, NotifyError};
+use fintek::provider::MockProvider;
+use fintek::{PricePoint, Tickers};
+
+const RULES: &str = r#"
+cooldown_secs = 600
+
+[[rules]]
+symbol = "NVDA"
+when = "above"
+price = 140.0
+
+[[rules]]
+symbol = "NVDA"
+name = "NVDA sliding"
+when = "move"
+percent = -3.0
+minutes = 15
+"#;
+
+struct Stdout;
+
+#[async_trait]
+impl Notifier for Stdout {
+    fn name(&self) -> &str {
+        "stdout"
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        println!(
+            "[{:?}] {}: {}",
+            notification.urgency, notification.title, notification.body
+        );
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let start = Utc.with_ymd_and_hms(2026, 10, 15, 14, 30, 0).unwrap();
+    // Up past 140, then a slide.
+    let prices = [132., 135., 138., 141., 143., 142., 139., 137., 136., 138.];
+    let points = prices
+        .iter()
+        .enumerate()
+        .map(|(i, &price)| PricePoint {
+            timestamp: start + chrono::Duration::minutes(5 * i as i64),
+            price,
+        })
+        .collect();
+    let series = BTreeMap::from([("NVDA".to_string(), points)]);
+
+    let rules: AlertsConfig = toml::from_str(RULES).expect("rules parse");
+    let mut notifiers = Notifiers::default();
+    notifiers.push(Arc::new(Stdout));
+    let alerts = Arc::new(Alerts::new(&rules, notifiers));
+
+    let mut config = Config {
+        state_dir: std::env::temp_dir().join("fintek-alert-bot"),
+        ..Config::default()
+    };
+    config.priority.interval_secs = 60;
+    let clock: Arc<dyn Clock> = Arc::new(VirtualClock::new(start));
+    let provider = Arc::new(MockProvider::new(series, clock.clone()));
+    let engine = Engine::new(provider, clock, &config).with_sink(alerts);
+
+    let end = start + chrono::Duration::minutes(5 * prices.len() as i64);
+    engine
+        .run_until(Tickers::new(vec!["NVDA".into()]), Some(end))
+        .await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+}
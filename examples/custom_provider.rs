@@ -0,0 +1,78 @@
+//! Plugs a price source of your own into the engine by implementing
+//! [`Provider`]; here a random walk standing in for an internal feed.
+//!
+//! ```text
+//! cargo run --example custom_provider
+//! ```
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use fintek::clock::{Clock, SystemClock};
+use fintek::config::Config;
+use fintek::engine::Engine;
+use fintek::events::Event;
+use fintek::provider::{Provider, Quote};
+use fintek::{Markets, Tickers};
+use rand::Rng;
+
+/// Moves every symbol up to 0.5% either way on each fetch, from 100.
+#[derive(Default)]
+struct RandomWalk {
+    prices: Mutex<HashMap<String, f64>>,
+}
+
+#[async_trait]
+impl Provider for RandomWalk {
+    fn name(&self) -> &str {
+        "random_walk"
+    }
+
+    async fn fetch_price(&self, symbol: &str) -> Result<Option<Quote>, reqwest::Error> {
+        let mut prices = self.prices.lock().unwrap();
+        let price = prices.entry(symbol.to_string()).or_insert(100.);
+        *price *= 1. + rand::thread_rng().gen_range(-0.005..0.005);
+        Ok(Some(Quote::new(*price)))
+    }
+
+    /// Batches are fetched in one go; the default fetches symbol by symbol.
+    fn batch_size(&self) -> usize {
+        50
+    }
+
+    // Always open.
+    async fn fetch_market_state(&self, _market: &Markets) -> Result<u64, reqwest::Error> {
+        Ok(0)
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let mut config = Config {
+        state_dir: std::env::temp_dir().join("fintek-custom-provider"),
+        // No plan limits to stay under.
+        rate_limits: vec![],
+        ..Config::default()
+    };
+    config.priority.interval_secs = 1;
+    let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+    let engine = Engine::new(Arc::new(RandomWalk::default()), clock, &config);
+    let mut events = engine.events().subscribe();
+
+    // The poll loop runs until dropped, here after five seconds.
+    let tickers = Tickers::new(vec!["INTERNAL:A".into(), "INTERNAL:B".into()]);
+    let prices = async {
+        while let Some(event) = events.recv().await {
+            if let Event::Price(update) = event {
+                println!("{:<12} {:>8.3}", update.symbol, update.price);
+            }
+        }
+    };
+    tokio::select! {
+        _ = engine.run(tickers) => {}
+        _ = prices => {}
+        _ = tokio::time::sleep(Duration::from_secs(5)) => {}
+    }
+}
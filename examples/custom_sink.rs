@@ -0,0 +1,98 @@
+//! Gets every price the engine takes by implementing [`Sink`], here to
+//! keep each symbol's range. Sinks are called in line with polling, so
+//! they should only update state and leave slow work to a task.
+//!
+//! ```text
+//! cargo run --example custom_sink
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use fintek::clock::{Clock, VirtualClock};
+use fintek::config::Config;
+use fintek::engine::Engine;
+use fintek::provider::MockProvider;
+use fintek::sink::{FetchOutcome, PriceUpdate, Sink};
+use fintek::{PricePoint, Tickers};
+
+#[derive(Debug, Clone, Copy)]
+struct Range {
+    low: f64,
+    high: f64,
+    last: f64,
+}
+
+/// Low, high and last price of each symbol, plus fetches that came back
+/// without one.
+#[derive(Default)]
+struct RangeSink {
+    ranges: Mutex<BTreeMap<String, Range>>,
+    misses: Mutex<HashMap<String, usize>>,
+}
+
+impl Sink for RangeSink {
+    fn record(&self, update: &PriceUpdate) {
+        let mut ranges = self.ranges.lock().unwrap();
+        let range = ranges.entry(update.symbol.clone()).or_insert(Range {
+            low: update.price,
+            high: update.price,
+            last: update.price,
+        });
+        range.low = range.low.min(update.price);
+        range.high = range.high.max(update.price);
+        range.last = update.price;
+    }
+
+    fn on_fetch(&self, symbol: &str, outcome: FetchOutcome, _at: DateTime<Utc>) {
+        if outcome != FetchOutcome::Success {
+            *self
+                .misses
+                .lock()
+                .unwrap()
+                .entry(symbol.into())
+                .or_default() += 1;
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let start = Utc.with_ymd_and_hms(2026, 10, 15, 14, 30, 0).unwrap();
+    let series = BTreeMap::from([(
+        "MSFT".to_string(),
+        (0..390)
+            .map(|minute| PricePoint {
+                timestamp: start + Duration::minutes(minute),
+                price: 415. + (minute % 37) as f64 / 10. - (minute % 23) as f64 / 8.,
+            })
+            .collect::<Vec<_>>(),
+    )]);
+
+    let mut config = Config {
+        state_dir: std::env::temp_dir().join("fintek-custom-sink"),
+        ..Config::default()
+    };
+    config.priority.interval_secs = 60;
+    let clock: Arc<dyn Clock> = Arc::new(VirtualClock::new(start));
+    let provider = Arc::new(MockProvider::new(series, clock.clone()));
+    let ranges = Arc::new(RangeSink::default());
+    let engine = Engine::new(provider, clock, &config).with_sink(ranges.clone());
+
+    // A symbol the provider doesn't have, to show up as misses.
+    let tickers = Tickers::new(vec!["MSFT".into(), "NOPE".into()]);
+    engine
+        .run_until(tickers, Some(start + Duration::minutes(390)))
+        .await;
+
+    for (symbol, range) in ranges.ranges.lock().unwrap().iter() {
+        println!(
+            "{}: low {:.2}, high {:.2}, last {:.2}",
+            symbol, range.low, range.high, range.last
+        );
+    }
+    for (symbol, misses) in ranges.misses.lock().unwrap().iter() {
+        println!("{}: {} fetches without a price", symbol, misses);
+    }
+}
@@ -0,0 +1,60 @@
+//! Runs the engine inside another program: an hour of recorded prices is
+//! polled on a virtual clock, which only moves when the engine sleeps, so
+//! it finishes at once. Prices are read back off the event bus.
+//!
+//! ```text
+//! cargo run --example embedded_engine
+//! ```
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use chrono::{Duration, TimeZone, Utc};
+use fintek::clock::{Clock, VirtualClock};
+use fintek::config::Config;
+use fintek::engine::Engine;
+use fintek::events::Event;
+use fintek::provider::MockProvider;
+use fintek::{PricePoint, Tickers};
+
+#[tokio::main]
+async fn main() {
+    let start = Utc.with_ymd_and_hms(2026, 10, 15, 14, 30, 0).unwrap();
+    let end = start + Duration::hours(1);
+    let mut series = BTreeMap::new();
+    for (symbol, open) in [("AAPL", 231.5), ("BTC/USD", 67_250.)] {
+        let points: Vec<PricePoint> = (0..60)
+            .map(|minute| PricePoint {
+                timestamp: start + Duration::minutes(minute),
+                price: open * (1. + (minute as f64 / 10.).sin() / 100.),
+            })
+            .collect();
+        series.insert(symbol.to_string(), points);
+    }
+
+    let mut config = Config {
+        state_dir: std::env::temp_dir().join("fintek-embedded-engine"),
+        ..Config::default()
+    };
+    config.priority.interval_secs = 60;
+    let clock: Arc<dyn Clock> = Arc::new(VirtualClock::new(start));
+    let provider = Arc::new(MockProvider::new(series.clone(), clock.clone()));
+    let engine = Engine::new(provider, clock, &config);
+    let mut events = engine.events().subscribe();
+
+    let symbols = series.keys().cloned().collect();
+    engine.run_until(Tickers::new(symbols), Some(end)).await;
+
+    let mut last = BTreeMap::new();
+    let mut updates = 0;
+    while let Ok(event) = events.try_recv() {
+        if let Event::Price(update) = event {
+            updates += 1;
+            last.insert(update.symbol, (update.timestamp, update.price));
+        }
+    }
+    println!("{} prices over {} to {}", updates, start, end);
+    for (symbol, (at, price)) in last {
+        println!("{:>8} {:>10.2} at {}", symbol, price, at.format("%H:%M"));
+    }
+}
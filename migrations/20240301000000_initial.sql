@@ -0,0 +1,20 @@
+-- The schema stores had before migrations, hence IF NOT EXISTS: those
+-- stores take this as already applied.
+CREATE TABLE IF NOT EXISTS prices (
+    symbol TEXT NOT NULL,
+    price REAL NOT NULL,
+    timestamp TEXT NOT NULL,
+    provider_timestamp TEXT,
+    source TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS prices_symbol_timestamp ON prices (symbol, timestamp);
+CREATE TABLE IF NOT EXISTS alerts (
+    rule TEXT NOT NULL,
+    symbol TEXT,
+    value REAL,
+    title TEXT NOT NULL,
+    fired_at TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    failed TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS alerts_fired_at ON alerts (fired_at);
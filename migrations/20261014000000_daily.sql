@@ -0,0 +1,23 @@
+-- Written by the daily pipeline, one row per symbol and trading day, apart
+-- from the intraday price log.
+CREATE TABLE daily_bars (
+    symbol TEXT NOT NULL,
+    date TEXT NOT NULL,
+    open REAL NOT NULL,
+    high REAL NOT NULL,
+    low REAL NOT NULL,
+    close REAL NOT NULL,
+    volume REAL,
+    source TEXT NOT NULL,
+    PRIMARY KEY (symbol, date)
+);
+CREATE TABLE fundamentals (
+    symbol TEXT NOT NULL,
+    date TEXT NOT NULL,
+    market_cap REAL,
+    trailing_pe REAL,
+    eps REAL,
+    dividend_yield REAL,
+    beta REAL,
+    PRIMARY KEY (symbol, date)
+);
@@ -0,0 +1,10 @@
+-- Reported results against the consensus, one row per symbol and report.
+CREATE TABLE earnings_surprises (
+    symbol TEXT NOT NULL,
+    date TEXT NOT NULL,
+    eps_estimate REAL,
+    eps_actual REAL NOT NULL,
+    revenue_estimate REAL,
+    revenue_actual REAL,
+    PRIMARY KEY (symbol, date)
+);
@@ -0,0 +1,186 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::metrics;
+use crate::notify::{Notification, NotificationKind, Notifiers, Urgency};
+use crate::sink::{PriceUpdate, Sink};
+
+/// A depositary receipt and the foreign share it stands for.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AdrPair {
+    pub adr: String,
+    pub underlying: String,
+    /// Quotes the underlying's currency in the ADR's, e.g. `EUR/USD` for a
+    /// US listing of a share traded in euros. None for the same currency.
+    #[serde(default)]
+    pub fx: Option<String>,
+    /// Underlying shares one ADR represents.
+    #[serde(default = "default_ratio")]
+    pub ratio: f64,
+    /// Premium or discount, in percent either way, worth alerting on. No
+    /// alerts when unset.
+    #[serde(default)]
+    pub alert_percent: Option<f64>,
+}
+
+fn default_ratio() -> f64 {
+    1.
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AdrConfig {
+    pub pairs: Vec<AdrPair>,
+    /// A leg's price older than this, as when the foreign market is
+    /// closed, leaves the pair unpriced.
+    pub max_age_secs: u64,
+}
+
+impl Default for AdrConfig {
+    fn default() -> Self {
+        AdrConfig {
+            pairs: vec![],
+            max_age_secs: 900,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    prices: HashMap<String, (f64, DateTime<Utc>)>,
+    // Per pair, whether its dislocation was past the alert threshold.
+    dislocated: Vec<bool>,
+}
+
+/// Prices each ADR against its underlying converted at the current FX
+/// rate, exports the premium and notifies when a dislocation past a pair's
+/// threshold opens.
+pub struct AdrMonitor {
+    pairs: Vec<AdrPair>,
+    max_age: Duration,
+    notifiers: Notifiers,
+    state: Mutex<State>,
+}
+
+impl AdrMonitor {
+    /// `None` without pairs.
+    pub fn new(config: &AdrConfig, notifiers: Notifiers) -> Option<Self> {
+        if config.pairs.is_empty() {
+            return None;
+        }
+        Some(AdrMonitor {
+            pairs: config.pairs.clone(),
+            max_age: Duration::seconds(config.max_age_secs as i64),
+            notifiers,
+            state: Mutex::new(State {
+                prices: HashMap::new(),
+                dislocated: vec![false; config.pairs.len()],
+            }),
+        })
+    }
+
+    /// Both legs and the FX rate of every pair, for the engine to poll.
+    pub fn symbols(&self) -> Vec<String> {
+        self.pairs
+            .iter()
+            .flat_map(|p| [Some(&p.adr), Some(&p.underlying), p.fx.as_ref()])
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    // Notifications for the dislocations `update` opens, if `fire`.
+    fn evaluate(&self, update: &PriceUpdate, fire: bool) -> Vec<Notification> {
+        let now = update.timestamp;
+        let mut state = self.state.lock().unwrap();
+        let State { prices, dislocated } = &mut *state;
+        prices.insert(update.symbol.clone(), (update.price, now));
+        let fresh = |symbol: &str| {
+            prices
+                .get(symbol)
+                .filter(|(_, at)| now - *at <= self.max_age)
+                .map(|(price, _)| *price)
+        };
+
+        let mut notifications = vec![];
+        for (pair, dislocated) in self.pairs.iter().zip(dislocated.iter_mut()) {
+            let legs = [Some(&pair.adr), Some(&pair.underlying), pair.fx.as_ref()];
+            if !legs.iter().flatten().any(|s| **s == update.symbol) {
+                continue;
+            }
+            let priced = (|| {
+                let (adr, underlying) = (fresh(&pair.adr)?, fresh(&pair.underlying)?);
+                let fx = pair.fx.as_deref().map_or(Some(1.), fresh)?;
+                let implied = underlying * fx * pair.ratio;
+                (implied > 0.).then(|| (adr, underlying, implied, (adr / implied - 1.) * 100.))
+            })();
+            let Some((adr, underlying, implied, premium)) = priced else {
+                metrics::update_adr_premium(&pair.adr, &pair.underlying, None);
+                continue;
+            };
+            metrics::update_adr_premium(&pair.adr, &pair.underlying, Some((implied, premium)));
+
+            let Some(threshold) = pair.alert_percent else {
+                continue;
+            };
+            let holds = premium.abs() >= threshold;
+            let opened = holds && !*dislocated;
+            *dislocated = holds;
+            if !opened || !fire {
+                continue;
+            }
+            let side = if premium > 0. { "premium" } else { "discount" };
+            let title = format!("{} ADR dislocation", pair.adr);
+            info!(adr = %pair.adr, underlying = %pair.underlying, premium, "ADR dislocation");
+            notifications.push(Notification {
+                title: title.clone(),
+                body: format!(
+                    "{} trades at {} against {:.2} implied by {} at {}, a {:.2}% {}",
+                    pair.adr,
+                    adr,
+                    implied,
+                    pair.underlying,
+                    underlying,
+                    premium.abs(),
+                    side
+                ),
+                urgency: Urgency::High,
+                kind: NotificationKind::Alert,
+                symbols: vec![pair.adr.clone(), pair.underlying.clone()],
+                rule: Some(title),
+                value: Some(premium),
+                labels: BTreeMap::new(),
+            });
+        }
+        notifications
+    }
+}
+
+impl Sink for AdrMonitor {
+    fn record(&self, update: &PriceUpdate) {
+        let notifications = self.evaluate(update, true);
+        if notifications.is_empty() {
+            return;
+        }
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                let notifiers = self.notifiers.clone();
+                handle.spawn(async move {
+                    for notification in &notifications {
+                        notifiers.notify(notification).await;
+                    }
+                });
+            }
+            Err(_) => error!("No runtime to send alerts on"),
+        }
+    }
+
+    // Restored prices rebuild the legs without alerting again.
+    fn replay(&self, update: &PriceUpdate) {
+        self.evaluate(update, false);
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::notify::{Notification, NotificationKind, Notifiers, Urgency, WebhookNotifier};
+use crate::sink::{PriceUpdate, Sink};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AlertsConfig {
+    pub rules: Vec<AlertRule>,
+    /// Least time between two firings of one rule, unless the rule sets its
+    /// own.
+    pub cooldown_secs: u64,
+    /// URLs alerts are POSTed to on top of the configured notifiers. Slack
+    /// incoming webhooks take the payload as is.
+    pub webhooks: Vec<String>,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        AlertsConfig {
+            rules: vec![],
+            cooldown_secs: 3600,
+            webhooks: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AlertRule {
+    pub symbol: String,
+    /// Shown in the alert and its history. Made up from the condition when
+    /// unset.
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub condition: Condition,
+    #[serde(default)]
+    pub cooldown_secs: Option<u64>,
+    #[serde(default = "default_urgency")]
+    pub urgency: Urgency,
+}
+
+fn default_urgency() -> Urgency {
+    Urgency::High
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "when", rename_all = "lowercase")]
+pub enum Condition {
+    Above {
+        price: f64,
+    },
+    Below {
+        price: f64,
+    },
+    /// A change of `percent` or more within `minutes`: a fall when
+    /// negative, a rise when positive.
+    Move {
+        percent: f64,
+        minutes: u64,
+    },
+}
+
+impl AlertRule {
+    pub fn name(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        match self.condition {
+            Condition::Above { price } => format!("{} above {}", self.symbol, price),
+            Condition::Below { price } => format!("{} below {}", self.symbol, price),
+            Condition::Move { percent, minutes } => {
+                format!("{} {:+}% in {}m", self.symbol, percent, minutes)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct RuleState {
+    // Whether the condition held on the last price, so a rule fires when it
+    // starts to hold rather than on every price while it does.
+    holding: bool,
+    last_fired: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    // Recent prices per symbol, as long as its longest move rule looks back.
+    prices: HashMap<String, VecDeque<(DateTime<Utc>, f64)>>,
+    rules: Vec<RuleState>,
+}
+
+/// Checks every price against the configured rules and notifies when one
+/// starts to hold, at most once per cooldown.
+pub struct Alerts {
+    rules: Vec<AlertRule>,
+    cooldown: Duration,
+    notifiers: Notifiers,
+    state: Mutex<State>,
+}
+
+impl Alerts {
+    /// Alerts go to `notifiers`, plus the config's webhooks.
+    pub fn new(config: &AlertsConfig, mut notifiers: Notifiers) -> Self {
+        for url in &config.webhooks {
+            notifiers.push(Arc::new(WebhookNotifier::new(url)));
+        }
+        Alerts {
+            rules: config.rules.clone(),
+            cooldown: Duration::seconds(config.cooldown_secs as i64),
+            notifiers,
+            state: Mutex::new(State {
+                prices: HashMap::new(),
+                rules: config.rules.iter().map(|_| RuleState::default()).collect(),
+            }),
+        }
+    }
+
+    // How far back move rules on `symbol` look.
+    fn lookback(&self, symbol: &str) -> Option<Duration> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.symbol == symbol)
+            .filter_map(|rule| match rule.condition {
+                Condition::Move { minutes, .. } => Some(Duration::minutes(minutes as i64)),
+                _ => None,
+            })
+            .max()
+    }
+
+    // Notifications for the rules `update` sets off, if `fire`.
+    fn evaluate(&self, update: &PriceUpdate, fire: bool) -> Vec<Notification> {
+        let now = update.timestamp;
+        let price = update.price;
+        let mut state = self.state.lock().unwrap();
+        let State { prices, rules } = &mut *state;
+        let window = self.lookback(&update.symbol).map(|lookback| {
+            let window = prices.entry(update.symbol.clone()).or_default();
+            window.push_back((now, price));
+            while window.front().is_some_and(|(at, _)| now - *at > lookback) {
+                window.pop_front();
+            }
+            window
+        });
+        let mut notifications = vec![];
+        for (rule, rule_state) in self.rules.iter().zip(rules.iter_mut()) {
+            if rule.symbol != update.symbol {
+                continue;
+            }
+            let change = |minutes: u64| {
+                let since = now - Duration::minutes(minutes as i64);
+                let (_, first) = window.as_ref()?.iter().find(|(at, _)| *at >= since)?;
+                (*first != 0.).then(|| (price - first) / first * 100.)
+            };
+            let (holds, detail) = match rule.condition {
+                Condition::Above { price: limit } => (
+                    price > limit,
+                    format!("{} is at {}, above {}", rule.symbol, price, limit),
+                ),
+                Condition::Below { price: limit } => (
+                    price < limit,
+                    format!("{} is at {}, below {}", rule.symbol, price, limit),
+                ),
+                Condition::Move { percent, minutes } => {
+                    let change = change(minutes).unwrap_or(0.);
+                    let holds = match percent {
+                        p if p < 0. => change <= p,
+                        p => change >= p,
+                    };
+                    let detail = format!(
+                        "{} moved {:+.2}% in the last {} minutes, to {}",
+                        rule.symbol, change, minutes, price
+                    );
+                    (holds, detail)
+                }
+            };
+            let started = holds && !rule_state.holding;
+            rule_state.holding = holds;
+            if !started {
+                continue;
+            }
+            let cooldown = rule
+                .cooldown_secs
+                .map_or(self.cooldown, |secs| Duration::seconds(secs as i64));
+            if rule_state.last_fired.is_some_and(|at| now - at < cooldown) {
+                continue;
+            }
+            rule_state.last_fired = Some(now);
+            if !fire {
+                continue;
+            }
+            let name = rule.name();
+            info!(rule = %name, symbol = %rule.symbol, price, "Alert rule fired");
+            notifications.push(Notification {
+                title: name.clone(),
+                body: detail,
+                urgency: rule.urgency,
+                kind: NotificationKind::Alert,
+                symbols: vec![rule.symbol.clone()],
+                rule: Some(name),
+                value: Some(price),
+                labels: BTreeMap::new(),
+            });
+        }
+        notifications
+    }
+}
+
+impl Sink for Alerts {
+    fn record(&self, update: &PriceUpdate) {
+        let notifications = self.evaluate(update, true);
+        if notifications.is_empty() {
+            return;
+        }
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                let notifiers = self.notifiers.clone();
+                handle.spawn(async move {
+                    for notification in &notifications {
+                        notifiers.notify(notification).await;
+                    }
+                });
+            }
+            Err(_) => error!("No runtime to send alerts on"),
+        }
+    }
+
+    // Restored prices rebuild the windows and cooldowns without alerting
+    // again.
+    fn replay(&self, update: &PriceUpdate) {
+        self.evaluate(update, false);
+    }
+}
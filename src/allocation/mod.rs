@@ -0,0 +1,128 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::CycleContext;
+use crate::metrics;
+use crate::paper::{PaperAccount, Portfolio};
+use crate::sink::{PriceUpdate, Sink};
+use crate::symbol::{self, SymbolInfo};
+use crate::AssetClass;
+
+/// Classification the symbol itself doesn't carry.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SymbolMeta {
+    pub symbol: String,
+    pub sector: Option<String>,
+    /// Overrides the region implied by the listing exchange.
+    pub region: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AllocationConfig {
+    pub symbols: Vec<SymbolMeta>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Dimension {
+    AssetClass,
+    Sector,
+    Region,
+}
+
+impl Dimension {
+    const ALL: [Dimension; 3] = [Dimension::AssetClass, Dimension::Sector, Dimension::Region];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Dimension::AssetClass => "asset_class",
+            Dimension::Sector => "sector",
+            Dimension::Region => "region",
+        }
+    }
+}
+
+/// Splits paper equity by sector, asset class and region, cash counting as
+/// its own group so each dimension sums to one. Exported every cycle as
+/// `portfolio_allocation_ratio` for drift dashboards and alert rules.
+pub struct AllocationTracker {
+    config: AllocationConfig,
+    paper: Arc<PaperAccount>,
+    exported: Mutex<HashSet<(Dimension, String)>>,
+}
+
+impl AllocationTracker {
+    pub fn new(config: AllocationConfig, paper: Arc<PaperAccount>) -> Self {
+        AllocationTracker {
+            config,
+            paper,
+            exported: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn meta(&self, symbol: &str) -> Option<&SymbolMeta> {
+        self.config.symbols.iter().find(|m| m.symbol == symbol)
+    }
+
+    fn group(&self, symbol: &str, dimension: Dimension) -> String {
+        let info = SymbolInfo::parse(symbol);
+        let meta = self.meta(symbol);
+        match dimension {
+            Dimension::AssetClass => info.asset_class.as_str().to_string(),
+            Dimension::Sector => meta
+                .and_then(|m| m.sector.clone())
+                .unwrap_or_else(|| "unclassified".into()),
+            Dimension::Region => meta.and_then(|m| m.region.clone()).unwrap_or_else(|| {
+                match (info.exchange, info.asset_class) {
+                    (Some(exchange), _) => symbol::region(exchange).into(),
+                    (None, AssetClass::Stock) => symbol::region(crate::StockMarket::NYSE).into(),
+                    (None, _) => "Global".into(),
+                }
+            }),
+        }
+    }
+
+    /// Shares of equity per dimension and group.
+    pub fn allocations(&self, portfolio: &Portfolio) -> BTreeMap<(Dimension, String), f64> {
+        let mut shares = BTreeMap::new();
+        if portfolio.equity <= 0. {
+            return shares;
+        }
+        for dimension in Dimension::ALL {
+            *shares.entry((dimension, "cash".into())).or_default() +=
+                portfolio.cash / portfolio.equity;
+            for position in &portfolio.positions {
+                let value = position
+                    .market_value
+                    .unwrap_or(position.average_cost * position.quantity);
+                *shares
+                    .entry((dimension, self.group(&position.symbol, dimension)))
+                    .or_default() += value / portfolio.equity;
+            }
+        }
+        shares
+    }
+
+    fn export(&self) {
+        let shares = self.allocations(&self.paper.portfolio());
+        let mut exported = self.exported.lock().unwrap();
+        for key in exported.iter().filter(|k| !shares.contains_key(*k)) {
+            metrics::update_allocation(key.0.as_str(), &key.1, None);
+        }
+        for ((dimension, group), share) in &shares {
+            metrics::update_allocation(dimension.as_str(), group, Some(*share));
+        }
+        *exported = shares.into_keys().collect();
+    }
+}
+
+impl Sink for AllocationTracker {
+    fn record(&self, _update: &PriceUpdate) {}
+
+    fn on_cycle(&self, _cycle: &CycleContext) {
+        self.export();
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use reqwest::Error;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, instrument, warn};
+
+use crate::engine::Engine;
+use crate::metrics;
+use crate::notify::{Notification, NotificationKind, Notifiers, Urgency};
+use crate::provider::endpoints::{self, Endpoint, PriceTarget, RatingCounts};
+use crate::provider::{base_url, TWELVEDATA_URL};
+use crate::sink::{PriceUpdate, Sink};
+use crate::{read_tickers, AssetClass};
+
+pub const ANALYSTS_FILE: &str = "analysts.json";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AnalystsConfig {
+    /// Two requests per watched stock on every refresh.
+    pub enabled: bool,
+    pub refresh_hours: u64,
+    /// A move of the average target by this much from the last one alerts.
+    pub target_change_percent: f64,
+}
+
+impl Default for AnalystsConfig {
+    fn default() -> Self {
+        AnalystsConfig {
+            enabled: false,
+            refresh_hours: 24,
+            target_change_percent: 5.,
+        }
+    }
+}
+
+/// What the analysts covering a symbol expect, as of the last refresh.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct AnalystConsensus {
+    pub symbol: String,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub target_average: Option<f64>,
+    pub target_median: Option<f64>,
+    pub target_high: Option<f64>,
+    pub target_low: Option<f64>,
+    /// From 0 for strong sell to 10 for strong buy.
+    pub rating: Option<f64>,
+    pub strong_buy: u32,
+    pub buy: u32,
+    pub hold: u32,
+    pub sell: u32,
+    pub strong_sell: u32,
+}
+
+#[instrument(skip(api_key))]
+pub async fn fetch_price_target(
+    base_url: &str,
+    symbol: &str,
+    api_key: &str,
+) -> Result<Option<PriceTarget>, Error> {
+    let url =
+        endpoints::url::<endpoints::PriceTargets>(base_url, &format!("symbol={}", symbol), api_key);
+    let data = reqwest::get(&url).await?.text().await?;
+    Ok(
+        match endpoints::parse::<<endpoints::PriceTargets as Endpoint>::Response>(&data) {
+            Ok(Ok(response)) => Some(response.price_target),
+            Ok(Err(error)) => {
+                warn!(symbol, code = error.code, message = %error.message, "Price target request failed");
+                None
+            }
+            Err(e) => {
+                warn!(symbol, error = %e, "Unexpected price target response");
+                None
+            }
+        },
+    )
+}
+
+#[instrument(skip(api_key))]
+pub async fn fetch_recommendations(
+    base_url: &str,
+    symbol: &str,
+    api_key: &str,
+) -> Result<Option<(Option<f64>, RatingCounts)>, Error> {
+    let url = endpoints::url::<endpoints::Recommendations>(
+        base_url,
+        &format!("symbol={}", symbol),
+        api_key,
+    );
+    let data = reqwest::get(&url).await?.text().await?;
+    Ok(
+        match endpoints::parse::<<endpoints::Recommendations as Endpoint>::Response>(&data) {
+            Ok(Ok(response)) => Some((response.rating, response.trends.current_month)),
+            Ok(Err(error)) => {
+                warn!(symbol, code = error.code, message = %error.message, "Recommendations request failed");
+                None
+            }
+            Err(e) => {
+                warn!(symbol, error = %e, "Unexpected recommendations response");
+                None
+            }
+        },
+    )
+}
+
+/// Keeps the consensus rating and price target of every watched stock,
+/// refreshed on a schedule and saved so a restart compares new targets
+/// with the last ones seen. Exports the upside to the average target at
+/// every price, and notifies when the target moves by more than the
+/// configured percentage.
+pub struct AnalystTracker {
+    config: AnalystsConfig,
+    path: PathBuf,
+    base_url: String,
+    notifiers: Notifiers,
+    consensus: RwLock<BTreeMap<String, AnalystConsensus>>,
+}
+
+impl AnalystTracker {
+    pub fn new(config: &AnalystsConfig, state_dir: &Path, notifiers: Notifiers) -> Self {
+        let path = state_dir.join(ANALYSTS_FILE);
+        let consensus = match std::fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+                warn!(path = %path.display(), error = %e, "Ignoring corrupt analyst state");
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        AnalystTracker {
+            config: config.clone(),
+            path,
+            base_url: TWELVEDATA_URL.into(),
+            notifiers,
+            consensus: RwLock::new(consensus),
+        }
+    }
+
+    pub fn with_base_url(mut self, url: &str) -> Self {
+        self.base_url = base_url(url);
+        self
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<AnalystConsensus> {
+        self.consensus.read().unwrap().get(symbol).cloned()
+    }
+
+    pub fn all(&self) -> Vec<AnalystConsensus> {
+        self.consensus.read().unwrap().values().cloned().collect()
+    }
+
+    // An alert when the average target moved far enough from `old`.
+    fn target_change(
+        &self,
+        old: &AnalystConsensus,
+        new: &AnalystConsensus,
+    ) -> Option<Notification> {
+        let (from, to) = (old.target_average?, new.target_average?);
+        if from <= 0. {
+            return None;
+        }
+        let change = (to / from - 1.) * 100.;
+        if change.abs() < self.config.target_change_percent {
+            return None;
+        }
+        let direction = if change > 0. { "raised" } else { "cut" };
+        Some(Notification {
+            title: format!("{} price target {}", new.symbol, direction),
+            body: format!(
+                "Average analyst target for {} {} from {:.2} to {:.2} ({:+.1}%)",
+                new.symbol, direction, from, to, change
+            ),
+            urgency: Urgency::Normal,
+            kind: NotificationKind::Alert,
+            symbols: vec![new.symbol.clone()],
+            rule: Some("analyst_target_change".into()),
+            value: Some(to),
+            labels: BTreeMap::new(),
+        })
+    }
+
+    async fn refresh_symbol(&self, engine: &Engine, symbol: &str, api_key: &str) {
+        engine.limiter().acquire(1).await;
+        let target = match fetch_price_target(&self.base_url, symbol, api_key).await {
+            Ok(target) => target,
+            Err(e) => {
+                error!(symbol, error = ?e, "Failed to fetch price target");
+                None
+            }
+        };
+        engine.limiter().acquire(1).await;
+        let ratings = match fetch_recommendations(&self.base_url, symbol, api_key).await {
+            Ok(ratings) => ratings,
+            Err(e) => {
+                error!(symbol, error = ?e, "Failed to fetch recommendations");
+                None
+            }
+        };
+        if target.is_none() && ratings.is_none() {
+            return;
+        }
+        let old = self.get(symbol).unwrap_or_default();
+        let mut new = AnalystConsensus {
+            symbol: symbol.to_string(),
+            updated_at: Some(engine.clock().now()),
+            ..old.clone()
+        };
+        if let Some(target) = target {
+            new.target_average = target.average;
+            new.target_median = target.median;
+            new.target_high = target.high;
+            new.target_low = target.low;
+        }
+        if let Some((rating, counts)) = ratings {
+            new.rating = rating;
+            new.strong_buy = counts.strong_buy;
+            new.buy = counts.buy;
+            new.hold = counts.hold;
+            new.sell = counts.sell;
+            new.strong_sell = counts.strong_sell;
+        }
+        metrics::update_analyst_consensus(&new);
+        if let Some(price) = engine.latest().and_then(|latest| latest.get(symbol)) {
+            upside(&new, price.price);
+        }
+        if let Some(notification) = self.target_change(&old, &new) {
+            self.notifiers.notify(&notification).await;
+        }
+        self.consensus
+            .write()
+            .unwrap()
+            .insert(symbol.to_string(), new);
+    }
+
+    /// Refreshes every stock among `symbols` and saves the consensus.
+    pub async fn refresh(&self, engine: &Engine, symbols: &[String], api_key: &str) {
+        let stocks: Vec<&String> = symbols
+            .iter()
+            .filter(|s| AssetClass::of(s) == AssetClass::Stock)
+            .collect();
+        for symbol in &stocks {
+            self.refresh_symbol(engine, symbol, api_key).await;
+        }
+        let data = serde_json::to_vec_pretty(&*self.consensus.read().unwrap())
+            .expect("consensus serializes");
+        if let Err(e) = std::fs::write(&self.path, data) {
+            error!(path = %self.path.display(), error = %e, "Failed to save analyst state");
+        }
+        info!(symbols = stocks.len(), "Refreshed analyst consensus");
+    }
+
+    pub async fn run(self: Arc<Self>, engine: Arc<Engine>, api_key: String) {
+        loop {
+            match read_tickers().await {
+                Ok(tickers) => self.refresh(&engine, tickers.get_tickers(), &api_key).await,
+                Err(e) => warn!(error = %e, "Failed to read tickers, skipping analyst refresh"),
+            }
+            engine
+                .clock()
+                .sleep(Duration::from_secs(self.config.refresh_hours.max(1) * 3600))
+                .await;
+        }
+    }
+}
+
+fn upside(consensus: &AnalystConsensus, price: f64) {
+    if let Some(target) = consensus.target_average.filter(|_| price > 0.) {
+        metrics::update_analyst_upside(&consensus.symbol, (target / price - 1.) * 100.);
+    }
+}
+
+impl Sink for AnalystTracker {
+    fn record(&self, update: &PriceUpdate) {
+        if let Some(consensus) = self.consensus.read().unwrap().get(&update.symbol) {
+            upside(consensus, update.price);
+        }
+    }
+}
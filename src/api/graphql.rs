@@ -0,0 +1,382 @@
+use std::sync::Arc;
+
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, Enum, Object, Request, Response, Schema,
+    SimpleObject,
+};
+use axum::extract::State;
+use axum::{Extension, Json};
+use chrono::{DateTime, Duration, Utc};
+
+use super::{space, ApiState};
+use crate::auth::Caller;
+use crate::history::{self, Series};
+use crate::namespaces::Space;
+use crate::portfolio::{PositionValue, Valuation};
+use crate::provider::DayQuote;
+use crate::sink::PriceUpdate;
+use crate::storage::{count_rules, AlertOutcome, AlertRecord, RuleCount};
+use crate::PricePoint;
+
+pub type FintekSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// The schema, reading from the same handles as the JSON API.
+pub fn schema(state: ApiState) -> FintekSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(state)
+        .finish()
+}
+
+// The caller's namespace, `None` for shared-state callers, as `space` finds it.
+struct Namespace(Option<Arc<Space>>);
+
+// Errors come back in the response's `errors`, still with a 200.
+#[utoipa::path(
+    post,
+    path = "/api/v1/graphql",
+    tag = "graphql",
+    request_body(content = serde_json::Value, description = "`query`, and optional `variables` and `operationName`"),
+    responses(
+        (status = 200, body = serde_json::Value, description = "The query's `data` and `errors`"),
+    )
+)]
+pub(super) async fn execute(
+    State(state): State<ApiState>,
+    Extension(schema): Extension<FintekSchema>,
+    caller: Option<Extension<Caller>>,
+    Json(request): Json<Request>,
+) -> Json<Response> {
+    let space = match space(&state, caller) {
+        Ok(space) => space,
+        Err(e) => {
+            return Json(Response::from_errors(vec![
+                async_graphql::ServerError::new(e.to_string(), None),
+            ]))
+        }
+    };
+    Json(schema.execute(request.data(Namespace(space))).await)
+}
+
+#[derive(SimpleObject)]
+pub struct Price {
+    symbol: String,
+    price: f64,
+    /// When fintek received the price, by the local clock.
+    timestamp: DateTime<Utc>,
+    /// When the provider says the price was set, if it says.
+    provider_timestamp: Option<DateTime<Utc>>,
+    day: Option<Day>,
+}
+
+impl From<PriceUpdate> for Price {
+    fn from(update: PriceUpdate) -> Self {
+        Price {
+            symbol: update.symbol,
+            price: update.price,
+            timestamp: update.timestamp,
+            provider_timestamp: update.provider_timestamp,
+            day: update.day.map(Day::from),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Day {
+    open: f64,
+    high: f64,
+    low: f64,
+    previous_close: f64,
+    volume: Option<f64>,
+    change_percent: f64,
+    open_interest: Option<f64>,
+}
+
+impl From<DayQuote> for Day {
+    fn from(day: DayQuote) -> Self {
+        Day {
+            open: day.open,
+            high: day.high,
+            low: day.low,
+            previous_close: day.previous_close,
+            volume: day.volume,
+            change_percent: day.change_percent,
+            open_interest: day.open_interest,
+        }
+    }
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Auto,
+    Tick,
+    Minute,
+    Day,
+}
+
+impl From<Resolution> for history::Resolution {
+    fn from(resolution: Resolution) -> Self {
+        match resolution {
+            Resolution::Auto => history::Resolution::Auto,
+            Resolution::Tick => history::Resolution::Tick,
+            Resolution::Minute => history::Resolution::Minute,
+            Resolution::Day => history::Resolution::Day,
+        }
+    }
+}
+
+impl From<history::Resolution> for Resolution {
+    fn from(resolution: history::Resolution) -> Self {
+        match resolution {
+            history::Resolution::Auto => Resolution::Auto,
+            history::Resolution::Tick => Resolution::Tick,
+            history::Resolution::Minute => Resolution::Minute,
+            history::Resolution::Day => Resolution::Day,
+        }
+    }
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+pub enum Adjustment {
+    Raw,
+    Split,
+}
+
+impl From<Adjustment> for history::Adjustment {
+    fn from(adjustment: Adjustment) -> Self {
+        match adjustment {
+            Adjustment::Raw => history::Adjustment::Raw,
+            Adjustment::Split => history::Adjustment::Split,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct History {
+    symbol: String,
+    adjustment: Adjustment,
+    resolution: Resolution,
+    points: Vec<Point>,
+}
+
+#[derive(SimpleObject)]
+pub struct Point {
+    timestamp: DateTime<Utc>,
+    price: f64,
+}
+
+impl From<PricePoint> for Point {
+    fn from(point: PricePoint) -> Self {
+        Point {
+            timestamp: point.timestamp,
+            price: point.price,
+        }
+    }
+}
+
+/// Totals are over the positions with a price so far.
+#[derive(SimpleObject)]
+pub struct Portfolio {
+    total_value: f64,
+    total_cost: f64,
+    unrealized_pnl: f64,
+    positions: Vec<Position>,
+}
+
+impl From<Valuation> for Portfolio {
+    fn from(valuation: Valuation) -> Self {
+        Portfolio {
+            total_value: valuation.total_value,
+            total_cost: valuation.total_cost,
+            unrealized_pnl: valuation.unrealized_pnl,
+            positions: valuation
+                .positions
+                .into_iter()
+                .map(Position::from)
+                .collect(),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Position {
+    symbol: String,
+    quantity: f64,
+    cost_basis: f64,
+    last_price: Option<f64>,
+    market_value: Option<f64>,
+    unrealized_pnl: Option<f64>,
+}
+
+impl From<PositionValue> for Position {
+    fn from(position: PositionValue) -> Self {
+        Position {
+            symbol: position.symbol,
+            quantity: position.quantity,
+            cost_basis: position.cost_basis,
+            last_price: position.last_price,
+            market_value: position.market_value,
+            unrealized_pnl: position.unrealized_pnl,
+        }
+    }
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Sent,
+    Partial,
+    Failed,
+    Deduplicated,
+}
+
+impl From<AlertOutcome> for Outcome {
+    fn from(outcome: AlertOutcome) -> Self {
+        match outcome {
+            AlertOutcome::Sent => Outcome::Sent,
+            AlertOutcome::Partial => Outcome::Partial,
+            AlertOutcome::Failed => Outcome::Failed,
+            AlertOutcome::Deduplicated => Outcome::Deduplicated,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Alert {
+    rule: String,
+    symbol: Option<String>,
+    /// The symbol's price when it fired, when known.
+    value: Option<f64>,
+    title: String,
+    fired_at: DateTime<Utc>,
+    outcome: Outcome,
+    /// Notifiers that failed to deliver it.
+    failed: Vec<String>,
+}
+
+impl From<AlertRecord> for Alert {
+    fn from(alert: AlertRecord) -> Self {
+        Alert {
+            rule: alert.rule,
+            symbol: alert.symbol,
+            value: alert.value,
+            title: alert.title,
+            fired_at: alert.fired_at,
+            outcome: alert.outcome.into(),
+            failed: alert.failed,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Rule {
+    rule: String,
+    fired: u64,
+    /// Times some notifier failed to deliver it.
+    failed: u64,
+    last_fired: DateTime<Utc>,
+}
+
+impl From<RuleCount> for Rule {
+    fn from(count: RuleCount) -> Self {
+        Rule {
+            rule: count.rule,
+            fired: count.fired,
+            failed: count.failed,
+            last_fired: count.last_fired,
+        }
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Latest prices of watched symbols, by symbol.
+    async fn prices(&self, ctx: &Context<'_>) -> Vec<Price> {
+        let mut prices = ctx.data_unchecked::<ApiState>().prices.snapshot();
+        prices.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        prices.into_iter().map(Price::from).collect()
+    }
+
+    /// The latest price of a watched symbol.
+    async fn price(&self, ctx: &Context<'_>, symbol: String) -> Option<Price> {
+        let state = ctx.data_unchecked::<ApiState>();
+        state.prices.get(&symbol).map(Price::from)
+    }
+
+    /// Prices of a symbol since `since`, the last day by default.
+    async fn history(
+        &self,
+        ctx: &Context<'_>,
+        symbol: String,
+        since: Option<DateTime<Utc>>,
+        #[graphql(default_with = "Resolution::Auto")] resolution: Resolution,
+        #[graphql(default_with = "Adjustment::Raw")] adjustment: Adjustment,
+    ) -> History {
+        let state = ctx.data_unchecked::<ApiState>();
+        let now = Utc::now();
+        let since = since.unwrap_or_else(|| now - Duration::days(1));
+        let Series { resolution, points } =
+            state
+                .history
+                .query(&symbol, since, now, resolution.into(), adjustment.into());
+        History {
+            symbol,
+            adjustment,
+            resolution: resolution.into(),
+            points: points.into_iter().map(Point::from).collect(),
+        }
+    }
+
+    /// The caller's namespace portfolio, or the shared one.
+    async fn portfolio(&self, ctx: &Context<'_>) -> Portfolio {
+        match &ctx.data_unchecked::<Namespace>().0 {
+            Some(space) => space.portfolio.valuation().into(),
+            None => ctx
+                .data_unchecked::<ApiState>()
+                .portfolio
+                .valuation()
+                .into(),
+        }
+    }
+
+    /// Alerts fired since `since`, a day ago by default, newest first.
+    /// Namespaced callers only see alerts on symbols they watch or hold.
+    async fn alerts(
+        &self,
+        ctx: &Context<'_>,
+        symbol: Option<String>,
+        since: Option<DateTime<Utc>>,
+    ) -> async_graphql::Result<Vec<Alert>> {
+        let state = ctx.data_unchecked::<ApiState>();
+        let store = state.alerts.as_ref().ok_or("no alert store configured")?;
+        let since = since.unwrap_or_else(|| Utc::now() - Duration::days(1));
+        let mut alerts = store.alerts(symbol.as_deref(), since).await?;
+        if let Some(space) = &ctx.data_unchecked::<Namespace>().0 {
+            let symbols = space.symbols();
+            alerts.retain(|a| a.symbol.as_ref().is_some_and(|s| symbols.contains(s)));
+        }
+        Ok(alerts.into_iter().map(Alert::from).collect())
+    }
+
+    /// Alerts fired per rule since `since`, a day ago by default.
+    /// Namespaced callers only count alerts on symbols they watch or hold.
+    async fn alert_rules(
+        &self,
+        ctx: &Context<'_>,
+        since: Option<DateTime<Utc>>,
+    ) -> async_graphql::Result<Vec<Rule>> {
+        let state = ctx.data_unchecked::<ApiState>();
+        let store = state.alerts.as_ref().ok_or("no alert store configured")?;
+        let since = since.unwrap_or_else(|| Utc::now() - Duration::days(1));
+        let counts = match &ctx.data_unchecked::<Namespace>().0 {
+            Some(space) => {
+                let symbols = space.symbols();
+                let mut alerts = store.alerts(None, since).await?;
+                alerts.retain(|a| a.symbol.as_ref().is_some_and(|s| symbols.contains(s)));
+                count_rules(&alerts)
+            }
+            None => store.rule_counts(since).await?,
+        };
+        Ok(counts.into_iter().map(Rule::from).collect())
+    }
+}
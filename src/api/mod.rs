@@ -0,0 +1,1705 @@
+pub mod graphql;
+pub mod openapi;
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post, put};
+use axum::{middleware, Extension, Json, Router};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{error, info};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::analysts::AnalystTracker;
+use crate::auth::{self, Caller, TokenStore};
+use crate::chaos::{Chaos, Faults};
+use crate::corporate::CorporateCalendar;
+use crate::econ::EconCalendar;
+use crate::edgar::EdgarWatcher;
+use crate::engine::apply::{Applier, ChangeSource};
+use crate::engine::watch::{CloseWatch, Watch, MAX_WATCH_MINUTES};
+use crate::feed::AlertFeed;
+use crate::health::Health;
+use crate::history::{Adjustment, History, Resolution};
+use crate::indicators::{self, IndicatorsConfig};
+use crate::limits::Limits;
+use crate::market::MarketContext;
+use crate::movers::MoversFeed;
+use crate::namespaces::{NamespaceError, Namespaces, Space};
+use crate::notes::{NoteError, NoteStore, NoteUpdate};
+use crate::options::OptionsMonitor;
+use crate::paper::{Order, OrderRequest, PaperAccount, PaperError};
+use crate::portfolio::{Holding, PortfolioError, PortfolioTracker};
+use crate::profile::VolumeProfiles;
+use crate::provider::proxy::{ProxyError, QuoteProxy};
+use crate::quality::{QualityTracker, DEGRADED_SCORE};
+use crate::query::{self, QueryError};
+use crate::sink::LatestPrices;
+use crate::social::SocialMonitor;
+use crate::stats::StatsCache;
+use crate::storage::{count_rules, AlertStore, DailyStore, PriceStore};
+use crate::timeseries::gaps::GapRepair;
+use crate::toggles::{ToggleError, Toggles};
+use crate::tracking::BenchmarkTracker;
+use crate::version;
+use crate::{FintekError, Tickers};
+
+/// Shared handles the JSON API reads from.
+#[derive(Clone)]
+pub struct ApiState {
+    pub econ: Arc<EconCalendar>,
+    pub movers: Arc<MoversFeed>,
+    pub paper: Arc<PaperAccount>,
+    pub tokens: Arc<TokenStore>,
+    pub quality: Arc<QualityTracker>,
+    pub prices: Arc<LatestPrices>,
+    pub corporate: Arc<CorporateCalendar>,
+    pub feed: Arc<AlertFeed>,
+    pub history: Arc<History>,
+    /// Set when stored daily closes are repaired.
+    pub gaps: Option<Arc<GapRepair>>,
+    /// Set when options chains are fetched.
+    pub options: Option<Arc<OptionsMonitor>>,
+    pub analysts: Option<Arc<AnalystTracker>>,
+    pub stats: Option<Arc<StatsCache>>,
+    /// Set when volume profiles are built.
+    pub profiles: Option<Arc<VolumeProfiles>>,
+    /// Set when EDGAR is watched for filings.
+    pub edgar: Option<Arc<EdgarWatcher>>,
+    /// Set when social sites are sampled.
+    pub social: Option<Arc<SocialMonitor>>,
+    /// Set when market-wide indicators are on.
+    pub market: Option<Arc<MarketContext>>,
+    pub tracking: Option<Arc<BenchmarkTracker>>,
+    pub notes: Arc<NoteStore>,
+    pub portfolio: Arc<PortfolioTracker>,
+    /// Watchlists and portfolios of namespaced tokens.
+    pub namespaces: Arc<Namespaces>,
+    pub toggles: Arc<Toggles>,
+    pub limits: Arc<Limits>,
+    /// Puts watchlist changes made here on probation.
+    pub applier: Arc<Applier>,
+    pub watches: Arc<CloseWatch>,
+    pub tickers: Arc<tokio::sync::Mutex<Tickers>>,
+    pub store: Option<Arc<dyn PriceStore>>,
+    pub alerts: Option<Arc<dyn AlertStore>>,
+    /// Daily bars and fundamentals, next to `store`.
+    pub daily: Option<Arc<dyn DailyStore>>,
+    /// Set when unwatched symbols are fetched on demand.
+    pub proxy: Option<Arc<QuoteProxy>>,
+    pub chaos: Option<Arc<Chaos>>,
+    pub started_at: DateTime<Utc>,
+    pub health: Arc<Health>,
+    /// What `/api/v1/debug/indicators` replays.
+    pub indicators: IndicatorsConfig,
+}
+
+pub fn routes(state: ApiState) -> Router {
+    let schema = graphql::schema(state.clone());
+    Router::new()
+        .route("/api/v1/status", get(status))
+        .route("/api/v1/version", get(build_info))
+        .route("/api/v1/econ/events", get(econ_events))
+        .route("/api/v1/movers", get(movers))
+        .route("/api/v1/paper/orders", get(orders).post(place_order))
+        .route("/api/v1/paper/orders/:id", delete(cancel_order))
+        .route("/api/v1/paper/positions", get(positions))
+        .route("/api/v1/paper/contribution", get(contribution))
+        .route("/api/v1/paper/tracking", get(tracking))
+        .route("/api/v1/notes", get(notes))
+        .route(
+            "/api/v1/notes/:symbol",
+            get(note).put(set_note).delete(remove_note),
+        )
+        .route("/api/v1/portfolio", get(portfolio))
+        .route("/api/v1/toggles", get(toggles))
+        .route("/api/v1/toggles/:name", put(set_toggle))
+        .route(
+            "/api/v1/portfolio/:symbol",
+            put(set_holding).delete(remove_holding),
+        )
+        .route("/api/v1/tickers", get(tickers).post(add_ticker))
+        .route("/api/v1/tickers/:symbol", delete(remove_ticker))
+        .route("/api/v1/watch", get(watches))
+        .route("/api/v1/watch/:symbol", post(watch).delete(unwatch))
+        .route("/api/v1/quality", get(quality))
+        .route("/api/v1/prices", get(prices))
+        .route("/api/v1/prices.csv", get(prices_csv))
+        .route("/api/v1/prices/:symbol", get(price))
+        .route("/api/v1/proxy/usage", get(proxy_usage))
+        .route("/api/v1/history/:symbol", get(history))
+        .route("/api/v1/indicators/:symbol", get(timeframes))
+        .route("/api/v1/gaps", get(gaps))
+        .route("/api/v1/options/:symbol", get(options))
+        .route("/api/v1/analysts", get(analysts))
+        .route("/api/v1/analysts/:symbol", get(analyst))
+        .route("/api/v1/stats", get(symbol_stats))
+        .route("/api/v1/stats/:symbol", get(symbol_stat))
+        .route("/api/v1/profile", get(volume_profiles))
+        .route("/api/v1/profile/:symbol", get(volume_profile))
+        .route("/api/v1/filings", get(filings))
+        .route("/api/v1/social", get(social))
+        .route("/api/v1/market", get(market))
+        .route("/api/v1/store/:symbol", get(stored))
+        .route("/api/v1/debug/indicators/:symbol", get(indicator_state))
+        .route("/api/v1/daily/:symbol", get(daily_bars))
+        .route("/api/v1/fundamentals/:symbol", get(fundamentals))
+        .route("/api/v1/earnings/:symbol", get(earnings_surprises))
+        .route("/api/v1/query", get(query))
+        .route("/api/v1/graphql", post(graphql::execute))
+        .route("/api/v1/alerts/history", get(alert_history))
+        .route("/api/v1/alerts/rules", get(alert_rules))
+        .route("/api/v1/chaos", get(chaos).put(set_chaos))
+        .route("/api/v1/chaos/clock", post(jump_clock))
+        .route("/calendar.ics", get(calendar_ics))
+        .route("/feeds/alerts.atom", get(alerts_atom))
+        .route_layer(middleware::from_fn_with_state(
+            state.tokens.clone(),
+            auth::require_token,
+        ))
+        .layer(Extension(schema))
+        .with_state(state)
+}
+
+/// Liveness and readiness probes, open without a token.
+pub fn probe_routes(state: ApiState) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "probes",
+    responses(
+        (status = 200, description = "The process is up"),
+    )
+)]
+async fn healthz() -> impl IntoResponse {
+    Json(json!({ "status": "ok" }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "probes",
+    responses(
+        (status = 200, body = crate::health::Readiness),
+        (status = 503, body = crate::health::Readiness),
+    )
+)]
+async fn readyz(State(state): State<ApiState>) -> impl IntoResponse {
+    let readiness = state.health.readiness();
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(readiness))
+}
+
+/// Routes that hold requests open, served outside the request timeout and
+/// concurrency limit.
+pub fn long_poll_routes(state: ApiState) -> Router {
+    Router::new()
+        .route("/api/v1/prices/:symbol/next", get(next_price))
+        .route_layer(middleware::from_fn_with_state(
+            state.tokens.clone(),
+            auth::require_token,
+        ))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/status",
+    tag = "status",
+    responses(
+        (status = 200, body = serde_json::Value),
+    )
+)]
+async fn status(State(state): State<ApiState>) -> impl IntoResponse {
+    let now = Utc::now();
+    let prices = state.prices.snapshot();
+    let degraded = state
+        .quality
+        .reports()
+        .iter()
+        .filter(|r| r.score < DEGRADED_SCORE)
+        .count();
+    Json(json!({
+        "version": version::BUILD.version,
+        "started_at": state.started_at,
+        "uptime_secs": (now - state.started_at).num_seconds(),
+        "symbols": prices.len(),
+        "last_price_at": prices.iter().map(|p| p.timestamp).max(),
+        "degraded": degraded,
+        "close_watches": state.watches.list(now).len(),
+        "equity": state.paper.portfolio().equity,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/version",
+    tag = "status",
+    responses(
+        (status = 200, body = serde_json::Value),
+    )
+)]
+async fn build_info() -> impl IntoResponse {
+    Json(version::BUILD)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/econ/events",
+    tag = "calendars",
+    responses(
+        (status = 200, body = serde_json::Value),
+    )
+)]
+async fn econ_events(State(state): State<ApiState>) -> impl IntoResponse {
+    Json(state.econ.upcoming(Utc::now()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/movers",
+    tag = "prices",
+    responses(
+        (status = 200, body = serde_json::Value),
+    )
+)]
+async fn movers(State(state): State<ApiState>) -> impl IntoResponse {
+    Json(state.movers.latest())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/paper/orders",
+    tag = "paper",
+    responses(
+        (status = 200, body = [crate::paper::Order]),
+    )
+)]
+async fn orders(State(state): State<ApiState>) -> impl IntoResponse {
+    Json(state.paper.orders())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/paper/orders",
+    tag = "paper",
+    request_body = crate::paper::OrderRequest,
+    responses(
+        (status = 201, body = crate::paper::Order),
+        (status = 422, description = "Invalid order"),
+    )
+)]
+async fn place_order(State(state): State<ApiState>, Json(request): Json<OrderRequest>) -> Response {
+    paper_reply(state.paper.place(request, Utc::now()), StatusCode::CREATED)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/paper/orders/{id}",
+    tag = "paper",
+    params(("id" = u64, Path)),
+    responses(
+        (status = 200, body = crate::paper::Order),
+        (status = 404, description = "No such order"),
+        (status = 409, description = "The order isn't open"),
+    )
+)]
+async fn cancel_order(State(state): State<ApiState>, Path(id): Path<u64>) -> Response {
+    paper_reply(state.paper.cancel(id, Utc::now()), StatusCode::OK)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/paper/positions",
+    tag = "paper",
+    responses(
+        (status = 200, body = serde_json::Value),
+    )
+)]
+async fn positions(State(state): State<ApiState>) -> impl IntoResponse {
+    Json(state.paper.portfolio())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/paper/contribution",
+    tag = "paper",
+    responses(
+        (status = 200, body = serde_json::Value),
+    )
+)]
+async fn contribution(State(state): State<ApiState>) -> impl IntoResponse {
+    Json(state.paper.contributions())
+}
+
+// Null until two days of marks are in.
+#[utoipa::path(
+    get,
+    path = "/api/v1/paper/tracking",
+    tag = "paper",
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "No benchmark configured"),
+    )
+)]
+async fn tracking(State(state): State<ApiState>) -> Response {
+    match &state.tracking {
+        Some(tracking) => Json(tracking.report()).into_response(),
+        None => (StatusCode::NOT_FOUND, "no benchmark configured").into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/notes",
+    tag = "notes",
+    responses(
+        (status = 200, body = std::collections::BTreeMap<String, crate::notes::SymbolNote>),
+    )
+)]
+async fn notes(State(state): State<ApiState>) -> impl IntoResponse {
+    Json(state.notes.all())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/notes/{symbol}",
+    tag = "notes",
+    params(("symbol" = String, Path)),
+    responses(
+        (status = 200, body = crate::notes::SymbolNote),
+        (status = 404, description = "No note on the symbol"),
+    )
+)]
+async fn note(State(state): State<ApiState>, Path(symbol): Path<String>) -> Response {
+    match state.notes.get(&symbol) {
+        Some(note) => Json(note).into_response(),
+        None => note_error(NoteError::NotFound(symbol)),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/notes/{symbol}",
+    tag = "notes",
+    params(("symbol" = String, Path)),
+    request_body = NoteUpdate,
+    responses(
+        (status = 200, body = crate::notes::SymbolNote),
+    )
+)]
+async fn set_note(
+    State(state): State<ApiState>,
+    Path(symbol): Path<String>,
+    Json(update): Json<NoteUpdate>,
+) -> Response {
+    match state.notes.set(&symbol, update) {
+        Ok(note) => Json(note).into_response(),
+        Err(e) => note_error(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/notes/{symbol}",
+    tag = "notes",
+    params(("symbol" = String, Path)),
+    responses(
+        (status = 204),
+        (status = 404, description = "No note on the symbol"),
+    )
+)]
+async fn remove_note(State(state): State<ApiState>, Path(symbol): Path<String>) -> Response {
+    match state.notes.remove(&symbol) {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => note_error(e),
+    }
+}
+
+fn note_error(error: NoteError) -> Response {
+    let status = match error {
+        NoteError::NotFound(_) => StatusCode::NOT_FOUND,
+        NoteError::Io(_) | NoteError::Corrupt(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(json!({ "error": error.to_string() }))).into_response()
+}
+
+fn namespace_error(error: NamespaceError) -> Response {
+    error!(error = %error, "Failed to open namespace");
+    let status = match error {
+        NamespaceError::Invalid(_) => StatusCode::FORBIDDEN,
+        NamespaceError::Io(_) | NamespaceError::Corrupt(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(json!({ "error": error.to_string() }))).into_response()
+}
+
+// The caller's own namespace, `None` for shared-state callers.
+fn space(
+    state: &ApiState,
+    caller: Option<Extension<Caller>>,
+) -> Result<Option<Arc<Space>>, NamespaceError> {
+    match caller {
+        Some(Extension(Caller {
+            namespace: Some(name),
+            ..
+        })) => state.namespaces.get(&name).map(Some),
+        _ => Ok(None),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/portfolio",
+    tag = "portfolio",
+    responses(
+        (status = 200, body = crate::portfolio::Valuation),
+    )
+)]
+async fn portfolio(State(state): State<ApiState>, caller: Option<Extension<Caller>>) -> Response {
+    match space(&state, caller) {
+        Ok(Some(space)) => Json(space.portfolio.valuation()).into_response(),
+        Ok(None) => Json(state.portfolio.valuation()).into_response(),
+        Err(e) => namespace_error(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/portfolio/{symbol}",
+    tag = "portfolio",
+    params(("symbol" = String, Path)),
+    request_body = Holding,
+    responses(
+        (status = 200, body = Holding),
+        (status = 400, description = "Invalid quantity or cost basis"),
+    )
+)]
+async fn set_holding(
+    State(state): State<ApiState>,
+    Path(symbol): Path<String>,
+    caller: Option<Extension<Caller>>,
+    Json(holding): Json<Holding>,
+) -> Response {
+    let result = match space(&state, caller) {
+        Ok(Some(space)) => space.portfolio.set(&symbol, holding),
+        Ok(None) => state.portfolio.set(&symbol, holding),
+        Err(e) => return namespace_error(e),
+    };
+    match result {
+        Ok(holding) => Json(holding).into_response(),
+        Err(e) => portfolio_error(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/portfolio/{symbol}",
+    tag = "portfolio",
+    params(("symbol" = String, Path)),
+    responses(
+        (status = 204),
+        (status = 404, description = "No holding of the symbol"),
+    )
+)]
+async fn remove_holding(
+    State(state): State<ApiState>,
+    Path(symbol): Path<String>,
+    caller: Option<Extension<Caller>>,
+) -> Response {
+    let result = match space(&state, caller) {
+        Ok(Some(space)) => space.portfolio.remove(&symbol),
+        Ok(None) => state.portfolio.remove(&symbol),
+        Err(e) => return namespace_error(e),
+    };
+    match result {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => portfolio_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/toggles",
+    tag = "admin",
+    responses(
+        (status = 200, body = serde_json::Value),
+    )
+)]
+async fn toggles(State(state): State<ApiState>) -> impl IntoResponse {
+    Json(state.toggles.list())
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct Toggle {
+    enabled: bool,
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/toggles/{name}",
+    tag = "admin",
+    params(("name" = String, Path)),
+    request_body = Toggle,
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "No such toggle"),
+    )
+)]
+async fn set_toggle(
+    State(state): State<ApiState>,
+    Path(name): Path<String>,
+    Json(toggle): Json<Toggle>,
+) -> Response {
+    match state.toggles.set(&name, toggle.enabled) {
+        Ok(()) => Json(state.toggles.list()).into_response(),
+        Err(e) => {
+            let status = match e {
+                ToggleError::NotFound(_) => StatusCode::NOT_FOUND,
+                ToggleError::Io(_) | ToggleError::Json(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (status, Json(json!({ "error": e.to_string() }))).into_response()
+        }
+    }
+}
+
+fn portfolio_error(error: PortfolioError) -> Response {
+    let status = match error {
+        PortfolioError::Invalid => StatusCode::BAD_REQUEST,
+        PortfolioError::NotFound(_) => StatusCode::NOT_FOUND,
+        PortfolioError::Io(_) | PortfolioError::Corrupt(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(json!({ "error": error.to_string() }))).into_response()
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(default)]
+struct WatchRequest {
+    minutes: i64,
+}
+
+impl Default for WatchRequest {
+    fn default() -> Self {
+        WatchRequest { minutes: 120 }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/watch",
+    tag = "tickers",
+    responses(
+        (status = 200, body = [Watch]),
+    )
+)]
+async fn watches(State(state): State<ApiState>) -> impl IntoResponse {
+    Json(state.watches.list(Utc::now()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/watch/{symbol}",
+    tag = "tickers",
+    params(("symbol" = String, Path)),
+    request_body = WatchRequest,
+    responses(
+        (status = 201, body = Watch),
+        (status = 422, description = "Minutes out of range"),
+    )
+)]
+async fn watch(
+    State(state): State<ApiState>,
+    Path(symbol): Path<String>,
+    request: Option<Json<WatchRequest>>,
+) -> Response {
+    let request = request.map(|Json(r)| r).unwrap_or_default();
+    if !(1..=MAX_WATCH_MINUTES).contains(&request.minutes) {
+        let message = format!("minutes must be between 1 and {}", MAX_WATCH_MINUTES);
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({ "error": message })),
+        )
+            .into_response();
+    }
+    let now = Utc::now();
+    let until = now + Duration::minutes(request.minutes);
+    state.watches.start(&symbol, until, now);
+    (StatusCode::CREATED, Json(Watch { symbol, until })).into_response()
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/watch/{symbol}",
+    tag = "tickers",
+    params(("symbol" = String, Path)),
+    responses(
+        (status = 204),
+        (status = 404, description = "Not being watched"),
+    )
+)]
+async fn unwatch(State(state): State<ApiState>, Path(symbol): Path<String>) -> Response {
+    if state.watches.stop(&symbol, Utc::now()) {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        let message = format!("{} isn't being watched", symbol);
+        (StatusCode::NOT_FOUND, Json(json!({ "error": message }))).into_response()
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/tickers",
+    tag = "tickers",
+    responses(
+        (status = 200, body = [String]),
+    )
+)]
+async fn tickers(State(state): State<ApiState>, caller: Option<Extension<Caller>>) -> Response {
+    match space(&state, caller) {
+        Ok(Some(space)) => Json(space.tickers()).into_response(),
+        Ok(None) => Json(state.tickers.lock().await.get_tickers().clone()).into_response(),
+        Err(e) => namespace_error(e),
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct TickerRequest {
+    symbol: String,
+}
+
+fn tickers_unsaved(e: FintekError) -> Response {
+    error!(error = %e, "Failed to write tickers");
+    let message = format!("failed to save tickers: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({ "error": message })),
+    )
+        .into_response()
+}
+
+// Both edits save the file before releasing the lock, so a reload of the
+// file between cycles can't undo them.
+#[utoipa::path(
+    post,
+    path = "/api/v1/tickers",
+    tag = "tickers",
+    request_body = TickerRequest,
+    responses(
+        (status = 201, body = [String]),
+        (status = 409, description = "Already a ticker"),
+        (status = 422, description = "Empty symbol, or over the watchlist limits"),
+    )
+)]
+async fn add_ticker(
+    State(state): State<ApiState>,
+    caller: Option<Extension<Caller>>,
+    Json(request): Json<TickerRequest>,
+) -> Response {
+    let symbol = request.symbol.trim();
+    if symbol.is_empty() {
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({ "error": "symbol must not be empty" })),
+        )
+            .into_response();
+    }
+    let space = match space(&state, caller) {
+        Ok(space) => space,
+        Err(e) => return namespace_error(e),
+    };
+    let mut tickers = state.tickers.lock().await;
+    // The limits cover every symbol polled, namespaces' included, so a
+    // symbol already polled for someone costs nothing more.
+    let polled = polled(&state, &tickers);
+    if !polled.contains(symbol) {
+        if let Err(e) = state.limits.check_growth(polled.len(), polled.len() + 1) {
+            state
+                .applier
+                .rejected(ChangeSource::Api, polled.len() + 1, &e);
+            let message = e.to_string();
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(json!({ "error": message })),
+            )
+                .into_response();
+        }
+    }
+    if let Some(space) = space {
+        return match space.add(symbol) {
+            Ok(true) => (StatusCode::CREATED, Json(space.tickers())).into_response(),
+            Ok(false) => {
+                let message = format!("{} is already a ticker", symbol);
+                (StatusCode::CONFLICT, Json(json!({ "error": message }))).into_response()
+            }
+            Err(e) => namespace_error(e),
+        };
+    }
+    let previous = tickers.clone();
+    if !tickers.add(symbol) {
+        let message = format!("{} is already a ticker", symbol);
+        return (StatusCode::CONFLICT, Json(json!({ "error": message }))).into_response();
+    }
+    if let Err(e) = tickers.save().await {
+        *tickers = previous;
+        return tickers_unsaved(e);
+    }
+    state
+        .applier
+        .applied(previous, ChangeSource::Api, tickers.get_tickers().len());
+    info!(symbol, "Ticker added");
+    (StatusCode::CREATED, Json(tickers.get_tickers().clone())).into_response()
+}
+
+// Symbols the engine polls for the shared watchlist and every namespace.
+fn polled(state: &ApiState, shared: &Tickers) -> BTreeSet<String> {
+    let mut polled: BTreeSet<String> = shared.get_tickers().iter().cloned().collect();
+    polled.extend(state.namespaces.symbols());
+    polled
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/tickers/{symbol}",
+    tag = "tickers",
+    params(("symbol" = String, Path)),
+    responses(
+        (status = 204),
+        (status = 404, description = "Not a ticker"),
+    )
+)]
+async fn remove_ticker(
+    State(state): State<ApiState>,
+    Path(symbol): Path<String>,
+    caller: Option<Extension<Caller>>,
+) -> Response {
+    match space(&state, caller) {
+        Ok(Some(space)) => {
+            return match space.remove(&symbol) {
+                Ok(true) => StatusCode::NO_CONTENT.into_response(),
+                Ok(false) => {
+                    let message = format!("{} isn't a ticker", symbol);
+                    (StatusCode::NOT_FOUND, Json(json!({ "error": message }))).into_response()
+                }
+                Err(e) => namespace_error(e),
+            }
+        }
+        Ok(None) => {}
+        Err(e) => return namespace_error(e),
+    }
+    let mut tickers = state.tickers.lock().await;
+    let previous = tickers.clone();
+    if !tickers.remove(&symbol) {
+        let message = format!("{} isn't a ticker", symbol);
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": message }))).into_response();
+    }
+    if let Err(e) = tickers.save().await {
+        *tickers = previous;
+        return tickers_unsaved(e);
+    }
+    state
+        .applier
+        .applied(previous, ChangeSource::Api, tickers.get_tickers().len());
+    info!(symbol, "Ticker removed");
+    StatusCode::NO_CONTENT.into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/quality",
+    tag = "status",
+    responses(
+        (status = 200, body = serde_json::Value),
+    )
+)]
+async fn quality(State(state): State<ApiState>) -> impl IntoResponse {
+    Json(state.quality.reports())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/prices",
+    tag = "prices",
+    responses(
+        (status = 200, body = [crate::sink::PriceUpdate]),
+    )
+)]
+async fn prices(State(state): State<ApiState>) -> impl IntoResponse {
+    let mut prices = state.prices.snapshot();
+    prices.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+    Json(prices)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/prices/{symbol}",
+    tag = "prices",
+    params(("symbol" = String, Path)),
+    responses(
+        (status = 200, body = crate::sink::PriceUpdate),
+        (status = 404, description = "Not watched"),
+        (status = 429, description = "Read-through rate limit or quota reached"),
+        (status = 502, description = "The provider failed"),
+    )
+)]
+async fn price(
+    State(state): State<ApiState>,
+    Path(symbol): Path<String>,
+    caller: Option<Extension<Caller>>,
+) -> Response {
+    if let Some(update) = state.prices.get(&symbol) {
+        return Json(update).into_response();
+    }
+    let Some(proxy) = &state.proxy else {
+        return (StatusCode::NOT_FOUND, format!("{} is not watched", symbol)).into_response();
+    };
+    let caller = caller.map_or_else(Caller::anonymous, |Extension(c)| c);
+    match proxy.quote(&symbol, &caller).await {
+        Ok(update) => Json(update).into_response(),
+        Err(e) => {
+            let status = match e {
+                ProxyError::RateLimited | ProxyError::QuotaExceeded(_) => {
+                    StatusCode::TOO_MANY_REQUESTS
+                }
+                ProxyError::NotFound(_) => StatusCode::NOT_FOUND,
+                ProxyError::Provider(_) => StatusCode::BAD_GATEWAY,
+            };
+            (status, e.to_string()).into_response()
+        }
+    }
+}
+
+/// Longest a `/next` request waits.
+const MAX_LONG_POLL_SECS: u64 = 120;
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[serde(default)]
+#[into_params(parameter_in = Query)]
+struct NextQuery {
+    /// Seconds, or with an `s` or `m` suffix. Defaults to 30 seconds.
+    timeout: Option<String>,
+    /// The price the caller has; defaults to the latest.
+    since: Option<f64>,
+}
+
+// Clamped to `MAX_LONG_POLL_SECS` before converting, so huge values
+// can't overflow the duration.
+fn parse_timeout(s: &str) -> Option<std::time::Duration> {
+    let s = s.trim();
+    let (number, unit) = match s.strip_suffix('m') {
+        Some(minutes) => (minutes, 60.),
+        None => (s.strip_suffix('s').unwrap_or(s), 1.),
+    };
+    let secs = number.trim().parse::<f64>().ok()? * unit;
+    (secs.is_finite() && secs >= 0.)
+        .then(|| std::time::Duration::from_secs_f64(secs.min(MAX_LONG_POLL_SECS as f64)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/prices/{symbol}/next",
+    tag = "prices",
+    params(("symbol" = String, Path), NextQuery),
+    responses(
+        (status = 200, body = crate::sink::PriceUpdate),
+        (status = 204, description = "No change before the timeout"),
+        (status = 404, description = "Not watched"),
+    )
+)]
+async fn next_price(
+    State(state): State<ApiState>,
+    Path(symbol): Path<String>,
+    Query(query): Query<NextQuery>,
+) -> Response {
+    let timeout = match query.timeout.as_deref().map(parse_timeout) {
+        None => std::time::Duration::from_secs(30),
+        Some(Some(timeout)) => timeout,
+        Some(None) => {
+            let message = "timeout must be seconds, like 30 or 30s, or minutes, like 2m";
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(json!({ "error": message })),
+            )
+                .into_response();
+        }
+    };
+    let latest = state.prices.get(&symbol);
+    if latest.is_none() && !state.tickers.lock().await.get_tickers().contains(&symbol) {
+        return (StatusCode::NOT_FOUND, format!("{} is not watched", symbol)).into_response();
+    }
+    let from = query.since.or(latest.map(|u| u.price));
+    match state.prices.next_change(&symbol, from, timeout).await {
+        Some(update) => Json(update).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/proxy/usage",
+    tag = "prices",
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "Read-through is off"),
+    )
+)]
+async fn proxy_usage(State(state): State<ApiState>) -> Response {
+    match &state.proxy {
+        Some(proxy) => Json(proxy.usage()).into_response(),
+        None => (StatusCode::NOT_FOUND, "read-through is off").into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/prices.csv",
+    tag = "prices",
+    responses(
+        (status = 200, body = String, content_type = "text/csv"),
+    )
+)]
+async fn prices_csv(State(state): State<ApiState>) -> impl IntoResponse {
+    let mut body = String::from("symbol,price,timestamp,provider_timestamp\n");
+    for update in state.prices.snapshot() {
+        body.push_str(&format!(
+            "{},{},{},{}\n",
+            csv_field(&update.symbol),
+            update.price,
+            update.timestamp.to_rfc3339(),
+            update
+                .provider_timestamp
+                .map(|at| at.to_rfc3339())
+                .unwrap_or_default()
+        ));
+    }
+    ([(header::CONTENT_TYPE, "text/csv; charset=utf-8")], body)
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[serde(default)]
+#[into_params(parameter_in = Query)]
+struct HistoryQuery {
+    /// Defaults to the last day.
+    since: Option<DateTime<Utc>>,
+    adjustment: Adjustment,
+    resolution: Resolution,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/gaps",
+    tag = "history",
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "Gap repair is off"),
+    )
+)]
+async fn gaps(State(state): State<ApiState>) -> Response {
+    match &state.gaps {
+        Some(gaps) => Json(gaps.report()).into_response(),
+        None => (StatusCode::NOT_FOUND, "gap repair is off").into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/analysts",
+    tag = "research",
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "Analyst tracking is off"),
+    )
+)]
+async fn analysts(State(state): State<ApiState>) -> Response {
+    match &state.analysts {
+        Some(analysts) => Json(analysts.all()).into_response(),
+        None => (StatusCode::NOT_FOUND, "analyst tracking is off").into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/analysts/{symbol}",
+    tag = "research",
+    params(("symbol" = String, Path)),
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "No consensus for the symbol"),
+    )
+)]
+async fn analyst(State(state): State<ApiState>, Path(symbol): Path<String>) -> Response {
+    let Some(analysts) = &state.analysts else {
+        return (StatusCode::NOT_FOUND, "analyst tracking is off").into_response();
+    };
+    match analysts.get(&symbol) {
+        Some(consensus) => Json(consensus).into_response(),
+        None => {
+            let message = format!("no analyst consensus for {}", symbol);
+            (StatusCode::NOT_FOUND, message).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats",
+    tag = "research",
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "Symbol stats are off"),
+    )
+)]
+async fn symbol_stats(State(state): State<ApiState>) -> Response {
+    match &state.stats {
+        Some(stats) => Json(stats.all()).into_response(),
+        None => (StatusCode::NOT_FOUND, "symbol stats are off").into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/{symbol}",
+    tag = "research",
+    params(("symbol" = String, Path)),
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "No stats for the symbol"),
+    )
+)]
+async fn symbol_stat(State(state): State<ApiState>, Path(symbol): Path<String>) -> Response {
+    let Some(stats) = &state.stats else {
+        return (StatusCode::NOT_FOUND, "symbol stats are off").into_response();
+    };
+    match stats.get(&symbol) {
+        Some(stats) => Json(stats).into_response(),
+        None => {
+            let message = format!("no stats for {}", symbol);
+            (StatusCode::NOT_FOUND, message).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/profile",
+    tag = "research",
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "Volume profiles are off"),
+    )
+)]
+async fn volume_profiles(State(state): State<ApiState>) -> Response {
+    match &state.profiles {
+        Some(profiles) => Json(profiles.all()).into_response(),
+        None => (StatusCode::NOT_FOUND, "volume profiles are off").into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/profile/{symbol}",
+    tag = "research",
+    params(("symbol" = String, Path)),
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "No volume profile for the symbol"),
+    )
+)]
+async fn volume_profile(State(state): State<ApiState>, Path(symbol): Path<String>) -> Response {
+    let Some(profiles) = &state.profiles else {
+        return (StatusCode::NOT_FOUND, "volume profiles are off").into_response();
+    };
+    match profiles.get(&symbol) {
+        Some(profile) => Json(profile).into_response(),
+        None => {
+            let message = format!("no volume profile for {}", symbol);
+            (StatusCode::NOT_FOUND, message).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/filings",
+    tag = "research",
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "EDGAR watching is off"),
+    )
+)]
+async fn filings(State(state): State<ApiState>) -> Response {
+    match &state.edgar {
+        Some(edgar) => Json(edgar.recent()).into_response(),
+        None => (StatusCode::NOT_FOUND, "EDGAR watching is off").into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/social",
+    tag = "research",
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "Social sampling is off"),
+    )
+)]
+async fn social(State(state): State<ApiState>) -> Response {
+    match &state.social {
+        Some(social) => Json(social.latest()).into_response(),
+        None => (StatusCode::NOT_FOUND, "social sampling is off").into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/market",
+    tag = "research",
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "Market indicators are off"),
+    )
+)]
+async fn market(State(state): State<ApiState>) -> Response {
+    match &state.market {
+        Some(market) => Json(market.all()).into_response(),
+        None => (StatusCode::NOT_FOUND, "market indicators are off").into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/options/{symbol}",
+    tag = "research",
+    params(("symbol" = String, Path)),
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "No options chain for the symbol"),
+    )
+)]
+async fn options(State(state): State<ApiState>, Path(symbol): Path<String>) -> Response {
+    let Some(options) = &state.options else {
+        return (StatusCode::NOT_FOUND, "no options symbols configured").into_response();
+    };
+    match options.chain(&symbol) {
+        Some(chain) => Json(chain).into_response(),
+        None => {
+            let message = format!("no options chain for {}", symbol);
+            (StatusCode::NOT_FOUND, message).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/history/{symbol}",
+    tag = "history",
+    params(("symbol" = String, Path), HistoryQuery),
+    responses(
+        (status = 200, body = serde_json::Value),
+    )
+)]
+async fn history(
+    State(state): State<ApiState>,
+    Path(symbol): Path<String>,
+    Query(query): Query<HistoryQuery>,
+) -> impl IntoResponse {
+    let now = Utc::now();
+    let since = query.since.unwrap_or_else(|| now - Duration::days(1));
+    let series = state
+        .history
+        .query(&symbol, since, now, query.resolution, query.adjustment);
+    Json(json!({
+        "symbol": symbol,
+        "adjustment": query.adjustment,
+        "resolution": series.resolution,
+        "splits": state.history.splits(&symbol),
+        "points": series.points,
+    }))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct TimeframeQuery {
+    /// Comma separated candle widths, like `5m,1h,1d`.
+    #[serde(default = "default_timeframes")]
+    timeframes: String,
+    /// Defaults to now.
+    #[serde(default)]
+    at: Option<DateTime<Utc>>,
+}
+
+fn default_timeframes() -> String {
+    "5m,1h,1d".into()
+}
+
+// The configured indicators over candles of each timeframe, all as of the
+// same instant, built from split-adjusted history at the finest resolution
+// still kept for the span each needs.
+#[utoipa::path(
+    get,
+    path = "/api/v1/indicators/{symbol}",
+    tag = "history",
+    params(("symbol" = String, Path), TimeframeQuery),
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 400, description = "Bad timeframe"),
+    )
+)]
+async fn timeframes(
+    State(state): State<ApiState>,
+    Path(symbol): Path<String>,
+    Query(query): Query<TimeframeQuery>,
+) -> Response {
+    let now = Utc::now();
+    let at = query.at.unwrap_or(now);
+    let lookback = indicators::lookback(&state.indicators) as i64;
+    let mut timeframes = vec![];
+    for timeframe in query.timeframes.split(',').map(str::trim) {
+        let span = query::duration(timeframe).and_then(|width| {
+            let span = Duration::try_seconds(width.num_seconds().checked_mul(lookback)?)?;
+            Some((width, at.checked_sub_signed(span)?))
+        });
+        let Some((width, since)) = span else {
+            let message = format!("bad timeframe `{}`", timeframe);
+            return (StatusCode::BAD_REQUEST, message).into_response();
+        };
+        let mut series =
+            state
+                .history
+                .query(&symbol, since, now, Resolution::Auto, Adjustment::Split);
+        series.points.retain(|p| p.timestamp <= at);
+        // Points coarser than the candles, as when ticks have aged out,
+        // show in the resolution.
+        timeframes.push(json!({
+            "resolution": series.resolution,
+            "indicators": indicators::timeframe(&state.indicators, timeframe, width, &series.points),
+        }));
+    }
+    Json(json!({ "symbol": symbol, "at": at, "timeframes": timeframes })).into_response()
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ExprQuery {
+    expr: String,
+    /// Defaults to now.
+    #[serde(default)]
+    time: Option<DateTime<Utc>>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/query",
+    tag = "history",
+    params(ExprQuery),
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 400, description = "Bad expression"),
+        (status = 404, description = "No data to evaluate it on"),
+    )
+)]
+async fn query(State(state): State<ApiState>, Query(query): Query<ExprQuery>) -> Response {
+    let at = query.time.unwrap_or_else(Utc::now);
+    let value = query::parse(&query.expr).and_then(|expr| query::eval(&expr, &state.history, at));
+    match value {
+        Ok(value) => {
+            Json(json!({ "expr": query.expr, "time": at, "value": value })).into_response()
+        }
+        Err(e @ QueryError::NoData(_)) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[serde(default)]
+#[into_params(parameter_in = Query)]
+struct RangeQuery {
+    /// Defaults to a day before `to`.
+    from: Option<DateTime<Utc>>,
+    /// Defaults to now.
+    to: Option<DateTime<Utc>>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/store/{symbol}",
+    tag = "history",
+    params(("symbol" = String, Path), RangeQuery),
+    responses(
+        (status = 200, body = [crate::storage::StoredPrice]),
+        (status = 404, description = "No price store configured"),
+    )
+)]
+async fn stored(
+    State(state): State<ApiState>,
+    Path(symbol): Path<String>,
+    Query(query): Query<RangeQuery>,
+) -> Response {
+    let Some(store) = &state.store else {
+        return (StatusCode::NOT_FOUND, "no price store configured").into_response();
+    };
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or_else(|| to - Duration::days(1));
+    match store.range(&symbol, from, to).await {
+        Ok(prices) => Json(prices).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[serde(default)]
+#[into_params(parameter_in = Query)]
+struct IndicatorQuery {
+    /// Defaults to now.
+    at: Option<DateTime<Utc>>,
+    /// Where the replay starts, a day before `at` by default.
+    from: Option<DateTime<Utc>>,
+}
+
+// The indicator windows as they stood at `at`, replayed from the stored
+// prices before it, to answer why a signal did or didn't fire.
+#[utoipa::path(
+    get,
+    path = "/api/v1/debug/indicators/{symbol}",
+    tag = "admin",
+    params(("symbol" = String, Path), IndicatorQuery),
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "No price store configured"),
+    )
+)]
+async fn indicator_state(
+    State(state): State<ApiState>,
+    Path(symbol): Path<String>,
+    Query(query): Query<IndicatorQuery>,
+) -> Response {
+    let Some(store) = &state.store else {
+        return no_store();
+    };
+    let at = query.at.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or_else(|| at - Duration::days(1));
+    match store.range(&symbol, from, at).await {
+        Ok(prices) => Json(json!({
+            "symbol": symbol,
+            "at": at,
+            "from": from,
+            "last_price_at": prices.last().map(|p| p.timestamp),
+            "state": indicators::replay(&state.indicators, prices.iter().map(|p| p.price)),
+        }))
+        .into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[serde(default)]
+#[into_params(parameter_in = Query)]
+struct DayRange {
+    /// Defaults to a month before `to`.
+    from: Option<NaiveDate>,
+    /// Defaults to today.
+    to: Option<NaiveDate>,
+}
+
+fn no_store() -> Response {
+    (StatusCode::NOT_FOUND, "no price store configured").into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/daily/{symbol}",
+    tag = "history",
+    params(("symbol" = String, Path), DayRange),
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "No price store configured"),
+    )
+)]
+async fn daily_bars(
+    State(state): State<ApiState>,
+    Path(symbol): Path<String>,
+    Query(range): Query<DayRange>,
+) -> Response {
+    let Some(daily) = &state.daily else {
+        return no_store();
+    };
+    let to = range.to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = range.from.unwrap_or(to - Duration::days(30));
+    match daily.bars(&symbol, from, to).await {
+        Ok(bars) => Json(bars).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/fundamentals/{symbol}",
+    tag = "research",
+    params(("symbol" = String, Path)),
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "No fundamentals for the symbol"),
+    )
+)]
+async fn fundamentals(State(state): State<ApiState>, Path(symbol): Path<String>) -> Response {
+    let Some(daily) = &state.daily else {
+        return no_store();
+    };
+    match daily.fundamentals(&symbol).await {
+        Ok(Some(fundamentals)) => Json(fundamentals).into_response(),
+        Ok(None) => {
+            let message = format!("no fundamentals for {}", symbol);
+            (StatusCode::NOT_FOUND, message).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/earnings/{symbol}",
+    tag = "research",
+    params(("symbol" = String, Path)),
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "No price store configured"),
+    )
+)]
+async fn earnings_surprises(State(state): State<ApiState>, Path(symbol): Path<String>) -> Response {
+    let Some(daily) = &state.daily else {
+        return no_store();
+    };
+    match daily.surprises(&symbol).await {
+        Ok(surprises) => Json(surprises).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[serde(default)]
+#[into_params(parameter_in = Query)]
+struct AlertQuery {
+    symbol: Option<String>,
+    /// Defaults to a day ago.
+    since: Option<DateTime<Utc>>,
+}
+
+fn no_alert_store() -> Response {
+    (StatusCode::NOT_FOUND, "no alert store configured").into_response()
+}
+
+// Namespaced callers only see alerts on symbols they watch or hold.
+#[utoipa::path(
+    get,
+    path = "/api/v1/alerts/history",
+    tag = "alerts",
+    params(AlertQuery),
+    responses(
+        (status = 200, body = [crate::storage::AlertRecord]),
+        (status = 404, description = "No alert store configured"),
+    )
+)]
+async fn alert_history(
+    State(state): State<ApiState>,
+    Query(query): Query<AlertQuery>,
+    caller: Option<Extension<Caller>>,
+) -> Response {
+    let Some(alerts) = &state.alerts else {
+        return no_alert_store();
+    };
+    let symbols = match space(&state, caller) {
+        Ok(space) => space.map(|s| s.symbols()),
+        Err(e) => return namespace_error(e),
+    };
+    let since = query
+        .since
+        .unwrap_or_else(|| Utc::now() - Duration::days(1));
+    match alerts.alerts(query.symbol.as_deref(), since).await {
+        Ok(mut alerts) => {
+            if let Some(symbols) = symbols {
+                alerts.retain(|a| a.symbol.as_ref().is_some_and(|s| symbols.contains(s)));
+            }
+            Json(alerts).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+// Namespaced callers only count alerts on symbols they watch or hold.
+#[utoipa::path(
+    get,
+    path = "/api/v1/alerts/rules",
+    tag = "alerts",
+    params(AlertQuery),
+    responses(
+        (status = 200, body = [crate::storage::RuleCount]),
+        (status = 404, description = "No alert store configured"),
+    )
+)]
+async fn alert_rules(
+    State(state): State<ApiState>,
+    Query(query): Query<AlertQuery>,
+    caller: Option<Extension<Caller>>,
+) -> Response {
+    let Some(alerts) = &state.alerts else {
+        return no_alert_store();
+    };
+    let symbols = match space(&state, caller) {
+        Ok(space) => space.map(|s| s.symbols()),
+        Err(e) => return namespace_error(e),
+    };
+    let since = query
+        .since
+        .unwrap_or_else(|| Utc::now() - Duration::days(1));
+    let counts = match symbols {
+        Some(symbols) => alerts.alerts(None, since).await.map(|mut alerts| {
+            alerts.retain(|a| a.symbol.as_ref().is_some_and(|s| symbols.contains(s)));
+            count_rules(&alerts)
+        }),
+        None => alerts.rule_counts(since).await,
+    };
+    match counts {
+        Ok(counts) => Json(counts).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+fn chaos_off() -> Response {
+    (StatusCode::NOT_FOUND, "chaos testing is off").into_response()
+}
+
+fn chaos_state(chaos: &Chaos) -> Response {
+    Json(json!({
+        "faults": chaos.faults(),
+        "clock_offset_secs": chaos.clock().offset().num_seconds(),
+    }))
+    .into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/chaos",
+    tag = "admin",
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "Chaos testing is off"),
+    )
+)]
+async fn chaos(State(state): State<ApiState>) -> Response {
+    state.chaos.as_deref().map_or_else(chaos_off, chaos_state)
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/chaos",
+    tag = "admin",
+    request_body = serde_json::Value,
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "Chaos testing is off"),
+    )
+)]
+async fn set_chaos(State(state): State<ApiState>, Json(faults): Json<Faults>) -> Response {
+    let Some(chaos) = &state.chaos else {
+        return chaos_off();
+    };
+    chaos.set_faults(faults);
+    chaos_state(chaos)
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct ClockJump {
+    /// Negative to jump back.
+    jump_secs: i64,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/chaos/clock",
+    tag = "admin",
+    request_body = ClockJump,
+    responses(
+        (status = 200, body = serde_json::Value),
+        (status = 404, description = "Chaos testing is off"),
+    )
+)]
+async fn jump_clock(State(state): State<ApiState>, Json(jump): Json<ClockJump>) -> Response {
+    let Some(chaos) = &state.chaos else {
+        return chaos_off();
+    };
+    chaos.clock().jump(Duration::seconds(jump.jump_secs));
+    tracing::warn!(jump_secs = jump.jump_secs, "Chaos clock jumped");
+    chaos_state(chaos)
+}
+
+#[utoipa::path(
+    get,
+    path = "/calendar.ics",
+    tag = "calendars",
+    responses(
+        (status = 200, body = String, content_type = "text/calendar"),
+    )
+)]
+async fn calendar_ics(State(state): State<ApiState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+        state.corporate.to_ics(Utc::now()),
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/feeds/alerts.atom",
+    tag = "alerts",
+    responses(
+        (status = 200, body = String, content_type = "application/atom+xml"),
+    )
+)]
+async fn alerts_atom(State(state): State<ApiState>, caller: Option<Extension<Caller>>) -> Response {
+    let symbols = match space(&state, caller) {
+        Ok(space) => space.map(|s| s.symbols()),
+        Err(e) => return namespace_error(e),
+    };
+    (
+        [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        state.feed.to_atom(symbols.as_ref()),
+    )
+        .into_response()
+}
+
+// Quotes only when needed so plain symbols stay readable.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn paper_reply(result: Result<Order, PaperError>, status: StatusCode) -> Response {
+    match result {
+        Ok(order) => (status, Json(order)).into_response(),
+        Err(e) => {
+            let status = match e {
+                PaperError::UnknownOrder(_) => StatusCode::NOT_FOUND,
+                PaperError::NotOpen(_) => StatusCode::CONFLICT,
+                _ => StatusCode::UNPROCESSABLE_ENTITY,
+            };
+            let body = serde_json::json!({ "error": e.to_string() });
+            (status, Json(body)).into_response()
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use axum::Router;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
+
+/// The JSON API as an OpenAPI 3 document, for generating clients.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "fintek"),
+    paths(
+        super::healthz,
+        super::readyz,
+        super::status,
+        super::build_info,
+        super::econ_events,
+        super::movers,
+        super::orders,
+        super::place_order,
+        super::cancel_order,
+        super::positions,
+        super::contribution,
+        super::tracking,
+        super::notes,
+        super::note,
+        super::set_note,
+        super::remove_note,
+        super::portfolio,
+        super::set_holding,
+        super::remove_holding,
+        super::toggles,
+        super::set_toggle,
+        super::watches,
+        super::watch,
+        super::unwatch,
+        super::tickers,
+        super::add_ticker,
+        super::remove_ticker,
+        super::quality,
+        super::prices,
+        super::price,
+        super::next_price,
+        super::proxy_usage,
+        super::prices_csv,
+        super::gaps,
+        super::analysts,
+        super::analyst,
+        super::symbol_stats,
+        super::symbol_stat,
+        super::volume_profiles,
+        super::volume_profile,
+        super::filings,
+        super::social,
+        super::market,
+        super::options,
+        super::history,
+        super::timeframes,
+        super::query,
+        super::graphql::execute,
+        super::stored,
+        super::indicator_state,
+        super::daily_bars,
+        super::fundamentals,
+        super::earnings_surprises,
+        super::alert_history,
+        super::alert_rules,
+        super::chaos,
+        super::set_chaos,
+        super::jump_clock,
+        super::calendar_ics,
+        super::alerts_atom,
+    ),
+    // Only referenced from query parameters, which don't collect schemas.
+    components(schemas(crate::history::Adjustment, crate::history::Resolution)),
+    modifiers(&BearerToken),
+    security(("token" = []))
+)]
+pub struct ApiDoc;
+
+// Tokens go in `Authorization: Bearer`, or `?token=` for reads.
+struct BearerToken;
+
+impl Modify for BearerToken {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "token",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+/// `/api/openapi.json` and Swagger UI at `/api/docs`, open without a token
+/// as they describe the API but serve none of its data.
+pub fn routes() -> Router {
+    SwaggerUi::new("/api/docs")
+        .url("/api/openapi.json", ApiDoc::openapi())
+        .into()
+}
@@ -0,0 +1,322 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use axum::extract::{Request, State};
+use axum::http::{header, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::recovery;
+
+pub const TOKENS_FILE: &str = "tokens.json";
+
+/// `Read` covers every GET; `Admin` is needed for anything that changes state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Read,
+    Admin,
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(Scope::Read),
+            "admin" => Ok(Scope::Admin),
+            other => Err(format!("unknown scope {:?}, expected read or admin", other)),
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::Read => write!(f, "read"),
+            Scope::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TokenRecord {
+    pub name: String,
+    pub scope: Scope,
+    /// Hex SHA-256 of the token; the token itself is only shown once.
+    pub hash: String,
+    pub created_at: DateTime<Utc>,
+    /// Upstream fetches a day this token may cause through the quote proxy,
+    /// overriding `provider.read_through_daily_quota`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota: Option<u64>,
+    /// Watchlist, portfolio and alerts of its own instead of the shared
+    /// ones, for one user of a shared deployment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+}
+
+/// What namespaced tokens may change; everything else stays read-only to
+/// them, admin scope or not.
+const NAMESPACED_WRITES: [&str; 2] = ["/api/v1/tickers", "/api/v1/portfolio"];
+
+/// POSTed only because their queries don't fit in a URL; they change
+/// nothing, so the read scope is enough.
+const READ_POSTS: [&str; 1] = ["/api/v1/graphql"];
+
+/// Who made an API request, set by [`require_token`] for handlers to read.
+#[derive(Debug, Clone)]
+pub struct Caller {
+    /// Token name, `anonymous` while the API is open.
+    pub name: String,
+    pub quota: Option<u64>,
+    pub namespace: Option<String>,
+}
+
+impl Caller {
+    pub fn anonymous() -> Self {
+        Caller {
+            name: "anonymous".into(),
+            quota: None,
+            namespace: None,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TokenError {
+    #[error("token store: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("token store is corrupt: {0}")]
+    Corrupt(#[from] serde_json::Error),
+    #[error("a token named {0:?} already exists")]
+    Exists(String),
+    #[error("no token named {0:?}")]
+    NotFound(String),
+    #[error("namespace {0:?} must be letters, digits, '-' and '_'")]
+    Namespace(String),
+}
+
+/// Whether `name` works as a namespace, which is also a directory name.
+pub fn valid_namespace(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn hash(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[derive(Default)]
+struct Cache {
+    modified: Option<SystemTime>,
+    tokens: Vec<TokenRecord>,
+    // The file stopped parsing; the API stays locked until it does again.
+    unreadable: bool,
+}
+
+/// Hashed API tokens kept in the state directory. The server rereads the
+/// file when it changes so tokens created or revoked from the CLI apply
+/// without a restart.
+pub struct TokenStore {
+    path: PathBuf,
+    cache: RwLock<Cache>,
+}
+
+impl TokenStore {
+    pub fn new(state_dir: &Path) -> Self {
+        TokenStore {
+            path: state_dir.join(TOKENS_FILE),
+            cache: RwLock::new(Cache::default()),
+        }
+    }
+
+    pub fn load(&self) -> Result<Vec<TokenRecord>, TokenError> {
+        match std::fs::read(&self.path) {
+            Ok(data) => Ok(serde_json::from_slice(&data)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, tokens: &[TokenRecord]) -> Result<(), TokenError> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(tokens)?)?;
+        recovery::backup(&self.path)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    /// Returns the new token; only its hash is stored.
+    pub fn create(
+        &self,
+        name: &str,
+        scope: Scope,
+        quota: Option<u64>,
+        namespace: Option<&str>,
+    ) -> Result<String, TokenError> {
+        if let Some(namespace) = namespace.filter(|n| !valid_namespace(n)) {
+            return Err(TokenError::Namespace(namespace.to_string()));
+        }
+        let mut tokens = self.load()?;
+        if tokens.iter().any(|t| t.name == name) {
+            return Err(TokenError::Exists(name.to_string()));
+        }
+        let mut bytes = [0u8; 24];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let token = format!(
+            "ftk_{}",
+            bytes
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        );
+        tokens.push(TokenRecord {
+            name: name.to_string(),
+            scope,
+            hash: hash(&token),
+            created_at: Utc::now(),
+            quota,
+            namespace: namespace.map(str::to_string),
+        });
+        self.save(&tokens)?;
+        info!(name, %scope, "Created API token");
+        Ok(token)
+    }
+
+    pub fn revoke(&self, name: &str) -> Result<(), TokenError> {
+        let mut tokens = self.load()?;
+        let before = tokens.len();
+        tokens.retain(|t| t.name != name);
+        if tokens.len() == before {
+            return Err(TokenError::NotFound(name.to_string()));
+        }
+        self.save(&tokens)?;
+        info!(name, "Revoked API token");
+        Ok(())
+    }
+
+    fn refresh(&self) {
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .ok();
+        if self.cache.read().unwrap().modified == modified && modified.is_some() {
+            return;
+        }
+        match self.load() {
+            Ok(tokens) => {
+                *self.cache.write().unwrap() = Cache {
+                    modified,
+                    tokens,
+                    unreadable: false,
+                }
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to reload API tokens, keeping the API locked");
+                self.cache.write().unwrap().unreadable = true;
+            }
+        }
+    }
+
+    /// True when no tokens exist, which leaves the API open. Never while
+    /// the token file doesn't read, so damage to it can't open the API.
+    pub fn is_open(&self) -> bool {
+        self.refresh();
+        let cache = self.cache.read().unwrap();
+        !cache.unreadable && cache.tokens.is_empty()
+    }
+
+    pub fn lookup(&self, token: &str) -> Option<TokenRecord> {
+        self.refresh();
+        let digest = hash(token);
+        self.cache
+            .read()
+            .unwrap()
+            .tokens
+            .iter()
+            .find(|t| t.hash == digest)
+            .cloned()
+    }
+
+    pub fn scope(&self, token: &str) -> Option<Scope> {
+        self.lookup(token).map(|t| t.scope)
+    }
+}
+
+/// Middleware requiring `Authorization: Bearer <token>` with the read
+/// scope for GETs and GraphQL queries and admin for everything else, once
+/// any token exists.
+pub async fn require_token(
+    State(store): State<Arc<TokenStore>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if store.is_open() {
+        request.extensions_mut().insert(Caller::anonymous());
+        return next.run(request).await;
+    }
+    let required = match *request.method() {
+        Method::GET | Method::HEAD => Scope::Read,
+        Method::POST if READ_POSTS.contains(&request.uri().path()) => Scope::Read,
+        _ => Scope::Admin,
+    };
+    // Calendar apps and feed readers can't set headers, so reads may pass
+    // the token as `?token=` instead.
+    let query_token = || {
+        (required == Scope::Read)
+            .then(|| request.uri().query())
+            .flatten()
+            .and_then(|q| q.split('&').find_map(|pair| pair.strip_prefix("token=")))
+    };
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(query_token);
+    match token.and_then(|t| store.lookup(t)) {
+        None => (StatusCode::UNAUTHORIZED, "missing or unknown token").into_response(),
+        Some(record) if record.scope < required => {
+            (StatusCode::FORBIDDEN, "token lacks the required scope").into_response()
+        }
+        Some(record)
+            if record.namespace.is_some()
+                && required == Scope::Admin
+                && !NAMESPACED_WRITES
+                    .iter()
+                    .any(|p| request.uri().path().starts_with(p)) =>
+        {
+            (
+                StatusCode::FORBIDDEN,
+                "namespaced tokens can't change shared state",
+            )
+                .into_response()
+        }
+        Some(record) => {
+            request.extensions_mut().insert(Caller {
+                name: record.name,
+                quota: record.quota,
+                namespace: record.namespace,
+            });
+            next.run(request).await
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::env;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::provider::ProviderKind;
+use crate::Tickers;
+
+pub const ENV_FILE: &str = ".env";
+
+#[derive(Debug, Error)]
+pub enum BootstrapError {
+    #[error("API_KEY is not set; export it, add it to {ENV_FILE} or run `fintek init`")]
+    MissingApiKey,
+    #[error("FINNHUB_API_KEY is not set; export it or add it to {ENV_FILE}")]
+    MissingFinnhubKey,
+    #[error("FINTEK_SHARE_KEY is not set; watchlist exports are signed with it, so both sides need the same one")]
+    MissingShareKey,
+    #[error("provider.rest: {0}")]
+    Rest(String),
+    #[error("FINTEK_PROVIDER: {0}")]
+    UnknownProvider(String),
+    #[error("no tickers file at {0}; run `fintek init` to create one")]
+    MissingTickers(PathBuf),
+    #[error("failed to read {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{path} is not a valid tickers file: {source}")]
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("failed to write {path}: {source}")]
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{0} already exists; pass --force to overwrite it")]
+    Exists(PathBuf),
+}
+
+/// Loads `.env` from the working directory, if present, without
+/// overriding variables already set.
+pub fn load_env() {
+    match dotenv::dotenv() {
+        Ok(path) => info!(path = %path.display(), "Loaded environment file"),
+        Err(e) if e.not_found() => {}
+        Err(e) => warn!(error = %e, "Failed to load environment file"),
+    }
+}
+
+/// Exports the config file's key as `API_KEY` unless the environment
+/// already has one, so the environment wins like it does for `.env`.
+pub fn use_config_key(key: Option<&str>) {
+    let Some(key) = key.filter(|key| !key.trim().is_empty()) else {
+        return;
+    };
+    if api_key().is_err() {
+        info!("Using the API key from the config file");
+        env::set_var("API_KEY", key);
+    }
+}
+
+pub fn api_key() -> Result<String, BootstrapError> {
+    env::var("API_KEY")
+        .ok()
+        .filter(|key| !key.trim().is_empty())
+        .ok_or(BootstrapError::MissingApiKey)
+}
+
+pub fn finnhub_key() -> Result<String, BootstrapError> {
+    env::var("FINNHUB_API_KEY")
+        .ok()
+        .filter(|key| !key.trim().is_empty())
+        .ok_or(BootstrapError::MissingFinnhubKey)
+}
+
+/// The key watchlist exports are signed and checked with.
+pub fn share_key() -> Result<String, BootstrapError> {
+    env::var("FINTEK_SHARE_KEY")
+        .ok()
+        .filter(|key| !key.trim().is_empty())
+        .ok_or(BootstrapError::MissingShareKey)
+}
+
+/// The key filled in for `{api_key}` by the REST provider, which some APIs
+/// don't need.
+pub fn rest_key() -> Option<String> {
+    env::var("REST_API_KEY")
+        .ok()
+        .filter(|key| !key.trim().is_empty())
+}
+
+/// The secret requests are signed with under `provider.rest.signing`.
+pub fn rest_secret() -> Option<String> {
+    env::var("REST_API_SECRET")
+        .ok()
+        .filter(|secret| !secret.trim().is_empty())
+}
+
+/// The passphrase Coinbase wants with signed requests.
+pub fn rest_passphrase() -> Option<String> {
+    env::var("REST_API_PASSPHRASE")
+        .ok()
+        .filter(|passphrase| !passphrase.trim().is_empty())
+}
+
+/// The configured provider unless `FINTEK_PROVIDER` names another.
+pub fn provider_kind(configured: ProviderKind) -> Result<ProviderKind, BootstrapError> {
+    match env::var("FINTEK_PROVIDER") {
+        Ok(kind) if !kind.trim().is_empty() => {
+            kind.parse().map_err(BootstrapError::UnknownProvider)
+        }
+        _ => Ok(configured),
+    }
+}
+
+/// `API_KEY` first, then any standbys listed comma-separated in `API_KEYS`.
+pub fn api_keys() -> Result<Vec<String>, BootstrapError> {
+    let mut keys = vec![api_key()?];
+    for key in env::var("API_KEYS").unwrap_or_default().split(',') {
+        let key = key.trim();
+        if !key.is_empty() && !keys.iter().any(|k| k == key) {
+            keys.push(key.to_string());
+        }
+    }
+    Ok(keys)
+}
+
+pub fn load_tickers(path: &Path) -> Result<Tickers, BootstrapError> {
+    let data = match std::fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(BootstrapError::MissingTickers(path.to_path_buf()))
+        }
+        Err(source) => {
+            return Err(BootstrapError::Read {
+                path: path.to_path_buf(),
+                source,
+            })
+        }
+    };
+    serde_json::from_str(&data).map_err(|source| BootstrapError::Parse {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct InitOptions {
+    pub config: PathBuf,
+    pub tickers: PathBuf,
+    pub api_key: Option<String>,
+    pub symbols: Vec<String>,
+    pub force: bool,
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), BootstrapError> {
+    std::fs::write(path, contents).map_err(|source| BootstrapError::Write {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Asks on the terminal; `None` for an empty answer or without a terminal.
+pub fn prompt(question: &str) -> Option<String> {
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        return None;
+    }
+    print!("{}: ", question);
+    std::io::stdout().flush().ok()?;
+    let mut answer = String::new();
+    stdin.lock().read_line(&mut answer).ok()?;
+    let answer = answer.trim().to_string();
+    (!answer.is_empty()).then_some(answer)
+}
+
+/// First-run setup: writes the API key to `.env`, a tickers file and a
+/// starter config, asking on the terminal for anything not passed in.
+pub fn init(options: InitOptions) -> Result<Vec<PathBuf>, BootstrapError> {
+    for path in [&options.config, &options.tickers] {
+        if path.exists() && !options.force {
+            return Err(BootstrapError::Exists(path.clone()));
+        }
+    }
+
+    let api_key = options
+        .api_key
+        .or_else(|| api_key().ok())
+        .or_else(|| prompt("Twelve Data API key"));
+    let mut symbols = options.symbols;
+    if symbols.is_empty() {
+        symbols = prompt("Symbols to watch, comma separated (e.g. AAPL,MSFT)")
+            .map(|s| s.split(',').map(String::from).collect())
+            .unwrap_or_default();
+    }
+    let symbols: Vec<String> = symbols
+        .iter()
+        .map(|s| s.trim().to_uppercase())
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    let mut written = vec![];
+    if let Some(api_key) = api_key {
+        let path = PathBuf::from(ENV_FILE);
+        let mut lines: Vec<String> = std::fs::read_to_string(&path)
+            .unwrap_or_default()
+            .lines()
+            .filter(|l| !l.starts_with("API_KEY="))
+            .map(String::from)
+            .collect();
+        lines.push(format!("API_KEY={}", api_key));
+        write(&path, &(lines.join("\n") + "\n"))?;
+        written.push(path);
+    }
+
+    let tickers = serde_json::to_string(&Tickers::new(symbols)).expect("tickers serialize");
+    write(&options.tickers, &tickers)?;
+    written.push(options.tickers.clone());
+
+    let config = format!(
+        "# Generated by `fintek init`; see `fintek validate-config`.\ntickers_path = {:?}\nexchange = \"NYSE\"\n\n[metrics]\naddr = \"127.0.0.1:9091\"\n",
+        options.tickers.display().to_string()
+    );
+    write(&options.config, &config)?;
+    written.push(options.config);
+    Ok(written)
+}
@@ -0,0 +1,316 @@
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, instrument};
+
+use crate::clock::Clock;
+use crate::error::FintekError;
+use crate::metrics;
+use crate::provider::retry::retry;
+use crate::provider::Provider;
+use crate::{Markets, StockMarket};
+
+/// Regular weekday trading hours in the exchange's own time zone. Holidays
+/// aren't known locally, only the provider's market state reflects them; see
+/// [`MarketCalendar`].
+#[derive(Debug, Clone, Copy)]
+pub struct Session {
+    pub tz: Tz,
+    pub open: NaiveTime,
+    pub close: NaiveTime,
+    /// Midday break observed by Asian exchanges.
+    pub lunch: Option<(NaiveTime, NaiveTime)>,
+}
+
+fn hm(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).expect("valid session time")
+}
+
+pub fn session(market: StockMarket) -> Session {
+    use chrono_tz::{America, Asia, Europe};
+    match market {
+        StockMarket::NYSE | StockMarket::NASDAQ => Session {
+            tz: America::New_York,
+            open: hm(9, 30),
+            close: hm(16, 0),
+            lunch: None,
+        },
+        StockMarket::LSE => Session {
+            tz: Europe::London,
+            open: hm(8, 0),
+            close: hm(16, 30),
+            lunch: None,
+        },
+        StockMarket::XETR => Session {
+            tz: Europe::Berlin,
+            open: hm(9, 0),
+            close: hm(17, 30),
+            lunch: None,
+        },
+        StockMarket::Euronext => Session {
+            tz: Europe::Paris,
+            open: hm(9, 0),
+            close: hm(17, 30),
+            lunch: None,
+        },
+        StockMarket::SIX => Session {
+            tz: Europe::Zurich,
+            open: hm(9, 0),
+            close: hm(17, 30),
+            lunch: None,
+        },
+        StockMarket::JPX => Session {
+            tz: Asia::Tokyo,
+            open: hm(9, 0),
+            close: hm(15, 30),
+            lunch: Some((hm(11, 30), hm(12, 30))),
+        },
+        StockMarket::HKEX => Session {
+            tz: Asia::Hong_Kong,
+            open: hm(9, 30),
+            close: hm(16, 0),
+            lunch: Some((hm(12, 0), hm(13, 0))),
+        },
+    }
+}
+
+impl Session {
+    fn is_trading_day(weekday: Weekday) -> bool {
+        !matches!(weekday, Weekday::Sat | Weekday::Sun)
+    }
+
+    pub fn is_open(&self, now: DateTime<Utc>) -> bool {
+        let local = now.with_timezone(&self.tz);
+        let time = local.time();
+        let in_lunch = self
+            .lunch
+            .is_some_and(|(start, end)| time >= start && time < end);
+        Self::is_trading_day(local.weekday()) && time >= self.open && time < self.close && !in_lunch
+    }
+
+    /// Seconds until today's close in the exchange's time zone, zero once past it.
+    pub fn seconds_until_close(&self, now: DateTime<Utc>) -> u64 {
+        let local = now.with_timezone(&self.tz);
+        self.tz
+            .from_local_datetime(&local.date_naive().and_time(self.close))
+            .earliest()
+            .map(|close| (close.with_timezone(&Utc) - now).num_seconds().max(0) as u64)
+            .unwrap_or_default()
+    }
+
+    pub fn seconds_until_open(&self, now: DateTime<Utc>) -> u64 {
+        if self.is_open(now) {
+            return 0;
+        }
+        let local = now.with_timezone(&self.tz);
+        let opens: Vec<NaiveTime> = std::iter::once(self.open)
+            .chain(self.lunch.map(|(_, end)| end))
+            .collect();
+        for days in 0..8 {
+            let date = local.date_naive() + Duration::days(days);
+            if !Self::is_trading_day(date.weekday()) {
+                continue;
+            }
+            for time in &opens {
+                let Some(open) = self
+                    .tz
+                    .from_local_datetime(&date.and_time(*time))
+                    .earliest()
+                else {
+                    continue;
+                };
+                let open = open.with_timezone(&Utc);
+                if open > now {
+                    return (open - now).num_seconds().max(0) as u64;
+                }
+            }
+        }
+        0
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Schedule {
+    fetched: DateTime<Utc>,
+    // At or before `fetched` when it was open then.
+    opens_at: DateTime<Utc>,
+    closes_at: Option<DateTime<Utc>>,
+}
+
+impl Schedule {
+    // The open or close it predicts next, after which it's out of date.
+    fn transition(&self) -> Option<DateTime<Utc>> {
+        if self.opens_at > self.fetched {
+            Some(self.opens_at)
+        } else {
+            self.closes_at
+        }
+    }
+}
+
+/// The provider's market state per exchange, holidays and half days
+/// included, answered locally until the open or close it predicts comes
+/// round or it is older than its maximum age.
+pub struct MarketCalendar {
+    clock: Arc<dyn Clock>,
+    max_age: Duration,
+    schedules: Mutex<HashMap<String, Schedule>>,
+}
+
+impl MarketCalendar {
+    pub fn new(clock: Arc<dyn Clock>, max_age_secs: u64) -> Self {
+        MarketCalendar {
+            clock,
+            max_age: Duration::seconds(max_age_secs as i64),
+            schedules: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn current(&self, market: &Markets) -> Option<(Schedule, DateTime<Utc>)> {
+        let now = self.clock.now();
+        let schedule = *self.schedules.lock().unwrap().get(&market.to_string())?;
+        let fresh = now - schedule.fetched < self.max_age
+            && schedule.transition().is_none_or(|at| now < at);
+        fresh.then_some((schedule, now))
+    }
+
+    /// Whether `market` has to be fetched before it can be answered.
+    pub fn is_stale(&self, market: &Markets) -> bool {
+        self.current(market).is_none()
+    }
+
+    /// Fetches `market`'s state, whatever is cached.
+    #[instrument(skip(self, provider), fields(provider = provider.name()))]
+    pub async fn refresh(
+        &self,
+        provider: &dyn Provider,
+        market: &Markets,
+    ) -> Result<(), FintekError> {
+        let hours = retry("market_state", || provider.fetch_market_hours(market)).await?;
+        let now = self.clock.now();
+        let after = |seconds: u64| now + Duration::seconds(seconds as i64);
+        let mut closes_at = hours.seconds_until_close.map(after);
+        // Without a close from the provider an open exchange is taken to
+        // shut with its regular session.
+        if let (0, None, Markets::Stock(stock)) = (hours.seconds_until_open, closes_at, market) {
+            let close = session(*stock).seconds_until_close(now);
+            closes_at = (close > 0).then(|| after(close));
+        }
+        let schedule = Schedule {
+            fetched: now,
+            opens_at: after(hours.seconds_until_open),
+            closes_at,
+        };
+        debug!(%market, opens_at = %schedule.opens_at, closes_at = ?schedule.closes_at, "Market state fetched");
+        self.schedules
+            .lock()
+            .unwrap()
+            .insert(market.to_string(), schedule);
+        Ok(())
+    }
+
+    /// Seconds until `market` opens, zero when it is open now; `None` when
+    /// what's cached is out of date.
+    pub fn seconds_until_open(&self, market: &Markets) -> Option<u64> {
+        let (schedule, now) = self.current(market)?;
+        Some((schedule.opens_at - now).num_seconds().max(0) as u64)
+    }
+}
+
+/// Commodity futures trade Sunday to Friday 18:00-17:00 New York time with
+/// an hour's maintenance break each evening.
+pub fn commodity_is_open(now: DateTime<Utc>) -> bool {
+    let local = now.with_timezone(&chrono_tz::America::New_York);
+    let time = local.time();
+    let (close, reopen) = (hm(17, 0), hm(18, 0));
+    match local.weekday() {
+        Weekday::Sat => false,
+        Weekday::Sun => time >= reopen,
+        Weekday::Fri => time < close,
+        _ => time < close || time >= reopen,
+    }
+}
+
+pub fn commodity_seconds_until_open(now: DateTime<Utc>) -> u64 {
+    if commodity_is_open(now) {
+        return 0;
+    }
+    let tz = chrono_tz::America::New_York;
+    let local = now.with_timezone(&tz);
+    for days in 0..8 {
+        let date = local.date_naive() + Duration::days(days);
+        if matches!(date.weekday(), Weekday::Fri | Weekday::Sat) {
+            continue;
+        }
+        let Some(open) = tz.from_local_datetime(&date.and_time(hm(18, 0))).earliest() else {
+            continue;
+        };
+        let open = open.with_timezone(&Utc);
+        if open > now {
+            return (open - now).num_seconds().max(0) as u64;
+        }
+    }
+    0
+}
+
+/// Spot forex trades around the clock from Sunday 17:00 to Friday 17:00
+/// New York time.
+pub fn forex_is_open(now: DateTime<Utc>) -> bool {
+    let local = now.with_timezone(&chrono_tz::America::New_York);
+    let time = local.time();
+    match local.weekday() {
+        Weekday::Sat => false,
+        Weekday::Sun => time >= hm(17, 0),
+        Weekday::Fri => time < hm(17, 0),
+        _ => true,
+    }
+}
+
+pub fn forex_seconds_until_open(now: DateTime<Utc>) -> u64 {
+    if forex_is_open(now) {
+        return 0;
+    }
+    let tz = chrono_tz::America::New_York;
+    let local = now.with_timezone(&tz);
+    for days in 0..8 {
+        let date = local.date_naive() + Duration::days(days);
+        if date.weekday() != Weekday::Sun {
+            continue;
+        }
+        let Some(open) = tz.from_local_datetime(&date.and_time(hm(17, 0))).earliest() else {
+            continue;
+        };
+        let open = open.with_timezone(&Utc);
+        if open > now {
+            return (open - now).num_seconds().max(0) as u64;
+        }
+    }
+    0
+}
+
+/// Publishes `market_open` and `seconds_until_open` for every exchange, for
+/// commodity futures, labelled `CME`, and for spot forex, labelled `FX`.
+pub fn export(now: DateTime<Utc>) {
+    for market in StockMarket::ALL {
+        let session = session(market);
+        metrics::update_market_state(
+            &format!("{:?}", market),
+            session.is_open(now),
+            session.seconds_until_open(now),
+        );
+    }
+    metrics::update_market_state(
+        "CME",
+        commodity_is_open(now),
+        commodity_seconds_until_open(now),
+    );
+    metrics::update_market_state("FX", forex_is_open(now), forex_seconds_until_open(now));
+}
+
+pub async fn run_exporter(clock: Arc<dyn Clock>) {
+    loop {
+        export(clock.now());
+        clock.sleep(std::time::Duration::from_secs(15)).await;
+    }
+}
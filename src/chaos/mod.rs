@@ -0,0 +1,197 @@
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::clock::Clock;
+use crate::metrics;
+use crate::provider::base_url;
+
+/// How often each fault is injected, as a fraction of upstream requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Faults {
+    /// Stalls, then drops the connection mid-response.
+    pub timeout: f64,
+    /// A truncated JSON body.
+    pub malformed: f64,
+    /// Twelve Data's out-of-credits error.
+    pub rate_limited: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ChaosConfig {
+    /// Route Twelve Data traffic through a fault-injecting proxy and run on
+    /// a clock that can be jumped. For resilience testing only.
+    pub enabled: bool,
+    pub listen: SocketAddr,
+    pub faults: Faults,
+    /// How long a timeout stalls before the connection is dropped.
+    pub timeout_secs: u64,
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        ChaosConfig {
+            enabled: false,
+            listen: ([127, 0, 0, 1], 9099).into(),
+            faults: Faults::default(),
+            timeout_secs: 10,
+        }
+    }
+}
+
+/// The system clock plus an offset the chaos endpoint can move either way.
+#[derive(Debug, Default)]
+pub struct JumpClock {
+    offset_ms: AtomicI64,
+}
+
+impl JumpClock {
+    pub fn jump(&self, by: chrono::Duration) {
+        self.offset_ms
+            .fetch_add(by.num_milliseconds(), Ordering::Relaxed);
+    }
+
+    pub fn offset(&self) -> chrono::Duration {
+        chrono::Duration::milliseconds(self.offset_ms.load(Ordering::Relaxed))
+    }
+}
+
+#[async_trait]
+impl Clock for JumpClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now() + self.offset()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Fault {
+    Timeout,
+    Malformed,
+    RateLimited,
+}
+
+impl Fault {
+    fn as_str(self) -> &'static str {
+        match self {
+            Fault::Timeout => "timeout",
+            Fault::Malformed => "malformed",
+            Fault::RateLimited => "rate_limited",
+        }
+    }
+}
+
+/// Fault rates, adjustable at runtime, and the clock they share.
+pub struct Chaos {
+    faults: RwLock<Faults>,
+    timeout: Duration,
+    upstream: String,
+    clock: Arc<JumpClock>,
+}
+
+impl Chaos {
+    pub fn new(config: &ChaosConfig, upstream: &str) -> Self {
+        Chaos {
+            faults: RwLock::new(config.faults),
+            timeout: Duration::from_secs(config.timeout_secs),
+            upstream: base_url(upstream),
+            clock: Arc::new(JumpClock::default()),
+        }
+    }
+
+    pub fn faults(&self) -> Faults {
+        *self.faults.read().unwrap()
+    }
+
+    pub fn set_faults(&self, faults: Faults) {
+        warn!(?faults, "Chaos fault rates changed");
+        *self.faults.write().unwrap() = faults;
+    }
+
+    pub fn clock(&self) -> &Arc<JumpClock> {
+        &self.clock
+    }
+
+    fn roll(&self) -> Option<Fault> {
+        let faults = self.faults();
+        let mut roll: f64 = rand::thread_rng().gen();
+        for (rate, fault) in [
+            (faults.timeout, Fault::Timeout),
+            (faults.malformed, Fault::Malformed),
+            (faults.rate_limited, Fault::RateLimited),
+        ] {
+            if roll < rate {
+                return Some(fault);
+            }
+            roll -= rate;
+        }
+        None
+    }
+
+    /// Serves the proxy on `addr`; point the provider's base URL at it.
+    pub async fn serve(self: Arc<Self>, addr: SocketAddr) {
+        let listener = match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!(%addr, error = %e, "Failed to bind chaos proxy");
+                return;
+            }
+        };
+        warn!(%addr, upstream = %self.upstream, "Chaos proxy injecting faults into provider traffic");
+        let app = Router::new().fallback(forward).with_state(self);
+        if let Err(e) = axum::serve(listener, app).await {
+            error!(error = %e, "Chaos proxy failed");
+        }
+    }
+}
+
+async fn forward(State(chaos): State<Arc<Chaos>>, uri: Uri) -> Response {
+    let path = uri.path_and_query().map_or("/", |p| p.as_str());
+    if let Some(fault) = chaos.roll() {
+        info!(fault = fault.as_str(), path = uri.path(), "Injecting fault");
+        metrics::update_chaos_fault(fault.as_str());
+        return match fault {
+            Fault::Timeout => {
+                tokio::time::sleep(chaos.timeout).await;
+                let body = futures_util::stream::once(async {
+                    Err::<String, _>(std::io::Error::other("chaos: connection dropped"))
+                });
+                Response::new(Body::from_stream(body))
+            }
+            Fault::Malformed => r#"{"price": "12"#.into_response(),
+            Fault::RateLimited => {
+                r#"{"code":429,"message":"chaos: out of API credits","status":"error"}"#
+                    .into_response()
+            }
+        };
+    }
+    let url = format!("{}{}", chaos.upstream, path);
+    match reqwest::get(&url).await {
+        Ok(response) => {
+            let status =
+                StatusCode::from_u16(response.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+            match response.text().await {
+                Ok(body) => (status, body).into_response(),
+                Err(e) => (StatusCode::BAD_GATEWAY, e.to_string()).into_response(),
+            }
+        }
+        Err(e) => (StatusCode::BAD_GATEWAY, e.to_string()).into_response(),
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use reqwest::{Method, RequestBuilder, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use crate::engine::watch::Watch;
+use crate::history::{Adjustment, Resolution, Split};
+use crate::notes::{NoteUpdate, SymbolNote};
+use crate::paper::{Order, OrderRequest};
+use crate::portfolio::{Holding, Valuation};
+use crate::sink::PriceUpdate;
+use crate::storage::{AlertRecord, DailyBar, RuleCount, StoredPrice};
+use crate::PricePoint;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("fintek request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("bad fintek URL: {0}")]
+    Url(String),
+    /// The daemon answered, but not with success.
+    #[error("fintek answered {status}: {message}")]
+    Status { status: StatusCode, message: String },
+}
+
+impl ClientError {
+    /// Whether the daemon said the thing asked for doesn't exist or isn't
+    /// enabled.
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, ClientError::Status { status, .. } if *status == StatusCode::NOT_FOUND)
+    }
+}
+
+/// What `/api/v1/history/:symbol` returns.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HistorySeries {
+    pub symbol: String,
+    pub adjustment: Adjustment,
+    pub resolution: Resolution,
+    pub splits: Vec<Split>,
+    pub points: Vec<PricePoint>,
+}
+
+/// What `/api/v1/query` returns.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct QueryValue {
+    pub expr: String,
+    pub time: DateTime<Utc>,
+    pub value: f64,
+}
+
+/// A typed client for a running daemon's JSON API, so other Rust services
+/// needn't hand-write requests. Prices are pushed by long-polling
+/// `/next` rather than a socket, as that's what the daemon serves.
+#[derive(Debug, Clone)]
+pub struct Client {
+    base: Url,
+    token: Option<String>,
+    http: reqwest::Client,
+}
+
+impl Client {
+    /// A client of the daemon at `base_url`, such as `http://localhost:9090`.
+    pub fn new(base_url: &str) -> Result<Self, ClientError> {
+        Ok(Client {
+            base: Url::parse(base_url).map_err(|e| ClientError::Url(e.to_string()))?,
+            token: None,
+            http: reqwest::Client::new(),
+        })
+    }
+
+    /// Sends `token` as a bearer token, for daemons with `[auth]` on.
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    // Segments are percent-encoded, so `BTC/USD` stays one.
+    fn request(&self, method: Method, segments: &[&str]) -> RequestBuilder {
+        let mut url = self.base.clone();
+        if let Ok(mut path) = url.path_segments_mut() {
+            path.pop_if_empty().extend(segments);
+        }
+        let request = self.http.request(method, url);
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<reqwest::Response, ClientError> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        // Errors come as `{"error": ...}` or plain text, depending on the route.
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<Value>(&body)
+            .ok()
+            .and_then(|v| v.get("error")?.as_str().map(str::to_string))
+            .unwrap_or(body);
+        Err(ClientError::Status { status, message })
+    }
+
+    async fn json<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, ClientError> {
+        Ok(self.send(request).await?.json().await?)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, segments: &[&str]) -> Result<T, ClientError> {
+        self.json(self.request(Method::GET, segments)).await
+    }
+
+    async fn delete(&self, segments: &[&str]) -> Result<(), ClientError> {
+        self.send(self.request(Method::DELETE, segments)).await?;
+        Ok(())
+    }
+
+    /// Uptime, symbol count and the like.
+    pub async fn status(&self) -> Result<Value, ClientError> {
+        self.get(&["api", "v1", "status"]).await
+    }
+
+    /// Latest prices of watched symbols, by symbol.
+    pub async fn prices(&self) -> Result<Vec<PriceUpdate>, ClientError> {
+        self.get(&["api", "v1", "prices"]).await
+    }
+
+    pub async fn price(&self, symbol: &str) -> Result<PriceUpdate, ClientError> {
+        self.get(&["api", "v1", "prices", symbol]).await
+    }
+
+    /// The next price of `symbol` different from `since`, or from the latest
+    /// when not given. `None` when none came within `timeout`, which the
+    /// daemon caps at two minutes.
+    pub async fn next_price(
+        &self,
+        symbol: &str,
+        since: Option<f64>,
+        timeout: Duration,
+    ) -> Result<Option<PriceUpdate>, ClientError> {
+        let mut query = vec![("timeout", format!("{}s", timeout.as_secs()))];
+        if let Some(since) = since {
+            query.push(("since", since.to_string()));
+        }
+        let request = self
+            .request(Method::GET, &["api", "v1", "prices", symbol, "next"])
+            .query(&query);
+        let response = self.send(request).await?;
+        if response.status() == StatusCode::NO_CONTENT {
+            return Ok(None);
+        }
+        Ok(Some(response.json().await?))
+    }
+
+    /// Prices of `symbol` since `since`, the last day when not given.
+    pub async fn history(
+        &self,
+        symbol: &str,
+        since: Option<DateTime<Utc>>,
+        resolution: Resolution,
+        adjustment: Adjustment,
+    ) -> Result<HistorySeries, ClientError> {
+        let mut query = vec![
+            ("resolution", enum_param(resolution)),
+            ("adjustment", enum_param(adjustment)),
+        ];
+        if let Some(since) = since {
+            query.push(("since", since.to_rfc3339()));
+        }
+        let request = self
+            .request(Method::GET, &["api", "v1", "history", symbol])
+            .query(&query);
+        self.json(request).await
+    }
+
+    /// Evaluates a query expression at `time`, or now.
+    pub async fn query(
+        &self,
+        expr: &str,
+        time: Option<DateTime<Utc>>,
+    ) -> Result<QueryValue, ClientError> {
+        let mut query = vec![("expr", expr.to_string())];
+        if let Some(time) = time {
+            query.push(("time", time.to_rfc3339()));
+        }
+        let request = self
+            .request(Method::GET, &["api", "v1", "query"])
+            .query(&query);
+        self.json(request).await
+    }
+
+    /// Stored prices of `symbol` between `from` and `to`.
+    pub async fn stored(
+        &self,
+        symbol: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<StoredPrice>, ClientError> {
+        let request = self
+            .request(Method::GET, &["api", "v1", "store", symbol])
+            .query(&[("from", from.to_rfc3339()), ("to", to.to_rfc3339())]);
+        self.json(request).await
+    }
+
+    /// Daily bars of `symbol` dated `from` through `to`.
+    pub async fn daily_bars(
+        &self,
+        symbol: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DailyBar>, ClientError> {
+        let request = self
+            .request(Method::GET, &["api", "v1", "daily", symbol])
+            .query(&[("from", from.to_string()), ("to", to.to_string())]);
+        self.json(request).await
+    }
+
+    /// The watchlist, or the token's namespace's.
+    pub async fn tickers(&self) -> Result<Vec<String>, ClientError> {
+        self.get(&["api", "v1", "tickers"]).await
+    }
+
+    /// Adds `symbol` to the watchlist, returning the new list.
+    pub async fn add_ticker(&self, symbol: &str) -> Result<Vec<String>, ClientError> {
+        let request = self
+            .request(Method::POST, &["api", "v1", "tickers"])
+            .json(&json!({ "symbol": symbol }));
+        self.json(request).await
+    }
+
+    pub async fn remove_ticker(&self, symbol: &str) -> Result<(), ClientError> {
+        self.delete(&["api", "v1", "tickers", symbol]).await
+    }
+
+    pub async fn watches(&self) -> Result<Vec<Watch>, ClientError> {
+        self.get(&["api", "v1", "watch"]).await
+    }
+
+    /// Polls `symbol` every cycle for `minutes`.
+    pub async fn watch(&self, symbol: &str, minutes: i64) -> Result<Watch, ClientError> {
+        let request = self
+            .request(Method::POST, &["api", "v1", "watch", symbol])
+            .json(&json!({ "minutes": minutes }));
+        self.json(request).await
+    }
+
+    pub async fn unwatch(&self, symbol: &str) -> Result<(), ClientError> {
+        self.delete(&["api", "v1", "watch", symbol]).await
+    }
+
+    /// The portfolio valued at the latest prices, or the token's namespace's.
+    pub async fn portfolio(&self) -> Result<Valuation, ClientError> {
+        self.get(&["api", "v1", "portfolio"]).await
+    }
+
+    pub async fn set_holding(
+        &self,
+        symbol: &str,
+        holding: Holding,
+    ) -> Result<Holding, ClientError> {
+        let request = self
+            .request(Method::PUT, &["api", "v1", "portfolio", symbol])
+            .json(&holding);
+        self.json(request).await
+    }
+
+    pub async fn remove_holding(&self, symbol: &str) -> Result<(), ClientError> {
+        self.delete(&["api", "v1", "portfolio", symbol]).await
+    }
+
+    pub async fn notes(&self) -> Result<BTreeMap<String, SymbolNote>, ClientError> {
+        self.get(&["api", "v1", "notes"]).await
+    }
+
+    pub async fn note(&self, symbol: &str) -> Result<SymbolNote, ClientError> {
+        self.get(&["api", "v1", "notes", symbol]).await
+    }
+
+    pub async fn set_note(
+        &self,
+        symbol: &str,
+        update: &NoteUpdate,
+    ) -> Result<SymbolNote, ClientError> {
+        let request = self
+            .request(Method::PUT, &["api", "v1", "notes", symbol])
+            .json(update);
+        self.json(request).await
+    }
+
+    pub async fn remove_note(&self, symbol: &str) -> Result<(), ClientError> {
+        self.delete(&["api", "v1", "notes", symbol]).await
+    }
+
+    pub async fn orders(&self) -> Result<Vec<Order>, ClientError> {
+        self.get(&["api", "v1", "paper", "orders"]).await
+    }
+
+    pub async fn place_order(&self, order: &OrderRequest) -> Result<Order, ClientError> {
+        let request = self
+            .request(Method::POST, &["api", "v1", "paper", "orders"])
+            .json(order);
+        self.json(request).await
+    }
+
+    pub async fn cancel_order(&self, id: u64) -> Result<Order, ClientError> {
+        let id = id.to_string();
+        let request = self.request(Method::DELETE, &["api", "v1", "paper", "orders", &id]);
+        self.json(request).await
+    }
+
+    /// Alerts fired since `since`, about `symbol` when given, newest first.
+    pub async fn alerts(
+        &self,
+        symbol: Option<&str>,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<AlertRecord>, ClientError> {
+        let mut query = vec![("since", since.to_rfc3339())];
+        if let Some(symbol) = symbol {
+            query.push(("symbol", symbol.to_string()));
+        }
+        let request = self
+            .request(Method::GET, &["api", "v1", "alerts", "history"])
+            .query(&query);
+        self.json(request).await
+    }
+
+    /// Alerts fired per rule since `since`.
+    pub async fn alert_rules(&self, since: DateTime<Utc>) -> Result<Vec<RuleCount>, ClientError> {
+        let request = self
+            .request(Method::GET, &["api", "v1", "alerts", "rules"])
+            .query(&[("since", since.to_rfc3339())]);
+        self.json(request).await
+    }
+
+    /// Runs a GraphQL query, returning the whole response, `errors` and all.
+    pub async fn graphql(&self, query: &str, variables: Value) -> Result<Value, ClientError> {
+        let request = self
+            .request(Method::POST, &["api", "v1", "graphql"])
+            .json(&json!({ "query": query, "variables": variables }));
+        self.json(request).await
+    }
+}
+
+// Query strings take the same lowercase names the JSON does.
+fn enum_param<T: Serialize>(value: T) -> String {
+    match serde_json::to_value(value) {
+        Ok(Value::String(s)) => s,
+        _ => String::new(),
+    }
+}
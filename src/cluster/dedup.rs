@@ -0,0 +1,109 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use sha2::{Digest, Sha256};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, warn};
+
+use super::ClusterConfig;
+use crate::clock::Clock;
+use crate::metrics;
+use crate::notify::Notification;
+
+const DIR: &str = "notifications";
+
+/// Makes replicas agree on who sends a notification. Each one is keyed on
+/// its rule (kind, title and symbols, but not the body, which carries
+/// instance-local prices) and the dedup window it fires in; the first
+/// replica to create the key's claim file in the shared store sends it and
+/// the others drop it.
+pub struct NotificationDedup {
+    dir: PathBuf,
+    instance: String,
+    window_secs: i64,
+    clock: Arc<dyn Clock>,
+}
+
+impl NotificationDedup {
+    /// `None` when clustering or deduplication is off.
+    pub fn new(config: &ClusterConfig, clock: Arc<dyn Clock>) -> Option<Self> {
+        (config.enabled && config.dedup_window_secs > 0).then(|| NotificationDedup {
+            dir: config.store.join(DIR),
+            instance: config.instance_name(),
+            window_secs: config.dedup_window_secs as i64,
+            clock,
+        })
+    }
+
+    fn key(notification: &Notification) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(format!("{:?}", notification.kind));
+        hasher.update([0]);
+        hasher.update(&notification.title);
+        for symbol in &notification.symbols {
+            hasher.update([0]);
+            hasher.update(symbol);
+        }
+        hasher.finalize()[..8]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// True when this replica should send. Fails open: if the store can't
+    /// be written a duplicate page beats a missed one.
+    pub async fn claim(&self, notification: &Notification) -> bool {
+        let window = self.clock.now().timestamp().div_euclid(self.window_secs);
+        let path = self
+            .dir
+            .join(format!("{}-{}.claim", Self::key(notification), window));
+        if let Err(e) = fs::create_dir_all(&self.dir).await {
+            warn!(error = %e, "Failed to create notification dedup store, sending anyway");
+            return true;
+        }
+        let claimed = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .await
+        {
+            Ok(mut file) => {
+                let _ = file.write_all(self.instance.as_bytes()).await;
+                true
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => false,
+            Err(e) => {
+                warn!(error = %e, "Failed to claim notification, sending anyway");
+                true
+            }
+        };
+        if claimed {
+            self.prune(window).await;
+        } else {
+            let owner = fs::read_to_string(&path).await.unwrap_or_default();
+            debug!(title = %notification.title, owner, "Notification already sent by another replica");
+            metrics::update_notifications_deduplicated();
+        }
+        claimed
+    }
+
+    // Claims older than the previous window can't collide any more.
+    async fn prune(&self, window: i64) {
+        let Ok(mut entries) = fs::read_dir(&self.dir).await else {
+            return;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let name = entry.file_name();
+            let stale = name
+                .to_str()
+                .and_then(|n| n.strip_suffix(".claim"))
+                .and_then(|n| n.rsplit_once('-'))
+                .and_then(|(_, w)| w.parse::<i64>().ok())
+                .is_some_and(|w| w < window - 1);
+            if stale {
+                let _ = fs::remove_file(entry.path()).await;
+            }
+        }
+    }
+}
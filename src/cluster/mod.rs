@@ -0,0 +1,174 @@
+pub mod dedup;
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::fs;
+use tracing::{error, info, instrument};
+
+use crate::clock::Clock;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ClusterConfig {
+    pub enabled: bool,
+    /// Unique per instance; defaults to the host name.
+    pub instance: Option<String>,
+    /// Directory shared by every instance, e.g. on a network mount.
+    pub store: PathBuf,
+    pub heartbeat_secs: u64,
+    /// Members silent for longer than this drop out of the ring.
+    pub ttl_secs: u64,
+    pub virtual_nodes: usize,
+    /// Replicas send a given notification at most once per window; 0 lets
+    /// every replica send its own.
+    pub dedup_window_secs: u64,
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        ClusterConfig {
+            enabled: false,
+            instance: None,
+            store: PathBuf::from("cluster"),
+            heartbeat_secs: 10,
+            ttl_secs: 30,
+            virtual_nodes: 64,
+            dedup_window_secs: 300,
+        }
+    }
+}
+
+impl ClusterConfig {
+    pub fn instance_name(&self) -> String {
+        self.instance
+            .clone()
+            .or_else(|| std::env::var("HOSTNAME").ok())
+            .unwrap_or_else(|| "fintek".into())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Heartbeat {
+    instance: String,
+    last_seen: DateTime<Utc>,
+}
+
+fn hash(key: &str) -> u64 {
+    let digest = Sha256::digest(key.as_bytes());
+    u64::from_be_bytes(digest[..8].try_into().expect("digest is 32 bytes"))
+}
+
+/// Splits symbols across the instances heartbeating into a shared
+/// directory with a consistent-hash ring, so a member joining or leaving
+/// only moves the symbols next to it on the ring.
+pub struct Cluster {
+    config: ClusterConfig,
+    instance: String,
+    ring: RwLock<BTreeMap<u64, String>>,
+}
+
+impl Cluster {
+    pub fn new(config: ClusterConfig) -> Self {
+        let instance = config.instance_name();
+        let cluster = Cluster {
+            config,
+            instance: instance.clone(),
+            ring: RwLock::new(BTreeMap::new()),
+        };
+        cluster.set_members(&[instance]);
+        cluster
+    }
+
+    pub fn instance(&self) -> &str {
+        &self.instance
+    }
+
+    pub fn members(&self) -> Vec<String> {
+        let mut members: Vec<String> = self.ring.read().unwrap().values().cloned().collect();
+        members.sort();
+        members.dedup();
+        members
+    }
+
+    fn set_members(&self, members: &[String]) {
+        let mut ring = BTreeMap::new();
+        for member in members {
+            for node in 0..self.config.virtual_nodes.max(1) {
+                ring.insert(hash(&format!("{}#{}", member, node)), member.clone());
+            }
+        }
+        *self.ring.write().unwrap() = ring;
+    }
+
+    pub fn owner(&self, symbol: &str) -> Option<String> {
+        let ring = self.ring.read().unwrap();
+        let h = hash(symbol);
+        ring.range(h..)
+            .next()
+            .or_else(|| ring.iter().next())
+            .map(|(_, member)| member.clone())
+    }
+
+    pub fn owns(&self, symbol: &str) -> bool {
+        self.owner(symbol)
+            .is_none_or(|owner| owner == self.instance)
+    }
+
+    #[instrument(skip(self), fields(instance = %self.instance))]
+    pub async fn heartbeat(&self, now: DateTime<Utc>) -> std::io::Result<()> {
+        fs::create_dir_all(&self.config.store).await?;
+        let beat = Heartbeat {
+            instance: self.instance.clone(),
+            last_seen: now,
+        };
+        let path = self.config.store.join(format!("{}.json", self.instance));
+        let tmp = path.with_extension("json.tmp");
+        fs::write(
+            &tmp,
+            serde_json::to_vec(&beat).expect("Failed to serialize heartbeat"),
+        )
+        .await?;
+        fs::rename(&tmp, &path).await?;
+
+        let mut members = vec![self.instance.clone()];
+        let mut entries = fs::read_dir(&self.config.store).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.path().extension().is_none_or(|e| e != "json") {
+                continue;
+            }
+            let Ok(data) = fs::read(entry.path()).await else {
+                continue;
+            };
+            let Ok(beat) = serde_json::from_slice::<Heartbeat>(&data) else {
+                continue;
+            };
+            let fresh = (now - beat.last_seen).num_seconds() <= self.config.ttl_secs as i64;
+            if fresh && !members.contains(&beat.instance) {
+                members.push(beat.instance);
+            }
+        }
+        members.sort();
+        if members != self.members() {
+            info!(?members, "Cluster membership changed");
+            self.set_members(&members);
+        }
+        Ok(())
+    }
+
+    pub async fn run(self: Arc<Self>, clock: Arc<dyn Clock>) {
+        loop {
+            if let Err(e) = self.heartbeat(clock.now()).await {
+                error!(error = %e, "Failed to heartbeat into cluster store");
+            }
+            clock
+                .sleep(Duration::from_secs(self.config.heartbeat_secs.max(1)))
+                .await;
+        }
+    }
+}
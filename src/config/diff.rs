@@ -0,0 +1,151 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::Config;
+use crate::usage::{self, Keys, UsageEstimate};
+
+// Top-level keys whose changes alter what fires rather than how often.
+const RULE_SECTIONS: &[&str] = &["alerts", "patterns", "strategy", "slo"];
+const INTERVAL_SUFFIXES: &[&str] = &["_ms", "_secs", "_minutes", "_hours", "interval"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// Alerts, patterns, strategies or objectives.
+    Rule,
+    /// How often something polls, refreshes or waits.
+    Interval,
+    Setting,
+}
+
+/// One key set differently, as both configs resolve it, defaults included.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    /// Like `alerts.rules[2].above`.
+    pub path: String,
+    pub kind: ChangeKind,
+    /// `None` when only the new config has it.
+    pub old: Option<Value>,
+    /// `None` when only the old config has it.
+    pub new: Option<Value>,
+}
+
+/// A provider's estimated daily credits under each config.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CreditChange {
+    pub provider: &'static str,
+    pub old: f64,
+    pub new: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigDiff {
+    pub added_symbols: Vec<String>,
+    pub removed_symbols: Vec<String>,
+    pub changes: Vec<Change>,
+    /// Providers whose estimate moved.
+    pub credits: Vec<CreditChange>,
+    pub old_usage: UsageEstimate,
+    pub new_usage: UsageEstimate,
+}
+
+impl ConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_symbols.is_empty() && self.removed_symbols.is_empty() && self.changes.is_empty()
+    }
+
+    /// Whether the new config breaks a plan limit the old one kept to.
+    pub fn newly_exceeded(&self) -> bool {
+        self.new_usage.exceeded() && !self.old_usage.exceeded()
+    }
+}
+
+/// What changes going from `old` to `new`, each with the symbols of its
+/// tickers file, and what that does to API usage.
+pub fn diff(
+    old: &Config,
+    old_symbols: &[String],
+    new: &Config,
+    new_symbols: &[String],
+    keys: Keys,
+) -> ConfigDiff {
+    let before: BTreeSet<String> = usage::polled_symbols(old, old_symbols)
+        .into_iter()
+        .collect();
+    let after: BTreeSet<String> = usage::polled_symbols(new, new_symbols)
+        .into_iter()
+        .collect();
+
+    let mut changes = vec![];
+    let old_value = serde_json::to_value(old).expect("config serializes");
+    let new_value = serde_json::to_value(new).expect("config serializes");
+    walk("", Some(&old_value), Some(&new_value), &mut changes);
+
+    let old_usage = usage::estimate(old, old_symbols, keys);
+    let new_usage = usage::estimate(new, new_symbols, keys);
+    let mut credits: BTreeMap<&'static str, (f64, f64)> = BTreeMap::new();
+    for source in &old_usage.sources {
+        credits.entry(source.provider).or_default().0 += source.credits_per_day;
+    }
+    for source in &new_usage.sources {
+        credits.entry(source.provider).or_default().1 += source.credits_per_day;
+    }
+    ConfigDiff {
+        added_symbols: after.difference(&before).cloned().collect(),
+        removed_symbols: before.difference(&after).cloned().collect(),
+        changes,
+        credits: credits
+            .into_iter()
+            .filter(|(_, (old, new))| old != new)
+            .map(|(provider, (old, new))| CreditChange { provider, old, new })
+            .collect(),
+        old_usage,
+        new_usage,
+    }
+}
+
+fn kind(path: &str) -> ChangeKind {
+    let section = path.split(['.', '[']).next().unwrap_or_default();
+    let leaf = path.rsplit('.').next().unwrap_or_default();
+    if RULE_SECTIONS.contains(&section) {
+        ChangeKind::Rule
+    } else if INTERVAL_SUFFIXES.iter().any(|s| leaf.ends_with(s)) {
+        ChangeKind::Interval
+    } else {
+        ChangeKind::Setting
+    }
+}
+
+// Tables are compared key by key and arrays of tables entry by entry, so a
+// changed rule shows as its changed keys. Anything else changes whole.
+fn walk(path: &str, old: Option<&Value>, new: Option<&Value>, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Some(Value::Object(old)), Some(Value::Object(new))) => {
+            let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+            for key in keys {
+                let path = match path {
+                    "" => key.clone(),
+                    _ => format!("{}.{}", path, key),
+                };
+                walk(&path, old.get(key), new.get(key), changes);
+            }
+        }
+        (Some(Value::Array(old)), Some(Value::Array(new)))
+            if old.iter().chain(new).any(Value::is_object) =>
+        {
+            for i in 0..old.len().max(new.len()) {
+                let path = format!("{}[{}]", path, i);
+                walk(&path, old.get(i), new.get(i), changes);
+            }
+        }
+        _ if old == new => {}
+        _ => changes.push(Change {
+            path: path.to_string(),
+            kind: kind(path),
+            old: old.cloned(),
+            new: new.cloned(),
+        }),
+    }
+}
@@ -0,0 +1,417 @@
+pub mod diff;
+pub mod schema;
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::fs;
+use tracing::{info, instrument, warn};
+
+use crate::adr::AdrConfig;
+use crate::alerts::AlertsConfig;
+use crate::allocation::AllocationConfig;
+use crate::analysts::AnalystsConfig;
+use crate::chaos::ChaosConfig;
+use crate::cluster::ClusterConfig;
+use crate::consensus::ConsensusConfig;
+use crate::corporate::CorporateConfig;
+use crate::currency::CurrencyConfig;
+use crate::daily::DailyConfig;
+use crate::dca::DcaPlan;
+use crate::econ::EconConfig;
+use crate::edgar::EdgarConfig;
+use crate::encryption::EncryptionConfig;
+use crate::engine::apply::ApplyConfig;
+use crate::engine::warmup::WarmupConfig;
+use crate::events::EventsConfig;
+use crate::futures::FuturesConfig;
+use crate::history::HistoryConfig;
+use crate::indicators::IndicatorsConfig;
+use crate::limits::LimitsConfig;
+use crate::listings::Company;
+use crate::market::MarketConfig;
+use crate::movers::MoversConfig;
+use crate::notify::NotifierConfig;
+use crate::onboard::OnboardConfig;
+use crate::ops::OpsConfig;
+use crate::options::OptionsConfig;
+use crate::paper::PaperConfig;
+use crate::patterns::PatternRule;
+use crate::portfolio::PortfolioConfig;
+use crate::priority::adaptive::AdaptiveConfig;
+use crate::priority::jitter::JitterConfig;
+use crate::priority::PriorityConfig;
+use crate::profile::ProfileConfig;
+use crate::provider::keys::KeysConfig;
+use crate::provider::ProviderConfig;
+use crate::quality::QualityConfig;
+use crate::rates::RatesConfig;
+use crate::returns::ReturnsConfig;
+use crate::sheets::SheetsConfig;
+use crate::slo::FreshnessObjective;
+use crate::smoothing::SmoothingConfig;
+use crate::social::SocialConfig;
+use crate::stats::StatsConfig;
+use crate::storage::StorageConfig;
+use crate::stream::StreamConfig;
+use crate::timeseries::TimeSeriesConfig;
+use crate::tracking::TrackingConfig;
+use crate::venues::Venue;
+use crate::watchdog::WatchdogConfig;
+use crate::StockMarket;
+use schema::{Diagnostic, Severity};
+
+pub const DEFAULT_PATH: &str = "fintek.toml";
+/// Env vars starting with this override config keys, `__` separating the
+/// path: `FINTEK__METRICS__ADDR=0.0.0.0:9091` sets `metrics.addr`. Values
+/// are read as TOML where they parse, else as strings.
+pub const ENV_PREFIX: &str = "FINTEK__";
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("failed to read config: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("config has {} error(s)", .0.iter().filter(|d| d.severity == Severity::Error).count())]
+    Invalid(Vec<Diagnostic>),
+    #[error("{var}: {message}")]
+    Override { var: String, message: String },
+}
+
+// A raw env value as TOML when it is one, so numbers and booleans keep
+// their type, else as a string.
+fn override_value(raw: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("v = {}", raw))
+        .ok()
+        .and_then(|mut t| t.remove("v"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
+
+fn apply_override(table: &mut toml::Table, var: &str, raw: &str) -> Result<(), ConfigError> {
+    let error = |message: &str| ConfigError::Override {
+        var: var.to_string(),
+        message: message.to_string(),
+    };
+    let path: Vec<String> = var[ENV_PREFIX.len()..]
+        .split("__")
+        .map(|part| part.to_ascii_lowercase())
+        .collect();
+    let Some((leaf, parents)) = path.split_last().filter(|(leaf, _)| !leaf.is_empty()) else {
+        return Err(error("names no config key"));
+    };
+    let mut table = table;
+    for parent in parents {
+        table = table
+            .entry(parent.clone())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| error(&format!("{} is not a table", parent)))?;
+    }
+    table.insert(leaf.clone(), override_value(raw));
+    Ok(())
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    pub tickers_path: PathBuf,
+    /// Where fintek keeps files it manages itself, such as API tokens.
+    pub state_dir: PathBuf,
+    pub exchange: StockMarket,
+    pub metrics: MetricsConfig,
+    pub rate_limits: Vec<RateLimit>,
+    pub limits: LimitsConfig,
+    pub slo: SloConfig,
+    pub priority: PriorityConfig,
+    pub adaptive: AdaptiveConfig,
+    pub listings: Vec<Company>,
+    /// How bare tickers are resolved to a listing when added.
+    pub onboard: OnboardConfig,
+    pub rates: RatesConfig,
+    pub econ: EconConfig,
+    pub movers: MoversConfig,
+    pub notifiers: Vec<NotifierConfig>,
+    pub paper: PaperConfig,
+    pub strategy: StrategyConfig,
+    pub events: EventsConfig,
+    pub cluster: ClusterConfig,
+    pub quality: QualityConfig,
+    pub smoothing: SmoothingConfig,
+    pub indicators: IndicatorsConfig,
+    /// Candlestick patterns to look for.
+    pub patterns: Vec<PatternRule>,
+    pub adr: AdrConfig,
+    /// Crypto exchanges to price trading costs for.
+    pub venues: Vec<Venue>,
+    pub currency: CurrencyConfig,
+    pub watchdog: WatchdogConfig,
+    pub portfolio: PortfolioConfig,
+    /// Recurring purchases `fintek dca` simulates over the stored prices.
+    pub dca: Vec<DcaPlan>,
+    pub daily: DailyConfig,
+    /// Continuous futures stitched from their contracts.
+    pub futures: FuturesConfig,
+    pub options: OptionsConfig,
+    /// Consensus ratings and price targets of the watched stocks.
+    pub analysts: AnalystsConfig,
+    /// Average volume, ATR and 52-week range from the daily bars.
+    pub stats: StatsConfig,
+    /// Intraday volume by price, its point of control and value area.
+    pub profile: ProfileConfig,
+    pub edgar: EdgarConfig,
+    pub social: SocialConfig,
+    pub market: MarketConfig,
+    pub jitter: JitterConfig,
+    pub keys: KeysConfig,
+    pub provider: ProviderConfig,
+    pub sheets: SheetsConfig,
+    pub corporate: CorporateConfig,
+    /// Send an end-of-day recap through the notifiers at each close.
+    pub eod_summary: bool,
+    pub ops: OpsConfig,
+    pub history: HistoryConfig,
+    /// Sector and region of held symbols, for the allocation gauges.
+    pub allocation: AllocationConfig,
+    pub tracking: TrackingConfig,
+    /// How days are cut for daily P&L, daily closes and tracking.
+    pub returns: ReturnsConfig,
+    pub timeseries: TimeSeriesConfig,
+    pub storage: StorageConfig,
+    pub stream: StreamConfig,
+    pub chaos: ChaosConfig,
+    pub consensus: ConsensusConfig,
+    /// The first fetch of every symbol at startup.
+    pub warmup: WarmupConfig,
+    /// How watchlist changes are put on probation and rolled back.
+    pub apply: ApplyConfig,
+    pub alerts: AlertsConfig,
+    /// Key the price store and event log are encrypted with.
+    pub encryption: EncryptionConfig,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StrategyConfig {
+    /// Width of the candles passed to `Strategy::on_candle`.
+    pub candle_secs: i64,
+    /// Rhai scripts run as strategies, reloaded when edited.
+    pub scripts: Vec<PathBuf>,
+}
+
+impl Default for StrategyConfig {
+    fn default() -> Self {
+        StrategyConfig {
+            candle_secs: 60,
+            scripts: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SloConfig {
+    pub objectives: Vec<FreshnessObjective>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MetricsConfig {
+    pub addr: SocketAddr,
+    pub max_concurrent: usize,
+    pub request_timeout_secs: u64,
+    /// How long fetches may keep failing before `/readyz` reports not ready.
+    pub ready_stale_secs: u64,
+    /// How far behind ingest or a writer may fall before
+    /// `pipeline_degraded` flags it.
+    pub degraded_lag_secs: u64,
+    /// Keys of tickers file labels exported on `ticker_labels`, for
+    /// slicing dashboards with e.g.
+    /// `stock_price * on(symbol) group_left(owner) ticker_labels`. Each is
+    /// a label on every symbol's series, so keep them few.
+    pub ticker_labels: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub struct RateLimit {
+    pub requests: u64,
+    pub period_secs: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            tickers_path: PathBuf::from("tickers"),
+            state_dir: PathBuf::from("state"),
+            exchange: StockMarket::NYSE,
+            metrics: MetricsConfig::default(),
+            rate_limits: vec![
+                RateLimit {
+                    requests: 8,
+                    period_secs: 60,
+                },
+                RateLimit {
+                    requests: 800,
+                    period_secs: (6.5 * 60. * 60.) as u64,
+                },
+            ],
+            limits: LimitsConfig::default(),
+            slo: SloConfig::default(),
+            priority: PriorityConfig::default(),
+            adaptive: AdaptiveConfig::default(),
+            listings: vec![],
+            onboard: OnboardConfig::default(),
+            rates: RatesConfig::default(),
+            econ: EconConfig::default(),
+            movers: MoversConfig::default(),
+            notifiers: vec![],
+            paper: PaperConfig::default(),
+            strategy: StrategyConfig::default(),
+            events: EventsConfig::default(),
+            cluster: ClusterConfig::default(),
+            quality: QualityConfig::default(),
+            smoothing: SmoothingConfig::default(),
+            indicators: IndicatorsConfig::default(),
+            patterns: vec![],
+            adr: AdrConfig::default(),
+            venues: vec![],
+            currency: CurrencyConfig::default(),
+            watchdog: WatchdogConfig::default(),
+            portfolio: PortfolioConfig::default(),
+            dca: vec![],
+            daily: DailyConfig::default(),
+            futures: FuturesConfig::default(),
+            options: OptionsConfig::default(),
+            analysts: AnalystsConfig::default(),
+            stats: StatsConfig::default(),
+            profile: ProfileConfig::default(),
+            edgar: EdgarConfig::default(),
+            social: SocialConfig::default(),
+            market: MarketConfig::default(),
+            jitter: JitterConfig::default(),
+            keys: KeysConfig::default(),
+            provider: ProviderConfig::default(),
+            sheets: SheetsConfig::default(),
+            corporate: CorporateConfig::default(),
+            eod_summary: false,
+            ops: OpsConfig::default(),
+            history: HistoryConfig::default(),
+            allocation: AllocationConfig::default(),
+            tracking: TrackingConfig::default(),
+            returns: ReturnsConfig::default(),
+            timeseries: TimeSeriesConfig::default(),
+            storage: StorageConfig::default(),
+            stream: StreamConfig::default(),
+            chaos: ChaosConfig::default(),
+            consensus: ConsensusConfig::default(),
+            warmup: WarmupConfig::default(),
+            apply: ApplyConfig::default(),
+            alerts: AlertsConfig::default(),
+            encryption: EncryptionConfig::default(),
+        }
+    }
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        MetricsConfig {
+            addr: ([127, 0, 0, 1], 9091).into(),
+            max_concurrent: 32,
+            request_timeout_secs: 10,
+            ready_stale_secs: 600,
+            degraded_lag_secs: 60,
+            ticker_labels: vec![],
+        }
+    }
+}
+
+impl Config {
+    /// Loads and validates the config, falling back to defaults when the
+    /// file doesn't exist, then applies [`ENV_PREFIX`] overrides.
+    #[instrument]
+    pub async fn load(path: &Path) -> Result<Self, ConfigError> {
+        let source = if fs::try_exists(path).await.unwrap_or(false) {
+            fs::read_to_string(path).await?
+        } else {
+            info!(path = %path.display(), "No config file found, using defaults");
+            String::new()
+        };
+        let overrides: Vec<(String, String)> = std::env::vars()
+            .filter(|(var, _)| var.starts_with(ENV_PREFIX))
+            .collect();
+        let (config, diagnostics) = Config::parse_with_overrides(&source, &overrides)?;
+        for diagnostic in diagnostics {
+            warn!(path = %path.display(), %diagnostic, "Config warning");
+        }
+        for (var, _) in &overrides {
+            info!(var, "Config key overridden from the environment");
+        }
+        Ok(config)
+    }
+
+    /// [`Config::parse`], then each `(var, value)` override on top. The
+    /// file is validated as written, each override against the schema on
+    /// its own.
+    pub fn parse_with_overrides(
+        source: &str,
+        overrides: &[(String, String)],
+    ) -> Result<(Self, Vec<Diagnostic>), ConfigError> {
+        let (config, diagnostics) = Config::parse(source)?;
+        if overrides.is_empty() {
+            return Ok((config, diagnostics));
+        }
+        let mut table: toml::Table = toml::from_str(source).unwrap_or_default();
+        for (var, raw) in overrides {
+            let mut alone = toml::Table::new();
+            apply_override(&mut alone, var, raw)?;
+            let alone = toml::to_string(&alone).unwrap_or_default();
+            for diagnostic in schema::validate(&alone) {
+                let message = format!("`{}` {}", diagnostic.path, diagnostic.message);
+                match diagnostic.severity {
+                    Severity::Error => {
+                        return Err(ConfigError::Override {
+                            var: var.clone(),
+                            message,
+                        })
+                    }
+                    Severity::Warning => warn!(var, warning = %message, "Config override warning"),
+                }
+            }
+            apply_override(&mut table, var, raw)?;
+        }
+        let config = Config::deserialize(table).map_err(|e| ConfigError::Override {
+            var: overrides
+                .iter()
+                .map(|(var, _)| var.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            message: e.message().to_string(),
+        })?;
+        Ok((config, diagnostics))
+    }
+
+    /// Validates `source` against the schema before deserializing it. Warnings
+    /// are returned alongside the config, errors fail the parse.
+    pub fn parse(source: &str) -> Result<(Self, Vec<Diagnostic>), ConfigError> {
+        let diagnostics = schema::validate(source);
+        if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+            return Err(ConfigError::Invalid(diagnostics));
+        }
+        match toml::from_str(source) {
+            Ok(config) => Ok((config, diagnostics)),
+            Err(e) => {
+                let (line, column) = e
+                    .span()
+                    .map(|span| schema::line_column(source, span.start))
+                    .unwrap_or((1, 1));
+                Err(ConfigError::Invalid(vec![Diagnostic {
+                    severity: Severity::Error,
+                    path: String::new(),
+                    line,
+                    column,
+                    message: e.message().to_string(),
+                }]))
+            }
+        }
+    }
+}
@@ -0,0 +1,2130 @@
+use std::fmt::{self, Display};
+use std::net::SocketAddr;
+use std::ops::Range;
+
+use serde::Serialize;
+use toml_edit::{ImDocument, Item, TableLike, Value};
+
+pub enum Kind {
+    String,
+    Integer {
+        min: i64,
+        max: i64,
+    },
+    Float {
+        min: f64,
+        max: f64,
+    },
+    Bool,
+    StringArray,
+    /// A table of strings under any keys.
+    StringMap,
+    IntegerArray {
+        min: i64,
+        max: i64,
+    },
+    FloatArray {
+        min: f64,
+        max: f64,
+    },
+    OneOf(&'static [&'static str]),
+    SocketAddr,
+    DateTime,
+    Time,
+    Table(&'static [Field]),
+    TableArray(&'static [Field]),
+}
+
+pub struct Field {
+    pub name: &'static str,
+    pub kind: Kind,
+}
+
+const RATE_LIMIT: &[Field] = &[
+    Field {
+        name: "requests",
+        kind: Kind::Integer {
+            min: 1,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "period_secs",
+        kind: Kind::Integer {
+            min: 1,
+            max: 31 * 24 * 60 * 60,
+        },
+    },
+];
+
+const METRICS: &[Field] = &[
+    Field {
+        name: "addr",
+        kind: Kind::SocketAddr,
+    },
+    Field {
+        name: "max_concurrent",
+        kind: Kind::Integer {
+            min: 1,
+            max: 10_000,
+        },
+    },
+    Field {
+        name: "request_timeout_secs",
+        kind: Kind::Integer { min: 1, max: 3600 },
+    },
+    Field {
+        name: "ready_stale_secs",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "degraded_lag_secs",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "ticker_labels",
+        kind: Kind::StringArray,
+    },
+];
+
+const ASSET_CLASSES: &[&str] = &["stock", "forex", "crypto", "commodity", "index", "rate"];
+
+const FRESHNESS_OBJECTIVE: &[Field] = &[
+    Field {
+        name: "asset_class",
+        kind: Kind::OneOf(ASSET_CLASSES),
+    },
+    Field {
+        name: "max_staleness_secs",
+        kind: Kind::Integer {
+            min: 1,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "target",
+        kind: Kind::Float { min: 0., max: 1. },
+    },
+    Field {
+        name: "market_hours_only",
+        kind: Kind::Bool,
+    },
+];
+
+const SLO: &[Field] = &[Field {
+    name: "objectives",
+    kind: Kind::TableArray(FRESHNESS_OBJECTIVE),
+}];
+
+const PRIORITY: &[Field] = &[
+    Field {
+        name: "interval_secs",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "critical",
+        kind: Kind::StringArray,
+    },
+    Field {
+        name: "low",
+        kind: Kind::StringArray,
+    },
+    Field {
+        name: "max_cycle_secs",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "over_budget",
+        kind: Kind::OneOf(&["stretch", "fail"]),
+    },
+];
+
+const ADAPTIVE: &[Field] = &[
+    Field {
+        name: "enabled",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "window",
+        kind: Kind::Integer {
+            min: 2,
+            max: 10_000,
+        },
+    },
+    Field {
+        name: "max_tighten",
+        kind: Kind::Float { min: 1., max: 100. },
+    },
+    Field {
+        name: "max_relax",
+        kind: Kind::Float { min: 1., max: 100. },
+    },
+];
+
+const LISTING: &[Field] = &[
+    Field {
+        name: "symbol",
+        kind: Kind::String,
+    },
+    Field {
+        name: "exchange",
+        kind: Kind::String,
+    },
+];
+
+const COMPANY: &[Field] = &[
+    Field {
+        name: "name",
+        kind: Kind::String,
+    },
+    Field {
+        name: "primary",
+        kind: Kind::String,
+    },
+    Field {
+        name: "listings",
+        kind: Kind::TableArray(LISTING),
+    },
+];
+
+const SPREAD: &[Field] = &[
+    Field {
+        name: "name",
+        kind: Kind::String,
+    },
+    Field {
+        name: "long",
+        kind: Kind::String,
+    },
+    Field {
+        name: "short",
+        kind: Kind::String,
+    },
+];
+
+const EQUITY_RISK_PREMIUM: &[Field] = &[
+    Field {
+        name: "yield_symbol",
+        kind: Kind::String,
+    },
+    Field {
+        name: "earnings_yield",
+        kind: Kind::Float {
+            min: -100.,
+            max: 100.,
+        },
+    },
+];
+
+const RATES: &[Field] = &[
+    Field {
+        name: "spreads",
+        kind: Kind::TableArray(SPREAD),
+    },
+    Field {
+        name: "equity_risk_premium",
+        kind: Kind::Table(EQUITY_RISK_PREMIUM),
+    },
+];
+
+const ECON_EVENT: &[Field] = &[
+    Field {
+        name: "name",
+        kind: Kind::String,
+    },
+    Field {
+        name: "time",
+        kind: Kind::DateTime,
+    },
+    Field {
+        name: "country",
+        kind: Kind::String,
+    },
+    Field {
+        name: "impact",
+        kind: Kind::String,
+    },
+];
+
+const ECON: &[Field] = &[
+    Field {
+        name: "events",
+        kind: Kind::TableArray(ECON_EVENT),
+    },
+    Field {
+        name: "watch",
+        kind: Kind::StringArray,
+    },
+    Field {
+        name: "action",
+        kind: Kind::OneOf(&["pause", "boost"]),
+    },
+    Field {
+        name: "before_mins",
+        kind: Kind::Integer {
+            min: 0,
+            max: 24 * 60,
+        },
+    },
+    Field {
+        name: "after_mins",
+        kind: Kind::Integer {
+            min: 0,
+            max: 24 * 60,
+        },
+    },
+    Field {
+        name: "boost_factor",
+        kind: Kind::Float { min: 1., max: 100. },
+    },
+];
+
+const MOVERS: &[Field] = &[
+    Field {
+        name: "enabled",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "time",
+        kind: Kind::Time,
+    },
+    Field {
+        name: "count",
+        kind: Kind::Integer { min: 1, max: 50 },
+    },
+    Field {
+        name: "auto_add",
+        kind: Kind::Bool,
+    },
+];
+
+const NOTIFIER: &[Field] = &[
+    Field {
+        name: "kind",
+        kind: Kind::OneOf(&["log", "webhook", "email", "ntfy", "pushover"]),
+    },
+    Field {
+        name: "url",
+        kind: Kind::String,
+    },
+    Field {
+        name: "secret",
+        kind: Kind::String,
+    },
+    Field {
+        name: "bearer",
+        kind: Kind::String,
+    },
+    Field {
+        name: "headers",
+        kind: Kind::StringMap,
+    },
+    Field {
+        name: "smtp_host",
+        kind: Kind::String,
+    },
+    Field {
+        name: "smtp_port",
+        kind: Kind::Integer { min: 1, max: 65535 },
+    },
+    Field {
+        name: "username",
+        kind: Kind::String,
+    },
+    Field {
+        name: "from",
+        kind: Kind::String,
+    },
+    Field {
+        name: "to",
+        kind: Kind::StringArray,
+    },
+    Field {
+        name: "mode",
+        kind: Kind::OneOf(&["immediate", "digest"]),
+    },
+    Field {
+        name: "digest_at",
+        kind: Kind::Time,
+    },
+    Field {
+        name: "timezone",
+        kind: Kind::String,
+    },
+    Field {
+        name: "template",
+        kind: Kind::String,
+    },
+    Field {
+        name: "server",
+        kind: Kind::String,
+    },
+    Field {
+        name: "topic",
+        kind: Kind::String,
+    },
+    Field {
+        name: "token",
+        kind: Kind::String,
+    },
+    Field {
+        name: "user",
+        kind: Kind::String,
+    },
+];
+
+const PAPER: &[Field] = &[
+    Field {
+        name: "starting_cash",
+        kind: Kind::Float {
+            min: 0.,
+            max: f64::MAX,
+        },
+    },
+    Field {
+        name: "commission",
+        kind: Kind::Float {
+            min: 0.,
+            max: f64::MAX,
+        },
+    },
+];
+
+const STRATEGY: &[Field] = &[
+    Field {
+        name: "candle_secs",
+        kind: Kind::Integer {
+            min: 1,
+            max: 86_400,
+        },
+    },
+    Field {
+        name: "scripts",
+        kind: Kind::StringArray,
+    },
+];
+
+const EVENTS: &[Field] = &[Field {
+    name: "log",
+    kind: Kind::String,
+}];
+
+const CLUSTER: &[Field] = &[
+    Field {
+        name: "enabled",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "instance",
+        kind: Kind::String,
+    },
+    Field {
+        name: "store",
+        kind: Kind::String,
+    },
+    Field {
+        name: "heartbeat_secs",
+        kind: Kind::Integer { min: 1, max: 3600 },
+    },
+    Field {
+        name: "ttl_secs",
+        kind: Kind::Integer {
+            min: 1,
+            max: 86_400,
+        },
+    },
+    Field {
+        name: "virtual_nodes",
+        kind: Kind::Integer { min: 1, max: 1024 },
+    },
+    Field {
+        name: "dedup_window_secs",
+        kind: Kind::Integer {
+            min: 0,
+            max: 86_400,
+        },
+    },
+];
+
+const QUALITY: &[Field] = &[
+    Field {
+        name: "window_secs",
+        kind: Kind::Integer {
+            min: 60,
+            max: 7 * 86_400,
+        },
+    },
+    Field {
+        name: "anomaly_pct",
+        kind: Kind::Float {
+            min: 0.,
+            max: 1000.,
+        },
+    },
+    Field {
+        name: "stale_after_secs",
+        kind: Kind::Integer {
+            min: 1,
+            max: 86_400,
+        },
+    },
+];
+
+const SMOOTHING: &[Field] = &[
+    Field {
+        name: "method",
+        kind: Kind::OneOf(&["none", "median", "kalman"]),
+    },
+    Field {
+        name: "window",
+        kind: Kind::Integer { min: 1, max: 100 },
+    },
+    Field {
+        name: "process_noise",
+        kind: Kind::Float { min: 0., max: 1. },
+    },
+    Field {
+        name: "measurement_noise",
+        kind: Kind::Float { min: 0., max: 1. },
+    },
+    Field {
+        name: "symbols",
+        kind: Kind::StringArray,
+    },
+];
+
+const INDICATORS: &[Field] = &[
+    Field {
+        name: "sma",
+        kind: Kind::IntegerArray { min: 1, max: 10000 },
+    },
+    Field {
+        name: "ema",
+        kind: Kind::IntegerArray { min: 1, max: 10000 },
+    },
+    Field {
+        name: "rsi",
+        kind: Kind::Integer { min: 0, max: 10000 },
+    },
+    Field {
+        name: "symbols",
+        kind: Kind::StringArray,
+    },
+];
+
+const PATTERN: &[Field] = &[
+    Field {
+        name: "symbols",
+        kind: Kind::StringArray,
+    },
+    Field {
+        name: "timeframe_secs",
+        kind: Kind::Integer {
+            min: 1,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "detect",
+        kind: Kind::StringArray,
+    },
+];
+
+const ADR_PAIR: &[Field] = &[
+    Field {
+        name: "adr",
+        kind: Kind::String,
+    },
+    Field {
+        name: "underlying",
+        kind: Kind::String,
+    },
+    Field {
+        name: "fx",
+        kind: Kind::String,
+    },
+    Field {
+        name: "ratio",
+        kind: Kind::Float {
+            min: f64::MIN_POSITIVE,
+            max: f64::MAX,
+        },
+    },
+    Field {
+        name: "alert_percent",
+        kind: Kind::Float {
+            min: 0.,
+            max: f64::MAX,
+        },
+    },
+];
+
+const ADR: &[Field] = &[
+    Field {
+        name: "pairs",
+        kind: Kind::TableArray(ADR_PAIR),
+    },
+    Field {
+        name: "max_age_secs",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+];
+
+const FUTURES_CONTRACT: &[Field] = &[
+    Field {
+        name: "symbol",
+        kind: Kind::String,
+    },
+    Field {
+        name: "expiry",
+        kind: Kind::String,
+    },
+];
+
+const CONTINUOUS_FUTURE: &[Field] = &[
+    Field {
+        name: "symbol",
+        kind: Kind::String,
+    },
+    Field {
+        name: "contracts",
+        kind: Kind::TableArray(FUTURES_CONTRACT),
+    },
+    Field {
+        name: "roll",
+        kind: Kind::OneOf(&["calendar", "volume", "open_interest"]),
+    },
+    Field {
+        name: "roll_days",
+        kind: Kind::Integer { min: 0, max: 365 },
+    },
+];
+
+const FUTURES: &[Field] = &[Field {
+    name: "continuous",
+    kind: Kind::TableArray(CONTINUOUS_FUTURE),
+}];
+
+const OPTIONS: &[Field] = &[
+    Field {
+        name: "symbols",
+        kind: Kind::StringArray,
+    },
+    Field {
+        name: "expirations",
+        kind: Kind::Integer { min: 1, max: 52 },
+    },
+    Field {
+        name: "refresh_secs",
+        kind: Kind::Integer {
+            min: 60,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "risk_free_percent",
+        kind: Kind::Float {
+            min: -10.,
+            max: 100.,
+        },
+    },
+    Field {
+        name: "dividend_yield_percent",
+        kind: Kind::Float { min: 0., max: 100. },
+    },
+    Field {
+        name: "per_contract",
+        kind: Kind::Bool,
+    },
+];
+
+const ANALYSTS: &[Field] = &[
+    Field {
+        name: "enabled",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "refresh_hours",
+        kind: Kind::Integer {
+            min: 1,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "target_change_percent",
+        kind: Kind::Float {
+            min: 0.,
+            max: 1000.,
+        },
+    },
+];
+
+const STATS: &[Field] = &[
+    Field {
+        name: "enabled",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "ttl_hours",
+        kind: Kind::Integer {
+            min: 1,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "atr_days",
+        kind: Kind::Integer { min: 1, max: 250 },
+    },
+    Field {
+        name: "volume_days",
+        kind: Kind::Integer { min: 1, max: 250 },
+    },
+];
+
+const PROFILE: &[Field] = &[
+    Field {
+        name: "enabled",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "interval",
+        kind: Kind::OneOf(&["1min", "5min", "15min", "30min", "45min", "1h"]),
+    },
+    Field {
+        name: "buckets",
+        kind: Kind::Integer { min: 1, max: 1000 },
+    },
+    Field {
+        name: "value_area_percent",
+        kind: Kind::Float { min: 1., max: 100. },
+    },
+    Field {
+        name: "refresh_secs",
+        kind: Kind::Integer {
+            min: 1,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "metrics",
+        kind: Kind::Bool,
+    },
+];
+
+const EDGAR: &[Field] = &[
+    Field {
+        name: "enabled",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "user_agent",
+        kind: Kind::String,
+    },
+    Field {
+        name: "forms",
+        kind: Kind::StringArray,
+    },
+    Field {
+        name: "poll_secs",
+        kind: Kind::Integer {
+            min: 60,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "url",
+        kind: Kind::String,
+    },
+    Field {
+        name: "data_url",
+        kind: Kind::String,
+    },
+];
+
+const SOCIAL: &[Field] = &[
+    Field {
+        name: "sources",
+        kind: Kind::StringArray,
+    },
+    Field {
+        name: "poll_secs",
+        kind: Kind::Integer {
+            min: 60,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "window",
+        kind: Kind::Integer {
+            min: 1,
+            max: 10_000,
+        },
+    },
+    Field {
+        name: "min_samples",
+        kind: Kind::Integer {
+            min: 1,
+            max: 10_000,
+        },
+    },
+    Field {
+        name: "spike_zscore",
+        kind: Kind::Float { min: 0., max: 100. },
+    },
+    Field {
+        name: "min_mentions",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "subreddits",
+        kind: Kind::StringArray,
+    },
+    Field {
+        name: "user_agent",
+        kind: Kind::String,
+    },
+    Field {
+        name: "stocktwits_url",
+        kind: Kind::String,
+    },
+    Field {
+        name: "reddit_url",
+        kind: Kind::String,
+    },
+];
+
+const MARKET: &[Field] = &[
+    Field {
+        name: "fear_greed",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "fear_greed_url",
+        kind: Kind::String,
+    },
+    Field {
+        name: "refresh_secs",
+        kind: Kind::Integer {
+            min: 60,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "breadth",
+        kind: Kind::Bool,
+    },
+];
+
+const VENUE: &[Field] = &[
+    Field {
+        name: "name",
+        kind: Kind::String,
+    },
+    Field {
+        name: "symbols",
+        kind: Kind::StringArray,
+    },
+    Field {
+        name: "fee_percent",
+        kind: Kind::Float { min: 0., max: 100. },
+    },
+    Field {
+        name: "spread_percent",
+        kind: Kind::Float { min: 0., max: 100. },
+    },
+    Field {
+        name: "exchange",
+        kind: Kind::String,
+    },
+    Field {
+        name: "interval_secs",
+        kind: Kind::Integer { min: 1, max: 86400 },
+    },
+];
+
+const CURRENCY: &[Field] = &[Field {
+    name: "base",
+    kind: Kind::String,
+}];
+
+const PORTFOLIO: &[Field] = &[Field {
+    name: "ladder_percent",
+    kind: Kind::FloatArray {
+        min: 0.,
+        max: 100_000.,
+    },
+}];
+
+const DCA_PLAN: &[Field] = &[
+    Field {
+        name: "symbol",
+        kind: Kind::String,
+    },
+    Field {
+        name: "amount",
+        kind: Kind::Float {
+            min: 0.,
+            max: f64::MAX,
+        },
+    },
+    Field {
+        name: "schedule",
+        kind: Kind::OneOf(&["daily", "weekly", "biweekly", "monthly"]),
+    },
+];
+
+const DAILY: &[Field] = &[
+    Field {
+        name: "enabled",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "run_at",
+        kind: Kind::Time,
+    },
+    Field {
+        name: "timezone",
+        kind: Kind::String,
+    },
+    Field {
+        name: "budget",
+        kind: Kind::Integer {
+            min: 1,
+            max: 1_000_000,
+        },
+    },
+    Field {
+        name: "fundamentals",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "calendars",
+        kind: Kind::Bool,
+    },
+];
+
+const WATCHDOG: &[Field] = &[
+    Field {
+        name: "enabled",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "stall_secs",
+        kind: Kind::Integer {
+            min: 0,
+            max: 31 * 24 * 60 * 60,
+        },
+    },
+];
+
+const JITTER: &[Field] = &[
+    Field {
+        name: "fraction",
+        kind: Kind::Float { min: 0., max: 1. },
+    },
+    Field {
+        name: "seed",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+];
+
+const KEYS: &[Field] = &[Field {
+    name: "probe_interval_secs",
+    kind: Kind::Integer {
+        min: 30,
+        max: 86_400,
+    },
+}];
+
+const RETRY: &[Field] = &[
+    Field {
+        name: "attempts",
+        kind: Kind::Integer { min: 1, max: 10 },
+    },
+    Field {
+        name: "base_delay_ms",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "max_delay_ms",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+];
+
+const REGIONS: &[Field] = &[
+    Field {
+        name: "twelvedata",
+        kind: Kind::StringArray,
+    },
+    Field {
+        name: "finnhub",
+        kind: Kind::StringArray,
+    },
+    Field {
+        name: "probe_secs",
+        kind: Kind::Integer {
+            min: 1,
+            max: i64::MAX,
+        },
+    },
+];
+
+const ENCRYPTION: &[Field] = &[
+    Field {
+        name: "key_file",
+        kind: Kind::String,
+    },
+    Field {
+        name: "key",
+        kind: Kind::String,
+    },
+];
+
+const PROVIDER: &[Field] = &[
+    Field {
+        name: "kind",
+        kind: Kind::OneOf(&["twelvedata", "finnhub", "rest"]),
+    },
+    Field {
+        name: "twelvedata_url",
+        kind: Kind::String,
+    },
+    Field {
+        name: "finnhub_url",
+        kind: Kind::String,
+    },
+    Field {
+        name: "number_locale",
+        kind: Kind::OneOf(&["auto", "en", "de", "fr", "ch"]),
+    },
+    Field {
+        name: "quotes",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "batch_size",
+        kind: Kind::Integer { min: 1, max: 120 },
+    },
+    Field {
+        name: "concurrency",
+        kind: Kind::Integer { min: 1, max: 64 },
+    },
+    Field {
+        name: "market_state_secs",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "read_through",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "read_through_cache_secs",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "read_through_daily_quota",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "api_key",
+        kind: Kind::String,
+    },
+    Field {
+        name: "retry",
+        kind: Kind::Table(RETRY),
+    },
+    Field {
+        name: "regions",
+        kind: Kind::Table(REGIONS),
+    },
+    Field {
+        name: "rest",
+        kind: Kind::Table(REST),
+    },
+];
+
+const REST: &[Field] = &[
+    Field {
+        name: "url",
+        kind: Kind::String,
+    },
+    Field {
+        name: "price",
+        kind: Kind::String,
+    },
+    Field {
+        name: "timestamp",
+        kind: Kind::String,
+    },
+    Field {
+        name: "headers",
+        kind: Kind::StringMap,
+    },
+    Field {
+        name: "symbols",
+        kind: Kind::StringMap,
+    },
+    Field {
+        name: "signing",
+        kind: Kind::Table(SIGNING),
+    },
+];
+
+const SIGNING: &[Field] = &[
+    Field {
+        name: "scheme",
+        kind: Kind::OneOf(&["none", "binance", "coinbase", "hmac"]),
+    },
+    Field {
+        name: "recv_window_ms",
+        kind: Kind::Integer {
+            min: 1,
+            max: 60_000,
+        },
+    },
+];
+
+const SHEETS: &[Field] = &[
+    Field {
+        name: "enabled",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "spreadsheet_id",
+        kind: Kind::String,
+    },
+    Field {
+        name: "credentials",
+        kind: Kind::String,
+    },
+    Field {
+        name: "prices_sheet",
+        kind: Kind::String,
+    },
+    Field {
+        name: "portfolio_sheet",
+        kind: Kind::String,
+    },
+    Field {
+        name: "interval_secs",
+        kind: Kind::Integer {
+            min: 10,
+            max: 86_400,
+        },
+    },
+];
+
+const CORPORATE: &[Field] = &[
+    Field {
+        name: "enabled",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "refresh_hours",
+        kind: Kind::Integer { min: 1, max: 168 },
+    },
+    Field {
+        name: "horizon_days",
+        kind: Kind::Integer { min: 1, max: 366 },
+    },
+];
+
+const PAGERDUTY: &[Field] = &[Field {
+    name: "routing_key",
+    kind: Kind::String,
+}];
+
+const LIMITS: &[Field] = &[
+    Field {
+        name: "max_symbols",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "max_series",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "max_stored_per_sec",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+];
+
+const ONBOARD: &[Field] = &[Field {
+    name: "prefer",
+    kind: Kind::StringArray,
+}];
+
+const OPSGENIE: &[Field] = &[
+    Field {
+        name: "api_key",
+        kind: Kind::String,
+    },
+    Field {
+        name: "url",
+        kind: Kind::String,
+    },
+];
+
+const OPS: &[Field] = &[
+    Field {
+        name: "pagerduty",
+        kind: Kind::Table(PAGERDUTY),
+    },
+    Field {
+        name: "opsgenie",
+        kind: Kind::Table(OPSGENIE),
+    },
+    Field {
+        name: "failures_before_down",
+        kind: Kind::Integer { min: 1, max: 1000 },
+    },
+    Field {
+        name: "outage_after_secs",
+        kind: Kind::Integer {
+            min: 60,
+            max: 86_400,
+        },
+    },
+];
+
+const HISTORY: &[Field] = &[
+    Field {
+        name: "max_bytes_per_symbol",
+        kind: Kind::Integer {
+            min: 1024,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "split_refresh_hours",
+        kind: Kind::Integer {
+            min: 1,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "tick_retention_hours",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "minute_retention_days",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "tick_format",
+        kind: Kind::OneOf(&["ndjson", "delta"]),
+    },
+];
+
+const SYMBOL_META: &[Field] = &[
+    Field {
+        name: "symbol",
+        kind: Kind::String,
+    },
+    Field {
+        name: "sector",
+        kind: Kind::String,
+    },
+    Field {
+        name: "region",
+        kind: Kind::String,
+    },
+];
+
+const ALLOCATION: &[Field] = &[Field {
+    name: "symbols",
+    kind: Kind::TableArray(SYMBOL_META),
+}];
+
+const TRACKING: &[Field] = &[
+    Field {
+        name: "benchmark",
+        kind: Kind::String,
+    },
+    Field {
+        name: "window_days",
+        kind: Kind::Integer {
+            min: 2,
+            max: 10_000,
+        },
+    },
+];
+
+const RETURNS: &[Field] = &[
+    Field {
+        name: "convention",
+        kind: Kind::OneOf(&["mark_to_market", "close_to_close"]),
+    },
+    Field {
+        name: "timezone",
+        kind: Kind::String,
+    },
+    Field {
+        name: "settlement",
+        kind: Kind::OneOf(&["calendar", "trading_days"]),
+    },
+];
+
+const TIMESERIES: &[Field] = &[
+    Field {
+        name: "backfill",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "interval",
+        kind: Kind::OneOf(&[
+            "1min", "5min", "15min", "30min", "45min", "1h", "2h", "4h", "1day", "1week", "1month",
+        ]),
+    },
+    Field {
+        name: "output_size",
+        kind: Kind::Integer { min: 1, max: 5000 },
+    },
+    Field {
+        name: "repair_gaps",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "gap_lookback_days",
+        kind: Kind::Integer { min: 1, max: 3650 },
+    },
+    Field {
+        name: "gap_scan_hours",
+        kind: Kind::Integer { min: 1, max: 720 },
+    },
+];
+
+const STORAGE: &[Field] = &[
+    Field {
+        name: "path",
+        kind: Kind::String,
+    },
+    Field {
+        name: "replica_url",
+        kind: Kind::String,
+    },
+    Field {
+        name: "sqlite",
+        kind: Kind::Table(SQLITE),
+    },
+];
+
+const SQLITE: &[Field] = &[
+    Field {
+        name: "wal",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "synchronous",
+        kind: Kind::OneOf(&["off", "normal", "full", "extra"]),
+    },
+    Field {
+        name: "busy_timeout_ms",
+        kind: Kind::Integer {
+            min: 0,
+            max: 600_000,
+        },
+    },
+    Field {
+        name: "max_connections",
+        kind: Kind::Integer { min: 1, max: 64 },
+    },
+    Field {
+        name: "min_connections",
+        kind: Kind::Integer { min: 0, max: 64 },
+    },
+    Field {
+        name: "acquire_timeout_secs",
+        kind: Kind::Integer { min: 1, max: 600 },
+    },
+];
+
+const STREAM: &[Field] = &[
+    Field {
+        name: "enabled",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "url",
+        kind: Kind::String,
+    },
+    Field {
+        name: "heartbeat_secs",
+        kind: Kind::Integer {
+            min: 1,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "stale_secs",
+        kind: Kind::Integer {
+            min: 1,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "max_backoff_secs",
+        kind: Kind::Integer {
+            min: 1,
+            max: i64::MAX,
+        },
+    },
+];
+
+const FAULTS: &[Field] = &[
+    Field {
+        name: "timeout",
+        kind: Kind::Float { min: 0., max: 1. },
+    },
+    Field {
+        name: "malformed",
+        kind: Kind::Float { min: 0., max: 1. },
+    },
+    Field {
+        name: "rate_limited",
+        kind: Kind::Float { min: 0., max: 1. },
+    },
+];
+
+const CHAOS: &[Field] = &[
+    Field {
+        name: "enabled",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "listen",
+        kind: Kind::String,
+    },
+    Field {
+        name: "faults",
+        kind: Kind::Table(FAULTS),
+    },
+    Field {
+        name: "timeout_secs",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+];
+
+const CONSENSUS: &[Field] = &[
+    Field {
+        name: "sources",
+        kind: Kind::StringArray,
+    },
+    Field {
+        name: "interval_secs",
+        kind: Kind::Integer {
+            min: 1,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "max_age_secs",
+        kind: Kind::Integer {
+            min: 1,
+            max: i64::MAX,
+        },
+    },
+];
+
+const WARMUP: &[Field] = &[
+    Field {
+        name: "enabled",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "sources",
+        kind: Kind::StringArray,
+    },
+    Field {
+        name: "source_credits",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "timeout_secs",
+        kind: Kind::Integer { min: 1, max: 3600 },
+    },
+];
+
+const APPLY: &[Field] = &[
+    Field {
+        name: "grace_secs",
+        kind: Kind::Integer {
+            min: 0,
+            max: 86_400,
+        },
+    },
+    Field {
+        name: "max_failures",
+        kind: Kind::Integer {
+            min: 1,
+            max: i64::MAX,
+        },
+    },
+];
+
+const ALERT_RULE: &[Field] = &[
+    Field {
+        name: "symbol",
+        kind: Kind::String,
+    },
+    Field {
+        name: "name",
+        kind: Kind::String,
+    },
+    Field {
+        name: "when",
+        kind: Kind::OneOf(&["above", "below", "move"]),
+    },
+    Field {
+        name: "price",
+        kind: Kind::Float {
+            min: 0.,
+            max: f64::MAX,
+        },
+    },
+    Field {
+        name: "percent",
+        kind: Kind::Float {
+            min: -100.,
+            max: f64::MAX,
+        },
+    },
+    Field {
+        name: "minutes",
+        kind: Kind::Integer {
+            min: 1,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "cooldown_secs",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "urgency",
+        kind: Kind::OneOf(&["low", "normal", "high"]),
+    },
+];
+
+const ALERTS: &[Field] = &[
+    Field {
+        name: "rules",
+        kind: Kind::TableArray(ALERT_RULE),
+    },
+    Field {
+        name: "cooldown_secs",
+        kind: Kind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    },
+    Field {
+        name: "webhooks",
+        kind: Kind::StringArray,
+    },
+];
+
+pub const CONFIG: &[Field] = &[
+    Field {
+        name: "tickers_path",
+        kind: Kind::String,
+    },
+    Field {
+        name: "state_dir",
+        kind: Kind::String,
+    },
+    Field {
+        name: "exchange",
+        kind: Kind::OneOf(&[
+            "NYSE", "NASDAQ", "LSE", "XETR", "Euronext", "SIX", "JPX", "HKEX",
+        ]),
+    },
+    Field {
+        name: "metrics",
+        kind: Kind::Table(METRICS),
+    },
+    Field {
+        name: "rate_limits",
+        kind: Kind::TableArray(RATE_LIMIT),
+    },
+    Field {
+        name: "limits",
+        kind: Kind::Table(LIMITS),
+    },
+    Field {
+        name: "slo",
+        kind: Kind::Table(SLO),
+    },
+    Field {
+        name: "priority",
+        kind: Kind::Table(PRIORITY),
+    },
+    Field {
+        name: "adaptive",
+        kind: Kind::Table(ADAPTIVE),
+    },
+    Field {
+        name: "listings",
+        kind: Kind::TableArray(COMPANY),
+    },
+    Field {
+        name: "onboard",
+        kind: Kind::Table(ONBOARD),
+    },
+    Field {
+        name: "rates",
+        kind: Kind::Table(RATES),
+    },
+    Field {
+        name: "econ",
+        kind: Kind::Table(ECON),
+    },
+    Field {
+        name: "movers",
+        kind: Kind::Table(MOVERS),
+    },
+    Field {
+        name: "notifiers",
+        kind: Kind::TableArray(NOTIFIER),
+    },
+    Field {
+        name: "paper",
+        kind: Kind::Table(PAPER),
+    },
+    Field {
+        name: "strategy",
+        kind: Kind::Table(STRATEGY),
+    },
+    Field {
+        name: "events",
+        kind: Kind::Table(EVENTS),
+    },
+    Field {
+        name: "cluster",
+        kind: Kind::Table(CLUSTER),
+    },
+    Field {
+        name: "quality",
+        kind: Kind::Table(QUALITY),
+    },
+    Field {
+        name: "smoothing",
+        kind: Kind::Table(SMOOTHING),
+    },
+    Field {
+        name: "indicators",
+        kind: Kind::Table(INDICATORS),
+    },
+    Field {
+        name: "patterns",
+        kind: Kind::TableArray(PATTERN),
+    },
+    Field {
+        name: "adr",
+        kind: Kind::Table(ADR),
+    },
+    Field {
+        name: "venues",
+        kind: Kind::TableArray(VENUE),
+    },
+    Field {
+        name: "currency",
+        kind: Kind::Table(CURRENCY),
+    },
+    Field {
+        name: "watchdog",
+        kind: Kind::Table(WATCHDOG),
+    },
+    Field {
+        name: "portfolio",
+        kind: Kind::Table(PORTFOLIO),
+    },
+    Field {
+        name: "dca",
+        kind: Kind::TableArray(DCA_PLAN),
+    },
+    Field {
+        name: "daily",
+        kind: Kind::Table(DAILY),
+    },
+    Field {
+        name: "futures",
+        kind: Kind::Table(FUTURES),
+    },
+    Field {
+        name: "options",
+        kind: Kind::Table(OPTIONS),
+    },
+    Field {
+        name: "analysts",
+        kind: Kind::Table(ANALYSTS),
+    },
+    Field {
+        name: "stats",
+        kind: Kind::Table(STATS),
+    },
+    Field {
+        name: "profile",
+        kind: Kind::Table(PROFILE),
+    },
+    Field {
+        name: "edgar",
+        kind: Kind::Table(EDGAR),
+    },
+    Field {
+        name: "social",
+        kind: Kind::Table(SOCIAL),
+    },
+    Field {
+        name: "market",
+        kind: Kind::Table(MARKET),
+    },
+    Field {
+        name: "jitter",
+        kind: Kind::Table(JITTER),
+    },
+    Field {
+        name: "keys",
+        kind: Kind::Table(KEYS),
+    },
+    Field {
+        name: "provider",
+        kind: Kind::Table(PROVIDER),
+    },
+    Field {
+        name: "sheets",
+        kind: Kind::Table(SHEETS),
+    },
+    Field {
+        name: "corporate",
+        kind: Kind::Table(CORPORATE),
+    },
+    Field {
+        name: "eod_summary",
+        kind: Kind::Bool,
+    },
+    Field {
+        name: "ops",
+        kind: Kind::Table(OPS),
+    },
+    Field {
+        name: "history",
+        kind: Kind::Table(HISTORY),
+    },
+    Field {
+        name: "allocation",
+        kind: Kind::Table(ALLOCATION),
+    },
+    Field {
+        name: "tracking",
+        kind: Kind::Table(TRACKING),
+    },
+    Field {
+        name: "returns",
+        kind: Kind::Table(RETURNS),
+    },
+    Field {
+        name: "timeseries",
+        kind: Kind::Table(TIMESERIES),
+    },
+    Field {
+        name: "storage",
+        kind: Kind::Table(STORAGE),
+    },
+    Field {
+        name: "stream",
+        kind: Kind::Table(STREAM),
+    },
+    Field {
+        name: "chaos",
+        kind: Kind::Table(CHAOS),
+    },
+    Field {
+        name: "consensus",
+        kind: Kind::Table(CONSENSUS),
+    },
+    Field {
+        name: "warmup",
+        kind: Kind::Table(WARMUP),
+    },
+    Field {
+        name: "apply",
+        kind: Kind::Table(APPLY),
+    },
+    Field {
+        name: "alerts",
+        kind: Kind::Table(ALERTS),
+    },
+    Field {
+        name: "encryption",
+        kind: Kind::Table(ENCRYPTION),
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub path: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        if self.path.is_empty() {
+            write!(
+                f,
+                "{}:{}: {}: {}",
+                self.line, self.column, severity, self.message
+            )
+        } else {
+            write!(
+                f,
+                "{}:{}: {}: `{}` {}",
+                self.line, self.column, severity, self.path, self.message
+            )
+        }
+    }
+}
+
+pub fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+    (line, column)
+}
+
+struct Validator<'a> {
+    source: &'a str,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validator<'_> {
+    fn push(
+        &mut self,
+        severity: Severity,
+        path: &str,
+        span: Option<Range<usize>>,
+        message: String,
+    ) {
+        let (line, column) = span
+            .map(|s| line_column(self.source, s.start))
+            .unwrap_or((1, 1));
+        self.diagnostics.push(Diagnostic {
+            severity,
+            path: path.to_string(),
+            line,
+            column,
+            message,
+        });
+    }
+
+    fn table(&mut self, table: &dyn TableLike, fields: &[Field], prefix: &str) {
+        for (key, item) in table.iter() {
+            let path = if prefix.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}.{}", prefix, key)
+            };
+            let span = table.key(key).and_then(|k| k.span());
+            match fields.iter().find(|f| f.name == key) {
+                Some(field) => self.item(item, &field.kind, &path, span),
+                None => {
+                    let known: Vec<&str> = fields.iter().map(|f| f.name).collect();
+                    self.push(
+                        Severity::Warning,
+                        &path,
+                        span,
+                        format!("is not a known key, expected one of: {}", known.join(", ")),
+                    );
+                }
+            }
+        }
+    }
+
+    fn item(&mut self, item: &Item, kind: &Kind, path: &str, key_span: Option<Range<usize>>) {
+        let span = item.span().or(key_span);
+        match (kind, item) {
+            (Kind::Table(fields), _) if item.is_table_like() => {
+                self.table(item.as_table_like().unwrap(), fields, path)
+            }
+            (Kind::StringMap, _) if item.is_table_like() => {
+                let table = item.as_table_like().unwrap();
+                for (key, value) in table.iter() {
+                    if !value.is_str() {
+                        let path = format!("{}.{}", path, key);
+                        let span = value
+                            .span()
+                            .or_else(|| table.key(key).and_then(|k| k.span()));
+                        self.mismatch(&path, span, "string", value.type_name());
+                    }
+                }
+            }
+            (Kind::TableArray(fields), Item::ArrayOfTables(array)) => {
+                for (i, table) in array.iter().enumerate() {
+                    self.table(table, fields, &format!("{}[{}]", path, i));
+                }
+            }
+            (Kind::TableArray(fields), Item::Value(Value::Array(array))) => {
+                for (i, value) in array.iter().enumerate() {
+                    let path = format!("{}[{}]", path, i);
+                    match value.as_inline_table() {
+                        Some(table) => self.table(table, fields, &path),
+                        None => self.mismatch(&path, value.span(), "table", value.type_name()),
+                    }
+                }
+            }
+            (_, Item::Value(value)) => self.value(value, kind, path, span),
+            (_, other) => self.mismatch(path, span, expected(kind), other.type_name()),
+        }
+    }
+
+    fn value(&mut self, value: &Value, kind: &Kind, path: &str, span: Option<Range<usize>>) {
+        match (kind, value) {
+            (Kind::String, Value::String(_)) | (Kind::Bool, Value::Boolean(_)) => {}
+            (Kind::StringArray, Value::Array(array)) => {
+                for (i, value) in array.iter().enumerate() {
+                    if !value.is_str() {
+                        let path = format!("{}[{}]", path, i);
+                        self.mismatch(&path, value.span(), "string", value.type_name());
+                    }
+                }
+            }
+            (Kind::IntegerArray { min, max }, Value::Array(array)) => {
+                let kind = Kind::Integer {
+                    min: *min,
+                    max: *max,
+                };
+                for (i, value) in array.iter().enumerate() {
+                    let path = format!("{}[{}]", path, i);
+                    self.value(value, &kind, &path, value.span());
+                }
+            }
+            (Kind::FloatArray { min, max }, Value::Array(array)) => {
+                let kind = Kind::Float {
+                    min: *min,
+                    max: *max,
+                };
+                for (i, value) in array.iter().enumerate() {
+                    let path = format!("{}[{}]", path, i);
+                    self.value(value, &kind, &path, value.span());
+                }
+            }
+            (Kind::Integer { min, max }, Value::Integer(v)) => {
+                let v = *v.value();
+                if v < *min || v > *max {
+                    let range = if *max == i64::MAX {
+                        format!("at least {}", min)
+                    } else {
+                        format!("between {} and {}", min, max)
+                    };
+                    self.push(
+                        Severity::Error,
+                        path,
+                        span,
+                        format!("must be {}, found {}", range, v),
+                    );
+                }
+            }
+            (Kind::Float { min, max }, Value::Float(_) | Value::Integer(_)) => {
+                let v = value
+                    .as_float()
+                    .or_else(|| value.as_integer().map(|i| i as f64))
+                    .unwrap_or_default();
+                if v < *min || v > *max {
+                    self.push(
+                        Severity::Error,
+                        path,
+                        span,
+                        format!("must be between {} and {}, found {}", min, max, v),
+                    );
+                }
+            }
+            (Kind::OneOf(choices), Value::String(v)) => {
+                if !choices.contains(&v.value().as_str()) {
+                    self.push(
+                        Severity::Error,
+                        path,
+                        span,
+                        format!(
+                            "must be one of {}, found \"{}\"",
+                            choices.join(", "),
+                            v.value()
+                        ),
+                    );
+                }
+            }
+            (Kind::DateTime, Value::String(v)) => {
+                if v.value().parse::<chrono::DateTime<chrono::Utc>>().is_err() {
+                    self.push(
+                        Severity::Error,
+                        path,
+                        span,
+                        format!(
+                            "must be an RFC 3339 time like 2026-01-28T19:00:00Z, found \"{}\"",
+                            v.value()
+                        ),
+                    );
+                }
+            }
+            (Kind::Time, Value::String(v)) => {
+                if v.value().parse::<chrono::NaiveTime>().is_err() {
+                    self.push(
+                        Severity::Error,
+                        path,
+                        span,
+                        format!("must be a time like 08:30, found \"{}\"", v.value()),
+                    );
+                }
+            }
+            (Kind::SocketAddr, Value::String(v)) => {
+                if v.value().parse::<SocketAddr>().is_err() {
+                    self.push(
+                        Severity::Error,
+                        path,
+                        span,
+                        format!(
+                            "must be an address like 127.0.0.1:9091, found \"{}\"",
+                            v.value()
+                        ),
+                    );
+                }
+            }
+            _ => self.mismatch(path, span, expected(kind), value.type_name()),
+        }
+    }
+
+    fn mismatch(&mut self, path: &str, span: Option<Range<usize>>, expected: &str, found: &str) {
+        self.push(
+            Severity::Error,
+            path,
+            span,
+            format!("expected {}, found {}", expected, found),
+        );
+    }
+}
+
+fn expected(kind: &Kind) -> &'static str {
+    match kind {
+        Kind::String | Kind::OneOf(_) | Kind::SocketAddr | Kind::DateTime | Kind::Time => "string",
+        Kind::Integer { .. } => "integer",
+        Kind::Float { .. } => "float",
+        Kind::Bool => "boolean",
+        Kind::StringArray => "array of strings",
+        Kind::StringMap => "table of strings",
+        Kind::IntegerArray { .. } => "array of integers",
+        Kind::FloatArray { .. } => "array of floats",
+        Kind::Table(_) => "table",
+        Kind::TableArray(_) => "array of tables",
+    }
+}
+
+pub fn validate(source: &str) -> Vec<Diagnostic> {
+    let document = match ImDocument::parse(source) {
+        Ok(document) => document,
+        Err(e) => {
+            let (line, column) = e
+                .span()
+                .map(|s| line_column(source, s.start))
+                .unwrap_or((1, 1));
+            return vec![Diagnostic {
+                severity: Severity::Error,
+                path: String::new(),
+                line,
+                column,
+                message: e.message().to_string(),
+            }];
+        }
+    };
+    let mut validator = Validator {
+        source,
+        diagnostics: vec![],
+    };
+    validator.table(document.as_table(), CONFIG, "");
+    validator.diagnostics
+}
+
+/// Formats diagnostics compiler-style, quoting the offending line with a caret.
+pub fn render(source: &str, file: &str, diagnostics: &[Diagnostic]) -> String {
+    let mut out = String::new();
+    for d in diagnostics {
+        out.push_str(&format!("{}:{}\n", file, d));
+        if let Some(line) = source.lines().nth(d.line - 1) {
+            out.push_str(&format!("  | {}\n", line));
+            out.push_str(&format!("  | {}^\n", " ".repeat(d.column - 1)));
+        }
+    }
+    out
+}
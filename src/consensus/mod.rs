@@ -0,0 +1,154 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::clock::Clock;
+use crate::engine::Engine;
+use crate::metrics;
+use crate::provider::{Provider, ProviderKind};
+use crate::sink::{PriceUpdate, Sink};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConsensusConfig {
+    /// Providers polled for every watched symbol on top of the main one,
+    /// each with its own key and its own calls.
+    pub sources: Vec<ProviderKind>,
+    pub interval_secs: u64,
+    /// A source's price older than this is left out of the band.
+    pub max_age_secs: u64,
+}
+
+impl Default for ConsensusConfig {
+    fn default() -> Self {
+        ConsensusConfig {
+            sources: vec![],
+            interval_secs: 300,
+            max_age_secs: 900,
+        }
+    }
+}
+
+/// Where the sources put a symbol's price.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Band {
+    pub min: f64,
+    pub median: f64,
+    pub max: f64,
+    pub sources: usize,
+}
+
+impl Band {
+    fn of(mut prices: Vec<f64>) -> Option<Self> {
+        prices.sort_by(f64::total_cmp);
+        let n = prices.len();
+        let median = match n {
+            0 => return None,
+            _ if n % 2 == 1 => prices[n / 2],
+            _ => (prices[n / 2 - 1] + prices[n / 2]) / 2.,
+        };
+        Some(Band {
+            min: prices[0],
+            median,
+            max: prices[n - 1],
+            sources: n,
+        })
+    }
+}
+
+// Latest price from each source and when it came in.
+type Sources = BTreeMap<String, (f64, DateTime<Utc>)>;
+
+/// Latest price of each symbol from each source, exported as min, median
+/// and max once two sources agree to have a fresh price, so dashboards can
+/// draw how far providers disagree.
+pub struct Consensus {
+    primary: String,
+    clock: Arc<dyn Clock>,
+    interval: Duration,
+    max_age: chrono::Duration,
+    quotes: Mutex<HashMap<String, Sources>>,
+}
+
+impl Consensus {
+    /// `primary` names the engine's provider, whose prices arrive as a sink.
+    pub fn new(config: &ConsensusConfig, primary: &str, clock: Arc<dyn Clock>) -> Self {
+        Consensus {
+            primary: primary.to_string(),
+            clock,
+            interval: Duration::from_secs(config.interval_secs.max(1)),
+            max_age: chrono::Duration::seconds(config.max_age_secs as i64),
+            quotes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn observe(&self, symbol: &str, source: &str, price: f64, at: DateTime<Utc>) {
+        let mut quotes = self.quotes.lock().unwrap();
+        let sources = quotes.entry(symbol.to_string()).or_default();
+        sources.insert(source.to_string(), (price, at));
+        let fresh: Vec<f64> = sources
+            .values()
+            .filter(|(_, seen)| at - *seen <= self.max_age)
+            .map(|(price, _)| *price)
+            .collect();
+        match Band::of(fresh) {
+            Some(band) if band.sources > 1 => metrics::update_price_band(symbol, &band),
+            _ => metrics::remove_price_band(symbol),
+        }
+    }
+
+    /// The band for `symbol`, when two or more sources have a fresh price.
+    pub fn band(&self, symbol: &str) -> Option<Band> {
+        let now = self.clock.now();
+        let quotes = self.quotes.lock().unwrap();
+        let fresh = quotes
+            .get(symbol)?
+            .values()
+            .filter(|(_, seen)| now - *seen <= self.max_age)
+            .map(|(price, _)| *price)
+            .collect();
+        Band::of(fresh).filter(|band| band.sources > 1)
+    }
+
+    /// Polls every watched symbol from each of `providers` forever.
+    pub async fn run(self: Arc<Self>, providers: Vec<Arc<dyn Provider>>, engine: Arc<Engine>) {
+        let names: Vec<&str> = providers.iter().map(|p| p.name()).collect();
+        info!(primary = %self.primary, sources = ?names, "Cross-checking prices");
+        loop {
+            let symbols = engine.watched();
+            for provider in &providers {
+                for chunk in symbols.chunks(provider.batch_size().max(1)) {
+                    match provider.fetch_prices(chunk).await {
+                        Ok(quotes) => {
+                            let now = self.clock.now();
+                            for (symbol, quote) in chunk.iter().zip(quotes) {
+                                if let Ok(Some(quote)) = quote {
+                                    self.observe(symbol, provider.name(), quote.price, now);
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            warn!(source = provider.name(), error = %e, "Cross-check fetch failed")
+                        }
+                    }
+                }
+            }
+            self.clock.sleep(self.interval).await;
+        }
+    }
+}
+
+impl Sink for Consensus {
+    fn record(&self, update: &PriceUpdate) {
+        self.observe(
+            &update.symbol,
+            &self.primary,
+            update.price,
+            update.timestamp,
+        );
+    }
+}
@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use reqwest::Error;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, instrument, trace, warn};
+
+use crate::clock::Clock;
+use crate::dividends;
+use crate::metrics;
+use crate::provider::endpoints::{self, Endpoint};
+use crate::provider::{base_url, TWELVEDATA_URL};
+use crate::storage::EarningsSurprise;
+use crate::{read_tickers, AssetClass};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CorporateConfig {
+    /// Costs two requests per stock on every refresh.
+    pub enabled: bool,
+    pub refresh_hours: u64,
+    pub horizon_days: i64,
+}
+
+impl Default for CorporateConfig {
+    fn default() -> Self {
+        CorporateConfig {
+            enabled: false,
+            refresh_hours: 24,
+            horizon_days: 90,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CorporateEventKind {
+    Earnings,
+    ExDividend,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct CorporateEvent {
+    pub symbol: String,
+    pub kind: CorporateEventKind,
+    pub date: NaiveDate,
+    /// Report timing for earnings, the amount for dividends.
+    pub detail: Option<String>,
+    /// Dividend dates extrapolated from the payment history rather than announced.
+    pub projected: bool,
+}
+
+/// Earnings dates, past and upcoming, and the surprises of those reported.
+#[instrument(skip(api_key))]
+pub async fn fetch_earnings(
+    base_url: &str,
+    symbol: &str,
+    api_key: &str,
+) -> Result<(Vec<CorporateEvent>, Vec<EarningsSurprise>), Error> {
+    let url =
+        endpoints::url::<endpoints::Earnings>(base_url, &format!("symbol={}", symbol), api_key);
+    let response = reqwest::get(&url).await?;
+    let data = response.text().await?;
+    let records = match endpoints::parse::<<endpoints::Earnings as Endpoint>::Response>(&data) {
+        Ok(Ok(response)) => response.earnings,
+        Ok(Err(error)) => {
+            warn!(symbol, code = error.code, message = %error.message, "Earnings request failed");
+            vec![]
+        }
+        Err(e) => {
+            warn!(symbol, error = %e, "Unexpected earnings response");
+            vec![]
+        }
+    };
+
+    let surprises: Vec<EarningsSurprise> = records
+        .iter()
+        .filter_map(|e| {
+            Some(EarningsSurprise {
+                symbol: symbol.to_string(),
+                date: e.date,
+                eps_estimate: e.eps_estimate,
+                eps_actual: e.eps_actual?,
+                revenue_estimate: e.revenue_estimate,
+                revenue_actual: e.revenue_actual,
+            })
+        })
+        .collect();
+    let events: Vec<CorporateEvent> = records
+        .into_iter()
+        .map(|e| CorporateEvent {
+            symbol: symbol.to_string(),
+            kind: CorporateEventKind::Earnings,
+            date: e.date,
+            detail: e.time,
+            projected: false,
+        })
+        .collect();
+    trace!(symbol, count = events.len(), "Fetched earnings dates");
+    Ok((events, surprises))
+}
+
+/// Upcoming earnings and ex-dividend dates for the watched stocks, and how
+/// their past reports compared with the estimates.
+pub struct CorporateCalendar {
+    config: CorporateConfig,
+    events: RwLock<Vec<CorporateEvent>>,
+    surprises: RwLock<Vec<EarningsSurprise>>,
+    base_url: String,
+}
+
+impl CorporateCalendar {
+    pub fn new(config: CorporateConfig) -> Self {
+        CorporateCalendar {
+            config,
+            events: RwLock::new(vec![]),
+            surprises: RwLock::new(vec![]),
+            base_url: TWELVEDATA_URL.into(),
+        }
+    }
+
+    pub fn with_base_url(mut self, url: &str) -> Self {
+        self.base_url = base_url(url);
+        self
+    }
+
+    pub fn upcoming(&self, today: NaiveDate) -> Vec<CorporateEvent> {
+        let until = today + chrono::Duration::days(self.config.horizon_days);
+        self.events
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|e| e.date >= today && e.date <= until)
+            .cloned()
+            .collect()
+    }
+
+    /// Reports dated `since` or later, oldest first.
+    pub fn surprises(&self, since: NaiveDate) -> Vec<EarningsSurprise> {
+        self.surprises
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|s| s.date >= since)
+            .cloned()
+            .collect()
+    }
+
+    pub async fn refresh(&self, symbols: &[String], api_key: &str, today: NaiveDate) {
+        let (mut events, mut surprises) = (vec![], vec![]);
+        for symbol in symbols {
+            if AssetClass::of(symbol) != AssetClass::Stock {
+                continue;
+            }
+            match fetch_earnings(&self.base_url, symbol, api_key).await {
+                Ok((earnings, reported)) => {
+                    if let Some(latest) = reported.iter().max_by_key(|s| s.date) {
+                        metrics::update_earnings_surprise(symbol, "eps", latest.eps_percent());
+                        let revenue = latest.revenue_percent();
+                        metrics::update_earnings_surprise(symbol, "revenue", revenue);
+                    }
+                    events.extend(earnings);
+                    surprises.extend(reported);
+                }
+                Err(e) => error!(symbol, error = ?e, "Failed to fetch earnings dates"),
+            }
+            match dividends::fetch_dividends(&self.base_url, symbol, api_key).await {
+                Ok(history) => events.extend(ex_dividend_dates(symbol, history, today)),
+                Err(e) => error!(symbol, error = ?e, "Failed to fetch dividends"),
+            }
+        }
+        events.sort_by(|a, b| (a.date, &a.symbol, a.kind).cmp(&(b.date, &b.symbol, b.kind)));
+        info!(events = events.len(), "Refreshed corporate calendar");
+        *self.events.write().unwrap() = events;
+        surprises.sort_by(|a, b| (a.date, &a.symbol).cmp(&(b.date, &b.symbol)));
+        *self.surprises.write().unwrap() = surprises;
+    }
+
+    pub async fn run(self: Arc<Self>, api_key: String, clock: Arc<dyn Clock>) {
+        loop {
+            match read_tickers().await {
+                Ok(tickers) => {
+                    self.refresh(tickers.get_tickers(), &api_key, clock.now().date_naive())
+                        .await
+                }
+                Err(e) => warn!(error = %e, "Failed to read tickers, skipping calendar refresh"),
+            }
+            clock
+                .sleep(Duration::from_secs(self.config.refresh_hours * 3600))
+                .await;
+        }
+    }
+
+    /// The upcoming events as an iCalendar feed of all-day entries.
+    pub fn to_ics(&self, now: DateTime<Utc>) -> String {
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".into(),
+            "PRODID:-//fintek//corporate calendar//EN".into(),
+            "CALSCALE:GREGORIAN".into(),
+            "X-WR-CALNAME:fintek".into(),
+        ];
+        let stamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+        for event in self.upcoming(now.date_naive()) {
+            let (kind, label) = match event.kind {
+                CorporateEventKind::Earnings => ("earnings", "earnings"),
+                CorporateEventKind::ExDividend => ("exdiv", "ex-dividend"),
+            };
+            let mut summary = format!("{} {}", event.symbol, label);
+            if let Some(detail) = &event.detail {
+                summary.push_str(&format!(" ({})", detail));
+            }
+            if event.projected {
+                summary.push_str(" (projected)");
+            }
+            let end = event.date.succ_opt().unwrap_or(event.date);
+            lines.extend([
+                "BEGIN:VEVENT".to_string(),
+                format!(
+                    "UID:{}-{}-{}@fintek",
+                    escape(&event.symbol),
+                    kind,
+                    event.date.format("%Y%m%d")
+                ),
+                format!("DTSTAMP:{}", stamp),
+                format!("DTSTART;VALUE=DATE:{}", event.date.format("%Y%m%d")),
+                format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")),
+                format!("SUMMARY:{}", escape(&summary)),
+                "TRANSP:TRANSPARENT".into(),
+                "END:VEVENT".into(),
+            ]);
+        }
+        lines.push("END:VCALENDAR".into());
+        lines.iter().map(|l| fold(l) + "\r\n").collect()
+    }
+}
+
+/// Announced future ex-dates plus, when none is announced, the next ones
+/// extrapolated from the payment history.
+fn ex_dividend_dates(
+    symbol: &str,
+    history: Vec<dividends::Dividend>,
+    today: NaiveDate,
+) -> Vec<CorporateEvent> {
+    let event = |d: &dividends::Dividend, projected| CorporateEvent {
+        symbol: symbol.to_string(),
+        kind: CorporateEventKind::ExDividend,
+        date: d.ex_date,
+        detail: Some(format!("{}", d.amount)),
+        projected,
+    };
+    let mut events: Vec<CorporateEvent> = history
+        .iter()
+        .filter(|d| d.ex_date >= today)
+        .map(|d| event(d, false))
+        .collect();
+    if events.is_empty() {
+        let holdings = HashMap::from([(symbol.to_string(), 1.)]);
+        let history = HashMap::from([(symbol.to_string(), history)]);
+        let projection = dividends::project(&holdings, &history, today);
+        events.extend(projection.payments.iter().map(|d| event(d, true)));
+    }
+    events
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// RFC 5545 caps content lines at 75 octets; continuations start with a space.
+fn fold(line: &str) -> String {
+    let mut out = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out
+}
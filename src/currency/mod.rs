@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::metrics;
+use crate::sink::{PriceUpdate, Sink};
+use crate::symbol::SymbolInfo;
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CurrencyConfig {
+    /// Currency to also export prices in, as `stock_price_base`, e.g.
+    /// `USD`. The FX rates it takes are polled like any symbol.
+    pub base: Option<String>,
+}
+
+/// How a price in `currency` turns into `base`: times the rate of `pair`,
+/// if any, times `factor`. `None` for yields and index points.
+fn conversion(currency: &str, base: &str) -> Option<(Option<String>, f64)> {
+    // Pence, as London quotes most shares.
+    let (currency, factor) = match currency {
+        "percent" | "points" => return None,
+        "GBX" => ("GBP", 0.01),
+        other => (other, 1.),
+    };
+    let pair = (currency != base).then(|| format!("{}/{}", currency, base));
+    Some((pair, factor))
+}
+
+/// The FX pairs converting `symbols` to `base`.
+pub fn pairs(base: &str, symbols: &[String]) -> Vec<String> {
+    let mut pairs: Vec<String> = vec![];
+    for symbol in symbols {
+        let currency = SymbolInfo::parse(symbol).currency();
+        if let Some((Some(pair), _)) = conversion(&currency, base) {
+            if !pairs.contains(&pair) && !symbols.contains(&pair) {
+                pairs.push(pair);
+            }
+        }
+    }
+    pairs
+}
+
+#[derive(Debug, Default)]
+struct State {
+    rates: HashMap<String, f64>,
+    // Native price per symbol, for when its rate moves.
+    prices: HashMap<String, f64>,
+}
+
+/// Converts every price to the base currency at the latest rate and
+/// exports it as `stock_price_base`, next to the native `stock_price`.
+pub struct CurrencyConverter {
+    base: String,
+    state: Mutex<State>,
+}
+
+impl CurrencyConverter {
+    /// `None` without a base currency.
+    pub fn new(config: &CurrencyConfig) -> Option<Self> {
+        Some(CurrencyConverter {
+            base: config.base.clone()?,
+            state: Mutex::new(State::default()),
+        })
+    }
+
+    fn export(&self, state: &State, symbol: &str, price: f64) {
+        let info = SymbolInfo::parse(symbol);
+        let Some((pair, factor)) = conversion(&info.currency(), &self.base) else {
+            return;
+        };
+        let rate = match pair {
+            Some(pair) => match state.rates.get(&pair) {
+                Some(rate) => *rate,
+                None => return,
+            },
+            None => 1.,
+        };
+        metrics::update_stock_price_base(
+            price * rate * factor,
+            symbol,
+            info.asset_class.as_str(),
+            &self.base,
+        );
+    }
+}
+
+impl Sink for CurrencyConverter {
+    fn record(&self, update: &PriceUpdate) {
+        let mut state = self.state.lock().unwrap();
+        state.prices.insert(update.symbol.clone(), update.price);
+        let is_rate = update
+            .symbol
+            .strip_suffix(&self.base)
+            .is_some_and(|rest| rest.ends_with('/'));
+        if is_rate {
+            state.rates.insert(update.symbol.clone(), update.price);
+            let quoted: Vec<(String, f64)> = state
+                .prices
+                .iter()
+                .filter(|(symbol, _)| {
+                    let currency = SymbolInfo::parse(symbol).currency();
+                    conversion(&currency, &self.base)
+                        .is_some_and(|(pair, _)| pair.as_ref() == Some(&update.symbol))
+                })
+                .map(|(symbol, price)| (symbol.clone(), *price))
+                .collect();
+            for (symbol, price) in quoted {
+                self.export(&state, &symbol, price);
+            }
+        }
+        self.export(&state, &update.symbol, update.price);
+    }
+}
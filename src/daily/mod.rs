@@ -0,0 +1,299 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use reqwest::Error;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, instrument, warn};
+
+use crate::corporate::CorporateCalendar;
+use crate::engine::Engine;
+use crate::metrics;
+use crate::provider::endpoints::{self, Endpoint};
+use crate::provider::{base_url, TWELVEDATA_URL};
+use crate::storage::{DailyBar, DailyStore, Fundamentals};
+use crate::AssetClass;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DailyConfig {
+    /// End-of-day bars for every ticker, written to the store once a day.
+    /// Needs `storage.path`.
+    pub enabled: bool,
+    /// When the run starts, in `timezone`. After the close, so the bars are
+    /// final.
+    pub run_at: NaiveTime,
+    pub timezone: String,
+    /// Most credits one run spends; whatever doesn't fit waits for the next.
+    pub budget: u64,
+    /// Key statistics of every stock, one more request each.
+    pub fundamentals: bool,
+    /// Refresh the corporate calendar, when enabled, in the daily run rather
+    /// than on its own schedule.
+    pub calendars: bool,
+}
+
+impl Default for DailyConfig {
+    fn default() -> Self {
+        DailyConfig {
+            enabled: false,
+            run_at: NaiveTime::from_hms_opt(17, 30, 0).expect("valid run time"),
+            timezone: "America/New_York".into(),
+            budget: 200,
+            fundamentals: true,
+            calendars: true,
+        }
+    }
+}
+
+#[instrument(skip(api_key))]
+pub async fn fetch_bar(
+    base_url: &str,
+    symbol: &str,
+    today: NaiveDate,
+    api_key: &str,
+) -> Result<Option<DailyBar>, Error> {
+    let url =
+        endpoints::url::<endpoints::DailyQuote>(base_url, &format!("symbol={}", symbol), api_key);
+    let data = reqwest::get(&url).await?.text().await?;
+    let quote = match endpoints::parse::<<endpoints::DailyQuote as Endpoint>::Response>(&data) {
+        Ok(Ok(quote)) => quote,
+        Ok(Err(error)) => {
+            warn!(symbol, code = error.code, message = %error.message, "Quote request failed");
+            return Ok(None);
+        }
+        Err(e) => {
+            warn!(symbol, error = %e, "Unexpected quote response");
+            return Ok(None);
+        }
+    };
+    let date = quote
+        .datetime
+        .as_deref()
+        .and_then(|d| NaiveDate::parse_from_str(d.get(..10)?, "%Y-%m-%d").ok())
+        .unwrap_or(today);
+    Ok(Some(DailyBar {
+        symbol: symbol.to_string(),
+        date,
+        open: quote.open,
+        high: quote.high,
+        low: quote.low,
+        close: quote.close,
+        volume: quote.volume,
+        source: "twelvedata".into(),
+    }))
+}
+
+#[instrument(skip(api_key))]
+pub async fn fetch_fundamentals(
+    base_url: &str,
+    symbol: &str,
+    today: NaiveDate,
+    api_key: &str,
+) -> Result<Option<Fundamentals>, Error> {
+    let url = endpoints::url::<endpoints::KeyStatistics>(
+        base_url,
+        &format!("symbol={}", symbol),
+        api_key,
+    );
+    let data = reqwest::get(&url).await?.text().await?;
+    let statistics = match endpoints::parse::<<endpoints::KeyStatistics as Endpoint>::Response>(
+        &data,
+    ) {
+        Ok(Ok(response)) => response.statistics,
+        Ok(Err(error)) => {
+            warn!(symbol, code = error.code, message = %error.message, "Statistics request failed");
+            return Ok(None);
+        }
+        Err(e) => {
+            warn!(symbol, error = %e, "Unexpected statistics response");
+            return Ok(None);
+        }
+    };
+    Ok(Some(Fundamentals {
+        symbol: symbol.to_string(),
+        date: today,
+        market_cap: statistics.valuations_metrics.market_capitalization,
+        trailing_pe: statistics.valuations_metrics.trailing_pe,
+        eps: statistics.financials.income_statement.diluted_eps_ttm,
+        dividend_yield: statistics
+            .dividends_and_splits
+            .forward_annual_dividend_yield,
+        beta: statistics.stock_price_summary.beta,
+    }))
+}
+
+// Credits left in one run.
+struct Budget {
+    left: u64,
+}
+
+impl Budget {
+    fn spend(&mut self, cost: u64) -> bool {
+        if cost > self.left {
+            return false;
+        }
+        self.left -= cost;
+        metrics::update_daily_budget_remaining(self.left);
+        true
+    }
+}
+
+/// Low-frequency jobs run once a day after the close, apart from intraday
+/// polling: end-of-day bars, fundamentals, the earnings surprises the
+/// corporate calendar has seen and optionally refreshing the calendar. They draw on their own per-run budget, on top of going through
+/// the shared rate limiter, and write to their own tables.
+pub struct DailyPipeline {
+    config: DailyConfig,
+    tz: Tz,
+    base_url: String,
+    store: Arc<dyn DailyStore>,
+    corporate: Option<Arc<CorporateCalendar>>,
+}
+
+impl DailyPipeline {
+    /// An unknown timezone falls back to UTC.
+    pub fn new(config: DailyConfig, store: Arc<dyn DailyStore>) -> Self {
+        let tz = config.timezone.parse::<Tz>().unwrap_or_else(|e| {
+            warn!(timezone = %config.timezone, error = %e, "Unknown daily timezone, using UTC");
+            Tz::UTC
+        });
+        DailyPipeline {
+            config,
+            tz,
+            base_url: TWELVEDATA_URL.into(),
+            store,
+            corporate: None,
+        }
+    }
+
+    pub fn with_base_url(mut self, url: &str) -> Self {
+        self.base_url = base_url(url);
+        self
+    }
+
+    /// Refreshed in the run when `calendars` is set.
+    pub fn with_calendar(mut self, corporate: Arc<CorporateCalendar>) -> Self {
+        self.corporate = Some(corporate);
+        self
+    }
+
+    pub fn next_run(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let local = now.with_timezone(&self.tz);
+        for days in 0..3 {
+            let date = local.date_naive() + Duration::days(days);
+            if let Some(at) = self
+                .tz
+                .from_local_datetime(&date.and_time(self.config.run_at))
+                .earliest()
+            {
+                let at = at.with_timezone(&Utc);
+                if at > now {
+                    return at;
+                }
+            }
+        }
+        now + Duration::days(1)
+    }
+
+    // Spends from the run's budget, then waits for the shared limiter.
+    async fn spend(&self, engine: &Engine, budget: &mut Budget, cost: u64) -> bool {
+        if !budget.spend(cost) {
+            return false;
+        }
+        engine.limiter().acquire(cost).await;
+        true
+    }
+
+    /// One run over `symbols`. Stocks and other weekday listings are
+    /// skipped on weekends, when they have no new bar.
+    pub async fn run_once(&self, engine: &Engine, symbols: &[String], api_key: &str) {
+        let now = engine.clock().now();
+        let today = now.with_timezone(&self.tz).date_naive();
+        let weekend = matches!(today.weekday(), Weekday::Sat | Weekday::Sun);
+        let mut budget = Budget {
+            left: self.config.budget,
+        };
+        metrics::update_daily_budget_remaining(budget.left);
+        let (mut bars, mut fundamentals, mut skipped) = (vec![], vec![], 0);
+        for symbol in symbols {
+            let class = AssetClass::of(symbol);
+            if weekend && class != AssetClass::Crypto {
+                continue;
+            }
+            if !self.spend(engine, &mut budget, 1).await {
+                skipped += 1;
+                continue;
+            }
+            match fetch_bar(&self.base_url, symbol, today, api_key).await {
+                Ok(bar) => bars.extend(bar),
+                Err(e) => error!(symbol, error = ?e, "Failed to fetch daily bar"),
+            }
+            if !self.config.fundamentals || class != AssetClass::Stock {
+                continue;
+            }
+            if !self.spend(engine, &mut budget, 1).await {
+                skipped += 1;
+                continue;
+            }
+            match fetch_fundamentals(&self.base_url, symbol, today, api_key).await {
+                Ok(f) => fundamentals.extend(f),
+                Err(e) => error!(symbol, error = ?e, "Failed to fetch fundamentals"),
+            }
+        }
+        let refresh = self
+            .corporate
+            .as_ref()
+            .filter(|_| self.config.calendars && !weekend);
+        if let Some(corporate) = refresh {
+            let stocks = symbols
+                .iter()
+                .filter(|s| AssetClass::of(s) == AssetClass::Stock)
+                .count() as u64;
+            if self.spend(engine, &mut budget, 2 * stocks).await {
+                corporate.refresh(symbols, api_key, today).await;
+            } else {
+                skipped += 1;
+            }
+        }
+        if let Err(e) = self.store.upsert_bars(&bars).await {
+            error!(bars = bars.len(), error = %e, "Failed to store daily bars");
+        }
+        if let Err(e) = self.store.upsert_fundamentals(&fundamentals).await {
+            error!(symbols = fundamentals.len(), error = %e, "Failed to store fundamentals");
+        }
+        let surprises = self
+            .corporate
+            .as_ref()
+            .map(|corporate| corporate.surprises(NaiveDate::MIN))
+            .unwrap_or_default();
+        if let Err(e) = self.store.upsert_surprises(&surprises).await {
+            error!(reports = surprises.len(), error = %e, "Failed to store earnings surprises");
+        }
+        if skipped > 0 {
+            warn!(
+                skipped,
+                budget = self.config.budget,
+                "Daily budget spent, the rest waits for the next run"
+            );
+        }
+        info!(
+            bars = bars.len(),
+            fundamentals = fundamentals.len(),
+            surprises = surprises.len(),
+            spent = self.config.budget - budget.left,
+            "Finished daily run"
+        );
+        metrics::update_daily_run(engine.clock().now());
+    }
+
+    pub async fn run(self: Arc<Self>, engine: Arc<Engine>, api_key: String) {
+        loop {
+            let now = engine.clock().now();
+            let wait = (self.next_run(now) - now).to_std().unwrap_or_default();
+            engine.clock().sleep(wait).await;
+            let symbols = engine.tickers().lock().await.get_tickers().clone();
+            self.run_once(&engine, &symbols, &api_key).await;
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Months, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument};
+
+use crate::PricePoint;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Schedule {
+    Daily,
+    Weekly,
+    Biweekly,
+    Monthly,
+}
+
+impl Schedule {
+    // The `n`th date after `start`, counted from it so monthly plans keep
+    // their day: Jan 31, Feb 28, Mar 31.
+    fn nth(&self, start: DateTime<Utc>, n: u32) -> DateTime<Utc> {
+        match self {
+            Schedule::Daily => start + Duration::days(n.into()),
+            Schedule::Weekly => start + Duration::weeks(n.into()),
+            Schedule::Biweekly => start + Duration::weeks(2 * i64::from(n)),
+            Schedule::Monthly => start
+                .checked_add_months(Months::new(n))
+                .unwrap_or(DateTime::<Utc>::MAX_UTC),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DcaPlan {
+    pub symbol: String,
+    pub amount: f64,
+    pub schedule: Schedule,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DcaReport {
+    pub symbol: String,
+    pub schedule: Schedule,
+    pub purchases: usize,
+    pub invested: f64,
+    pub shares: f64,
+    pub average_cost: f64,
+    pub final_price: f64,
+    pub final_value: f64,
+    pub total_return: f64,
+}
+
+/// Buys `plan.amount` worth of shares at the first price on or after each
+/// scheduled date. `history` must be sorted by timestamp.
+#[instrument(skip(history))]
+pub fn simulate(plan: &DcaPlan, history: &[PricePoint]) -> Option<DcaReport> {
+    let first = history.first()?;
+    let last = history.last()?;
+
+    let mut next_buy = first.timestamp;
+    let mut scheduled = 0;
+    let mut purchases = 0;
+    let mut invested = 0.;
+    let mut shares = 0.;
+
+    for point in history {
+        if point.timestamp < next_buy || point.price <= 0. {
+            continue;
+        }
+        shares += plan.amount / point.price;
+        invested += plan.amount;
+        purchases += 1;
+        while next_buy <= point.timestamp {
+            scheduled += 1;
+            next_buy = plan.schedule.nth(first.timestamp, scheduled);
+        }
+    }
+
+    if purchases == 0 {
+        return None;
+    }
+
+    let final_value = shares * last.price;
+    let report = DcaReport {
+        symbol: plan.symbol.clone(),
+        schedule: plan.schedule,
+        purchases,
+        invested,
+        shares,
+        average_cost: invested / shares,
+        final_price: last.price,
+        final_value,
+        total_return: (final_value - invested) / invested,
+    };
+    info!(
+        symbol = %report.symbol,
+        purchases,
+        invested,
+        final_value,
+        total_return = report.total_return,
+        "DCA simulation finished"
+    );
+    Some(report)
+}
+
+/// Runs every plan over the history of its own symbol, best total return
+/// first. Plans without history are left out.
+pub fn compare(plans: &[DcaPlan], histories: &HashMap<String, Vec<PricePoint>>) -> Vec<DcaReport> {
+    let mut reports: Vec<DcaReport> = plans
+        .iter()
+        .filter_map(|plan| simulate(plan, histories.get(&plan.symbol)?))
+        .collect();
+    reports.sort_by(|a, b| b.total_return.total_cmp(&a.total_return));
+    reports
+}
@@ -0,0 +1,156 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{Datelike, Duration, NaiveDate};
+use reqwest::Error;
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument, trace, warn};
+
+use crate::metrics;
+use crate::provider::endpoints::{self, Endpoint};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Dividend {
+    pub symbol: String,
+    pub ex_date: NaiveDate,
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DividendProjection {
+    // Keyed by "YYYY-MM"
+    pub monthly: BTreeMap<String, f64>,
+    pub total: f64,
+    pub payments: Vec<Dividend>,
+}
+
+#[instrument(skip(api_key))]
+pub async fn fetch_dividends(
+    base_url: &str,
+    symbol: &str,
+    api_key: &str,
+) -> Result<Vec<Dividend>, Error> {
+    let url = endpoints::url::<endpoints::Dividends>(
+        base_url.trim_end_matches('/'),
+        &format!("symbol={}", symbol),
+        api_key,
+    );
+    let response = reqwest::get(&url).await?;
+    let data = response.text().await?;
+    let records = match endpoints::parse::<<endpoints::Dividends as Endpoint>::Response>(&data) {
+        Ok(Ok(response)) => response.dividends,
+        Ok(Err(error)) => {
+            warn!(symbol, code = error.code, message = %error.message, "Dividends request failed");
+            vec![]
+        }
+        Err(e) => {
+            warn!(symbol, error = %e, "Unexpected dividends response");
+            vec![]
+        }
+    };
+
+    let mut dividends: Vec<Dividend> = records
+        .into_iter()
+        .map(|d| Dividend {
+            symbol: symbol.to_string(),
+            ex_date: d.ex_date,
+            amount: d.amount,
+        })
+        .collect();
+    dividends.sort_by_key(|d| d.ex_date);
+    trace!(symbol, count = dividends.len(), "Fetched dividends");
+    Ok(dividends)
+}
+
+// Median spacing between past ex-dates, so one special dividend doesn't skew the cadence.
+fn payment_interval(history: &[Dividend]) -> Option<Duration> {
+    let mut gaps: Vec<i64> = history
+        .windows(2)
+        .map(|w| (w[1].ex_date - w[0].ex_date).num_days())
+        .filter(|days| *days > 0)
+        .collect();
+    if gaps.is_empty() {
+        return None;
+    }
+    gaps.sort_unstable();
+    Some(Duration::days(gaps[gaps.len() / 2]))
+}
+
+/// Extrapolates the last paid amount forward at the historical cadence for the
+/// twelve months starting at `today`, weighted by the held quantity.
+pub fn project(
+    holdings: &HashMap<String, f64>,
+    history: &HashMap<String, Vec<Dividend>>,
+    today: NaiveDate,
+) -> DividendProjection {
+    let horizon = today
+        .checked_add_months(chrono::Months::new(12))
+        .unwrap_or(today + Duration::days(365));
+    let mut projection = DividendProjection::default();
+
+    let mut month = today.with_day(1).unwrap_or(today);
+    while month < horizon {
+        projection
+            .monthly
+            .insert(format!("{:04}-{:02}", month.year(), month.month()), 0.);
+        month = month
+            .checked_add_months(chrono::Months::new(1))
+            .unwrap_or(horizon);
+    }
+
+    for (symbol, quantity) in holdings {
+        let Some(past) = history.get(symbol) else {
+            continue;
+        };
+        let (Some(last), Some(interval)) = (past.last(), payment_interval(past)) else {
+            continue;
+        };
+        let mut ex_date = last.ex_date + interval;
+        while ex_date < horizon {
+            if ex_date >= today {
+                let income = last.amount * quantity;
+                let key = format!("{:04}-{:02}", ex_date.year(), ex_date.month());
+                *projection.monthly.entry(key).or_default() += income;
+                projection.total += income;
+                projection.payments.push(Dividend {
+                    symbol: symbol.clone(),
+                    ex_date,
+                    amount: last.amount,
+                });
+            }
+            ex_date += interval;
+        }
+    }
+    projection.payments.sort_by_key(|d| d.ex_date);
+    projection
+}
+
+pub fn export(projection: &DividendProjection) {
+    for (month, income) in &projection.monthly {
+        metrics::update_dividend_income(*income, month);
+    }
+    metrics::update_dividend_income_total(projection.total);
+}
+
+/// Logs and returns the projected ex-dates falling within `days` of `today`.
+pub fn upcoming_ex_dates(
+    projection: &DividendProjection,
+    today: NaiveDate,
+    days: i64,
+) -> Vec<Dividend> {
+    let until = today + Duration::days(days);
+    let upcoming: Vec<Dividend> = projection
+        .payments
+        .iter()
+        .filter(|d| d.ex_date >= today && d.ex_date <= until)
+        .cloned()
+        .collect();
+    for dividend in &upcoming {
+        info!(
+            symbol = %dividend.symbol,
+            ex_date = %dividend.ex_date,
+            amount = dividend.amount,
+            "Upcoming ex-dividend date"
+        );
+    }
+    upcoming
+}
@@ -0,0 +1,178 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use reqwest::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{info, instrument};
+
+use crate::metrics;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct EconEvent {
+    pub name: String,
+    pub time: DateTime<Utc>,
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub impact: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventAction {
+    Pause,
+    Boost,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EconConfig {
+    /// Events known ahead of time, merged with anything fetched.
+    pub events: Vec<EconEvent>,
+    /// Substrings of fetched event names worth tracking.
+    pub watch: Vec<String>,
+    pub action: Option<EventAction>,
+    pub before_mins: i64,
+    pub after_mins: i64,
+    pub boost_factor: f64,
+}
+
+impl Default for EconConfig {
+    fn default() -> Self {
+        EconConfig {
+            events: vec![],
+            watch: vec![
+                "CPI".into(),
+                "Interest Rate Decision".into(),
+                "Non Farm Payrolls".into(),
+            ],
+            action: None,
+            before_mins: 15,
+            after_mins: 15,
+            boost_factor: 4.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PollMode {
+    Normal,
+    Paused { until: DateTime<Utc> },
+    Boosted(f64),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpcomingEvent {
+    #[serde(flatten)]
+    pub event: EconEvent,
+    pub minutes_until: i64,
+}
+
+pub struct EconCalendar {
+    config: EconConfig,
+    fetched: RwLock<Vec<EconEvent>>,
+}
+
+impl EconCalendar {
+    pub fn new(config: EconConfig) -> Self {
+        EconCalendar {
+            config,
+            fetched: RwLock::new(vec![]),
+        }
+    }
+
+    fn events(&self) -> Vec<EconEvent> {
+        let mut events = self.config.events.clone();
+        events.extend(self.fetched.read().unwrap().iter().cloned());
+        events.sort_by_key(|e| e.time);
+        events.dedup_by(|a, b| a.name == b.name && a.time == b.time);
+        events
+    }
+
+    pub fn upcoming(&self, now: DateTime<Utc>) -> Vec<UpcomingEvent> {
+        let after = Duration::minutes(self.config.after_mins);
+        self.events()
+            .into_iter()
+            .filter(|e| e.time + after >= now)
+            .map(|event| UpcomingEvent {
+                minutes_until: (event.time - now).num_minutes(),
+                event,
+            })
+            .collect()
+    }
+
+    /// Minutes until the next occurrence of each event name.
+    pub fn export(&self, now: DateTime<Utc>) {
+        let mut seen = vec![];
+        for upcoming in self.upcoming(now) {
+            if seen.contains(&upcoming.event.name) {
+                continue;
+            }
+            metrics::update_econ_event_minutes(&upcoming.event.name, upcoming.minutes_until as f64);
+            seen.push(upcoming.event.name);
+        }
+    }
+
+    /// What the scheduler should do right now, based on events within the
+    /// configured window around `now`.
+    pub fn mode(&self, now: DateTime<Utc>) -> PollMode {
+        let Some(action) = self.config.action else {
+            return PollMode::Normal;
+        };
+        let before = Duration::minutes(self.config.before_mins);
+        let after = Duration::minutes(self.config.after_mins);
+        let active = self
+            .events()
+            .into_iter()
+            .filter(|e| now >= e.time - before && now < e.time + after)
+            .max_by_key(|e| e.time);
+        match (active, action) {
+            (None, _) => PollMode::Normal,
+            (Some(event), EventAction::Pause) => PollMode::Paused {
+                until: event.time + after,
+            },
+            (Some(_), EventAction::Boost) => PollMode::Boosted(self.config.boost_factor),
+        }
+    }
+
+    #[instrument(skip(self, token))]
+    pub async fn refresh_finnhub(&self, base_url: &str, token: &str) -> Result<usize, Error> {
+        let url = format!(
+            "{}/calendar/economic?token={}",
+            base_url.trim_end_matches('/'),
+            token
+        );
+        let response = reqwest::get(&url).await?;
+        let data = response.text().await?;
+        let v: Value = serde_json::from_str(&data).unwrap_or(Value::Null);
+
+        let mut events = vec![];
+        if let Some(array) = v["economicCalendar"].as_array() {
+            for object in array {
+                let Some(name) = object["event"].as_str() else {
+                    continue;
+                };
+                if !self.config.watch.iter().any(|w| name.contains(w.as_str())) {
+                    continue;
+                }
+                let time = object["time"]
+                    .as_str()
+                    .and_then(|t| NaiveDateTime::parse_from_str(t, "%Y-%m-%d %H:%M:%S").ok())
+                    .map(|t| t.and_utc());
+                if let Some(time) = time {
+                    events.push(EconEvent {
+                        name: name.to_string(),
+                        time,
+                        country: object["country"].as_str().map(String::from),
+                        impact: object["impact"].as_str().map(String::from),
+                    });
+                }
+            }
+        }
+        let count = events.len();
+        *self.fetched.write().unwrap() = events;
+        info!(count, "Refreshed economic calendar");
+        Ok(count)
+    }
+}
@@ -0,0 +1,297 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{error, info, instrument, warn};
+
+use crate::clock::Clock;
+use crate::metrics;
+use crate::notify::{Notification, NotificationKind, Notifiers, Urgency};
+use crate::{read_tickers, AssetClass};
+
+pub const EDGAR_FILE: &str = "edgar.json";
+pub const SEC_URL: &str = "https://www.sec.gov";
+pub const SEC_DATA_URL: &str = "https://data.sec.gov";
+// How many new filings are kept for the API.
+const RECENT: usize = 100;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EdgarConfig {
+    pub enabled: bool,
+    /// Who is asking, as the SEC requires of automated access, e.g.
+    /// `fintek admin@example.com`. Nothing is polled without it.
+    pub user_agent: String,
+    /// Form types to notify about, matched exactly, so amendments such as
+    /// `8-K/A` need listing on their own.
+    pub forms: Vec<String>,
+    /// One request per watched stock each time.
+    pub poll_secs: u64,
+    pub url: String,
+    pub data_url: String,
+}
+
+impl Default for EdgarConfig {
+    fn default() -> Self {
+        EdgarConfig {
+            enabled: false,
+            user_agent: String::new(),
+            forms: vec!["8-K".into(), "10-Q".into(), "10-K".into()],
+            poll_secs: 600,
+            url: SEC_URL.into(),
+            data_url: SEC_DATA_URL.into(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum EdgarError {
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error("unexpected EDGAR response: {0}")]
+    Response(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Deserialize)]
+struct CompanyTicker {
+    cik_str: u64,
+    ticker: String,
+}
+
+// Parallel arrays, newest filing first.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecentFilings {
+    accession_number: Vec<String>,
+    filing_date: Vec<NaiveDate>,
+    form: Vec<String>,
+    primary_document: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Filings {
+    #[serde(default)]
+    recent: RecentFilings,
+}
+
+#[derive(Debug, Deserialize)]
+struct Submissions {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    filings: Filings,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Filing {
+    pub symbol: String,
+    pub company: String,
+    pub form: String,
+    pub filed: NaiveDate,
+    pub accession: String,
+    pub url: String,
+}
+
+/// Polls SEC EDGAR for new filings of the configured form types by the
+/// watched stocks and sends each one's link through the notifiers. Filings
+/// already seen are saved, so a restart doesn't announce them again; the
+/// first poll of a company only records what it has filed so far.
+pub struct EdgarWatcher {
+    config: EdgarConfig,
+    client: reqwest::Client,
+    notifiers: Notifiers,
+    path: PathBuf,
+    ciks: Mutex<HashMap<String, u64>>,
+    // Accession numbers per symbol.
+    seen: Mutex<BTreeMap<String, BTreeSet<String>>>,
+    recent: Mutex<VecDeque<Filing>>,
+}
+
+impl EdgarWatcher {
+    /// `None` without a user agent, which the SEC turns requests away for.
+    pub fn new(config: &EdgarConfig, state_dir: &Path, notifiers: Notifiers) -> Option<Self> {
+        if config.user_agent.trim().is_empty() {
+            return None;
+        }
+        let client = reqwest::Client::builder()
+            .user_agent(config.user_agent.clone())
+            .build()
+            .ok()?;
+        let path = state_dir.join(EDGAR_FILE);
+        let seen = match std::fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+                warn!(path = %path.display(), error = %e, "Ignoring corrupt EDGAR state");
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Some(EdgarWatcher {
+            config: config.clone(),
+            client,
+            notifiers,
+            path,
+            ciks: Mutex::new(HashMap::new()),
+            seen: Mutex::new(seen),
+            recent: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    /// The filings announced since the start, newest first.
+    pub fn recent(&self) -> Vec<Filing> {
+        self.recent.lock().unwrap().iter().cloned().collect()
+    }
+
+    async fn get<T: DeserializeOwned>(&self, url: &str) -> Result<T, EdgarError> {
+        let response = self.client.get(url).send().await?.error_for_status()?;
+        Ok(serde_json::from_str(&response.text().await?)?)
+    }
+
+    #[instrument(skip(self))]
+    async fn load_ciks(&self) -> Result<(), EdgarError> {
+        let url = format!(
+            "{}/files/company_tickers.json",
+            self.config.url.trim_end_matches('/')
+        );
+        let companies: HashMap<String, CompanyTicker> = self.get(&url).await?;
+        let ciks: HashMap<String, u64> = companies
+            .into_values()
+            .map(|c| (c.ticker.to_uppercase(), c.cik_str))
+            .collect();
+        info!(companies = ciks.len(), "Loaded EDGAR company index");
+        *self.ciks.lock().unwrap() = ciks;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn fetch_filings(&self, symbol: &str, cik: u64) -> Result<Vec<Filing>, EdgarError> {
+        let url = format!(
+            "{}/submissions/CIK{:010}.json",
+            self.config.data_url.trim_end_matches('/'),
+            cik
+        );
+        let submissions: Submissions = self.get(&url).await?;
+        let recent = submissions.filings.recent;
+        let filings = recent
+            .accession_number
+            .iter()
+            .zip(&recent.filing_date)
+            .zip(&recent.form)
+            .zip(&recent.primary_document)
+            .map(|(((accession, filed), form), document)| Filing {
+                symbol: symbol.to_string(),
+                company: submissions.name.clone(),
+                form: form.clone(),
+                filed: *filed,
+                accession: accession.clone(),
+                url: format!(
+                    "{}/Archives/edgar/data/{}/{}/{}",
+                    self.config.url.trim_end_matches('/'),
+                    cik,
+                    accession.replace('-', ""),
+                    document
+                ),
+            })
+            .collect();
+        Ok(filings)
+    }
+
+    /// New filings of `symbol` of the configured forms, oldest first, now
+    /// marked as seen.
+    pub async fn poll_symbol(&self, symbol: &str) -> Vec<Filing> {
+        let Some(cik) = self
+            .ciks
+            .lock()
+            .unwrap()
+            .get(&symbol.to_uppercase())
+            .copied()
+        else {
+            return vec![];
+        };
+        let filings = match self.fetch_filings(symbol, cik).await {
+            Ok(filings) => filings,
+            Err(e) => {
+                warn!(symbol, error = %e, "Failed to fetch EDGAR filings");
+                return vec![];
+            }
+        };
+        let mut seen = self.seen.lock().unwrap();
+        let baseline = !seen.contains_key(symbol);
+        let known = seen.entry(symbol.to_string()).or_default();
+        let mut new: Vec<Filing> = filings
+            .into_iter()
+            .filter(|f| known.insert(f.accession.clone()))
+            .filter(|f| !baseline && self.config.forms.contains(&f.form))
+            .collect();
+        new.reverse();
+        new
+    }
+
+    fn notification(filing: &Filing) -> Notification {
+        Notification {
+            title: format!("{} filed {}", filing.symbol, filing.form),
+            body: format!(
+                "{} filed {} on {}: {}",
+                filing.company, filing.form, filing.filed, filing.url
+            ),
+            urgency: Urgency::Normal,
+            kind: NotificationKind::Alert,
+            symbols: vec![filing.symbol.clone()],
+            rule: Some(format!("edgar_{}", filing.form.to_lowercase())),
+            value: None,
+            labels: BTreeMap::new(),
+        }
+    }
+
+    /// Polls every stock among `symbols` once and saves what was seen.
+    pub async fn poll(&self, symbols: &[String]) {
+        if self.ciks.lock().unwrap().is_empty() {
+            if let Err(e) = self.load_ciks().await {
+                error!(error = %e, "Failed to load EDGAR company index");
+                return;
+            }
+        }
+        let mut found = 0;
+        for symbol in symbols
+            .iter()
+            .filter(|s| AssetClass::of(s) == AssetClass::Stock)
+        {
+            for filing in self.poll_symbol(symbol).await {
+                info!(
+                    symbol,
+                    form = filing.form,
+                    url = filing.url,
+                    "New EDGAR filing"
+                );
+                metrics::update_edgar_filing(symbol, &filing.form);
+                self.notifiers.notify(&Self::notification(&filing)).await;
+                let mut recent = self.recent.lock().unwrap();
+                recent.push_front(filing);
+                recent.truncate(RECENT);
+                found += 1;
+            }
+        }
+        let data =
+            serde_json::to_vec_pretty(&*self.seen.lock().unwrap()).expect("filings serialize");
+        if let Err(e) = std::fs::write(&self.path, data) {
+            error!(path = %self.path.display(), error = %e, "Failed to save EDGAR state");
+        }
+        info!(filings = found, "Polled EDGAR");
+    }
+
+    pub async fn run(self: Arc<Self>, clock: Arc<dyn Clock>) {
+        loop {
+            match read_tickers().await {
+                Ok(tickers) => self.poll(tickers.get_tickers()).await,
+                Err(e) => warn!(error = %e, "Failed to read tickers, skipping EDGAR poll"),
+            }
+            clock
+                .sleep(Duration::from_secs(self.config.poll_secs.max(60)))
+                .await;
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::info;
+
+const KEY_ENV: &str = "FINTEK_STORAGE_KEY";
+const KEY_LEN: usize = 32;
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EncryptionConfig {
+    /// File holding the key, for secrets mounted by an orchestrator. Used
+    /// when `FINTEK_STORAGE_KEY` isn't set.
+    pub key_file: Option<PathBuf>,
+    /// The key itself, used when neither of the above is set. Prefer them;
+    /// a key here lives in plain text with the config.
+    #[serde(skip_serializing)]
+    pub key: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum EncryptionError {
+    #[error("storage key must be 32 bytes as hex or base64, e.g. from `openssl rand -hex 32`")]
+    InvalidKey,
+    #[error("failed to read the storage key file: {0}")]
+    KeyFile(#[from] std::io::Error),
+    #[error("failed to decrypt: wrong key or corrupted data")]
+    Decrypt,
+}
+
+/// AES-256 key for the price store and the event log. With one configured
+/// neither is readable without it.
+#[derive(Clone)]
+pub struct StorageKey([u8; KEY_LEN]);
+
+impl fmt::Debug for StorageKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("StorageKey(..)")
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl StorageKey {
+    pub fn parse(s: &str) -> Result<Self, EncryptionError> {
+        let s = s.trim();
+        let bytes = if s.len() == KEY_LEN * 2 && s.bytes().all(|b| b.is_ascii_hexdigit()) {
+            (0..s.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&s[i..i + 2], 16))
+                .collect::<Result<Vec<u8>, _>>()
+                .map_err(|_| EncryptionError::InvalidKey)?
+        } else {
+            STANDARD
+                .decode(s)
+                .map_err(|_| EncryptionError::InvalidKey)?
+        };
+        let key = bytes.try_into().map_err(|_| EncryptionError::InvalidKey)?;
+        Ok(StorageKey(key))
+    }
+
+    /// The key from `FINTEK_STORAGE_KEY`, else the config's key file, else
+    /// the config; `None` when there is none and storage stays plain.
+    pub fn load(config: &EncryptionConfig) -> Result<Option<Self>, EncryptionError> {
+        if let Some(key) = std::env::var(KEY_ENV).ok().filter(|k| !k.trim().is_empty()) {
+            return Self::parse(&key).map(Some);
+        }
+        if let Some(path) = &config.key_file {
+            info!(path = %path.display(), "Using the storage key file");
+            return Self::parse(&std::fs::read_to_string(path)?).map(Some);
+        }
+        config.key.as_deref().map(Self::parse).transpose()
+    }
+
+    /// The key as SQLCipher's `PRAGMA key` takes a raw key.
+    pub fn sqlcipher(&self) -> String {
+        format!("\"x'{}'\"", hex(&self.0))
+    }
+
+    fn cipher(&self) -> LessSafeKey {
+        LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &self.0).expect("key is 32 bytes"))
+    }
+
+    /// `plaintext` encrypted under a fresh nonce, as base64 of the nonce
+    /// followed by the ciphertext and tag.
+    pub fn seal(&self, plaintext: &[u8]) -> String {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .expect("system randomness");
+        let mut data = plaintext.to_vec();
+        self.cipher()
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+            .expect("plaintext fits AES-GCM");
+        let mut sealed = nonce.to_vec();
+        sealed.append(&mut data);
+        STANDARD.encode(sealed)
+    }
+
+    pub fn open(&self, sealed: &str) -> Result<Vec<u8>, EncryptionError> {
+        let data = STANDARD
+            .decode(sealed.trim())
+            .map_err(|_| EncryptionError::Decrypt)?;
+        if data.len() < NONCE_LEN {
+            return Err(EncryptionError::Decrypt);
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let nonce =
+            Nonce::try_assume_unique_for_key(nonce).map_err(|_| EncryptionError::Decrypt)?;
+        let mut ciphertext = ciphertext.to_vec();
+        let plaintext = self
+            .cipher()
+            .open_in_place(nonce, Aad::empty(), &mut ciphertext)
+            .map_err(|_| EncryptionError::Decrypt)?;
+        Ok(plaintext.to_vec())
+    }
+}
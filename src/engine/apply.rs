@@ -0,0 +1,244 @@
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::clock::Clock;
+use crate::events::{Event, EventBus};
+use crate::limits::{LimitError, Limits};
+use crate::metrics;
+use crate::sink::{FetchOutcome, PriceUpdate, Sink};
+use crate::Tickers;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ApplyConfig {
+    /// How long the engine is watched after a watchlist change, from a
+    /// reload of the file or the API, before the change is kept. 0 keeps
+    /// every change at once.
+    pub grace_secs: u64,
+    /// Fetches failing in a row within that time that put the watchlist
+    /// from before the change back.
+    pub max_failures: u32,
+}
+
+impl Default for ApplyConfig {
+    fn default() -> Self {
+        ApplyConfig {
+            grace_secs: 300,
+            max_failures: 5,
+        }
+    }
+}
+
+/// Where a watchlist change came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeSource {
+    Reload,
+    Api,
+}
+
+impl ChangeSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeSource::Reload => "reload",
+            ChangeSource::Api => "api",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeOutcome {
+    /// Failed validation and never took effect.
+    Rejected,
+    /// Polled from now on, on probation.
+    Applied,
+    /// The engine stayed healthy through the grace period.
+    Confirmed,
+    /// The engine went unhealthy, so the watchlist before it is back.
+    RolledBack,
+}
+
+impl ChangeOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeOutcome::Rejected => "rejected",
+            ChangeOutcome::Applied => "applied",
+            ChangeOutcome::Confirmed => "confirmed",
+            ChangeOutcome::RolledBack => "rolled_back",
+        }
+    }
+}
+
+// A change still on probation, and the last watchlist known good.
+struct Staged {
+    previous: Tickers,
+    source: ChangeSource,
+    applied_at: DateTime<Utc>,
+    failures: u32,
+}
+
+/// Applies watchlist changes in two phases: validated before anything
+/// changes, then swapped in whole and watched for the grace period, when
+/// too many fetches failing in a row put the last good watchlist back.
+/// Each step is published as an [`Event::ConfigChange`].
+pub struct Applier {
+    config: ApplyConfig,
+    clock: Arc<dyn Clock>,
+    events: EventBus,
+    staged: Mutex<Option<Staged>>,
+}
+
+impl Applier {
+    pub fn new(config: &ApplyConfig, clock: Arc<dyn Clock>, events: EventBus) -> Self {
+        Applier {
+            config: config.clone(),
+            clock,
+            events,
+            staged: Mutex::new(None),
+        }
+    }
+
+    /// Replaces `tickers` with `new` when within `limits`. An unchanged
+    /// watchlist is left alone.
+    pub fn stage(
+        &self,
+        tickers: &mut Tickers,
+        new: Tickers,
+        source: ChangeSource,
+        limits: &Limits,
+    ) -> Result<(), LimitError> {
+        if new == *tickers {
+            return Ok(());
+        }
+        let (from, to) = (tickers.get_tickers().len(), new.get_tickers().len());
+        if let Err(e) = limits.check_growth(from, to) {
+            self.rejected(source, to, &e);
+            return Err(e);
+        }
+        let previous = std::mem::replace(tickers, new);
+        self.applied(previous, source, to);
+        Ok(())
+    }
+
+    /// Records a change to `symbols` symbols refused for `error`.
+    pub fn rejected(&self, source: ChangeSource, symbols: usize, error: &LimitError) {
+        self.publish(
+            source,
+            ChangeOutcome::Rejected,
+            symbols,
+            Some(error.to_string()),
+        );
+    }
+
+    /// Puts a change already made on probation, `previous` being the
+    /// watchlist before it. Changes made while another is on probation
+    /// roll back to the watchlist before the first.
+    pub fn applied(&self, previous: Tickers, source: ChangeSource, symbols: usize) {
+        self.publish(source, ChangeOutcome::Applied, symbols, None);
+        if self.config.grace_secs == 0 {
+            return;
+        }
+        let mut staged = self.staged.lock().unwrap();
+        let previous = match staged.take() {
+            Some(staged) => staged.previous,
+            None => previous,
+        };
+        *staged = Some(Staged {
+            previous,
+            source,
+            applied_at: self.clock.now(),
+            failures: 0,
+        });
+    }
+
+    /// Keeps the change on probation once its grace period is over, or
+    /// puts the watchlist before it back into `tickers` and the file when
+    /// the engine went unhealthy. Call with the watchlist locked.
+    pub async fn check(&self, tickers: &mut Tickers) {
+        let rollback = {
+            let mut staged = self.staged.lock().unwrap();
+            let Some(current) = staged.as_ref() else {
+                return;
+            };
+            let grace = chrono::Duration::seconds(self.config.grace_secs as i64);
+            if current.failures >= self.config.max_failures.max(1) {
+                staged.take()
+            } else {
+                if self.clock.now() - current.applied_at >= grace {
+                    let symbols = tickers.get_tickers().len();
+                    self.publish(current.source, ChangeOutcome::Confirmed, symbols, None);
+                    *staged = None;
+                }
+                None
+            }
+        };
+        let Some(staged) = rollback else {
+            return;
+        };
+        let reason = format!("{} fetches in a row failed", staged.failures);
+        *tickers = staged.previous;
+        if let Err(e) = tickers.save().await {
+            error!(error = %e, "Failed to write rolled back tickers");
+        }
+        let symbols = tickers.get_tickers().len();
+        self.publish(
+            staged.source,
+            ChangeOutcome::RolledBack,
+            symbols,
+            Some(reason),
+        );
+    }
+
+    fn publish(
+        &self,
+        source: ChangeSource,
+        outcome: ChangeOutcome,
+        symbols: usize,
+        reason: Option<String>,
+    ) {
+        metrics::update_config_change(source.as_str(), outcome.as_str());
+        match outcome {
+            ChangeOutcome::Applied | ChangeOutcome::Confirmed => {
+                info!(
+                    source = source.as_str(),
+                    symbols,
+                    "Watchlist change {}",
+                    outcome.as_str()
+                )
+            }
+            ChangeOutcome::Rejected | ChangeOutcome::RolledBack => warn!(
+                source = source.as_str(),
+                symbols,
+                reason = reason.as_deref(),
+                "Watchlist change {}",
+                outcome.as_str()
+            ),
+        }
+        self.events.publish(Event::ConfigChange {
+            at: self.clock.now(),
+            source,
+            outcome,
+            symbols,
+            reason,
+        });
+    }
+}
+
+impl Sink for Applier {
+    fn record(&self, _update: &PriceUpdate) {}
+
+    fn on_fetch(&self, _symbol: &str, outcome: FetchOutcome, _at: DateTime<Utc>) {
+        if let Some(staged) = self.staged.lock().unwrap().as_mut() {
+            match outcome {
+                FetchOutcome::Failure => staged.failures += 1,
+                _ => staged.failures = 0,
+            }
+        }
+    }
+
+    fn replay(&self, _update: &PriceUpdate) {}
+}
@@ -0,0 +1,1109 @@
+pub mod apply;
+pub mod warmup;
+pub mod watch;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use serde::Serialize;
+use tracing::{error, info, instrument, warn};
+
+use self::apply::{Applier, ChangeSource};
+use crate::adr::AdrMonitor;
+use crate::alerts::Alerts;
+use crate::allocation::AllocationTracker;
+use crate::analysts::AnalystTracker;
+use crate::calendar::{self, MarketCalendar};
+use crate::clock::Clock;
+use crate::cluster::dedup::NotificationDedup;
+use crate::cluster::Cluster;
+use crate::config::Config;
+use crate::currency::{self, CurrencyConverter};
+use crate::econ::{EconCalendar, PollMode};
+use crate::encryption::StorageKey;
+use crate::events::{self, Event, EventBus};
+use crate::feed::AlertFeed;
+use crate::futures::Futures;
+use crate::history::History;
+use crate::indicators::Indicators;
+use crate::lag;
+use crate::limits::Limits;
+use crate::listings::Consolidator;
+use crate::market::MarketContext;
+use crate::metrics;
+use crate::namespaces::Namespaces;
+use crate::notes::NoteStore;
+use crate::notify::Notifiers;
+use crate::ops::{OpsAlerter, OpsMonitor};
+use crate::paper::{PaperAccount, PaperConfig};
+use crate::patterns::PatternDetector;
+use crate::portfolio::PortfolioTracker;
+use crate::priority::adaptive::VolatilityTracker;
+use crate::priority::jitter::Jitter;
+use crate::priority::{self, PriorityConfig};
+use crate::provider::{retry::retry, Provider, Quote};
+use crate::quality::QualityTracker;
+use crate::ratelimit::RateLimiter;
+use crate::rates::RatesTracker;
+use crate::returns::Conventions;
+use crate::sink::candles::DailyCandles;
+use crate::sink::skew::SkewTracker;
+use crate::sink::{FetchOutcome, LatestPrices, MetricsSink, PriceUpdate, Sink};
+use crate::slo::FreshnessTracker;
+use crate::smoothing::Smoother;
+use crate::strategy::{ScriptStrategy, Strategy, StrategyRunner};
+use crate::stream::PriceStream;
+use crate::symbol::SymbolInfo;
+use crate::toggles::Toggles;
+use crate::tracking::BenchmarkTracker;
+use crate::venues::VenuePricer;
+use crate::watchdog::Watchdog;
+use crate::{check_tickers, AssetClass, Markets, StockMarket, Tickers};
+use watch::CloseWatch;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CycleSummary {
+    pub symbols: usize,
+    pub successes: usize,
+    pub empty: usize,
+    pub failures: usize,
+    pub credits: u64,
+    pub duration_secs: f64,
+    /// Due symbols left to the next cycle by `max_cycle_secs`.
+    pub deferred: usize,
+}
+
+impl CycleSummary {
+    fn log(&self) {
+        info!(
+            event = "cycle_summary",
+            symbols = self.symbols,
+            successes = self.successes,
+            empty = self.empty,
+            failures = self.failures,
+            credits = self.credits,
+            duration_secs = self.duration_secs,
+            deferred = self.deferred,
+            "Poll cycle finished"
+        );
+    }
+}
+
+pub struct CycleContext<'a> {
+    pub now: DateTime<Utc>,
+    pub market_open: bool,
+    pub symbols: &'a [String],
+    pub summary: &'a CycleSummary,
+}
+
+pub struct Engine {
+    provider: Arc<dyn Provider>,
+    clock: Arc<dyn Clock>,
+    // Each with the name it's toggled by.
+    sinks: Vec<(String, Arc<dyn Sink>)>,
+    toggles: Arc<Toggles>,
+    limits: Arc<Limits>,
+    market: Markets,
+    calendar: MarketCalendar,
+    limiter: Arc<RateLimiter>,
+    budget: f64,
+    priorities: PriorityConfig,
+    adaptive: Option<VolatilityTracker>,
+    next_due: Mutex<HashMap<String, DateTime<Utc>>>,
+    pinned: Vec<String>,
+    // Prices are also converted to it, so its FX rates are polled.
+    base_currency: Option<String>,
+    temporary: Mutex<HashMap<String, DateTime<Utc>>>,
+    watches: Arc<CloseWatch>,
+    econ: Option<Arc<EconCalendar>>,
+    paper: Option<Arc<PaperAccount>>,
+    notifiers: Notifiers,
+    strategies: Option<Arc<StrategyRunner>>,
+    candle_secs: i64,
+    events: EventBus,
+    cluster: Option<Arc<Cluster>>,
+    quality: Option<Arc<QualityTracker>>,
+    latest: Option<Arc<LatestPrices>>,
+    feed: Option<Arc<AlertFeed>>,
+    history: Option<Arc<History>>,
+    tracking: Option<Arc<BenchmarkTracker>>,
+    notes: Option<Arc<NoteStore>>,
+    portfolio: Option<Arc<PortfolioTracker>>,
+    namespaces: Option<Arc<Namespaces>>,
+    futures: Option<Arc<Futures>>,
+    analysts: Option<Arc<AnalystTracker>>,
+    market_context: Option<Arc<MarketContext>>,
+    venues: Option<Arc<VenuePricer>>,
+    watchdog: Option<Arc<Watchdog>>,
+    conventions: Conventions,
+    smoother: Smoother,
+    jitter: Jitter,
+    shard: Mutex<Vec<String>>,
+    watched: Mutex<Vec<String>>,
+    stream: Option<Arc<PriceStream>>,
+    reload_tickers: bool,
+    ticker_updates: Option<Mutex<tokio::sync::watch::Receiver<Tickers>>>,
+    tickers: Arc<tokio::sync::Mutex<Tickers>>,
+    applier: Arc<Applier>,
+    // Price requests in flight at once.
+    concurrency: usize,
+}
+
+impl Engine {
+    pub fn new(provider: Arc<dyn Provider>, clock: Arc<dyn Clock>, config: &Config) -> Self {
+        let events = EventBus::default();
+        let limiter = Arc::new(RateLimiter::new(&config.rate_limits, clock.clone()));
+        let calendar = MarketCalendar::new(clock.clone(), config.provider.market_state_secs);
+        let watchdog = Watchdog::new(&config.watchdog, clock.clone()).map(Arc::new);
+        let applier = Arc::new(Applier::new(&config.apply, clock.clone(), events.clone()));
+        let mut engine = Engine {
+            provider,
+            clock,
+            sinks: vec![],
+            toggles: Arc::new(Toggles::load(&config.state_dir)),
+            limits: Arc::new(Limits::new(&config.limits)),
+            market: Markets::Stock(config.exchange),
+            calendar,
+            limiter,
+            budget: priority::budget(&config.rate_limits),
+            priorities: config.priority.clone(),
+            adaptive: config
+                .adaptive
+                .enabled
+                .then(|| VolatilityTracker::new(config.adaptive.clone())),
+            next_due: Mutex::new(HashMap::new()),
+            pinned: vec![],
+            base_currency: config.currency.base.clone(),
+            temporary: Mutex::new(HashMap::new()),
+            watches: Arc::new(CloseWatch::new(events.clone())),
+            econ: None,
+            paper: None,
+            notifiers: Notifiers::default(),
+            strategies: None,
+            candle_secs: config.strategy.candle_secs,
+            events,
+            cluster: None,
+            quality: None,
+            latest: None,
+            feed: None,
+            history: None,
+            tracking: None,
+            notes: None,
+            portfolio: None,
+            namespaces: None,
+            futures: None,
+            analysts: None,
+            market_context: None,
+            venues: None,
+            watchdog,
+            conventions: Conventions::new(&config.returns, config.exchange),
+            smoother: Smoother::new(config.smoothing.clone()),
+            jitter: Jitter::new(&config.jitter),
+            shard: Mutex::new(vec![]),
+            watched: Mutex::new(vec![]),
+            stream: None,
+            reload_tickers: false,
+            ticker_updates: None,
+            tickers: Arc::default(),
+            applier: applier.clone(),
+            concurrency: config.provider.concurrency.max(1),
+        };
+        engine.push_sink(applier);
+        engine
+    }
+
+    /// An engine with every sink the config enables: metrics, freshness
+    /// tracking, listing consolidation and rate spreads, plus the economic
+    /// calendar, data quality scoring, latest prices and history, the paper-trading account and its allocation, the
+    /// configured notifiers plus the alert feed, and script strategies.
+    pub fn from_config(
+        provider: Arc<dyn Provider>,
+        clock: Arc<dyn Clock>,
+        config: &Config,
+    ) -> Self {
+        let listings = Arc::new(Consolidator::new(config.listings.clone()));
+        let engine = Engine::new(provider, clock, config);
+        let conventions = engine.conventions;
+        let paper = Arc::new(
+            PaperAccount::new(config.paper.clone())
+                .with_events(engine.events.clone())
+                .with_conventions(conventions),
+        );
+        let notes = Arc::new(NoteStore::new(&config.state_dir));
+        let mut notifiers = Notifiers::from_config(&config.notifiers).with_notes(notes.clone());
+        if let Some(dedup) = NotificationDedup::new(&config.cluster, engine.clock.clone()) {
+            notifiers = notifiers.with_dedup(Arc::new(dedup));
+        }
+        let allocation = AllocationTracker::new(config.allocation.clone(), paper.clone());
+        let feed = AlertFeed::new(engine.clock.clone());
+        let mut engine = engine
+            .with_econ(Arc::new(EconCalendar::new(config.econ.clone())))
+            .with_paper(paper.clone())
+            .with_sink(Arc::new(allocation))
+            .with_notifiers(notifiers.clone())
+            .with_notes(notes)
+            .with_portfolio(Arc::new(
+                PortfolioTracker::new(&config.state_dir).with_ladder(&config.portfolio, notifiers),
+            ))
+            .with_namespaces(Arc::new(Namespaces::load(&config.state_dir)))
+            .with_feed(Arc::new(feed))
+            .with_symbols(listings.symbols())
+            .with_sink(listings)
+            .with_sink(Arc::new(MetricsSink))
+            .with_sink(Arc::new(SkewTracker::default()))
+            .with_sink(Arc::new(DailyCandles::new(conventions)))
+            .with_sink(Arc::new(FreshnessTracker::new(
+                config.slo.objectives.clone(),
+            )))
+            .with_sink(Arc::new(RatesTracker::new(config.rates.clone())))
+            .with_quality(Arc::new(QualityTracker::new(config.quality.clone())))
+            .with_latest(Arc::new(LatestPrices::default()))
+            .with_history(Arc::new(
+                History::new(&config.history, &config.state_dir).with_conventions(conventions),
+            ));
+        if let Some(tracking) = BenchmarkTracker::new(&config.tracking, paper, &config.state_dir) {
+            engine = engine.with_tracking(Arc::new(tracking.with_conventions(conventions)));
+        }
+        if let Some(market) = MarketContext::new(&config.market) {
+            engine = engine.with_market_context(Arc::new(market));
+        }
+        for script in &config.strategy.scripts {
+            let mut strategy = ScriptStrategy::load(script);
+            if let Some(market) = &engine.market_context {
+                strategy = strategy.with_market(market.clone());
+            }
+            engine = engine.with_strategy(Box::new(strategy));
+        }
+        if let Some(indicators) = Indicators::new(config.indicators.clone()) {
+            engine = engine.with_sink(Arc::new(indicators));
+        }
+        let events = engine.events.clone();
+        if let Some(patterns) = PatternDetector::new(&config.patterns, events, conventions) {
+            engine = engine.with_sink(Arc::new(patterns));
+        }
+        if let Some(adr) = AdrMonitor::new(&config.adr, engine.notifiers.clone()) {
+            engine = engine.with_symbols(adr.symbols()).with_sink(Arc::new(adr));
+        }
+        if let Some(futures) = Futures::new(&config.futures, &config.state_dir) {
+            engine = engine.with_futures(Arc::new(futures));
+        }
+        // Spot prices for the options Greeks.
+        engine = engine.with_symbols(config.options.symbols.clone());
+        if config.analysts.enabled {
+            let analysts = AnalystTracker::new(
+                &config.analysts,
+                &config.state_dir,
+                engine.notifiers.clone(),
+            )
+            .with_base_url(&config.provider.twelvedata_url);
+            engine = engine.with_analysts(Arc::new(analysts));
+        }
+        if let Some(converter) = CurrencyConverter::new(&config.currency) {
+            engine = engine.with_sink(Arc::new(converter));
+        }
+        if let Some(venues) = VenuePricer::new(&config.venues) {
+            engine = engine.with_venues(Arc::new(venues));
+        }
+        if !config.alerts.rules.is_empty() {
+            let alerts = Alerts::new(&config.alerts, engine.notifiers.clone());
+            engine = engine.with_sink(Arc::new(alerts));
+        }
+        engine
+    }
+
+    fn push_sink(&mut self, sink: Arc<dyn Sink>) {
+        let name = sink.name();
+        self.toggles.register(&name);
+        self.sinks.push((name, sink));
+    }
+
+    // The sinks not switched off.
+    fn active_sinks(&self) -> impl Iterator<Item = &Arc<dyn Sink>> {
+        self.sinks
+            .iter()
+            .filter(|(name, _)| self.toggles.enabled(name))
+            .map(|(_, sink)| sink)
+    }
+
+    pub fn toggles(&self) -> &Arc<Toggles> {
+        &self.toggles
+    }
+
+    pub fn limits(&self) -> &Arc<Limits> {
+        &self.limits
+    }
+
+    pub fn with_sink(mut self, sink: Arc<dyn Sink>) -> Self {
+        self.push_sink(sink);
+        self
+    }
+
+    pub fn with_econ(mut self, econ: Arc<EconCalendar>) -> Self {
+        self.econ = Some(econ);
+        self
+    }
+
+    pub fn econ(&self) -> Option<&Arc<EconCalendar>> {
+        self.econ.as_ref()
+    }
+
+    pub fn with_paper(mut self, paper: Arc<PaperAccount>) -> Self {
+        self.push_sink(paper.clone());
+        self.paper = Some(paper);
+        self
+    }
+
+    pub fn paper(&self) -> Option<&Arc<PaperAccount>> {
+        self.paper.as_ref()
+    }
+
+    pub fn with_quality(mut self, quality: Arc<QualityTracker>) -> Self {
+        self.push_sink(quality.clone());
+        self.quality = Some(quality);
+        self
+    }
+
+    pub fn quality(&self) -> Option<&Arc<QualityTracker>> {
+        self.quality.as_ref()
+    }
+
+    pub fn with_latest(mut self, latest: Arc<LatestPrices>) -> Self {
+        self.push_sink(latest.clone());
+        self.latest = Some(latest);
+        self
+    }
+
+    pub fn latest(&self) -> Option<&Arc<LatestPrices>> {
+        self.latest.as_ref()
+    }
+
+    pub fn with_history(mut self, history: Arc<History>) -> Self {
+        self.push_sink(history.clone());
+        self.history = Some(history);
+        self
+    }
+
+    pub fn history(&self) -> Option<&Arc<History>> {
+        self.history.as_ref()
+    }
+
+    /// Also polls the benchmark, whether or not it's on the watchlist.
+    pub fn with_tracking(mut self, tracking: Arc<BenchmarkTracker>) -> Self {
+        self.push_sink(tracking.clone());
+        self = self.with_symbols(vec![tracking.benchmark().to_string()]);
+        self.tracking = Some(tracking);
+        self
+    }
+
+    pub fn tracking(&self) -> Option<&Arc<BenchmarkTracker>> {
+        self.tracking.as_ref()
+    }
+
+    pub fn with_notes(mut self, notes: Arc<NoteStore>) -> Self {
+        self.notes = Some(notes);
+        self
+    }
+
+    pub fn notes(&self) -> Option<&Arc<NoteStore>> {
+        self.notes.as_ref()
+    }
+
+    pub fn with_portfolio(mut self, portfolio: Arc<PortfolioTracker>) -> Self {
+        self.push_sink(portfolio.clone());
+        self.portfolio = Some(portfolio);
+        self
+    }
+
+    pub fn portfolio(&self) -> Option<&Arc<PortfolioTracker>> {
+        self.portfolio.as_ref()
+    }
+
+    pub fn with_namespaces(mut self, namespaces: Arc<Namespaces>) -> Self {
+        self.push_sink(namespaces.clone());
+        self.namespaces = Some(namespaces);
+        self
+    }
+
+    pub fn namespaces(&self) -> Option<&Arc<Namespaces>> {
+        self.namespaces.as_ref()
+    }
+
+    pub fn with_analysts(mut self, analysts: Arc<AnalystTracker>) -> Self {
+        self.push_sink(analysts.clone());
+        self.analysts = Some(analysts);
+        self
+    }
+
+    pub fn analysts(&self) -> Option<&Arc<AnalystTracker>> {
+        self.analysts.as_ref()
+    }
+
+    pub fn with_market_context(mut self, market: Arc<MarketContext>) -> Self {
+        self.push_sink(market.clone());
+        self.market_context = Some(market);
+        self
+    }
+
+    pub fn market_context(&self) -> Option<&Arc<MarketContext>> {
+        self.market_context.as_ref()
+    }
+
+    /// Polls every contract and records the continuous series next to them.
+    pub fn with_futures(mut self, futures: Arc<Futures>) -> Self {
+        self = self.with_symbols(futures.symbols());
+        self.futures = Some(futures);
+        self
+    }
+
+    pub fn futures(&self) -> Option<&Arc<Futures>> {
+        self.futures.as_ref()
+    }
+
+    pub fn with_venues(mut self, venues: Arc<VenuePricer>) -> Self {
+        self.push_sink(venues.clone());
+        self.venues = Some(venues);
+        self
+    }
+
+    pub fn venues(&self) -> Option<&Arc<VenuePricer>> {
+        self.venues.as_ref()
+    }
+
+    pub fn watchdog(&self) -> Option<&Arc<Watchdog>> {
+        self.watchdog.as_ref()
+    }
+
+    // Checks in with the watchdog ahead of `idle` spent sleeping on purpose.
+    fn arm(&self, idle: Duration) {
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.arm(idle);
+        }
+    }
+
+    // Sleeps for `idle`, checked in with the watchdog first.
+    async fn rest(&self, idle: Duration) {
+        self.arm(idle);
+        self.clock.sleep(idle).await;
+    }
+
+    // Waits on the rate limiter, each wait for credits pushing the
+    // watchdog's deadline out so a long queue doesn't count as a stall.
+    async fn acquire(&self, cost: u64) {
+        self.limiter
+            .acquire_with(cost, |wait| {
+                if let Some(watchdog) = &self.watchdog {
+                    watchdog.extend(wait);
+                }
+            })
+            .await;
+    }
+
+    pub fn with_notifiers(mut self, notifiers: Notifiers) -> Self {
+        self.notifiers = notifiers;
+        self
+    }
+
+    /// Records every notification sent through the engine's notifiers, so
+    /// register it after them.
+    pub fn with_feed(mut self, feed: Arc<AlertFeed>) -> Self {
+        self.notifiers.push(feed.clone());
+        self.feed = Some(feed);
+        self
+    }
+
+    /// Raises provider-down and data-outage incidents through `ops`.
+    pub fn with_ops(self, ops: Arc<OpsAlerter>) -> Self {
+        let monitor = OpsMonitor::new(ops, self.provider.name());
+        self.with_sink(Arc::new(monitor))
+    }
+
+    pub fn feed(&self) -> Option<&Arc<AlertFeed>> {
+        self.feed.as_ref()
+    }
+
+    /// The watchlist being polled. Edit it, then save it with
+    /// [`Tickers::dump_to_file`] while still holding the lock, or the next
+    /// reload of the file undoes the edit.
+    pub fn tickers(&self) -> &Arc<tokio::sync::Mutex<Tickers>> {
+        &self.tickers
+    }
+
+    /// Puts watchlist changes on probation, rolling them back when the
+    /// engine goes unhealthy.
+    pub fn applier(&self) -> &Arc<Applier> {
+        &self.applier
+    }
+
+    pub fn notifiers(&self) -> &Notifiers {
+        &self.notifiers
+    }
+
+    /// Runs `strategy` on every price update, trading against the paper
+    /// account. Register the paper account and notifiers first.
+    pub fn with_strategy(mut self, strategy: Box<dyn Strategy>) -> Self {
+        let runner = match &self.strategies {
+            Some(runner) => runner.clone(),
+            None => {
+                let paper = self.paper.clone().unwrap_or_else(|| {
+                    Arc::new(
+                        PaperAccount::new(PaperConfig::default())
+                            .with_events(self.events.clone())
+                            .with_conventions(self.conventions),
+                    )
+                });
+                if self.paper.is_none() {
+                    self = self.with_paper(paper.clone());
+                }
+                let mut runner = StrategyRunner::new(
+                    paper,
+                    self.notifiers.clone(),
+                    self.events.clone(),
+                    self.candle_secs,
+                )
+                .with_conventions(self.conventions);
+                if let Some(history) = &self.history {
+                    runner = runner.with_history(history.clone());
+                }
+                let runner = Arc::new(runner);
+                self.push_sink(runner.clone());
+                self.strategies = Some(runner.clone());
+                runner
+            }
+        };
+        runner.add(strategy);
+        self
+    }
+
+    /// Symbols polled every cycle on top of the tickers file.
+    pub fn with_symbols(mut self, symbols: Vec<String>) -> Self {
+        for symbol in symbols {
+            if !self.pinned.contains(&symbol) {
+                self.pinned.push(symbol);
+            }
+        }
+        self
+    }
+
+    /// Polls `symbol` alongside the watchlist until `until` passes.
+    pub fn watch_until(&self, symbol: &str, until: DateTime<Utc>) {
+        info!(symbol, %until, "Watching symbol temporarily");
+        self.temporary
+            .lock()
+            .unwrap()
+            .insert(symbol.to_string(), until);
+    }
+
+    /// Symbols in time-boxed close watch, shared with the API.
+    pub fn watches(&self) -> &Arc<CloseWatch> {
+        &self.watches
+    }
+
+    /// Pick up edits to the tickers file between cycles.
+    pub fn with_ticker_reload(mut self, reload: bool) -> Self {
+        self.reload_tickers = reload;
+        self
+    }
+
+    /// Take the watchlist from `updates`, as sent by
+    /// [`crate::reload::watch_tickers`], instead of checking the file.
+    pub fn with_ticker_updates(mut self, updates: tokio::sync::watch::Receiver<Tickers>) -> Self {
+        self.ticker_updates = Some(Mutex::new(updates));
+        self
+    }
+
+    /// Polls only the symbols this instance owns in `cluster`.
+    /// Symbols live on the stream are left out of polling until it drops.
+    pub fn with_stream(mut self, stream: Arc<PriceStream>) -> Self {
+        self.stream = Some(stream);
+        self
+    }
+
+    pub fn with_cluster(mut self, cluster: Arc<Cluster>) -> Self {
+        self.cluster = Some(cluster);
+        self
+    }
+
+    // Keeps this instance's share of the watchlist and drops the gauges of
+    // symbols another member has taken over.
+    fn shard(&self, watched: Vec<String>) -> Vec<String> {
+        let Some(cluster) = &self.cluster else {
+            return watched;
+        };
+        let owned: Vec<String> = watched.into_iter().filter(|s| cluster.owns(s)).collect();
+        let mut shard = self.shard.lock().unwrap();
+        for symbol in shard.iter().filter(|s| !owned.contains(s)) {
+            let info = SymbolInfo::parse(symbol);
+            metrics::remove_stock_price(symbol, info.asset_class.as_str(), &info.currency());
+            if let Some(base) = &self.base_currency {
+                metrics::remove_stock_price_base(symbol, info.asset_class.as_str(), base);
+            }
+        }
+        metrics::update_shard(cluster.members().len(), owned.len());
+        *shard = owned.clone();
+        owned
+    }
+
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    /// Rebuilds prices, paper positions and indicators from an event log.
+    /// Call before anything subscribes to the bus so replayed orders are
+    /// not logged twice. Returns the last sequence number seen.
+    pub async fn restore(&self, path: &Path, key: Option<&StorageKey>) -> std::io::Result<u64> {
+        let log = events::load(path, key).await?;
+        let mut last_seq = 0;
+        for envelope in &log {
+            last_seq = envelope.seq;
+            match &envelope.event {
+                Event::Price(update) => self.replay(update),
+                Event::OrderPlaced { at, request } => {
+                    if let Some(paper) = &self.paper {
+                        let _ = paper.place(request.clone(), *at);
+                    }
+                }
+                Event::OrderCancelled { at, id } => {
+                    if let Some(paper) = &self.paper {
+                        let _ = paper.cancel(*id, *at);
+                    }
+                }
+                Event::Signal { .. } | Event::ConfigChange { .. } => {}
+                Event::WatchStarted { symbol, until, .. } => self.watches.insert(symbol, *until),
+                Event::WatchStopped { symbol, .. } => {
+                    self.watches.remove(symbol);
+                }
+            }
+        }
+        info!(path = %path.display(), events = log.len(), last_seq, "Restored state from event log");
+        Ok(last_seq)
+    }
+
+    fn replay(&self, update: &PriceUpdate) {
+        if let Some(adaptive) = &self.adaptive {
+            adaptive.observe(&update.symbol, update.price);
+        }
+        // The continuous series are in the log already.
+        if let Some(futures) = &self.futures {
+            futures.replay(update);
+        }
+        for sink in self.active_sinks() {
+            sink.replay(update);
+        }
+    }
+
+    /// Feeds older prices, oldest first, to the sinks the way a restore
+    /// does, so they reach history and the indicators without counting as
+    /// fresh quotes.
+    pub fn backfill(&self, updates: &[PriceUpdate]) {
+        for update in updates {
+            self.replay(update);
+        }
+    }
+
+    pub fn provider(&self) -> &Arc<dyn Provider> {
+        &self.provider
+    }
+
+    /// Shared with anything else spending the price provider's credits.
+    pub fn limiter(&self) -> &Arc<RateLimiter> {
+        &self.limiter
+    }
+
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    pub async fn run(&self, tickers: Tickers) {
+        self.run_until(tickers, None).await
+    }
+
+    // Takes a reloaded watchlist unless it grows past `[limits]`.
+    fn reload(&self, tickers: &mut Tickers, new: Tickers) {
+        let staged = self
+            .applier
+            .stage(tickers, new, ChangeSource::Reload, &self.limits);
+        if let Err(e) = staged {
+            error!(error = %e, "Refusing reloaded tickers, keeping the watchlist");
+        }
+    }
+
+    /// Polls until the clock passes `until`, or forever when it is `None`.
+    pub async fn run_until(&self, tickers: Tickers, until: Option<DateTime<Utc>>) {
+        *self.tickers.lock().await = tickers;
+        while until.is_none_or(|until| self.clock.now() < until) {
+            let started = self.clock.now();
+            self.arm(Duration::ZERO);
+            let (listed, mut watched) = {
+                let mut tickers = self.tickers.lock().await;
+                self.applier.check(&mut tickers).await;
+                if let Some(updates) = &self.ticker_updates {
+                    let mut updates = updates.lock().unwrap();
+                    if updates.has_changed().unwrap_or(false) {
+                        let new = updates.borrow_and_update().clone();
+                        self.reload(&mut tickers, new);
+                    }
+                } else if self.reload_tickers {
+                    match check_tickers().await {
+                        Ok(Some(new)) => self.reload(&mut tickers, new),
+                        Ok(None) => {}
+                        Err(e) => {
+                            error!(error = %e, "Failed to reload tickers, keeping the watchlist")
+                        }
+                    }
+                }
+                crate::set_asset_classes(tickers.classes().clone());
+                crate::set_exchanges(tickers.exchanges());
+                let labels = tickers.labels();
+                metrics::update_ticker_labels(tickers.get_tickers(), &labels);
+                crate::set_labels(labels);
+                (tickers.clone(), tickers.get_tickers().clone())
+            };
+            let now = self.clock.now();
+            let temporary: Vec<String> = {
+                let mut temporary = self.temporary.lock().unwrap();
+                temporary.retain(|_, until| *until > now);
+                temporary.keys().cloned().collect()
+            };
+            let closely = self.watches.expire(now);
+            let namespaced = self
+                .namespaces
+                .as_ref()
+                .map(|n| n.symbols())
+                .unwrap_or_default();
+            let extra = temporary.iter().chain(&closely).chain(&namespaced);
+            for symbol in self.pinned.iter().chain(extra) {
+                if !watched.contains(symbol) {
+                    watched.push(symbol.clone());
+                }
+            }
+            if let Some(base) = &self.base_currency {
+                watched.extend(currency::pairs(base, &watched));
+            }
+            let watched = listed.select(self.shard(watched));
+            *self.watched.lock().unwrap() = watched.get_tickers().clone();
+
+            let mut boost = 1.;
+            if let Some(econ) = &self.econ {
+                let now = self.clock.now();
+                econ.export(now);
+                match econ.mode(now) {
+                    PollMode::Paused { until } => {
+                        info!(%until, "Pausing polling around economic event");
+                        let pause = (until - now).to_std().unwrap_or_default();
+                        self.rest(pause.max(Duration::from_secs(1))).await;
+                        continue;
+                    }
+                    PollMode::Boosted(factor) => boost = factor,
+                    PollMode::Normal => {}
+                }
+            }
+
+            let mut lookups = 0;
+            for market in self.markets(&watched) {
+                if !self.calendar.is_stale(&market) {
+                    continue;
+                }
+                self.acquire(1).await;
+                lookups += 1;
+                if let Err(e) = self.calendar.refresh(&*self.provider, &market).await {
+                    warn!(%market, error = %e, "Failed to fetch market state");
+                }
+            }
+            // Unknown, the primary market is polled as if open.
+            let primary_wait = self.calendar.seconds_until_open(&self.market).unwrap_or(0);
+            let night_time = self.night_time(&watched, primary_wait);
+            self.rest(Duration::from_secs(night_time)).await;
+            let primary_open = primary_wait <= night_time;
+
+            let mut summary = self.cycle(&watched, primary_open, boost).await;
+            // Market state lookups cost a call as well.
+            summary.credits += lookups;
+            summary.log();
+            let context = CycleContext {
+                now: self.clock.now(),
+                market_open: primary_open,
+                symbols: watched.get_tickers(),
+                summary: &summary,
+            };
+            for sink in self.active_sinks() {
+                sink.on_cycle(&context);
+            }
+
+            // With nothing due, wait for the first symbol that will be.
+            if summary.symbols == 0 {
+                self.rest(self.until_next_due()).await;
+            }
+            // Never spin without time passing, e.g. on an empty watchlist.
+            if self.clock.now() == started {
+                self.rest(Duration::from_secs(1)).await;
+            }
+        }
+    }
+
+    // Up to a minute, so watchlist changes are still picked up, and a second
+    // when nothing is scheduled.
+    fn until_next_due(&self) -> Duration {
+        let now = self.clock.now();
+        let next = self
+            .next_due
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|at| **at > now)
+            .min()
+            .copied();
+        next.and_then(|at| (at - now).to_std().ok())
+            .unwrap_or(Duration::from_secs(1))
+            .min(Duration::from_secs(60))
+    }
+
+    fn primary(&self) -> Option<StockMarket> {
+        match self.market {
+            Markets::Stock(market) => Some(market),
+            _ => None,
+        }
+    }
+
+    // The primary market and every other exchange a watched listing trades
+    // on, each with its own hours.
+    fn markets(&self, tickers: &Tickers) -> Vec<Markets> {
+        let mut exchanges = vec![];
+        for ticker in tickers.get_tickers() {
+            let info = SymbolInfo::parse(ticker);
+            if matches!(
+                info.asset_class,
+                AssetClass::Crypto | AssetClass::Forex | AssetClass::Commodity
+            ) {
+                continue;
+            }
+            if let Some(exchange) = info.exchange.filter(|e| Some(*e) != self.primary()) {
+                if !exchanges.contains(&exchange) {
+                    exchanges.push(exchange);
+                }
+            }
+        }
+        std::iter::once(self.market)
+            .chain(exchanges.into_iter().map(Markets::Stock))
+            .collect()
+    }
+
+    // Another exchange by its fetched state, or its regular session when
+    // that's out of date.
+    fn exchange_wait(&self, exchange: StockMarket, now: DateTime<Utc>) -> u64 {
+        self.calendar
+            .seconds_until_open(&Markets::Stock(exchange))
+            .unwrap_or_else(|| calendar::session(exchange).seconds_until_open(now))
+    }
+
+    // Listings on other exchanges follow their own hours, so sleep only
+    // until the first watched exchange opens.
+    fn night_time(&self, tickers: &Tickers, primary_wait: u64) -> u64 {
+        let now = self.clock.now();
+        let mut waits = vec![];
+        for ticker in tickers.get_tickers() {
+            let info = SymbolInfo::parse(ticker);
+            match info.exchange {
+                _ if info.asset_class == AssetClass::Crypto => waits.push(0),
+                _ if info.asset_class == AssetClass::Forex => {
+                    waits.push(calendar::forex_seconds_until_open(now))
+                }
+                _ if info.asset_class == AssetClass::Commodity => {
+                    waits.push(calendar::commodity_seconds_until_open(now))
+                }
+                Some(exchange) if Some(exchange) != self.primary() => {
+                    waits.push(self.exchange_wait(exchange, now))
+                }
+                _ => waits.push(primary_wait),
+            }
+        }
+        waits.into_iter().min().unwrap_or(primary_wait)
+    }
+
+    fn is_open(&self, ticker: &str, primary_open: bool) -> bool {
+        let info = SymbolInfo::parse(ticker);
+        match info.exchange {
+            _ if info.asset_class == AssetClass::Crypto => true,
+            _ if info.asset_class == AssetClass::Forex => calendar::forex_is_open(self.clock.now()),
+            _ if info.asset_class == AssetClass::Commodity => {
+                calendar::commodity_is_open(self.clock.now())
+            }
+            Some(exchange) if Some(exchange) != self.primary() => {
+                self.exchange_wait(exchange, self.clock.now()) == 0
+            }
+            _ => primary_open,
+        }
+    }
+
+    fn record(&self, symbol: &str, quote: Quote) {
+        let price = self.smoother.apply(symbol, quote.price);
+        if let Some(adaptive) = &self.adaptive {
+            adaptive.observe(symbol, price);
+        }
+        let update = PriceUpdate {
+            symbol: symbol.to_string(),
+            price,
+            timestamp: self.clock.now(),
+            provider_timestamp: quote.timestamp,
+            day: quote.day,
+        };
+        let continued = self
+            .futures
+            .as_ref()
+            .map(|futures| futures.continue_with(&update))
+            .unwrap_or_default();
+        self.dispatch(update);
+        for (symbol, factor) in &continued.rolls {
+            for sink in self.active_sinks() {
+                sink.rebase(symbol, *factor, self.clock.now());
+            }
+        }
+        for update in continued.updates {
+            self.dispatch(update);
+        }
+    }
+
+    fn dispatch(&self, update: PriceUpdate) {
+        for sink in self.active_sinks() {
+            sink.record(&update);
+        }
+        self.events.publish(Event::Price(update));
+    }
+
+    /// Takes a price pushed by a stream the same way as a polled one.
+    pub fn ingest(&self, symbol: &str, quote: Quote) {
+        self.record(symbol, quote);
+        let now = self.clock.now();
+        for sink in self.active_sinks() {
+            sink.on_fetch(symbol, FetchOutcome::Success, now);
+        }
+    }
+
+    /// Symbols the last cycle considered, after sharding.
+    pub fn watched(&self) -> Vec<String> {
+        self.watched.lock().unwrap().clone()
+    }
+
+    #[instrument(skip_all)]
+    pub async fn cycle(&self, tickers: &Tickers, primary_open: bool, boost: f64) -> CycleSummary {
+        let started = self.clock.now();
+        let mut summary = CycleSummary::default();
+        let mut plan = priority::plan(tickers, &self.priorities, self.budget);
+        if let Some(adaptive) = &self.adaptive {
+            adaptive.adjust(&mut plan, self.budget);
+        }
+        for (symbol, interval) in plan.intervals.iter_mut() {
+            *interval /= boost;
+            if self.watches.contains(symbol) {
+                *interval = 0.;
+            }
+        }
+        let mut due: Vec<&String> = {
+            let next_due = self.next_due.lock().unwrap();
+            tickers
+                .get_tickers()
+                .iter()
+                .filter(|t| next_due.get(*t).is_none_or(|at| *at <= started))
+                .filter(|t| self.is_open(t, primary_open))
+                .filter(|t| self.stream.as_ref().is_none_or(|s| !s.is_live(t)))
+                .collect()
+        };
+        {
+            // Within a class, whatever has waited longest goes first, so
+            // symbols deferred below take turns.
+            let next_due = self.next_due.lock().unwrap();
+            due.sort_by_key(|t| {
+                (
+                    !self.watches.contains(t),
+                    self.priorities.of(t),
+                    next_due.get(*t).copied(),
+                )
+            });
+        }
+        // Symbols never fetched count as due from the start of the cycle.
+        let oldest = |symbols: &[&String]| {
+            let next_due = self.next_due.lock().unwrap();
+            symbols
+                .iter()
+                .map(|t| next_due.get(*t).copied().unwrap_or(started))
+                .min()
+        };
+        lag::behind(lag::INGEST, oldest(&due));
+        let mut deferred = vec![];
+        if let Some(budget) = priority::cycle_budget(due.len(), &self.priorities, self.budget) {
+            summary.deferred = due.len().saturating_sub(budget.per_cycle);
+            deferred = due.split_off(budget.per_cycle.min(due.len()));
+            metrics::update_deferred_symbols(summary.deferred);
+        }
+
+        let due: Vec<String> = due.into_iter().cloned().collect();
+        let plan = &plan;
+        // The longest any fetch waited past its due time, in milliseconds.
+        let behind = &AtomicI64::new(0);
+        // Batches start in priority order, up to `concurrency` at a time.
+        let mut fetches = futures_util::stream::iter(due.chunks(self.provider.batch_size().max(1)))
+            .map(|batch| async move {
+                // Each symbol costs a credit, batched or not.
+                self.acquire(batch.len() as u64).await;
+                for ticker in batch {
+                    let interval = plan.intervals.get(ticker).copied().unwrap_or_default();
+                    let interval = interval * (1. + self.jitter.sample());
+                    let now = self.clock.now();
+                    let was_due = self.next_due.lock().unwrap().insert(
+                        ticker.clone(),
+                        now + chrono::Duration::milliseconds((interval * 1000.) as i64),
+                    );
+                    behind.fetch_max(
+                        (now - was_due.unwrap_or(started)).num_milliseconds(),
+                        Ordering::Relaxed,
+                    );
+                }
+                // A symbol failing on its own counts as a failure just as
+                // the whole call failing does.
+                let prices: Vec<Result<Option<Quote>, ()>> =
+                    match retry("prices", || self.provider.fetch_prices(batch)).await {
+                        Ok(prices) => prices.into_iter().map(|p| p.map_err(|_| ())).collect(),
+                        Err(e) => {
+                            error!(error = ?e, symbols = batch.len(), "Failed to call API");
+                            vec![Err(()); batch.len()]
+                        }
+                    };
+                (batch, prices)
+            })
+            .buffer_unordered(self.concurrency);
+        while let Some((batch, prices)) = fetches.next().await {
+            summary.symbols += batch.len();
+            summary.credits += batch.len() as u64;
+            for (ticker, price) in batch.iter().zip(prices) {
+                let outcome = match price {
+                    Ok(Some(quote)) => {
+                        summary.successes += 1;
+                        self.record(ticker, quote);
+                        FetchOutcome::Success
+                    }
+                    Ok(None) => {
+                        summary.empty += 1;
+                        FetchOutcome::Empty
+                    }
+                    Err(()) => {
+                        summary.failures += 1;
+                        FetchOutcome::Failure
+                    }
+                };
+                let now = self.clock.now();
+                for sink in self.active_sinks() {
+                    sink.on_fetch(ticker, outcome, now);
+                }
+            }
+        }
+        lag::caught_up(lag::INGEST, behind.load(Ordering::Relaxed) as f64 / 1000.);
+        lag::behind(lag::INGEST, oldest(&deferred));
+        lag::backlog(lag::INGEST, deferred.len());
+        summary.duration_secs = (self.clock.now() - started).num_milliseconds() as f64 / 1000.;
+        summary
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use super::Engine;
+use crate::priority::{self, Plan};
+use crate::provider::{Provider, ProviderKind};
+use crate::Tickers;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WarmupConfig {
+    /// Fetches every symbol once at startup, from all sources at once,
+    /// before the first cycle.
+    pub enabled: bool,
+    /// Providers that share the first fetch with the main one. The
+    /// consensus sources when empty.
+    pub sources: Vec<ProviderKind>,
+    /// Most credits each of those may spend on it. The main provider
+    /// spends whatever its rate limits have left, without waiting.
+    pub source_credits: u64,
+    /// Longest startup waits for it. Symbols not fetched by then are left
+    /// to the first cycle.
+    pub timeout_secs: u64,
+}
+
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        WarmupConfig {
+            enabled: true,
+            sources: vec![],
+            source_credits: 100,
+            timeout_secs: 15,
+        }
+    }
+}
+
+/// What a warm-up fetched, by provider.
+#[derive(Debug, Clone, Default)]
+pub struct Warmup {
+    pub symbols: usize,
+    pub fetched: BTreeMap<String, usize>,
+}
+
+// Symbols left to fetch, shared by every source.
+type Queue = Mutex<VecDeque<String>>;
+
+impl Engine {
+    /// Fetches the last price of every symbol on `tickers`, the main
+    /// provider and `sources` each taking batches off one queue until it's
+    /// empty or their budget is spent, so the first prices are all in
+    /// after about one round trip instead of a whole cycle. Fetched symbols
+    /// aren't due again until their interval has passed.
+    pub async fn warm_up(
+        &self,
+        tickers: &Tickers,
+        sources: &[Arc<dyn Provider>],
+        config: &WarmupConfig,
+    ) -> Warmup {
+        let symbols = tickers.get_tickers();
+        let queue: Queue = Mutex::new(symbols.iter().cloned().collect());
+        let fetched: Mutex<BTreeMap<String, usize>> = Mutex::default();
+        let plan = priority::plan(tickers, &self.priorities, self.budget);
+        let started = self.clock.now();
+        let (queue, fetched, plan) = (&queue, &fetched, &plan);
+        let main = (0..self.concurrency).map(|_| (&*self.provider, None));
+        let others = sources
+            .iter()
+            .map(|source| (&**source, Some(config.source_credits)));
+        let workers = futures_util::future::join_all(
+            main.chain(others)
+                .map(|(provider, credits)| self.drain(provider, credits, queue, fetched, plan)),
+        );
+        tokio::select! {
+            _ = workers => {}
+            _ = self.clock.sleep(Duration::from_secs(config.timeout_secs)) => {
+                warn!(left = queue.lock().unwrap().len(), "Warm-up timed out, leaving the rest to the first cycle");
+            }
+        }
+
+        let warm = Warmup {
+            symbols: symbols.len(),
+            fetched: fetched.lock().unwrap().clone(),
+        };
+        let secs = (self.clock.now() - started).num_milliseconds() as f64 / 1000.;
+        info!(symbols = warm.symbols, fetched = ?warm.fetched, secs, "Warmed up");
+        warm
+    }
+
+    // Takes batches off `queue` for `provider` while `credits`, or the rate
+    // limiter for the main provider, allow, counting what it fetched.
+    async fn drain(
+        &self,
+        provider: &dyn Provider,
+        mut credits: Option<u64>,
+        queue: &Queue,
+        fetched: &Mutex<BTreeMap<String, usize>>,
+        plan: &Plan,
+    ) {
+        let size = provider.batch_size().max(1);
+        loop {
+            let batch: Vec<String> = {
+                let mut queue = queue.lock().unwrap();
+                let take = size
+                    .min(queue.len())
+                    .min(credits.unwrap_or(u64::MAX) as usize);
+                queue.drain(..take).collect()
+            };
+            if batch.is_empty() {
+                break;
+            }
+            let cost = batch.len() as u64;
+            let allowed = match &mut credits {
+                Some(credits) => {
+                    *credits -= cost;
+                    true
+                }
+                None => self.limiter.try_acquire(cost),
+            };
+            if !allowed {
+                queue.lock().unwrap().extend(batch);
+                break;
+            }
+            let quotes = match provider.fetch_prices(&batch).await {
+                Ok(quotes) => quotes,
+                Err(e) => {
+                    warn!(source = provider.name(), error = %e, "Warm-up fetch failed");
+                    continue;
+                }
+            };
+            for (symbol, quote) in batch.iter().zip(quotes) {
+                let Ok(Some(quote)) = quote else { continue };
+                self.ingest(symbol, quote);
+                let interval = plan.intervals.get(symbol).copied().unwrap_or_default();
+                let interval = interval * (1. + self.jitter.sample());
+                self.next_due.lock().unwrap().insert(
+                    symbol.clone(),
+                    self.clock.now() + chrono::Duration::milliseconds((interval * 1000.) as i64),
+                );
+                *fetched
+                    .lock()
+                    .unwrap()
+                    .entry(provider.name().to_string())
+                    .or_default() += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use utoipa::ToSchema;
+
+use crate::events::{Event, EventBus};
+use crate::metrics;
+
+/// Longest close watch accepted, so a typo can't burn the budget for days.
+pub const MAX_WATCH_MINUTES: i64 = 24 * 60;
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct Watch {
+    pub symbol: String,
+    pub until: DateTime<Utc>,
+}
+
+/// Symbols put under close watch around a catalyst. The engine polls them
+/// every cycle ahead of everything else, whether or not they're on the
+/// watchlist, and drops them back to their normal schedule once the window
+/// ends.
+#[derive(Default)]
+pub struct CloseWatch {
+    watches: Mutex<HashMap<String, DateTime<Utc>>>,
+    events: EventBus,
+}
+
+impl CloseWatch {
+    pub fn new(events: EventBus) -> Self {
+        CloseWatch {
+            watches: Mutex::new(HashMap::new()),
+            events,
+        }
+    }
+
+    /// Starts or extends a watch; a shorter window replaces a longer one.
+    pub fn start(&self, symbol: &str, until: DateTime<Utc>, now: DateTime<Utc>) {
+        self.insert(symbol, until);
+        info!(symbol, %until, "Watching symbol closely");
+        self.events.publish(Event::WatchStarted {
+            at: now,
+            symbol: symbol.to_string(),
+            until,
+        });
+    }
+
+    /// False when the symbol wasn't being watched.
+    pub fn stop(&self, symbol: &str, now: DateTime<Utc>) -> bool {
+        if !self.remove(symbol) {
+            return false;
+        }
+        info!(symbol, "Stopped close watch");
+        self.events.publish(Event::WatchStopped {
+            at: now,
+            symbol: symbol.to_string(),
+        });
+        true
+    }
+
+    // Restoring from the event log goes through these without republishing.
+    pub(crate) fn insert(&self, symbol: &str, until: DateTime<Utc>) {
+        let mut watches = self.watches.lock().unwrap();
+        watches.insert(symbol.to_string(), until);
+        metrics::update_close_watches(watches.len());
+    }
+
+    pub(crate) fn remove(&self, symbol: &str) -> bool {
+        let mut watches = self.watches.lock().unwrap();
+        let removed = watches.remove(symbol).is_some();
+        metrics::update_close_watches(watches.len());
+        removed
+    }
+
+    /// Drops watches whose window has passed and returns the rest.
+    pub fn expire(&self, now: DateTime<Utc>) -> Vec<String> {
+        let mut watches = self.watches.lock().unwrap();
+        watches.retain(|symbol, until| {
+            let active = *until > now;
+            if !active {
+                info!(symbol, "Close watch ended, back to the normal schedule");
+            }
+            active
+        });
+        metrics::update_close_watches(watches.len());
+        watches.keys().cloned().collect()
+    }
+
+    pub fn contains(&self, symbol: &str) -> bool {
+        self.watches.lock().unwrap().contains_key(symbol)
+    }
+
+    /// Watches still running at `now`, soonest to end first.
+    pub fn list(&self, now: DateTime<Utc>) -> Vec<Watch> {
+        let mut watches: Vec<Watch> = self
+            .watches
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, until)| **until > now)
+            .map(|(symbol, until)| Watch {
+                symbol: symbol.clone(),
+                until: *until,
+            })
+            .collect();
+        watches.sort_by_key(|w| w.until);
+        watches
+    }
+}
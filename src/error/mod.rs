@@ -0,0 +1,34 @@
+use thiserror::Error;
+
+use crate::bootstrap::BootstrapError;
+use crate::provider::retry;
+
+/// What the crate-level file and API helpers fail with.
+#[derive(Debug, Error)]
+pub enum FintekError {
+    #[error("provider request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("provider request failed after {attempts} attempts: {source}")]
+    Exhausted {
+        attempts: u32,
+        source: reqwest::Error,
+    },
+    #[error(transparent)]
+    Tickers(#[from] BootstrapError),
+    #[error("failed to write tickers: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to encode tickers: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl FintekError {
+    /// Whether the same call might work later.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            FintekError::Http(e) | FintekError::Exhausted { source: e, .. } => {
+                retry::is_transient(e)
+            }
+            _ => false,
+        }
+    }
+}
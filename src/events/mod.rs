@@ -0,0 +1,259 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, Receiver, Sender, UnboundedReceiver, UnboundedSender};
+use tracing::{error, info, warn};
+
+use crate::encryption::StorageKey;
+use crate::engine::apply::{ChangeOutcome, ChangeSource};
+use crate::lag;
+use crate::paper::OrderRequest;
+use crate::sink::PriceUpdate;
+use crate::strategy::Signal;
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EventsConfig {
+    /// Append every domain event here and rebuild state from it on start.
+    pub log: Option<PathBuf>,
+}
+
+/// Something that changed engine state. Replaying the stream in order
+/// rebuilds the same prices, positions and indicators.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Price(PriceUpdate),
+    OrderPlaced {
+        at: DateTime<Utc>,
+        request: OrderRequest,
+    },
+    OrderCancelled {
+        at: DateTime<Utc>,
+        id: u64,
+    },
+    Signal {
+        at: DateTime<Utc>,
+        signal: Signal,
+    },
+    WatchStarted {
+        at: DateTime<Utc>,
+        symbol: String,
+        until: DateTime<Utc>,
+    },
+    WatchStopped {
+        at: DateTime<Utc>,
+        symbol: String,
+    },
+    ConfigChange {
+        at: DateTime<Utc>,
+        source: ChangeSource,
+        outcome: ChangeOutcome,
+        symbols: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Envelope {
+    pub seq: u64,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+#[derive(Debug)]
+enum Subscriber {
+    Unbounded(UnboundedSender<Event>),
+    // Events that didn't fit since the last that did.
+    Bounded { tx: Sender<Event>, dropped: u64 },
+}
+
+/// Fans events out to every subscriber. Unbounded by default so the audit
+/// log never drops an event behind a slow writer.
+#[derive(Debug, Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+}
+
+impl EventBus {
+    pub fn subscribe(&self) -> UnboundedReceiver<Event> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.subscribers
+            .lock()
+            .unwrap()
+            .push(Subscriber::Unbounded(tx));
+        rx
+    }
+
+    /// A subscription holding at most `capacity` unread events, for readers
+    /// that may fall behind; events beyond it are dropped with a warning.
+    pub fn subscribe_bounded(&self, capacity: usize) -> Receiver<Event> {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        self.subscribers
+            .lock()
+            .unwrap()
+            .push(Subscriber::Bounded { tx, dropped: 0 });
+        rx
+    }
+
+    /// Ends every subscription once what was published is read, so writers
+    /// finish on shutdown.
+    pub fn close(&self) {
+        self.subscribers.lock().unwrap().clear();
+    }
+
+    pub fn publish(&self, event: Event) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain_mut(|subscriber| match subscriber {
+                Subscriber::Unbounded(tx) => tx.send(event.clone()).is_ok(),
+                Subscriber::Bounded { tx, dropped } => match tx.try_send(event.clone()) {
+                    Ok(()) => {
+                        if *dropped > 0 {
+                            warn!(
+                                dropped = *dropped,
+                                "Event reader caught up after dropping events"
+                            );
+                            *dropped = 0;
+                        }
+                        true
+                    }
+                    Err(TrySendError::Full(_)) => {
+                        if *dropped == 0 {
+                            warn!("Event reader is falling behind, dropping events");
+                        }
+                        *dropped += 1;
+                        true
+                    }
+                    Err(TrySendError::Closed(_)) => false,
+                },
+            });
+    }
+}
+
+/// The log at `path`, each line decrypted with `key` when given.
+pub async fn load(path: &Path, key: Option<&StorageKey>) -> std::io::Result<Vec<Envelope>> {
+    let data = match fs::read_to_string(path).await {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    let mut events = vec![];
+    let mut unreadable = 0;
+    for (number, line) in data.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_line(line, key) {
+            Ok(envelope) => events.push(envelope),
+            Err(e) => {
+                unreadable += 1;
+                warn!(line = number + 1, error = %e, "Skipping unreadable event");
+            }
+        }
+    }
+    // Nothing readable under a key is a wrong key, not a damaged line, and
+    // appending to it would mix two keys in one log.
+    if key.is_some() && events.is_empty() && unreadable > 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "no event decrypts with the storage key",
+        ));
+    }
+    Ok(events)
+}
+
+/// One line of the log, decrypted with `key` when given.
+pub fn parse_line(line: &str, key: Option<&StorageKey>) -> Result<Envelope, String> {
+    match key {
+        Some(key) => key
+            .open(line)
+            .map_err(|e| e.to_string())
+            .and_then(|data| serde_json::from_slice(&data).map_err(|e| e.to_string())),
+        None => serde_json::from_str(line).map_err(|e| e.to_string()),
+    }
+}
+
+/// Appends events from `events` to the log at `path`, numbering them after
+/// `last_seq`. With a `key` every line is encrypted on its own, so the log
+/// stays append-only.
+pub async fn write_log(
+    path: PathBuf,
+    mut events: UnboundedReceiver<Event>,
+    last_seq: u64,
+    key: Option<StorageKey>,
+) {
+    let mut file = match OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .await
+    {
+        Ok(file) => file,
+        Err(e) => {
+            error!(path = %path.display(), error = %e, "Failed to open event log");
+            return;
+        }
+    };
+    info!(path = %path.display(), "Writing event log");
+    let mut seq = last_seq;
+    while let Some(event) = events.recv().await {
+        seq += 1;
+        // Only prices carry the time they were fetched.
+        let fetched = match &event {
+            Event::Price(update) => Some(update.timestamp),
+            _ => None,
+        };
+        lag::behind(lag::EVENT_LOG, fetched);
+        let mut line =
+            serde_json::to_string(&Envelope { seq, event }).expect("Failed to serialize event");
+        if let Some(key) = &key {
+            line = key.seal(line.as_bytes());
+        }
+        line.push('\n');
+        if let Err(e) = file.write_all(line.as_bytes()).await {
+            error!(path = %path.display(), error = %e, "Failed to append event");
+        }
+        match fetched {
+            Some(at) => {
+                let lag = (Utc::now() - at).num_milliseconds() as f64 / 1000.;
+                lag::caught_up(lag::EVENT_LOG, lag);
+            }
+            None => lag::behind(lag::EVENT_LOG, None),
+        }
+        lag::backlog(lag::EVENT_LOG, events.len());
+    }
+    if let Err(e) = file.flush().await {
+        error!(path = %path.display(), error = %e, "Failed to flush event log");
+    }
+}
+
+/// Events held for a slow stdout reader before newer ones are dropped.
+pub const STDOUT_CAPACITY: usize = 4096;
+
+/// Writes events from `events` to stdout, one JSON object a line, for
+/// piping into other tools. Stops when the reader goes away.
+pub async fn write_stdout(mut events: Receiver<Event>) {
+    let mut stdout = tokio::io::stdout();
+    while let Some(event) = events.recv().await {
+        let mut line = serde_json::to_string(&event).expect("Failed to serialize event");
+        line.push('\n');
+        // Flushed a line at a time, so a pipeline sees each event as it
+        // happens.
+        let written = match stdout.write_all(line.as_bytes()).await {
+            Ok(()) => stdout.flush().await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            warn!(error = %e, "Stopped writing events to stdout");
+            return;
+        }
+    }
+}
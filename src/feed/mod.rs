@@ -0,0 +1,156 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::clock::Clock;
+use crate::events::Event;
+use crate::notify::{Notification, Notifier, NotifyError, Urgency};
+
+const CAPACITY: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    Alert,
+    Signal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedEntry {
+    /// Counts up from 1 since start, telling apart entries of the same
+    /// time.
+    pub seq: u64,
+    pub at: DateTime<Utc>,
+    pub kind: EntryKind,
+    pub symbols: Vec<String>,
+    pub title: String,
+    pub body: String,
+}
+
+/// The most recent alerts and strategy signals, served as an Atom feed so
+/// a feed reader can follow them without any push notifier configured.
+pub struct AlertFeed {
+    clock: Arc<dyn Clock>,
+    entries: Mutex<VecDeque<FeedEntry>>,
+    // Last entry's `seq`.
+    seq: Mutex<u64>,
+}
+
+impl AlertFeed {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        AlertFeed {
+            clock,
+            entries: Mutex::new(VecDeque::new()),
+            seq: Mutex::new(0),
+        }
+    }
+
+    fn push(&self, mut entry: FeedEntry) {
+        let mut seq = self.seq.lock().unwrap();
+        *seq += 1;
+        entry.seq = *seq;
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Newest first.
+    pub fn entries(&self) -> Vec<FeedEntry> {
+        self.entries.lock().unwrap().iter().rev().cloned().collect()
+    }
+
+    /// Records signals published on the event bus.
+    pub async fn follow(self: Arc<Self>, mut rx: UnboundedReceiver<Event>) {
+        while let Some(event) = rx.recv().await {
+            if let Event::Signal { at, signal } = event {
+                self.push(FeedEntry {
+                    seq: 0,
+                    at,
+                    kind: EntryKind::Signal,
+                    symbols: vec![signal.symbol.clone()],
+                    title: format!("{} {} = {}", signal.symbol, signal.name, signal.value),
+                    body: format!("Signal from {}", signal.source),
+                });
+            }
+        }
+    }
+
+    /// Only entries on one of `symbols` when given, as for namespaced
+    /// callers.
+    pub fn to_atom(&self, symbols: Option<&BTreeSet<String>>) -> String {
+        let mut entries = self.entries();
+        if let Some(symbols) = symbols {
+            entries.retain(|e| e.symbols.iter().any(|s| symbols.contains(s)));
+        }
+        let updated = entries.first().map_or_else(|| self.clock.now(), |e| e.at);
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+        xml.push_str("  <title>fintek alerts</title>\n");
+        xml.push_str("  <id>urn:fintek:alerts</id>\n");
+        xml.push_str("  <author><name>fintek</name></author>\n");
+        xml.push_str(&format!("  <updated>{}</updated>\n", updated.to_rfc3339()));
+        for entry in &entries {
+            let kind = match entry.kind {
+                EntryKind::Alert => "alert",
+                EntryKind::Signal => "signal",
+            };
+            xml.push_str("  <entry>\n");
+            xml.push_str(&format!("    <title>{}</title>\n", escape(&entry.title)));
+            xml.push_str(&format!(
+                "    <id>urn:fintek:{}:{}:{}:{}</id>\n",
+                kind,
+                escape(&entry.symbols.join(",")),
+                entry.at.timestamp_nanos_opt().unwrap_or_default(),
+                entry.seq
+            ));
+            xml.push_str(&format!(
+                "    <updated>{}</updated>\n",
+                entry.at.to_rfc3339()
+            ));
+            xml.push_str(&format!("    <category term=\"{}\"/>\n", kind));
+            xml.push_str(&format!(
+                "    <content type=\"text\">{}</content>\n",
+                escape(&entry.body)
+            ));
+            xml.push_str("  </entry>\n");
+        }
+        xml.push_str("</feed>\n");
+        xml
+    }
+}
+
+#[async_trait]
+impl Notifier for AlertFeed {
+    fn name(&self) -> &str {
+        "feed"
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let mut title = notification.title.clone();
+        if notification.urgency == Urgency::High {
+            title.insert_str(0, "[urgent] ");
+        }
+        self.push(FeedEntry {
+            seq: 0,
+            at: self.clock.now(),
+            kind: EntryKind::Alert,
+            symbols: notification.symbols.clone(),
+            title,
+            body: notification.body.clone(),
+        });
+        Ok(())
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,274 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::metrics;
+use crate::sink::PriceUpdate;
+
+pub const FUTURES_FILE: &str = "futures.json";
+
+/// When a continuous future moves from the front contract to the next.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RollRule {
+    /// `roll_days` before the front contract expires.
+    #[default]
+    Calendar,
+    /// Once the next contract trades more, or at the calendar roll.
+    Volume,
+    /// Once the next contract has more open, or at the calendar roll. Without
+    /// open interest in the quotes, only the calendar rolls.
+    OpenInterest,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FuturesContract {
+    pub symbol: String,
+    pub expiry: NaiveDate,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ContinuousFuture {
+    /// What the stitched series is recorded as, e.g. `CL1!`.
+    pub symbol: String,
+    /// Oldest expiry first.
+    pub contracts: Vec<FuturesContract>,
+    #[serde(default)]
+    pub roll: RollRule,
+    #[serde(default = "default_roll_days")]
+    pub roll_days: i64,
+}
+
+fn default_roll_days() -> i64 {
+    5
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FuturesConfig {
+    pub continuous: Vec<ContinuousFuture>,
+}
+
+// The latest figures of one contract.
+#[derive(Debug, Clone, Copy, Default)]
+struct Leg {
+    price: Option<f64>,
+    volume: Option<f64>,
+    open_interest: Option<f64>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    legs: HashMap<String, Leg>,
+    // Per continuous symbol, the contract it follows.
+    active: BTreeMap<String, String>,
+    // Per continuous symbol, the last day rolls were considered.
+    checked: HashMap<String, NaiveDate>,
+}
+
+/// What one contract price means for the continuous futures.
+#[derive(Debug, Default)]
+pub struct Continued {
+    /// Continuous symbols that rolled, with what their earlier prices are
+    /// multiplied by to join the new contract.
+    pub rolls: Vec<(String, f64)>,
+    /// The price under each continuous symbol following the contract.
+    pub updates: Vec<PriceUpdate>,
+}
+
+/// Stitches contract prices into continuous series, one per configured
+/// future. Rolls happen at the first price of a UTC day, so every day of a
+/// series is on one contract, and are saved so a restart keeps following
+/// the same contract.
+pub struct Futures {
+    continuous: Vec<ContinuousFuture>,
+    path: PathBuf,
+    state: Mutex<State>,
+}
+
+impl Futures {
+    /// `None` without continuous futures.
+    pub fn new(config: &FuturesConfig, state_dir: &Path) -> Option<Self> {
+        if config.continuous.is_empty() {
+            return None;
+        }
+        let mut continuous = config.continuous.clone();
+        for future in &mut continuous {
+            future.contracts.sort_by_key(|c| c.expiry);
+        }
+        let path = state_dir.join(FUTURES_FILE);
+        let active = match std::fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+                warn!(path = %path.display(), error = %e, "Ignoring corrupt futures state");
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Some(Futures {
+            continuous,
+            path,
+            state: Mutex::new(State {
+                active,
+                ..State::default()
+            }),
+        })
+    }
+
+    /// Every contract, for the engine to poll.
+    pub fn symbols(&self) -> Vec<String> {
+        self.continuous
+            .iter()
+            .flat_map(|f| f.contracts.iter().map(|c| c.symbol.clone()))
+            .collect()
+    }
+
+    /// The contract each continuous symbol follows.
+    pub fn active(&self) -> BTreeMap<String, String> {
+        self.state.lock().unwrap().active.clone()
+    }
+
+    fn observe(&self, state: &mut State, update: &PriceUpdate) {
+        let leg = state.legs.entry(update.symbol.clone()).or_default();
+        leg.price = Some(update.price);
+        if let Some(day) = &update.day {
+            leg.volume = day.volume.or(leg.volume);
+            leg.open_interest = day.open_interest.or(leg.open_interest);
+        }
+    }
+
+    /// Takes a restored contract price without rolling.
+    pub fn replay(&self, update: &PriceUpdate) {
+        self.observe(&mut self.state.lock().unwrap(), update);
+    }
+
+    pub fn continue_with(&self, update: &PriceUpdate) -> Continued {
+        let mut continued = Continued::default();
+        let today = update.timestamp.date_naive();
+        let mut state = self.state.lock().unwrap();
+        self.observe(&mut state, update);
+        let before = state.active.clone();
+        for future in &self.continuous {
+            if !future.contracts.iter().any(|c| c.symbol == update.symbol) {
+                continue;
+            }
+            if state.checked.get(&future.symbol) != Some(&today) {
+                let factor = match roll(&mut state, future, today) {
+                    Roll::Waiting => continue,
+                    Roll::Stay => None,
+                    Roll::Rolled(factor) => Some(factor),
+                };
+                state.checked.insert(future.symbol.clone(), today);
+                if let Some(factor) = factor {
+                    info!(
+                        symbol = future.symbol,
+                        contract = state.active.get(&future.symbol),
+                        factor,
+                        "Rolled continuous future"
+                    );
+                    metrics::update_futures_roll(&future.symbol);
+                    continued.rolls.push((future.symbol.clone(), factor));
+                }
+                for contract in &future.contracts {
+                    let active = state.active.get(&future.symbol) == Some(&contract.symbol);
+                    metrics::update_futures_active(&future.symbol, &contract.symbol, active);
+                }
+            }
+            if state.active.get(&future.symbol) == Some(&update.symbol) {
+                continued.updates.push(PriceUpdate {
+                    symbol: future.symbol.clone(),
+                    ..update.clone()
+                });
+            }
+        }
+        if state.active != before {
+            if let Err(e) = save(&self.path, &state.active) {
+                error!(error = %e, "Failed to save futures state");
+            }
+        }
+        continued
+    }
+}
+
+enum Roll {
+    Stay,
+    /// With the combined price factor.
+    Rolled(f64),
+    /// Due, but a leg isn't priced yet.
+    Waiting,
+}
+
+// Moves `future` past every contract due to roll on `today`. The first time
+// a future is seen, or when its contract is no longer configured, it starts
+// on the nearest contract not yet due, without a factor. A roll waits for
+// both legs' prices unless the front has expired.
+fn roll(state: &mut State, future: &ContinuousFuture, today: NaiveDate) -> Roll {
+    let due =
+        |contract: &FuturesContract| today >= contract.expiry - Duration::days(future.roll_days);
+    let active = state.active.get(&future.symbol);
+    let Some(mut index) = active.and_then(|a| future.contracts.iter().position(|c| c.symbol == *a))
+    else {
+        let start = future
+            .contracts
+            .iter()
+            .find(|c| !due(c))
+            .or(future.contracts.last());
+        if let Some(start) = start {
+            state
+                .active
+                .insert(future.symbol.clone(), start.symbol.clone());
+        }
+        return Roll::Stay;
+    };
+    let start = index;
+    let mut factor = 1.;
+    while let Some(next) = future.contracts.get(index + 1) {
+        let front = &future.contracts[index];
+        let (a, b) = (
+            state.legs.get(&front.symbol).copied().unwrap_or_default(),
+            state.legs.get(&next.symbol).copied().unwrap_or_default(),
+        );
+        let overtaken =
+            |a: Option<f64>, b: Option<f64>| matches!((a, b), (Some(a), Some(b)) if b > a);
+        let roll = due(front)
+            || match future.roll {
+                RollRule::Calendar => false,
+                RollRule::Volume => overtaken(a.volume, b.volume),
+                RollRule::OpenInterest => overtaken(a.open_interest, b.open_interest),
+            };
+        if !roll {
+            break;
+        }
+        match (a.price, b.price) {
+            (Some(a), Some(b)) if a > 0. => factor *= b / a,
+            _ if today <= front.expiry => return Roll::Waiting,
+            _ => warn!(
+                symbol = future.symbol,
+                from = front.symbol,
+                to = next.symbol,
+                "Rolling off an expired contract without both prices"
+            ),
+        }
+        index += 1;
+    }
+    if index == start {
+        return Roll::Stay;
+    }
+    state.active.insert(
+        future.symbol.clone(),
+        future.contracts[index].symbol.clone(),
+    );
+    Roll::Rolled(factor)
+}
+
+fn save(path: &Path, active: &BTreeMap<String, String>) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(active)?)?;
+    std::fs::rename(&tmp, path)
+}
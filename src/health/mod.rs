@@ -0,0 +1,86 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::clock::Clock;
+use crate::sink::{FetchOutcome, PriceUpdate, Sink};
+
+#[derive(Debug, Default)]
+struct Fetches {
+    last_success: Option<DateTime<Utc>>,
+    last_failed: bool,
+}
+
+/// What `/readyz` reports.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Readiness {
+    pub ready: bool,
+    /// False when the last fetch failed.
+    pub provider_reachable: bool,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_success_age_secs: Option<i64>,
+    pub shutting_down: bool,
+}
+
+/// Tracks fetch outcomes for the readiness probe. Ready unless shutting
+/// down or fetches have failed for longer than the allowed staleness; no
+/// fetches at all, as overnight, doesn't count against it.
+pub struct Health {
+    clock: Arc<dyn Clock>,
+    started_at: DateTime<Utc>,
+    max_stale: Duration,
+    fetches: Mutex<Fetches>,
+    shutting_down: AtomicBool,
+}
+
+impl Health {
+    pub fn new(clock: Arc<dyn Clock>, max_stale_secs: u64) -> Self {
+        Health {
+            started_at: clock.now(),
+            clock,
+            max_stale: Duration::seconds(max_stale_secs as i64),
+            fetches: Mutex::new(Fetches::default()),
+            shutting_down: AtomicBool::new(false),
+        }
+    }
+
+    /// From now on the probe reports not ready, so traffic drains.
+    pub fn shutting_down(&self) {
+        self.shutting_down.store(true, Ordering::Relaxed);
+    }
+
+    pub fn readiness(&self) -> Readiness {
+        let now = self.clock.now();
+        let fetches = self.fetches.lock().unwrap();
+        let shutting_down = self.shutting_down.load(Ordering::Relaxed);
+        // Failing since the last success, or since start without one.
+        let failing_for = fetches
+            .last_failed
+            .then(|| now - fetches.last_success.unwrap_or(self.started_at));
+        Readiness {
+            ready: !shutting_down && failing_for.is_none_or(|age| age <= self.max_stale),
+            provider_reachable: !fetches.last_failed,
+            last_success_at: fetches.last_success,
+            last_success_age_secs: fetches.last_success.map(|at| (now - at).num_seconds()),
+            shutting_down,
+        }
+    }
+}
+
+impl Sink for Health {
+    fn record(&self, _update: &PriceUpdate) {}
+
+    fn on_fetch(&self, _symbol: &str, outcome: FetchOutcome, at: DateTime<Utc>) {
+        let mut fetches = self.fetches.lock().unwrap();
+        // An empty answer still means the provider is up.
+        fetches.last_failed = outcome == FetchOutcome::Failure;
+        if !fetches.last_failed {
+            fetches.last_success = Some(at);
+        }
+    }
+
+    fn replay(&self, _update: &PriceUpdate) {}
+}
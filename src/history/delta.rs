@@ -0,0 +1,245 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::PricePoint;
+
+/// Extension of delta-encoded tick files, next to the `.ndjson` ones.
+pub const EXTENSION: &str = "ticks";
+
+const MAGIC: u8 = 0xd7;
+// Prices that no decimal scale holds exactly are stored as their bits.
+const RAW: u8 = u8::MAX;
+const MAX_DECIMALS: u8 = 9;
+// Digits of a second timestamps are kept to: seconds, then milli-, micro-
+// and nanoseconds.
+const UNITS: [u8; 4] = [0, 3, 6, 9];
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DeltaError {
+    #[error("not a tick block at byte {0}")]
+    Magic(usize),
+    #[error("tick block cut short at byte {0}")]
+    Truncated(usize),
+    #[error("tick block has an unknown scale at byte {0}")]
+    Scale(usize),
+    #[error("timestamp out of range at byte {0}")]
+    Timestamp(usize),
+}
+
+/// Appends `points` to `out` as one block: a header, then each point's
+/// timestamp and price as the variable-length difference from the one
+/// before.
+///
+/// Timestamps are counted in the coarsest unit that keeps all of them
+/// exact, and prices in the fewest decimals that do, so ticks a second
+/// apart quoted to the cent take 6 or 7 bytes each against some 60 for a
+/// JSON line. Decoding gives back exactly the points encoded.
+pub fn encode(points: &[PricePoint], out: &mut Vec<u8>) {
+    let unit = UNITS
+        .into_iter()
+        .find(|&digits| {
+            let step = 10u32.pow(9 - digits as u32);
+            points
+                .iter()
+                .all(|p| p.timestamp.timestamp_subsec_nanos() % step == 0)
+        })
+        .unwrap_or(9);
+    let decimals = (0..=MAX_DECIMALS)
+        .find(|&decimals| points.iter().all(|p| scaled(p.price, decimals).is_some()))
+        .unwrap_or(RAW);
+
+    out.push(MAGIC);
+    put_varint(out, points.len() as u128);
+    out.push(unit);
+    out.push(decimals);
+    let (mut time, mut price) = (0i128, 0i64);
+    for point in points {
+        let t = ticks(point.timestamp, unit);
+        put_varint(out, zigzag(t - time));
+        time = t;
+        match decimals {
+            RAW => out.extend_from_slice(&point.price.to_bits().to_le_bytes()),
+            decimals => {
+                let p = scaled(point.price, decimals).expect("scale checked above");
+                put_varint(out, zigzag(p as i128 - price as i128));
+                price = p;
+            }
+        }
+    }
+}
+
+/// Every point of every block in `data`, oldest block first.
+pub fn decode(data: &[u8]) -> Result<Vec<PricePoint>, DeltaError> {
+    let mut points = vec![];
+    for block in Blocks::new(data) {
+        points.extend(block?);
+    }
+    Ok(points)
+}
+
+/// The blocks of `data` one at a time, ending at the first it can't read.
+pub struct Blocks<'a> {
+    data: &'a [u8],
+    at: usize,
+    failed: bool,
+}
+
+impl<'a> Blocks<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Blocks {
+            data,
+            at: 0,
+            failed: false,
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, DeltaError> {
+        let byte = *self
+            .data
+            .get(self.at)
+            .ok_or(DeltaError::Truncated(self.at))?;
+        self.at += 1;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<u128, DeltaError> {
+        let mut value = 0u128;
+        for shift in (0..128).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u128) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(DeltaError::Truncated(self.at))
+    }
+
+    fn block(&mut self) -> Result<Vec<PricePoint>, DeltaError> {
+        let start = self.at;
+        if self.byte()? != MAGIC {
+            return Err(DeltaError::Magic(start));
+        }
+        let count = self.varint()?;
+        let unit = self.byte()?;
+        let decimals = self.byte()?;
+        if !UNITS.contains(&unit) || (decimals > MAX_DECIMALS && decimals != RAW) {
+            return Err(DeltaError::Scale(start));
+        }
+        // Each point takes at least two bytes.
+        let remaining = (self.data.len() - self.at) as u128;
+        if count > remaining / 2 {
+            return Err(DeltaError::Truncated(self.data.len()));
+        }
+        let (mut time, mut price) = (0i128, 0i128);
+        let mut points = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            time = time
+                .checked_add(unzigzag(self.varint()?))
+                .ok_or(DeltaError::Timestamp(self.at))?;
+            let timestamp = timestamp(time, unit).ok_or(DeltaError::Timestamp(self.at))?;
+            let price = match decimals {
+                RAW => {
+                    let bytes = self
+                        .data
+                        .get(self.at..self.at + 8)
+                        .ok_or(DeltaError::Truncated(self.data.len()))?;
+                    self.at += 8;
+                    f64::from_bits(u64::from_le_bytes(bytes.try_into().unwrap()))
+                }
+                decimals => {
+                    price = price
+                        .checked_add(unzigzag(self.varint()?))
+                        .ok_or(DeltaError::Scale(start))?;
+                    price as f64 / 10f64.powi(decimals as i32)
+                }
+            };
+            points.push(PricePoint { timestamp, price });
+        }
+        Ok(points)
+    }
+}
+
+impl Iterator for Blocks<'_> {
+    type Item = Result<Vec<PricePoint>, DeltaError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.at >= self.data.len() {
+            return None;
+        }
+        let block = self.block();
+        self.failed = block.is_err();
+        Some(block)
+    }
+}
+
+/// Appends `points` to the file at `path` as one block, creating both.
+pub fn append(path: &Path, points: &[PricePoint]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut block = vec![];
+    encode(points, &mut block);
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(&block)
+}
+
+/// The points in the file at `path`. A block cut short by a crash, and
+/// anything after it, is skipped with a warning.
+pub fn read(path: &Path) -> Vec<PricePoint> {
+    let Ok(data) = std::fs::read(path) else {
+        return vec![];
+    };
+    let mut points = vec![];
+    for block in Blocks::new(&data) {
+        match block {
+            Ok(block) => points.extend(block),
+            Err(e) => warn!(path = %path.display(), error = %e, "Skipping unreadable ticks"),
+        }
+    }
+    points
+}
+
+// `price` as a whole number of 10^-decimals, when that's exact.
+fn scaled(price: f64, decimals: u8) -> Option<i64> {
+    let factor = 10f64.powi(decimals as i32);
+    let scaled = (price * factor).round();
+    (price.is_finite() && scaled.abs() < (1u64 << 53) as f64 && scaled / factor == price)
+        .then_some(scaled as i64)
+}
+
+fn ticks(at: DateTime<Utc>, unit: u8) -> i128 {
+    let per_second = 10i128.pow(unit as u32);
+    let nanos = at.timestamp_subsec_nanos() as i128 / 10i128.pow(9 - unit as u32);
+    at.timestamp() as i128 * per_second + nanos
+}
+
+fn timestamp(ticks: i128, unit: u8) -> Option<DateTime<Utc>> {
+    let per_second = 10i128.pow(unit as u32);
+    let secs = i64::try_from(ticks.div_euclid(per_second)).ok()?;
+    let nanos = ticks.rem_euclid(per_second) * 10i128.pow(9 - unit as u32);
+    DateTime::from_timestamp(secs, nanos as u32)
+}
+
+fn zigzag(n: i128) -> u128 {
+    ((n << 1) ^ (n >> 127)) as u128
+}
+
+fn unzigzag(n: u128) -> i128 {
+    (n >> 1) as i128 ^ -((n & 1) as i128)
+}
+
+fn put_varint(out: &mut Vec<u8>, mut n: u128) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
@@ -0,0 +1,537 @@
+pub mod delta;
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, DurationRound, NaiveDate, TimeDelta, Utc};
+use reqwest::Error;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, instrument, trace, warn};
+use utoipa::ToSchema;
+
+use crate::clock::Clock;
+use crate::metrics;
+use crate::provider::endpoints::{self, Endpoint};
+use crate::returns::Conventions;
+use crate::sink::{PriceUpdate, Sink};
+use crate::{read_tickers, AssetClass, PricePoint};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// In-memory budget per symbol. Past it the oldest half is moved to disk.
+    pub max_bytes_per_symbol: usize,
+    /// How often stock splits are refetched for split-adjusted queries.
+    pub split_refresh_hours: u64,
+    /// Ranges starting within this are answered from raw ticks.
+    pub tick_retention_hours: i64,
+    /// Ranges starting within this, but past the tick retention, from
+    /// one-minute closes. Anything older comes from daily closes.
+    pub minute_retention_days: i64,
+    /// How ticks moved to disk are written. Files of either format are
+    /// read, so it can be changed on a running history.
+    pub tick_format: TickFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TickFormat {
+    /// A JSON line per tick, in `<symbol>.ndjson`.
+    #[default]
+    Ndjson,
+    /// Blocks of timestamp and price differences in `<symbol>.ticks`,
+    /// several times smaller, for symbols polled every few seconds. See
+    /// [`delta::encode`].
+    Delta,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        HistoryConfig {
+            max_bytes_per_symbol: 1 << 20,
+            split_refresh_hours: 24,
+            tick_retention_hours: 24,
+            minute_retention_days: 7,
+            tick_format: TickFormat::Ndjson,
+        }
+    }
+}
+
+/// Which price series a query reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Adjustment {
+    /// Prices as they were quoted. Right for fills, P&L and anything that
+    /// compares against what was actually traded at the time.
+    #[default]
+    Raw,
+    /// Earlier prices scaled by every later split, so a split doesn't show
+    /// up as a crash. Right for returns, indicators and charts.
+    Split,
+}
+
+/// How finely a query's points are spaced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Resolution {
+    /// The finest series still retained for the start of the range.
+    #[default]
+    Auto,
+    Tick,
+    Minute,
+    Day,
+}
+
+impl Resolution {
+    const ROLLUPS: [Resolution; 2] = [Resolution::Minute, Resolution::Day];
+
+    fn dir(self) -> &'static str {
+        match self {
+            Resolution::Minute => "1m",
+            Resolution::Day => "1d",
+            Resolution::Auto | Resolution::Tick => "",
+        }
+    }
+}
+
+/// Points at one resolution, each rollup point being the last price of the
+/// bucket it's timestamped with the start of.
+#[derive(Debug, Clone, Serialize)]
+pub struct Series {
+    pub resolution: Resolution,
+    pub points: Vec<PricePoint>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Split {
+    /// First day trading at the new share count.
+    pub date: NaiveDate,
+    /// What earlier prices are multiplied by, 0.25 for a 4-for-1 split.
+    pub factor: f64,
+}
+
+#[instrument(skip(api_key))]
+pub async fn fetch_splits(
+    base_url: &str,
+    symbol: &str,
+    api_key: &str,
+) -> Result<Vec<Split>, Error> {
+    let url = endpoints::url::<endpoints::Splits>(base_url, &format!("symbol={}", symbol), api_key);
+    let response = reqwest::get(&url).await?;
+    let data = response.text().await?;
+    let records = match endpoints::parse::<<endpoints::Splits as Endpoint>::Response>(&data) {
+        Ok(Ok(response)) => response.splits,
+        Ok(Err(error)) => {
+            warn!(symbol, code = error.code, message = %error.message, "Splits request failed");
+            return Ok(vec![]);
+        }
+        Err(e) => {
+            warn!(symbol, error = %e, "Unexpected splits response");
+            return Ok(vec![]);
+        }
+    };
+    let mut splits: Vec<Split> = records
+        .into_iter()
+        .filter(|s| s.from_factor > 0. && s.to_factor > 0.)
+        .map(|s| Split {
+            date: s.date,
+            factor: s.to_factor / s.from_factor,
+        })
+        .collect();
+    splits.sort_by_key(|s| s.date);
+    trace!(symbol, count = splits.len(), "Fetched splits");
+    Ok(splits)
+}
+
+const POINT_BYTES: usize = size_of::<PricePoint>();
+
+/// Per-symbol price history, bounded in memory. Older windows are appended
+/// to `<state_dir>/history/<symbol>.ndjson` and read back on demand.
+///
+/// Only raw prices are stored, next to each symbol's splits in
+/// `splits.json`; the split-adjusted series is derived when read, so a new
+/// split never means rewriting what's already on disk.
+///
+/// One-minute and daily closes are rolled up as ticks arrive and appended
+/// under `1m/` and `1d/` as each bucket closes, so long ranges don't read
+/// every tick.
+pub struct History {
+    config: HistoryConfig,
+    dir: PathBuf,
+    capacity: usize,
+    series: Mutex<HashMap<String, VecDeque<PricePoint>>>,
+    /// The open minute and day bucket of each symbol.
+    open_buckets: Mutex<HashMap<String, [Option<PricePoint>; 2]>>,
+    splits: RwLock<HashMap<String, Vec<Split>>>,
+    conventions: Conventions,
+}
+
+fn file_name(symbol: &str) -> String {
+    let safe: String = symbol
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}.ndjson", safe)
+}
+
+impl History {
+    pub fn new(config: &HistoryConfig, state_dir: &Path) -> Self {
+        let dir = state_dir.join("history");
+        let splits = std::fs::read_to_string(dir.join("splits.json"))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        History {
+            config: config.clone(),
+            capacity: (config.max_bytes_per_symbol / POINT_BYTES).max(2),
+            series: Mutex::new(HashMap::new()),
+            open_buckets: Mutex::new(HashMap::new()),
+            splits: RwLock::new(splits),
+            conventions: Conventions::default(),
+            dir,
+        }
+    }
+
+    /// Daily closes follow these trading days rather than UTC midnight.
+    pub fn with_conventions(mut self, conventions: Conventions) -> Self {
+        self.conventions = conventions;
+        self
+    }
+
+    pub fn conventions(&self) -> Conventions {
+        self.conventions
+    }
+
+    /// Adds daily closes for days the stored series is missing, keeping
+    /// the file in order. Returns how many were new.
+    pub fn fill_days(&self, symbol: &str, closes: &[PricePoint]) -> std::io::Result<usize> {
+        // Held throughout, so a day closing meanwhile isn't lost.
+        let open_buckets = self.open_buckets.lock().unwrap();
+        let path = self.path(symbol, Resolution::Day);
+        let mut points = read(&path, |_| true);
+        let known: Vec<NaiveDate> = points
+            .iter()
+            .map(|p| self.conventions.day_of(p.timestamp))
+            .collect();
+        let open = open_buckets.get(symbol).and_then(|b| b[1]);
+        let open = open.map(|p| self.conventions.day_of(p.timestamp));
+        let mut added = 0;
+        for close in closes {
+            let day = self.conventions.day_of(close.timestamp);
+            if known.contains(&day) || open == Some(day) {
+                continue;
+            }
+            points.push(PricePoint {
+                timestamp: self.conventions.day_start(day),
+                price: close.price,
+            });
+            added += 1;
+        }
+        if added == 0 {
+            return Ok(0);
+        }
+        points.sort_by_key(|p| p.timestamp);
+        let tmp = path.with_extension("ndjson.tmp");
+        let _ = std::fs::remove_file(&tmp);
+        self.spill(&tmp, &points)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(added)
+    }
+
+    // Start of the rollup bucket `at` falls in, `None` for raw ticks.
+    fn bucket(&self, resolution: Resolution, at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match resolution {
+            Resolution::Minute => at.duration_trunc(TimeDelta::minutes(1)).ok(),
+            Resolution::Day => Some(self.conventions.truncate(at)),
+            Resolution::Auto | Resolution::Tick => None,
+        }
+    }
+
+    pub fn splits(&self, symbol: &str) -> Vec<Split> {
+        self.splits
+            .read()
+            .unwrap()
+            .get(symbol)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Replaces the symbol's known splits and saves them with the history.
+    pub fn set_splits(&self, symbol: &str, splits: Vec<Split>) {
+        let mut all = self.splits.write().unwrap();
+        if all.get(symbol) == Some(&splits) {
+            return;
+        }
+        all.insert(symbol.to_string(), splits);
+        let saved = std::fs::create_dir_all(&self.dir).and_then(|()| {
+            let data = serde_json::to_string_pretty(&*all)?;
+            std::fs::write(self.dir.join("splits.json"), data)
+        });
+        if let Err(e) = saved {
+            error!(error = %e, "Failed to save splits");
+        }
+    }
+
+    pub async fn refresh_splits(&self, base_url: &str, symbols: &[String], api_key: &str) {
+        for symbol in symbols {
+            if AssetClass::of(symbol) != AssetClass::Stock {
+                continue;
+            }
+            match fetch_splits(base_url, symbol, api_key).await {
+                Ok(splits) => self.set_splits(symbol, splits),
+                Err(e) => error!(symbol, error = ?e, "Failed to fetch splits"),
+            }
+        }
+        info!(symbols = symbols.len(), "Refreshed splits");
+    }
+
+    pub async fn run_splits(
+        self: Arc<Self>,
+        base_url: String,
+        api_key: String,
+        clock: Arc<dyn Clock>,
+    ) {
+        loop {
+            match read_tickers().await {
+                Ok(tickers) => {
+                    self.refresh_splits(&base_url, tickers.get_tickers(), &api_key)
+                        .await
+                }
+                Err(e) => warn!(error = %e, "Failed to read tickers, skipping split refresh"),
+            }
+            clock
+                .sleep(Duration::from_secs(self.config.split_refresh_hours * 3600))
+                .await;
+        }
+    }
+
+    fn path(&self, symbol: &str, resolution: Resolution) -> PathBuf {
+        self.dir.join(resolution.dir()).join(file_name(symbol))
+    }
+
+    fn spill(&self, path: &Path, points: &[PricePoint]) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let mut writer = BufWriter::new(file);
+        for point in points {
+            serde_json::to_writer(&mut writer, point)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()
+    }
+
+    // Closes a bucket once a tick lands past it, writing its close out.
+    fn roll_up(&self, update: &PriceUpdate, spill: bool) {
+        let mut open_buckets = self.open_buckets.lock().unwrap();
+        let buckets = open_buckets.entry(update.symbol.clone()).or_default();
+        for (bucket, resolution) in buckets.iter_mut().zip(Resolution::ROLLUPS) {
+            let Some(start) = self.bucket(resolution, update.timestamp) else {
+                continue;
+            };
+            let point = PricePoint {
+                timestamp: start,
+                price: update.price,
+            };
+            match bucket.replace(point) {
+                Some(closed) if closed.timestamp < start && spill => {
+                    if let Err(e) = self.spill(&self.path(&update.symbol, resolution), &[closed]) {
+                        error!(symbol = %update.symbol, error = %e, "Failed to write history rollup")
+                    }
+                }
+                // Late ticks don't reopen a closed bucket.
+                Some(closed) if closed.timestamp > start => *bucket = Some(closed),
+                _ => {}
+            }
+        }
+    }
+
+    fn push(&self, update: &PriceUpdate, spill: bool) {
+        self.roll_up(update, spill);
+        let mut series = self.series.lock().unwrap();
+        let points = series.entry(update.symbol.clone()).or_default();
+        points.push_back(PricePoint {
+            timestamp: update.timestamp,
+            price: update.price,
+        });
+        if points.len() > self.capacity {
+            let window: Vec<PricePoint> = points.drain(..self.capacity / 2).collect();
+            if spill {
+                let path = self.path(&update.symbol, Resolution::Tick);
+                let spilled = match self.config.tick_format {
+                    TickFormat::Ndjson => self.spill(&path, &window),
+                    TickFormat::Delta => {
+                        delta::append(&path.with_extension(delta::EXTENSION), &window)
+                    }
+                };
+                match spilled {
+                    Ok(()) => {
+                        trace!(symbol = %update.symbol, points = window.len(), "Spilled history to disk");
+                        metrics::update_history_spilled(window.len());
+                    }
+                    Err(e) => {
+                        error!(symbol = %update.symbol, error = %e, "Failed to spill history")
+                    }
+                }
+            }
+        }
+        let bytes = series.values().map(|p| p.len() * POINT_BYTES).sum();
+        metrics::update_history_bytes(bytes);
+    }
+
+    /// The finest series retained far enough back to cover `since`.
+    pub fn resolution_for(&self, since: DateTime<Utc>, now: DateTime<Utc>) -> Resolution {
+        let age = now - since;
+        let ticks = TimeDelta::try_hours(self.config.tick_retention_hours);
+        let minutes = TimeDelta::try_days(self.config.minute_retention_days);
+        if age <= ticks.unwrap_or_else(TimeDelta::max_value) {
+            Resolution::Tick
+        } else if age <= minutes.unwrap_or_else(TimeDelta::max_value) {
+            Resolution::Minute
+        } else {
+            Resolution::Day
+        }
+    }
+
+    /// Points at or after `since` at the requested resolution, `Auto`
+    /// picking by how far back the range reaches from `now`.
+    pub fn query(
+        &self,
+        symbol: &str,
+        since: DateTime<Utc>,
+        now: DateTime<Utc>,
+        resolution: Resolution,
+        adjustment: Adjustment,
+    ) -> Series {
+        let resolution = match resolution {
+            Resolution::Auto => self.resolution_for(since, now),
+            resolution => resolution,
+        };
+        // Include the bucket `since` falls in.
+        let Some(since) = self.bucket(resolution, since) else {
+            return Series {
+                resolution,
+                points: self.since(symbol, since, adjustment),
+            };
+        };
+        let mut points = read(&self.path(symbol, resolution), |p| p.timestamp >= since);
+        let open = self
+            .open_buckets
+            .lock()
+            .unwrap()
+            .get(symbol)
+            .and_then(|buckets| {
+                let index = Resolution::ROLLUPS.iter().position(|r| *r == resolution)?;
+                buckets[index]
+            });
+        points.extend(open.filter(|p| p.timestamp >= since));
+        if adjustment == Adjustment::Split {
+            adjust(&mut points, &self.splits(symbol));
+        }
+        Series { resolution, points }
+    }
+
+    /// Points at or after `since`, oldest first, reading spilled windows
+    /// from disk only when the in-memory part doesn't reach back far enough.
+    pub fn since(
+        &self,
+        symbol: &str,
+        since: DateTime<Utc>,
+        adjustment: Adjustment,
+    ) -> Vec<PricePoint> {
+        let mut points = self.raw_since(symbol, since);
+        if adjustment == Adjustment::Split {
+            adjust(&mut points, &self.splits(symbol));
+        }
+        points
+    }
+
+    fn raw_since(&self, symbol: &str, since: DateTime<Utc>) -> Vec<PricePoint> {
+        let memory: Vec<PricePoint> = self
+            .series
+            .lock()
+            .unwrap()
+            .get(symbol)
+            .map(|p| p.iter().copied().collect())
+            .unwrap_or_default();
+        let covered = memory.first().is_some_and(|p| p.timestamp <= since);
+        let mut points = vec![];
+        if !covered {
+            let first = memory.first().map(|p| p.timestamp);
+            let keep =
+                |p: &PricePoint| p.timestamp >= since && first.is_none_or(|f| p.timestamp < f);
+            let path = self.path(symbol, Resolution::Tick);
+            points = read(&path, keep);
+            let packed = delta::read(&path.with_extension(delta::EXTENSION));
+            if !packed.is_empty() {
+                points.extend(packed.into_iter().filter(keep));
+                points.sort_by_key(|p| p.timestamp);
+            }
+        }
+        points.extend(memory.into_iter().filter(|p| p.timestamp >= since));
+        points
+    }
+}
+
+fn read(path: &Path, keep: impl Fn(&PricePoint) -> bool) -> Vec<PricePoint> {
+    let Ok(file) = File::open(path) else {
+        return vec![];
+    };
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|l| serde_json::from_str::<PricePoint>(&l).ok())
+        .filter(keep)
+        .collect()
+}
+
+/// Scales each point by the factors of all splits dated after it.
+pub fn adjust(points: &mut [PricePoint], splits: &[Split]) {
+    if splits.is_empty() {
+        return;
+    }
+    for point in points {
+        let date = point.timestamp.date_naive();
+        let factor: f64 = splits
+            .iter()
+            .filter(|s| s.date > date)
+            .map(|s| s.factor)
+            .product();
+        point.price *= factor;
+    }
+}
+
+impl Sink for History {
+    fn record(&self, update: &PriceUpdate) {
+        self.push(update, true);
+    }
+
+    // Anything that overflows during a replay was spilled by the original run.
+    fn replay(&self, update: &PriceUpdate) {
+        self.push(update, false);
+    }
+
+    // Kept as a split on the day, so only split-adjusted reads are rebased.
+    fn rebase(&self, symbol: &str, factor: f64, at: DateTime<Utc>) {
+        let date = at.date_naive();
+        let mut splits = self.splits(symbol);
+        match splits.iter_mut().find(|s| s.date == date) {
+            Some(split) => split.factor *= factor,
+            None => splits.push(Split { date, factor }),
+        }
+        splits.sort_by_key(|s| s.date);
+        self.set_splits(symbol, splits);
+    }
+}
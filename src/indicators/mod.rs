@@ -0,0 +1,325 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::metrics;
+use crate::sink::{PriceUpdate, Sink};
+use crate::PricePoint;
+
+/// Indicators are over the last prices fetched rather than over fixed
+/// bars, so their time span follows the symbol's poll interval.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct IndicatorsConfig {
+    /// Simple moving average periods, in prices.
+    pub sma: Vec<usize>,
+    /// Exponential moving average periods, in prices.
+    pub ema: Vec<usize>,
+    /// Relative strength index period, 0 for none.
+    pub rsi: usize,
+    /// Symbols to compute them for; all when empty.
+    pub symbols: Vec<String>,
+}
+
+impl Default for IndicatorsConfig {
+    fn default() -> Self {
+        IndicatorsConfig {
+            sma: vec![20, 50],
+            ema: vec![12, 26],
+            rsi: 14,
+            symbols: vec![],
+        }
+    }
+}
+
+// Wilder's average gain and loss per change.
+#[derive(Debug, Default)]
+struct Rsi {
+    changes: usize,
+    gain: f64,
+    loss: f64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    // As many recent prices as the longest SMA needs.
+    window: VecDeque<f64>,
+    // Per EMA period, with the prices seen so far until it is seeded.
+    ema: HashMap<usize, (usize, f64)>,
+    rsi: Rsi,
+    last: Option<f64>,
+    prices: usize,
+}
+
+/// Keeps rolling windows of prices per symbol and exports moving averages
+/// and the RSI as gauges, once each has seen enough prices.
+pub struct Indicators {
+    config: IndicatorsConfig,
+    window: usize,
+    state: Mutex<HashMap<String, State>>,
+}
+
+impl Indicators {
+    /// `None` when no indicator is configured.
+    pub fn new(config: IndicatorsConfig) -> Option<Self> {
+        if config.sma.is_empty() && config.ema.is_empty() && config.rsi == 0 {
+            return None;
+        }
+        Some(Indicators {
+            window: config.sma.iter().copied().max().unwrap_or(0),
+            config,
+            state: Mutex::new(HashMap::new()),
+        })
+    }
+}
+
+/// One symbol's windows as they stood after its last price, for debugging
+/// why a signal fired. Values are `None` until seeded.
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowState {
+    /// Prices taken in.
+    pub prices: usize,
+    /// Most recent last, as long as the longest SMA.
+    pub window: Vec<f64>,
+    pub last: Option<f64>,
+    pub sma: BTreeMap<usize, Option<f64>>,
+    pub ema: BTreeMap<usize, EmaState>,
+    pub rsi: Option<RsiState>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmaState {
+    /// Prices in the seed mean, which stops counting at the period.
+    pub seen: usize,
+    pub value: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RsiState {
+    pub period: usize,
+    pub changes: usize,
+    pub average_gain: f64,
+    pub average_loss: f64,
+    pub value: Option<f64>,
+}
+
+// What a price brought up to date, by period.
+#[derive(Debug, Default)]
+struct Values {
+    sma: Vec<(usize, f64)>,
+    ema: Vec<(usize, f64)>,
+    rsi: Option<f64>,
+}
+
+impl State {
+    fn push(&mut self, config: &IndicatorsConfig, window: usize, price: f64) -> Values {
+        let mut values = Values::default();
+        self.prices += 1;
+        self.window.push_back(price);
+        if self.window.len() > window {
+            self.window.pop_front();
+        }
+        for &period in config.sma.iter().filter(|p| **p > 0) {
+            if let Some(sma) = self.sma(period) {
+                values.sma.push((period, sma));
+            }
+        }
+
+        for &period in config.ema.iter().filter(|p| **p > 0) {
+            let (seen, ema) = self.ema.entry(period).or_insert((0, 0.));
+            // Seeded with the mean of the first `period` prices.
+            if *seen < period {
+                *seen += 1;
+                *ema += (price - *ema) / *seen as f64;
+            } else {
+                let alpha = 2. / (period as f64 + 1.);
+                *ema += alpha * (price - *ema);
+            }
+            if *seen == period {
+                values.ema.push((period, *ema));
+            }
+        }
+
+        let period = config.rsi;
+        if let Some(last) = self.last.filter(|_| period > 0) {
+            let change = price - last;
+            let rsi = &mut self.rsi;
+            let n = (rsi.changes + 1).min(period) as f64;
+            rsi.gain += (change.max(0.) - rsi.gain) / n;
+            rsi.loss += ((-change).max(0.) - rsi.loss) / n;
+            rsi.changes += 1;
+            values.rsi = self.rsi(period);
+        }
+        self.last = Some(price);
+        values
+    }
+
+    fn sma(&self, period: usize) -> Option<f64> {
+        (self.window.len() >= period)
+            .then(|| self.window.iter().rev().take(period).sum::<f64>() / period as f64)
+    }
+
+    fn rsi(&self, period: usize) -> Option<f64> {
+        let rsi = &self.rsi;
+        if rsi.changes < period {
+            return None;
+        }
+        Some(if rsi.gain == 0. && rsi.loss == 0. {
+            50.
+        } else if rsi.loss == 0. {
+            100.
+        } else {
+            100. - 100. / (1. + rsi.gain / rsi.loss)
+        })
+    }
+
+    fn snapshot(&self, config: &IndicatorsConfig) -> WindowState {
+        let periods = |periods: &[usize]| {
+            periods
+                .iter()
+                .copied()
+                .filter(|p| *p > 0)
+                .collect::<Vec<_>>()
+        };
+        WindowState {
+            prices: self.prices,
+            window: self.window.iter().copied().collect(),
+            last: self.last,
+            sma: periods(&config.sma)
+                .into_iter()
+                .map(|period| (period, self.sma(period)))
+                .collect(),
+            ema: periods(&config.ema)
+                .into_iter()
+                .map(|period| {
+                    let (seen, ema) = self.ema.get(&period).copied().unwrap_or((0, 0.));
+                    let value = (seen == period).then_some(ema);
+                    (period, EmaState { seen, value })
+                })
+                .collect(),
+            rsi: (config.rsi > 0).then(|| RsiState {
+                period: config.rsi,
+                changes: self.rsi.changes,
+                average_gain: self.rsi.gain,
+                average_loss: self.rsi.loss,
+                value: self.rsi(config.rsi),
+            }),
+        }
+    }
+}
+
+/// The windows `prices` leave behind, as the live indicators would hold
+/// them had they seen just these, in order. EMAs and the RSI converge, so
+/// a few times their period is plenty.
+pub fn replay(config: &IndicatorsConfig, prices: impl IntoIterator<Item = f64>) -> WindowState {
+    let window = config.sma.iter().copied().max().unwrap_or(0);
+    let mut state = State::default();
+    for price in prices {
+        state.push(config, window, price);
+    }
+    state.snapshot(config)
+}
+
+/// Candles `replay` wants to settle every configured indicator.
+pub fn lookback(config: &IndicatorsConfig) -> usize {
+    let sma = config.sma.iter().copied().max().unwrap_or(0);
+    let ema = config.ema.iter().copied().max().unwrap_or(0);
+    sma.max(3 * ema).max(3 * config.rsi + 1)
+}
+
+/// The close of each candle of `width` the `points` fall in, oldest first,
+/// timestamped with its start. Candles are aligned to the Unix epoch, so
+/// daily ones run midnight to midnight UTC.
+pub fn candles(points: &[PricePoint], width: Duration) -> Vec<PricePoint> {
+    let secs = width.num_seconds().max(1);
+    let mut candles: Vec<PricePoint> = vec![];
+    for point in points {
+        let start = point.timestamp.timestamp().div_euclid(secs) * secs;
+        let start = DateTime::from_timestamp(start, 0).unwrap_or(point.timestamp);
+        match candles.last_mut() {
+            Some(last) if last.timestamp == start => last.price = point.price,
+            _ => candles.push(PricePoint {
+                timestamp: start,
+                price: point.price,
+            }),
+        }
+    }
+    candles
+}
+
+/// The indicators over one timeframe's candles, the last of which may still
+/// be forming.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimeframeState {
+    /// As asked for, like `5m`.
+    pub timeframe: String,
+    pub candles: usize,
+    /// Start of the last candle.
+    pub candle_start: Option<DateTime<Utc>>,
+    pub close: Option<f64>,
+    pub sma: BTreeMap<usize, Option<f64>>,
+    pub ema: BTreeMap<usize, Option<f64>>,
+    pub rsi: Option<f64>,
+}
+
+/// [`replay`] over the closes of `points` in candles of `width`.
+pub fn timeframe(
+    config: &IndicatorsConfig,
+    timeframe: &str,
+    width: Duration,
+    points: &[PricePoint],
+) -> TimeframeState {
+    let candles = candles(points, width);
+    let state = replay(config, candles.iter().map(|c| c.price));
+    TimeframeState {
+        timeframe: timeframe.to_string(),
+        candles: candles.len(),
+        candle_start: candles.last().map(|c| c.timestamp),
+        close: state.last,
+        sma: state.sma,
+        ema: state
+            .ema
+            .into_iter()
+            .map(|(p, ema)| (p, ema.value))
+            .collect(),
+        rsi: state.rsi.and_then(|rsi| rsi.value),
+    }
+}
+
+impl Sink for Indicators {
+    fn record(&self, update: &PriceUpdate) {
+        let symbol = &update.symbol;
+        if !self.config.symbols.is_empty() && !self.config.symbols.contains(symbol) {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        let values =
+            state
+                .entry(symbol.clone())
+                .or_default()
+                .push(&self.config, self.window, update.price);
+        for (period, sma) in values.sma {
+            metrics::update_stock_sma(symbol, period, sma);
+        }
+        for (period, ema) in values.ema {
+            metrics::update_stock_ema(symbol, period, ema);
+        }
+        if let Some(rsi) = values.rsi {
+            metrics::update_stock_rsi(symbol, self.config.rsi, rsi);
+        }
+    }
+
+    fn rebase(&self, symbol: &str, factor: f64, _at: DateTime<Utc>) {
+        let mut state = self.state.lock().unwrap();
+        let Some(state) = state.get_mut(symbol) else {
+            return;
+        };
+        state.window.iter_mut().for_each(|p| *p *= factor);
+        state.ema.values_mut().for_each(|(_, ema)| *ema *= factor);
+        state.rsi.gain *= factor;
+        state.rsi.loss *= factor;
+        state.last = state.last.map(|p| p * factor);
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+
+use crate::metrics;
+
+/// The engine, from a symbol falling due to its fetch going out.
+pub const INGEST: &str = "ingest";
+/// The price store writer, from a price's fetch to its write.
+pub const STORAGE: &str = "storage";
+/// The event log writer, from a price's fetch to its append.
+pub const EVENT_LOG: &str = "event_log";
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Progress {
+    // Lag of the last work finished.
+    lag: f64,
+    // When the oldest work still in hand was due.
+    behind_since: Option<DateTime<Utc>>,
+    backlog: usize,
+}
+
+static PROGRESS: Mutex<BTreeMap<&'static str, Progress>> = Mutex::new(BTreeMap::new());
+
+/// `subsystem` has work in hand that was due at `since`, or none.
+pub fn behind(subsystem: &'static str, since: Option<DateTime<Utc>>) {
+    PROGRESS
+        .lock()
+        .unwrap()
+        .entry(subsystem)
+        .or_default()
+        .behind_since = since;
+}
+
+/// `subsystem` finished what it had in hand, the oldest of it `lag_secs`
+/// after it was due.
+pub fn caught_up(subsystem: &'static str, lag_secs: f64) {
+    let mut all = PROGRESS.lock().unwrap();
+    let progress = all.entry(subsystem).or_default();
+    progress.lag = lag_secs.max(0.);
+    progress.behind_since = None;
+}
+
+/// Work waiting for `subsystem` beyond what it has in hand.
+pub fn backlog(subsystem: &'static str, waiting: usize) {
+    PROGRESS
+        .lock()
+        .unwrap()
+        .entry(subsystem)
+        .or_default()
+        .backlog = waiting;
+}
+
+/// How far behind `subsystem` is at `now`: the lag of its last finished
+/// work, or, while it is still on older work, how long that has been due,
+/// so a stuck subsystem keeps falling behind instead of reporting its last
+/// good value. `None` until it reports.
+pub fn lag(subsystem: &str, now: DateTime<Utc>) -> Option<f64> {
+    PROGRESS
+        .lock()
+        .unwrap()
+        .get(subsystem)
+        .map(|progress| effective(progress, now))
+}
+
+fn effective(progress: &Progress, now: DateTime<Utc>) -> f64 {
+    let pending = progress
+        .behind_since
+        .map(|since| (now - since).num_milliseconds() as f64 / 1000.)
+        .unwrap_or_default();
+    progress.lag.max(pending)
+}
+
+/// Sets the lag gauges as of `now`, each subsystem degraded while more
+/// than `degraded_after_secs` behind. Run on every scrape, so the series
+/// move even when the subsystems themselves can't.
+pub fn export(now: DateTime<Utc>, degraded_after_secs: u64) {
+    let progress = PROGRESS.lock().unwrap();
+    for (subsystem, progress) in progress.iter() {
+        let lag = effective(progress, now);
+        metrics::update_pipeline_lag(
+            subsystem,
+            lag,
+            progress.backlog,
+            lag > degraded_after_secs as f64,
+        );
+    }
+}
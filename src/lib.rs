@@ -1,18 +1,210 @@
+// The metrics lazy_static! block outgrows the default.
+#![recursion_limit = "256"]
+
+pub mod adr;
+pub mod alerts;
+pub mod allocation;
+pub mod analysts;
+pub mod api;
+pub mod auth;
+pub mod bootstrap;
+pub mod calendar;
+pub mod chaos;
+#[cfg(feature = "client")]
+pub mod client;
+pub mod clock;
+pub mod cluster;
+pub mod config;
+pub mod consensus;
+pub mod corporate;
+pub mod currency;
+pub mod daily;
+pub mod dca;
+pub mod dividends;
+pub mod econ;
+pub mod edgar;
+pub mod encryption;
+pub mod engine;
+pub mod error;
+pub mod events;
+pub mod feed;
+pub mod futures;
+pub mod health;
+pub mod history;
+pub mod indicators;
+pub mod lag;
+pub mod limits;
+pub mod listings;
+pub mod market;
 pub mod metrics;
+pub mod movers;
+pub mod namespaces;
+pub mod notes;
+pub mod notify;
+pub mod onboard;
+pub mod ops;
+pub mod options;
+pub mod paper;
+pub mod patterns;
+pub mod portfolio;
+pub mod priority;
+pub mod profile;
+pub mod provider;
+pub mod quality;
+pub mod query;
+pub mod ratelimit;
+pub mod rates;
+pub mod recovery;
+pub mod reload;
+pub mod returns;
+pub mod service;
+pub mod share;
+pub mod sheets;
+pub mod sim;
+pub mod sink;
+pub mod slo;
+pub mod smoothing;
+pub mod social;
+pub mod stats;
+pub mod storage;
+pub mod strategy;
+pub mod stream;
+pub mod supervisor;
+pub mod symbol;
+pub mod ticks;
+pub mod timeseries;
+pub mod toggles;
+pub mod tracking;
+pub mod usage;
+pub mod venues;
+pub mod version;
+pub mod watchdog;
 
-use reqwest::Error;
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use serde::Serialize;
-use serde_json::Value;
 use std::fmt::{self, Display};
+use utoipa::ToSchema;
+
+pub use error::FintekError;
 
+use config::RateLimit;
+use provider::{retry::retry, Provider, Quote, TwelveData};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::RwLock;
 use std::{path::Path, sync::atomic::AtomicU64};
+use symbol::SymbolInfo;
 use tokio::fs::{self};
+use tracing::error;
 use tracing::info;
 use tracing::instrument;
 use tracing::trace;
+use tracing::warn;
+
+const CRYPTO_BASES: &[&str] = &["BTC", "ETH", "LTC", "SOL", "XRP", "DOGE", "ADA", "USDT"];
+const COMMODITY_BASES: &[&str] = &["XAU", "XAG", "XPT", "XPD", "WTI", "BRENT", "NG", "HG"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetClass {
+    Stock,
+    Forex,
+    Crypto,
+    Commodity,
+    Index,
+    Rate,
+}
+
+static ASSET_CLASSES: RwLock<BTreeMap<String, AssetClass>> = RwLock::new(BTreeMap::new());
+
+/// Sets the classes of symbols tagged in the tickers file, which win over
+/// the guess from their shape.
+pub fn set_asset_classes(classes: BTreeMap<String, AssetClass>) {
+    *ASSET_CLASSES.write().unwrap() = classes;
+}
+
+static EXCHANGES: RwLock<BTreeMap<String, StockMarket>> = RwLock::new(BTreeMap::new());
+
+/// Sets the exchanges of symbols tagged in the tickers file, for listings
+/// without a suffix.
+pub fn set_exchanges(exchanges: BTreeMap<String, StockMarket>) {
+    *EXCHANGES.write().unwrap() = exchanges;
+}
+
+pub(crate) fn tagged_exchange(symbol: &str) -> Option<StockMarket> {
+    EXCHANGES.read().unwrap().get(symbol).copied()
+}
+
+static LABELS: RwLock<BTreeMap<String, BTreeMap<String, String>>> = RwLock::new(BTreeMap::new());
+
+/// Sets the labels of symbols tagged in the tickers file, which alerts
+/// about them carry.
+pub fn set_labels(labels: BTreeMap<String, BTreeMap<String, String>>) {
+    *LABELS.write().unwrap() = labels;
+}
 
-#[derive(Debug)]
+/// The labels of `symbols` together, the first symbol's winning where two
+/// set a key differently.
+pub fn labels_of(symbols: &[String]) -> BTreeMap<String, String> {
+    let all = LABELS.read().unwrap();
+    let mut labels = BTreeMap::new();
+    for own in symbols.iter().filter_map(|symbol| all.get(symbol)) {
+        for (key, value) in own {
+            labels.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+    labels
+}
+
+impl AssetClass {
+    /// The tagged class, else a guess from the symbol shape: known index,
+    /// yield and commodity codes first, then pairs like `EUR/USD` are forex
+    /// unless one leg is a known coin, anything else is treated as a stock.
+    pub fn of(symbol: &str) -> Self {
+        if let Some(class) = ASSET_CLASSES.read().unwrap().get(symbol) {
+            return *class;
+        }
+        if symbol::index_exchange(symbol).is_some() {
+            return AssetClass::Index;
+        }
+        if symbol::is_yield(symbol) {
+            return AssetClass::Rate;
+        }
+        let base = symbol.split_once('/').map_or(symbol, |(base, _)| base);
+        if COMMODITY_BASES.contains(&base) {
+            return AssetClass::Commodity;
+        }
+        match symbol.split_once('/') {
+            Some((base, quote))
+                if CRYPTO_BASES.contains(&base) || CRYPTO_BASES.contains(&quote) =>
+            {
+                AssetClass::Crypto
+            }
+            Some(_) => AssetClass::Forex,
+            None => AssetClass::Stock,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AssetClass::Stock => "stock",
+            AssetClass::Forex => "forex",
+            AssetClass::Crypto => "crypto",
+            AssetClass::Commodity => "commodity",
+            AssetClass::Index => "index",
+            AssetClass::Rate => "rate",
+        }
+    }
+}
+
+impl Display for AssetClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
 pub enum Markets {
     Stock(StockMarket),
     Forex(ForexMarket),
@@ -29,62 +221,66 @@ impl Display for Markets {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 pub enum StockMarket {
     NYSE,
     NASDAQ,
+    LSE,
+    XETR,
+    Euronext,
+    SIX,
+    JPX,
+    HKEX,
+}
+
+impl StockMarket {
+    pub const ALL: [StockMarket; 8] = [
+        StockMarket::NYSE,
+        StockMarket::NASDAQ,
+        StockMarket::LSE,
+        StockMarket::XETR,
+        StockMarket::Euronext,
+        StockMarket::SIX,
+        StockMarket::JPX,
+        StockMarket::HKEX,
+    ];
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum ForexMarket {
     EURUSD,
     GBPUSD,
     USDJPY,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum CryptoMarket {
     BTCUSD,
     ETHUSD,
     LTCUSD,
 }
 
+/// Seconds until `market` opens, zero when it is open now, as Twelve Data
+/// reports it to the key `api_key`.
+#[deprecated(
+    since = "0.1.2",
+    note = "the engine schedules around market hours itself; use `calendar::MarketCalendar`, or `Provider::fetch_market_state` directly"
+)]
 #[instrument(skip(api_key))]
-pub async fn should_sleep(market: Markets, api_key: &str) -> Result<u64, Error> {
-    let m = market.to_string();
-    let url = format!(
-        "https://api.twelvedata.com/market_state?exchange={}&apikey={}",
-        market, api_key
-    );
-    let response = reqwest::get(&url).await?;
-    let data = response.text().await?;
-    let maybe_value: Value = serde_json::from_str(&data).unwrap_or_default();
-    if let Some(array) = maybe_value.as_array() {
-        for object in array {
-            if let Some(is_market_open) = object["is_market_open"].as_bool() {
-                if is_market_open {
-                    trace!(market = %m, "Market is open");
-                    return Ok(0);
-                } else {
-                    trace!(market = %m, "Market is closed");
-                    let time_to_open = object["time_to_open"]
-                        .as_str()
-                        .unwrap_or_else(|| "0:0:0")
-                        .split(':')
-                        .collect::<Vec<_>>();
-                    let hours: u64 = time_to_open[0].parse().ok().unwrap_or_default();
-                    let minutes: u64 = time_to_open[1].parse().ok().unwrap_or_default();
-                    let seconds: u64 = time_to_open[2].parse().ok().unwrap_or_default();
-                    info!(market = %m, hours, minutes, seconds, "Time to open");
-                    return Ok(hours * 3600 + minutes * 60 + seconds);
-                }
-            }
-        }
-    }
-
-    Ok(0)
+pub async fn should_sleep(market: Markets, api_key: &str) -> Result<u64, FintekError> {
+    let provider = TwelveData::new(api_key);
+    retry("market_state", || provider.fetch_market_state(&market)).await
 }
 
+/// Seconds to pause between tickers to stay within both limits, the
+/// stricter winning; `None` for no tickers or a limit allowing no requests.
+/// The pause is the spacing of the
+/// request budget [`priority::budget`] hands out, which the
+/// [`ratelimit::RateLimiter`] enforcing the limits never has to wait on.
+#[deprecated(
+    since = "0.1.2",
+    note = "use `ratelimit::RateLimiter::acquire` before each request instead"
+)]
 pub fn calculate_sleep_duration(
     num_tickers: usize,
     rate_limit1: u64,
@@ -95,115 +291,410 @@ pub fn calculate_sleep_duration(
     if num_tickers == 0 {
         return None;
     }
-
-    let calls_per_ticker1 = rate_limit1
-        .checked_div(num_tickers as u64)
-        .unwrap_or_default();
-    let sleep_duration1 = period_in_seconds1
-        .checked_sub(calls_per_ticker1)
-        .unwrap_or_default();
-
-    let calls_per_ticker2 = rate_limit2
-        .checked_sub(num_tickers as u64)
-        .unwrap_or_default();
-    let sleep_duration2 = period_in_seconds2
-        .checked_sub(calls_per_ticker2)
-        .unwrap_or_default();
-
-    // Choose the stricter rate limit
-    let sleep_duration = std::cmp::min(sleep_duration1, sleep_duration2);
-
-    Some(sleep_duration)
+    let budget = priority::budget(&[
+        RateLimit {
+            requests: rate_limit1,
+            period_secs: period_in_seconds1,
+        },
+        RateLimit {
+            requests: rate_limit2,
+            period_secs: period_in_seconds2,
+        },
+    ]);
+    let pause = std::time::Duration::try_from_secs_f64(1. / budget).ok()?;
+    Some(pause.as_secs() + u64::from(pause.subsec_nanos() > 0))
 }
 
-#[instrument(skip(api_key))]
-pub async fn call_api(symbol: &str, api_key: &str) -> Result<(), Error> {
-    let url = format!(
-        "https://api.twelvedata.com/price?symbol={}&apikey={}",
-        symbol, api_key
-    );
-    let response = reqwest::get(&url).await?;
-
-    let data = response.text().await?;
-    let v: Value = serde_json::from_str(&data).unwrap_or_else(|_| Value::Null);
-    if let Some(price) = v["price"].as_str() {
+#[instrument(skip(provider), fields(provider = provider.name()))]
+pub async fn call_api(symbol: &str, provider: &dyn Provider) -> Result<(), FintekError> {
+    if let Some(Quote { price, .. }) = retry("price", || provider.fetch_price(symbol)).await? {
         trace!(price, symbol, "Updating stock price");
-        if let Some(parsed) = price.parse::<f64>().ok() {
-            metrics::update_stock_price(parsed, symbol);
+        let info = SymbolInfo::parse(symbol);
+        metrics::update_stock_price(price, symbol, info.asset_class.as_str(), &info.currency());
+        metrics::update_last_update(symbol, Utc::now());
+    }
+    Ok(())
+}
+
+/// [`call_api`] for many symbols, `provider.batch_size()` per request.
+#[instrument(skip(symbols, provider), fields(provider = provider.name(), symbols = symbols.len()))]
+pub async fn call_api_batch(
+    symbols: &[String],
+    provider: &dyn Provider,
+) -> Result<(), FintekError> {
+    for chunk in symbols.chunks(provider.batch_size().max(1)) {
+        let prices = retry("prices", || provider.fetch_prices(chunk)).await?;
+        for (symbol, price) in chunk.iter().zip(prices) {
+            let Ok(Some(Quote { price, .. })) = price else {
+                continue;
+            };
+            trace!(price, symbol, "Updating stock price");
+            let info = SymbolInfo::parse(symbol);
+            metrics::update_stock_price(price, symbol, info.asset_class.as_str(), &info.currency());
+            metrics::update_last_update(symbol, Utc::now());
         }
     }
     Ok(())
 }
 
-#[instrument]
-pub async fn read_tickers() -> Tickers {
-    let path = Path::new("tickers");
-    serde_json::from_str(&fs::read_to_string(&path).await.unwrap()).unwrap_or_default()
+static TICKERS_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Sets the file the watchlist is read from and saved to, `tickers` until set.
+pub fn set_tickers_path(path: &Path) {
+    *TICKERS_PATH.write().unwrap() = Some(path.to_path_buf());
+}
+
+pub fn tickers_path() -> PathBuf {
+    TICKERS_PATH
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("tickers"))
 }
 
 #[instrument]
-pub async fn check_tickers() -> Option<Tickers> {
+pub async fn read_tickers() -> Result<Tickers, FintekError> {
+    Ok(bootstrap::load_tickers(&tickers_path())?)
+}
+
+/// The watchlist when the file changed since the last check. A file that
+/// won't parse is an error; callers keep the watchlist they have. Polling
+/// stands in for [`reload::watch_tickers`] where the file can't be watched.
+pub async fn check_tickers() -> Result<Option<Tickers>, FintekError> {
     static LAST_MODIFIED: AtomicU64 = AtomicU64::new(0);
-    let metdata = fs::metadata("tickers")
+    let modified = match fs::metadata(tickers_path())
         .await
-        .expect("Failed to read metadata");
-    let modified = metdata
-        .modified()
-        .expect("Failed to read modified")
-        .elapsed()
-        .map(|d| d.as_secs())
-        .unwrap_or_default();
+        .and_then(|m| m.modified())
+    {
+        Ok(modified) => modified
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default(),
+        Err(e) => {
+            warn!(error = %e, "Failed to read tickers metadata");
+            return Ok(None);
+        }
+    };
     if LAST_MODIFIED.load(std::sync::atomic::Ordering::Relaxed) != modified {
         LAST_MODIFIED.store(modified, std::sync::atomic::Ordering::Relaxed);
         info!(modified, "File modified updating tickers");
-        Some(read_tickers().await)
+        read_tickers().await.map(Some)
     } else {
-        None
+        Ok(None)
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct PricePoint {
+    pub timestamp: DateTime<Utc>,
+    pub price: f64,
+}
+
+/// What `fintek onboard` resolved a ticker from.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TickerMeta {
+    pub name: String,
+    pub exchange: String,
+    pub currency: String,
+    /// "Common Stock", "ETF" and so on.
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The name or ISIN it was looked up by.
+    pub query: String,
+    /// Provider that matched it.
+    pub source: String,
+}
+
+/// Per-symbol settings a tickers file entry can carry besides the symbol.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TickerOptions {
+    /// The exchange it trades on, for listings without a suffix saying so.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exchange: Option<StockMarket>,
+    /// Poll interval for this symbol instead of `priority.interval_secs`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval_secs: Option<u64>,
+    /// Disabled entries stay in the file but aren't polled.
+    #[serde(default = "enabled", skip_serializing_if = "is_enabled")]
+    pub enabled: bool,
+    /// Free-form tags like `{"owner":"alice","strategy":"momentum"}`, sent
+    /// with alerts about the symbol. Keys listed in
+    /// `metrics.ticker_labels` are exported on `ticker_labels` too.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+fn is_enabled(enabled: &bool) -> bool {
+    *enabled
+}
+
+impl Default for TickerOptions {
+    fn default() -> Self {
+        TickerOptions {
+            exchange: None,
+            interval_secs: None,
+            enabled: true,
+            labels: BTreeMap::new(),
+        }
+    }
+}
+
+/// A tickers file entry: a bare symbol, or one with options like
+/// `{"symbol":"AAPL","exchange":"NASDAQ","interval_secs":60}`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+enum TickerEntry {
+    Symbol(String),
+    Detailed {
+        symbol: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        asset_class: Option<AssetClass>,
+        #[serde(flatten)]
+        options: TickerOptions,
+    },
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct TickersFile {
+    tickers: Vec<TickerEntry>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, TickerMeta>,
+    // Classes used to be tagged here rather than on the entry.
+    #[serde(default, skip_serializing)]
+    classes: BTreeMap<String, AssetClass>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(from = "TickersFile", into = "TickersFile")]
 pub struct Tickers {
+    /// The enabled symbols, in file order.
     tickers: Vec<String>,
+    metadata: BTreeMap<String, TickerMeta>,
+    /// Classes for symbols whose shape doesn't say, such as a coin quoted
+    /// without a pair.
+    classes: BTreeMap<String, AssetClass>,
+    /// Options of the entries that set any, disabled ones included.
+    options: BTreeMap<String, TickerOptions>,
 }
 
-impl Default for Tickers {
-    fn default() -> Self {
-        Tickers { tickers: vec![] }
+impl From<TickersFile> for Tickers {
+    fn from(file: TickersFile) -> Self {
+        let mut tickers = Tickers {
+            tickers: vec![],
+            metadata: file.metadata,
+            classes: file.classes,
+            options: BTreeMap::new(),
+        };
+        for entry in file.tickers {
+            let (symbol, options) = match entry {
+                TickerEntry::Symbol(symbol) => (symbol, TickerOptions::default()),
+                TickerEntry::Detailed {
+                    symbol,
+                    asset_class,
+                    options,
+                } => {
+                    if let Some(class) = asset_class {
+                        tickers.classes.insert(symbol.clone(), class);
+                    }
+                    (symbol, options)
+                }
+            };
+            if options.enabled && !tickers.tickers.contains(&symbol) {
+                tickers.tickers.push(symbol.clone());
+            }
+            if options != TickerOptions::default() {
+                tickers.options.insert(symbol, options);
+            }
+        }
+        tickers
+    }
+}
+
+impl From<Tickers> for TickersFile {
+    fn from(tickers: Tickers) -> Self {
+        let disabled = tickers
+            .options
+            .iter()
+            .filter(|(symbol, options)| !options.enabled && !tickers.tickers.contains(symbol))
+            .map(|(symbol, _)| symbol.clone());
+        let entries = tickers
+            .tickers
+            .iter()
+            .cloned()
+            .chain(disabled.collect::<Vec<_>>())
+            .map(|symbol| {
+                let asset_class = tickers.classes.get(&symbol).copied();
+                let options = tickers.options.get(&symbol).cloned().unwrap_or_default();
+                if asset_class.is_none() && options == TickerOptions::default() {
+                    TickerEntry::Symbol(symbol)
+                } else {
+                    TickerEntry::Detailed {
+                        symbol,
+                        asset_class,
+                        options,
+                    }
+                }
+            })
+            .collect();
+        TickersFile {
+            tickers: entries,
+            metadata: tickers.metadata,
+            classes: BTreeMap::new(),
+        }
     }
 }
 
 impl Tickers {
+    /// The watchlist on disk, created empty when missing. One that can't be
+    /// read starts the daemon with nothing to poll until the file is fixed.
     pub async fn init() -> Self {
-        let exists = fs::try_exists("tickers").await;
-        if exists.is_err() || !exists.unwrap() {
-            create_tickers().await;
-            read_tickers().await
-        } else {
-            read_tickers().await
+        if !fs::try_exists(tickers_path()).await.unwrap_or(false) {
+            if let Err(e) = create_tickers().await {
+                error!(error = %e, "Failed to create tickers");
+            }
         }
+        read_tickers().await.unwrap_or_else(|e| {
+            error!(error = %e, "Failed to read tickers, starting with none");
+            Tickers::default()
+        })
     }
 
     pub fn new(t: Vec<String>) -> Self {
-        Tickers { tickers: t }
+        Tickers {
+            tickers: t,
+            metadata: BTreeMap::new(),
+            classes: BTreeMap::new(),
+            options: BTreeMap::new(),
+        }
     }
 
+    /// Replaces the watchlist, disabled entries included.
     pub fn set_tickers(&mut self, tickers: Vec<String>) {
+        self.metadata.retain(|symbol, _| tickers.contains(symbol));
+        self.classes.retain(|symbol, _| tickers.contains(symbol));
+        self.options.retain(|symbol, _| tickers.contains(symbol));
+        for options in self.options.values_mut() {
+            options.enabled = true;
+        }
         self.tickers = tickers;
     }
 
+    /// Just `symbols`, keeping what is known about them.
+    pub fn select(&self, symbols: Vec<String>) -> Self {
+        let keep = |symbol: &String| symbols.contains(symbol);
+        Tickers {
+            metadata: self
+                .metadata
+                .clone()
+                .into_iter()
+                .filter(|(s, _)| keep(s))
+                .collect(),
+            classes: self
+                .classes
+                .clone()
+                .into_iter()
+                .filter(|(s, _)| keep(s))
+                .collect(),
+            options: self
+                .options
+                .clone()
+                .into_iter()
+                .filter(|(s, _)| keep(s))
+                .collect(),
+            tickers: symbols,
+        }
+    }
+
     pub fn get_tickers(&self) -> &Vec<String> {
         &self.tickers
     }
 
+    pub fn metadata(&self, symbol: &str) -> Option<&TickerMeta> {
+        self.metadata.get(symbol)
+    }
+
+    pub fn set_metadata(&mut self, symbol: &str, meta: TickerMeta) {
+        self.metadata.insert(symbol.to_string(), meta);
+    }
+
+    pub fn set_exchange(&mut self, symbol: &str, exchange: StockMarket) {
+        self.options.entry(symbol.to_string()).or_default().exchange = Some(exchange);
+    }
+
+    pub fn classes(&self) -> &BTreeMap<String, AssetClass> {
+        &self.classes
+    }
+
+    pub fn options(&self, symbol: &str) -> Option<&TickerOptions> {
+        self.options.get(symbol)
+    }
+
+    /// Exchanges set on entries, by symbol.
+    pub fn exchanges(&self) -> BTreeMap<String, StockMarket> {
+        self.options
+            .iter()
+            .filter_map(|(symbol, options)| Some((symbol.clone(), options.exchange?)))
+            .collect()
+    }
+
+    /// Labels set on entries, by symbol.
+    pub fn labels(&self) -> BTreeMap<String, BTreeMap<String, String>> {
+        self.options
+            .iter()
+            .filter(|(_, options)| !options.labels.is_empty())
+            .map(|(symbol, options)| (symbol.clone(), options.labels.clone()))
+            .collect()
+    }
+
+    /// The symbol's own poll interval, when its entry sets one.
+    pub fn interval_secs(&self, symbol: &str) -> Option<u64> {
+        self.options.get(symbol)?.interval_secs
+    }
+
+    /// False when it is already there. A disabled entry is enabled again.
+    pub fn add(&mut self, symbol: &str) -> bool {
+        if self.tickers.iter().any(|t| t == symbol) {
+            return false;
+        }
+        if let Some(options) = self.options.get_mut(symbol) {
+            options.enabled = true;
+        }
+        self.tickers.push(symbol.to_string());
+        true
+    }
+
+    /// False when it wasn't there.
+    pub fn remove(&mut self, symbol: &str) -> bool {
+        let before = self.tickers.len();
+        self.tickers.retain(|t| t != symbol);
+        self.metadata.remove(symbol);
+        self.classes.remove(symbol);
+        let disabled = self.options.remove(symbol).is_some_and(|o| !o.enabled);
+        self.tickers.len() != before || disabled
+    }
+
     pub async fn dump_to_file(&self) {
-        let serde_output = serde_json::to_string(self).unwrap();
-        fs::write("tickers", serde_output)
-            .await
-            .expect("Failed to write to file");
+        if let Err(e) = self.save().await {
+            error!(error = %e, "Failed to write tickers");
+        }
+    }
+
+    /// Written aside and renamed over the file, so a reader never sees it
+    /// half written.
+    pub async fn save(&self) -> Result<(), FintekError> {
+        let serde_output = serde_json::to_string(self)?;
+        let path = tickers_path();
+        recovery::backup(&path)?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_output).await?;
+        Ok(fs::rename(&tmp, &path).await?)
     }
 }
 
-pub async fn create_tickers() {
-    Tickers::default().dump_to_file().await
+pub async fn create_tickers() -> Result<(), FintekError> {
+    Tickers::default().save().await
 }
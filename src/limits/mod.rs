@@ -0,0 +1,166 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{error, info};
+
+use crate::clock::Clock;
+use crate::metrics;
+
+// How often the exported series are counted.
+const SERIES_CHECK_SECS: u64 = 60;
+
+/// Hard caps keeping an accidentally huge watchlist from flooding a shared
+/// Prometheus or the disk. 0 turns one off; `--ignore-limits` turns all
+/// off.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    /// Symbols in the tickers file. More refuses to start, and a reload or
+    /// an add going over is refused.
+    pub max_symbols: usize,
+    /// Series on `/metrics`. While over, no symbol can be added.
+    pub max_series: usize,
+    /// Prices written to the store a second. The rest of the second's are
+    /// dropped.
+    pub max_stored_per_sec: u64,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig {
+            max_symbols: 1000,
+            max_series: 200_000,
+            max_stored_per_sec: 0,
+        }
+    }
+}
+
+impl LimitsConfig {
+    pub fn off() -> Self {
+        LimitsConfig {
+            max_symbols: 0,
+            max_series: 0,
+            max_stored_per_sec: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum LimitError {
+    #[error(
+        "{symbols} symbols is over limits.max_symbols of {max}; raise it or run with \
+         --ignore-limits"
+    )]
+    TooManySymbols { symbols: usize, max: usize },
+    #[error(
+        "{series} metric series is over limits.max_series of {max}, so no symbols are added \
+         until it drops; raise it or run with --ignore-limits"
+    )]
+    TooManySeries { series: usize, max: usize },
+}
+
+impl LimitError {
+    fn limit(&self) -> &'static str {
+        match self {
+            LimitError::TooManySymbols { .. } => "symbols",
+            LimitError::TooManySeries { .. } => "series",
+        }
+    }
+}
+
+/// Enforces [`LimitsConfig`] wherever the watchlist grows or prices are
+/// stored.
+pub struct Limits {
+    config: LimitsConfig,
+    // Series counted at the last check, while over the limit.
+    series_over: Mutex<Option<usize>>,
+    // The second being stored and the prices stored in it.
+    stored: Mutex<(i64, u64)>,
+}
+
+impl Limits {
+    pub fn new(config: &LimitsConfig) -> Self {
+        Limits {
+            config: config.clone(),
+            series_over: Mutex::new(None),
+            stored: Mutex::new((0, 0)),
+        }
+    }
+
+    pub fn config(&self) -> &LimitsConfig {
+        &self.config
+    }
+
+    /// Whether a watchlist of `symbols` may be started with.
+    pub fn check_symbols(&self, symbols: usize) -> Result<(), LimitError> {
+        let max = self.config.max_symbols;
+        if max > 0 && symbols > max {
+            metrics::update_limit_rejection("symbols", 1);
+            return Err(LimitError::TooManySymbols { symbols, max });
+        }
+        Ok(())
+    }
+
+    /// Whether the watchlist may go from `from` symbols to `to`. Shrinking
+    /// always may.
+    pub fn check_growth(&self, from: usize, to: usize) -> Result<(), LimitError> {
+        if to <= from {
+            return Ok(());
+        }
+        self.check_symbols(to)?;
+        if let Some(series) = *self.series_over.lock().unwrap() {
+            let error = LimitError::TooManySeries {
+                series,
+                max: self.config.max_series,
+            };
+            metrics::update_limit_rejection(error.limit(), 1);
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    /// How many of `prices` received at `now` may be stored.
+    pub fn storable(&self, prices: usize, now: DateTime<Utc>) -> usize {
+        let max = self.config.max_stored_per_sec;
+        if max == 0 {
+            return prices;
+        }
+        let mut stored = self.stored.lock().unwrap();
+        let second = now.timestamp();
+        if stored.0 != second {
+            *stored = (second, 0);
+        }
+        let allowed = (max - stored.1).min(prices as u64);
+        stored.1 += allowed;
+        if (allowed as usize) < prices {
+            metrics::update_limit_rejection("storage", prices - allowed as usize);
+        }
+        allowed as usize
+    }
+
+    /// Counts the exported series every minute, holding the watchlist at
+    /// its size while there are more than `max_series`.
+    pub async fn watch_series(self: Arc<Self>, clock: Arc<dyn Clock>) {
+        let max = self.config.max_series;
+        if max == 0 {
+            return;
+        }
+        loop {
+            let series = metrics::series_count();
+            let over = (series > max).then_some(series);
+            let was = std::mem::replace(&mut *self.series_over.lock().unwrap(), over);
+            match (was, over) {
+                (None, Some(_)) => {
+                    let error = LimitError::TooManySeries { series, max };
+                    error!(error = %error, "Metric series limit reached");
+                }
+                (Some(_), None) => info!(series, max, "Metric series back under the limit"),
+                _ => {}
+            }
+            clock.sleep(Duration::from_secs(SERIES_CHECK_SECS)).await;
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tracing::trace;
+
+use crate::metrics;
+use crate::sink::{PriceUpdate, Sink};
+use crate::symbol::SymbolInfo;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Listing {
+    pub symbol: String,
+    pub exchange: String,
+}
+
+/// One company traded under several symbols. `primary` picks the listing whose
+/// price stands for the company, the others are exported per exchange.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Company {
+    pub name: String,
+    pub primary: String,
+    pub listings: Vec<Listing>,
+}
+
+pub struct Consolidator {
+    companies: Vec<Company>,
+    // symbol -> (company index, listing index)
+    index: HashMap<String, (usize, usize)>,
+    latest: Mutex<HashMap<String, f64>>,
+}
+
+impl Consolidator {
+    pub fn new(companies: Vec<Company>) -> Self {
+        let mut index = HashMap::new();
+        for (c, company) in companies.iter().enumerate() {
+            for (l, listing) in company.listings.iter().enumerate() {
+                index.insert(listing.symbol.clone(), (c, l));
+            }
+        }
+        Consolidator {
+            companies,
+            index,
+            latest: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Every listing symbol, so the engine polls them even if they aren't in the tickers file.
+    pub fn symbols(&self) -> Vec<String> {
+        self.companies
+            .iter()
+            .flat_map(|c| c.listings.iter().map(|l| l.symbol.clone()))
+            .collect()
+    }
+
+    pub fn primary_price(&self, company: &str) -> Option<f64> {
+        let company = self.companies.iter().find(|c| c.name == company)?;
+        let latest = self.latest.lock().unwrap();
+        latest.get(&company.primary).copied().or_else(|| {
+            company
+                .listings
+                .iter()
+                .find_map(|l| latest.get(&l.symbol).copied())
+        })
+    }
+}
+
+impl Sink for Consolidator {
+    fn record(&self, update: &PriceUpdate) {
+        let Some((c, l)) = self.index.get(&update.symbol).copied() else {
+            return;
+        };
+        let company = &self.companies[c];
+        let listing = &company.listings[l];
+        self.latest
+            .lock()
+            .unwrap()
+            .insert(update.symbol.clone(), update.price);
+        metrics::update_listing_price(
+            update.price,
+            &company.name,
+            &listing.exchange,
+            &listing.symbol,
+            &SymbolInfo::parse(&listing.symbol).currency(),
+        );
+
+        if let Some(price) = self.primary_price(&company.name) {
+            trace!(company = %company.name, price, "Updating consolidated price");
+            metrics::update_company_price(price, &company.name);
+        }
+    }
+}
@@ -1,16 +1,470 @@
 use ::std::env;
-use dotenv::dotenv;
-use fintek::{check_tickers, metrics::MetricServer, Markets, StockMarket, Tickers};
-use reqwest::Error;
+use std::collections::{BTreeMap, HashMap};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use fintek::api::ApiState;
+use fintek::auth::{Scope, TokenStore};
+use fintek::bootstrap::{self, BootstrapError, InitOptions};
+use fintek::calendar;
+use fintek::chaos::Chaos;
+use fintek::clock::{Clock, SystemClock};
+use fintek::cluster::Cluster;
+use fintek::config::{self, diff, schema, Config, ConfigError};
+use fintek::consensus::Consensus;
+use fintek::corporate::CorporateCalendar;
+use fintek::daily::DailyPipeline;
+use fintek::dca;
+use fintek::edgar::EdgarWatcher;
+use fintek::encryption::{EncryptionError, StorageKey};
+use fintek::engine::Engine;
+use fintek::events;
+use fintek::health::Health;
+use fintek::limits::{LimitError, LimitsConfig};
+use fintek::movers::MoversFeed;
+use fintek::notes::{NoteStore, NoteUpdate};
+use fintek::notify::eod;
+use fintek::onboard::{self, Candidate, Resolver};
+use fintek::ops::OpsAlerter;
+use fintek::options::OptionsMonitor;
+use fintek::portfolio::{self, PortfolioTracker};
+use fintek::priority::{self, OverBudget};
+use fintek::profile::VolumeProfiles;
+use fintek::provider::{
+    Finnhub, KeyPool, MockProvider, Provider, ProviderConfig, ProviderKind, QuoteProxy,
+    RecordingProvider, Regions, ReplayProvider, RestConfig, RestProvider, Signer, SigningScheme,
+    TwelveData,
+};
+use fintek::quality::DEGRADED_SCORE;
+use fintek::ratelimit::RateLimiter;
+use fintek::recovery::{self, RecoveryError};
+use fintek::reload;
+use fintek::service::{self, ServiceOptions};
+use fintek::share;
+use fintek::sheets::SheetsSync;
+use fintek::sim::{self, Recording};
+use fintek::sink::PriceUpdate;
+use fintek::social::SocialMonitor;
+use fintek::stats::StatsCache;
+use fintek::storage::{
+    self, replica::ReadReplica, sqlite::SqliteStore, AlertStore, DailyStore, PriceStore,
+};
+use fintek::stream::PriceStream;
+use fintek::supervisor;
+use fintek::ticks;
+use fintek::timeseries::gaps::GapRepair;
+use fintek::usage;
+use fintek::{metrics::MetricServer, PricePoint, Tickers};
+use serde_json::Value;
+use tokio::io::BufReader;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[arg(long, short, env = "FINTEK_CONFIG", default_value = config::DEFAULT_PATH)]
+    config: PathBuf,
+    /// Replay a recorded day of ticks (JSON lines) on a virtual clock and print a report
+    #[arg(long, value_name = "RECORDING")]
+    simulate: Option<PathBuf>,
+    /// Append every provider response to a cassette file
+    #[arg(long, value_name = "CASSETTE", conflicts_with = "replay")]
+    record: Option<PathBuf>,
+    /// Serve provider responses from a cassette instead of the network
+    #[arg(long, value_name = "CASSETTE")]
+    replay: Option<PathBuf>,
+    /// Take prices from JSON lines of symbol, price and optional timestamp
+    /// in this file, or stdin for `-`, instead of polling; stops at its end
+    #[arg(long, value_name = "TICKS", conflicts_with_all = ["record", "replay"])]
+    ticks: Option<PathBuf>,
+    /// Write every engine event to stdout as a JSON line, logging to
+    /// stderr instead, e.g. `fintek --events-stdout | jq ...`
+    #[arg(long, global = true)]
+    events_stdout: bool,
+    /// Turn off `[limits]` for this run
+    #[arg(long)]
+    ignore_limits: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Poll prices and serve metrics (default)
+    Run,
+    /// Check the config file and report problems without starting
+    ValidateConfig {
+        /// Also check the provider accepts the API keys
+        #[arg(long)]
+        key: bool,
+    },
+    /// Install fintek as a systemd unit (Linux) or Windows service
+    InstallService {
+        #[arg(long, default_value = "fintek")]
+        name: String,
+        /// Install a per-user unit
+        #[arg(long)]
+        user: bool,
+        /// Environment file holding API_KEY and friends
+        #[arg(long, default_value = ".env")]
+        env_file: PathBuf,
+        /// Print the unit instead of installing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// First-run setup: API key, tickers file and a starter config
+    Init {
+        #[arg(long, env = "API_KEY", hide_env_values = true)]
+        api_key: Option<String>,
+        /// Comma separated symbols to watch
+        #[arg(long, value_delimiter = ',')]
+        tickers: Vec<String>,
+        /// Overwrite an existing config and tickers file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Check the setup and summarize data quality from a running instance
+    Doctor,
+    /// Look names, tickers or ISINs up with the providers and add the
+    /// chosen matches, once they return a price, to the tickers file
+    Onboard {
+        names: Vec<String>,
+        /// Also read names from this file, one per line
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// Take the best match instead of asking
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Manage API tokens
+    Token {
+        #[command(subcommand)]
+        action: TokenAction,
+    },
+    /// Poll a symbol every cycle for a while on a running instance
+    Watch {
+        symbol: String,
+        /// How long to watch for
+        #[arg(long, default_value_t = 120)]
+        minutes: i64,
+        /// End the watch early instead
+        #[arg(long)]
+        stop: bool,
+    },
+    /// Manage notes and tags on symbols
+    Note {
+        #[command(subcommand)]
+        action: NoteAction,
+    },
+    /// Manage the price store's schema
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+    },
+    /// Latest prices from a running instance
+    Price {
+        /// Only these symbols
+        symbols: Vec<String>,
+        /// Fetch the symbols from the provider once instead
+        #[arg(long, requires = "symbols")]
+        fetch: bool,
+        #[arg(long, value_enum, default_value_t = Output::Table)]
+        output: Output,
+    },
+    /// Edit the tickers file
+    Tickers {
+        #[command(subcommand)]
+        action: TickerAction,
+    },
+    /// Share the watchlist with its indicator and alert settings, signed
+    /// with `FINTEK_SHARE_KEY`
+    Watchlist {
+        #[command(subcommand)]
+        action: WatchlistAction,
+    },
+    /// Uptime and data freshness of a running instance
+    Status {
+        #[arg(long, value_enum, default_value_t = Output::Table)]
+        output: Output,
+    },
+    /// Paper portfolio of a running instance, or edit the declared holdings
+    Portfolio {
+        #[arg(long, value_enum, default_value_t = Output::Table)]
+        output: Output,
+        #[command(subcommand)]
+        action: Option<PortfolioAction>,
+    },
+    /// Estimate daily API usage for the config and flag plan limits it would exceed
+    Plan {
+        #[arg(long, value_enum, default_value_t = Output::Table)]
+        output: Output,
+    },
+    /// Simulate the configured `[[dca]]` plans over the stored prices of
+    /// their symbols, best return first
+    Dca {
+        /// How many days back the purchases start
+        #[arg(long, default_value_t = 365)]
+        days: i64,
+        #[arg(long, value_enum, default_value_t = Output::Table)]
+        output: Output,
+    },
+    /// Work with config files
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Print a completion script, e.g. `fintek completions bash > /etc/bash_completion.d/fintek`
+    Completions { shell: Shell },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Output {
+    /// Aligned columns for people
+    Table,
+    /// The API's JSON, for scripts
+    Json,
+}
+
+#[derive(Subcommand)]
+enum TokenAction {
+    /// Create a token and print it once
+    Create {
+        name: String,
+        /// read or admin
+        #[arg(long, default_value = "read")]
+        scope: Scope,
+        /// Upstream fetches a day through the quote proxy, overriding the
+        /// configured default
+        #[arg(long)]
+        quota: Option<u64>,
+        /// Give the token a watchlist, portfolio and alerts of its own
+        #[arg(long)]
+        namespace: Option<String>,
+    },
+    /// List token names and scopes
+    List,
+    /// Revoke a token by name
+    Revoke { name: String },
+}
+
+#[derive(Subcommand)]
+enum NoteAction {
+    /// Create or update a symbol's note
+    Set {
+        symbol: String,
+        /// Replaces the note text
+        text: Option<String>,
+        /// Comma separated tags, replacing the current ones
+        #[arg(long, value_delimiter = ',')]
+        tags: Option<Vec<String>>,
+    },
+    /// Show every note, or one symbol's
+    Show { symbol: Option<String> },
+    /// Delete a symbol's note
+    Remove { symbol: String },
+}
+
+#[derive(Subcommand)]
+enum TickerAction {
+    /// Add symbols, or enable them again if disabled. Bare stock tickers
+    /// listed on several exchanges go in as the `onboard.prefer` listing,
+    /// or the one picked from a list
+    Add {
+        #[arg(required = true)]
+        symbols: Vec<String>,
+        /// Add the symbols as typed, without looking their listings up
+        #[arg(long)]
+        as_is: bool,
+    },
+    /// Remove symbols
+    Remove {
+        #[arg(required = true)]
+        symbols: Vec<String>,
+    },
+    /// List the watched symbols
+    List,
+}
+
+#[derive(Subcommand)]
+enum WatchlistAction {
+    /// Print the watched symbols, indicator settings and alert rules as one
+    /// string another instance can import
+    Export {
+        /// Write it to this file instead
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Add the symbols, indicator periods and alert rules of an export that
+    /// aren't configured yet
+    Import {
+        #[arg(required_unless_present = "file")]
+        export: Option<String>,
+        /// Read the export from this file instead
+        #[arg(long, conflicts_with = "export")]
+        file: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum PortfolioAction {
+    /// Import holdings from a CSV with symbol, quantity, cost (per unit)
+    /// and optional date columns, replacing those of the symbols in it
+    Import { path: PathBuf },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Show what changes between two configs, symbols and rules included,
+    /// and what it does to API usage
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+        #[arg(long, value_enum, default_value_t = Output::Table)]
+        output: Output,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbAction {
+    /// Apply pending migrations, as `run` does on start
+    Migrate,
+    /// List migrations and which are applied
+    Status,
+}
+
 #[tokio::main]
-async fn main() -> Result<(), Error> {
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    if let Some(Command::ValidateConfig { key }) = cli.command {
+        return validate_config(&cli.config, key).await;
+    }
+
+    if let Some(Command::Init {
+        api_key,
+        tickers,
+        force,
+    }) = cli.command
+    {
+        return init(cli.config, api_key, tickers, force).await;
+    }
+
+    if let Some(Command::Doctor) = cli.command {
+        return doctor(&cli.config).await;
+    }
+
+    if let Some(Command::Onboard { names, file, yes }) = cli.command {
+        return onboard(&cli.config, names, file, yes).await;
+    }
+
+    if let Some(Command::Token { action }) = cli.command {
+        return token(&cli.config, action).await;
+    }
+
+    if let Some(Command::Watch {
+        symbol,
+        minutes,
+        stop,
+    }) = cli.command
+    {
+        return watch(&cli.config, &symbol, minutes, stop).await;
+    }
+
+    if let Some(Command::Note { action }) = cli.command {
+        return note(&cli.config, action).await;
+    }
+
+    if let Some(Command::Db { action }) = cli.command {
+        return db(&cli.config, action).await;
+    }
+
+    if let Some(Command::Completions { shell }) = cli.command {
+        clap_complete::generate(shell, &mut Cli::command(), "fintek", &mut std::io::stdout());
+        return ExitCode::SUCCESS;
+    }
+
+    if let Some(Command::Config {
+        action: ConfigAction::Diff { old, new, output },
+    }) = cli.command
+    {
+        return config_diff(&old, &new, output);
+    }
+
+    if let Some(Command::Dca { days, output }) = cli.command {
+        return dca(&cli.config, days, output).await;
+    }
+
+    if let Some(Command::Plan { output }) = cli.command {
+        return plan(&cli.config, output).await;
+    }
+
+    if let Some(Command::Price {
+        symbols,
+        fetch,
+        output,
+    }) = cli.command
+    {
+        if fetch {
+            return fetch_price(&cli.config, &symbols, output).await;
+        }
+        return price(&cli.config, &symbols, output).await;
+    }
+
+    if let Some(Command::Tickers { action }) = cli.command {
+        return tickers(&cli.config, action).await;
+    }
+
+    if let Some(Command::Watchlist { action }) = cli.command {
+        return watchlist(&cli.config, action).await;
+    }
+
+    if let Some(Command::Status { output }) = cli.command {
+        return status(&cli.config, output).await;
+    }
+
+    if let Some(Command::Portfolio { output, action }) = cli.command {
+        return match action {
+            Some(PortfolioAction::Import { path }) => import_portfolio(&cli.config, &path).await,
+            None => portfolio(&cli.config, output).await,
+        };
+    }
+
+    if let Some(Command::InstallService {
+        name,
+        user,
+        env_file,
+        dry_run,
+    }) = cli.command
+    {
+        return install_service(&cli.config, name, user, &env_file, dry_run);
+    }
+
     let filter = EnvFilter::new(std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()));
 
+    #[cfg(feature = "sentry")]
+    let _sentry = init_sentry();
+    #[cfg(feature = "sentry")]
+    let sentry_layer = Some(sentry_tracing::layer().enable_span_attributes());
+    #[cfg(not(feature = "sentry"))]
+    let sentry_layer: Option<tracing_subscriber::layer::Identity> = None;
+
     tracing_subscriber::registry()
         .with(filter)
+        .with(sentry_layer)
         .with(
             tracing_subscriber::fmt::layer()
+                .with_writer(if cli.events_stdout {
+                    BoxMakeWriter::new(std::io::stderr)
+                } else {
+                    BoxMakeWriter::new(std::io::stdout)
+                })
                 .without_time()
                 .with_thread_ids(true)
                 .with_target(true)
@@ -20,38 +474,2094 @@ async fn main() -> Result<(), Error> {
         )
         .init();
 
-    tokio::spawn(async move {
-        MetricServer::start(([127, 0, 0, 1], 9091).into()).await;
-    });
-    dotenv().ok();
-    let api_key = env::var("API_KEY").expect("API_KEY must be set");
+    // Before the config, so `.env` can hold `FINTEK__` overrides too.
+    bootstrap::load_env();
+    let mut config = match Config::load(&cli.config).await {
+        Ok(config) => config,
+        Err(ConfigError::Invalid(diagnostics)) => {
+            let source = std::fs::read_to_string(&cli.config).unwrap_or_default();
+            let file = cli.config.display().to_string();
+            eprint!("{}", schema::render(&source, &file, &diagnostics));
+            return ExitCode::FAILURE;
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to load config");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Some(recording) = cli.simulate {
+        return simulate(&recording, &config).await;
+    }
 
-    let mut tickers = Tickers::init().await;
+    if cli.ignore_limits {
+        tracing::warn!("Running without limits");
+        config.limits = LimitsConfig::off();
+    }
+    let traffic = Traffic {
+        record: cli.record,
+        replay: cli.replay,
+        ticks: cli.ticks,
+    };
+    match run(config, traffic, cli.events_stdout).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            tracing::error!(error = %e, "Exiting");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn validate_config(path: &Path, key: bool) -> ExitCode {
+    let file = path.display().to_string();
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("{}: {}", file, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    match Config::parse(&source) {
+        Ok((config, diagnostics)) => {
+            eprint!("{}", schema::render(&source, &file, &diagnostics));
+            println!("{}: ok", file);
+            if key {
+                return check_keys(&config.provider).await;
+            }
+            ExitCode::SUCCESS
+        }
+        Err(ConfigError::Invalid(diagnostics)) => {
+            eprint!("{}", schema::render(&source, &file, &diagnostics));
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("{}: {}", file, e);
+            ExitCode::FAILURE
+        }
+    }
+}
 
+// Twelve Data keys are probed, which costs no credits; a Finnhub key is
+// only checked to be set.
+async fn check_keys(urls: &ProviderConfig) -> ExitCode {
+    bootstrap::load_env();
+    bootstrap::use_config_key(urls.api_key.as_deref());
+    let kind = match bootstrap::provider_kind(urls.kind) {
+        Ok(kind) => kind,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let keys = match kind {
+        ProviderKind::TwelveData => bootstrap::api_keys(),
+        ProviderKind::Finnhub => bootstrap::finnhub_key().map(|key| vec![key]),
+        ProviderKind::Rest => {
+            let set = bootstrap::rest_key().is_some();
+            println!("REST_API_KEY: {}", if set { "set" } else { "not set" });
+            if urls.rest.signing.scheme != SigningScheme::None {
+                let set = bootstrap::rest_secret().is_some();
+                println!("REST_API_SECRET: {}", if set { "set" } else { "not set" });
+            }
+            return ExitCode::SUCCESS;
+        }
+    };
+    let keys = match keys {
+        Ok(keys) => keys,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if kind == ProviderKind::Finnhub {
+        println!("FINNHUB_API_KEY: set");
+        return ExitCode::SUCCESS;
+    }
+    let mut valid = true;
+    let pool = KeyPool::new(keys).with_base_url(&urls.twelvedata_url);
+    for (key, result) in pool.verify().await {
+        let state = match result {
+            Some(true) => "valid",
+            Some(false) => "rejected",
+            None => "unreachable",
+        };
+        valid &= result == Some(true);
+        println!("key {}: {}", key, state);
+    }
+    if valid {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+async fn init(
+    config: PathBuf,
+    api_key: Option<String>,
+    symbols: Vec<String>,
+    force: bool,
+) -> ExitCode {
+    bootstrap::load_env();
+    let tickers = Config::load(&config)
+        .await
+        .map(|c| c.tickers_path)
+        .unwrap_or_else(|_| Config::default().tickers_path);
+    let options = InitOptions {
+        config,
+        tickers,
+        api_key,
+        symbols,
+        force,
+    };
+    match bootstrap::init(options) {
+        Ok(written) => {
+            for path in written {
+                println!("Wrote {}", path.display());
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn install_service(
+    config: &Path,
+    name: String,
+    user: bool,
+    env_file: &Path,
+    dry_run: bool,
+) -> ExitCode {
+    let absolute = |path: &Path| std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let options = ServiceOptions {
+        name,
+        executable: env::current_exe().unwrap_or_else(|_| PathBuf::from("fintek")),
+        config: absolute(config),
+        working_dir: absolute(Path::new(".")),
+        env_file: absolute(env_file),
+        user,
+    };
+    if dry_run {
+        print!("{}", service::systemd_unit(&options));
+        return ExitCode::SUCCESS;
+    }
+    match service::install(&options) {
+        Ok(path) => {
+            println!("Installed {}", path.display());
+            if cfg!(windows) {
+                println!("Start it with: sc.exe start {}", options.name);
+            } else {
+                let user = if options.user { "--user " } else { "" };
+                println!(
+                    "Start it with: systemctl {}enable --now {}",
+                    user, options.name
+                );
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Failed to install service: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Lists the matches and asks for one; `None` skips the name.
+fn pick<'a>(name: &str, candidates: &'a [Candidate]) -> Option<&'a Candidate> {
+    println!("{}:", name);
+    for (i, c) in candidates.iter().enumerate() {
+        println!(
+            "  {:>2}) {:<10} {:<32} {:<8} {:<4} {} ({})",
+            i + 1,
+            c.ticker,
+            c.meta.name,
+            c.meta.exchange,
+            c.meta.currency,
+            c.meta.kind,
+            c.meta.source
+        );
+    }
+    let question = format!("Pick 1-{}, Enter for 1, s to skip", candidates.len());
     loop {
-        let night_time = fintek::should_sleep(Markets::Stock(StockMarket::NYSE), &api_key)
+        let answer = bootstrap::prompt(&question).unwrap_or_else(|| "1".into());
+        if answer.eq_ignore_ascii_case("s") {
+            return None;
+        }
+        match answer.parse::<usize>() {
+            Ok(n) if (1..=candidates.len()).contains(&n) => return Some(&candidates[n - 1]),
+            _ => println!("Not a choice: {}", answer),
+        }
+    }
+}
+
+// Searches every provider there's a key for, failing when there's none.
+fn resolver(config: &Config) -> Result<(Resolver, Arc<RateLimiter>), BootstrapError> {
+    let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+    let limiter = Arc::new(RateLimiter::new(&config.rate_limits, clock));
+    let mut resolver = Resolver::new(limiter.clone());
+    let twelvedata = bootstrap::api_key();
+    if let Ok(key) = &twelvedata {
+        resolver = resolver.with_twelvedata(&config.provider.twelvedata_url, key);
+    }
+    let finnhub = bootstrap::finnhub_key();
+    if let Ok(token) = &finnhub {
+        resolver = resolver.with_finnhub(&config.provider.finnhub_url, token);
+    }
+    match (twelvedata, finnhub) {
+        (Err(e), Err(_)) => Err(e),
+        _ => Ok((resolver, limiter)),
+    }
+}
+
+async fn onboard(
+    path: &Path,
+    mut names: Vec<String>,
+    file: Option<PathBuf>,
+    yes: bool,
+) -> ExitCode {
+    bootstrap::load_env();
+    let config = match Config::load(path).await {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Some(file) = file {
+        match std::fs::read_to_string(&file) {
+            Ok(text) => names.extend(
+                text.lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(String::from),
+            ),
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", file.display(), e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    if names.is_empty() {
+        eprintln!("Nothing to onboard; pass names, tickers or ISINs, or --file");
+        return ExitCode::FAILURE;
+    }
+    if !yes && !std::io::stdin().is_terminal() {
+        eprintln!("Not a terminal; pass --yes to take the best match");
+        return ExitCode::FAILURE;
+    }
+
+    let (resolver, limiter) = match resolver(&config) {
+        Ok(resolver) => resolver,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let traffic = Traffic {
+        record: None,
+        replay: None,
+        ticks: None,
+    };
+    let ops = Arc::new(OpsAlerter::new(config.ops.clone()));
+    let provider = match provider(&traffic, &config.provider, ops).await {
+        Ok((provider, _, _)) => provider,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    fintek::set_tickers_path(&config.tickers_path);
+    let mut tickers = match bootstrap::load_tickers(&config.tickers_path) {
+        Ok(tickers) => tickers,
+        Err(BootstrapError::MissingTickers(_)) => Tickers::default(),
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let (mut added, mut failed) = (0, 0);
+    for name in &names {
+        let candidates = resolver.resolve(name).await;
+        if candidates.is_empty() {
+            println!("{}: no match", name);
+            failed += 1;
+            continue;
+        }
+        let chosen = if yes {
+            candidates.first()
+        } else {
+            pick(name, &candidates)
+        };
+        let Some(Candidate {
+            ticker,
+            exchange,
+            meta,
+        }) = chosen
+        else {
+            println!("{}: skipped", name);
+            continue;
+        };
+        if tickers.get_tickers().contains(ticker) {
+            println!("{}: {} is already a ticker", name, ticker);
+            continue;
+        }
+        limiter.acquire(1).await;
+        match provider.fetch_price(ticker).await {
+            Ok(Some(quote)) => {
+                println!(
+                    "{}: added {} ({}, {}) at {}",
+                    name, ticker, meta.name, meta.exchange, quote.price
+                );
+                tickers.add(ticker);
+                tickers.set_metadata(ticker, meta.clone());
+                if let Some(exchange) = exchange.filter(|_| onboard::is_bare(ticker)) {
+                    tickers.set_exchange(ticker, exchange);
+                }
+                added += 1;
+            }
+            Ok(None) => {
+                println!(
+                    "{}: {} has no price from {}, not added",
+                    name,
+                    ticker,
+                    provider.name()
+                );
+                failed += 1;
+            }
+            Err(e) => {
+                println!("{}: failed to price {}: {}", name, ticker, e);
+                failed += 1;
+            }
+        }
+    }
+    if added > 0 {
+        if let Err(e) = tickers.save().await {
+            eprintln!("Failed to write {}: {}", config.tickers_path.display(), e);
+            return ExitCode::FAILURE;
+        }
+        println!("Wrote {} ({} added)", config.tickers_path.display(), added);
+    }
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+async fn doctor(path: &Path) -> ExitCode {
+    bootstrap::load_env();
+    let mut healthy = true;
+    let mut check = |name: &str, result: Result<String, String>| match result {
+        Ok(detail) => println!("ok    {:<10} {}", name, detail),
+        Err(detail) => {
+            healthy = false;
+            println!("FAIL  {:<10} {}", name, detail);
+        }
+    };
+
+    let config = match Config::load(path).await {
+        Ok(config) => {
+            check("config", Ok(path.display().to_string()));
+            config
+        }
+        Err(e) => {
+            check("config", Err(e.to_string()));
+            Config::default()
+        }
+    };
+    check(
+        "api key",
+        bootstrap::api_key()
+            .map(|_| "API_KEY is set".into())
+            .map_err(|e| e.to_string()),
+    );
+    check(
+        "tickers",
+        bootstrap::load_tickers(&config.tickers_path)
+            .map(|t| format!("{} symbols", t.get_tickers().len()))
+            .map_err(|e| e.to_string()),
+    );
+
+    let url = format!("http://{}/api/v1/quality", config.metrics.addr);
+    let mut request = reqwest::Client::new().get(&url);
+    if let Ok(token) = env::var("FINTEK_TOKEN") {
+        request = request.bearer_auth(token);
+    }
+    let reports = match request.send().await.and_then(|r| r.error_for_status()) {
+        Ok(response) => response.text().await.unwrap_or_default(),
+        Err(e) => {
+            check("instance", Err(format!("{}: {}", url, e)));
+            return if healthy {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            };
+        }
+    };
+    check("instance", Ok(url));
+    let reports: Vec<serde_json::Value> = serde_json::from_str(&reports).unwrap_or_default();
+
+    println!();
+    println!(
+        "{:<12} {:>6} {:>8} {:>10} {:>9}",
+        "symbol", "score", "success", "stale (s)", "anomalies"
+    );
+    for report in &reports {
+        let score = report["score"].as_f64().unwrap_or_default();
+        println!(
+            "{:<12} {:>6.2} {:>7.0}% {:>10} {:>9}{}",
+            report["symbol"].as_str().unwrap_or_default(),
+            score,
+            report["success_rate"].as_f64().unwrap_or_default() * 100.,
+            report["staleness_secs"].as_i64().unwrap_or_default(),
+            report["anomalies"].as_u64().unwrap_or_default(),
+            if score < DEGRADED_SCORE {
+                "  <- degraded"
+            } else {
+                ""
+            }
+        );
+    }
+
+    if healthy {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+async fn token(path: &Path, action: TokenAction) -> ExitCode {
+    let config = match Config::load(path).await {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let store = TokenStore::new(&config.state_dir);
+    let result = match action {
+        TokenAction::Create {
+            name,
+            scope,
+            quota,
+            namespace,
+        } => store
+            .create(&name, scope, quota, namespace.as_deref())
+            .map(|token| {
+                println!("{}", token);
+                eprintln!("Store this token now, it cannot be shown again.");
+            }),
+        TokenAction::List => store.load().map(|tokens| {
+            for t in tokens {
+                let quota = t.quota.map_or("-".into(), |q| q.to_string());
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    t.name,
+                    t.scope,
+                    quota,
+                    t.namespace.as_deref().unwrap_or("-"),
+                    t.created_at.to_rfc3339()
+                );
+            }
+        }),
+        TokenAction::Revoke { name } => store.revoke(&name),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn watch(path: &Path, symbol: &str, minutes: i64, stop: bool) -> ExitCode {
+    bootstrap::load_env();
+    let config = match Config::load(path).await {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let url = format!("http://{}/api/v1/watch/{}", config.metrics.addr, symbol);
+    let client = reqwest::Client::new();
+    let mut request = if stop {
+        client.delete(&url)
+    } else {
+        client
+            .post(&url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(serde_json::json!({ "minutes": minutes }).to_string())
+    };
+    if let Ok(token) = env::var("FINTEK_TOKEN") {
+        request = request.bearer_auth(token);
+    }
+    match request.send().await {
+        Ok(response) if response.status().is_success() => {
+            let body = response.text().await.unwrap_or_default();
+            match serde_json::from_str::<serde_json::Value>(&body) {
+                Ok(watch) => println!(
+                    "Watching {} closely until {}",
+                    symbol,
+                    watch["until"].as_str().unwrap_or_default()
+                ),
+                Err(_) => println!("Stopped watching {}", symbol),
+            }
+            ExitCode::SUCCESS
+        }
+        Ok(response) => {
+            let status = response.status();
+            eprintln!(
+                "{}: {} {}",
+                url,
+                status,
+                response.text().await.unwrap_or_default()
+            );
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("{}: {}", url, e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn note(path: &Path, action: NoteAction) -> ExitCode {
+    let config = match Config::load(path).await {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let store = NoteStore::new(&config.state_dir);
+    let result = match action {
+        NoteAction::Set { symbol, text, tags } => store
+            .set(&symbol, NoteUpdate { text, tags })
+            .map(|note| println!("{}", note.line(&symbol))),
+        NoteAction::Show { symbol } => store.load().map(|notes| {
+            for (s, note) in notes {
+                if symbol.as_ref().is_none_or(|symbol| *symbol == s) {
+                    println!("{}\t{}", note.updated_at.to_rfc3339(), note.line(&s));
+                }
+            }
+        }),
+        NoteAction::Remove { symbol } => store.remove(&symbol),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn tickers(path: &Path, action: TickerAction) -> ExitCode {
+    let config = match Config::load(path).await {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let file = &config.tickers_path;
+    let loaded = match bootstrap::load_tickers(file) {
+        Err(BootstrapError::MissingTickers(_)) if matches!(action, TickerAction::Add { .. }) => {
+            Ok(Tickers::default())
+        }
+        loaded => loaded,
+    };
+    let mut tickers = match loaded {
+        Ok(tickers) => tickers,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut ok = true;
+    match action {
+        TickerAction::List => {
+            for symbol in tickers.get_tickers() {
+                println!("{}", symbol);
+            }
+            return ExitCode::SUCCESS;
+        }
+        TickerAction::Add { symbols, as_is } => {
+            bootstrap::load_env();
+            // Without a provider key bare tickers go in as typed.
+            let resolver = match as_is {
+                false => resolver(&config).ok().map(|(resolver, _)| resolver),
+                true => None,
+            };
+            let prefer = match config.onboard.prefer.as_slice() {
+                [] => vec![config.exchange],
+                prefer => prefer.to_vec(),
+            };
+            let interactive = std::io::stdin().is_terminal();
+            for symbol in symbols.iter().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+                let listings = match &resolver {
+                    Some(resolver)
+                        if onboard::is_bare(symbol)
+                            && !tickers.get_tickers().contains(&symbol.to_string()) =>
+                    {
+                        resolver.listings(symbol).await
+                    }
+                    _ => vec![],
+                };
+                let listing = match listings.as_slice() {
+                    [] => None,
+                    [only] => Some(only),
+                    _ => match onboard::preferred(&listings, &prefer) {
+                        Some(listing) => Some(listing),
+                        None if interactive => match pick(symbol, &listings) {
+                            Some(listing) => Some(listing),
+                            None => {
+                                println!("{}: skipped", symbol);
+                                continue;
+                            }
+                        },
+                        None => listings.first(),
+                    },
+                };
+                let Some(Candidate {
+                    ticker,
+                    exchange,
+                    meta,
+                }) = listing
+                else {
+                    match tickers.add(symbol) {
+                        true => println!("Added {}", symbol),
+                        false => println!("{} is already watched", symbol),
+                    }
+                    continue;
+                };
+                match tickers.add(ticker) {
+                    true => println!("Added {} ({}, {})", ticker, meta.exchange, meta.currency),
+                    false => println!("{} is already watched", ticker),
+                }
+                tickers.set_metadata(ticker, meta.clone());
+                if let Some(exchange) = exchange.filter(|_| onboard::is_bare(ticker)) {
+                    tickers.set_exchange(ticker, exchange);
+                }
+            }
+        }
+        TickerAction::Remove { symbols } => {
+            for symbol in &symbols {
+                match tickers.remove(symbol) {
+                    true => println!("Removed {}", symbol),
+                    false => {
+                        ok = false;
+                        eprintln!("{} isn't in {}", symbol, file.display());
+                    }
+                }
+            }
+        }
+    }
+    // A running instance picks the change up when it next reads the file.
+    fintek::set_tickers_path(file);
+    if let Err(e) = tickers.save().await {
+        eprintln!("{}: {}", file.display(), e);
+        return ExitCode::FAILURE;
+    }
+    if ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+async fn watchlist(path: &Path, action: WatchlistAction) -> ExitCode {
+    bootstrap::load_env();
+    let config = match Config::load(path).await {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let key = match bootstrap::share_key() {
+        Ok(key) => key,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let file = &config.tickers_path;
+    let loaded = match bootstrap::load_tickers(file) {
+        Err(BootstrapError::MissingTickers(_))
+            if matches!(action, WatchlistAction::Import { .. }) =>
+        {
+            Ok(Tickers::default())
+        }
+        loaded => loaded,
+    };
+    let mut tickers = match loaded {
+        Ok(tickers) => tickers,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let imported = match action {
+        WatchlistAction::Export { file } => {
+            let shared = share::Shared::new(&tickers, &config.indicators, &config.alerts);
+            let Some(file) = file else {
+                println!("{}", share::export(&shared, key.as_bytes()));
+                return ExitCode::SUCCESS;
+            };
+            if let Err(e) = share::export_to_file(&shared, key.as_bytes(), &file).await {
+                eprintln!("{}: {}", file.display(), e);
+                return ExitCode::FAILURE;
+            }
+            println!(
+                "Exported {} symbols to {}",
+                shared.tickers.len(),
+                file.display()
+            );
+            return ExitCode::SUCCESS;
+        }
+        WatchlistAction::Import {
+            file: Some(file), ..
+        } => share::import_from_file(key.as_bytes(), &file)
             .await
-            .unwrap_or_default();
+            .map_err(|e| format!("{}: {}", file.display(), e)),
+        WatchlistAction::Import { export, .. } => {
+            share::import(&export.unwrap_or_default(), key.as_bytes()).map_err(|e| e.to_string())
+        }
+    };
+    let shared = match imported {
+        Ok(shared) => shared,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    // The config first, so a file that doesn't parse leaves both untouched.
+    let text = match tokio::fs::read_to_string(path).await {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let (text, rules) = match share::merge_config(&text, &shared) {
+        Ok(merged) => merged,
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(e) = tokio::fs::write(path, text).await {
+        eprintln!("{}: {}", path.display(), e);
+        return ExitCode::FAILURE;
+    }
+    for symbol in &shared.tickers {
+        if !tickers.get_tickers().contains(symbol) {
+            println!("Added {}", symbol);
+        }
+    }
+    for rule in &rules {
+        println!("Added alert rule {}", rule);
+    }
+    share::merge(&mut tickers, &shared.tickers);
+    // A running instance picks the change up when it next reads the file.
+    fintek::set_tickers_path(file);
+    if let Err(e) = tickers.save().await {
+        eprintln!("{}: {}", file.display(), e);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
 
-        tokio::time::sleep(tokio::time::Duration::from_secs(night_time)).await;
+async fn db(path: &Path, action: DbAction) -> ExitCode {
+    bootstrap::load_env();
+    let config = match Config::load(path).await {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let Some(store_path) = &config.storage.path else {
+        eprintln!("No price store configured, set storage.path");
+        return ExitCode::FAILURE;
+    };
+    let result = async {
+        let key = StorageKey::load(&config.encryption).map_err(|e| e.to_string())?;
+        let store = SqliteStore::connect(store_path, key.as_ref(), &config.storage.sqlite)
+            .await
+            .map_err(|e| e.to_string())?;
+        match action {
+            DbAction::Migrate => {
+                let applied = store.migrate().await.map_err(|e| e.to_string())?;
+                if applied.is_empty() {
+                    println!("Up to date");
+                }
+                for version in applied {
+                    println!("Applied {}", version);
+                }
+            }
+            DbAction::Status => {
+                for migration in store.migrations().await.map_err(|e| e.to_string())? {
+                    let state = migration
+                        .applied_at
+                        .map_or("pending".to_string(), |at| at.to_rfc3339());
+                    println!(
+                        "{}\t{}\t{}",
+                        migration.version, state, migration.description
+                    );
+                }
+            }
+        }
+        Ok::<(), String>(())
+    };
+    match result.await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}: {}", store_path.display(), e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// GETs `api` from the instance the config at `path` points at.
+async fn query(path: &Path, api: &str) -> Result<Value, String> {
+    bootstrap::load_env();
+    let config = Config::load(path)
+        .await
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    let url = format!("http://{}{}", config.metrics.addr, api);
+    let mut request = reqwest::Client::new().get(&url);
+    if let Ok(token) = env::var("FINTEK_TOKEN") {
+        request = request.bearer_auth(token);
+    }
+    let body = match request.send().await.and_then(|r| r.error_for_status()) {
+        Ok(response) => response.text().await,
+        Err(e) => Err(e),
+    }
+    .map_err(|e| format!("{}: {}", url, e))?;
+    serde_json::from_str(&body).map_err(|e| format!("{}: {}", url, e))
+}
+
+/// Prints a query result as JSON, or through `table` for people.
+fn show(result: Result<Value, String>, output: Output, table: impl FnOnce(&Value)) -> ExitCode {
+    match result {
+        Ok(value) => {
+            match output {
+                Output::Json => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&value).expect("Failed to serialize response")
+                ),
+                Output::Table => table(&value),
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Floats to two places, `-` where the API has nothing yet.
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => "-".into(),
+        Value::Number(n) if n.is_f64() => format!("{:.2}", n.as_f64().unwrap_or_default()),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn price_table(prices: &Value) {
+    println!("{:<12} {:>12}  as of", "symbol", "price");
+    for p in prices.as_array().into_iter().flatten() {
+        println!(
+            "{:<12} {:>12}  {}",
+            cell(&p["symbol"]),
+            cell(&p["price"]),
+            cell(&p["timestamp"])
+        );
+    }
+}
+
+async fn price(path: &Path, symbols: &[String], output: Output) -> ExitCode {
+    let prices = query(path, "/api/v1/prices").await.map(|prices| {
+        prices
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|p| symbols.is_empty() || symbols.iter().any(|s| p["symbol"] == **s))
+            .cloned()
+            .collect()
+    });
+    show(prices, output, price_table)
+}
+
+async fn fetch_price(path: &Path, symbols: &[String], output: Output) -> ExitCode {
+    bootstrap::load_env();
+    let config = match Config::load(path).await {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    bootstrap::use_config_key(config.provider.api_key.as_deref());
+    let traffic = Traffic {
+        record: None,
+        replay: None,
+        ticks: None,
+    };
+    let ops = Arc::new(OpsAlerter::new(config.ops.clone()));
+    let provider = match provider(&traffic, &config.provider, ops).await {
+        Ok((provider, _, _)) => provider,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let quotes = match provider.fetch_prices(symbols).await {
+        Ok(quotes) => quotes,
+        Err(e) => {
+            eprintln!("{}: {}", provider.name(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let now = SystemClock.now();
+    let mut missing = false;
+    let mut prices = vec![];
+    for (symbol, quote) in symbols.iter().zip(quotes) {
+        let quote = match quote {
+            Ok(Some(quote)) => quote,
+            Ok(None) => {
+                missing = true;
+                eprintln!("No price for {}", symbol);
+                continue;
+            }
+            Err(e) => {
+                missing = true;
+                eprintln!("{}: {}: {}", provider.name(), symbol, e);
+                continue;
+            }
+        };
+        prices.push(PriceUpdate {
+            symbol: symbol.clone(),
+            price: quote.price,
+            timestamp: now,
+            provider_timestamp: quote.timestamp,
+            day: quote.day,
+        });
+    }
+    let value = serde_json::to_value(&prices).expect("Failed to serialize prices");
+    let printed = show(Ok(value), output, price_table);
+    if missing {
+        ExitCode::FAILURE
+    } else {
+        printed
+    }
+}
+
+async fn status(path: &Path, output: Output) -> ExitCode {
+    show(query(path, "/api/v1/status").await, output, |status| {
+        for (name, key) in [
+            ("version", "version"),
+            ("started", "started_at"),
+            ("uptime (s)", "uptime_secs"),
+            ("symbols", "symbols"),
+            ("last price", "last_price_at"),
+            ("degraded", "degraded"),
+            ("watches", "close_watches"),
+            ("equity", "equity"),
+        ] {
+            println!("{:<12} {}", name, cell(&status[key]));
+        }
+    })
+}
+
+async fn portfolio(path: &Path, output: Output) -> ExitCode {
+    let result = query(path, "/api/v1/paper/positions").await;
+    show(result, output, |portfolio| {
+        println!(
+            "{:<12} {:>4} {:>10} {:>10} {:>10} {:>12} {:>10}",
+            "symbol", "ccy", "quantity", "avg cost", "last", "value", "unrealized"
+        );
+        for p in portfolio["positions"].as_array().into_iter().flatten() {
+            println!(
+                "{:<12} {:>4} {:>10} {:>10} {:>10} {:>12} {:>10}",
+                cell(&p["symbol"]),
+                cell(&p["currency"]),
+                cell(&p["quantity"]),
+                cell(&p["average_cost"]),
+                cell(&p["last_price"]),
+                cell(&p["market_value"]),
+                cell(&p["unrealized_pnl"])
+            );
+        }
+        println!();
+        println!("cash   {}", cell(&portfolio["cash"]));
+        println!("equity {}", cell(&portfolio["equity"]));
+    })
+}
+
+async fn import_portfolio(path: &Path, csv: &Path) -> ExitCode {
+    let config = match Config::load(path).await {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let data = match std::fs::read_to_string(csv) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("{}: {}", csv.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let holdings = match portfolio::parse_csv(&data) {
+        Ok(holdings) => holdings,
+        Err(errors) => {
+            for e in &errors {
+                eprintln!("{}: {}", csv.display(), e);
+            }
+            eprintln!("Nothing imported");
+            return ExitCode::FAILURE;
+        }
+    };
+    let count = holdings.len();
+    let tracker = PortfolioTracker::new(&config.state_dir);
+    if let Err(e) = tracker.import(holdings) {
+        eprintln!("{}", e);
+        return ExitCode::FAILURE;
+    }
+    println!(
+        "Imported {} holdings into {}",
+        count,
+        config.state_dir.join(portfolio::PORTFOLIO_FILE).display()
+    );
+    ExitCode::SUCCESS
+}
+
+async fn plan(path: &Path, output: Output) -> ExitCode {
+    bootstrap::load_env();
+    let config = match Config::load(path).await {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let tickers = match bootstrap::load_tickers(&config.tickers_path) {
+        Ok(tickers) => tickers,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let keys = usage::Keys {
+        standbys: bootstrap::api_keys().map_or(0, |keys| keys.len() - 1),
+        finnhub: bootstrap::finnhub_key().is_ok(),
+    };
+    let estimate = usage::estimate(&config, tickers.get_tickers(), keys);
+    let value = serde_json::to_value(&estimate).expect("Failed to serialize estimate");
+    let printed = show(Ok(value), output, |_| {
+        println!("{} symbols", estimate.symbols);
+        println!();
+        println!(
+            "{:<12} {:<18} {:>12} {:>12} {:>8}",
+            "provider", "traffic", "requests/day", "credits/day", "burst"
+        );
+        for source in &estimate.sources {
+            println!(
+                "{:<12} {:<18} {:>12.0} {:>12.0} {:>8}",
+                source.provider,
+                source.name,
+                source.requests_per_day,
+                source.credits_per_day,
+                source.burst
+            );
+        }
+        println!();
+        for limit in &estimate.limits {
+            println!(
+                "{:<5} {} per {}s, busiest window needs {:.0}",
+                if limit.exceeded { "OVER" } else { "ok" },
+                limit.requests,
+                limit.period_secs,
+                limit.estimated
+            );
+        }
+        if let Some(cycle) = &estimate.cycle {
+            println!(
+                "{:<5} a cycle over every symbol takes {:.0}s of {}s, {} symbols per cycle",
+                if cycle.exceeded() { "OVER" } else { "ok" },
+                cycle.needed_secs,
+                cycle.max_secs,
+                cycle.per_cycle
+            );
+        }
+        for warning in &estimate.warnings {
+            println!("warn  {}", warning);
+        }
+    });
+    if estimate.exceeded() {
+        return ExitCode::FAILURE;
+    }
+    printed
+}
+
+async fn dca(path: &Path, days: i64, output: Output) -> ExitCode {
+    bootstrap::load_env();
+    let config = match Config::load(path).await {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if config.dca.is_empty() {
+        eprintln!("No DCA plans configured, add [[dca]] entries");
+        return ExitCode::FAILURE;
+    }
+    let Some(store_path) = &config.storage.path else {
+        eprintln!("No price store configured, set storage.path");
+        return ExitCode::FAILURE;
+    };
+    let result = async {
+        let key = StorageKey::load(&config.encryption).map_err(|e| e.to_string())?;
+        let store = SqliteStore::open(store_path, key.as_ref(), &config.storage.sqlite)
+            .await
+            .map_err(|e| format!("{}: {}", store_path.display(), e))?;
+        let to = Utc::now();
+        let from = to - chrono::Duration::days(days);
+        let mut histories = HashMap::new();
+        for plan in &config.dca {
+            if histories.contains_key(&plan.symbol) {
+                continue;
+            }
+            let prices = store
+                .range(&plan.symbol, from, to)
+                .await
+                .map_err(|e| format!("{}: {}", store_path.display(), e))?;
+            let history: Vec<PricePoint> = prices
+                .into_iter()
+                .map(|p| PricePoint {
+                    timestamp: p.timestamp,
+                    price: p.price,
+                })
+                .collect();
+            histories.insert(plan.symbol.clone(), history);
+        }
+        let reports = dca::compare(&config.dca, &histories);
+        Ok(serde_json::to_value(&reports).expect("Failed to serialize reports"))
+    };
+    show(result.await, output, |reports| {
+        println!(
+            "{:<12} {:<9} {:>9} {:>12} {:>12} {:>12} {:>9}",
+            "symbol", "schedule", "purchases", "invested", "avg cost", "value", "return"
+        );
+        for report in reports.as_array().into_iter().flatten() {
+            println!(
+                "{:<12} {:<9} {:>9} {:>12} {:>12} {:>12} {:>8.2}%",
+                cell(&report["symbol"]),
+                cell(&report["schedule"]),
+                cell(&report["purchases"]),
+                cell(&report["invested"]),
+                cell(&report["average_cost"]),
+                cell(&report["final_value"]),
+                report["total_return"].as_f64().unwrap_or_default() * 100.
+            );
+        }
+    })
+}
+
+// Configs as written, without env overrides, and the symbols of the
+// tickers file each points at.
+fn load_for_diff(path: &Path) -> Result<(Config, Vec<String>), String> {
+    let source = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let (config, _) = Config::parse(&source).map_err(|e| match e {
+        ConfigError::Invalid(diagnostics) => {
+            schema::render(&source, &path.display().to_string(), &diagnostics)
+        }
+        e => format!("{}: {}", path.display(), e),
+    })?;
+    let symbols = match bootstrap::load_tickers(&config.tickers_path) {
+        Ok(tickers) => tickers.get_tickers().to_vec(),
+        Err(e) => return Err(e.to_string()),
+    };
+    Ok((config, symbols))
+}
+
+fn config_diff(old: &Path, new: &Path, output: Output) -> ExitCode {
+    bootstrap::load_env();
+    let loaded = load_for_diff(old).and_then(|old| Ok((old, load_for_diff(new)?)));
+    let ((old, old_symbols), (new, new_symbols)) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let keys = usage::Keys {
+        standbys: bootstrap::api_keys().map_or(0, |keys| keys.len() - 1),
+        finnhub: bootstrap::finnhub_key().is_ok(),
+    };
+    let diff = diff::diff(&old, &old_symbols, &new, &new_symbols, keys);
+    let value = serde_json::to_value(&diff).expect("Failed to serialize diff");
+    let printed = show(Ok(value), output, |_| {
+        if diff.is_empty() {
+            println!("no changes");
+        }
+        for symbol in &diff.added_symbols {
+            println!("+ {}", symbol);
+        }
+        for symbol in &diff.removed_symbols {
+            println!("- {}", symbol);
+        }
+        if !diff.changes.is_empty() {
+            println!();
+            println!("{:<9} {:<40} {:>20}    new", "kind", "key", "old");
+        }
+        for change in &diff.changes {
+            let side = |value: &Option<Value>| value.as_ref().map_or("-".into(), cell);
+            let kind = serde_json::to_value(change.kind).expect("Failed to serialize kind");
+            println!(
+                "{:<9} {:<40} {:>20} -> {}",
+                cell(&kind),
+                change.path,
+                side(&change.old),
+                side(&change.new)
+            );
+        }
+        if !diff.credits.is_empty() {
+            println!();
+            println!(
+                "{:<12} {:>12} {:>12} {:>10}",
+                "provider", "credits/day", "new", "change"
+            );
+        }
+        for credits in &diff.credits {
+            println!(
+                "{:<12} {:>12.0} {:>12.0} {:>+10.0}",
+                credits.provider,
+                credits.old,
+                credits.new,
+                credits.new - credits.old
+            );
+        }
+        if diff.newly_exceeded() {
+            println!();
+            println!("OVER  the new config exceeds the plan limits, see `fintek plan`");
+        }
+    });
+    if diff.newly_exceeded() {
+        return ExitCode::FAILURE;
+    }
+    printed
+}
+
+async fn simulate(path: &Path, config: &Config) -> ExitCode {
+    let recording = match Recording::load(path).await {
+        Ok(recording) => recording,
+        Err(e) => {
+            tracing::error!(error = %e, path = %path.display(), "Failed to load recording");
+            return ExitCode::FAILURE;
+        }
+    };
+    match sim::simulate(recording, config).await {
+        Some((report, _)) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&report).expect("Failed to serialize report")
+            );
+            ExitCode::SUCCESS
+        }
+        None => {
+            tracing::error!(path = %path.display(), "Recording is empty");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Errors become Sentry events and lower levels breadcrumbs; panics are
+/// captured with their stack traces. A no-op without `SENTRY_DSN`.
+#[cfg(feature = "sentry")]
+fn init_sentry() -> sentry::ClientInitGuard {
+    bootstrap::load_env();
+    sentry::init(sentry::ClientOptions {
+        release: sentry::release_name!(),
+        attach_stacktrace: true,
+        ..Default::default()
+    })
+}
+
+struct Traffic {
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
+    ticks: Option<PathBuf>,
+}
+
+impl Traffic {
+    // Whether prices come from a file rather than the network, which also
+    // keeps the jobs that call providers on their own from starting.
+    fn offline(&self) -> bool {
+        self.replay.is_some() || self.ticks.is_some()
+    }
+}
+
+async fn provider(
+    traffic: &Traffic,
+    urls: &ProviderConfig,
+    ops: Arc<OpsAlerter>,
+) -> Result<
+    (
+        Arc<dyn Provider>,
+        Option<Arc<KeyPool>>,
+        Option<Arc<Regions>>,
+    ),
+    RunError,
+> {
+    fintek::provider::number::set_locale(urls.number_locale);
+    fintek::provider::retry::set_policy(urls.retry);
+    if let Some(path) = &traffic.replay {
+        return Ok((Arc::new(ReplayProvider::load(path).await?), None, None));
+    }
+    if traffic.ticks.is_some() {
+        let idle = MockProvider::new(BTreeMap::new(), Arc::new(SystemClock));
+        return Ok((Arc::new(idle), None, None));
+    }
+    let record = traffic.record.as_deref();
+    Ok(match bootstrap::provider_kind(urls.kind)? {
+        ProviderKind::TwelveData => {
+            let keys = Arc::new(
+                KeyPool::new(bootstrap::api_keys()?)
+                    .with_base_url(&urls.twelvedata_url)
+                    .with_ops(ops),
+            );
+            let regions = Arc::new(Regions::new(
+                "twelvedata",
+                &urls.twelvedata_url,
+                &urls.regions.twelvedata,
+            ));
+            let provider = TwelveData::with_keys(keys.clone())
+                .with_regions(regions.clone())
+                .with_batch_size(urls.batch_size)
+                .with_quotes(urls.quotes);
+            let probed = (!urls.regions.twelvedata.is_empty()).then_some(regions);
+            (recorded(provider, record).await?, Some(keys), probed)
+        }
+        ProviderKind::Finnhub => {
+            let regions = Arc::new(Regions::new(
+                "finnhub",
+                &urls.finnhub_url,
+                &urls.regions.finnhub,
+            ));
+            let provider = Finnhub::new(&bootstrap::finnhub_key()?).with_regions(regions.clone());
+            let probed = (!urls.regions.finnhub.is_empty()).then_some(regions);
+            (recorded(provider, record).await?, None, probed)
+        }
+        ProviderKind::Rest => {
+            let provider = rest_provider(&urls.rest)?;
+            (recorded(provider, record).await?, None, None)
+        }
+    })
+}
+
+// The REST provider, signing its requests when configured to.
+fn rest_provider(config: &RestConfig) -> Result<RestProvider, BootstrapError> {
+    let key = bootstrap::rest_key();
+    let provider = RestProvider::new(config, key.clone()).map_err(BootstrapError::Rest)?;
+    let signer = Signer::new(
+        &config.signing,
+        key,
+        bootstrap::rest_secret(),
+        bootstrap::rest_passphrase(),
+    )
+    .map_err(|e| BootstrapError::Rest(format!("signing: {}", e)))?;
+    Ok(match signer {
+        Some(signer) => provider.with_signer(signer),
+        None => provider,
+    })
+}
+
+// Providers the consensus bands poll besides the main one.
+fn cross_checks(
+    traffic: &Traffic,
+    urls: &ProviderConfig,
+    sources: &[ProviderKind],
+) -> Result<Vec<Arc<dyn Provider>>, RunError> {
+    if traffic.offline() {
+        return Ok(vec![]);
+    }
+    let primary = bootstrap::provider_kind(urls.kind)?;
+    let mut providers: Vec<Arc<dyn Provider>> = vec![];
+    for kind in sources {
+        if *kind == primary {
+            continue;
+        }
+        providers.push(match kind {
+            ProviderKind::TwelveData => Arc::new(
+                TwelveData::new(&bootstrap::api_key()?).with_base_url(&urls.twelvedata_url),
+            ),
+            ProviderKind::Finnhub => {
+                Arc::new(Finnhub::new(&bootstrap::finnhub_key()?).with_base_url(&urls.finnhub_url))
+            }
+            ProviderKind::Rest => Arc::new(rest_provider(&urls.rest)?),
+        });
+    }
+    Ok(providers)
+}
+
+async fn recorded<P: Provider + 'static>(
+    provider: P,
+    record: Option<&Path>,
+) -> std::io::Result<Arc<dyn Provider>> {
+    Ok(match record {
+        Some(path) => Arc::new(RecordingProvider::new(provider, path).await?),
+        None => Arc::new(provider),
+    })
+}
+
+#[derive(Debug, thiserror::Error)]
+enum RunError {
+    #[error(transparent)]
+    Bootstrap(#[from] BootstrapError),
+    #[error("failed to open cassette: {0}")]
+    Cassette(#[from] std::io::Error),
+    #[error(transparent)]
+    Encryption(#[from] EncryptionError),
+    #[error(transparent)]
+    Limit(#[from] LimitError),
+    #[error(transparent)]
+    Recovery(#[from] RecoveryError),
+    #[error(
+        "fetching {symbols} symbols takes {needed_secs:.0}s at the rate limits, over \
+         priority.max_cycle_secs of {max_secs}s; raise it, trim the watchlist or set \
+         priority.over_budget = \"stretch\""
+    )]
+    OverBudget {
+        symbols: usize,
+        needed_secs: f64,
+        max_secs: f64,
+    },
+}
+
+async fn run(mut config: Config, traffic: Traffic, events_stdout: bool) -> Result<(), RunError> {
+    let build = fintek::version::BUILD;
+    tracing::info!(
+        version = build.version,
+        git_sha = build.git_sha,
+        rustc = build.rustc,
+        build_date = build.build_date,
+        "Starting fintek"
+    );
+    bootstrap::load_env();
+    bootstrap::use_config_key(config.provider.api_key.as_deref());
+    fintek::set_tickers_path(&config.tickers_path);
+    recovery::check_state(&config.tickers_path, &config.state_dir)?;
+    let chaos = config.chaos.enabled.then(|| {
+        let chaos = Arc::new(Chaos::new(&config.chaos, &config.provider.twelvedata_url));
+        config.provider.twelvedata_url = format!("http://{}", config.chaos.listen);
+        let (proxy, listen) = (chaos.clone(), config.chaos.listen);
+        supervisor::spawn("chaos", move || proxy.clone().serve(listen));
+        chaos
+    });
+    let storage_key = StorageKey::load(&config.encryption)?;
+    let ops = Arc::new(OpsAlerter::new(config.ops.clone()));
+    let (provider, keys, regions) = provider(&traffic, &config.provider, ops.clone()).await?;
+
+    let tickers = Tickers::init().await;
+    let symbols = tickers.get_tickers().len();
+    let budget = priority::budget(&config.rate_limits);
+    if let Some(cycle) = priority::cycle_budget(symbols, &config.priority, budget) {
+        if cycle.exceeded() {
+            if config.priority.over_budget == OverBudget::Fail {
+                return Err(RunError::OverBudget {
+                    symbols,
+                    needed_secs: cycle.needed_secs,
+                    max_secs: cycle.max_secs,
+                });
+            }
+            tracing::warn!(
+                symbols,
+                needed_secs = cycle.needed_secs,
+                max_secs = cycle.max_secs,
+                per_cycle = cycle.per_cycle,
+                cycles = cycle.cycles,
+                "Cycle over budget, spreading symbols over several cycles"
+            );
+        }
+    }
+    #[cfg(feature = "sentry")]
+    sentry::configure_scope(|scope| scope.set_tag("provider", provider.name()));
+
+    let clock: Arc<dyn Clock> = match &chaos {
+        Some(chaos) => chaos.clock().clone(),
+        None => Arc::new(SystemClock),
+    };
+    if let Some(keys) = keys {
+        let interval = std::time::Duration::from_secs(config.keys.probe_interval_secs);
+        let clock = clock.clone();
+        supervisor::spawn("key_probe", move || {
+            keys.clone().run(clock.clone(), interval)
+        });
+    }
+    if let Some(regions) = regions {
+        let interval = std::time::Duration::from_secs(config.provider.regions.probe_secs.max(1));
+        let clock = clock.clone();
+        supervisor::spawn("region_probe", move || {
+            regions.clone().run(clock.clone(), interval)
+        });
+    }
+    let source = provider.name().to_string();
+    let read_through = provider.clone();
+    let health = Arc::new(Health::new(clock.clone(), config.metrics.ready_stale_secs));
+    let mut engine = Engine::from_config(provider, clock.clone(), &config)
+        .with_ops(ops)
+        .with_sink(health.clone());
+    engine.limits().check_symbols(symbols)?;
+    if config.limits.max_series > 0 {
+        let (limits, clock) = (engine.limits().clone(), clock.clone());
+        supervisor::spawn("limits", move || limits.clone().watch_series(clock.clone()));
+    }
+    engine = match reload::watch_tickers(&config.tickers_path, tickers.clone()) {
+        Ok(updates) => engine.with_ticker_updates(updates),
+        Err(e) => {
+            tracing::warn!(error = %e, "Can't watch the tickers file, checking it every cycle");
+            engine.with_ticker_reload(true)
+        }
+    };
+    // Other providers sharing the first fetch, the consensus sources unless
+    // named.
+    let warmup = match config.warmup.sources.as_slice() {
+        _ if !config.warmup.enabled => vec![],
+        [] => cross_checks(&traffic, &config.provider, &config.consensus.sources)?,
+        sources => cross_checks(&traffic, &config.provider, sources)?,
+    };
+    let cross_checks = cross_checks(&traffic, &config.provider, &config.consensus.sources)?;
+    let consensus = (!cross_checks.is_empty())
+        .then(|| Arc::new(Consensus::new(&config.consensus, &source, clock.clone())));
+    if let Some(consensus) = &consensus {
+        engine = engine.with_sink(consensus.clone());
+    }
+    let stream_key = env::var("API_KEY")
+        .ok()
+        .filter(|_| config.stream.enabled && !traffic.offline());
+    let stream = stream_key
+        .is_some()
+        .then(|| Arc::new(PriceStream::new(config.stream.clone(), clock.clone())));
+    if let Some(stream) = &stream {
+        engine = engine.with_stream(stream.clone());
+    }
+    if config.cluster.enabled {
+        let cluster = Arc::new(Cluster::new(config.cluster.clone()));
+        if let Err(e) = cluster.heartbeat(clock.now()).await {
+            tracing::error!(error = %e, "Failed to join cluster");
+        }
+        tracing::info!(instance = cluster.instance(), members = ?cluster.members(), "Joined cluster");
+        let (heartbeat, clock) = (cluster.clone(), clock.clone());
+        supervisor::spawn("cluster", move || heartbeat.clone().run(clock.clone()));
+        engine = engine.with_cluster(cluster);
+    }
+    let engine = Arc::new(engine);
+    if let Some(consensus) = consensus {
+        let engine = engine.clone();
+        supervisor::spawn("consensus", move || {
+            consensus.clone().run(cross_checks.clone(), engine.clone())
+        });
+    }
+    if let Some(venues) = engine.venues().filter(|v| v.polls()).cloned() {
+        let engine = engine.clone();
+        supervisor::spawn("venues", move || venues.clone().run(engine.clone()));
+    }
+
+    // Drained before exiting, so nothing published is lost.
+    let mut writers = vec![];
+    if let Some(path) = &config.events.log {
+        recovery::recover_journal(path, storage_key.as_ref());
+        match engine.restore(path, storage_key.as_ref()).await {
+            Ok(last_seq) => {
+                writers.push(tokio::spawn(events::write_log(
+                    path.clone(),
+                    engine.events().subscribe(),
+                    last_seq,
+                    storage_key.clone(),
+                )));
+            }
+            Err(e) => {
+                tracing::error!(path = %path.display(), error = %e, "Failed to restore event log")
+            }
+        }
+    }
+
+    if events_stdout {
+        writers.push(tokio::spawn(events::write_stdout(
+            engine.events().subscribe_bounded(events::STDOUT_CAPACITY),
+        )));
+    }
+
+    if let (Some(stream), Some(api_key)) = (stream, stream_key) {
+        let engine = engine.clone();
+        supervisor::spawn("stream", move || {
+            stream.clone().run(engine.clone(), api_key.clone())
+        });
+    }
+
+    let store: Option<Arc<SqliteStore>> = match &config.storage.path {
+        Some(path) => {
+            match SqliteStore::open(path, storage_key.as_ref(), &config.storage.sqlite).await {
+                Ok(store) => {
+                    let store = Arc::new(store);
+                    writers.push(tokio::spawn(storage::write_prices(
+                        store.clone(),
+                        source,
+                        engine.events().subscribe(),
+                        engine.toggles().clone(),
+                        engine.limits().clone(),
+                    )));
+                    engine.notifiers().record_to(store.clone(), clock.clone());
+                    Some(store)
+                }
+                Err(e) => {
+                    tracing::error!(path = %path.display(), error = %e, "Failed to open price store");
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+    type Reads = (
+        Arc<dyn PriceStore>,
+        Arc<dyn AlertStore>,
+        Arc<dyn DailyStore>,
+    );
+    let reads: Option<Reads> = match (&store, &config.storage.replica_url) {
+        (Some(primary), Some(url)) => {
+            match SqliteStore::replica(url, storage_key.as_ref(), &config.storage.sqlite).await {
+                Ok(replica) => {
+                    tracing::info!(url, "Serving reads from the store replica");
+                    let reads = Arc::new(ReadReplica::new(replica, primary.clone()));
+                    Some((reads.clone(), reads.clone(), reads))
+                }
+                Err(e) => {
+                    tracing::warn!(url, error = %e, "Failed to open store replica, reading from the primary");
+                    Some((primary.clone(), primary.clone(), primary.clone()))
+                }
+            }
+        }
+        (Some(primary), None) => Some((primary.clone(), primary.clone(), primary.clone())),
+        (None, _) => None,
+    };
+
+    let econ = engine
+        .econ()
+        .cloned()
+        .expect("engine has an economic calendar");
+    if let Ok(token) = env::var("FINNHUB_API_KEY") {
+        let econ = econ.clone();
+        let finnhub_url = config.provider.finnhub_url.clone();
+        supervisor::spawn("econ", move || {
+            let (econ, finnhub_url, token) = (econ.clone(), finnhub_url.clone(), token.clone());
+            async move {
+                loop {
+                    if let Err(e) = econ.refresh_finnhub(&finnhub_url, &token).await {
+                        tracing::error!(error = ?e, "Failed to refresh economic calendar");
+                    }
+                    tokio::time::sleep(tokio::time::Duration::from_secs(60 * 60)).await;
+                }
+            }
+        });
+    }
+
+    let exporter_clock = clock.clone();
+    supervisor::spawn("calendar", move || {
+        calendar::run_exporter(exporter_clock.clone())
+    });
+
+    let movers = Arc::new(
+        MoversFeed::new(config.movers.clone()).with_base_url(&config.provider.twelvedata_url),
+    );
+    if config.movers.enabled {
+        if let Ok(api_key) = env::var("API_KEY") {
+            let (movers, clock, engine) = (movers.clone(), clock.clone(), engine.clone());
+            supervisor::spawn("movers", move || {
+                movers.clone().run(
+                    api_key.clone(),
+                    clock.clone(),
+                    engine.clone(),
+                    engine.notifiers().clone(),
+                )
+            });
+        }
+    }
+
+    let corporate = Arc::new(
+        CorporateCalendar::new(config.corporate.clone())
+            .with_base_url(&config.provider.twelvedata_url),
+    );
+    let daily = match (&store, config.daily.enabled) {
+        (Some(store), true) => {
+            let mut daily = DailyPipeline::new(config.daily.clone(), store.clone())
+                .with_base_url(&config.provider.twelvedata_url);
+            if config.corporate.enabled {
+                daily = daily.with_calendar(corporate.clone());
+            }
+            Some(Arc::new(daily))
+        }
+        (None, true) => {
+            tracing::warn!("The daily pipeline needs storage.path, not starting it");
+            None
+        }
+        (_, false) => None,
+    };
+    if let Ok(api_key) = env::var("API_KEY") {
+        if let Some(daily) = &daily {
+            let (daily, engine, api_key) = (daily.clone(), engine.clone(), api_key.clone());
+            supervisor::spawn("daily", move || {
+                daily.clone().run(engine.clone(), api_key.clone())
+            });
+        }
+        if config.corporate.enabled && !(daily.is_some() && config.daily.calendars) {
+            let (corporate, clock) = (corporate.clone(), clock.clone());
+            supervisor::spawn("corporate", move || {
+                corporate.clone().run(api_key.clone(), clock.clone())
+            });
+        }
+    }
 
-        if let Some(new) = check_tickers().await {
-            tickers = new;
+    let stats = match (&store, config.stats.enabled) {
+        (Some(store), true) => {
+            let stats = Arc::new(StatsCache::new(&config.stats, &config.state_dir));
+            let (cache, store, clock) = (stats.clone(), store.clone(), clock.clone());
+            supervisor::spawn("stats", move || {
+                cache
+                    .clone()
+                    .run(store.clone() as Arc<dyn DailyStore>, clock.clone())
+            });
+            Some(stats)
+        }
+        (None, true) => {
+            tracing::warn!("Symbol stats need storage.path, not deriving them");
+            None
         }
+        (_, false) => None,
+    };
 
-        let num_tickers = tickers.get_tickers().len();
-        let sleep_duration =
-            fintek::calculate_sleep_duration(num_tickers, 8, 60, 800, (6.5 * 60. * 60.) as u64);
+    let profiles = config.profile.enabled.then(|| {
+        Arc::new(
+            VolumeProfiles::new(&config.profile, config.exchange)
+                .with_base_url(&config.provider.twelvedata_url),
+        )
+    });
+    if let (Some(profiles), Ok(api_key)) = (&profiles, env::var("API_KEY")) {
+        let (profiles, limiter, clock) =
+            (profiles.clone(), engine.limiter().clone(), clock.clone());
+        supervisor::spawn("profile", move || {
+            profiles
+                .clone()
+                .run(api_key.clone(), limiter.clone(), clock.clone())
+        });
+    }
 
-        if let Some(sleep_duration) = sleep_duration {
-            for ticker in tickers.get_tickers() {
-                let _ = fintek::call_api(ticker, &api_key).await.map_err(|e| {
-                    tracing::error!(error = ?e, "Failed to call API");
-                    e
+    let metrics = config.metrics.clone();
+    let paper = engine.paper().cloned().expect("engine has a paper account");
+    let tokens = Arc::new(TokenStore::new(&config.state_dir));
+    if tokens.is_open() {
+        tracing::warn!("No API tokens configured, the HTTP API is unauthenticated");
+    }
+    let quality = engine
+        .quality()
+        .cloned()
+        .expect("engine has a quality tracker");
+    let prices = engine
+        .latest()
+        .cloned()
+        .expect("engine tracks latest prices");
+    if config.sheets.enabled {
+        let sheets = Arc::new(SheetsSync::new(
+            config.sheets.clone(),
+            prices.clone(),
+            paper.clone(),
+        ));
+        let clock = clock.clone();
+        supervisor::spawn("sheets", move || sheets.clone().run(clock.clone()));
+    }
+    if config.eod_summary {
+        let (exchange, clock, prices, paper) = (
+            config.exchange,
+            clock.clone(),
+            prices.clone(),
+            paper.clone(),
+        );
+        let tracking = engine.tracking().cloned();
+        let corporate = config.corporate.enabled.then(|| corporate.clone());
+        let notifiers = engine.notifiers().clone();
+        supervisor::spawn("eod_summary", move || {
+            eod::run(
+                exchange,
+                clock.clone(),
+                prices.clone(),
+                paper.clone(),
+                tracking.clone(),
+                corporate.clone(),
+                notifiers.clone(),
+            )
+        });
+    }
+    let history = engine
+        .history()
+        .cloned()
+        .expect("engine keeps price history");
+    let mut gaps = None;
+    let mut options = None;
+    let mut edgar = None;
+    let mut social = None;
+    if !traffic.offline() && config.edgar.enabled {
+        match EdgarWatcher::new(&config.edgar, &config.state_dir, engine.notifiers().clone()) {
+            Some(watcher) => {
+                let (watcher, clock) = (Arc::new(watcher), clock.clone());
+                edgar = Some(watcher.clone());
+                supervisor::spawn("edgar", move || watcher.clone().run(clock.clone()));
+            }
+            None => tracing::warn!("EDGAR needs edgar.user_agent, not watching filings"),
+        }
+    }
+    if !traffic.offline() && config.market.fear_greed {
+        if let Some(market) = engine.market_context().cloned() {
+            let clock = clock.clone();
+            supervisor::spawn("market", move || market.clone().run(clock.clone()));
+        }
+    }
+    if !traffic.offline() {
+        if let Some(monitor) = SocialMonitor::new(
+            &config.social,
+            &config.state_dir,
+            engine.notifiers().clone(),
+        ) {
+            let (monitor, clock) = (Arc::new(monitor), clock.clone());
+            social = Some(monitor.clone());
+            supervisor::spawn("social", move || monitor.clone().run(clock.clone()));
+        }
+    }
+    if !traffic.offline() {
+        if let Ok(api_key) = env::var("API_KEY") {
+            let base_url = fintek::provider::base_url(&config.provider.twelvedata_url);
+            if config.timeseries.repair_gaps {
+                let repair = Arc::new(GapRepair::new(&config.timeseries, history.clone()));
+                let (engine, base_url, api_key) =
+                    (engine.clone(), base_url.clone(), api_key.clone());
+                gaps = Some(repair.clone());
+                supervisor::spawn("gap_repair", move || {
+                    repair
+                        .clone()
+                        .run(engine.clone(), base_url.clone(), api_key.clone())
                 });
-                tokio::time::sleep(tokio::time::Duration::from_secs(sleep_duration)).await;
             }
+            if let Some(analysts) = engine.analysts().cloned() {
+                let (engine, api_key) = (engine.clone(), api_key.clone());
+                supervisor::spawn("analysts", move || {
+                    analysts.clone().run(engine.clone(), api_key.clone())
+                });
+            }
+            if let Some(monitor) = OptionsMonitor::new(&config.options) {
+                let monitor = Arc::new(monitor.with_base_url(&config.provider.twelvedata_url));
+                let (engine, api_key) = (engine.clone(), api_key.clone());
+                options = Some(monitor.clone());
+                supervisor::spawn("options", move || {
+                    monitor.clone().run(engine.clone(), api_key.clone())
+                });
+            }
+            let (history, clock) = (history.clone(), clock.clone());
+            supervisor::spawn("splits", move || {
+                history
+                    .clone()
+                    .run_splits(base_url.clone(), api_key.clone(), clock.clone())
+            });
         }
     }
+    let notes = engine.notes().cloned().expect("engine keeps symbol notes");
+    let portfolio = engine
+        .portfolio()
+        .cloned()
+        .expect("engine tracks the portfolio");
+    let namespaces = engine
+        .namespaces()
+        .cloned()
+        .expect("engine keeps user namespaces");
+    let feed = engine.feed().cloned().expect("engine has an alert feed");
+    let (follower, bus) = (feed.clone(), engine.events().clone());
+    supervisor::spawn("alert_feed", move || {
+        follower.clone().follow(bus.subscribe())
+    });
+    let state = ApiState {
+        econ,
+        movers,
+        paper,
+        tokens,
+        quality,
+        prices,
+        corporate,
+        feed,
+        history,
+        gaps,
+        options,
+        analysts: engine.analysts().cloned(),
+        stats,
+        profiles,
+        edgar,
+        social,
+        market: engine.market_context().cloned(),
+        tracking: engine.tracking().cloned(),
+        notes,
+        portfolio,
+        namespaces,
+        toggles: engine.toggles().clone(),
+        limits: engine.limits().clone(),
+        applier: engine.applier().clone(),
+        watches: engine.watches().clone(),
+        tickers: engine.tickers().clone(),
+        store: reads.as_ref().map(|(prices, _, _)| prices.clone()),
+        alerts: reads.as_ref().map(|(_, alerts, _)| alerts.clone()),
+        daily: reads.map(|(_, _, daily)| daily),
+        chaos,
+        proxy: config.provider.read_through.then(|| {
+            Arc::new(
+                QuoteProxy::new(
+                    read_through,
+                    engine.limiter().clone(),
+                    clock.clone(),
+                    config.provider.read_through_cache_secs,
+                )
+                .with_default_quota(config.provider.read_through_daily_quota),
+            )
+        }),
+        started_at: clock.now(),
+        health: health.clone(),
+        indicators: config.indicators.clone(),
+    };
+    supervisor::spawn("metrics_server", move || {
+        let (metrics, state) = (metrics.clone(), state.clone());
+        async move { MetricServer::serve(&metrics, state).await }
+    });
+
+    service::notify_ready();
+    if let Some(interval) = service::watchdog_interval() {
+        tokio::spawn(async move {
+            loop {
+                service::notify("WATCHDOG=1");
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+
+    // Before the first cycle, so history stays in order.
+    if config.timeseries.backfill && !traffic.offline() {
+        if let Ok(api_key) = env::var("API_KEY") {
+            let base_url = fintek::provider::base_url(&config.provider.twelvedata_url);
+            fintek::timeseries::backfill(
+                &engine,
+                &config.timeseries,
+                &base_url,
+                &api_key,
+                tickers.get_tickers(),
+            )
+            .await;
+        }
+    }
+
+    if config.warmup.enabled && !traffic.offline() {
+        engine.warm_up(&tickers, &warmup, &config.warmup).await;
+    }
+
+    tokio::select! {
+        _ = take_prices(&engine, tickers, traffic.ticks.as_deref()) => {}
+        signal = shutdown_signal() => tracing::info!(signal, "Shutting down"),
+    }
+    health.shutting_down();
+    service::notify("STOPPING=1");
+    engine.tickers().lock().await.dump_to_file().await;
+    engine.events().close();
+    let flushed = tokio::time::timeout(SHUTDOWN_TIMEOUT, futures_util::future::join_all(writers));
+    if flushed.await.is_err() {
+        tracing::warn!(
+            timeout_secs = SHUTDOWN_TIMEOUT.as_secs(),
+            "Gave up waiting for writes to finish"
+        );
+    }
+    tracing::info!("Stopped");
+    Ok(())
 }
 
+/// How long writes get to finish once a stop is asked for.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Prices from the tick input when given, else from polling.
+async fn take_prices(engine: &Engine, tickers: Tickers, ticks: Option<&Path>) {
+    let Some(path) = ticks else {
+        return poll(engine, tickers).await;
+    };
+    // The poll loop would have set it; it's saved back at shutdown.
+    *engine.tickers().lock().await = tickers;
+    match path {
+        path if path == Path::new("-") => {
+            ticks::feed(engine, BufReader::new(tokio::io::stdin())).await;
+        }
+        path => match tokio::fs::File::open(path).await {
+            Ok(file) => {
+                ticks::feed(engine, BufReader::new(file)).await;
+            }
+            Err(e) => tracing::error!(path = %path.display(), error = %e, "Failed to open ticks"),
+        },
+    }
+}
+
+// Runs the poll loop, dropping and restarting it on the watchlist it had
+// whenever the watchdog finds it stuck.
+async fn poll(engine: &Engine, tickers: Tickers) {
+    let Some(watchdog) = engine.watchdog() else {
+        return engine.run(tickers).await;
+    };
+    let mut tickers = tickers;
+    loop {
+        tokio::select! {
+            _ = engine.run(tickers) => return,
+            _ = watchdog.tripped() => {
+                tracing::warn!("Restarting the poll loop");
+                fintek::metrics::update_task_restart("engine", "watchdog");
+            }
+        }
+        tickers = engine.tickers().lock().await.clone();
+    }
+}
+
+// The signal that asked for a stop, SIGTERM from an orchestrator or SIGINT
+// from a terminal.
+async fn shutdown_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => tokio::select! {
+                _ = term.recv() => "SIGTERM",
+                _ = tokio::signal::ctrl_c() => "SIGINT",
+            },
+            Err(e) => {
+                tracing::warn!(error = %e, "Can't listen for SIGTERM");
+                let _ = tokio::signal::ctrl_c().await;
+                "SIGINT"
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        "SIGINT"
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{info, instrument, warn};
+
+use crate::clock::Clock;
+use crate::metrics;
+use crate::provider::number;
+use crate::sink::{PriceUpdate, Sink};
+use crate::AssetClass;
+
+pub const FEAR_GREED_URL: &str = "https://api.alternative.me";
+
+pub const FEAR_GREED: &str = "crypto_fear_greed";
+pub const ADVANCERS: &str = "advancers";
+pub const DECLINERS: &str = "decliners";
+pub const UNCHANGED: &str = "unchanged";
+pub const ADVANCE_DECLINE_RATIO: &str = "advance_decline_ratio";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MarketConfig {
+    /// The crypto Fear & Greed index, from 0 for extreme fear to 100 for
+    /// extreme greed, which alternative.me updates daily.
+    pub fear_greed: bool,
+    pub fear_greed_url: String,
+    pub refresh_secs: u64,
+    /// Advancing and declining stocks among the watched ones, by their
+    /// change since the previous close.
+    pub breadth: bool,
+}
+
+impl Default for MarketConfig {
+    fn default() -> Self {
+        MarketConfig {
+            fear_greed: false,
+            fear_greed_url: FEAR_GREED_URL.into(),
+            refresh_secs: 3600,
+            breadth: false,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum MarketError {
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error("unexpected response: {0}")]
+    Response(#[from] serde_json::Error),
+    #[error("no index value in the response")]
+    Empty,
+}
+
+#[derive(Debug, Deserialize)]
+struct FearGreedValue {
+    #[serde(deserialize_with = "number::deserialize")]
+    value: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct FearGreedResponse {
+    #[serde(default)]
+    data: Vec<FearGreedValue>,
+}
+
+#[instrument]
+pub async fn fetch_fear_greed(base_url: &str) -> Result<f64, MarketError> {
+    let url = format!("{}/fng/?limit=1", base_url.trim_end_matches('/'));
+    let data = reqwest::get(&url).await?.error_for_status()?.text().await?;
+    let response: FearGreedResponse = serde_json::from_str(&data)?;
+    response
+        .data
+        .first()
+        .map(|v| v.value)
+        .ok_or(MarketError::Empty)
+}
+
+/// Market-wide indicators, for context rather than any one symbol: exported
+/// as `market_indicator` and readable from scripts with `market(name)`.
+pub struct MarketContext {
+    config: MarketConfig,
+    values: RwLock<BTreeMap<String, f64>>,
+    // Change percent per watched stock, for the breadth.
+    changes: Mutex<HashMap<String, f64>>,
+}
+
+impl MarketContext {
+    /// `None` with every indicator off.
+    pub fn new(config: &MarketConfig) -> Option<Self> {
+        if !config.fear_greed && !config.breadth {
+            return None;
+        }
+        Some(MarketContext {
+            config: config.clone(),
+            values: RwLock::new(BTreeMap::new()),
+            changes: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.values.read().unwrap().get(name).copied()
+    }
+
+    pub fn all(&self) -> BTreeMap<String, f64> {
+        self.values.read().unwrap().clone()
+    }
+
+    fn set(&self, name: &str, value: Option<f64>) {
+        let mut values = self.values.write().unwrap();
+        match value {
+            Some(value) => values.insert(name.to_string(), value),
+            None => values.remove(name),
+        };
+        metrics::update_market_indicator(name, value);
+    }
+
+    fn breadth(&self, update: &PriceUpdate) {
+        let Some(day) = &update.day else {
+            return;
+        };
+        if AssetClass::of(&update.symbol) != AssetClass::Stock {
+            return;
+        }
+        let (mut up, mut down, mut flat) = (0., 0., 0.);
+        {
+            let mut changes = self.changes.lock().unwrap();
+            changes.insert(update.symbol.clone(), day.change_percent);
+            for change in changes.values() {
+                match change {
+                    c if *c > 0. => up += 1.,
+                    c if *c < 0. => down += 1.,
+                    _ => flat += 1.,
+                }
+            }
+        }
+        self.set(ADVANCERS, Some(up));
+        self.set(DECLINERS, Some(down));
+        self.set(UNCHANGED, Some(flat));
+        self.set(ADVANCE_DECLINE_RATIO, (down > 0.).then(|| up / down));
+    }
+
+    /// Refreshes the Fear & Greed index, when on.
+    pub async fn run(self: Arc<Self>, clock: Arc<dyn Clock>) {
+        loop {
+            match fetch_fear_greed(&self.config.fear_greed_url).await {
+                Ok(value) => {
+                    info!(value, "Fetched crypto Fear & Greed index");
+                    self.set(FEAR_GREED, Some(value));
+                }
+                Err(e) => warn!(error = %e, "Failed to fetch crypto Fear & Greed index"),
+            }
+            clock
+                .sleep(Duration::from_secs(self.config.refresh_secs.max(60)))
+                .await;
+        }
+    }
+}
+
+impl Sink for MarketContext {
+    fn record(&self, update: &PriceUpdate) {
+        if self.config.breadth {
+            self.breadth(update);
+        }
+    }
+}
@@ -1,11 +1,47 @@
+use axum::body::Bytes;
+use axum::error_handling::HandleErrorLayer;
+use axum::http::header::{CONTENT_TYPE, RETRY_AFTER};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{BoxError, Router};
+use chrono::{DateTime, NaiveDate, Utc};
 use lazy_static::lazy_static;
 use prometheus::Encoder;
+use prometheus::Gauge;
 use prometheus::GaugeVec;
+use prometheus::HistogramOpts;
+use prometheus::HistogramVec;
+use prometheus::IntCounter;
+use prometheus::IntCounterVec;
+use prometheus::IntGauge;
+use prometheus::IntGaugeVec;
 use prometheus::Opts;
+use std::collections::BTreeMap;
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Once, OnceLock};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tower::load_shed::error::Overloaded;
+use tower::timeout::error::Elapsed;
+use tower::ServiceBuilder;
+use tower_http::compression::CompressionLayer;
+use tower_http::trace::TraceLayer;
 use tracing::trace;
-use tracing::{info, instrument};
-use warp::Filter;
+
+use crate::analysts::AnalystConsensus;
+use crate::api::{self, ApiState};
+use crate::config::MetricsConfig;
+use crate::consensus::Band;
+use crate::lag;
+use crate::profile::VolumeProfile;
+use crate::provider::DayQuote;
+use crate::sink::candles::DailyCandle;
+use crate::social::Sample;
+use crate::stats::SymbolStats;
+use crate::version::{self, BuildInfo};
+use tracing::{error, info, instrument, warn};
 
 #[derive(Debug)]
 pub struct Metric {
@@ -15,14 +51,908 @@ pub struct Metric {
 
 lazy_static! {
     pub static ref REGISTRY: prometheus::Registry = prometheus::Registry::new();
-    static ref STOCK_PRICE: GaugeVec =
-        GaugeVec::new(Opts::new("stock_price", "Current stock price"), &["symbol"],).unwrap();
+    static ref SCRAPE_ENCODER: ScrapeEncoder = ScrapeEncoder::default();
+    static ref STOCK_PRICE: GaugeVec = GaugeVec::new(
+        Opts::new("stock_price", "Current stock price"),
+        &["symbol", "asset_class", "currency"],
+    )
+    .unwrap();
+    static ref DIVIDEND_INCOME: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "dividend_projected_income",
+            "Projected dividend income per month"
+        ),
+        &["month"],
+    )
+    .unwrap();
+    static ref DIVIDEND_INCOME_TOTAL: Gauge = Gauge::new(
+        "dividend_projected_income_total",
+        "Projected dividend income over the next twelve months"
+    )
+    .unwrap();
+    static ref SLO_COMPLIANCE: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "slo_freshness_compliance_ratio",
+            "Share of freshness samples within objective"
+        ),
+        &["asset_class", "window"],
+    )
+    .unwrap();
+    static ref SLO_BURN_RATE: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "slo_freshness_burn_rate",
+            "Rate the freshness error budget is being spent, 1 means exactly on budget"
+        ),
+        &["asset_class", "window"],
+    )
+    .unwrap();
+    static ref SLO_OBJECTIVE_SECONDS: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "slo_freshness_objective_seconds",
+            "Maximum allowed staleness"
+        ),
+        &["asset_class"],
+    )
+    .unwrap();
+    static ref SLO_TARGET: GaugeVec = GaugeVec::new(
+        Opts::new("slo_freshness_target_ratio", "Freshness compliance target"),
+        &["asset_class"],
+    )
+    .unwrap();
+    static ref STRETCH_FACTOR: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "schedule_stretch_factor",
+            "Multiplier applied to poll intervals when the rate budget is short"
+        ),
+        &["priority"],
+    )
+    .unwrap();
+    static ref DEFERRED_SYMBOLS: Gauge = Gauge::new(
+        "schedule_deferred_symbols",
+        "Due symbols left to the next cycle to keep it within max_cycle_secs"
+    )
+    .unwrap();
+    static ref ADAPTIVE_FACTOR: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "schedule_adaptive_factor",
+            "Poll rate multiplier from relative volatility, above 1 polls more often"
+        ),
+        &["symbol"],
+    )
+    .unwrap();
+    static ref LISTING_PRICE: GaugeVec = GaugeVec::new(
+        Opts::new("listing_price", "Latest price per exchange listing"),
+        &["company", "exchange", "symbol", "currency"],
+    )
+    .unwrap();
+    static ref COMPANY_PRICE: GaugeVec = GaugeVec::new(
+        Opts::new("company_price", "Price of a company's primary listing"),
+        &["company"],
+    )
+    .unwrap();
+    static ref RATE_SPREAD: GaugeVec = GaugeVec::new(
+        Opts::new("rate_spread", "Yield spread in percentage points"),
+        &["name"],
+    )
+    .unwrap();
+    static ref EQUITY_RISK_PREMIUM: Gauge = Gauge::new(
+        "equity_risk_premium",
+        "Equity earnings yield minus the treasury yield, in percentage points"
+    )
+    .unwrap();
+    static ref ECON_EVENT_MINUTES: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "econ_event_minutes_until",
+            "Minutes until the next occurrence of a macro event, negative just after it"
+        ),
+        &["event"],
+    )
+    .unwrap();
+    static ref CLUSTER_MEMBERS: Gauge = Gauge::new(
+        "cluster_members",
+        "Live instances sharing the symbol universe"
+    )
+    .unwrap();
+    static ref SHARD_SYMBOLS: Gauge =
+        Gauge::new("shard_symbols", "Watched symbols owned by this instance").unwrap();
+    static ref HTTP_SHED: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "http_requests_shed_total",
+            "HTTP requests answered with 503 instead of being served"
+        ),
+        &["reason"],
+    )
+    .unwrap();
+    static ref MARKET_OPEN: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "market_open",
+            "1 while the exchange's regular session is open"
+        ),
+        &["exchange"],
+    )
+    .unwrap();
+    static ref SECONDS_UNTIL_OPEN: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "seconds_until_open",
+            "Seconds until the exchange's next session opens, 0 while open"
+        ),
+        &["exchange"],
+    )
+    .unwrap();
+    static ref DATA_QUALITY: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "data_quality_score",
+            "Per-symbol data quality from 0 to 1 over the rolling window"
+        ),
+        &["symbol"],
+    )
+    .unwrap();
+    static ref API_KEY_VALID: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "api_key_valid",
+            "1 if the key passed its last probe, 0 if it failed, -1 if never checked"
+        ),
+        &["key"],
+    )
+    .unwrap();
+    static ref API_KEY_ACTIVE: GaugeVec = GaugeVec::new(
+        Opts::new("api_key_active", "1 for the key currently serving requests"),
+        &["key"],
+    )
+    .unwrap();
+    static ref TASK_RESTARTS: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "task_restarts_total",
+            "Restarts of supervised background tasks"
+        ),
+        &["task", "reason"],
+    )
+    .unwrap();
+    static ref HISTORY_BYTES: IntGauge = IntGauge::new(
+        "history_memory_bytes",
+        "Bytes of price history held in memory across all symbols"
+    )
+    .unwrap();
+    static ref HISTORY_SPILLED: IntCounter = IntCounter::new(
+        "history_spilled_points_total",
+        "Price points moved from memory to disk"
+    )
+    .unwrap();
+    static ref ALLOCATION: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "portfolio_allocation_ratio",
+            "Share of paper equity per sector, asset class or region"
+        ),
+        &["dimension", "group"],
+    )
+    .unwrap();
+    static ref TRACKING_ERROR: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "portfolio_tracking_error",
+            "Annualized tracking error of the paper portfolio against its benchmark"
+        ),
+        &["benchmark"],
+    )
+    .unwrap();
+    static ref INFORMATION_RATIO: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "portfolio_information_ratio",
+            "Annualized active return over tracking error against the benchmark"
+        ),
+        &["benchmark"],
+    )
+    .unwrap();
+    static ref CLOSE_WATCHES: IntGauge = IntGauge::new(
+        "close_watch_symbols",
+        "Symbols currently in time-boxed close watch"
+    )
+    .unwrap();
+    static ref NOTIFICATIONS_DEDUPLICATED: IntCounter = IntCounter::new(
+        "notifications_deduplicated_total",
+        "Notifications dropped because another replica already sent them"
+    )
+    .unwrap();
+    static ref RATE_LIMIT_REMAINING: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            "rate_limit_remaining",
+            "Requests left before the rate limit over the trailing window"
+        ),
+        &["window"],
+    )
+    .unwrap();
+    static ref PRICE_TIMESTAMP_SKEW: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "price_timestamp_skew_seconds",
+            "Local receive time minus the provider's timestamp for the latest price"
+        ),
+        &["symbol"],
+    )
+    .unwrap();
+    static ref CLOCK_SKEW: Gauge = Gauge::new(
+        "clock_skew_estimate_seconds",
+        "Smallest receive skew across symbols last cycle, an upper bound on how far the host clock runs ahead"
+    )
+    .unwrap();
+    static ref LIMIT_REJECTIONS: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "limit_rejections_total",
+            "Symbol additions and stored prices refused by a configured limit"
+        ),
+        &["limit"]
+    )
+    .unwrap();
+    static ref CONFIG_CHANGES: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "config_changes_total",
+            "Watchlist changes by source and whether they were rejected, applied, confirmed after the grace period or rolled back"
+        ),
+        &["source", "outcome"]
+    )
+    .unwrap();
+    static ref STATE_RECOVERIES: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "state_recoveries_total",
+            "State files found corrupt at startup, by file and whether a backup was restored, bad lines dropped or the file reset"
+        ),
+        &["file", "outcome"]
+    )
+    .unwrap();
+    static ref PRICES_STORED: IntCounter =
+        IntCounter::new("prices_stored_total", "Prices appended to the local store").unwrap();
+    static ref READ_THROUGH: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "read_through_requests_total",
+            "On-demand quotes for unwatched symbols by client, and whether they were cached, fetched or refused for the quota or rate limit"
+        ),
+        &["client", "outcome"]
+    )
+    .unwrap();
+    static ref STREAM_CONNECTED: IntGauge =
+        IntGauge::new("stream_connected", "1 while the price WebSocket is connected").unwrap();
+    static ref STREAM_SYMBOLS: IntGauge = IntGauge::new(
+        "stream_symbols",
+        "Symbols confirmed or ticking on the price stream"
+    )
+    .unwrap();
+    static ref CHAOS_FAULTS: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "chaos_faults_injected_total",
+            "Faults the chaos proxy injected into provider traffic"
+        ),
+        &["fault"]
+    )
+    .unwrap();
+    static ref ALERTS_FIRED: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "alerts_fired_total",
+            "Alerts fired by rule, and whether they were sent, partly sent, failed or deduplicated"
+        ),
+        &["rule", "outcome"]
+    )
+    .unwrap();
+    static ref PRICE_BAND: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "stock_price_band",
+            "Lowest, median and highest price across providers with a fresh price"
+        ),
+        &["symbol", "stat"]
+    )
+    .unwrap();
+    static ref PRICE_SOURCES: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            "stock_price_sources",
+            "Providers with a fresh price in the stock_price_band"
+        ),
+        &["symbol"]
+    )
+    .unwrap();
+    static ref PROVIDER_RETRIES: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "provider_retries_total",
+            "Provider calls tried again after a transient failure"
+        ),
+        &["operation"]
+    )
+    .unwrap();
+    static ref DAILY_CANDLE: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "stock_price_daily",
+            "Open, high, low and close of the symbol's trading day so far"
+        ),
+        &["symbol", "session", "stat"]
+    )
+    .unwrap();
+    static ref PROVIDER_REQUEST_DURATION: HistogramVec = HistogramVec::new(
+        HistogramOpts::new(
+            "provider_request_duration_seconds",
+            "Time each provider call took, failed attempts included"
+        )
+        .buckets(vec![0.05, 0.1, 0.25, 0.5, 1., 2.5, 5., 10., 30.]),
+        &["operation"]
+    )
+    .unwrap();
+    static ref PROVIDER_ERRORS: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "provider_errors_total",
+            "Failed provider calls by HTTP status, or none, and kind of failure"
+        ),
+        &["operation", "status", "kind"]
+    )
+    .unwrap();
+    static ref LAST_UPDATE: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "last_update_timestamp_seconds",
+            "Unix time the symbol's price was last updated"
+        ),
+        &["symbol"]
+    )
+    .unwrap();
+    static ref PROVIDER_ENDPOINT_LATENCY: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "provider_endpoint_latency_seconds",
+            "Round trip to each regional provider endpoint at the last probe, while it answers"
+        ),
+        &["provider", "endpoint"]
+    )
+    .unwrap();
+    static ref PROVIDER_ENDPOINT_SELECTED: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            "provider_endpoint_selected",
+            "1 for the regional provider endpoint requests go to, 0 for the others"
+        ),
+        &["provider", "endpoint"]
+    )
+    .unwrap();
+    static ref STOCK_SMA: GaugeVec =
+        GaugeVec::new(Opts::new("stock_sma", "Simple moving average of the symbol's last period prices"), &["symbol", "period"]).unwrap();
+    static ref STOCK_EMA: GaugeVec =
+        GaugeVec::new(Opts::new("stock_ema", "Exponential moving average of the symbol's prices over period"), &["symbol", "period"]).unwrap();
+    static ref STOCK_RSI: GaugeVec =
+        GaugeVec::new(Opts::new("stock_rsi", "Relative strength index of the symbol's prices over period"), &["symbol", "period"]).unwrap();
+    static ref STORE_REPLICA_FALLBACKS: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "store_replica_fallbacks_total",
+            "Store reads the replica failed and the primary answered, by query"
+        ),
+        &["query"]
+    )
+    .unwrap();
+    static ref CANDLE_PATTERNS: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "candle_patterns_total",
+            "Candlestick patterns found on closed candles"
+        ),
+        &["symbol", "timeframe", "pattern"]
+    )
+    .unwrap();
+    static ref STOCK_DAY_OPEN: GaugeVec =
+        GaugeVec::new(Opts::new("stock_day_open", "Opening price of the symbol's trading day"), &["symbol"]).unwrap();
+    static ref STOCK_DAY_HIGH: GaugeVec =
+        GaugeVec::new(Opts::new("stock_day_high", "Highest price of the symbol's trading day"), &["symbol"]).unwrap();
+    static ref STOCK_DAY_LOW: GaugeVec =
+        GaugeVec::new(Opts::new("stock_day_low", "Lowest price of the symbol's trading day"), &["symbol"]).unwrap();
+    static ref STOCK_PREVIOUS_CLOSE: GaugeVec =
+        GaugeVec::new(Opts::new("stock_previous_close", "Closing price of the symbol's previous trading day"), &["symbol"]).unwrap();
+    static ref STOCK_VOLUME: GaugeVec =
+        GaugeVec::new(Opts::new("stock_volume", "Volume traded in the symbol's trading day"), &["symbol"]).unwrap();
+    static ref STOCK_CHANGE_PERCENT: GaugeVec =
+        GaugeVec::new(Opts::new("stock_change_percent", "Percent change of the symbol's price since the previous close"), &["symbol"]).unwrap();
+    static ref PORTFOLIO_POSITION_VALUE: GaugeVec =
+        GaugeVec::new(Opts::new("portfolio_position_value", "Market value of the held position in the symbol"), &["symbol"]).unwrap();
+    static ref PORTFOLIO_UNREALIZED_PNL: GaugeVec =
+        GaugeVec::new(Opts::new("portfolio_unrealized_pnl", "Unrealized profit or loss of the held position in the symbol against its cost basis"), &["symbol"]).unwrap();
+    static ref PORTFOLIO_TOTAL_VALUE: Gauge =
+        Gauge::new("portfolio_total_value", "Market value of all held positions with a price").unwrap();
+    static ref PORTFOLIO_TOTAL_UNREALIZED_PNL: Gauge =
+        Gauge::new("portfolio_total_unrealized_pnl", "Unrealized profit or loss of all held positions with a price").unwrap();
+    static ref ADR_PREMIUM: GaugeVec = GaugeVec::new(
+        Opts::new("adr_premium_percent", "Premium of the ADR over its underlying converted at the FX rate, negative for a discount"),
+        &["adr", "underlying"]
+    )
+    .unwrap();
+    static ref ADR_IMPLIED_PRICE: GaugeVec = GaugeVec::new(
+        Opts::new("adr_implied_price", "ADR price implied by its underlying, the FX rate and the ratio"),
+        &["adr", "underlying"]
+    )
+    .unwrap();
+    static ref CRYPTO_EFFECTIVE_PRICE: GaugeVec = GaugeVec::new(
+        Opts::new("crypto_effective_price", "Price per unit to buy or sell the pair on the venue after its fee and spread"),
+        &["symbol", "venue", "side"]
+    )
+    .unwrap();
+    static ref STOCK_PRICE_BASE: GaugeVec = GaugeVec::new(
+        Opts::new("stock_price_base", "Current price converted to the base currency at the latest FX rate"),
+        &["symbol", "asset_class", "currency"],
+    )
+    .unwrap();
+    static ref CRYPTO_BEST_PRICE: GaugeVec = GaugeVec::new(
+        Opts::new("crypto_best_price", "Best bid and ask for a crypto pair across venues, labelled with the venue quoting it"),
+        &["symbol", "side", "venue"],
+    )
+    .unwrap();
+    static ref CRYPTO_CONSOLIDATED_MID: GaugeVec = GaugeVec::new(
+        Opts::new("crypto_consolidated_mid", "Midpoint of the best bid and ask for a crypto pair across venues"),
+        &["symbol"],
+    )
+    .unwrap();
+    static ref WATCHDOG_TRIPS: IntCounter = IntCounter::new(
+        "watchdog_trips_total",
+        "Times the poll loop missed its watchdog deadline and was restarted"
+    )
+    .unwrap();
+    static ref BUILD_INFO: IntGaugeVec = IntGaugeVec::new(
+        Opts::new("build_info", "Always 1, labelled with what the running binary was built from"),
+        &["version", "git_sha", "rustc", "build_date"],
+    )
+    .unwrap();
+    static ref FEATURE_ENABLED: IntGaugeVec = IntGaugeVec::new(
+        Opts::new("feature_enabled", "1 while a runtime-toggleable subsystem is on, 0 while switched off"),
+        &["feature"],
+    )
+    .unwrap();
+    static ref HISTORY_GAPS: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            "history_gap_days",
+            "Trading days still missing from the stored daily closes"
+        ),
+        &["symbol"]
+    )
+    .unwrap();
+    static ref GAPS_REPAIRED: IntCounter = IntCounter::new(
+        "history_gaps_repaired_total",
+        "Missing daily closes fetched again"
+    )
+    .unwrap();
+    static ref DAILY_BUDGET: IntGauge = IntGauge::new(
+        "daily_budget_remaining",
+        "Credits left in the current daily pipeline run"
+    )
+    .unwrap();
+    static ref DAILY_RUN: IntGauge = IntGauge::new(
+        "daily_last_run_timestamp_seconds",
+        "When the daily pipeline last finished a run"
+    )
+    .unwrap();
+    static ref FUTURES_ACTIVE: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            "futures_active_contract",
+            "1 for the contract a continuous future follows, 0 for the others"
+        ),
+        &["symbol", "contract"]
+    )
+    .unwrap();
+    static ref FUTURES_ROLLS: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "futures_rolls_total",
+            "Times a continuous future moved to the next contract"
+        ),
+        &["symbol"]
+    )
+    .unwrap();
+    static ref OPTIONS_EXPOSURE: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "options_exposure",
+            "Greeks summed over a symbol's options weighted by open interest, in shares"
+        ),
+        &["symbol", "greek"]
+    )
+    .unwrap();
+    static ref OPTION_GREEK: GaugeVec = GaugeVec::new(
+        Opts::new("option_greek", "Black-Scholes Greek of one options contract"),
+        &["symbol", "contract", "greek"]
+    )
+    .unwrap();
+    static ref EARNINGS_SURPRISE: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "earnings_surprise_percent",
+            "How far the latest reported quarter beat the estimate"
+        ),
+        &["symbol", "measure"]
+    )
+    .unwrap();
+    static ref ANALYST_TARGET: GaugeVec = GaugeVec::new(
+        Opts::new("analyst_price_target", "Consensus analyst price target"),
+        &["symbol", "stat"]
+    )
+    .unwrap();
+    static ref ANALYST_RATING: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "analyst_rating",
+            "Consensus rating, 0 for strong sell to 10 for strong buy"
+        ),
+        &["symbol"]
+    )
+    .unwrap();
+    static ref ANALYST_RATINGS: IntGaugeVec = IntGaugeVec::new(
+        Opts::new("analyst_ratings", "Analysts per rating this month"),
+        &["symbol", "rating"]
+    )
+    .unwrap();
+    static ref ANALYST_UPSIDE: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "analyst_target_upside_percent",
+            "How far the average analyst target is above the price"
+        ),
+        &["symbol"]
+    )
+    .unwrap();
+    static ref SYMBOL_STAT: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "symbol_stat",
+            "Slow-moving statistics derived from daily bars"
+        ),
+        &["symbol", "stat"]
+    )
+    .unwrap();
+    static ref PIPELINE_LAG: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "pipeline_lag_seconds",
+            "How far behind each subsystem is, growing while it is stuck on overdue work"
+        ),
+        &["subsystem"]
+    )
+    .unwrap();
+    static ref PIPELINE_BACKLOG: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            "pipeline_backlog",
+            "Work waiting for each subsystem: deferred symbols or queued events"
+        ),
+        &["subsystem"]
+    )
+    .unwrap();
+    static ref PIPELINE_DEGRADED: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            "pipeline_degraded",
+            "1 while a subsystem is further behind than degraded_lag_secs"
+        ),
+        &["subsystem"]
+    )
+    .unwrap();
+    static ref VOLUME_PROFILE: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "volume_profile_level",
+            "The session volume profile's point of control and value area bounds"
+        ),
+        &["symbol", "level"]
+    )
+    .unwrap();
+    static ref EDGAR_FILINGS: IntCounterVec = IntCounterVec::new(
+        Opts::new("edgar_filings_total", "New SEC filings seen per form type"),
+        &["symbol", "form"]
+    )
+    .unwrap();
+    static ref SOCIAL_MENTIONS: GaugeVec = GaugeVec::new(
+        Opts::new("social_mentions", "Mentions over the last social poll"),
+        &["symbol", "source"]
+    )
+    .unwrap();
+    static ref SOCIAL_BUZZ: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "social_buzz_zscore",
+            "How unusual the last poll's mentions are against recent polls"
+        ),
+        &["symbol", "source"]
+    )
+    .unwrap();
+    static ref SOCIAL_SENTIMENT: GaugeVec = GaugeVec::new(
+        Opts::new(
+            "social_sentiment",
+            "Bullish less bearish over tagged messages, from -1 to 1"
+        ),
+        &["symbol", "source"]
+    )
+    .unwrap();
+    static ref MARKET_INDICATOR: GaugeVec = GaugeVec::new(
+        Opts::new("market_indicator", "Market-wide context indicators"),
+        &["name"]
+    )
+    .unwrap();
+}
+
+pub fn register_metrics() {
+    static REGISTER: Once = Once::new();
+    REGISTER.call_once(register);
 }
 
-fn register_metrics() {
+fn register() {
     REGISTRY
         .register(Box::new(STOCK_PRICE.clone()))
         .expect("Failed to register stock_price metric");
+    REGISTRY
+        .register(Box::new(DIVIDEND_INCOME.clone()))
+        .expect("Failed to register dividend_projected_income metric");
+    REGISTRY
+        .register(Box::new(DIVIDEND_INCOME_TOTAL.clone()))
+        .expect("Failed to register dividend_projected_income_total metric");
+    REGISTRY
+        .register(Box::new(SLO_COMPLIANCE.clone()))
+        .expect("Failed to register slo_freshness_compliance_ratio metric");
+    REGISTRY
+        .register(Box::new(SLO_BURN_RATE.clone()))
+        .expect("Failed to register slo_freshness_burn_rate metric");
+    REGISTRY
+        .register(Box::new(SLO_OBJECTIVE_SECONDS.clone()))
+        .expect("Failed to register slo_freshness_objective_seconds metric");
+    REGISTRY
+        .register(Box::new(SLO_TARGET.clone()))
+        .expect("Failed to register slo_freshness_target_ratio metric");
+    REGISTRY
+        .register(Box::new(STRETCH_FACTOR.clone()))
+        .expect("Failed to register schedule_stretch_factor metric");
+    REGISTRY
+        .register(Box::new(DEFERRED_SYMBOLS.clone()))
+        .expect("Failed to register schedule_deferred_symbols metric");
+    REGISTRY
+        .register(Box::new(ADAPTIVE_FACTOR.clone()))
+        .expect("Failed to register schedule_adaptive_factor metric");
+    REGISTRY
+        .register(Box::new(LISTING_PRICE.clone()))
+        .expect("Failed to register listing_price metric");
+    REGISTRY
+        .register(Box::new(COMPANY_PRICE.clone()))
+        .expect("Failed to register company_price metric");
+    REGISTRY
+        .register(Box::new(RATE_SPREAD.clone()))
+        .expect("Failed to register rate_spread metric");
+    REGISTRY
+        .register(Box::new(EQUITY_RISK_PREMIUM.clone()))
+        .expect("Failed to register equity_risk_premium metric");
+    REGISTRY
+        .register(Box::new(ECON_EVENT_MINUTES.clone()))
+        .expect("Failed to register econ_event_minutes_until metric");
+    REGISTRY
+        .register(Box::new(CLUSTER_MEMBERS.clone()))
+        .expect("Failed to register cluster_members metric");
+    REGISTRY
+        .register(Box::new(SHARD_SYMBOLS.clone()))
+        .expect("Failed to register shard_symbols metric");
+    REGISTRY
+        .register(Box::new(HTTP_SHED.clone()))
+        .expect("Failed to register http_requests_shed_total metric");
+    REGISTRY
+        .register(Box::new(MARKET_OPEN.clone()))
+        .expect("Failed to register market_open metric");
+    REGISTRY
+        .register(Box::new(SECONDS_UNTIL_OPEN.clone()))
+        .expect("Failed to register seconds_until_open metric");
+    REGISTRY
+        .register(Box::new(DATA_QUALITY.clone()))
+        .expect("Failed to register data_quality_score metric");
+    REGISTRY
+        .register(Box::new(API_KEY_VALID.clone()))
+        .expect("Failed to register api_key_valid metric");
+    REGISTRY
+        .register(Box::new(API_KEY_ACTIVE.clone()))
+        .expect("Failed to register api_key_active metric");
+    REGISTRY
+        .register(Box::new(TASK_RESTARTS.clone()))
+        .expect("Failed to register task_restarts_total metric");
+    REGISTRY
+        .register(Box::new(HISTORY_BYTES.clone()))
+        .expect("Failed to register history_memory_bytes metric");
+    REGISTRY
+        .register(Box::new(HISTORY_SPILLED.clone()))
+        .expect("Failed to register history_spilled_points_total metric");
+    REGISTRY
+        .register(Box::new(ALLOCATION.clone()))
+        .expect("Failed to register portfolio_allocation_ratio metric");
+    REGISTRY
+        .register(Box::new(TRACKING_ERROR.clone()))
+        .expect("Failed to register portfolio_tracking_error metric");
+    REGISTRY
+        .register(Box::new(INFORMATION_RATIO.clone()))
+        .expect("Failed to register portfolio_information_ratio metric");
+    REGISTRY
+        .register(Box::new(CLOSE_WATCHES.clone()))
+        .expect("Failed to register close_watch_symbols metric");
+    REGISTRY
+        .register(Box::new(NOTIFICATIONS_DEDUPLICATED.clone()))
+        .expect("Failed to register notifications_deduplicated_total metric");
+    REGISTRY
+        .register(Box::new(RATE_LIMIT_REMAINING.clone()))
+        .expect("Failed to register rate_limit_remaining metric");
+    REGISTRY
+        .register(Box::new(PRICE_TIMESTAMP_SKEW.clone()))
+        .expect("Failed to register price_timestamp_skew_seconds metric");
+    REGISTRY
+        .register(Box::new(CLOCK_SKEW.clone()))
+        .expect("Failed to register clock_skew_estimate_seconds metric");
+    REGISTRY
+        .register(Box::new(PRICES_STORED.clone()))
+        .expect("Failed to register prices_stored_total metric");
+    REGISTRY
+        .register(Box::new(LIMIT_REJECTIONS.clone()))
+        .expect("Failed to register limit_rejections_total metric");
+    REGISTRY
+        .register(Box::new(CONFIG_CHANGES.clone()))
+        .expect("Failed to register config_changes_total metric");
+    REGISTRY
+        .register(Box::new(STATE_RECOVERIES.clone()))
+        .expect("Failed to register state_recoveries_total metric");
+    REGISTRY
+        .register(Box::new(READ_THROUGH.clone()))
+        .expect("Failed to register read_through_requests_total metric");
+    REGISTRY
+        .register(Box::new(STREAM_CONNECTED.clone()))
+        .expect("Failed to register stream_connected metric");
+    REGISTRY
+        .register(Box::new(STREAM_SYMBOLS.clone()))
+        .expect("Failed to register stream_symbols metric");
+    REGISTRY
+        .register(Box::new(CHAOS_FAULTS.clone()))
+        .expect("Failed to register chaos_faults_injected_total metric");
+    REGISTRY
+        .register(Box::new(ALERTS_FIRED.clone()))
+        .expect("Failed to register alerts_fired_total metric");
+    REGISTRY
+        .register(Box::new(PRICE_BAND.clone()))
+        .expect("Failed to register stock_price_band metric");
+    REGISTRY
+        .register(Box::new(PRICE_SOURCES.clone()))
+        .expect("Failed to register stock_price_sources metric");
+    REGISTRY
+        .register(Box::new(PROVIDER_RETRIES.clone()))
+        .expect("Failed to register provider_retries_total metric");
+    REGISTRY
+        .register(Box::new(DAILY_CANDLE.clone()))
+        .expect("Failed to register stock_price_daily metric");
+    REGISTRY
+        .register(Box::new(PROVIDER_REQUEST_DURATION.clone()))
+        .expect("Failed to register provider_request_duration_seconds metric");
+    REGISTRY
+        .register(Box::new(PROVIDER_ERRORS.clone()))
+        .expect("Failed to register provider_errors_total metric");
+    REGISTRY
+        .register(Box::new(LAST_UPDATE.clone()))
+        .expect("Failed to register last_update_timestamp_seconds metric");
+    REGISTRY
+        .register(Box::new(PROVIDER_ENDPOINT_LATENCY.clone()))
+        .expect("Failed to register provider_endpoint_latency_seconds metric");
+    REGISTRY
+        .register(Box::new(PROVIDER_ENDPOINT_SELECTED.clone()))
+        .expect("Failed to register provider_endpoint_selected metric");
+    REGISTRY
+        .register(Box::new(STOCK_SMA.clone()))
+        .expect("Failed to register stock_sma metric");
+    REGISTRY
+        .register(Box::new(STOCK_EMA.clone()))
+        .expect("Failed to register stock_ema metric");
+    REGISTRY
+        .register(Box::new(STOCK_RSI.clone()))
+        .expect("Failed to register stock_rsi metric");
+    REGISTRY
+        .register(Box::new(STORE_REPLICA_FALLBACKS.clone()))
+        .expect("Failed to register store_replica_fallbacks_total metric");
+    REGISTRY
+        .register(Box::new(CANDLE_PATTERNS.clone()))
+        .expect("Failed to register candle_patterns_total metric");
+    REGISTRY
+        .register(Box::new(STOCK_DAY_OPEN.clone()))
+        .expect("Failed to register stock_day_open metric");
+    REGISTRY
+        .register(Box::new(STOCK_DAY_HIGH.clone()))
+        .expect("Failed to register stock_day_high metric");
+    REGISTRY
+        .register(Box::new(STOCK_DAY_LOW.clone()))
+        .expect("Failed to register stock_day_low metric");
+    REGISTRY
+        .register(Box::new(STOCK_PREVIOUS_CLOSE.clone()))
+        .expect("Failed to register stock_previous_close metric");
+    REGISTRY
+        .register(Box::new(STOCK_VOLUME.clone()))
+        .expect("Failed to register stock_volume metric");
+    REGISTRY
+        .register(Box::new(STOCK_CHANGE_PERCENT.clone()))
+        .expect("Failed to register stock_change_percent metric");
+    REGISTRY
+        .register(Box::new(PORTFOLIO_POSITION_VALUE.clone()))
+        .expect("Failed to register portfolio_position_value metric");
+    REGISTRY
+        .register(Box::new(PORTFOLIO_UNREALIZED_PNL.clone()))
+        .expect("Failed to register portfolio_unrealized_pnl metric");
+    REGISTRY
+        .register(Box::new(PORTFOLIO_TOTAL_VALUE.clone()))
+        .expect("Failed to register portfolio_total_value metric");
+    REGISTRY
+        .register(Box::new(PORTFOLIO_TOTAL_UNREALIZED_PNL.clone()))
+        .expect("Failed to register portfolio_total_unrealized_pnl metric");
+    REGISTRY
+        .register(Box::new(ADR_PREMIUM.clone()))
+        .expect("Failed to register adr_premium_percent metric");
+    REGISTRY
+        .register(Box::new(ADR_IMPLIED_PRICE.clone()))
+        .expect("Failed to register adr_implied_price metric");
+    REGISTRY
+        .register(Box::new(CRYPTO_EFFECTIVE_PRICE.clone()))
+        .expect("Failed to register crypto_effective_price metric");
+    REGISTRY
+        .register(Box::new(STOCK_PRICE_BASE.clone()))
+        .expect("Failed to register stock_price_base metric");
+    REGISTRY
+        .register(Box::new(CRYPTO_BEST_PRICE.clone()))
+        .expect("Failed to register crypto_best_price metric");
+    REGISTRY
+        .register(Box::new(CRYPTO_CONSOLIDATED_MID.clone()))
+        .expect("Failed to register crypto_consolidated_mid metric");
+    REGISTRY
+        .register(Box::new(WATCHDOG_TRIPS.clone()))
+        .expect("Failed to register watchdog_trips_total metric");
+    REGISTRY
+        .register(Box::new(BUILD_INFO.clone()))
+        .expect("Failed to register build_info metric");
+    update_build_info(&version::BUILD);
+    REGISTRY
+        .register(Box::new(FEATURE_ENABLED.clone()))
+        .expect("Failed to register feature_enabled metric");
+    REGISTRY
+        .register(Box::new(HISTORY_GAPS.clone()))
+        .expect("Failed to register history_gap_days metric");
+    REGISTRY
+        .register(Box::new(GAPS_REPAIRED.clone()))
+        .expect("Failed to register history_gaps_repaired_total metric");
+    REGISTRY
+        .register(Box::new(DAILY_BUDGET.clone()))
+        .expect("Failed to register daily_budget_remaining metric");
+    REGISTRY
+        .register(Box::new(DAILY_RUN.clone()))
+        .expect("Failed to register daily_last_run_timestamp_seconds metric");
+    REGISTRY
+        .register(Box::new(FUTURES_ACTIVE.clone()))
+        .expect("Failed to register futures_active_contract metric");
+    REGISTRY
+        .register(Box::new(FUTURES_ROLLS.clone()))
+        .expect("Failed to register futures_rolls_total metric");
+    REGISTRY
+        .register(Box::new(OPTIONS_EXPOSURE.clone()))
+        .expect("Failed to register options_exposure metric");
+    REGISTRY
+        .register(Box::new(OPTION_GREEK.clone()))
+        .expect("Failed to register option_greek metric");
+    REGISTRY
+        .register(Box::new(EARNINGS_SURPRISE.clone()))
+        .expect("Failed to register earnings_surprise_percent metric");
+    REGISTRY
+        .register(Box::new(ANALYST_TARGET.clone()))
+        .expect("Failed to register analyst_price_target metric");
+    REGISTRY
+        .register(Box::new(ANALYST_RATING.clone()))
+        .expect("Failed to register analyst_rating metric");
+    REGISTRY
+        .register(Box::new(ANALYST_RATINGS.clone()))
+        .expect("Failed to register analyst_ratings metric");
+    REGISTRY
+        .register(Box::new(ANALYST_UPSIDE.clone()))
+        .expect("Failed to register analyst_target_upside_percent metric");
+    REGISTRY
+        .register(Box::new(SYMBOL_STAT.clone()))
+        .expect("Failed to register symbol_stat metric");
+    REGISTRY
+        .register(Box::new(VOLUME_PROFILE.clone()))
+        .expect("Failed to register volume_profile_level metric");
+    REGISTRY
+        .register(Box::new(PIPELINE_LAG.clone()))
+        .expect("Failed to register pipeline_lag_seconds metric");
+    REGISTRY
+        .register(Box::new(PIPELINE_BACKLOG.clone()))
+        .expect("Failed to register pipeline_backlog metric");
+    REGISTRY
+        .register(Box::new(PIPELINE_DEGRADED.clone()))
+        .expect("Failed to register pipeline_degraded metric");
+    REGISTRY
+        .register(Box::new(EDGAR_FILINGS.clone()))
+        .expect("Failed to register edgar_filings_total metric");
+    REGISTRY
+        .register(Box::new(SOCIAL_MENTIONS.clone()))
+        .expect("Failed to register social_mentions metric");
+    REGISTRY
+        .register(Box::new(SOCIAL_BUZZ.clone()))
+        .expect("Failed to register social_buzz_zscore metric");
+    REGISTRY
+        .register(Box::new(SOCIAL_SENTIMENT.clone()))
+        .expect("Failed to register social_sentiment metric");
+    REGISTRY
+        .register(Box::new(MARKET_INDICATOR.clone()))
+        .expect("Failed to register market_indicator metric");
 }
 
 pub struct MetricServer;
@@ -30,26 +960,861 @@ pub struct MetricServer;
 impl MetricServer {
     #[instrument]
     pub async fn start(addr: SocketAddr) {
-        info!(addr = %addr, "Starting metrics server");
+        let config = MetricsConfig {
+            addr,
+            ..MetricsConfig::default()
+        };
+        Self::run(&config, Router::new(), Router::new()).await;
+    }
+
+    /// Serves `/metrics` together with the JSON API, gzip or deflate
+    /// compressed when the client's Accept-Encoding allows it.
+    #[instrument(skip_all, fields(addr = %config.addr))]
+    pub async fn serve(config: &MetricsConfig, state: ApiState) {
+        Self::run(
+            config,
+            api::routes(state.clone())
+                .merge(api::probe_routes(state.clone()))
+                .merge(api::openapi::routes()),
+            api::long_poll_routes(state),
+        )
+        .await;
+    }
+
+    // Requests beyond `max_concurrent` or slower than the timeout get a 503
+    // so HTTP load cannot starve the polling tasks. `long_poll` routes wait
+    // on purpose and bound their own time instead.
+    async fn run(config: &MetricsConfig, routes: Router, long_poll: Router) {
+        info!(addr = %config.addr, "Starting metrics server");
         register_metrics();
-        let route = metrics_route();
-        warp::serve(route).run(addr).await;
+        register_ticker_labels(&config.ticker_labels);
+        let degraded_lag_secs = config.degraded_lag_secs;
+        let app = routes
+            .route("/metrics", get(move || scrape(degraded_lag_secs)))
+            .layer(
+                ServiceBuilder::new()
+                    .layer(HandleErrorLayer::new(shed))
+                    .load_shed()
+                    .concurrency_limit(config.max_concurrent.max(1))
+                    .timeout(Duration::from_secs(config.request_timeout_secs.max(1))),
+            )
+            .merge(long_poll)
+            .layer(CompressionLayer::new())
+            .layer(TraceLayer::new_for_http());
+
+        let listener = match TcpListener::bind(config.addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!(error = %e, "Failed to bind metrics server");
+                return;
+            }
+        };
+        if let Err(e) = axum::serve(listener, app).await {
+            error!(error = %e, "Metrics server failed");
+        }
     }
 }
 
-#[instrument]
-fn metrics_route() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-    warp::path!("metrics").map(move || -> String {
-        let metric_families = REGISTRY.gather();
-        let encoder = prometheus::TextEncoder::new();
-        let mut buffer = vec![];
-        encoder.encode(&metric_families, &mut buffer).unwrap();
-        String::from_utf8(buffer).unwrap()
-    })
+async fn shed(error: BoxError) -> impl IntoResponse {
+    let reason = if error.is::<Overloaded>() {
+        "overloaded"
+    } else if error.is::<Elapsed>() {
+        "timeout"
+    } else {
+        "error"
+    };
+    HTTP_SHED.with_label_values(&[reason]).inc();
+    warn!(reason, error = %error, "Shedding HTTP request");
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(RETRY_AFTER, "1")],
+        reason,
+    )
+}
+
+pub fn encode() -> String {
+    let mut buffer = vec![];
+    encode_into(&REGISTRY, &mut buffer);
+    String::from_utf8(buffer).unwrap()
+}
+
+/// Encodes `registry` in the text format into `buffer`, replacing its
+/// contents but keeping its capacity for the next call.
+pub fn encode_into(registry: &prometheus::Registry, buffer: &mut Vec<u8>) {
+    buffer.clear();
+    prometheus::TextEncoder::new()
+        .encode(&registry.gather(), buffer)
+        .expect("text encoding into a Vec cannot fail");
+}
+
+/// Remembers the size of the last scrape so the next one allocates its
+/// buffer once, at the right size, instead of doubling its way up, and
+/// hands the buffer to the response without copying it.
+#[derive(Debug, Default)]
+pub struct ScrapeEncoder {
+    last_len: AtomicUsize,
+}
+
+impl ScrapeEncoder {
+    pub fn encode(&self, registry: &prometheus::Registry) -> Bytes {
+        let hint = self.last_len.load(Ordering::Relaxed);
+        let mut buffer = Vec::with_capacity(hint + hint / 16);
+        encode_into(registry, &mut buffer);
+        self.last_len.store(buffer.len(), Ordering::Relaxed);
+        Bytes::from(buffer)
+    }
+}
+
+async fn scrape(degraded_lag_secs: u64) -> impl IntoResponse {
+    lag::export(Utc::now(), degraded_lag_secs);
+    (
+        [(CONTENT_TYPE, prometheus::TEXT_FORMAT)],
+        SCRAPE_ENCODER.encode(&REGISTRY),
+    )
 }
 
 #[instrument]
-pub fn update_stock_price(price: f64, symbol: &str) {
+pub fn update_stock_price(price: f64, symbol: &str, asset_class: &str, currency: &str) {
     trace!("Updating stock price");
-    STOCK_PRICE.with_label_values(&[symbol]).set(price);
+    STOCK_PRICE
+        .with_label_values(&[symbol, asset_class, currency])
+        .set(price);
+}
+
+#[instrument]
+pub fn update_dividend_income(income: f64, month: &str) {
+    DIVIDEND_INCOME.with_label_values(&[month]).set(income);
+}
+
+#[instrument]
+pub fn update_dividend_income_total(income: f64) {
+    DIVIDEND_INCOME_TOTAL.set(income);
+}
+
+#[instrument]
+pub fn update_slo_objective(asset_class: &str, max_staleness_secs: f64, target: f64) {
+    SLO_OBJECTIVE_SECONDS
+        .with_label_values(&[asset_class])
+        .set(max_staleness_secs);
+    SLO_TARGET.with_label_values(&[asset_class]).set(target);
+}
+
+#[instrument]
+pub fn update_slo_compliance(asset_class: &str, window: &str, compliance: f64, burn_rate: f64) {
+    SLO_COMPLIANCE
+        .with_label_values(&[asset_class, window])
+        .set(compliance);
+    SLO_BURN_RATE
+        .with_label_values(&[asset_class, window])
+        .set(burn_rate);
+}
+
+#[instrument]
+pub fn update_stretch_factor(priority: &str, stretch: f64) {
+    STRETCH_FACTOR.with_label_values(&[priority]).set(stretch);
+}
+
+#[instrument]
+pub fn update_deferred_symbols(symbols: usize) {
+    DEFERRED_SYMBOLS.set(symbols as f64);
+}
+
+#[instrument]
+pub fn update_adaptive_factor(symbol: &str, factor: f64) {
+    ADAPTIVE_FACTOR.with_label_values(&[symbol]).set(factor);
+}
+
+#[instrument]
+pub fn update_listing_price(
+    price: f64,
+    company: &str,
+    exchange: &str,
+    symbol: &str,
+    currency: &str,
+) {
+    LISTING_PRICE
+        .with_label_values(&[company, exchange, symbol, currency])
+        .set(price);
+}
+
+#[instrument]
+pub fn update_company_price(price: f64, company: &str) {
+    COMPANY_PRICE.with_label_values(&[company]).set(price);
+}
+
+#[instrument]
+pub fn update_rate_spread(name: &str, spread: f64) {
+    RATE_SPREAD.with_label_values(&[name]).set(spread);
+}
+
+#[instrument]
+pub fn update_equity_risk_premium(premium: f64) {
+    EQUITY_RISK_PREMIUM.set(premium);
+}
+
+#[instrument]
+pub fn update_econ_event_minutes(event: &str, minutes: f64) {
+    ECON_EVENT_MINUTES.with_label_values(&[event]).set(minutes);
+}
+
+#[instrument]
+pub fn update_shard(members: usize, symbols: usize) {
+    CLUSTER_MEMBERS.set(members as f64);
+    SHARD_SYMBOLS.set(symbols as f64);
+}
+
+#[instrument]
+pub fn remove_stock_price(symbol: &str, asset_class: &str, currency: &str) {
+    let _ = STOCK_PRICE.remove_label_values(&[symbol, asset_class, currency]);
+}
+
+#[instrument]
+pub fn update_market_state(exchange: &str, open: bool, seconds_until_open: u64) {
+    MARKET_OPEN
+        .with_label_values(&[exchange])
+        .set(if open { 1. } else { 0. });
+    SECONDS_UNTIL_OPEN
+        .with_label_values(&[exchange])
+        .set(seconds_until_open as f64);
+}
+
+#[instrument]
+pub fn update_data_quality(symbol: &str, score: f64) {
+    DATA_QUALITY.with_label_values(&[symbol]).set(score);
+}
+
+#[instrument]
+pub fn update_api_key(key: &str, valid: Option<bool>, active: bool) {
+    let valid = match valid {
+        Some(true) => 1.,
+        Some(false) => 0.,
+        None => -1.,
+    };
+    API_KEY_VALID.with_label_values(&[key]).set(valid);
+    API_KEY_ACTIVE
+        .with_label_values(&[key])
+        .set(if active { 1. } else { 0. });
+}
+
+#[instrument]
+pub fn update_task_restart(task: &str, reason: &str) {
+    TASK_RESTARTS.with_label_values(&[task, reason]).inc();
+}
+
+#[instrument]
+pub fn update_history_bytes(bytes: usize) {
+    HISTORY_BYTES.set(bytes as i64);
+}
+
+#[instrument]
+pub fn update_history_spilled(points: usize) {
+    HISTORY_SPILLED.inc_by(points as u64);
+}
+
+#[instrument]
+pub fn update_allocation(dimension: &str, group: &str, share: Option<f64>) {
+    match share {
+        Some(share) => ALLOCATION.with_label_values(&[dimension, group]).set(share),
+        None => {
+            let _ = ALLOCATION.remove_label_values(&[dimension, group]);
+        }
+    }
+}
+
+#[instrument]
+pub fn update_tracking(benchmark: &str, tracking_error: f64, information_ratio: Option<f64>) {
+    TRACKING_ERROR
+        .with_label_values(&[benchmark])
+        .set(tracking_error);
+    match information_ratio {
+        Some(ratio) => INFORMATION_RATIO.with_label_values(&[benchmark]).set(ratio),
+        None => {
+            let _ = INFORMATION_RATIO.remove_label_values(&[benchmark]);
+        }
+    }
+}
+
+#[instrument]
+pub fn update_close_watches(count: usize) {
+    CLOSE_WATCHES.set(count as i64);
+}
+
+#[instrument]
+pub fn update_notifications_deduplicated() {
+    NOTIFICATIONS_DEDUPLICATED.inc();
+}
+
+#[instrument]
+pub fn update_rate_limit_remaining(window: &str, remaining: u64) {
+    RATE_LIMIT_REMAINING
+        .with_label_values(&[window])
+        .set(remaining as i64);
+}
+
+#[instrument]
+pub fn update_price_timestamp_skew(symbol: &str, skew_secs: f64) {
+    PRICE_TIMESTAMP_SKEW
+        .with_label_values(&[symbol])
+        .set(skew_secs);
+}
+
+#[instrument]
+pub fn update_clock_skew(skew_secs: f64) {
+    CLOCK_SKEW.set(skew_secs);
+}
+
+#[instrument]
+pub fn update_prices_stored(count: usize) {
+    PRICES_STORED.inc_by(count as u64);
+}
+
+#[instrument]
+pub fn update_limit_rejection(limit: &str, count: usize) {
+    LIMIT_REJECTIONS
+        .with_label_values(&[limit])
+        .inc_by(count as u64);
+}
+
+pub fn update_config_change(source: &str, outcome: &str) {
+    CONFIG_CHANGES.with_label_values(&[source, outcome]).inc();
+}
+
+pub fn update_state_recovery(file: &str, outcome: &str) {
+    STATE_RECOVERIES.with_label_values(&[file, outcome]).inc();
+}
+
+// Label keys of tickers file entries and the gauge exporting them, set up
+// from the config.
+static TICKER_LABELS: OnceLock<(Vec<String>, IntGaugeVec)> = OnceLock::new();
+
+/// Exports `ticker_labels{symbol, <key>...}` with the given label keys of
+/// tickers file entries, for joining onto per-symbol series. Only the
+/// first call takes.
+pub fn register_ticker_labels(keys: &[String]) {
+    let keys: Vec<String> = keys.iter().filter(|k| *k != "symbol").cloned().collect();
+    if keys.is_empty() || TICKER_LABELS.get().is_some() {
+        return;
+    }
+    let names: Vec<&str> = std::iter::once("symbol")
+        .chain(keys.iter().map(String::as_str))
+        .collect();
+    let gauge = match IntGaugeVec::new(
+        Opts::new(
+            "ticker_labels",
+            "Always 1, labelled with the symbol's tickers file labels",
+        ),
+        &names,
+    ) {
+        Ok(gauge) => gauge,
+        Err(e) => {
+            warn!(keys = ?keys, error = %e, "Can't export ticker labels");
+            return;
+        }
+    };
+    match REGISTRY.register(Box::new(gauge.clone())) {
+        Ok(()) => {
+            let _ = TICKER_LABELS.set((keys, gauge));
+        }
+        Err(e) => warn!(error = %e, "Failed to register ticker_labels metric"),
+    }
+}
+
+/// Sets `ticker_labels` for `symbols`, a key they don't have being empty.
+#[instrument(skip_all)]
+pub fn update_ticker_labels(
+    symbols: &[String],
+    labels: &BTreeMap<String, BTreeMap<String, String>>,
+) {
+    let Some((keys, gauge)) = TICKER_LABELS.get() else {
+        return;
+    };
+    gauge.reset();
+    for symbol in symbols {
+        let own = labels.get(symbol);
+        let values: Vec<&str> = std::iter::once(symbol.as_str())
+            .chain(
+                keys.iter()
+                    .map(|key| own.and_then(|own| own.get(key)).map_or("", String::as_str)),
+            )
+            .collect();
+        gauge.with_label_values(&values).set(1);
+    }
+}
+
+/// Series currently exported.
+pub fn series_count() -> usize {
+    REGISTRY.gather().iter().map(|f| f.get_metric().len()).sum()
+}
+
+#[instrument]
+pub fn update_read_through(client: &str, outcome: &str) {
+    READ_THROUGH.with_label_values(&[client, outcome]).inc();
+}
+
+#[instrument]
+pub fn update_stream_connected(connected: bool) {
+    STREAM_CONNECTED.set(connected as i64);
+}
+
+#[instrument]
+pub fn update_stream_symbols(count: usize) {
+    STREAM_SYMBOLS.set(count as i64);
+}
+
+#[instrument]
+pub fn update_chaos_fault(fault: &str) {
+    CHAOS_FAULTS.with_label_values(&[fault]).inc();
+}
+
+#[instrument]
+pub fn update_alert_fired(rule: &str, outcome: &str) {
+    ALERTS_FIRED.with_label_values(&[rule, outcome]).inc();
+}
+
+#[instrument(skip(band))]
+pub fn update_price_band(symbol: &str, band: &Band) {
+    for (stat, value) in [
+        ("min", band.min),
+        ("median", band.median),
+        ("max", band.max),
+    ] {
+        PRICE_BAND.with_label_values(&[symbol, stat]).set(value);
+    }
+    PRICE_SOURCES
+        .with_label_values(&[symbol])
+        .set(band.sources as i64);
+}
+
+// Below two sources there's no band to draw.
+#[instrument]
+pub fn remove_price_band(symbol: &str) {
+    for stat in ["min", "median", "max"] {
+        let _ = PRICE_BAND.remove_label_values(&[symbol, stat]);
+    }
+    let _ = PRICE_SOURCES.remove_label_values(&[symbol]);
+}
+
+#[instrument]
+pub fn update_provider_retry(operation: &str) {
+    PROVIDER_RETRIES.with_label_values(&[operation]).inc();
+}
+
+#[instrument(skip(candle))]
+pub fn update_daily_candle(symbol: &str, candle: &DailyCandle) {
+    let session = candle.session.to_string();
+    for (stat, value) in [
+        ("open", candle.open),
+        ("high", candle.high),
+        ("low", candle.low),
+        ("close", candle.close),
+    ] {
+        DAILY_CANDLE
+            .with_label_values(&[symbol, &session, stat])
+            .set(value);
+    }
+}
+
+#[instrument]
+pub fn remove_daily_candle(symbol: &str, session: NaiveDate) {
+    let session = session.to_string();
+    for stat in ["open", "high", "low", "close"] {
+        let _ = DAILY_CANDLE.remove_label_values(&[symbol, &session, stat]);
+    }
+}
+
+#[instrument]
+pub fn update_provider_request_duration(operation: &str, secs: f64) {
+    PROVIDER_REQUEST_DURATION
+        .with_label_values(&[operation])
+        .observe(secs);
+}
+
+#[instrument]
+pub fn update_provider_error(operation: &str, status: &str, kind: &str) {
+    PROVIDER_ERRORS
+        .with_label_values(&[operation, status, kind])
+        .inc();
+}
+
+#[instrument]
+pub fn update_last_update(symbol: &str, at: DateTime<Utc>) {
+    LAST_UPDATE
+        .with_label_values(&[symbol])
+        .set(at.timestamp_millis() as f64 / 1000.);
+}
+
+#[instrument]
+pub fn update_provider_endpoint_latency(provider: &str, endpoint: &str, secs: Option<f64>) {
+    match secs {
+        Some(secs) => PROVIDER_ENDPOINT_LATENCY
+            .with_label_values(&[provider, endpoint])
+            .set(secs),
+        None => {
+            let _ = PROVIDER_ENDPOINT_LATENCY.remove_label_values(&[provider, endpoint]);
+        }
+    }
+}
+
+#[instrument]
+pub fn update_provider_endpoint_selected(provider: &str, endpoint: &str, selected: bool) {
+    PROVIDER_ENDPOINT_SELECTED
+        .with_label_values(&[provider, endpoint])
+        .set(selected as i64);
+}
+
+#[instrument]
+pub fn update_stock_sma(symbol: &str, period: usize, value: f64) {
+    STOCK_SMA
+        .with_label_values(&[symbol, &period.to_string()])
+        .set(value);
+}
+
+#[instrument]
+pub fn update_stock_ema(symbol: &str, period: usize, value: f64) {
+    STOCK_EMA
+        .with_label_values(&[symbol, &period.to_string()])
+        .set(value);
+}
+
+#[instrument]
+pub fn update_stock_rsi(symbol: &str, period: usize, value: f64) {
+    STOCK_RSI
+        .with_label_values(&[symbol, &period.to_string()])
+        .set(value);
+}
+
+#[instrument]
+pub fn update_replica_fallbacks(query: &str) {
+    STORE_REPLICA_FALLBACKS.with_label_values(&[query]).inc();
+}
+
+#[instrument]
+pub fn update_candle_pattern(symbol: &str, timeframe: &str, pattern: &str) {
+    CANDLE_PATTERNS
+        .with_label_values(&[symbol, timeframe, pattern])
+        .inc();
+}
+
+#[instrument]
+pub fn update_day_quote(symbol: &str, day: &DayQuote) {
+    STOCK_DAY_OPEN.with_label_values(&[symbol]).set(day.open);
+    STOCK_DAY_HIGH.with_label_values(&[symbol]).set(day.high);
+    STOCK_DAY_LOW.with_label_values(&[symbol]).set(day.low);
+    STOCK_PREVIOUS_CLOSE
+        .with_label_values(&[symbol])
+        .set(day.previous_close);
+    if let Some(volume) = day.volume {
+        STOCK_VOLUME.with_label_values(&[symbol]).set(volume);
+    }
+    STOCK_CHANGE_PERCENT
+        .with_label_values(&[symbol])
+        .set(day.change_percent);
+}
+
+/// `None` drops the symbol's series, for positions without a price or no
+/// longer held.
+#[instrument]
+pub fn update_portfolio_position(symbol: &str, value_and_pnl: Option<(f64, f64)>) {
+    match value_and_pnl {
+        Some((value, pnl)) => {
+            PORTFOLIO_POSITION_VALUE
+                .with_label_values(&[symbol])
+                .set(value);
+            PORTFOLIO_UNREALIZED_PNL
+                .with_label_values(&[symbol])
+                .set(pnl);
+        }
+        None => {
+            let _ = PORTFOLIO_POSITION_VALUE.remove_label_values(&[symbol]);
+            let _ = PORTFOLIO_UNREALIZED_PNL.remove_label_values(&[symbol]);
+        }
+    }
+}
+
+#[instrument]
+pub fn update_portfolio_totals(value: f64, unrealized_pnl: f64) {
+    PORTFOLIO_TOTAL_VALUE.set(value);
+    PORTFOLIO_TOTAL_UNREALIZED_PNL.set(unrealized_pnl);
+}
+
+/// `None` drops the pair's series while a leg has no fresh price.
+#[instrument]
+pub fn update_adr_premium(adr: &str, underlying: &str, implied_and_premium: Option<(f64, f64)>) {
+    match implied_and_premium {
+        Some((implied, premium)) => {
+            ADR_IMPLIED_PRICE
+                .with_label_values(&[adr, underlying])
+                .set(implied);
+            ADR_PREMIUM
+                .with_label_values(&[adr, underlying])
+                .set(premium);
+        }
+        None => {
+            let _ = ADR_IMPLIED_PRICE.remove_label_values(&[adr, underlying]);
+            let _ = ADR_PREMIUM.remove_label_values(&[adr, underlying]);
+        }
+    }
+}
+
+#[instrument]
+pub fn update_effective_price(symbol: &str, venue: &str, buy: f64, sell: f64) {
+    CRYPTO_EFFECTIVE_PRICE
+        .with_label_values(&[symbol, venue, "buy"])
+        .set(buy);
+    CRYPTO_EFFECTIVE_PRICE
+        .with_label_values(&[symbol, venue, "sell"])
+        .set(sell);
+}
+
+#[instrument]
+pub fn update_stock_price_base(price: f64, symbol: &str, asset_class: &str, currency: &str) {
+    STOCK_PRICE_BASE
+        .with_label_values(&[symbol, asset_class, currency])
+        .set(price);
+}
+
+#[instrument]
+pub fn remove_stock_price_base(symbol: &str, asset_class: &str, currency: &str) {
+    let _ = STOCK_PRICE_BASE.remove_label_values(&[symbol, asset_class, currency]);
+}
+
+/// Moves the `side` series of `symbol` from the `previous` venue to the
+/// one now quoting best, or drops it.
+#[instrument]
+pub fn update_best_price(
+    symbol: &str,
+    side: &str,
+    previous: Option<&str>,
+    best: Option<(&str, f64)>,
+) {
+    if let Some(previous) = previous.filter(|p| best.is_none_or(|(venue, _)| venue != *p)) {
+        let _ = CRYPTO_BEST_PRICE.remove_label_values(&[symbol, side, previous]);
+    }
+    if let Some((venue, price)) = best {
+        CRYPTO_BEST_PRICE
+            .with_label_values(&[symbol, side, venue])
+            .set(price);
+    }
+}
+
+#[instrument]
+pub fn update_consolidated_mid(symbol: &str, mid: Option<f64>) {
+    match mid {
+        Some(mid) => CRYPTO_CONSOLIDATED_MID
+            .with_label_values(&[symbol])
+            .set(mid),
+        None => {
+            let _ = CRYPTO_CONSOLIDATED_MID.remove_label_values(&[symbol]);
+        }
+    }
+}
+
+#[instrument]
+pub fn update_watchdog_trip() {
+    WATCHDOG_TRIPS.inc();
+}
+
+#[instrument]
+pub fn update_build_info(build: &BuildInfo) {
+    BUILD_INFO
+        .with_label_values(&[build.version, build.git_sha, build.rustc, build.build_date])
+        .set(1);
+}
+
+#[instrument]
+pub fn update_feature_enabled(feature: &str, enabled: bool) {
+    FEATURE_ENABLED
+        .with_label_values(&[feature])
+        .set(enabled as i64);
+}
+
+#[instrument]
+pub fn update_history_gaps(symbol: &str, days: usize) {
+    HISTORY_GAPS.with_label_values(&[symbol]).set(days as i64);
+}
+
+#[instrument]
+pub fn update_gaps_repaired(days: usize) {
+    GAPS_REPAIRED.inc_by(days as u64);
+}
+
+#[instrument]
+pub fn update_daily_budget_remaining(credits: u64) {
+    DAILY_BUDGET.set(credits as i64);
+}
+
+#[instrument]
+pub fn update_daily_run(at: DateTime<Utc>) {
+    DAILY_RUN.set(at.timestamp());
+}
+
+#[instrument]
+pub fn update_futures_active(symbol: &str, contract: &str, active: bool) {
+    FUTURES_ACTIVE
+        .with_label_values(&[symbol, contract])
+        .set(active as i64);
+}
+
+#[instrument]
+pub fn update_futures_roll(symbol: &str) {
+    FUTURES_ROLLS.with_label_values(&[symbol]).inc();
+}
+
+#[instrument]
+pub fn update_options_exposure(symbol: &str, greek: &str, value: f64) {
+    OPTIONS_EXPOSURE
+        .with_label_values(&[symbol, greek])
+        .set(value);
+}
+
+/// `None` drops the contract's series, for contracts no longer listed.
+#[instrument]
+pub fn update_option_greeks(symbol: &str, contract: &str, greeks: Option<[(&str, f64); 4]>) {
+    match greeks {
+        Some(greeks) => {
+            for (greek, value) in greeks {
+                OPTION_GREEK
+                    .with_label_values(&[symbol, contract, greek])
+                    .set(value);
+            }
+        }
+        None => {
+            for greek in ["delta", "gamma", "theta", "vega"] {
+                let _ = OPTION_GREEK.remove_label_values(&[symbol, contract, greek]);
+            }
+        }
+    }
+}
+
+/// `None` drops the series, for reports without an estimate.
+#[instrument]
+pub fn update_earnings_surprise(symbol: &str, measure: &str, percent: Option<f64>) {
+    match percent {
+        Some(percent) => EARNINGS_SURPRISE
+            .with_label_values(&[symbol, measure])
+            .set(percent),
+        None => {
+            let _ = EARNINGS_SURPRISE.remove_label_values(&[symbol, measure]);
+        }
+    }
+}
+
+#[instrument(skip_all, fields(symbol = consensus.symbol))]
+pub fn update_analyst_consensus(consensus: &AnalystConsensus) {
+    let symbol = consensus.symbol.as_str();
+    for (stat, value) in [
+        ("average", consensus.target_average),
+        ("median", consensus.target_median),
+        ("high", consensus.target_high),
+        ("low", consensus.target_low),
+    ] {
+        match value {
+            Some(value) => ANALYST_TARGET.with_label_values(&[symbol, stat]).set(value),
+            None => {
+                let _ = ANALYST_TARGET.remove_label_values(&[symbol, stat]);
+            }
+        }
+    }
+    if let Some(rating) = consensus.rating {
+        ANALYST_RATING.with_label_values(&[symbol]).set(rating);
+    }
+    for (rating, count) in [
+        ("strong_buy", consensus.strong_buy),
+        ("buy", consensus.buy),
+        ("hold", consensus.hold),
+        ("sell", consensus.sell),
+        ("strong_sell", consensus.strong_sell),
+    ] {
+        ANALYST_RATINGS
+            .with_label_values(&[symbol, rating])
+            .set(count as i64);
+    }
+}
+
+#[instrument]
+pub fn update_analyst_upside(symbol: &str, percent: f64) {
+    ANALYST_UPSIDE.with_label_values(&[symbol]).set(percent);
+}
+
+#[instrument(skip_all, fields(symbol = stats.symbol))]
+pub fn update_symbol_stats(stats: &SymbolStats) {
+    let symbol = stats.symbol.as_str();
+    for (stat, value) in [
+        ("average_volume", stats.average_volume),
+        ("atr", stats.atr),
+        ("year_high", stats.year_high),
+        ("year_low", stats.year_low),
+    ] {
+        match value {
+            Some(value) => SYMBOL_STAT.with_label_values(&[symbol, stat]).set(value),
+            None => {
+                let _ = SYMBOL_STAT.remove_label_values(&[symbol, stat]);
+            }
+        }
+    }
+}
+
+const PROFILE_LEVELS: [&str; 3] = ["point_of_control", "value_area_high", "value_area_low"];
+
+pub fn update_volume_profile(profile: &VolumeProfile) {
+    let values = [
+        profile.point_of_control,
+        profile.value_area_high,
+        profile.value_area_low,
+    ];
+    for (level, value) in PROFILE_LEVELS.iter().zip(values) {
+        VOLUME_PROFILE
+            .with_label_values(&[&profile.symbol, level])
+            .set(value);
+    }
+}
+
+pub fn remove_volume_profile(symbol: &str) {
+    for level in PROFILE_LEVELS {
+        let _ = VOLUME_PROFILE.remove_label_values(&[symbol, level]);
+    }
+}
+
+pub fn update_pipeline_lag(subsystem: &str, lag_secs: f64, backlog: usize, degraded: bool) {
+    PIPELINE_LAG.with_label_values(&[subsystem]).set(lag_secs);
+    PIPELINE_BACKLOG
+        .with_label_values(&[subsystem])
+        .set(backlog as i64);
+    PIPELINE_DEGRADED
+        .with_label_values(&[subsystem])
+        .set(degraded as i64);
+}
+
+#[instrument]
+pub fn update_edgar_filing(symbol: &str, form: &str) {
+    EDGAR_FILINGS.with_label_values(&[symbol, form]).inc();
+}
+
+#[instrument(skip_all, fields(symbol = sample.symbol))]
+pub fn update_social_sample(sample: &Sample) {
+    let labels = [sample.symbol.as_str(), sample.source.name()];
+    SOCIAL_MENTIONS
+        .with_label_values(&labels)
+        .set(sample.mentions as f64);
+    for (gauge, value) in [
+        (&*SOCIAL_BUZZ, sample.zscore),
+        (&*SOCIAL_SENTIMENT, sample.sentiment),
+    ] {
+        match value {
+            Some(value) => gauge.with_label_values(&labels).set(value),
+            None => {
+                let _ = gauge.remove_label_values(&labels);
+            }
+        }
+    }
+}
+
+#[instrument]
+pub fn update_market_indicator(name: &str, value: Option<f64>) {
+    match value {
+        Some(value) => MARKET_INDICATOR.with_label_values(&[name]).set(value),
+        None => {
+            let _ = MARKET_INDICATOR.remove_label_values(&[name]);
+        }
+    }
 }
@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, NaiveTime, TimeZone, Utc, Weekday};
+use reqwest::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{error, info, instrument};
+
+use crate::calendar;
+use crate::clock::Clock;
+use crate::engine::Engine;
+use crate::notify::{Notification, NotificationKind, Notifiers, Urgency};
+use crate::provider::{base_url, number, TWELVEDATA_URL};
+use crate::symbol::SymbolInfo;
+use crate::StockMarket;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MoversConfig {
+    pub enabled: bool,
+    /// New York time the feed is fetched each trading day.
+    pub time: NaiveTime,
+    pub count: usize,
+    /// Watch the movers until the close.
+    pub auto_add: bool,
+}
+
+impl Default for MoversConfig {
+    fn default() -> Self {
+        MoversConfig {
+            enabled: false,
+            time: NaiveTime::from_hms_opt(8, 30, 0).expect("valid time"),
+            count: 5,
+            auto_add: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Mover {
+    pub symbol: String,
+    pub name: Option<String>,
+    pub price: Option<f64>,
+    pub currency: String,
+    pub percent_change: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MoversReport {
+    pub fetched_at: DateTime<Utc>,
+    pub gainers: Vec<Mover>,
+    pub losers: Vec<Mover>,
+}
+
+#[instrument(skip(api_key))]
+pub async fn fetch_market_movers(
+    base_url: &str,
+    direction: &str,
+    count: usize,
+    api_key: &str,
+) -> Result<Vec<Mover>, Error> {
+    let url = format!(
+        "{}/market_movers/stocks?direction={}&outputsize={}&apikey={}",
+        base_url, direction, count, api_key
+    );
+    let response = reqwest::get(&url).await?;
+    let data = response.text().await?;
+    let v: Value = serde_json::from_str(&data).unwrap_or(Value::Null);
+
+    let number = number::from_value;
+    let mut movers = vec![];
+    if let Some(array) = v["values"].as_array() {
+        for object in array {
+            let (Some(symbol), Some(percent_change)) =
+                (object["symbol"].as_str(), number(&object["percent_change"]))
+            else {
+                continue;
+            };
+            movers.push(Mover {
+                symbol: symbol.to_string(),
+                name: object["name"].as_str().map(String::from),
+                price: number(&object["last"]),
+                currency: object["currency"]
+                    .as_str()
+                    .map(String::from)
+                    .unwrap_or_else(|| SymbolInfo::parse(symbol).currency()),
+                percent_change,
+            });
+        }
+    }
+    Ok(movers)
+}
+
+pub struct MoversFeed {
+    config: MoversConfig,
+    latest: RwLock<Option<MoversReport>>,
+    base_url: String,
+}
+
+impl MoversFeed {
+    pub fn new(config: MoversConfig) -> Self {
+        MoversFeed {
+            config,
+            latest: RwLock::new(None),
+            base_url: TWELVEDATA_URL.into(),
+        }
+    }
+
+    pub fn with_base_url(mut self, url: &str) -> Self {
+        self.base_url = base_url(url);
+        self
+    }
+
+    pub fn latest(&self) -> Option<MoversReport> {
+        self.latest.read().unwrap().clone()
+    }
+
+    fn next_run(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let tz = chrono_tz::America::New_York;
+        let local = now.with_timezone(&tz);
+        for days in 0..8 {
+            let date = local.date_naive() + chrono::Duration::days(days);
+            if matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+                continue;
+            }
+            if let Some(at) = tz
+                .from_local_datetime(&date.and_time(self.config.time))
+                .earliest()
+            {
+                let at = at.with_timezone(&Utc);
+                if at > now {
+                    return at;
+                }
+            }
+        }
+        now + chrono::Duration::days(1)
+    }
+
+    pub async fn refresh(&self, api_key: &str, now: DateTime<Utc>) -> Result<MoversReport, Error> {
+        let report = MoversReport {
+            fetched_at: now,
+            gainers: fetch_market_movers(&self.base_url, "gainers", self.config.count, api_key)
+                .await?,
+            losers: fetch_market_movers(&self.base_url, "losers", self.config.count, api_key)
+                .await?,
+        };
+        *self.latest.write().unwrap() = Some(report.clone());
+        info!(
+            gainers = report.gainers.len(),
+            losers = report.losers.len(),
+            "Fetched pre-market movers"
+        );
+        Ok(report)
+    }
+
+    /// Fetches the movers every trading morning, publishes them and, when
+    /// configured, adds them to the engine's watchlist until the close.
+    pub async fn run(
+        self: Arc<Self>,
+        api_key: String,
+        clock: Arc<dyn Clock>,
+        engine: Arc<Engine>,
+        notifiers: Notifiers,
+    ) {
+        loop {
+            let now = clock.now();
+            let next = self.next_run(now);
+            clock
+                .sleep((next - now).to_std().unwrap_or(Duration::from_secs(60)))
+                .await;
+
+            let report = match self.refresh(&api_key, clock.now()).await {
+                Ok(report) => report,
+                Err(e) => {
+                    error!(error = ?e, "Failed to fetch pre-market movers");
+                    continue;
+                }
+            };
+
+            let line = |m: &Mover| format!("{} {:+.2}%", m.symbol, m.percent_change);
+            let body = format!(
+                "Gainers: {}\nLosers: {}",
+                report
+                    .gainers
+                    .iter()
+                    .map(line)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                report
+                    .losers
+                    .iter()
+                    .map(line)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            notifiers
+                .notify(&Notification {
+                    title: "Pre-market movers".into(),
+                    body,
+                    urgency: Urgency::Low,
+                    kind: NotificationKind::Summary,
+                    symbols: vec![],
+                    rule: None,
+                    value: None,
+                    labels: BTreeMap::new(),
+                })
+                .await;
+
+            if self.config.auto_add {
+                let now = clock.now();
+                let close = now
+                    + chrono::Duration::seconds(
+                        calendar::session(StockMarket::NYSE).seconds_until_close(now) as i64,
+                    );
+                for mover in report.gainers.iter().chain(&report.losers) {
+                    engine.watch_until(&mover.symbol, close);
+                }
+            }
+        }
+    }
+}
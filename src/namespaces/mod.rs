@@ -0,0 +1,167 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::auth;
+use crate::portfolio::PortfolioTracker;
+use crate::sink::{PriceUpdate, Sink};
+
+pub const NAMESPACES_DIR: &str = "namespaces";
+const TICKERS_FILE: &str = "tickers.json";
+
+#[derive(Debug, Error)]
+pub enum NamespaceError {
+    #[error("namespace: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("namespace watchlist is corrupt: {0}")]
+    Corrupt(#[from] serde_json::Error),
+    #[error("invalid namespace {0:?}")]
+    Invalid(String),
+}
+
+/// One user's watchlist and portfolio on a shared deployment.
+pub struct Space {
+    path: PathBuf,
+    tickers: Mutex<Vec<String>>,
+    pub portfolio: PortfolioTracker,
+}
+
+impl Space {
+    fn open(dir: &Path) -> Result<Self, NamespaceError> {
+        let path = dir.join(TICKERS_FILE);
+        let tickers = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Space {
+            path,
+            tickers: Mutex::new(tickers),
+            portfolio: PortfolioTracker::new(dir).without_metrics(),
+        })
+    }
+
+    pub fn tickers(&self) -> Vec<String> {
+        self.tickers.lock().unwrap().clone()
+    }
+
+    fn save(&self, tickers: &[String]) -> Result<(), NamespaceError> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(tickers)?)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    /// False when already watched.
+    pub fn add(&self, symbol: &str) -> Result<bool, NamespaceError> {
+        let mut tickers = self.tickers.lock().unwrap();
+        if tickers.iter().any(|t| t == symbol) {
+            return Ok(false);
+        }
+        let mut next = tickers.clone();
+        next.push(symbol.to_string());
+        self.save(&next)?;
+        *tickers = next;
+        Ok(true)
+    }
+
+    /// False when not watched.
+    pub fn remove(&self, symbol: &str) -> Result<bool, NamespaceError> {
+        let mut tickers = self.tickers.lock().unwrap();
+        if !tickers.iter().any(|t| t == symbol) {
+            return Ok(false);
+        }
+        let next: Vec<String> = tickers.iter().filter(|t| *t != symbol).cloned().collect();
+        self.save(&next)?;
+        *tickers = next;
+        Ok(true)
+    }
+
+    /// Watched or held, what the namespace's alerts are about.
+    pub fn symbols(&self) -> BTreeSet<String> {
+        let mut symbols: BTreeSet<String> = self.tickers().into_iter().collect();
+        match self.portfolio.load() {
+            Ok(holdings) => symbols.extend(holdings.into_keys()),
+            Err(e) => warn!(path = %self.path.display(), error = %e, "Failed to read holdings"),
+        }
+        symbols
+    }
+}
+
+/// Per-token namespaces under the state directory, so a small team can
+/// share one daemon. The engine polls every namespace's symbols on top of
+/// the shared watchlist.
+pub struct Namespaces {
+    dir: PathBuf,
+    spaces: RwLock<BTreeMap<String, Arc<Space>>>,
+}
+
+impl Namespaces {
+    /// Opens the namespaces already on disk, skipping broken ones.
+    pub fn load(state_dir: &Path) -> Self {
+        let dir = state_dir.join(NAMESPACES_DIR);
+        let mut spaces = BTreeMap::new();
+        let entries = std::fs::read_dir(&dir).into_iter().flatten().flatten();
+        for entry in entries.filter(|e| e.path().is_dir()) {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !auth::valid_namespace(&name) {
+                continue;
+            }
+            match Space::open(&entry.path()) {
+                Ok(space) => {
+                    spaces.insert(name, Arc::new(space));
+                }
+                Err(e) => warn!(namespace = name, error = %e, "Skipping namespace"),
+            }
+        }
+        if !spaces.is_empty() {
+            info!(namespaces = spaces.len(), "Loaded namespaces");
+        }
+        Namespaces {
+            dir,
+            spaces: RwLock::new(spaces),
+        }
+    }
+
+    /// The namespace called `name`, created on first use.
+    pub fn get(&self, name: &str) -> Result<Arc<Space>, NamespaceError> {
+        if let Some(space) = self.spaces.read().unwrap().get(name) {
+            return Ok(space.clone());
+        }
+        if !auth::valid_namespace(name) {
+            return Err(NamespaceError::Invalid(name.to_string()));
+        }
+        let mut spaces = self.spaces.write().unwrap();
+        if let Some(space) = spaces.get(name) {
+            return Ok(space.clone());
+        }
+        let space = Arc::new(Space::open(&self.dir.join(name))?);
+        spaces.insert(name.to_string(), space.clone());
+        Ok(space)
+    }
+
+    /// Every namespace's watched and held symbols, for the engine to poll.
+    pub fn symbols(&self) -> Vec<String> {
+        let spaces: Vec<Arc<Space>> = self.spaces.read().unwrap().values().cloned().collect();
+        let mut symbols = BTreeSet::new();
+        for space in spaces {
+            symbols.extend(space.symbols());
+        }
+        symbols.into_iter().collect()
+    }
+}
+
+impl Sink for Namespaces {
+    fn record(&self, update: &PriceUpdate) {
+        let spaces: Vec<Arc<Space>> = self.spaces.read().unwrap().values().cloned().collect();
+        for space in spaces {
+            space.portfolio.record(update);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::recovery;
+
+pub const NOTES_FILE: &str = "notes.json";
+
+/// Freeform context on a symbol, such as the thesis or why a position was opened.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct SymbolNote {
+    pub text: String,
+    pub tags: BTreeSet<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SymbolNote {
+    /// `AAPL [earnings, long]: text`, as appended to alerts.
+    pub fn line(&self, symbol: &str) -> String {
+        let mut line = symbol.to_string();
+        if !self.tags.is_empty() {
+            let tags: Vec<&str> = self.tags.iter().map(String::as_str).collect();
+            line.push_str(&format!(" [{}]", tags.join(", ")));
+        }
+        if !self.text.is_empty() {
+            line.push_str(&format!(": {}", self.text));
+        }
+        line
+    }
+}
+
+/// Fields left out keep their current value.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, ToSchema)]
+#[serde(default)]
+pub struct NoteUpdate {
+    pub text: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+#[derive(Debug, Error)]
+pub enum NoteError {
+    #[error("note store: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("note store is corrupt: {0}")]
+    Corrupt(#[from] serde_json::Error),
+    #[error("no note on {0:?}")]
+    NotFound(String),
+}
+
+#[derive(Default)]
+struct Cache {
+    modified: Option<SystemTime>,
+    notes: BTreeMap<String, SymbolNote>,
+}
+
+/// Symbol notes kept in the state directory. Like API tokens they can be
+/// edited from the CLI while the server runs, which rereads the file when
+/// it changes.
+pub struct NoteStore {
+    path: PathBuf,
+    cache: RwLock<Cache>,
+}
+
+impl NoteStore {
+    pub fn new(state_dir: &Path) -> Self {
+        NoteStore {
+            path: state_dir.join(NOTES_FILE),
+            cache: RwLock::new(Cache::default()),
+        }
+    }
+
+    pub fn load(&self) -> Result<BTreeMap<String, SymbolNote>, NoteError> {
+        match std::fs::read(&self.path) {
+            Ok(data) => Ok(serde_json::from_slice(&data)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, notes: &BTreeMap<String, SymbolNote>) -> Result<(), NoteError> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(notes)?)?;
+        recovery::backup(&self.path)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    /// Creates the note if the symbol has none. Tags are trimmed, and
+    /// blank ones dropped.
+    pub fn set(&self, symbol: &str, update: NoteUpdate) -> Result<SymbolNote, NoteError> {
+        let mut notes = self.load()?;
+        let note = notes
+            .entry(symbol.to_string())
+            .or_insert_with(|| SymbolNote {
+                text: String::new(),
+                tags: BTreeSet::new(),
+                updated_at: Utc::now(),
+            });
+        if let Some(text) = update.text {
+            note.text = text.trim().to_string();
+        }
+        if let Some(tags) = update.tags {
+            note.tags = tags
+                .iter()
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .collect();
+        }
+        note.updated_at = Utc::now();
+        let note = note.clone();
+        self.save(&notes)?;
+        info!(symbol, tags = note.tags.len(), "Updated symbol note");
+        Ok(note)
+    }
+
+    pub fn remove(&self, symbol: &str) -> Result<(), NoteError> {
+        let mut notes = self.load()?;
+        if notes.remove(symbol).is_none() {
+            return Err(NoteError::NotFound(symbol.to_string()));
+        }
+        self.save(&notes)?;
+        info!(symbol, "Removed symbol note");
+        Ok(())
+    }
+
+    fn refresh(&self) {
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .ok();
+        if self.cache.read().unwrap().modified == modified {
+            return;
+        }
+        match self.load() {
+            Ok(notes) => *self.cache.write().unwrap() = Cache { modified, notes },
+            Err(e) => warn!(error = %e, "Failed to reload symbol notes"),
+        }
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<SymbolNote> {
+        self.refresh();
+        self.cache.read().unwrap().notes.get(symbol).cloned()
+    }
+
+    pub fn all(&self) -> BTreeMap<String, SymbolNote> {
+        self.refresh();
+        self.cache.read().unwrap().notes.clone()
+    }
+}
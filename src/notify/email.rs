@@ -0,0 +1,290 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, instrument, warn};
+
+use super::{Notification, NotificationKind, Notifier, NotifyError};
+
+const DIGEST_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<body style="font-family: sans-serif">
+<h2>fintek digest for {{date}}</h2>
+{{summary}}
+<h3>Alerts ({{count}})</h3>
+{{alerts}}
+</body>
+</html>
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailMode {
+    /// One email per notification.
+    #[default]
+    Immediate,
+    /// Everything collected into one email a day.
+    Digest,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EmailConfig {
+    pub smtp_host: String,
+    /// 465 connects over TLS, 25 in plain text, anything else upgrades with STARTTLS.
+    pub smtp_port: u16,
+    /// Login user; the password comes from `SMTP_PASSWORD`.
+    pub username: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    pub mode: EmailMode,
+    /// When the digest goes out, in `timezone`.
+    pub digest_at: NaiveTime,
+    pub timezone: String,
+    /// HTML file with `{{date}}`, `{{summary}}`, `{{alerts}}` and `{{count}}`
+    /// placeholders, replacing the built-in digest layout.
+    pub template: Option<PathBuf>,
+}
+
+impl Default for EmailConfig {
+    fn default() -> Self {
+        EmailConfig {
+            smtp_host: "localhost".into(),
+            smtp_port: 587,
+            username: None,
+            from: String::new(),
+            to: vec![],
+            mode: EmailMode::default(),
+            digest_at: NaiveTime::from_hms_opt(17, 30, 0).expect("valid digest time"),
+            timezone: "America/New_York".into(),
+            template: None,
+        }
+    }
+}
+
+pub struct EmailNotifier {
+    config: EmailConfig,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    tz: Tz,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    pending: Mutex<Vec<(DateTime<Utc>, Notification)>>,
+}
+
+fn email_error(e: impl std::fmt::Display) -> NotifyError {
+    NotifyError::Email(e.to_string())
+}
+
+impl EmailNotifier {
+    pub fn new(config: EmailConfig) -> Result<Self, NotifyError> {
+        let from = config.from.parse().map_err(email_error)?;
+        let to = config
+            .to
+            .iter()
+            .map(|to| to.parse())
+            .collect::<Result<Vec<Mailbox>, _>>()
+            .map_err(email_error)?;
+        if to.is_empty() {
+            return Err(NotifyError::Email("no recipients configured".into()));
+        }
+        let tz = config
+            .timezone
+            .parse::<Tz>()
+            .map_err(|e| NotifyError::Email(format!("bad timezone: {}", e)))?;
+
+        let host = config.smtp_host.as_str();
+        let mut transport = match config.smtp_port {
+            465 => AsyncSmtpTransport::<Tokio1Executor>::relay(host).map_err(email_error)?,
+            25 => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+            _ => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host).map_err(email_error)?,
+        }
+        .port(config.smtp_port);
+        if let Some(username) = &config.username {
+            let password = std::env::var("SMTP_PASSWORD").unwrap_or_default();
+            transport = transport.credentials(Credentials::new(username.clone(), password));
+        }
+
+        Ok(EmailNotifier {
+            from,
+            to,
+            tz,
+            transport: transport.build(),
+            pending: Mutex::new(vec![]),
+            config,
+        })
+    }
+
+    /// Wraps the notifier for use, starting the daily digest task in digest mode.
+    pub fn start(self) -> Arc<dyn Notifier> {
+        let notifier = Arc::new(self);
+        if notifier.config.mode == EmailMode::Digest {
+            tokio::spawn(notifier.clone().run_digest());
+        }
+        notifier
+    }
+
+    async fn send(&self, subject: &str, text: String, html: String) -> Result<(), NotifyError> {
+        let mut builder = Message::builder().from(self.from.clone()).subject(subject);
+        for to in &self.to {
+            builder = builder.to(to.clone());
+        }
+        let message = builder
+            .multipart(MultiPart::alternative_plain_html(text, html))
+            .map_err(email_error)?;
+        self.transport.send(message).await.map_err(email_error)?;
+        Ok(())
+    }
+
+    fn next_digest(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let local = now.with_timezone(&self.tz);
+        for days in 0..3 {
+            let date = local.date_naive() + Duration::days(days);
+            if let Some(at) = self
+                .tz
+                .from_local_datetime(&date.and_time(self.config.digest_at))
+                .earliest()
+            {
+                let at = at.with_timezone(&Utc);
+                if at > now {
+                    return at;
+                }
+            }
+        }
+        now + Duration::days(1)
+    }
+
+    async fn template(&self) -> String {
+        let Some(path) = &self.config.template else {
+            return DIGEST_TEMPLATE.into();
+        };
+        match tokio::fs::read_to_string(path).await {
+            Ok(template) => template,
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Failed to read digest template, using the default");
+                DIGEST_TEMPLATE.into()
+            }
+        }
+    }
+
+    /// Sends everything collected since the last digest as one email.
+    #[instrument(skip(self))]
+    pub async fn flush(&self, now: DateTime<Utc>) -> Result<(), NotifyError> {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        if pending.is_empty() {
+            return Ok(());
+        }
+        let (summaries, alerts): (Vec<_>, Vec<_>) = pending
+            .iter()
+            .partition(|(_, n)| n.kind == NotificationKind::Summary);
+
+        let date = now.with_timezone(&self.tz).format("%Y-%m-%d").to_string();
+        let summary_html: String = summaries
+            .iter()
+            .map(|(_, n)| {
+                format!(
+                    "<h3>{}</h3>\n<pre>{}</pre>\n",
+                    escape(&n.title),
+                    escape(&n.body)
+                )
+            })
+            .collect();
+        let alerts_html = if alerts.is_empty() {
+            "<p>No alerts today.</p>\n".to_string()
+        } else {
+            let items: String = alerts
+                .iter()
+                .map(|(at, n)| {
+                    format!(
+                        "<li><b>{}</b> {}<br>{}</li>\n",
+                        at.with_timezone(&self.tz).format("%H:%M"),
+                        escape(&n.title),
+                        escape(&n.body).replace('\n', "<br>")
+                    )
+                })
+                .collect();
+            format!("<ul>\n{}</ul>\n", items)
+        };
+        let html = self
+            .template()
+            .await
+            .replace("{{date}}", &date)
+            .replace("{{summary}}", &summary_html)
+            .replace("{{alerts}}", &alerts_html)
+            .replace("{{count}}", &alerts.len().to_string());
+
+        let mut text = format!("fintek digest for {}\n", date);
+        for (_, n) in &summaries {
+            text.push_str(&format!("\n{}\n{}\n", n.title, n.body));
+        }
+        text.push_str(&format!("\nAlerts ({})\n", alerts.len()));
+        for (at, n) in &alerts {
+            text.push_str(&format!(
+                "{} {}: {}\n",
+                at.with_timezone(&self.tz).format("%H:%M"),
+                n.title,
+                n.body
+            ));
+        }
+
+        let subject = format!("fintek digest for {}: {} alerts", date, alerts.len());
+        if let Err(e) = self.send(&subject, text, html).await {
+            // Keep them for the next attempt rather than losing a day's alerts.
+            self.pending.lock().unwrap().splice(0..0, pending);
+            return Err(e);
+        }
+        info!(
+            alerts = alerts.len(),
+            summaries = summaries.len(),
+            "Sent email digest"
+        );
+        Ok(())
+    }
+
+    async fn run_digest(self: Arc<Self>) {
+        loop {
+            let now = Utc::now();
+            let next = self.next_digest(now);
+            tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+            if let Err(e) = self.flush(Utc::now()).await {
+                error!(error = %e, "Failed to send email digest");
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        if self.config.mode == EmailMode::Digest {
+            self.pending
+                .lock()
+                .unwrap()
+                .push((Utc::now(), notification.clone()));
+            return Ok(());
+        }
+        let html = format!(
+            "<h3>{}</h3>\n<p>{}</p>\n",
+            escape(&notification.title),
+            escape(&notification.body).replace('\n', "<br>")
+        );
+        self.send(&notification.title, notification.body.clone(), html)
+            .await
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,163 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::NaiveDate;
+
+use crate::calendar;
+use crate::clock::Clock;
+use crate::corporate::CorporateCalendar;
+use crate::paper::{ContributionReport, PaperAccount, Portfolio};
+use crate::sink::{LatestPrices, PriceUpdate};
+use crate::storage::EarningsSurprise;
+use crate::symbol::SymbolInfo;
+use crate::tracking::{BenchmarkTracker, TrackingReport};
+use crate::StockMarket;
+
+use super::{Notification, NotificationKind, Notifiers, Urgency};
+
+// Enough to see what moved the day without listing every position again.
+const TOP_MOVERS: usize = 3;
+
+// Reports older than this when first seen, as on startup, aren't news.
+const SURPRISE_DAYS: i64 = 3;
+
+fn percent(value: Option<f64>) -> String {
+    value.map_or_else(|| "n/a".into(), |p| format!("{:+.1}%", p))
+}
+
+pub fn summary(
+    prices: &[PriceUpdate],
+    portfolio: &Portfolio,
+    contributions: &ContributionReport,
+    tracking: Option<&TrackingReport>,
+    surprises: &[EarningsSurprise],
+) -> Notification {
+    let mut body = String::new();
+    for update in prices {
+        let currency = SymbolInfo::parse(&update.symbol).currency();
+        body.push_str(&format!(
+            "{} {:.2} {}\n",
+            update.symbol, update.price, currency
+        ));
+    }
+    body.push_str(&format!(
+        "Paper equity {:.2} (cash {:.2})\n",
+        portfolio.equity, portfolio.cash
+    ));
+    for position in &portfolio.positions {
+        body.push_str(&format!(
+            "  {} x{} unrealized {:+.2}\n",
+            position.symbol,
+            position.quantity,
+            position.unrealized_pnl.unwrap_or_default()
+        ));
+    }
+    if !contributions.positions.is_empty() {
+        body.push_str(&format!(
+            "Day P&L {:+.2} ({:+.2}%)\n",
+            contributions.daily_pnl,
+            contributions.daily_return * 100.
+        ));
+        for (label, movers) in [
+            (
+                "Top contributors",
+                contributions.contributors(TOP_MOVERS).collect::<Vec<_>>(),
+            ),
+            (
+                "Top detractors",
+                contributions.detractors(TOP_MOVERS).collect(),
+            ),
+        ] {
+            if movers.is_empty() {
+                continue;
+            }
+            body.push_str(&format!("{}:\n", label));
+            for c in movers {
+                body.push_str(&format!(
+                    "  {} {:+.2} ({:+.2}%)\n",
+                    c.symbol,
+                    c.daily_pnl,
+                    c.contribution * 100.
+                ));
+            }
+        }
+    }
+    if let Some(tracking) = tracking {
+        body.push_str(&format!(
+            "Against {} over {} days: tracking error {:.2}%, information ratio {}\n",
+            tracking.benchmark,
+            tracking.days,
+            tracking.tracking_error * 100.,
+            tracking
+                .information_ratio
+                .map_or_else(|| "n/a".into(), |ir| format!("{:.2}", ir))
+        ));
+    }
+    if !surprises.is_empty() {
+        body.push_str("Earnings surprises:\n");
+        for s in surprises {
+            body.push_str(&format!(
+                "  {} EPS {:.2} vs {} est ({})",
+                s.symbol,
+                s.eps_actual,
+                s.eps_estimate
+                    .map_or_else(|| "n/a".into(), |e| format!("{:.2}", e)),
+                percent(s.eps_percent())
+            ));
+            if s.revenue_actual.is_some() {
+                body.push_str(&format!(", revenue {}", percent(s.revenue_percent())));
+            }
+            body.push('\n');
+        }
+    }
+    Notification {
+        title: "End of day summary".into(),
+        body,
+        urgency: Urgency::Low,
+        kind: NotificationKind::Summary,
+        symbols: vec![],
+        rule: None,
+        value: None,
+        labels: BTreeMap::new(),
+    }
+}
+
+/// Sends [`summary`] through the notifiers at every close of `market`,
+/// with the earnings surprises of watched symbols reported since the last.
+pub async fn run(
+    market: StockMarket,
+    clock: Arc<dyn Clock>,
+    prices: Arc<LatestPrices>,
+    paper: Arc<PaperAccount>,
+    tracking: Option<Arc<BenchmarkTracker>>,
+    corporate: Option<Arc<CorporateCalendar>>,
+    notifiers: Notifiers,
+) {
+    let session = calendar::session(market);
+    let mut included: HashSet<(String, NaiveDate)> = HashSet::new();
+    loop {
+        let open_in = session.seconds_until_open(clock.now());
+        clock.sleep(Duration::from_secs(open_in + 1)).await;
+        let close_in = session.seconds_until_close(clock.now());
+        clock.sleep(Duration::from_secs(close_in)).await;
+        let snapshot = prices.snapshot();
+        let since = clock.now().date_naive() - chrono::Duration::days(SURPRISE_DAYS);
+        included.retain(|(_, date)| *date >= since);
+        let surprises: Vec<EarningsSurprise> = corporate
+            .iter()
+            .flat_map(|corporate| corporate.surprises(since))
+            .filter(|s| snapshot.iter().any(|u| u.symbol == s.symbol))
+            .filter(|s| included.insert((s.symbol.clone(), s.date)))
+            .collect();
+        notifiers
+            .notify(&summary(
+                &snapshot,
+                &paper.portfolio(),
+                &paper.contributions(),
+                tracking.as_ref().and_then(|t| t.report()).as_ref(),
+                &surprises,
+            ))
+            .await;
+    }
+}
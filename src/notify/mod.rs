@@ -0,0 +1,384 @@
+pub mod email;
+pub mod eod;
+pub mod push;
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use thiserror::Error;
+use tracing::{error, info, instrument, warn};
+
+use crate::clock::Clock;
+use crate::cluster::dedup::NotificationDedup;
+use crate::metrics;
+use crate::notes::NoteStore;
+use crate::storage::{AlertOutcome, AlertRecord, AlertStore};
+use email::{EmailConfig, EmailNotifier};
+use push::{NtfyNotifier, PushoverNotifier};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Urgency {
+    Low,
+    Normal,
+    High,
+}
+
+/// Alerts fire on events; summaries are scheduled reports such as the
+/// end-of-day recap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationKind {
+    #[default]
+    Alert,
+    Summary,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+    pub urgency: Urgency,
+    pub kind: NotificationKind,
+    /// Symbols an alert is about; their notes are appended for context.
+    pub symbols: Vec<String>,
+    /// What fired an alert, for its history. The title when unset.
+    pub rule: Option<String>,
+    /// The price that set it off.
+    pub value: Option<f64>,
+    /// Labels of the tickers file entries of `symbols`, filled in when
+    /// sent.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Error)]
+pub enum NotifyError {
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error("failed to send email: {0}")]
+    Email(String),
+}
+
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError>;
+}
+
+#[derive(Debug, Default)]
+pub struct LogNotifier;
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    fn name(&self) -> &str {
+        "log"
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        info!(
+            title = %notification.title,
+            body = %notification.body,
+            urgency = ?notification.urgency,
+            "Notification"
+        );
+        Ok(())
+    }
+}
+
+pub const SIGNATURE_HEADER: &str = "x-fintek-signature";
+pub const TIMESTAMP_HEADER: &str = "x-fintek-timestamp";
+
+/// What a webhook sends so its receiver can tell a notification came from
+/// fintek. Any combination works; none sends the payload bare.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct WebhookAuth {
+    /// Signs every payload: `X-Fintek-Timestamp` is the Unix seconds it was
+    /// sent at and `X-Fintek-Signature` is `sha256=` and the hex HMAC-SHA256
+    /// of `<timestamp>.<body>` under this secret. See [`sign`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// Sent as `Authorization: Bearer <token>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bearer: Option<String>,
+    /// Sent with every request, e.g. a receiver's own API key header.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// The `X-Fintek-Signature` of `body` sent at `timestamp`, for receivers
+/// to compare against.
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("sha256={}", hex)
+}
+
+/// POSTs the notification as JSON. The `text` field makes the payload
+/// acceptable to Slack-compatible incoming webhooks as is.
+pub struct WebhookNotifier {
+    url: String,
+    auth: WebhookAuth,
+    client: reqwest::Client,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: &str) -> Self {
+        WebhookNotifier {
+            url: url.to_string(),
+            auth: WebhookAuth::default(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn with_auth(mut self, auth: WebhookAuth) -> Self {
+        self.auth = auth;
+        self
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    #[instrument(skip_all, fields(url = %self.url))]
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let mut payload = serde_json::json!({
+            "text": format!("*{}*\n{}", notification.title, notification.body),
+            "title": notification.title,
+            "body": notification.body,
+            "urgency": notification.urgency,
+        });
+        if !notification.labels.is_empty() {
+            payload["labels"] = serde_json::json!(notification.labels);
+        }
+        let body = payload.to_string();
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header("content-type", "application/json");
+        for (name, value) in &self.auth.headers {
+            request = request.header(name, value);
+        }
+        if let Some(token) = &self.auth.bearer {
+            request = request.bearer_auth(token);
+        }
+        if let Some(secret) = &self.auth.secret {
+            let timestamp = Utc::now().timestamp();
+            request = request
+                .header(TIMESTAMP_HEADER, timestamp)
+                .header(SIGNATURE_HEADER, sign(secret, timestamp, &body));
+        }
+        request.body(body).send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum NotifierConfig {
+    Log,
+    Webhook {
+        url: String,
+        #[serde(flatten)]
+        auth: WebhookAuth,
+    },
+    Email(EmailConfig),
+    Ntfy {
+        #[serde(default = "default_ntfy_server")]
+        server: String,
+        topic: String,
+        /// Access token for protected topics.
+        token: Option<String>,
+    },
+    Pushover {
+        /// Application token.
+        token: String,
+        /// User or group key.
+        user: String,
+    },
+}
+
+fn default_ntfy_server() -> String {
+    push::NTFY_URL.into()
+}
+
+impl NotifierConfig {
+    pub fn build(&self) -> Arc<dyn Notifier> {
+        match self {
+            NotifierConfig::Log => Arc::new(LogNotifier),
+            NotifierConfig::Webhook { url, auth } => {
+                Arc::new(WebhookNotifier::new(url).with_auth(auth.clone()))
+            }
+            NotifierConfig::Ntfy {
+                server,
+                topic,
+                token,
+            } => Arc::new(NtfyNotifier::new(server, topic, token.clone())),
+            NotifierConfig::Pushover { token, user } => {
+                Arc::new(PushoverNotifier::new(token, user))
+            }
+            NotifierConfig::Email(config) => match EmailNotifier::new(config.clone()) {
+                Ok(notifier) => notifier.start(),
+                Err(e) => {
+                    error!(error = %e, "Failed to set up email notifier, logging instead");
+                    Arc::new(LogNotifier)
+                }
+            },
+        }
+    }
+}
+
+/// Fans a notification out to every configured notifier, logging failures
+/// instead of failing the caller.
+#[derive(Clone, Default)]
+pub struct Notifiers {
+    notifiers: Vec<Arc<dyn Notifier>>,
+    notes: Option<Arc<NoteStore>>,
+    dedup: Option<Arc<NotificationDedup>>,
+    // Shared by every clone, so strategies that took theirs before the
+    // store was opened still record to it.
+    history: Arc<OnceLock<AlertHistory>>,
+}
+
+struct AlertHistory {
+    store: Arc<dyn AlertStore>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Notifiers {
+    pub fn from_config(configs: &[NotifierConfig]) -> Self {
+        Notifiers {
+            notifiers: configs.iter().map(NotifierConfig::build).collect(),
+            notes: None,
+            dedup: None,
+            history: Arc::default(),
+        }
+    }
+
+    /// Records every alert, and what became of it, in `store` from now on,
+    /// on this and every clone. Only the first store set takes.
+    pub fn record_to(&self, store: Arc<dyn AlertStore>, clock: Arc<dyn Clock>) {
+        if self.history.set(AlertHistory { store, clock }).is_err() {
+            warn!("Alert history already recording, ignoring another store");
+        }
+    }
+
+    async fn record(
+        &self,
+        notification: &Notification,
+        outcome: AlertOutcome,
+        failed: Vec<String>,
+    ) {
+        if notification.kind != NotificationKind::Alert {
+            return;
+        }
+        let rule = notification.rule.as_ref().unwrap_or(&notification.title);
+        metrics::update_alert_fired(rule, outcome.as_str());
+        let Some(AlertHistory { store, clock }) = self.history.get() else {
+            return;
+        };
+        let symbols: Vec<Option<String>> = match notification.symbols.as_slice() {
+            [] => vec![None],
+            symbols => symbols.iter().cloned().map(Some).collect(),
+        };
+        for symbol in symbols {
+            let alert = AlertRecord {
+                rule: rule.clone(),
+                symbol,
+                value: notification.value,
+                title: notification.title.clone(),
+                fired_at: clock.now(),
+                outcome,
+                failed: failed.clone(),
+            };
+            if let Err(e) = store.record_alert(&alert).await {
+                error!(rule, error = %e, "Failed to record alert");
+            }
+        }
+    }
+
+    pub fn with_notes(mut self, notes: Arc<NoteStore>) -> Self {
+        self.notes = Some(notes);
+        self
+    }
+
+    /// Sends only what no other replica has sent in the same window.
+    pub fn with_dedup(mut self, dedup: Arc<NotificationDedup>) -> Self {
+        self.dedup = Some(dedup);
+        self
+    }
+
+    // Summaries go out as they are.
+    fn annotate(&self, notification: &Notification) -> Option<Notification> {
+        if notification.kind != NotificationKind::Alert {
+            return None;
+        }
+        let lines: Vec<String> = match &self.notes {
+            Some(notes) => notification
+                .symbols
+                .iter()
+                .filter_map(|symbol| notes.get(symbol).map(|note| note.line(symbol)))
+                .collect(),
+            None => vec![],
+        };
+        let labels = crate::labels_of(&notification.symbols);
+        if lines.is_empty() && labels.is_empty() {
+            return None;
+        }
+        let mut annotated = notification.clone();
+        if !lines.is_empty() {
+            annotated.body = format!("{}\n\nNotes:\n{}", notification.body, lines.join("\n"));
+        }
+        for (key, value) in labels {
+            annotated.labels.entry(key).or_insert(value);
+        }
+        Some(annotated)
+    }
+
+    pub fn push(&mut self, notifier: Arc<dyn Notifier>) {
+        self.notifiers.push(notifier);
+    }
+
+    pub async fn notify(&self, notification: &Notification) {
+        if let Some(dedup) = &self.dedup {
+            if !dedup.claim(notification).await {
+                self.record(notification, AlertOutcome::Deduplicated, vec![])
+                    .await;
+                return;
+            }
+        }
+        let annotated = self.annotate(notification);
+        let annotated = annotated.as_ref().unwrap_or(notification);
+        let mut failed = vec![];
+        for notifier in &self.notifiers {
+            if let Err(e) = notifier.notify(annotated).await {
+                error!(notifier = notifier.name(), error = ?e, "Failed to send notification");
+                failed.push(notifier.name().to_string());
+            }
+        }
+        let outcome = match failed.len() {
+            0 => AlertOutcome::Sent,
+            n if n == self.notifiers.len() => AlertOutcome::Failed,
+            _ => AlertOutcome::Partial,
+        };
+        self.record(notification, outcome, failed).await;
+    }
+}
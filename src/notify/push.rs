@@ -0,0 +1,98 @@
+use async_trait::async_trait;
+use tracing::instrument;
+
+use super::{Notification, Notifier, NotifyError, Urgency};
+
+pub const NTFY_URL: &str = "https://ntfy.sh";
+const PUSHOVER_URL: &str = "https://api.pushover.net/1/messages.json";
+
+/// Publishes to an ntfy topic, on ntfy.sh or a self-hosted server.
+pub struct NtfyNotifier {
+    url: String,
+    token: Option<String>,
+    client: reqwest::Client,
+}
+
+impl NtfyNotifier {
+    pub fn new(server: &str, topic: &str, token: Option<String>) -> Self {
+        NtfyNotifier {
+            url: format!("{}/{}", server.trim_end_matches('/'), topic),
+            token,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for NtfyNotifier {
+    fn name(&self) -> &str {
+        "ntfy"
+    }
+
+    #[instrument(skip_all, fields(url = %self.url))]
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        // ntfy priorities run 1 (min) to 5 (max), 3 being the default.
+        let priority = match notification.urgency {
+            Urgency::Low => "2",
+            Urgency::Normal => "3",
+            Urgency::High => "5",
+        };
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header("Title", &notification.title)
+            .header("Priority", priority)
+            .body(notification.body.clone());
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+pub struct PushoverNotifier {
+    token: String,
+    user: String,
+    client: reqwest::Client,
+}
+
+impl PushoverNotifier {
+    pub fn new(token: &str, user: &str) -> Self {
+        PushoverNotifier {
+            token: token.to_string(),
+            user: user.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for PushoverNotifier {
+    fn name(&self) -> &str {
+        "pushover"
+    }
+
+    #[instrument(skip_all)]
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        // Pushover's 2 (emergency) needs acknowledgement settings, so High stops at 1.
+        let priority = match notification.urgency {
+            Urgency::Low => "-1",
+            Urgency::Normal => "0",
+            Urgency::High => "1",
+        };
+        self.client
+            .post(PUSHOVER_URL)
+            .form(&[
+                ("token", self.token.as_str()),
+                ("user", self.user.as_str()),
+                ("title", notification.title.as_str()),
+                ("message", notification.body.as_str()),
+                ("priority", priority),
+            ])
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+use std::sync::Arc;
+
+use reqwest::Error;
+use serde::{Deserialize, Serialize};
+use tracing::{instrument, warn};
+
+use crate::provider::endpoints::{self, Endpoint, SymbolSearch};
+use crate::provider::{base_url, finnhub};
+use crate::ratelimit::RateLimiter;
+use crate::symbol::{self, SymbolInfo};
+use crate::{AssetClass, StockMarket, TickerMeta};
+
+/// Most matches offered per name.
+pub const MAX_CANDIDATES: usize = 10;
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OnboardConfig {
+    /// Exchanges to take, in order, when a bare ticker being added is
+    /// listed on several. Just `exchange` when empty.
+    pub prefer: Vec<StockMarket>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub ticker: String,
+    /// `None` where the provider doesn't say which US venue.
+    pub exchange: Option<StockMarket>,
+    pub meta: TickerMeta,
+}
+
+/// Whether `symbol` names a stock without saying where it's listed.
+pub fn is_bare(symbol: &str) -> bool {
+    let info = SymbolInfo::parse(symbol);
+    info.exchange.is_none() && info.asset_class == AssetClass::Stock
+}
+
+/// The candidate on the first exchange of `prefer` that has one.
+pub fn preferred<'a>(candidates: &'a [Candidate], prefer: &[StockMarket]) -> Option<&'a Candidate> {
+    prefer
+        .iter()
+        .find_map(|exchange| candidates.iter().find(|c| c.exchange == Some(*exchange)))
+}
+
+/// Looks names, tickers and ISINs up with every provider it has a key for,
+/// keeping matches on exchanges fintek follows.
+pub struct Resolver {
+    limiter: Arc<RateLimiter>,
+    twelvedata: Option<(String, String)>,
+    finnhub: Option<(String, String)>,
+}
+
+impl Resolver {
+    pub fn new(limiter: Arc<RateLimiter>) -> Self {
+        Resolver {
+            limiter,
+            twelvedata: None,
+            finnhub: None,
+        }
+    }
+
+    pub fn with_twelvedata(mut self, url: &str, api_key: &str) -> Self {
+        self.twelvedata = Some((base_url(url), api_key.to_string()));
+        self
+    }
+
+    pub fn with_finnhub(mut self, url: &str, token: &str) -> Self {
+        self.finnhub = Some((base_url(url), token.to_string()));
+        self
+    }
+
+    async fn twelvedata(&self, url: &str, key: &str, query: &str) -> Result<Vec<Candidate>, Error> {
+        self.limiter.acquire(1).await;
+        let data = reqwest::Client::new()
+            .get(format!("{}{}", url, SymbolSearch::PATH))
+            .query(&[("symbol", query), ("apikey", key)])
+            .send()
+            .await?
+            .text()
+            .await?;
+        let response = match endpoints::parse::<<SymbolSearch as Endpoint>::Response>(&data) {
+            Ok(Ok(response)) => response,
+            Ok(Err(error)) => {
+                warn!(query, code = error.code, message = %error.message, "Symbol search failed");
+                return Ok(vec![]);
+            }
+            Err(e) => {
+                warn!(query, error = %e, "Unexpected symbol search response");
+                return Ok(vec![]);
+            }
+        };
+        Ok(response
+            .data
+            .into_iter()
+            .filter_map(|m| {
+                Some(Candidate {
+                    ticker: symbol::listing(&m.symbol, &m.mic_code)?,
+                    exchange: symbol::mic_exchange(&m.mic_code),
+                    meta: TickerMeta {
+                        name: m.instrument_name,
+                        exchange: m.exchange,
+                        currency: m.currency,
+                        kind: m.instrument_type,
+                        query: query.to_string(),
+                        source: "twelvedata".into(),
+                    },
+                })
+            })
+            .collect())
+    }
+
+    async fn finnhub(&self, url: &str, token: &str, query: &str) -> Result<Vec<Candidate>, Error> {
+        Ok(finnhub::search(url, token, query)
+            .await?
+            .into_iter()
+            .filter(|r| symbol::is_supported(&r.symbol))
+            .map(|r| {
+                let info = SymbolInfo::parse(&r.symbol);
+                Candidate {
+                    exchange: info.exchange,
+                    meta: TickerMeta {
+                        name: r.description,
+                        exchange: info
+                            .exchange
+                            .map_or_else(|| "US".into(), |e| format!("{:?}", e)),
+                        currency: info.currency(),
+                        kind: r.kind,
+                        query: query.to_string(),
+                        source: "finnhub".into(),
+                    },
+                    ticker: r.symbol,
+                }
+            })
+            .collect())
+    }
+
+    /// Candidates for `query`, an exact ticker match first, then in the
+    /// providers' order. A provider that fails is skipped.
+    #[instrument(skip(self))]
+    pub async fn resolve(&self, query: &str) -> Vec<Candidate> {
+        let mut candidates = vec![];
+        if let Some((url, key)) = &self.twelvedata {
+            match self.twelvedata(url, key, query).await {
+                Ok(found) => candidates.extend(found),
+                Err(e) => warn!(query, error = %e, "Twelve Data symbol search failed"),
+            }
+        }
+        if let Some((url, token)) = &self.finnhub {
+            match self.finnhub(url, token, query).await {
+                Ok(found) => candidates.extend(found),
+                Err(e) => warn!(query, error = %e, "Finnhub search failed"),
+            }
+        }
+        let mut unique: Vec<Candidate> = vec![];
+        for candidate in candidates {
+            if !unique.iter().any(|c| c.ticker == candidate.ticker) {
+                unique.push(candidate);
+            }
+        }
+        unique.sort_by_key(|c| !c.ticker.eq_ignore_ascii_case(query));
+        unique.truncate(MAX_CANDIDATES);
+        unique
+    }
+
+    /// Listings of the bare ticker `ticker` on the exchanges fintek follows,
+    /// such as `SHOP` and `SHOP.DE`.
+    pub async fn listings(&self, ticker: &str) -> Vec<Candidate> {
+        let mut listings = self.resolve(ticker).await;
+        listings.retain(|c| {
+            SymbolInfo::parse(&c.ticker)
+                .base
+                .eq_ignore_ascii_case(ticker)
+        });
+        listings
+    }
+}
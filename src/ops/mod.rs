@@ -0,0 +1,262 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::engine::CycleContext;
+use crate::sink::{FetchOutcome, PriceUpdate, Sink};
+
+const PAGERDUTY_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+const OPSGENIE_URL: &str = "https://api.opsgenie.com";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PagerDutyConfig {
+    /// Events API v2 integration key.
+    pub routing_key: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OpsgenieConfig {
+    pub api_key: String,
+    /// `https://api.eu.opsgenie.com` for EU accounts.
+    #[serde(default = "default_opsgenie_url")]
+    pub url: String,
+}
+
+fn default_opsgenie_url() -> String {
+    OPSGENIE_URL.into()
+}
+
+/// Where operational failures go, kept apart from the market alert notifiers.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OpsConfig {
+    pub pagerduty: Option<PagerDutyConfig>,
+    pub opsgenie: Option<OpsgenieConfig>,
+    /// Consecutive failed fetches before the provider counts as down.
+    pub failures_before_down: u32,
+    /// Open-market time without a single price before it's a data outage.
+    pub outage_after_secs: i64,
+}
+
+impl Default for OpsConfig {
+    fn default() -> Self {
+        OpsConfig {
+            pagerduty: None,
+            opsgenie: None,
+            failures_before_down: 5,
+            outage_after_secs: 900,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IncidentSeverity {
+    Critical,
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Incident {
+    /// Stable per failure mode, so repeats update one incident.
+    pub dedup_key: String,
+    pub summary: String,
+    pub severity: IncidentSeverity,
+    pub component: String,
+}
+
+/// Opens and resolves incidents, sending each transition once.
+pub struct OpsAlerter {
+    config: OpsConfig,
+    open: Mutex<HashSet<String>>,
+    client: reqwest::Client,
+}
+
+impl OpsAlerter {
+    pub fn new(config: OpsConfig) -> Self {
+        OpsAlerter {
+            config,
+            open: Mutex::new(HashSet::new()),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn raise(self: &Arc<Self>, incident: Incident) {
+        if !self.open.lock().unwrap().insert(incident.dedup_key.clone()) {
+            return;
+        }
+        error!(
+            dedup_key = %incident.dedup_key,
+            component = %incident.component,
+            "Operational incident: {}",
+            incident.summary
+        );
+        let alerter = self.clone();
+        tokio::spawn(async move { alerter.send(&incident, true).await });
+    }
+
+    pub fn resolve(self: &Arc<Self>, dedup_key: &str) {
+        if !self.open.lock().unwrap().remove(dedup_key) {
+            return;
+        }
+        info!(dedup_key, "Operational incident resolved");
+        let incident = Incident {
+            dedup_key: dedup_key.to_string(),
+            summary: String::new(),
+            severity: IncidentSeverity::Warning,
+            component: String::new(),
+        };
+        let alerter = self.clone();
+        tokio::spawn(async move { alerter.send(&incident, false).await });
+    }
+
+    async fn send(&self, incident: &Incident, trigger: bool) {
+        if let Some(pagerduty) = &self.config.pagerduty {
+            let mut body = json!({
+                "routing_key": pagerduty.routing_key,
+                "event_action": if trigger { "trigger" } else { "resolve" },
+                "dedup_key": incident.dedup_key,
+            });
+            if trigger {
+                body["payload"] = json!({
+                    "summary": incident.summary,
+                    "source": "fintek",
+                    "severity": incident.severity,
+                    "component": incident.component,
+                });
+            }
+            let result = self
+                .client
+                .post(PAGERDUTY_URL)
+                .header("content-type", "application/json")
+                .body(body.to_string())
+                .send()
+                .await
+                .and_then(|r| r.error_for_status());
+            if let Err(e) = result {
+                error!(error = %e, "Failed to send PagerDuty event");
+            }
+        }
+
+        if let Some(opsgenie) = &self.config.opsgenie {
+            let base = opsgenie.url.trim_end_matches('/');
+            let request = if trigger {
+                let priority = match incident.severity {
+                    IncidentSeverity::Critical => "P1",
+                    IncidentSeverity::Error => "P2",
+                    IncidentSeverity::Warning => "P3",
+                };
+                let body = json!({
+                    "message": incident.summary,
+                    "alias": incident.dedup_key,
+                    "source": "fintek",
+                    "entity": incident.component,
+                    "priority": priority,
+                });
+                self.client
+                    .post(format!("{}/v2/alerts", base))
+                    .body(body.to_string())
+            } else {
+                self.client
+                    .post(format!(
+                        "{}/v2/alerts/{}/close?identifierType=alias",
+                        base, incident.dedup_key
+                    ))
+                    .body("{}")
+            };
+            let result = request
+                .header("content-type", "application/json")
+                .header("Authorization", format!("GenieKey {}", opsgenie.api_key))
+                .send()
+                .await
+                .and_then(|r| r.error_for_status());
+            if let Err(e) = result {
+                error!(error = %e, "Failed to send Opsgenie alert");
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Health {
+    consecutive_failures: u32,
+    last_price: Option<DateTime<Utc>>,
+    open_since: Option<DateTime<Utc>>,
+}
+
+/// Watches fetch outcomes for a provider outage and for an open market
+/// that has gone quiet.
+pub struct OpsMonitor {
+    alerter: Arc<OpsAlerter>,
+    provider: String,
+    health: Mutex<Health>,
+}
+
+impl OpsMonitor {
+    pub fn new(alerter: Arc<OpsAlerter>, provider: &str) -> Self {
+        OpsMonitor {
+            alerter,
+            provider: provider.to_string(),
+            health: Mutex::new(Health::default()),
+        }
+    }
+
+    fn provider_key(&self) -> String {
+        format!("fintek-provider-down-{}", self.provider)
+    }
+}
+
+impl Sink for OpsMonitor {
+    fn record(&self, update: &PriceUpdate) {
+        self.health.lock().unwrap().last_price = Some(update.timestamp);
+    }
+
+    fn on_fetch(&self, _symbol: &str, outcome: FetchOutcome, _at: DateTime<Utc>) {
+        let mut health = self.health.lock().unwrap();
+        if outcome == FetchOutcome::Failure {
+            health.consecutive_failures += 1;
+            if health.consecutive_failures == self.alerter.config.failures_before_down {
+                self.alerter.raise(Incident {
+                    dedup_key: self.provider_key(),
+                    summary: format!(
+                        "Provider {} failed {} fetches in a row",
+                        self.provider, health.consecutive_failures
+                    ),
+                    severity: IncidentSeverity::Critical,
+                    component: self.provider.clone(),
+                });
+            }
+        } else {
+            health.consecutive_failures = 0;
+            self.alerter.resolve(&self.provider_key());
+        }
+    }
+
+    fn on_cycle(&self, cycle: &CycleContext) {
+        let mut health = self.health.lock().unwrap();
+        if !cycle.market_open {
+            health.open_since = None;
+            return;
+        }
+        let open_since = *health.open_since.get_or_insert(cycle.now);
+        let quiet_since = health
+            .last_price
+            .map_or(open_since, |at| at.max(open_since));
+        let quiet = (cycle.now - quiet_since).num_seconds();
+        if quiet >= self.alerter.config.outage_after_secs {
+            self.alerter.raise(Incident {
+                dedup_key: "fintek-data-outage".into(),
+                summary: format!("No prices received for {}s while the market is open", quiet),
+                severity: IncidentSeverity::Error,
+                component: "data".into(),
+            });
+        } else {
+            self.alerter.resolve("fintek-data-outage");
+        }
+    }
+}
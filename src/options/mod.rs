@@ -0,0 +1,402 @@
+use std::collections::BTreeMap;
+use std::f64::consts::{PI, SQRT_2};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::America::New_York;
+use reqwest::Error;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, instrument, warn};
+
+use crate::engine::Engine;
+use crate::metrics;
+use crate::provider::endpoints::{self, Endpoint, OptionChainResponse, OptionQuote};
+use crate::provider::{base_url, TWELVEDATA_URL};
+
+/// Shares one contract is for.
+pub const CONTRACT_SIZE: f64 = 100.;
+
+// How long a refresh waits for a first spot price after startup.
+const SPOT_WAIT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OptionsConfig {
+    /// Underlyings whose chains are fetched. They're polled like tickers
+    /// for the spot price.
+    pub symbols: Vec<String>,
+    /// Nearest expirations per symbol, one request each.
+    pub expirations: usize,
+    pub refresh_secs: u64,
+    /// Annual, continuously compounded.
+    pub risk_free_percent: f64,
+    pub dividend_yield_percent: f64,
+    /// Export each contract's Greeks too, not just the per-symbol totals.
+    pub per_contract: bool,
+}
+
+impl Default for OptionsConfig {
+    fn default() -> Self {
+        OptionsConfig {
+            symbols: vec![],
+            expirations: 1,
+            refresh_secs: 900,
+            risk_free_percent: 4.,
+            dividend_yield_percent: 0.,
+            per_contract: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OptionKind {
+    Call,
+    Put,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Greeks {
+    pub delta: f64,
+    pub gamma: f64,
+    /// Per calendar day.
+    pub theta: f64,
+    /// Per volatility point.
+    pub vega: f64,
+}
+
+impl Greeks {
+    pub fn named(&self) -> [(&'static str, f64); 4] {
+        [
+            ("delta", self.delta),
+            ("gamma", self.gamma),
+            ("theta", self.theta),
+            ("vega", self.vega),
+        ]
+    }
+}
+
+fn pdf(x: f64) -> f64 {
+    (-x * x / 2.).exp() / (2. * PI).sqrt()
+}
+
+// Complementary error function, to within 1.2e-7 (Numerical Recipes).
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1. / (1. + z / 2.);
+    let poly = -z * z - 1.26551223
+        + t * (1.00002368
+            + t * (0.37409196
+                + t * (0.09678418
+                    + t * (-0.18628806
+                        + t * (0.27886807
+                            + t * (-1.13520398
+                                + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277))))))));
+    let r = t * poly.exp();
+    if x >= 0. {
+        r
+    } else {
+        2. - r
+    }
+}
+
+fn cdf(x: f64) -> f64 {
+    erfc(-x / SQRT_2) / 2.
+}
+
+/// Black-Scholes Greeks of a European option with `years` to expiry, rates
+/// and `volatility` as fractions. `None` when any input leaves them
+/// undefined, as at expiry.
+pub fn black_scholes(
+    kind: OptionKind,
+    spot: f64,
+    strike: f64,
+    years: f64,
+    rate: f64,
+    dividend: f64,
+    volatility: f64,
+) -> Option<Greeks> {
+    if spot <= 0. || strike <= 0. || years <= 0. || volatility <= 0. {
+        return None;
+    }
+    let root = years.sqrt();
+    let d1 = ((spot / strike).ln() + (rate - dividend + volatility * volatility / 2.) * years)
+        / (volatility * root);
+    let d2 = d1 - volatility * root;
+    let (carry, discount) = ((-dividend * years).exp(), (-rate * years).exp());
+    let decay = -spot * carry * pdf(d1) * volatility / (2. * root);
+    let (delta, theta) = match kind {
+        OptionKind::Call => (
+            carry * cdf(d1),
+            decay - rate * strike * discount * cdf(d2) + dividend * spot * carry * cdf(d1),
+        ),
+        OptionKind::Put => (
+            -carry * cdf(-d1),
+            decay + rate * strike * discount * cdf(-d2) - dividend * spot * carry * cdf(-d1),
+        ),
+    };
+    Some(Greeks {
+        delta,
+        gamma: carry * pdf(d1) / (spot * volatility * root),
+        theta: theta / 365.,
+        vega: spot * carry * pdf(d1) * root / 100.,
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ContractGreeks {
+    pub contract: String,
+    pub kind: OptionKind,
+    pub strike: f64,
+    pub expiry: NaiveDate,
+    pub implied_volatility: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_interest: Option<f64>,
+    pub greeks: Greeks,
+}
+
+/// The Greeks of a symbol's chain at one spot price.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainGreeks {
+    pub symbol: String,
+    pub spot: f64,
+    pub computed_at: DateTime<Utc>,
+    /// Summed over the contracts, each weighted by its open interest times
+    /// the contract size: the share-equivalent exposure of all open
+    /// positions held long.
+    pub exposure: Greeks,
+    pub contracts: Vec<ContractGreeks>,
+}
+
+// Options expire after the US close.
+fn expires_at(date: NaiveDate) -> Option<DateTime<Utc>> {
+    let close = NaiveTime::from_hms_opt(16, 0, 0)?;
+    let at = New_York
+        .from_local_datetime(&date.and_time(close))
+        .earliest()?;
+    Some(at.with_timezone(&Utc))
+}
+
+#[instrument(skip(api_key))]
+pub async fn fetch_expirations(
+    base_url: &str,
+    symbol: &str,
+    api_key: &str,
+) -> Result<Vec<NaiveDate>, Error> {
+    let url = endpoints::url::<endpoints::OptionExpirations>(
+        base_url,
+        &format!("symbol={}", symbol),
+        api_key,
+    );
+    let data = reqwest::get(&url).await?.text().await?;
+    let response = match endpoints::parse::<<endpoints::OptionExpirations as Endpoint>::Response>(
+        &data,
+    ) {
+        Ok(Ok(response)) => response,
+        Ok(Err(error)) => {
+            warn!(symbol, code = error.code, message = %error.message, "Option expirations request failed");
+            return Ok(vec![]);
+        }
+        Err(e) => {
+            warn!(symbol, error = %e, "Unexpected option expirations response");
+            return Ok(vec![]);
+        }
+    };
+    let mut dates: Vec<NaiveDate> = response
+        .dates
+        .iter()
+        .filter_map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .collect();
+    dates.sort();
+    Ok(dates)
+}
+
+#[instrument(skip(api_key))]
+pub async fn fetch_chain(
+    base_url: &str,
+    symbol: &str,
+    expiry: NaiveDate,
+    api_key: &str,
+) -> Result<Option<OptionChainResponse>, Error> {
+    let url = endpoints::url::<endpoints::OptionChain>(
+        base_url,
+        &format!("symbol={}&expiration_date={}", symbol, expiry),
+        api_key,
+    );
+    let data = reqwest::get(&url).await?.text().await?;
+    Ok(
+        match endpoints::parse::<<endpoints::OptionChain as Endpoint>::Response>(&data) {
+            Ok(Ok(response)) => Some(response),
+            Ok(Err(error)) => {
+                warn!(symbol, code = error.code, message = %error.message, "Option chain request failed");
+                None
+            }
+            Err(e) => {
+                warn!(symbol, error = %e, "Unexpected option chain response");
+                None
+            }
+        },
+    )
+}
+
+/// Fetches the options chains of the configured underlyings on a schedule
+/// and computes each contract's Greeks locally with Black-Scholes, from the
+/// chain's implied volatility and the latest polled spot price.
+pub struct OptionsMonitor {
+    config: OptionsConfig,
+    base_url: String,
+    chains: RwLock<BTreeMap<String, ChainGreeks>>,
+}
+
+impl OptionsMonitor {
+    /// `None` without symbols.
+    pub fn new(config: &OptionsConfig) -> Option<Self> {
+        if config.symbols.is_empty() {
+            return None;
+        }
+        Some(OptionsMonitor {
+            config: config.clone(),
+            base_url: TWELVEDATA_URL.into(),
+            chains: RwLock::new(BTreeMap::new()),
+        })
+    }
+
+    pub fn with_base_url(mut self, url: &str) -> Self {
+        self.base_url = base_url(url);
+        self
+    }
+
+    pub fn chain(&self, symbol: &str) -> Option<ChainGreeks> {
+        self.chains.read().unwrap().get(symbol).cloned()
+    }
+
+    /// Greeks of the contracts in `chain` expiring on `expiry`, skipping
+    /// those without an implied volatility.
+    pub fn greeks(
+        &self,
+        chain: &OptionChainResponse,
+        expiry: NaiveDate,
+        spot: f64,
+        now: DateTime<Utc>,
+    ) -> Vec<ContractGreeks> {
+        let Some(expires) = expires_at(expiry) else {
+            return vec![];
+        };
+        let years = (expires - now).num_seconds() as f64 / (365. * 86400.);
+        let rate = self.config.risk_free_percent / 100.;
+        let dividend = self.config.dividend_yield_percent / 100.;
+        let sides = [
+            (OptionKind::Call, &chain.calls),
+            (OptionKind::Put, &chain.puts),
+        ];
+        sides
+            .into_iter()
+            .flat_map(|(kind, quotes)| quotes.iter().map(move |q| (kind, q)))
+            .filter_map(|(kind, quote): (OptionKind, &OptionQuote)| {
+                let volatility = quote.implied_volatility?;
+                let greeks =
+                    black_scholes(kind, spot, quote.strike, years, rate, dividend, volatility)?;
+                Some(ContractGreeks {
+                    contract: quote.contract_name.clone(),
+                    kind,
+                    strike: quote.strike,
+                    expiry,
+                    implied_volatility: volatility,
+                    open_interest: quote.open_interest,
+                    greeks,
+                })
+            })
+            .collect()
+    }
+
+    async fn refresh_symbol(&self, engine: &Engine, symbol: &str, api_key: &str) {
+        let spot = match engine.latest() {
+            Some(latest) => match latest.get(symbol) {
+                Some(update) => Some(update.price),
+                None => latest
+                    .next_change(symbol, None, SPOT_WAIT)
+                    .await
+                    .map(|update| update.price),
+            },
+            None => None,
+        };
+        let Some(spot) = spot else {
+            warn!(symbol, "No spot price, skipping options chain");
+            return;
+        };
+        engine.limiter().acquire(1).await;
+        let expirations = match fetch_expirations(&self.base_url, symbol, api_key).await {
+            Ok(dates) => dates,
+            Err(e) => {
+                error!(symbol, error = ?e, "Failed to fetch option expirations");
+                return;
+            }
+        };
+        let now = engine.clock().now();
+        let upcoming = expirations
+            .into_iter()
+            .filter(|d| expires_at(*d).is_some_and(|at| at > now))
+            .take(self.config.expirations.max(1));
+        let mut contracts = vec![];
+        for expiry in upcoming {
+            engine.limiter().acquire(1).await;
+            match fetch_chain(&self.base_url, symbol, expiry, api_key).await {
+                Ok(Some(chain)) => contracts.extend(self.greeks(&chain, expiry, spot, now)),
+                Ok(None) => {}
+                Err(e) => error!(symbol, %expiry, error = ?e, "Failed to fetch option chain"),
+            }
+        }
+        let mut exposure = Greeks::default();
+        for contract in &contracts {
+            let weight = contract.open_interest.unwrap_or(0.) * CONTRACT_SIZE;
+            exposure.delta += contract.greeks.delta * weight;
+            exposure.gamma += contract.greeks.gamma * weight;
+            exposure.theta += contract.greeks.theta * weight;
+            exposure.vega += contract.greeks.vega * weight;
+        }
+        for (greek, value) in exposure.named() {
+            metrics::update_options_exposure(symbol, greek, value);
+        }
+        if self.config.per_contract {
+            for contract in &contracts {
+                let greeks = Some(contract.greeks.named());
+                metrics::update_option_greeks(symbol, &contract.contract, greeks);
+            }
+            let listed = |name: &str| contracts.iter().any(|c| c.contract == name);
+            for old in self.chain(symbol).into_iter().flat_map(|c| c.contracts) {
+                if !listed(&old.contract) {
+                    metrics::update_option_greeks(symbol, &old.contract, None);
+                }
+            }
+        }
+        info!(
+            symbol,
+            spot,
+            contracts = contracts.len(),
+            "Computed option Greeks"
+        );
+        self.chains.write().unwrap().insert(
+            symbol.to_string(),
+            ChainGreeks {
+                symbol: symbol.to_string(),
+                spot,
+                computed_at: now,
+                exposure,
+                contracts,
+            },
+        );
+    }
+
+    pub async fn run(self: Arc<Self>, engine: Arc<Engine>, api_key: String) {
+        loop {
+            for symbol in &self.config.symbols {
+                self.refresh_symbol(&engine, symbol, &api_key).await;
+            }
+            engine
+                .clock()
+                .sleep(Duration::from_secs(self.config.refresh_secs.max(60)))
+                .await;
+        }
+    }
+}
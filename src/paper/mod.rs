@@ -0,0 +1,391 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::info;
+use utoipa::ToSchema;
+
+use crate::events::{Event, EventBus};
+use crate::returns::Conventions;
+use crate::sink::{PriceUpdate, Sink};
+use crate::symbol::SymbolInfo;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PaperConfig {
+    pub starting_cash: f64,
+    /// Flat fee charged per fill.
+    pub commission: f64,
+}
+
+impl Default for PaperConfig {
+    fn default() -> Self {
+        PaperConfig {
+            starting_cash: 100_000.,
+            commission: 0.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderStatus {
+    Open,
+    Filled,
+    Cancelled,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct OrderRequest {
+    pub symbol: String,
+    pub side: Side,
+    pub quantity: f64,
+    /// Market order when absent.
+    pub limit_price: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct Order {
+    pub id: u64,
+    pub symbol: String,
+    pub side: Side,
+    pub quantity: f64,
+    pub limit_price: Option<f64>,
+    pub status: OrderStatus,
+    pub created_at: DateTime<Utc>,
+    pub filled_at: Option<DateTime<Utc>>,
+    pub fill_price: Option<f64>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Position {
+    pub symbol: String,
+    pub currency: String,
+    pub quantity: f64,
+    pub average_cost: f64,
+    pub last_price: Option<f64>,
+    pub market_value: Option<f64>,
+    pub unrealized_pnl: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Portfolio {
+    pub cash: f64,
+    pub equity: f64,
+    pub positions: Vec<Position>,
+}
+
+/// One position's share of today's P&L.
+#[derive(Debug, Clone, Serialize)]
+pub struct Contribution {
+    pub symbol: String,
+    pub daily_pnl: f64,
+    /// `daily_pnl` over the start-of-day equity, so contributions sum to the daily return.
+    pub contribution: f64,
+    /// Market value over current equity.
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ContributionReport {
+    pub date: Option<NaiveDate>,
+    pub daily_pnl: f64,
+    pub daily_return: f64,
+    /// Largest contributor first, largest detractor last.
+    pub positions: Vec<Contribution>,
+}
+
+impl ContributionReport {
+    pub fn contributors(&self, count: usize) -> impl Iterator<Item = &Contribution> {
+        self.positions
+            .iter()
+            .filter(|c| c.daily_pnl > 0.)
+            .take(count)
+    }
+
+    pub fn detractors(&self, count: usize) -> impl Iterator<Item = &Contribution> {
+        self.positions
+            .iter()
+            .rev()
+            .filter(|c| c.daily_pnl < 0.)
+            .take(count)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PaperError {
+    #[error("quantity must be positive")]
+    InvalidQuantity,
+    #[error("no price seen yet for {0}")]
+    NoPrice(String),
+    #[error("unknown order {0}")]
+    UnknownOrder(u64),
+    #[error("order {0} is no longer open")]
+    NotOpen(u64),
+}
+
+#[derive(Debug, Default)]
+struct Holding {
+    quantity: f64,
+    cost: f64,
+}
+
+#[derive(Debug)]
+struct State {
+    cash: f64,
+    holdings: BTreeMap<String, Holding>,
+    prices: HashMap<String, f64>,
+    /// Last prices of the previous trading day, the baseline for daily P&L.
+    previous_close: HashMap<String, f64>,
+    day: Option<NaiveDate>,
+    orders: Vec<Order>,
+}
+
+/// A simulated brokerage account filled against the prices the engine
+/// polls. Market orders fill at the last seen price, limit orders on the
+/// first update that crosses the limit.
+pub struct PaperAccount {
+    config: PaperConfig,
+    state: Mutex<State>,
+    events: EventBus,
+    conventions: Conventions,
+}
+
+impl PaperAccount {
+    pub fn new(config: PaperConfig) -> Self {
+        PaperAccount {
+            state: Mutex::new(State {
+                cash: config.starting_cash,
+                holdings: BTreeMap::new(),
+                prices: HashMap::new(),
+                previous_close: HashMap::new(),
+                day: None,
+                orders: vec![],
+            }),
+            config,
+            events: EventBus::default(),
+            conventions: Conventions::default(),
+        }
+    }
+
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Decides when the previous close rolls over.
+    pub fn with_conventions(mut self, conventions: Conventions) -> Self {
+        self.conventions = conventions;
+        self
+    }
+
+    pub fn place(&self, request: OrderRequest, now: DateTime<Utc>) -> Result<Order, PaperError> {
+        if request.quantity.is_nan() || request.quantity <= 0. {
+            return Err(PaperError::InvalidQuantity);
+        }
+        let mut state = self.state.lock().unwrap();
+        let last = state.prices.get(&request.symbol).copied();
+        if request.limit_price.is_none() && last.is_none() {
+            return Err(PaperError::NoPrice(request.symbol));
+        }
+
+        self.events.publish(Event::OrderPlaced {
+            at: now,
+            request: request.clone(),
+        });
+        let mut order = Order {
+            id: state.orders.len() as u64 + 1,
+            symbol: request.symbol,
+            side: request.side,
+            quantity: request.quantity,
+            limit_price: request.limit_price,
+            status: OrderStatus::Open,
+            created_at: now,
+            filled_at: None,
+            fill_price: None,
+            reason: None,
+        };
+        if let Some(price) = last {
+            self.try_fill(&mut state, &mut order, price, now);
+        }
+        info!(id = order.id, symbol = %order.symbol, status = ?order.status, "Placed paper order");
+        state.orders.push(order.clone());
+        Ok(order)
+    }
+
+    pub fn cancel(&self, id: u64, now: DateTime<Utc>) -> Result<Order, PaperError> {
+        let mut state = self.state.lock().unwrap();
+        let order = state
+            .orders
+            .iter_mut()
+            .find(|o| o.id == id)
+            .ok_or(PaperError::UnknownOrder(id))?;
+        if order.status != OrderStatus::Open {
+            return Err(PaperError::NotOpen(id));
+        }
+        order.status = OrderStatus::Cancelled;
+        self.events.publish(Event::OrderCancelled { at: now, id });
+        Ok(order.clone())
+    }
+
+    pub fn orders(&self) -> Vec<Order> {
+        self.state.lock().unwrap().orders.clone()
+    }
+
+    pub fn portfolio(&self) -> Portfolio {
+        let state = self.state.lock().unwrap();
+        let positions: Vec<Position> = state
+            .holdings
+            .iter()
+            .map(|(symbol, holding)| {
+                let last_price = state.prices.get(symbol).copied();
+                let market_value = last_price.map(|p| p * holding.quantity);
+                Position {
+                    symbol: symbol.clone(),
+                    currency: SymbolInfo::parse(symbol).currency(),
+                    quantity: holding.quantity,
+                    average_cost: holding.cost / holding.quantity,
+                    last_price,
+                    market_value,
+                    unrealized_pnl: market_value.map(|v| v - holding.cost),
+                }
+            })
+            .collect();
+        let equity = state.cash
+            + positions
+                .iter()
+                .map(|p| p.market_value.unwrap_or(p.average_cost * p.quantity))
+                .sum::<f64>();
+        Portfolio {
+            cash: state.cash,
+            equity,
+            positions,
+        }
+    }
+
+    /// Today's P&L per open position, measured from the previous close, or
+    /// from the average cost for positions without one. Realized P&L from
+    /// today's sells isn't attributed.
+    pub fn contributions(&self) -> ContributionReport {
+        let portfolio = self.portfolio();
+        let state = self.state.lock().unwrap();
+        let mut positions: Vec<Contribution> = portfolio
+            .positions
+            .iter()
+            .filter_map(|p| {
+                let last = p.last_price?;
+                let reference = state
+                    .previous_close
+                    .get(&p.symbol)
+                    .copied()
+                    .unwrap_or(p.average_cost);
+                Some(Contribution {
+                    symbol: p.symbol.clone(),
+                    daily_pnl: (last - reference) * p.quantity,
+                    contribution: 0.,
+                    weight: p.market_value.unwrap_or_default() / portfolio.equity,
+                })
+            })
+            .collect();
+        let daily_pnl: f64 = positions.iter().map(|c| c.daily_pnl).sum();
+        let start_equity = portfolio.equity - daily_pnl;
+        for c in &mut positions {
+            c.contribution = c.daily_pnl / start_equity;
+        }
+        positions.sort_by(|a, b| b.daily_pnl.total_cmp(&a.daily_pnl));
+        ContributionReport {
+            date: state.day,
+            daily_pnl,
+            daily_return: daily_pnl / start_equity,
+            positions,
+        }
+    }
+
+    fn try_fill(&self, state: &mut State, order: &mut Order, price: f64, now: DateTime<Utc>) {
+        let crosses = match (order.side, order.limit_price) {
+            (_, None) => true,
+            (Side::Buy, Some(limit)) => price <= limit,
+            (Side::Sell, Some(limit)) => price >= limit,
+        };
+        if !crosses {
+            return;
+        }
+
+        let notional = price * order.quantity;
+        let held = state
+            .holdings
+            .get(&order.symbol)
+            .map(|h| h.quantity)
+            .unwrap_or_default();
+        match order.side {
+            Side::Buy if notional + self.config.commission > state.cash => {
+                order.status = OrderStatus::Rejected;
+                order.reason = Some("insufficient cash".into());
+                return;
+            }
+            Side::Sell if order.quantity > held => {
+                order.status = OrderStatus::Rejected;
+                order.reason = Some("insufficient position".into());
+                return;
+            }
+            _ => {}
+        }
+
+        let holding = state.holdings.entry(order.symbol.clone()).or_default();
+        match order.side {
+            Side::Buy => {
+                holding.quantity += order.quantity;
+                holding.cost += notional;
+                state.cash -= notional + self.config.commission;
+            }
+            Side::Sell => {
+                holding.cost -= holding.cost * order.quantity / holding.quantity;
+                holding.quantity -= order.quantity;
+                state.cash += notional - self.config.commission;
+            }
+        }
+        if holding.quantity <= 0. {
+            state.holdings.remove(&order.symbol);
+        }
+        order.status = OrderStatus::Filled;
+        order.filled_at = Some(now);
+        order.fill_price = Some(price);
+    }
+}
+
+impl Sink for PaperAccount {
+    fn record(&self, update: &PriceUpdate) {
+        let mut state = self.state.lock().unwrap();
+        let day = self.conventions.day_of(update.timestamp);
+        if state.day.is_some_and(|d| d < day) {
+            state.previous_close = state.prices.clone();
+        }
+        if state.day.is_none_or(|d| d < day) {
+            state.day = Some(day);
+        }
+        state.prices.insert(update.symbol.clone(), update.price);
+
+        let mut orders = std::mem::take(&mut state.orders);
+        for order in orders
+            .iter_mut()
+            .filter(|o| o.status == OrderStatus::Open && o.symbol == update.symbol)
+        {
+            self.try_fill(&mut state, order, update.price, update.timestamp);
+            if order.status != OrderStatus::Open {
+                info!(id = order.id, symbol = %order.symbol, status = ?order.status, "Paper order resolved");
+            }
+        }
+        state.orders = orders;
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::events::{Event, EventBus};
+use crate::metrics;
+use crate::returns::Conventions;
+use crate::sink::{PriceUpdate, Sink};
+use crate::strategy::Signal;
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+// A body this small against the candle's range is a doji.
+const DOJI_BODY: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PatternKind {
+    Engulfing,
+    Doji,
+    Hammer,
+}
+
+impl PatternKind {
+    const ALL: [PatternKind; 3] = [
+        PatternKind::Engulfing,
+        PatternKind::Doji,
+        PatternKind::Hammer,
+    ];
+}
+
+/// Where to look for patterns: which symbols, on candles how long.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PatternRule {
+    /// All watched symbols when empty.
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    /// Candle length, a day spanning a trading day of record.
+    pub timeframe_secs: u64,
+    /// Every pattern when empty.
+    #[serde(default)]
+    pub detect: Vec<PatternKind>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bar {
+    start: DateTime<Utc>,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+}
+
+impl Bar {
+    fn body(&self) -> f64 {
+        (self.close - self.open).abs()
+    }
+
+    fn range(&self) -> f64 {
+        self.high - self.low
+    }
+
+    fn bullish(&self) -> bool {
+        self.close > self.open
+    }
+
+    fn bearish(&self) -> bool {
+        self.close < self.open
+    }
+}
+
+/// A pattern found on a closed candle: its name and +1 when bullish, -1
+/// when bearish, 0 for indecision.
+fn detect(kind: PatternKind, previous: Option<&Bar>, bar: &Bar) -> Option<(&'static str, f64)> {
+    let range = bar.range();
+    if range <= 0. {
+        return None;
+    }
+    let body = bar.body();
+    match kind {
+        PatternKind::Doji => (body <= DOJI_BODY * range).then_some(("doji", 0.)),
+        PatternKind::Hammer => {
+            let lower = bar.open.min(bar.close) - bar.low;
+            let upper = bar.high - bar.open.max(bar.close);
+            (body > DOJI_BODY * range && lower >= 2. * body && upper <= body)
+                .then_some(("hammer", 1.))
+        }
+        PatternKind::Engulfing => {
+            let previous = previous?;
+            let engulfs = bar.open.min(bar.close) <= previous.open.min(previous.close)
+                && bar.open.max(bar.close) >= previous.open.max(previous.close)
+                && body > previous.body();
+            if engulfs && previous.bearish() && bar.bullish() {
+                Some(("bullish_engulfing", 1.))
+            } else if engulfs && previous.bullish() && bar.bearish() {
+                Some(("bearish_engulfing", -1.))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+// `300` as `5m`, for labels.
+fn timeframe_label(secs: u64) -> String {
+    match secs {
+        s if s % DAY_SECS == 0 => format!("{}d", s / DAY_SECS),
+        s if s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+#[derive(Debug, Default)]
+struct Candles {
+    previous: Option<Bar>,
+    current: Option<Bar>,
+}
+
+/// Aggregates prices into candles per symbol and timeframe and checks each
+/// as it closes for the configured patterns. Finds are counted, logged and
+/// published on the event bus as signals from `patterns`.
+pub struct PatternDetector {
+    rules: Vec<PatternRule>,
+    events: EventBus,
+    conventions: Conventions,
+    candles: Mutex<HashMap<(String, u64), Candles>>,
+}
+
+impl PatternDetector {
+    /// `None` without rules.
+    pub fn new(rules: &[PatternRule], events: EventBus, conventions: Conventions) -> Option<Self> {
+        if rules.is_empty() {
+            return None;
+        }
+        Some(PatternDetector {
+            rules: rules.to_vec(),
+            events,
+            conventions,
+            candles: Mutex::new(HashMap::new()),
+        })
+    }
+
+    // The timeframes `symbol` is watched on, with the patterns for each.
+    fn timeframes(&self, symbol: &str) -> BTreeMap<u64, Vec<PatternKind>> {
+        let mut timeframes: BTreeMap<u64, Vec<PatternKind>> = BTreeMap::new();
+        for rule in &self.rules {
+            if rule.timeframe_secs == 0
+                || !(rule.symbols.is_empty() || rule.symbols.iter().any(|s| s == symbol))
+            {
+                continue;
+            }
+            let kinds = timeframes.entry(rule.timeframe_secs).or_default();
+            let detect = if rule.detect.is_empty() {
+                &PatternKind::ALL[..]
+            } else {
+                &rule.detect[..]
+            };
+            for kind in detect {
+                if !kinds.contains(kind) {
+                    kinds.push(*kind);
+                }
+            }
+        }
+        timeframes
+    }
+
+    fn bucket(&self, secs: u64, timestamp: DateTime<Utc>) -> DateTime<Utc> {
+        if secs == DAY_SECS {
+            return self.conventions.truncate(timestamp);
+        }
+        let (at, secs) = (timestamp.timestamp(), secs as i64);
+        DateTime::from_timestamp(at - at.rem_euclid(secs), 0).unwrap_or(timestamp)
+    }
+
+    // Adds the price to its candles and returns the ones it closed, with the
+    // candle before each and the patterns to check.
+    fn update(&self, update: &PriceUpdate) -> Vec<(u64, Option<Bar>, Bar, Vec<PatternKind>)> {
+        let mut closed = vec![];
+        let mut candles = self.candles.lock().unwrap();
+        for (secs, kinds) in self.timeframes(&update.symbol) {
+            let start = self.bucket(secs, update.timestamp);
+            let price = update.price;
+            let candles = candles.entry((update.symbol.clone(), secs)).or_default();
+            match &mut candles.current {
+                Some(bar) if bar.start == start => {
+                    bar.high = bar.high.max(price);
+                    bar.low = bar.low.min(price);
+                    bar.close = price;
+                    continue;
+                }
+                // A late tick for a closed candle would reopen it.
+                Some(bar) if bar.start > start => continue,
+                _ => {}
+            }
+            let fresh = Bar {
+                start,
+                open: price,
+                high: price,
+                low: price,
+                close: price,
+            };
+            if let Some(done) = candles.current.replace(fresh) {
+                closed.push((secs, candles.previous, done, kinds));
+                candles.previous = Some(done);
+            }
+        }
+        closed
+    }
+}
+
+impl Sink for PatternDetector {
+    fn record(&self, update: &PriceUpdate) {
+        for (secs, previous, bar, kinds) in self.update(update) {
+            let timeframe = timeframe_label(secs);
+            for kind in kinds {
+                let Some((name, value)) = detect(kind, previous.as_ref(), &bar) else {
+                    continue;
+                };
+                info!(symbol = %update.symbol, timeframe, pattern = name, start = %bar.start, "Candlestick pattern");
+                metrics::update_candle_pattern(&update.symbol, &timeframe, name);
+                self.events.publish(Event::Signal {
+                    at: update.timestamp,
+                    signal: Signal {
+                        source: "patterns".into(),
+                        symbol: update.symbol.clone(),
+                        name: format!("{}_{}", name, timeframe),
+                        value,
+                    },
+                });
+            }
+        }
+    }
+
+    // Restored prices rebuild the candles without reporting again.
+    fn replay(&self, update: &PriceUpdate) {
+        self.update(update);
+    }
+}
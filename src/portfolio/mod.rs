@@ -0,0 +1,523 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+
+use crate::metrics;
+use crate::notify::{Notification, NotificationKind, Notifiers, Urgency};
+use crate::recovery;
+use crate::sink::{PriceUpdate, Sink};
+
+pub const PORTFOLIO_FILE: &str = "portfolio.json";
+pub const LADDER_FILE: &str = "ladder.json";
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PortfolioConfig {
+    /// Gains over each holding's cost basis, in percent, to notify about
+    /// once each, e.g. `[10, 20, 30]`.
+    pub ladder_percent: Vec<f64>,
+}
+
+/// A position actually held, as opposed to the paper account's.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct Holding {
+    pub quantity: f64,
+    /// Average price paid per unit.
+    pub cost_basis: f64,
+    /// When the position was opened, the earliest lot's date.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acquired: Option<NaiveDate>,
+}
+
+impl Holding {
+    fn valid(&self) -> bool {
+        self.quantity > 0.
+            && self.quantity.is_finite()
+            && self.cost_basis >= 0.
+            && self.cost_basis.is_finite()
+    }
+
+    // Another lot of the same symbol, averaging the cost.
+    fn add(&mut self, lot: Holding) {
+        let quantity = self.quantity + lot.quantity;
+        self.cost_basis =
+            (self.cost_basis * self.quantity + lot.cost_basis * lot.quantity) / quantity;
+        self.quantity = quantity;
+        self.acquired = match (self.acquired, lot.acquired) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
+}
+
+/// A CSV row that can't be imported.
+#[derive(Debug, Error)]
+#[error("line {line}: {message}")]
+pub struct RowError {
+    pub line: usize,
+    pub message: String,
+}
+
+const COLUMNS: [&str; 4] = ["symbol", "quantity", "cost", "date"];
+
+// One CSV line's fields, unquoting `"a, b"` and `""`.
+fn fields(line: &str) -> Vec<String> {
+    let (mut fields, mut field, mut quoted) = (vec![], String::new(), false);
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            c => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+/// Holdings from a CSV with a header naming the `symbol`, `quantity`,
+/// `cost` (paid per unit) and, optionally, `date` (`YYYY-MM-DD`) columns.
+/// Rows of the same symbol are lots of one holding. Every bad row is
+/// reported, and any one fails the import.
+pub fn parse_csv(data: &str) -> Result<BTreeMap<String, Holding>, Vec<RowError>> {
+    let mut lines = data
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim_start_matches('\u{feff}')))
+        .filter(|(_, line)| !line.trim().is_empty());
+    let Some((header_line, header)) = lines.next() else {
+        return Err(vec![RowError {
+            line: 1,
+            message: "no header".into(),
+        }]);
+    };
+    let header: Vec<String> = fields(header).iter().map(|f| f.to_lowercase()).collect();
+    let column = |name: &str| header.iter().position(|h| h == name);
+    let mut errors = vec![];
+    for name in &COLUMNS[..3] {
+        if column(name).is_none() {
+            errors.push(RowError {
+                line: header_line,
+                message: format!("missing column {:?}", name),
+            });
+        }
+    }
+    if let Some(unknown) = header.iter().find(|h| !COLUMNS.contains(&h.as_str())) {
+        errors.push(RowError {
+            line: header_line,
+            message: format!(
+                "unknown column {:?}, expected {}",
+                unknown,
+                COLUMNS.join(", ")
+            ),
+        });
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let (symbol, quantity, cost) = (
+        column("symbol").unwrap_or_default(),
+        column("quantity").unwrap_or_default(),
+        column("cost").unwrap_or_default(),
+    );
+    let date = column("date");
+
+    let mut holdings: BTreeMap<String, Holding> = BTreeMap::new();
+    for (line, text) in lines {
+        let row = fields(text);
+        let fail = |message: String| RowError { line, message };
+        if row.len() != header.len() {
+            errors.push(fail(format!(
+                "{} fields, the header has {}",
+                row.len(),
+                header.len()
+            )));
+            continue;
+        }
+        let number = |i: usize, name: &str| {
+            row[i]
+                .parse::<f64>()
+                .map_err(|_| fail(format!("{} {:?} isn't a number", name, row[i])))
+        };
+        let parsed = (|| {
+            if row[symbol].is_empty() {
+                return Err(fail("no symbol".into()));
+            }
+            let acquired = match date.map(|i| row[i].as_str()) {
+                None | Some("") => None,
+                Some(text) => Some(
+                    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                        .map_err(|_| fail(format!("date {:?} isn't YYYY-MM-DD", text)))?,
+                ),
+            };
+            let lot = Holding {
+                quantity: number(quantity, "quantity")?,
+                cost_basis: number(cost, "cost")?,
+                acquired,
+            };
+            if !lot.valid() {
+                return Err(fail(PortfolioError::Invalid.to_string()));
+            }
+            Ok((row[symbol].clone(), lot))
+        })();
+        match parsed {
+            Ok((symbol, lot)) => match holdings.get_mut(&symbol) {
+                Some(holding) => holding.add(lot),
+                None => {
+                    holdings.insert(symbol, lot);
+                }
+            },
+            Err(e) => errors.push(e),
+        }
+    }
+    if errors.is_empty() {
+        Ok(holdings)
+    } else {
+        Err(errors)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct PositionValue {
+    pub symbol: String,
+    pub quantity: f64,
+    pub cost_basis: f64,
+    pub last_price: Option<f64>,
+    pub market_value: Option<f64>,
+    pub unrealized_pnl: Option<f64>,
+}
+
+/// Totals are over the positions with a price so far.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct Valuation {
+    pub total_value: f64,
+    pub total_cost: f64,
+    pub unrealized_pnl: f64,
+    pub positions: Vec<PositionValue>,
+}
+
+#[derive(Debug, Error)]
+pub enum PortfolioError {
+    #[error("portfolio: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("portfolio is corrupt: {0}")]
+    Corrupt(#[from] serde_json::Error),
+    #[error("quantity must be positive and cost basis not negative")]
+    Invalid,
+    #[error("no holding of {0:?}")]
+    NotFound(String),
+}
+
+// The ladder rungs a holding reached at its cost basis. A new basis, as
+// after buying more, starts the ladder over.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+struct Rungs {
+    cost_basis: f64,
+    fired: Vec<f64>,
+}
+
+#[derive(Default)]
+struct State {
+    modified: Option<SystemTime>,
+    holdings: BTreeMap<String, Holding>,
+    prices: HashMap<String, f64>,
+    exported: BTreeSet<String>,
+    rungs: BTreeMap<String, Rungs>,
+}
+
+struct Ladder {
+    percents: Vec<f64>,
+    path: PathBuf,
+    notifiers: Notifiers,
+}
+
+/// Holdings declared in the state directory, by hand or through the API,
+/// valued at every price update and exported as the `portfolio_*` position
+/// gauges. Edits to the file are picked up while the server runs.
+pub struct PortfolioTracker {
+    path: PathBuf,
+    state: Mutex<State>,
+    ladder: Option<Ladder>,
+    metrics: bool,
+}
+
+impl PortfolioTracker {
+    pub fn new(state_dir: &Path) -> Self {
+        PortfolioTracker {
+            path: state_dir.join(PORTFOLIO_FILE),
+            state: Mutex::new(State::default()),
+            ladder: None,
+            metrics: true,
+        }
+    }
+
+    /// Values the holdings without exporting the gauges, which are the
+    /// shared portfolio's.
+    pub fn without_metrics(mut self) -> Self {
+        self.metrics = false;
+        self
+    }
+
+    /// Notifies as each holding first gains the configured percentages
+    /// over its cost basis. Rungs reached are kept next to the portfolio,
+    /// so each fires once across restarts.
+    pub fn with_ladder(mut self, config: &PortfolioConfig, notifiers: Notifiers) -> Self {
+        let mut percents: Vec<f64> = config
+            .ladder_percent
+            .iter()
+            .copied()
+            .filter(|p| p.is_finite() && *p > 0.)
+            .collect();
+        if percents.is_empty() {
+            return self;
+        }
+        percents.sort_by(f64::total_cmp);
+        percents.dedup();
+        let path = self.path.with_file_name(LADDER_FILE);
+        let rungs = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                warn!(path = %path.display(), error = %e, "Ignoring corrupt ladder state");
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        self.state.get_mut().unwrap().rungs = rungs;
+        self.ladder = Some(Ladder {
+            percents,
+            path,
+            notifiers,
+        });
+        self
+    }
+
+    // The highest rung `symbol` newly reached at `price`, if any, marking
+    // it and every one below as fired.
+    fn climb(&self, state: &mut State, symbol: &str, price: f64) -> Option<Notification> {
+        let ladder = self.ladder.as_ref()?;
+        let holding = *state.holdings.get(symbol)?;
+        if holding.cost_basis <= 0. {
+            return None;
+        }
+        let gain = (price / holding.cost_basis - 1.) * 100.;
+        let rungs = state.rungs.entry(symbol.to_string()).or_default();
+        if rungs.cost_basis != holding.cost_basis {
+            *rungs = Rungs {
+                cost_basis: holding.cost_basis,
+                fired: vec![],
+            };
+        }
+        let reached: Vec<f64> = ladder
+            .percents
+            .iter()
+            .copied()
+            .filter(|p| gain >= *p && !rungs.fired.contains(p))
+            .collect();
+        let rung = *reached.last()?;
+        rungs.fired.extend(reached);
+        let held: BTreeSet<&String> = state.holdings.keys().collect();
+        state.rungs.retain(|symbol, _| held.contains(symbol));
+        if let Err(e) = save_json(&ladder.path, &state.rungs) {
+            error!(error = %e, "Failed to save ladder state");
+        }
+        info!(symbol, rung, gain, "Profit target reached");
+        let rule = format!("{} +{}%", symbol, rung);
+        Some(Notification {
+            title: format!("{} up {:.1}%", symbol, gain),
+            body: format!(
+                "{} trades at {}, {:.2}% over its {} cost basis and past the +{}% target",
+                symbol, price, gain, holding.cost_basis, rung
+            ),
+            urgency: Urgency::Normal,
+            kind: NotificationKind::Alert,
+            symbols: vec![symbol.to_string()],
+            rule: Some(rule),
+            value: Some(gain),
+            labels: BTreeMap::new(),
+        })
+    }
+
+    pub fn load(&self) -> Result<BTreeMap<String, Holding>, PortfolioError> {
+        match std::fs::read(&self.path) {
+            Ok(data) => Ok(serde_json::from_slice(&data)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, holdings: &BTreeMap<String, Holding>) -> Result<(), PortfolioError> {
+        save_json(&self.path, holdings)
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .ok()
+    }
+
+    // Rereads the file when it changed. A broken file keeps the holdings
+    // last read.
+    fn refresh(&self, state: &mut State) {
+        let modified = self.modified();
+        if state.modified == modified {
+            return;
+        }
+        state.modified = modified;
+        match self.load() {
+            Ok(holdings) => state.holdings = holdings,
+            Err(e) => warn!(error = %e, "Failed to reload portfolio"),
+        }
+    }
+
+    /// Adds the holding or replaces the one of `symbol`.
+    pub fn set(&self, symbol: &str, holding: Holding) -> Result<Holding, PortfolioError> {
+        if !holding.valid() {
+            return Err(PortfolioError::Invalid);
+        }
+        let mut state = self.state.lock().unwrap();
+        let mut holdings = self.load()?;
+        holdings.insert(symbol.to_string(), holding);
+        self.save(&holdings)?;
+        info!(symbol, quantity = holding.quantity, "Updated holding");
+        state.holdings = holdings;
+        state.modified = self.modified();
+        self.export(&mut state);
+        Ok(holding)
+    }
+
+    /// Adds `imported`, replacing the holdings of the symbols in it.
+    pub fn import(&self, imported: BTreeMap<String, Holding>) -> Result<(), PortfolioError> {
+        let mut state = self.state.lock().unwrap();
+        let mut holdings = self.load()?;
+        holdings.extend(imported);
+        self.save(&holdings)?;
+        state.holdings = holdings;
+        state.modified = self.modified();
+        self.export(&mut state);
+        Ok(())
+    }
+
+    pub fn remove(&self, symbol: &str) -> Result<(), PortfolioError> {
+        let mut state = self.state.lock().unwrap();
+        let mut holdings = self.load()?;
+        if holdings.remove(symbol).is_none() {
+            return Err(PortfolioError::NotFound(symbol.to_string()));
+        }
+        self.save(&holdings)?;
+        info!(symbol, "Removed holding");
+        state.holdings = holdings;
+        state.modified = self.modified();
+        self.export(&mut state);
+        Ok(())
+    }
+
+    pub fn valuation(&self) -> Valuation {
+        let mut state = self.state.lock().unwrap();
+        self.refresh(&mut state);
+        value(&state)
+    }
+
+    fn export(&self, state: &mut State) {
+        if !self.metrics {
+            return;
+        }
+        let valuation = value(state);
+        let held: BTreeSet<String> = state.holdings.keys().cloned().collect();
+        for symbol in state.exported.difference(&held) {
+            metrics::update_portfolio_position(symbol, None);
+        }
+        for position in &valuation.positions {
+            metrics::update_portfolio_position(
+                &position.symbol,
+                position.market_value.zip(position.unrealized_pnl),
+            );
+        }
+        metrics::update_portfolio_totals(valuation.total_value, valuation.unrealized_pnl);
+        state.exported = held;
+    }
+}
+
+fn value(state: &State) -> Valuation {
+    let positions: Vec<PositionValue> = state
+        .holdings
+        .iter()
+        .map(|(symbol, holding)| {
+            let last_price = state.prices.get(symbol).copied();
+            let market_value = last_price.map(|p| p * holding.quantity);
+            PositionValue {
+                symbol: symbol.clone(),
+                quantity: holding.quantity,
+                cost_basis: holding.cost_basis,
+                last_price,
+                market_value,
+                unrealized_pnl: market_value.map(|v| v - holding.cost_basis * holding.quantity),
+            }
+        })
+        .collect();
+    let priced = positions.iter().filter(|p| p.market_value.is_some());
+    Valuation {
+        total_value: priced.clone().filter_map(|p| p.market_value).sum(),
+        total_cost: priced.clone().map(|p| p.cost_basis * p.quantity).sum(),
+        unrealized_pnl: priced.filter_map(|p| p.unrealized_pnl).sum(),
+        positions,
+    }
+}
+
+fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<(), PortfolioError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(value)?)?;
+    recovery::backup(path)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+impl PortfolioTracker {
+    fn update(&self, update: &PriceUpdate, climb: bool) -> Option<Notification> {
+        let mut state = self.state.lock().unwrap();
+        self.refresh(&mut state);
+        state.prices.insert(update.symbol.clone(), update.price);
+        if !state.holdings.is_empty() || !state.exported.is_empty() {
+            self.export(&mut state);
+        }
+        if !climb {
+            return None;
+        }
+        self.climb(&mut state, &update.symbol, update.price)
+    }
+}
+
+impl Sink for PortfolioTracker {
+    fn record(&self, update: &PriceUpdate) {
+        let Some(notification) = self.update(update, true) else {
+            return;
+        };
+        let Some(ladder) = &self.ladder else {
+            return;
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                let notifiers = ladder.notifiers.clone();
+                handle.spawn(async move { notifiers.notify(&notification).await });
+            }
+            Err(_) => error!("No runtime to send alerts on"),
+        }
+    }
+
+    // Restored prices revalue the holdings without climbing the ladder.
+    fn replay(&self, update: &PriceUpdate) {
+        self.update(update, false);
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use super::Plan;
+use crate::metrics;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AdaptiveConfig {
+    pub enabled: bool,
+    /// Number of recent returns used to estimate volatility.
+    pub window: usize,
+    /// Most a volatile symbol's interval is divided by.
+    pub max_tighten: f64,
+    /// Most a quiet symbol's interval is multiplied by.
+    pub max_relax: f64,
+}
+
+impl Default for AdaptiveConfig {
+    fn default() -> Self {
+        AdaptiveConfig {
+            enabled: false,
+            window: 20,
+            max_tighten: 4.,
+            max_relax: 4.,
+        }
+    }
+}
+
+/// Scales planned intervals by each symbol's realized volatility relative to
+/// the watchlist median, then pulls them back in if that overshoots the budget.
+pub struct VolatilityTracker {
+    config: AdaptiveConfig,
+    prices: Mutex<HashMap<String, VecDeque<f64>>>,
+}
+
+impl VolatilityTracker {
+    pub fn new(config: AdaptiveConfig) -> Self {
+        VolatilityTracker {
+            config,
+            prices: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn observe(&self, symbol: &str, price: f64) {
+        if price <= 0. {
+            return;
+        }
+        let mut prices = self.prices.lock().unwrap();
+        let window = prices.entry(symbol.to_string()).or_default();
+        window.push_back(price);
+        while window.len() > self.config.window + 1 {
+            window.pop_front();
+        }
+    }
+
+    fn volatility(prices: &VecDeque<f64>) -> Option<f64> {
+        if prices.len() < 3 {
+            return None;
+        }
+        let returns: Vec<f64> = prices
+            .iter()
+            .zip(prices.iter().skip(1))
+            .map(|(a, b)| (b / a).ln())
+            .collect();
+        let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+        let variance =
+            returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+        Some(variance.sqrt())
+    }
+
+    pub fn adjust(&self, plan: &mut Plan, budget: f64) {
+        let prices = self.prices.lock().unwrap();
+        let volatility: HashMap<String, f64> = plan
+            .intervals
+            .keys()
+            .filter_map(|s| {
+                prices
+                    .get(s)
+                    .and_then(Self::volatility)
+                    .map(|v| (s.clone(), v))
+            })
+            .collect();
+        let mut sorted: Vec<f64> = volatility.values().copied().collect();
+        if sorted.is_empty() {
+            return;
+        }
+        sorted.sort_by(f64::total_cmp);
+        let median = sorted[sorted.len() / 2];
+        if median <= 0. {
+            return;
+        }
+
+        let demand = |intervals: &HashMap<String, f64>| {
+            intervals
+                .values()
+                .filter(|i| **i > 0.)
+                .map(|i| 1. / i)
+                .sum::<f64>()
+        };
+        let before = demand(&plan.intervals);
+
+        let mut adjusted = vec![];
+        for (symbol, interval) in plan.intervals.iter_mut() {
+            let Some(v) = volatility.get(symbol) else {
+                continue;
+            };
+            if *interval <= 0. {
+                continue;
+            }
+            let factor = (v / median).clamp(1. / self.config.max_relax, self.config.max_tighten);
+            *interval /= factor;
+            adjusted.push(symbol.clone());
+            metrics::update_adaptive_factor(symbol, factor);
+        }
+
+        let after = demand(&plan.intervals);
+        let limit = budget.max(before);
+        if after > limit {
+            let scale = after / limit;
+            for symbol in adjusted {
+                if let Some(interval) = plan.intervals.get_mut(&symbol) {
+                    *interval *= scale;
+                }
+            }
+        }
+    }
+}
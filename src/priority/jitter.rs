@@ -0,0 +1,41 @@
+use std::sync::Mutex;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct JitterConfig {
+    /// Largest relative change to a poll interval, e.g. 0.1 for ±10%.
+    pub fraction: f64,
+    /// Same seed, same poll times: runs and simulations stay reproducible.
+    pub seed: u64,
+}
+
+/// Spreads poll times so symbols don't all fire on the same beat as the
+/// scraper.
+pub struct Jitter {
+    fraction: f64,
+    rng: Mutex<StdRng>,
+}
+
+impl Jitter {
+    pub fn new(config: &JitterConfig) -> Self {
+        Jitter {
+            fraction: config.fraction.clamp(0., 1.),
+            rng: Mutex::new(StdRng::seed_from_u64(config.seed)),
+        }
+    }
+
+    /// A factor offset in `[-fraction, fraction]`.
+    pub fn sample(&self) -> f64 {
+        if self.fraction == 0. {
+            return 0.;
+        }
+        self.rng
+            .lock()
+            .unwrap()
+            .gen_range(-self.fraction..=self.fraction)
+    }
+}
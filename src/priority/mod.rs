@@ -0,0 +1,182 @@
+pub mod adaptive;
+pub mod jitter;
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument};
+
+use crate::config::RateLimit;
+use crate::metrics;
+use crate::Tickers;
+
+// Cap so starved symbols are still polled occasionally rather than never.
+const MAX_STRETCH: f64 = 100.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Critical,
+    Normal,
+    Low,
+}
+
+impl Priority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Critical => "critical",
+            Priority::Normal => "normal",
+            Priority::Low => "low",
+        }
+    }
+}
+
+/// What to do when one cycle over every symbol can't finish within
+/// `max_cycle_secs` at the rate budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverBudget {
+    /// Fetch what fits each cycle, most urgent and longest waiting first,
+    /// so the rest wait a cycle or more.
+    #[default]
+    Stretch,
+    /// Refuse to start.
+    Fail,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PriorityConfig {
+    /// Desired poll interval for every symbol without its own in the tickers
+    /// file, 0 polls everything each cycle.
+    pub interval_secs: u64,
+    pub critical: Vec<String>,
+    pub low: Vec<String>,
+    /// Longest a poll cycle may spend fetching, 0 for no limit.
+    pub max_cycle_secs: u64,
+    pub over_budget: OverBudget,
+}
+
+impl PriorityConfig {
+    pub fn of(&self, symbol: &str) -> Priority {
+        if self.critical.iter().any(|s| s == symbol) {
+            Priority::Critical
+        } else if self.low.iter().any(|s| s == symbol) {
+            Priority::Low
+        } else {
+            Priority::Normal
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Plan {
+    pub stretch: BTreeMap<Priority, f64>,
+    pub intervals: HashMap<String, f64>,
+}
+
+/// Requests per second allowed by the strictest limit.
+pub fn budget(limits: &[RateLimit]) -> f64 {
+    limits
+        .iter()
+        .filter(|l| l.period_secs > 0)
+        .map(|l| l.requests as f64 / l.period_secs as f64)
+        .fold(f64::INFINITY, f64::min)
+}
+
+/// How a cycle fetching every symbol at once, as the first does, fits
+/// `max_cycle_secs` at `budget` requests a second.
+#[derive(Debug, Clone, Serialize)]
+pub struct CycleBudget {
+    pub symbols: usize,
+    /// Seconds the rate limits stretch such a cycle to.
+    pub needed_secs: f64,
+    pub max_secs: f64,
+    /// Symbols one cycle fetches.
+    pub per_cycle: usize,
+    /// Cycles it takes to get around to every symbol.
+    pub cycles: usize,
+}
+
+impl CycleBudget {
+    pub fn exceeded(&self) -> bool {
+        self.per_cycle < self.symbols
+    }
+}
+
+/// `None` without a `max_cycle_secs`.
+pub fn cycle_budget(symbols: usize, config: &PriorityConfig, budget: f64) -> Option<CycleBudget> {
+    if config.max_cycle_secs == 0 {
+        return None;
+    }
+    let max_secs = config.max_cycle_secs as f64;
+    let (needed_secs, per_cycle) = if budget.is_finite() && budget > 0. {
+        let fits = (max_secs * budget).floor() as usize;
+        (symbols as f64 / budget, fits.clamp(1, symbols.max(1)))
+    } else {
+        (0., symbols)
+    };
+    Some(CycleBudget {
+        symbols,
+        needed_secs,
+        max_secs,
+        per_cycle,
+        cycles: symbols.div_ceil(per_cycle.max(1)),
+    })
+}
+
+/// Hands the request budget out class by class. Critical symbols always keep
+/// their interval, lower classes share what is left and stretch to fit. A
+/// symbol's own interval from the tickers file wins over the configured one.
+#[instrument(skip(tickers, config))]
+pub fn plan(tickers: &Tickers, config: &PriorityConfig, budget: f64) -> Plan {
+    let mut plan = Plan::default();
+    let interval = |symbol: &str| {
+        tickers
+            .interval_secs(symbol)
+            .unwrap_or(config.interval_secs) as f64
+    };
+    let mut remaining = budget;
+
+    for class in [Priority::Critical, Priority::Normal, Priority::Low] {
+        let members: Vec<&String> = tickers
+            .get_tickers()
+            .iter()
+            .filter(|s| config.of(s) == class)
+            .collect();
+        // Symbols polled every cycle don't count against the budget.
+        let demand: f64 = members
+            .iter()
+            .map(|s| interval(s))
+            .filter(|i| *i > 0.)
+            .map(|i| 1. / i)
+            .sum();
+        let stretch = if demand == 0. {
+            1.
+        } else {
+            if class == Priority::Critical || remaining >= demand {
+                remaining -= demand;
+                1.
+            } else if remaining > 0. {
+                let stretch = (demand / remaining).min(MAX_STRETCH);
+                remaining = 0.;
+                stretch
+            } else {
+                MAX_STRETCH
+            }
+        };
+        for symbol in members {
+            plan.intervals
+                .insert(symbol.clone(), interval(symbol) * stretch);
+        }
+        plan.stretch.insert(class, stretch);
+        metrics::update_stretch_factor(class.as_str(), stretch);
+        if stretch > 1. {
+            info!(
+                priority = class.as_str(),
+                stretch, "Rate budget exceeded, stretching poll interval"
+            );
+        }
+    }
+    plan
+}
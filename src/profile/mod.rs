@@ -0,0 +1,289 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::calendar;
+use crate::clock::Clock;
+use crate::metrics;
+use crate::provider::{base_url, TWELVEDATA_URL};
+use crate::ratelimit::RateLimiter;
+use crate::read_tickers;
+use crate::symbol::SymbolInfo;
+use crate::timeseries::{fetch_candles, Candle};
+use crate::StockMarket;
+
+// A day of minute candles, so one request covers the session at any
+// interval.
+const SESSION_CANDLES: u32 = 1440;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ProfileConfig {
+    /// Builds each symbol's volume by price over the current session from
+    /// intraday candles, one Twelve Data request per symbol and refresh.
+    pub enabled: bool,
+    /// Candle size, e.g. `1min` or `5min`. Smaller places volume more
+    /// exactly for more of the response.
+    pub interval: String,
+    /// Price levels the session's range is divided into.
+    pub buckets: usize,
+    /// Share of the session's volume the value area holds, in percent.
+    pub value_area_percent: f64,
+    /// How often profiles are rebuilt while their market is open.
+    pub refresh_secs: u64,
+    /// Also export the point of control and value area as gauges.
+    pub metrics: bool,
+}
+
+impl Default for ProfileConfig {
+    fn default() -> Self {
+        ProfileConfig {
+            enabled: false,
+            interval: "5min".into(),
+            buckets: 50,
+            value_area_percent: 70.,
+            refresh_secs: 300,
+            metrics: false,
+        }
+    }
+}
+
+/// Volume traded between two prices.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Level {
+    pub low: f64,
+    pub high: f64,
+    pub volume: f64,
+}
+
+/// Where a symbol's volume traded in one session.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct VolumeProfile {
+    pub symbol: String,
+    /// The session's date where it trades.
+    pub session: NaiveDate,
+    pub computed_at: DateTime<Utc>,
+    pub candles: usize,
+    pub volume: f64,
+    /// Middle of the level that traded the most.
+    pub point_of_control: f64,
+    /// Top and bottom of the levels around the point of control holding
+    /// the configured share of the volume.
+    pub value_area_high: f64,
+    pub value_area_low: f64,
+    /// Lowest first.
+    pub levels: Vec<Level>,
+}
+
+/// The profile of the last session in `candles`, oldest first, its days
+/// cut where `session` trades. Each candle's volume is spread evenly over
+/// its range. `None` without volume, as for forex.
+pub fn build(
+    symbol: &str,
+    candles: &[Candle],
+    session: &calendar::Session,
+    config: &ProfileConfig,
+    now: DateTime<Utc>,
+) -> Option<VolumeProfile> {
+    let day = |c: &Candle| c.timestamp.with_timezone(&session.tz).date_naive();
+    let last = day(candles.last()?);
+    let candles: Vec<&Candle> = candles.iter().filter(|c| day(c) == last).collect();
+    let volume: f64 = candles.iter().map(|c| c.volume).sum();
+    if volume.is_nan() || volume <= 0. {
+        return None;
+    }
+    let low = candles.iter().map(|c| c.low).fold(f64::INFINITY, f64::min);
+    let high = candles
+        .iter()
+        .map(|c| c.high)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let buckets = if high > low { config.buckets.max(1) } else { 1 };
+    let size = (high - low) / buckets as f64;
+    let index = |price: f64| {
+        if size > 0. {
+            (((price - low) / size) as usize).min(buckets - 1)
+        } else {
+            0
+        }
+    };
+
+    let mut volumes = vec![0.; buckets];
+    for candle in &candles {
+        let range = candle.high - candle.low;
+        if range <= 0. || size <= 0. {
+            volumes[index(candle.close)] += candle.volume;
+            continue;
+        }
+        for (i, bucket) in volumes
+            .iter_mut()
+            .enumerate()
+            .take(index(candle.high) + 1)
+            .skip(index(candle.low))
+        {
+            let from = low + size * i as f64;
+            let overlap = (from + size).min(candle.high) - from.max(candle.low);
+            *bucket += candle.volume * overlap.max(0.) / range;
+        }
+    }
+
+    // Grown from the point of control a level at a time, toward whichever
+    // neighbour traded more.
+    let poc = (0..buckets)
+        .max_by(|&a, &b| volumes[a].total_cmp(&volumes[b]).then(b.cmp(&a)))
+        .unwrap_or(0);
+    let target = volume * config.value_area_percent.clamp(0., 100.) / 100.;
+    let (mut below, mut above, mut area) = (poc, poc, volumes[poc]);
+    while area < target && (below > 0 || above + 1 < buckets) {
+        let down = below.checked_sub(1).map(|i| volumes[i]);
+        let up = (above + 1 < buckets).then(|| volumes[above + 1]);
+        if up.unwrap_or(-1.) >= down.unwrap_or(-1.) {
+            above += 1;
+            area += volumes[above];
+        } else {
+            below -= 1;
+            area += volumes[below];
+        }
+    }
+
+    let edge = |i: usize| low + size * i as f64;
+    Some(VolumeProfile {
+        symbol: symbol.to_string(),
+        session: last,
+        computed_at: now,
+        candles: candles.len(),
+        volume,
+        point_of_control: edge(poc) + size / 2.,
+        value_area_high: edge(above + 1),
+        value_area_low: edge(below),
+        levels: volumes
+            .iter()
+            .enumerate()
+            .map(|(i, &volume)| Level {
+                low: edge(i),
+                high: edge(i + 1),
+                volume,
+            })
+            .collect(),
+    })
+}
+
+/// Every watched symbol's volume profile for its current or last session,
+/// rebuilt while its market is open.
+pub struct VolumeProfiles {
+    config: ProfileConfig,
+    exchange: StockMarket,
+    base_url: String,
+    profiles: RwLock<BTreeMap<String, VolumeProfile>>,
+}
+
+impl VolumeProfiles {
+    /// Symbols not listed on an exchange of their own are timed by
+    /// `exchange`'s session.
+    pub fn new(config: &ProfileConfig, exchange: StockMarket) -> Self {
+        VolumeProfiles {
+            config: config.clone(),
+            exchange,
+            base_url: TWELVEDATA_URL.into(),
+            profiles: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn with_base_url(mut self, url: &str) -> Self {
+        self.base_url = base_url(url);
+        self
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<VolumeProfile> {
+        self.profiles.read().unwrap().get(symbol).cloned()
+    }
+
+    pub fn all(&self) -> Vec<VolumeProfile> {
+        self.profiles.read().unwrap().values().cloned().collect()
+    }
+
+    fn session(&self, symbol: &str) -> calendar::Session {
+        calendar::session(SymbolInfo::parse(symbol).exchange.unwrap_or(self.exchange))
+    }
+
+    /// Rebuilds the profiles of `symbols` whose market is open or that
+    /// have none yet, and drops those of symbols no longer watched.
+    pub async fn refresh(
+        &self,
+        symbols: &[String],
+        api_key: &str,
+        limiter: &RateLimiter,
+        now: DateTime<Utc>,
+    ) {
+        let mut built = 0;
+        for symbol in symbols {
+            let session = self.session(symbol);
+            if self.get(symbol).is_some() && !session.is_open(now) {
+                continue;
+            }
+            limiter.acquire(1).await;
+            let candles = match fetch_candles(
+                &self.base_url,
+                symbol,
+                &self.config.interval,
+                SESSION_CANDLES,
+                api_key,
+            )
+            .await
+            {
+                Ok(candles) => candles,
+                Err(e) => {
+                    warn!(symbol, error = %e, "Failed to fetch candles for the volume profile");
+                    continue;
+                }
+            };
+            let Some(profile) = build(symbol, &candles, &session, &self.config, now) else {
+                continue;
+            };
+            if self.config.metrics {
+                metrics::update_volume_profile(&profile);
+            }
+            self.profiles
+                .write()
+                .unwrap()
+                .insert(symbol.clone(), profile);
+            built += 1;
+        }
+        let mut profiles = self.profiles.write().unwrap();
+        let gone: Vec<String> = profiles
+            .keys()
+            .filter(|symbol| !symbols.contains(symbol))
+            .cloned()
+            .collect();
+        for symbol in &gone {
+            profiles.remove(symbol);
+            metrics::remove_volume_profile(symbol);
+        }
+        if built > 0 || !gone.is_empty() {
+            info!(built, removed = gone.len(), "Refreshed volume profiles");
+        }
+    }
+
+    pub async fn run(
+        self: Arc<Self>,
+        api_key: String,
+        limiter: Arc<RateLimiter>,
+        clock: Arc<dyn Clock>,
+    ) {
+        loop {
+            match read_tickers().await {
+                Ok(tickers) => {
+                    self.refresh(tickers.get_tickers(), &api_key, &limiter, clock.now())
+                        .await
+                }
+                Err(e) => warn!(error = %e, "Failed to read tickers, skipping volume profiles"),
+            }
+            clock
+                .sleep(Duration::from_secs(self.config.refresh_secs.max(1)))
+                .await;
+        }
+    }
+}
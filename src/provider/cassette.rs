@@ -0,0 +1,190 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Error;
+use serde::{Deserialize, Serialize};
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tracing::{error, info, warn};
+
+use super::{Provider, Quote, SymbolQuote};
+use crate::Markets;
+
+/// One provider call and its outcome, stored one per line in a cassette file.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "call", rename_all = "snake_case")]
+pub enum Interaction {
+    Price {
+        symbol: String,
+        price: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timestamp: Option<DateTime<Utc>>,
+    },
+    MarketState {
+        market: String,
+        seconds_until_open: u64,
+    },
+}
+
+impl Interaction {
+    fn key(&self) -> (&'static str, &str) {
+        match self {
+            Interaction::Price { symbol, .. } => ("price", symbol),
+            Interaction::MarketState { market, .. } => ("market_state", market),
+        }
+    }
+}
+
+/// Passes calls through to `inner` and appends every successful response to a cassette.
+pub struct RecordingProvider<P> {
+    inner: P,
+    cassette: tokio::sync::Mutex<File>,
+}
+
+impl<P: Provider> RecordingProvider<P> {
+    pub async fn new(inner: P, path: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        info!(path = %path.display(), provider = inner.name(), "Recording provider traffic");
+        Ok(RecordingProvider {
+            inner,
+            cassette: tokio::sync::Mutex::new(file),
+        })
+    }
+
+    async fn record(&self, interaction: Interaction) {
+        let mut line =
+            serde_json::to_string(&interaction).expect("Failed to serialize interaction");
+        line.push('\n');
+        if let Err(e) = self.cassette.lock().await.write_all(line.as_bytes()).await {
+            error!(error = %e, "Failed to write cassette");
+        }
+    }
+}
+
+#[async_trait]
+impl<P: Provider> Provider for RecordingProvider<P> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn fetch_price(&self, symbol: &str) -> Result<Option<Quote>, Error> {
+        let quote = self.inner.fetch_price(symbol).await?;
+        self.record(Interaction::Price {
+            symbol: symbol.to_string(),
+            price: quote.map(|q| q.price),
+            timestamp: quote.and_then(|q| q.timestamp),
+        })
+        .await;
+        Ok(quote)
+    }
+
+    fn batch_size(&self) -> usize {
+        self.inner.batch_size()
+    }
+
+    // Batches are taped per symbol so a replay can serve any batch size.
+    // Failed symbols are left off the tape, as a failed call is.
+    async fn fetch_prices(&self, symbols: &[String]) -> Result<Vec<SymbolQuote>, Error> {
+        let quotes = self.inner.fetch_prices(symbols).await?;
+        for (symbol, quote) in symbols.iter().zip(&quotes) {
+            let Ok(quote) = quote else { continue };
+            self.record(Interaction::Price {
+                symbol: symbol.clone(),
+                price: quote.map(|q| q.price),
+                timestamp: quote.and_then(|q| q.timestamp),
+            })
+            .await;
+        }
+        Ok(quotes)
+    }
+
+    async fn fetch_venue_price(&self, symbol: &str, venue: &str) -> Result<Option<Quote>, Error> {
+        self.inner.fetch_venue_price(symbol, venue).await
+    }
+
+    async fn fetch_market_state(&self, market: &Markets) -> Result<u64, Error> {
+        let seconds_until_open = self.inner.fetch_market_state(market).await?;
+        self.record(Interaction::MarketState {
+            market: market.to_string(),
+            seconds_until_open,
+        })
+        .await;
+        Ok(seconds_until_open)
+    }
+}
+
+/// Serves a cassette back in recorded order per call and argument. Once the
+/// recorded responses for a key run out the last one keeps being returned.
+pub struct ReplayProvider {
+    tapes: Mutex<HashMap<(&'static str, String), VecDeque<Interaction>>>,
+}
+
+impl ReplayProvider {
+    pub async fn load(path: &Path) -> std::io::Result<Self> {
+        let data = fs::read_to_string(path).await?;
+        let mut tapes: HashMap<(&'static str, String), VecDeque<Interaction>> = HashMap::new();
+        for (i, line) in data.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<Interaction>(line) {
+                Ok(interaction) => {
+                    let (call, key) = interaction.key();
+                    let key = (call, key.to_string());
+                    tapes.entry(key).or_default().push_back(interaction);
+                }
+                Err(e) => warn!(line = i + 1, error = %e, "Skipping bad cassette entry"),
+            }
+        }
+        info!(path = %path.display(), keys = tapes.len(), "Replaying provider traffic");
+        Ok(ReplayProvider {
+            tapes: Mutex::new(tapes),
+        })
+    }
+
+    fn next(&self, call: &'static str, key: &str) -> Option<Interaction> {
+        let mut tapes = self.tapes.lock().unwrap();
+        let tape = tapes.get_mut(&(call, key.to_string()))?;
+        if tape.len() > 1 {
+            tape.pop_front()
+        } else {
+            tape.front().cloned()
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for ReplayProvider {
+    fn name(&self) -> &str {
+        "replay"
+    }
+
+    async fn fetch_price(&self, symbol: &str) -> Result<Option<Quote>, Error> {
+        match self.next("price", symbol) {
+            Some(Interaction::Price {
+                price, timestamp, ..
+            }) => Ok(price.map(|price| Quote {
+                price,
+                timestamp,
+                day: None,
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    async fn fetch_market_state(&self, market: &Markets) -> Result<u64, Error> {
+        match self.next("market_state", &market.to_string()) {
+            Some(Interaction::MarketState {
+                seconds_until_open, ..
+            }) => Ok(seconds_until_open),
+            _ => Ok(0),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use serde_json::Value;
+
+use super::endpoints::{
+    self, BatchPrice, DailyQuote, Dividends, Earnings, Endpoint, KeyStatistics, MarketStates,
+    OptionChain, OptionExpirations, Price, PriceTargets, Recommendations, Splits, SymbolSearch,
+    TimeSeries,
+};
+
+/// How a live response lines up with the typed shape fintek reads it into.
+#[derive(Debug, Clone, Default)]
+pub struct ContractReport {
+    pub endpoint: &'static str,
+    /// The request failed, the provider returned an error, or the body
+    /// didn't deserialize into the typed response (naming the field).
+    pub error: Option<String>,
+    /// Live fields nothing reads. Informational: new upstream fields are harmless.
+    pub unmapped: Vec<String>,
+}
+
+impl ContractReport {
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+impl fmt::Display for ContractReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = if self.passed() { "ok" } else { "FAILED" };
+        writeln!(f, "{} {}", self.endpoint, verdict)?;
+        if let Some(error) = &self.error {
+            writeln!(f, "  error: {}", error)?;
+        }
+        for field in &self.unmapped {
+            writeln!(f, "  + {}", field)?;
+        }
+        Ok(())
+    }
+}
+
+// Dotted paths of every non-null field, with `[]` standing for array elements.
+fn paths(value: &Value, prefix: &str, out: &mut BTreeSet<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                if value.is_null() {
+                    continue;
+                }
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                out.insert(path.clone());
+                paths(value, &path, out);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                paths(item, &format!("{}[]", prefix), out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Fetches the endpoint's sample query and diffs the live body against its typed response.
+pub async fn check<E: Endpoint>(base_url: &str, api_key: &str) -> ContractReport {
+    let mut report = ContractReport {
+        endpoint: E::PATH,
+        ..Default::default()
+    };
+    let url = endpoints::url::<E>(base_url, E::SAMPLE, api_key);
+    let data = match reqwest::get(&url).await {
+        Ok(response) => match response.text().await {
+            Ok(data) => data,
+            Err(e) => {
+                report.error = Some(e.to_string());
+                return report;
+            }
+        },
+        Err(e) => {
+            report.error = Some(e.to_string());
+            return report;
+        }
+    };
+    let typed = match endpoints::parse::<E::Response>(&data) {
+        Ok(Ok(typed)) => typed,
+        Ok(Err(api)) => {
+            report.error = Some(format!("provider error {}: {}", api.code, api.message));
+            return report;
+        }
+        Err(e) => {
+            report.error = Some(format!("doesn't deserialize: {}", e));
+            return report;
+        }
+    };
+
+    let live: Value = serde_json::from_str(&data).unwrap_or_default();
+    let expected = serde_json::to_value(&typed).unwrap_or_default();
+    let (mut live_paths, mut expected_paths) = (BTreeSet::new(), BTreeSet::new());
+    paths(&live, "", &mut live_paths);
+    paths(&expected, "", &mut expected_paths);
+    report.unmapped = live_paths.difference(&expected_paths).cloned().collect();
+    report
+}
+
+/// Every typed Twelve Data endpoint, in the order fintek depends on them.
+pub async fn check_all(base_url: &str, api_key: &str) -> Vec<ContractReport> {
+    vec![
+        check::<Price>(base_url, api_key).await,
+        check::<BatchPrice>(base_url, api_key).await,
+        check::<DailyQuote>(base_url, api_key).await,
+        check::<MarketStates>(base_url, api_key).await,
+        check::<Dividends>(base_url, api_key).await,
+        check::<Earnings>(base_url, api_key).await,
+        check::<Splits>(base_url, api_key).await,
+        check::<TimeSeries>(base_url, api_key).await,
+        check::<SymbolSearch>(base_url, api_key).await,
+        check::<KeyStatistics>(base_url, api_key).await,
+        check::<OptionExpirations>(base_url, api_key).await,
+        check::<OptionChain>(base_url, api_key).await,
+        check::<PriceTargets>(base_url, api_key).await,
+        check::<Recommendations>(base_url, api_key).await,
+    ]
+}
@@ -0,0 +1,447 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use super::number;
+
+/// A Twelve Data endpoint and the shape its successful response is read into.
+/// Every fetch goes through these types, so a field rename upstream shows up
+/// as one failing contract check rather than a silent `None` somewhere.
+pub trait Endpoint {
+    const PATH: &'static str;
+    /// Query used by the contract checks, against the free demo symbols.
+    const SAMPLE: &'static str;
+    type Response: DeserializeOwned + Serialize;
+}
+
+/// What Twelve Data sends instead of the payload on failure.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ApiError {
+    pub code: u16,
+    pub message: String,
+    pub status: String,
+}
+
+impl ApiError {
+    // 401 is a bad or revoked key, 429 an exhausted one.
+    pub fn key_error(&self) -> Option<&'static str> {
+        match self.code {
+            401 | 403 => Some("unauthorized"),
+            429 => Some("out of credits"),
+            _ => None,
+        }
+    }
+}
+
+/// A response body: the error object when `status` says so, else the payload.
+pub fn parse<T: DeserializeOwned>(data: &str) -> Result<Result<T, ApiError>, serde_json::Error> {
+    if let Ok(error) = serde_json::from_str::<ApiError>(data) {
+        if error.status == "error" {
+            return Ok(Err(error));
+        }
+    }
+    serde_json::from_str(data).map(Ok)
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PriceResponse {
+    #[serde(deserialize_with = "number::deserialize")]
+    pub price: f64,
+}
+
+/// The day so far from `/quote`, `close` being the latest price.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct QuoteResponse {
+    #[serde(deserialize_with = "number::deserialize")]
+    pub open: f64,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub high: f64,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub low: f64,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub close: f64,
+    /// Missing for currencies.
+    #[serde(default, deserialize_with = "number::deserialize_optional")]
+    pub volume: Option<f64>,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub previous_close: f64,
+    /// Trading day of the quote, `YYYY-MM-DD`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub datetime: Option<String>,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub percent_change: f64,
+    /// Only for futures.
+    #[serde(default, deserialize_with = "number::deserialize_optional")]
+    pub open_interest: Option<f64>,
+}
+
+/// One symbol of a multi-symbol `/price` response, which can fail on its own.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum BatchPriceEntry {
+    Price(PriceResponse),
+    Error(ApiError),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MarketState {
+    pub is_market_open: bool,
+    /// `HH:MM:SS`.
+    pub time_to_open: String,
+    /// `HH:MM:SS`, short on half days.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_to_close: Option<String>,
+}
+
+fn seconds(hms: &str) -> u64 {
+    hms.split(':')
+        .map(|part| part.parse::<u64>().unwrap_or_default())
+        .fold(0, |total, part| total * 60 + part)
+}
+
+impl MarketState {
+    pub fn seconds_to_open(&self) -> u64 {
+        seconds(&self.time_to_open)
+    }
+
+    pub fn seconds_to_close(&self) -> Option<u64> {
+        self.time_to_close.as_deref().map(seconds)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DividendRecord {
+    pub ex_date: NaiveDate,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DividendsResponse {
+    pub dividends: Vec<DividendRecord>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EarningsRecord {
+    pub date: NaiveDate,
+    /// "Before Open", "After Hours" and so on.
+    pub time: Option<String>,
+    #[serde(default, deserialize_with = "number::deserialize_optional")]
+    pub eps_estimate: Option<f64>,
+    /// Set once reported.
+    #[serde(default, deserialize_with = "number::deserialize_optional")]
+    pub eps_actual: Option<f64>,
+    /// Only from providers that report revenue with earnings.
+    #[serde(default, deserialize_with = "number::deserialize_optional")]
+    pub revenue_estimate: Option<f64>,
+    #[serde(default, deserialize_with = "number::deserialize_optional")]
+    pub revenue_actual: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EarningsResponse {
+    pub earnings: Vec<EarningsRecord>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SplitRecord {
+    pub date: NaiveDate,
+    /// A 4-for-1 split has `from_factor` 4 and `to_factor` 1; earlier prices
+    /// scale by `to_factor / from_factor`.
+    #[serde(deserialize_with = "number::deserialize")]
+    pub from_factor: f64,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub to_factor: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SplitsResponse {
+    pub splits: Vec<SplitRecord>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TimeSeriesMeta {
+    /// Zone the candle datetimes are written in, absent for crypto.
+    pub exchange_timezone: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TimeSeriesValue {
+    /// `YYYY-MM-DD` for daily and longer intervals, else with `HH:MM:SS`.
+    pub datetime: String,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub open: f64,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub high: f64,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub low: f64,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub close: f64,
+    /// Not sent for forex.
+    #[serde(default, deserialize_with = "number::deserialize")]
+    pub volume: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TimeSeriesResponse {
+    pub meta: TimeSeriesMeta,
+    /// Newest first.
+    pub values: Vec<TimeSeriesValue>,
+}
+
+/// One instrument matching a `/symbol_search` query.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SymbolMatch {
+    pub symbol: String,
+    pub instrument_name: String,
+    /// Twelve Data's exchange name, such as `NASDAQ` or `LSE`.
+    pub exchange: String,
+    /// ISO 10383 code of the exchange.
+    pub mic_code: String,
+    /// "Common Stock", "ETF" and so on.
+    pub instrument_type: String,
+    #[serde(default)]
+    pub currency: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SymbolSearchResponse {
+    /// Best match first.
+    pub data: Vec<SymbolMatch>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ValuationMetrics {
+    #[serde(default, deserialize_with = "number::deserialize_optional")]
+    pub market_capitalization: Option<f64>,
+    #[serde(default, deserialize_with = "number::deserialize_optional")]
+    pub trailing_pe: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct IncomeStatement {
+    #[serde(default, deserialize_with = "number::deserialize_optional")]
+    pub diluted_eps_ttm: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Financials {
+    #[serde(default)]
+    pub income_statement: IncomeStatement,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PriceSummary {
+    #[serde(default, deserialize_with = "number::deserialize_optional")]
+    pub beta: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DividendsAndSplits {
+    #[serde(default, deserialize_with = "number::deserialize_optional")]
+    pub forward_annual_dividend_yield: Option<f64>,
+}
+
+/// The parts of `/statistics` fintek keeps, everything optional as
+/// coverage varies by listing.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Statistics {
+    #[serde(default)]
+    pub valuations_metrics: ValuationMetrics,
+    #[serde(default)]
+    pub financials: Financials,
+    #[serde(default)]
+    pub stock_price_summary: PriceSummary,
+    #[serde(default)]
+    pub dividends_and_splits: DividendsAndSplits,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StatisticsResponse {
+    pub statistics: Statistics,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PriceTarget {
+    #[serde(default, deserialize_with = "number::deserialize_optional")]
+    pub high: Option<f64>,
+    #[serde(default, deserialize_with = "number::deserialize_optional")]
+    pub median: Option<f64>,
+    #[serde(default, deserialize_with = "number::deserialize_optional")]
+    pub low: Option<f64>,
+    #[serde(default, deserialize_with = "number::deserialize_optional")]
+    pub average: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PriceTargetResponse {
+    pub price_target: PriceTarget,
+}
+
+/// Analysts per rating in one month.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+pub struct RatingCounts {
+    #[serde(default)]
+    pub strong_buy: u32,
+    #[serde(default)]
+    pub buy: u32,
+    #[serde(default)]
+    pub hold: u32,
+    #[serde(default)]
+    pub sell: u32,
+    #[serde(default)]
+    pub strong_sell: u32,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RecommendationTrends {
+    #[serde(default)]
+    pub current_month: RatingCounts,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RecommendationsResponse {
+    #[serde(default)]
+    pub trends: RecommendationTrends,
+    /// From 0 for strong sell to 10 for strong buy.
+    #[serde(default, deserialize_with = "number::deserialize_optional")]
+    pub rating: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OptionExpirationsResponse {
+    /// `YYYY-MM-DD`, nearest first.
+    pub dates: Vec<String>,
+}
+
+/// One contract of an options chain.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OptionQuote {
+    pub contract_name: String,
+    #[serde(deserialize_with = "number::deserialize")]
+    pub strike: f64,
+    #[serde(default, deserialize_with = "number::deserialize_optional")]
+    pub last_price: Option<f64>,
+    #[serde(default, deserialize_with = "number::deserialize_optional")]
+    pub open_interest: Option<f64>,
+    /// As a fraction, 0.25 for 25%.
+    #[serde(default, deserialize_with = "number::deserialize_optional")]
+    pub implied_volatility: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OptionChainResponse {
+    #[serde(default)]
+    pub calls: Vec<OptionQuote>,
+    #[serde(default)]
+    pub puts: Vec<OptionQuote>,
+}
+
+pub struct Price;
+pub struct BatchPrice;
+pub struct DailyQuote;
+pub struct MarketStates;
+pub struct Dividends;
+pub struct Earnings;
+pub struct Splits;
+pub struct TimeSeries;
+pub struct SymbolSearch;
+pub struct KeyStatistics;
+pub struct OptionExpirations;
+pub struct OptionChain;
+pub struct PriceTargets;
+pub struct Recommendations;
+
+impl Endpoint for Price {
+    const PATH: &'static str = "/price";
+    const SAMPLE: &'static str = "symbol=AAPL";
+    type Response = PriceResponse;
+}
+
+/// `/price` with comma-separated symbols answers with an object keyed by
+/// symbol. A lone symbol gets the plain [`Price`] shape.
+impl Endpoint for BatchPrice {
+    const PATH: &'static str = "/price";
+    const SAMPLE: &'static str = "symbol=AAPL,EUR/USD";
+    type Response = BTreeMap<String, BatchPriceEntry>;
+}
+
+impl Endpoint for DailyQuote {
+    const PATH: &'static str = "/quote";
+    const SAMPLE: &'static str = "symbol=AAPL";
+    type Response = QuoteResponse;
+}
+
+impl Endpoint for MarketStates {
+    const PATH: &'static str = "/market_state";
+    const SAMPLE: &'static str = "exchange=NYSE";
+    type Response = Vec<MarketState>;
+}
+
+impl Endpoint for Dividends {
+    const PATH: &'static str = "/dividends";
+    const SAMPLE: &'static str = "symbol=AAPL";
+    type Response = DividendsResponse;
+}
+
+impl Endpoint for Earnings {
+    const PATH: &'static str = "/earnings";
+    const SAMPLE: &'static str = "symbol=AAPL";
+    type Response = EarningsResponse;
+}
+
+impl Endpoint for Splits {
+    const PATH: &'static str = "/splits";
+    const SAMPLE: &'static str = "symbol=AAPL";
+    type Response = SplitsResponse;
+}
+
+impl Endpoint for TimeSeries {
+    const PATH: &'static str = "/time_series";
+    const SAMPLE: &'static str = "symbol=AAPL&interval=1day&outputsize=5";
+    type Response = TimeSeriesResponse;
+}
+
+impl Endpoint for SymbolSearch {
+    const PATH: &'static str = "/symbol_search";
+    const SAMPLE: &'static str = "symbol=AAPL";
+    type Response = SymbolSearchResponse;
+}
+
+impl Endpoint for KeyStatistics {
+    const PATH: &'static str = "/statistics";
+    const SAMPLE: &'static str = "symbol=AAPL";
+    type Response = StatisticsResponse;
+}
+
+impl Endpoint for OptionExpirations {
+    const PATH: &'static str = "/options/expiration";
+    const SAMPLE: &'static str = "symbol=AAPL";
+    type Response = OptionExpirationsResponse;
+}
+
+impl Endpoint for PriceTargets {
+    const PATH: &'static str = "/price_target";
+    const SAMPLE: &'static str = "symbol=AAPL";
+    type Response = PriceTargetResponse;
+}
+
+impl Endpoint for Recommendations {
+    const PATH: &'static str = "/recommendations";
+    const SAMPLE: &'static str = "symbol=AAPL";
+    type Response = RecommendationsResponse;
+}
+
+/// The nearest expiration unless `expiration_date` is given.
+impl Endpoint for OptionChain {
+    const PATH: &'static str = "/options/chain";
+    const SAMPLE: &'static str = "symbol=AAPL";
+    type Response = OptionChainResponse;
+}
+
+/// `{base}{PATH}?{query}&apikey={key}`.
+pub fn url<E: Endpoint>(base_url: &str, query: &str, api_key: &str) -> String {
+    format!("{}{}?{}&apikey={}", base_url, E::PATH, query, api_key)
+}
@@ -0,0 +1,217 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use reqwest::Error;
+use serde::Deserialize;
+use tracing::{info, instrument, trace, warn};
+
+use std::sync::Arc;
+
+use super::retry::check_status;
+use super::{number, DayQuote, Provider, Quote, Regions, FINNHUB_URL};
+use crate::symbol::SymbolInfo;
+use crate::{calendar, AssetClass, Markets, StockMarket};
+
+#[derive(Debug, Deserialize)]
+struct QuoteResponse {
+    /// Current price, zero for symbols Finnhub doesn't know.
+    #[serde(rename = "c", deserialize_with = "number::deserialize")]
+    current: f64,
+    /// Unix time of the last trade.
+    #[serde(rename = "t", default)]
+    time: i64,
+    #[serde(rename = "o", default)]
+    open: f64,
+    #[serde(rename = "h", default)]
+    high: f64,
+    #[serde(rename = "l", default)]
+    low: f64,
+    #[serde(rename = "pc", default)]
+    previous_close: f64,
+    #[serde(rename = "dp", default)]
+    change_percent: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MarketStatus {
+    is_open: bool,
+}
+
+/// One match of a Finnhub `/search`, which takes names, tickers and ISINs.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SearchResult {
+    pub description: String,
+    /// Yahoo-style, so listings carry their exchange suffix.
+    pub symbol: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    result: Vec<SearchResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    error: String,
+}
+
+fn parse<T: serde::de::DeserializeOwned>(data: &str) -> Result<T, String> {
+    if let Ok(error) = serde_json::from_str::<ApiError>(data) {
+        return Err(error.error);
+    }
+    serde_json::from_str(data).map_err(|e| e.to_string())
+}
+
+/// Instruments matching `query`, best first.
+#[instrument(skip(base_url, token))]
+pub async fn search(base_url: &str, token: &str, query: &str) -> Result<Vec<SearchResult>, Error> {
+    let data = reqwest::Client::new()
+        .get(format!("{}/search", super::base_url(base_url)))
+        .query(&[("q", query), ("token", token)])
+        .send()
+        .await?
+        .text()
+        .await?;
+    Ok(match parse::<SearchResponse>(&data) {
+        Ok(response) => response.result,
+        Err(e) => {
+            warn!(query, error = %e, "Unexpected search response");
+            vec![]
+        }
+    })
+}
+
+/// Finnhub's name for a symbol. Listings keep their exchange suffix, which
+/// Finnhub shares; pairs are quoted off OANDA and Binance.
+pub fn quote_symbol(symbol: &str) -> String {
+    let info = SymbolInfo::parse(symbol);
+    match (info.asset_class, symbol.split_once('/')) {
+        (AssetClass::Forex, Some((base, quote))) => format!("OANDA:{}_{}", base, quote),
+        (AssetClass::Crypto, Some((base, quote))) => {
+            let quote = if quote == "USD" { "USDT" } else { quote };
+            format!("BINANCE:{}{}", base, quote)
+        }
+        _ => symbol.to_string(),
+    }
+}
+
+fn exchange(market: StockMarket) -> &'static str {
+    match market {
+        StockMarket::NYSE | StockMarket::NASDAQ => "US",
+        StockMarket::LSE => "L",
+        StockMarket::XETR => "DE",
+        StockMarket::Euronext => "PA",
+        StockMarket::SIX => "SW",
+        StockMarket::JPX => "T",
+        StockMarket::HKEX => "HK",
+    }
+}
+
+/// Finnhub as a fallback price source, e.g. once the Twelve Data quota is
+/// spent. Its market status only says open or closed, so the wait until
+/// the open comes from the local session calendar.
+pub struct Finnhub {
+    token: String,
+    regions: Arc<Regions>,
+}
+
+impl Finnhub {
+    pub fn new(token: &str) -> Self {
+        Finnhub {
+            token: token.to_string(),
+            regions: Arc::new(Regions::fixed("finnhub", FINNHUB_URL)),
+        }
+    }
+
+    pub fn with_base_url(mut self, url: &str) -> Self {
+        self.regions = Arc::new(Regions::fixed("finnhub", url));
+        self
+    }
+
+    /// Sends requests to whichever of `regions` is selected.
+    pub fn with_regions(mut self, regions: Arc<Regions>) -> Self {
+        self.regions = regions;
+        self
+    }
+}
+
+#[async_trait]
+impl Provider for Finnhub {
+    fn name(&self) -> &str {
+        "finnhub"
+    }
+
+    #[instrument(skip(self))]
+    async fn fetch_price(&self, symbol: &str) -> Result<Option<Quote>, Error> {
+        let url = format!(
+            "{}/quote?symbol={}&token={}",
+            self.regions.selected(),
+            quote_symbol(symbol),
+            self.token
+        );
+        let data = check_status(reqwest::get(&url).await?)?.text().await?;
+        Ok(match parse::<QuoteResponse>(&data) {
+            Ok(quote) if quote.current > 0. => Some(Quote {
+                price: quote.current,
+                timestamp: (quote.time > 0)
+                    .then(|| DateTime::from_timestamp(quote.time, 0))
+                    .flatten(),
+                // Finnhub doesn't report volume with quotes.
+                day: (quote.open > 0.).then_some(DayQuote {
+                    open: quote.open,
+                    high: quote.high,
+                    low: quote.low,
+                    previous_close: quote.previous_close,
+                    volume: None,
+                    change_percent: quote.change_percent.unwrap_or_default(),
+                    open_interest: None,
+                }),
+            }),
+            Ok(_) => None,
+            Err(e) => {
+                warn!(symbol, error = %e, "Unexpected price response");
+                None
+            }
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn fetch_market_state(&self, market: &Markets) -> Result<u64, Error> {
+        let Markets::Stock(stock) = market else {
+            return Ok(0);
+        };
+        let m = market.to_string();
+        let url = format!(
+            "{}/stock/market-status?exchange={}&token={}",
+            self.regions.selected(),
+            exchange(*stock),
+            self.token
+        );
+        let data = check_status(reqwest::get(&url).await?)?.text().await?;
+        let status = match parse::<MarketStatus>(&data) {
+            Ok(status) => status,
+            Err(e) => {
+                warn!(market = %m, error = %e, "Unexpected market state response");
+                return Ok(0);
+            }
+        };
+        if status.is_open {
+            trace!(market = %m, "Market is open");
+            return Ok(0);
+        }
+        let session = calendar::session(*stock);
+        let now = Utc::now();
+        let mut seconds = session.seconds_until_open(now);
+        // Closed inside regular hours means a holiday, so wait for the
+        // session after today's.
+        if seconds == 0 {
+            let close = session.seconds_until_close(now);
+            let after = now + Duration::seconds(close as i64 + 1);
+            seconds = close + 1 + session.seconds_until_open(after);
+        }
+        info!(market = %m, seconds, "Market is closed, time to open");
+        Ok(seconds)
+    }
+}
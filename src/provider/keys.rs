@@ -0,0 +1,194 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{error, info, instrument, warn};
+
+use crate::clock::Clock;
+use crate::metrics;
+use crate::ops::{Incident, IncidentSeverity, OpsAlerter};
+use crate::provider::{base_url, TWELVEDATA_URL};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct KeysConfig {
+    /// How often standby keys are checked. Probes are free but still count
+    /// toward the per-minute request limit.
+    pub probe_interval_secs: u64,
+}
+
+impl Default for KeysConfig {
+    fn default() -> Self {
+        KeysConfig {
+            probe_interval_secs: 300,
+        }
+    }
+}
+
+/// Twelve Data keys in priority order. The active key serves traffic; the
+/// others are probed in the background so failover lands on a key already
+/// known to work.
+pub struct KeyPool {
+    keys: Vec<String>,
+    /// Outcome of each key's last probe or use, `None` until checked.
+    valid: Mutex<Vec<Option<bool>>>,
+    active: AtomicUsize,
+    base_url: String,
+    ops: Option<Arc<OpsAlerter>>,
+}
+
+/// Enough of a key to tell keys apart in logs and metrics.
+pub fn label(key: &str) -> String {
+    let tail: String = key
+        .chars()
+        .rev()
+        .take(4)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    format!("...{}", tail)
+}
+
+impl KeyPool {
+    pub fn new(keys: Vec<String>) -> Self {
+        let valid = vec![None; keys.len()];
+        let pool = KeyPool {
+            keys,
+            valid: Mutex::new(valid),
+            active: AtomicUsize::new(0),
+            base_url: TWELVEDATA_URL.into(),
+            ops: None,
+        };
+        pool.export();
+        pool
+    }
+
+    pub fn with_base_url(mut self, url: &str) -> Self {
+        self.base_url = base_url(url);
+        self
+    }
+
+    /// Raises an operational incident for every key found invalid.
+    pub fn with_ops(mut self, ops: Arc<OpsAlerter>) -> Self {
+        self.ops = Some(ops);
+        self
+    }
+
+    fn report(&self, index: usize, valid: bool, reason: &str) {
+        let Some(ops) = &self.ops else {
+            return;
+        };
+        let key = label(&self.keys[index]);
+        let dedup_key = format!("fintek-api-key-invalid-{}", key);
+        if valid {
+            ops.resolve(&dedup_key);
+        } else {
+            ops.raise(Incident {
+                dedup_key,
+                summary: format!("API key {} is unusable: {}", key, reason),
+                severity: IncidentSeverity::Warning,
+                component: "twelvedata".into(),
+            });
+        }
+    }
+
+    pub fn active(&self) -> &str {
+        &self.keys[self.active.load(Ordering::Relaxed)]
+    }
+
+    /// Marks the active key bad and switches to the best remaining key:
+    /// one that passed its last probe, else one never probed. Returns
+    /// whether a different key is now active.
+    pub fn fail_over(&self, reason: &str) -> bool {
+        let current = self.active.load(Ordering::Relaxed);
+        let mut valid = self.valid.lock().unwrap();
+        valid[current] = Some(false);
+        self.report(current, false, reason);
+        let candidates = (1..self.keys.len()).map(|i| (current + i) % self.keys.len());
+        let next = candidates
+            .clone()
+            .find(|i| valid[*i] == Some(true))
+            .or_else(|| candidates.clone().find(|i| valid[*i].is_none()));
+        drop(valid);
+        match next {
+            Some(next) => {
+                warn!(
+                    from = label(&self.keys[current]),
+                    to = label(&self.keys[next]),
+                    reason,
+                    "Failing over to standby API key"
+                );
+                self.active.store(next, Ordering::Relaxed);
+                self.export();
+                true
+            }
+            None => {
+                error!(reason, "No healthy standby API key to fail over to");
+                self.export();
+                false
+            }
+        }
+    }
+
+    fn export(&self) {
+        let active = self.active.load(Ordering::Relaxed);
+        let valid = self.valid.lock().unwrap();
+        for (i, key) in self.keys.iter().enumerate() {
+            metrics::update_api_key(&label(key), valid[i], i == active);
+        }
+    }
+
+    // `/api_usage` costs no credits. `None` when it couldn't be reached.
+    #[instrument(skip(self))]
+    async fn probe(&self, index: usize) -> Option<bool> {
+        let key = &self.keys[index];
+        let url = format!("{}/api_usage?apikey={}", self.base_url, key);
+        let valid = match reqwest::get(&url).await {
+            Ok(response) => {
+                let data = response.text().await.unwrap_or_default();
+                let v: Value = serde_json::from_str(&data).unwrap_or_default();
+                v["status"].as_str() != Some("error") && !v["current_usage"].is_null()
+            }
+            Err(e) => {
+                warn!(key = label(key), error = %e, "Failed to probe API key");
+                return None;
+            }
+        };
+        info!(key = label(key), valid, "Probed API key");
+        self.valid.lock().unwrap()[index] = Some(valid);
+        self.report(index, valid, "failed probe");
+        Some(valid)
+    }
+
+    /// Probes every key, returning each one's label and whether it is
+    /// valid, `None` when the provider couldn't be reached.
+    pub async fn verify(&self) -> Vec<(String, Option<bool>)> {
+        let mut results = vec![];
+        for (index, key) in self.keys.iter().enumerate() {
+            results.push((label(key), self.probe(index).await));
+        }
+        self.export();
+        results
+    }
+
+    /// Probes every key except the active one, unless the active key has
+    /// been marked bad, in which case it is rechecked too.
+    pub async fn probe_standbys(&self) {
+        let active = self.active.load(Ordering::Relaxed);
+        let active_bad = self.valid.lock().unwrap()[active] == Some(false);
+        for index in (0..self.keys.len()).filter(|i| *i != active || active_bad) {
+            self.probe(index).await;
+        }
+        self.export();
+    }
+
+    pub async fn run(self: Arc<Self>, clock: Arc<dyn Clock>, interval: Duration) {
+        loop {
+            self.probe_standbys().await;
+            clock.sleep(interval).await;
+        }
+    }
+}
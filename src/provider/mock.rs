@@ -0,0 +1,58 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use reqwest::Error;
+
+use super::{Provider, Quote};
+use crate::clock::Clock;
+use crate::{Markets, PricePoint};
+
+/// Serves recorded prices: the latest point at or before the clock's current time.
+pub struct MockProvider {
+    series: BTreeMap<String, Vec<PricePoint>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl MockProvider {
+    pub fn new(mut series: BTreeMap<String, Vec<PricePoint>>, clock: Arc<dyn Clock>) -> Self {
+        for points in series.values_mut() {
+            points.sort_by_key(|p| p.timestamp);
+        }
+        MockProvider { series, clock }
+    }
+}
+
+#[async_trait]
+impl Provider for MockProvider {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    async fn fetch_price(&self, symbol: &str) -> Result<Option<Quote>, Error> {
+        let now = self.clock.now();
+        Ok(self.series.get(symbol).and_then(|points| {
+            let idx = points.partition_point(|p| p.timestamp <= now);
+            idx.checked_sub(1).map(|i| Quote {
+                price: points[i].price,
+                timestamp: Some(points[i].timestamp),
+                day: None,
+            })
+        }))
+    }
+
+    // The market opens with the first recorded tick.
+    async fn fetch_market_state(&self, _market: &Markets) -> Result<u64, Error> {
+        let now = self.clock.now();
+        let first = self
+            .series
+            .values()
+            .filter_map(|points| points.first())
+            .map(|p| p.timestamp)
+            .min();
+        Ok(first
+            .filter(|first| *first > now)
+            .map(|first| (first - now).num_seconds().max(0) as u64)
+            .unwrap_or_default())
+    }
+}
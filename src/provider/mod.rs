@@ -0,0 +1,245 @@
+pub mod cassette;
+pub mod contract;
+pub mod endpoints;
+pub mod finnhub;
+pub mod keys;
+pub mod mock;
+pub mod number;
+pub mod proxy;
+pub mod regions;
+pub mod rest;
+pub mod retry;
+pub mod signing;
+pub mod twelvedata;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Error;
+use serde::{Deserialize, Serialize};
+use thiserror::Error as ThisError;
+use utoipa::ToSchema;
+
+use crate::Markets;
+
+pub use cassette::{RecordingProvider, ReplayProvider};
+pub use finnhub::Finnhub;
+pub use keys::KeyPool;
+pub use mock::MockProvider;
+pub use number::NumberLocale;
+pub use proxy::QuoteProxy;
+pub use regions::{Regions, RegionsConfig};
+pub use rest::{RestConfig, RestProvider};
+pub use retry::RetryConfig;
+pub use signing::{Signer, SigningConfig, SigningScheme};
+pub use twelvedata::TwelveData;
+
+pub const TWELVEDATA_URL: &str = "https://api.twelvedata.com";
+pub const FINNHUB_URL: &str = "https://finnhub.io/api/v1";
+/// Most symbols Twelve Data takes in one `/price` call.
+pub const MAX_BATCH_SIZE: usize = 120;
+
+/// Which upstream serves prices and market state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    #[default]
+    TwelveData,
+    Finnhub,
+    /// Any JSON API, as `provider.rest` describes it.
+    Rest,
+}
+
+impl std::str::FromStr for ProviderKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "twelvedata" => Ok(ProviderKind::TwelveData),
+            "finnhub" => Ok(ProviderKind::Finnhub),
+            "rest" => Ok(ProviderKind::Rest),
+            other => Err(format!("unknown provider {:?}", other)),
+        }
+    }
+}
+
+/// Where each upstream API lives. Point these at an enterprise mirror or a
+/// mock server; every endpoint of that provider follows.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ProviderConfig {
+    /// Overridden by `FINTEK_PROVIDER`.
+    pub kind: ProviderKind,
+    pub twelvedata_url: String,
+    pub finnhub_url: String,
+    /// How numbers sent as strings are written; `auto` guesses per value.
+    pub number_locale: NumberLocale,
+    /// Poll `/quote` rather than `/price`, for the day's open, range,
+    /// volume and change besides the price. Twelve Data only, and not
+    /// batched.
+    pub quotes: bool,
+    /// Symbols per price request, up to 120. Saves round trips; each symbol
+    /// still counts against the rate limits.
+    pub batch_size: usize,
+    /// Price requests in flight at once. Each still waits for the rate
+    /// limiter, so this only speeds up cycles the limits leave room for.
+    pub concurrency: usize,
+    /// Longest a fetched market state is trusted. It's fetched again sooner
+    /// when the open or close it predicts comes round.
+    pub market_state_secs: u64,
+    /// Fetch unwatched symbols on demand for `/api/v1/prices/{symbol}`,
+    /// from whatever rate budget polling leaves.
+    pub read_through: bool,
+    /// How long an on-demand quote is served before it's fetched again.
+    pub read_through_cache_secs: u64,
+    /// Upstream fetches a day each API token may cause through read-through,
+    /// unless the token sets its own. Zero for no quota.
+    pub read_through_daily_quota: u64,
+    /// Twelve Data key, used when `API_KEY` isn't set. Prefer the
+    /// environment; a key here lives in plain text with the config.
+    #[serde(skip_serializing)]
+    pub api_key: Option<String>,
+    /// Retrying of timeouts, rate limiting and server errors.
+    pub retry: RetryConfig,
+    /// Regional endpoints, probed for the fastest.
+    pub regions: RegionsConfig,
+    /// The API polled with `kind = "rest"`.
+    pub rest: RestConfig,
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        ProviderConfig {
+            kind: ProviderKind::default(),
+            twelvedata_url: TWELVEDATA_URL.into(),
+            finnhub_url: FINNHUB_URL.into(),
+            number_locale: NumberLocale::default(),
+            quotes: false,
+            batch_size: 1,
+            concurrency: 1,
+            market_state_secs: 3600,
+            read_through: false,
+            read_through_cache_secs: 60,
+            read_through_daily_quota: 0,
+            api_key: None,
+            retry: RetryConfig::default(),
+            regions: RegionsConfig::default(),
+            rest: RestConfig::default(),
+        }
+    }
+}
+
+/// A fetched price and, when the provider says, the time it was set.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Quote {
+    pub price: f64,
+    pub timestamp: Option<DateTime<Utc>>,
+    /// The trading day so far, from quote endpoints.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub day: Option<DayQuote>,
+}
+
+/// The current or last trading day of a symbol, its close being the price.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct DayQuote {
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub previous_close: f64,
+    /// Not reported for currencies and by some providers.
+    pub volume: Option<f64>,
+    pub change_percent: f64,
+    /// Contracts still open, for futures where the provider reports it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_interest: Option<f64>,
+}
+
+impl Quote {
+    /// A price without a provider timestamp.
+    pub fn new(price: f64) -> Self {
+        Quote {
+            price,
+            timestamp: None,
+            day: None,
+        }
+    }
+}
+
+/// When a market next opens and, while open, when it closes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MarketHours {
+    /// Zero when open now.
+    pub seconds_until_open: u64,
+    /// Set when the provider says, which catches early closes.
+    pub seconds_until_close: Option<u64>,
+}
+
+/// Why one symbol of a [`Provider::fetch_prices`] call got no answer while
+/// others did.
+#[derive(Debug, Clone, ThisError)]
+#[error("{0}")]
+pub struct SymbolError(pub String);
+
+/// One symbol's answer to [`Provider::fetch_prices`].
+pub type SymbolQuote = Result<Option<Quote>, SymbolError>;
+
+/// `quotes` as [`Provider::fetch_prices`] returns them: `failure` instead
+/// when every symbol failed, so the call as a whole fails and is retried.
+pub fn settle(quotes: Vec<SymbolQuote>, failure: Option<Error>) -> Result<Vec<SymbolQuote>, Error> {
+    match failure {
+        Some(e) if quotes.iter().all(Result::is_err) => Err(e),
+        _ => Ok(quotes),
+    }
+}
+
+/// Drops trailing slashes so paths can be appended with `format!`.
+pub fn base_url(url: &str) -> String {
+    url.trim_end_matches('/').to_string()
+}
+
+#[async_trait]
+pub trait Provider: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn fetch_price(&self, symbol: &str) -> Result<Option<Quote>, Error>;
+
+    /// Most symbols worth passing to one `fetch_prices` call.
+    fn batch_size(&self) -> usize {
+        1
+    }
+
+    /// Prices for `symbols`, in the same order, each failing on its own;
+    /// an error only when all of them failed. Without a batch endpoint this
+    /// is one `fetch_price` per symbol.
+    async fn fetch_prices(&self, symbols: &[String]) -> Result<Vec<SymbolQuote>, Error> {
+        let mut prices = Vec::with_capacity(symbols.len());
+        let mut failure = None;
+        for symbol in symbols {
+            match self.fetch_price(symbol).await {
+                Ok(price) => prices.push(Ok(price)),
+                Err(e) => {
+                    prices.push(Err(SymbolError(e.to_string())));
+                    failure.get_or_insert(e);
+                }
+            }
+        }
+        settle(prices, failure)
+    }
+
+    /// Price of `symbol` on the exchange the provider calls `venue` rather
+    /// than its consolidated one. `None` where venues aren't told apart.
+    async fn fetch_venue_price(&self, _symbol: &str, _venue: &str) -> Result<Option<Quote>, Error> {
+        Ok(None)
+    }
+
+    /// Seconds until `market` opens, zero when it is open now.
+    async fn fetch_market_state(&self, market: &Markets) -> Result<u64, Error>;
+
+    /// [`Provider::fetch_market_state`] plus the close, for providers that
+    /// report it.
+    async fn fetch_market_hours(&self, market: &Markets) -> Result<MarketHours, Error> {
+        Ok(MarketHours {
+            seconds_until_open: self.fetch_market_state(market).await?,
+            seconds_until_close: None,
+        })
+    }
+}
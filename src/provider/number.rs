@@ -0,0 +1,172 @@
+use std::sync::RwLock;
+
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+/// How numbers sent as strings are written. Providers mostly send plain
+/// `1234.5`, but some localize them (`1.234,5`, `1 234,5`, `1'234.5`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NumberLocale {
+    /// Decides per value: the last of `.` and `,` is the decimal point, and a
+    /// lone comma followed by exactly three digits groups thousands.
+    #[default]
+    Auto,
+    /// `1,234.5`
+    En,
+    /// `1.234,5`
+    De,
+    /// `1 234,5`
+    Fr,
+    /// `1'234.5`
+    Ch,
+}
+
+static LOCALE: RwLock<NumberLocale> = RwLock::new(NumberLocale::Auto);
+
+/// Sets the locale every provider response is parsed with.
+pub fn set_locale(locale: NumberLocale) {
+    *LOCALE.write().unwrap() = locale;
+}
+
+pub fn locale() -> NumberLocale {
+    *LOCALE.read().unwrap()
+}
+
+fn is_group_mark(c: char) -> bool {
+    // Plain, no-break and narrow no-break spaces all show up as separators.
+    matches!(
+        c,
+        ',' | '.' | '\'' | '\u{2019}' | '_' | ' ' | '\u{a0}' | '\u{202f}'
+    )
+}
+
+// A lone `.` is always the decimal point, since that's how most providers
+// send plain numbers. A lone `,` is one too, unless exactly three digits
+// follow a non-zero integer part: "1,234" is 1234 but "0,125" a fraction.
+fn auto_decimal(s: &str) -> Option<char> {
+    let (dot, comma) = (s.rfind('.'), s.rfind(','));
+    match (dot, comma) {
+        (Some(d), Some(c)) => Some(if d > c { '.' } else { ',' }),
+        (None, None) => None,
+        (Some(_), None) => (s.matches('.').count() == 1).then_some('.'),
+        (None, Some(i)) => {
+            let integer = s[..i].trim_start_matches('0');
+            let thousands =
+                s.matches(',').count() > 1 || (s.len() - i - 1 == 3 && !integer.is_empty());
+            (!thousands).then_some(',')
+        }
+    }
+}
+
+/// Parses a localized number. Thousands groups must be three digits long.
+/// Accepts surrounding whitespace, a leading `+`,
+/// `-` or Unicode minus, accounting-style `(1,234.50)` negatives, a trailing
+/// `%` (kept as the bare number) and exponents. `None` for anything else,
+/// including empty strings and `NaN`/`inf`.
+pub fn parse(text: &str, locale: NumberLocale) -> Option<f64> {
+    let mut s = text.trim();
+    let mut negative = false;
+    if let Some(inner) = s.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+        negative = true;
+        s = inner.trim();
+    }
+    s = s.strip_suffix('%').unwrap_or(s).trim_end();
+    if let Some(rest) = s.strip_prefix(['-', '\u{2212}']) {
+        negative = !negative;
+        s = rest.trim_start();
+    } else if let Some(rest) = s.strip_prefix('+') {
+        s = rest.trim_start();
+    }
+    if s.is_empty() {
+        return None;
+    }
+
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    };
+    let decimal = match locale {
+        NumberLocale::Auto => auto_decimal(mantissa),
+        NumberLocale::En | NumberLocale::Ch => Some('.'),
+        NumberLocale::De | NumberLocale::Fr => Some(','),
+    };
+
+    let mut normalized = String::with_capacity(s.len());
+    let mut seen_decimal = false;
+    let mut seen_digit = false;
+    // Digits since the last grouping mark; every group after the first has three.
+    let mut group: Option<usize> = None;
+    let bad_group = |group: Option<usize>| group.is_some_and(|digits| digits != 3);
+    for c in mantissa.chars() {
+        match c {
+            '0'..='9' => {
+                seen_digit = true;
+                if let Some(digits) = group.as_mut() {
+                    *digits += 1;
+                }
+                normalized.push(c);
+            }
+            c if Some(c) == decimal => {
+                if seen_decimal || bad_group(group) {
+                    return None;
+                }
+                seen_decimal = true;
+                group = None;
+                normalized.push('.');
+            }
+            // Grouping is only allowed before the decimal point.
+            c if is_group_mark(c) && !seen_decimal && seen_digit => {
+                if bad_group(group) {
+                    return None;
+                }
+                group = Some(0);
+            }
+            _ => return None,
+        }
+    }
+    if !seen_digit || bad_group(group) {
+        return None;
+    }
+    if let Some(exponent) = exponent {
+        let exponent = exponent.strip_prefix('+').unwrap_or(exponent);
+        let digits = exponent.strip_prefix('-').unwrap_or(exponent);
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        normalized.push('e');
+        normalized.push_str(exponent);
+    }
+
+    let value: f64 = normalized.parse().ok()?;
+    value
+        .is_finite()
+        .then_some(if negative { -value } else { value })
+}
+
+/// A JSON number, or a string parsed with the configured locale.
+pub fn from_value(value: &Value) -> Option<f64> {
+    value
+        .as_f64()
+        .or_else(|| value.as_str().and_then(|s| parse(s, locale())))
+}
+
+/// `deserialize_with` for optional provider fields, `None` when missing or
+/// null.
+pub fn deserialize_optional<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<f64>, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::Null => Ok(None),
+        value => from_value(&value)
+            .map(Some)
+            .ok_or_else(|| serde::de::Error::custom(format!("expected a number, got {}", value))),
+    }
+}
+
+/// `deserialize_with` for provider fields that may be a number or a string.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let value = Value::deserialize(deserializer)?;
+    from_value(&value)
+        .ok_or_else(|| serde::de::Error::custom(format!("expected a number, got {}", value)))
+}
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{Duration, NaiveDate};
+use reqwest::Error;
+use serde::Serialize;
+use tracing::{debug, instrument};
+
+use super::Provider;
+use crate::auth::Caller;
+use crate::clock::Clock;
+use crate::metrics;
+use crate::ratelimit::RateLimiter;
+use crate::sink::PriceUpdate;
+
+/// Why an on-demand quote isn't there.
+#[derive(Debug, thiserror::Error)]
+pub enum ProxyError {
+    #[error("rate limit reached, retry later")]
+    RateLimited,
+    #[error("daily quota of {0} fetches used up")]
+    QuotaExceeded(u64),
+    #[error("provider has no price for {0}")]
+    NotFound(String),
+    #[error("provider request failed: {0}")]
+    Provider(#[from] Error),
+}
+
+/// One client's use of the proxy on one UTC day.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientUsage {
+    pub client: String,
+    pub day: NaiveDate,
+    /// Quotes served, cached or not.
+    pub requests: u64,
+    /// Upstream fetches, the part the quota counts.
+    pub fetched: u64,
+    /// Requests refused for the quota or the rate limit.
+    pub refused: u64,
+    pub quota: Option<u64>,
+}
+
+/// Quotes for symbols the engine doesn't poll, fetched when asked and kept
+/// for a while, so teams can share one provider key without holding it.
+/// Only spare credits are spent: a fetch that would have to wait for the
+/// limiter is refused rather than delaying the next cycle. Cache hits are
+/// free; each client's upstream fetches count against its daily quota.
+/// Usage is kept in memory and starts over on restart.
+pub struct QuoteProxy {
+    provider: Arc<dyn Provider>,
+    limiter: Arc<RateLimiter>,
+    clock: Arc<dyn Clock>,
+    ttl: Duration,
+    /// Quota for clients whose token doesn't set one, zero for none.
+    default_quota: u64,
+    cache: Mutex<HashMap<String, PriceUpdate>>,
+    usage: Mutex<HashMap<String, ClientUsage>>,
+}
+
+impl QuoteProxy {
+    pub fn new(
+        provider: Arc<dyn Provider>,
+        limiter: Arc<RateLimiter>,
+        clock: Arc<dyn Clock>,
+        cache_secs: u64,
+    ) -> Self {
+        QuoteProxy {
+            provider,
+            limiter,
+            clock,
+            ttl: Duration::seconds(cache_secs as i64),
+            default_quota: 0,
+            cache: Mutex::new(HashMap::new()),
+            usage: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_default_quota(mut self, quota: u64) -> Self {
+        self.default_quota = quota;
+        self
+    }
+
+    /// Today's usage per client, by name.
+    pub fn usage(&self) -> Vec<ClientUsage> {
+        let today = self.clock.now().date_naive();
+        let mut usage: Vec<ClientUsage> = self
+            .usage
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|u| u.day == today)
+            .cloned()
+            .collect();
+        usage.sort_by(|a, b| a.client.cmp(&b.client));
+        usage
+    }
+
+    // Runs `f` on the caller's usage for today, starting over at midnight.
+    fn account<T>(&self, caller: &Caller, f: impl FnOnce(&mut ClientUsage) -> T) -> T {
+        let today = self.clock.now().date_naive();
+        let quota = caller
+            .quota
+            .or((self.default_quota > 0).then_some(self.default_quota));
+        let mut usage = self.usage.lock().unwrap();
+        let entry = usage
+            .entry(caller.name.clone())
+            .or_insert_with(|| ClientUsage {
+                client: caller.name.clone(),
+                day: today,
+                requests: 0,
+                fetched: 0,
+                refused: 0,
+                quota,
+            });
+        if entry.day != today {
+            *entry = ClientUsage {
+                day: today,
+                requests: 0,
+                fetched: 0,
+                refused: 0,
+                ..entry.clone()
+            };
+        }
+        entry.quota = quota;
+        f(entry)
+    }
+
+    #[instrument(skip(self, caller), fields(client = %caller.name))]
+    pub async fn quote(&self, symbol: &str, caller: &Caller) -> Result<PriceUpdate, ProxyError> {
+        let now = self.clock.now();
+        let cached = {
+            let mut cache = self.cache.lock().unwrap();
+            cache.retain(|_, update| now - update.timestamp < self.ttl);
+            cache.get(symbol).cloned()
+        };
+        if let Some(update) = cached {
+            self.account(caller, |u| u.requests += 1);
+            metrics::update_read_through(&caller.name, "cached");
+            return Ok(update);
+        }
+        let refused = self.account(caller, |u| {
+            let refused = match u.quota {
+                Some(quota) if u.fetched >= quota => Some(ProxyError::QuotaExceeded(quota)),
+                _ if !self.limiter.try_acquire(1) => Some(ProxyError::RateLimited),
+                _ => None,
+            };
+            match refused {
+                Some(_) => u.refused += 1,
+                None => {
+                    u.requests += 1;
+                    u.fetched += 1;
+                }
+            }
+            refused
+        });
+        if let Some(e) = refused {
+            debug!(symbol, error = %e, "Refused an on-demand quote");
+            let outcome = match e {
+                ProxyError::QuotaExceeded(_) => "over_quota",
+                _ => "limited",
+            };
+            metrics::update_read_through(&caller.name, outcome);
+            return Err(e);
+        }
+        metrics::update_read_through(&caller.name, "fetched");
+        let Some(quote) = self.provider.fetch_price(symbol).await? else {
+            return Err(ProxyError::NotFound(symbol.to_string()));
+        };
+        let update = PriceUpdate {
+            symbol: symbol.to_string(),
+            price: quote.price,
+            timestamp: self.clock.now(),
+            provider_timestamp: quote.timestamp,
+            day: quote.day,
+        };
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(symbol.to_string(), update.clone());
+        Ok(update)
+    }
+}
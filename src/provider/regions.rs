@@ -0,0 +1,129 @@
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument, warn};
+
+use crate::clock::Clock;
+use crate::metrics;
+use crate::provider::base_url;
+
+/// How long a probe waits before counting an endpoint as down.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RegionsConfig {
+    /// Regional Twelve Data base URLs to pick from besides `twelvedata_url`.
+    pub twelvedata: Vec<String>,
+    /// Regional Finnhub base URLs to pick from besides `finnhub_url`.
+    pub finnhub: Vec<String>,
+    /// How often the endpoints are timed again.
+    pub probe_secs: u64,
+}
+
+impl Default for RegionsConfig {
+    fn default() -> Self {
+        RegionsConfig {
+            twelvedata: vec![],
+            finnhub: vec![],
+            probe_secs: 300,
+        }
+    }
+}
+
+/// A provider's base URLs and the one requests go to: the fastest to
+/// answer at the last probe, the configured one until then.
+#[derive(Debug)]
+pub struct Regions {
+    provider: &'static str,
+    urls: Vec<String>,
+    selected: RwLock<String>,
+}
+
+impl Regions {
+    /// Just `url`, as for a provider without regional endpoints.
+    pub fn fixed(provider: &'static str, url: &str) -> Self {
+        Regions::new(provider, url, &[])
+    }
+
+    pub fn new(provider: &'static str, primary: &str, others: &[String]) -> Self {
+        let mut urls = vec![base_url(primary)];
+        for url in others.iter().map(|url| base_url(url)) {
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+        Regions {
+            provider,
+            selected: RwLock::new(urls[0].clone()),
+            urls,
+        }
+    }
+
+    pub fn selected(&self) -> String {
+        self.selected.read().unwrap().clone()
+    }
+
+    // Any answer short of a server error counts as up.
+    async fn latency(client: &reqwest::Client, url: &str) -> Option<Duration> {
+        let started = Instant::now();
+        match client.get(format!("{}/", url)).send().await {
+            Ok(response) if !response.status().is_server_error() => Some(started.elapsed()),
+            Ok(response) => {
+                warn!(endpoint = url, status = %response.status(), "Provider endpoint unhealthy");
+                None
+            }
+            Err(e) => {
+                warn!(endpoint = url, error = %e.without_url(), "Provider endpoint unreachable");
+                None
+            }
+        }
+    }
+
+    /// Times every endpoint and switches to the fastest that is up. With
+    /// none up the current one stays.
+    #[instrument(skip(self), fields(provider = self.provider))]
+    pub async fn probe(&self) {
+        let client = reqwest::Client::builder()
+            .timeout(PROBE_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        let mut fastest: Option<(&String, Duration)> = None;
+        for url in &self.urls {
+            let latency = Self::latency(&client, url).await;
+            metrics::update_provider_endpoint_latency(
+                self.provider,
+                url,
+                latency.map(|l| l.as_secs_f64()),
+            );
+            if let Some(latency) = latency {
+                if fastest.is_none_or(|(_, best)| latency < best) {
+                    fastest = Some((url, latency));
+                }
+            }
+        }
+        let current = self.selected();
+        match fastest {
+            Some((url, latency)) if *url != current => {
+                info!(from = %current, to = %url, latency_ms = latency.as_millis() as u64, "Switched to faster provider endpoint");
+                *self.selected.write().unwrap() = url.clone();
+            }
+            Some(_) => {}
+            None => {
+                warn!(endpoint = %current, "No provider endpoint answered, keeping the current one")
+            }
+        }
+        let selected = self.selected();
+        for url in &self.urls {
+            metrics::update_provider_endpoint_selected(self.provider, url, *url == selected);
+        }
+    }
+
+    pub async fn run(self: Arc<Self>, clock: Arc<dyn Clock>, interval: Duration) {
+        loop {
+            self.probe().await;
+            clock.sleep(interval).await;
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use reqwest::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{instrument, trace, warn};
+
+use super::retry::check_status;
+use super::signing::{Signer, SigningConfig};
+use super::{number, Provider, Quote};
+use crate::{calendar, Markets};
+
+/// A price API described in the config rather than in code: a URL per
+/// symbol and where the price sits in the JSON it returns.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RestConfig {
+    /// Fetched for every symbol, with `{symbol}` and `{api_key}` filled in.
+    /// The key comes from `REST_API_KEY`.
+    pub url: String,
+    /// Where the price is in the response, e.g. `$.data[0].last` or
+    /// `$['{symbol}'].usd`. Numbers sent as strings are read too.
+    pub price: String,
+    /// Where the time of the price is, as Unix seconds or milliseconds or a
+    /// date and time string. Without it prices are timed on arrival.
+    pub timestamp: Option<String>,
+    /// Sent with every request; values take `{api_key}` as well.
+    pub headers: BTreeMap<String, String>,
+    /// What the API calls a symbol, where that differs.
+    pub symbols: BTreeMap<String, String>,
+    /// Signing for endpoints that need it.
+    pub signing: SigningConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// A member, `{symbol}` in it standing for the API's symbol.
+    Key(String),
+    /// An array element, counted from the end when negative.
+    Index(i64),
+}
+
+/// A JSONPath-like location in a response: `$`, then `.key`, `['key']` and
+/// `[index]` steps, the leading `$` and first dot optional.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath(pub Vec<Segment>);
+
+impl FromStr for JsonPath {
+    type Err = String;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let mut rest = path.trim();
+        rest = rest.strip_prefix('$').unwrap_or(rest);
+        let mut segments = vec![];
+        while !rest.is_empty() {
+            if let Some(tail) = rest.strip_prefix('[') {
+                let end = tail
+                    .find(']')
+                    .ok_or_else(|| format!("unclosed `[` in {:?}", path))?;
+                let inner = tail[..end].trim();
+                let quoted = ['\'', '"']
+                    .iter()
+                    .find_map(|q| inner.strip_prefix(*q)?.strip_suffix(*q));
+                segments.push(match quoted {
+                    Some(key) => Segment::Key(key.to_string()),
+                    None => Segment::Index(
+                        inner
+                            .parse()
+                            .map_err(|_| format!("bad index {:?} in {:?}", inner, path))?,
+                    ),
+                });
+                rest = &tail[end + 1..];
+                continue;
+            }
+            let tail = match rest.strip_prefix('.') {
+                Some(tail) => tail,
+                None if segments.is_empty() => rest,
+                None => return Err(format!("expected `.` or `[` in {:?}", path)),
+            };
+            let end = tail.find(['.', '[']).unwrap_or(tail.len());
+            if end == 0 {
+                return Err(format!("empty key in {:?}", path));
+            }
+            segments.push(Segment::Key(tail[..end].to_string()));
+            rest = &tail[end..];
+        }
+        Ok(JsonPath(segments))
+    }
+}
+
+impl JsonPath {
+    pub fn get<'a>(&self, value: &'a Value, symbol: &str) -> Option<&'a Value> {
+        self.0
+            .iter()
+            .try_fold(value, |value, segment| match segment {
+                Segment::Key(key) => value.get(key.replace("{symbol}", symbol)),
+                Segment::Index(i) => {
+                    let array = value.as_array()?;
+                    let i = if *i < 0 { array.len() as i64 + i } else { *i };
+                    array.get(usize::try_from(i).ok()?)
+                }
+            })
+    }
+}
+
+/// Unix seconds or milliseconds, RFC 3339, or a date with or without a
+/// time, taken as UTC.
+pub fn parse_timestamp(value: &Value) -> Option<DateTime<Utc>> {
+    if let Some(text) = value.as_str() {
+        let text = text.trim();
+        if let Ok(at) = DateTime::parse_from_rfc3339(text) {
+            return Some(at.with_timezone(&Utc));
+        }
+        for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"] {
+            if let Ok(at) = NaiveDateTime::parse_from_str(text, format) {
+                return Some(at.and_utc());
+            }
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+            return Some(date.and_hms_opt(0, 0, 0)?.and_utc());
+        }
+    }
+    let number = number::from_value(value)?;
+    // Seconds would be past the year 5000.
+    if number.abs() >= 1e11 {
+        DateTime::from_timestamp_millis(number as i64)
+    } else {
+        DateTime::from_timestamp(number as i64, 0)
+    }
+}
+
+// Percent-encodes all but unreserved characters, so `BTC/USD` stays one
+// path segment.
+fn encode(symbol: &str) -> String {
+    symbol
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Prices from any JSON API, polled one symbol at a time. Such APIs seldom
+/// say when markets open, so that comes from the local session calendar.
+pub struct RestProvider {
+    config: RestConfig,
+    api_key: String,
+    price: JsonPath,
+    timestamp: Option<JsonPath>,
+    signer: Option<Signer>,
+    client: reqwest::Client,
+}
+
+impl RestProvider {
+    pub fn new(config: &RestConfig, api_key: Option<String>) -> Result<Self, String> {
+        if config.url.trim().is_empty() {
+            return Err("url is not set".into());
+        }
+        let price = config.price.parse().map_err(|e| format!("price: {}", e))?;
+        let timestamp = match &config.timestamp {
+            Some(path) => Some(path.parse().map_err(|e| format!("timestamp: {}", e))?),
+            None => None,
+        };
+        Ok(RestProvider {
+            config: config.clone(),
+            api_key: api_key.unwrap_or_default(),
+            price,
+            timestamp,
+            signer: None,
+            client: reqwest::Client::new(),
+        })
+    }
+
+    /// Signs every request with `signer`.
+    pub fn with_signer(mut self, signer: Signer) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    fn fill(&self, template: &str, symbol: &str) -> String {
+        template
+            .replace("{symbol}", &encode(symbol))
+            .replace("{api_key}", &self.api_key)
+    }
+}
+
+#[async_trait]
+impl Provider for RestProvider {
+    fn name(&self) -> &str {
+        "rest"
+    }
+
+    #[instrument(skip(self))]
+    async fn fetch_price(&self, symbol: &str) -> Result<Option<Quote>, Error> {
+        let theirs = self
+            .config
+            .symbols
+            .get(symbol)
+            .map_or(symbol, String::as_str);
+        let mut request = self.client.get(self.fill(&self.config.url, theirs));
+        for (name, value) in &self.config.headers {
+            request = request.header(name, value.replace("{api_key}", &self.api_key));
+        }
+        let mut request = request.build()?;
+        if let Some(signer) = &self.signer {
+            signer.sign(&mut request, Utc::now());
+        }
+        let data = check_status(self.client.execute(request).await?)?
+            .text()
+            .await?;
+        let response: Value = match serde_json::from_str(&data) {
+            Ok(response) => response,
+            Err(e) => {
+                warn!(symbol, error = %e, "Unexpected price response");
+                return Ok(None);
+            }
+        };
+        let Some(price) = self
+            .price
+            .get(&response, theirs)
+            .and_then(number::from_value)
+            .filter(|p| *p > 0.)
+        else {
+            warn!(
+                symbol,
+                path = self.config.price,
+                "No price at the configured path"
+            );
+            return Ok(None);
+        };
+        let timestamp = self
+            .timestamp
+            .as_ref()
+            .and_then(|path| path.get(&response, theirs))
+            .and_then(parse_timestamp);
+        trace!(symbol, price, "Fetched price");
+        Ok(Some(Quote {
+            price,
+            timestamp,
+            day: None,
+        }))
+    }
+
+    async fn fetch_market_state(&self, market: &Markets) -> Result<u64, Error> {
+        Ok(match market {
+            Markets::Stock(stock) => calendar::session(*stock).seconds_until_open(Utc::now()),
+            _ => 0,
+        })
+    }
+}
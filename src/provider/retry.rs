@@ -0,0 +1,134 @@
+use std::future::Future;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use reqwest::{Error, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::error::FintekError;
+use crate::metrics;
+
+/// How transient provider failures are retried: each wait doubles from
+/// `base_delay_ms` up to `max_delay_ms`, then a random part of it is taken
+/// off so clients that failed together don't retry together.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Tries per call, the first included. One turns retrying off.
+    pub attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig::DEFAULT
+    }
+}
+
+impl RetryConfig {
+    const DEFAULT: RetryConfig = RetryConfig {
+        attempts: 3,
+        base_delay_ms: 250,
+        max_delay_ms: 5_000,
+    };
+
+    /// The wait after the `attempt`th failure, counting from one.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let doubled = self
+            .base_delay_ms
+            .saturating_mul(1 << attempt.saturating_sub(1).min(32));
+        let ceiling = doubled.min(self.max_delay_ms);
+        Duration::from_millis(rand::thread_rng().gen_range(ceiling / 2..=ceiling))
+    }
+}
+
+static POLICY: RwLock<RetryConfig> = RwLock::new(RetryConfig::DEFAULT);
+
+/// Sets the policy [`retry`] follows, the default until set.
+pub fn set_policy(config: RetryConfig) {
+    *POLICY.write().unwrap() = config;
+}
+
+pub fn policy() -> RetryConfig {
+    *POLICY.read().unwrap()
+}
+
+/// Timeouts, dropped connections, rate limiting and server errors.
+pub fn is_transient(e: &Error) -> bool {
+    e.is_timeout()
+        || e.is_connect()
+        || e.is_body()
+        || e.status()
+            .is_some_and(|s| s.is_server_error() || s == StatusCode::TOO_MANY_REQUESTS)
+}
+
+fn error_kind(e: &Error) -> &'static str {
+    if e.is_timeout() {
+        "timeout"
+    } else if e.is_connect() {
+        "connect"
+    } else if e.is_status() {
+        "status"
+    } else if e.is_body() || e.is_decode() {
+        "body"
+    } else {
+        "other"
+    }
+}
+
+/// Turns rate limiting and server errors into errors, so they are retried
+/// rather than parsed as a body. Other statuses carry the provider's own
+/// error payload and pass through.
+pub fn check_status(response: Response) -> Result<Response, Error> {
+    let status = response.status();
+    if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+        // The URL carries the API key.
+        return response.error_for_status().map_err(Error::without_url);
+    }
+    Ok(response)
+}
+
+/// Runs `call` until it succeeds, fails for good, or runs out of attempts.
+pub async fn retry<T, F, Fut>(operation: &str, mut call: F) -> Result<T, FintekError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let policy = policy();
+    let attempts = policy.attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        let started = Instant::now();
+        let result = call().await;
+        metrics::update_provider_request_duration(operation, started.elapsed().as_secs_f64());
+        if let Err(e) = &result {
+            let status = e
+                .status()
+                .map_or("none".to_string(), |s| s.as_u16().to_string());
+            metrics::update_provider_error(operation, &status, error_kind(e));
+        }
+        match result {
+            Ok(value) => return Ok(value),
+            Err(e) if !is_transient(&e) => return Err(e.into()),
+            Err(e) if attempt >= attempts => {
+                return Err(match attempts {
+                    1 => e.into(),
+                    _ => FintekError::Exhausted {
+                        attempts,
+                        source: e,
+                    },
+                })
+            }
+            Err(e) => {
+                let delay = policy.delay(attempt);
+                warn!(operation, attempt, delay_ms = delay.as_millis() as u64, error = %e, "Retrying provider call");
+                metrics::update_provider_retry(operation);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use async_trait::async_trait;
+use reqwest::Error;
+use tracing::{info, instrument, trace, warn};
+
+use std::sync::Arc;
+
+use super::endpoints::{
+    self, BatchPrice, BatchPriceEntry, DailyQuote, Endpoint, MarketStates, Price, QuoteResponse,
+};
+use super::retry::check_status;
+use super::{
+    settle, DayQuote, KeyPool, MarketHours, Provider, Quote, Regions, SymbolError, SymbolQuote,
+    MAX_BATCH_SIZE, TWELVEDATA_URL,
+};
+use crate::symbol::SymbolInfo;
+use crate::Markets;
+
+pub struct TwelveData {
+    keys: Arc<KeyPool>,
+    regions: Arc<Regions>,
+    batch_size: usize,
+    quotes: bool,
+}
+
+impl From<QuoteResponse> for Quote {
+    fn from(quote: QuoteResponse) -> Self {
+        Quote {
+            day: Some(DayQuote {
+                open: quote.open,
+                high: quote.high,
+                low: quote.low,
+                previous_close: quote.previous_close,
+                volume: quote.volume,
+                change_percent: quote.percent_change,
+                open_interest: quote.open_interest,
+            }),
+            ..Quote::new(quote.close)
+        }
+    }
+}
+
+impl TwelveData {
+    pub fn new(api_key: &str) -> Self {
+        TwelveData::with_keys(Arc::new(KeyPool::new(vec![api_key.to_string()])))
+    }
+
+    pub fn with_keys(keys: Arc<KeyPool>) -> Self {
+        TwelveData {
+            keys,
+            regions: Arc::new(Regions::fixed("twelvedata", TWELVEDATA_URL)),
+            batch_size: 1,
+            quotes: false,
+        }
+    }
+
+    pub fn with_base_url(mut self, url: &str) -> Self {
+        self.regions = Arc::new(Regions::fixed("twelvedata", url));
+        self
+    }
+
+    /// Sends requests to whichever of `regions` is selected.
+    pub fn with_regions(mut self, regions: Arc<Regions>) -> Self {
+        self.regions = regions;
+        self
+    }
+
+    pub fn with_batch_size(mut self, size: usize) -> Self {
+        self.batch_size = size.clamp(1, MAX_BATCH_SIZE);
+        self
+    }
+
+    /// Fetches from `/quote`, one symbol a request, for the day's figures.
+    pub fn with_quotes(mut self, quotes: bool) -> Self {
+        self.quotes = quotes;
+        self
+    }
+
+    async fn fetch_one<E: Endpoint>(
+        &self,
+        symbol: &str,
+        venue: Option<&str>,
+        quote: fn(E::Response) -> Quote,
+    ) -> Result<Option<Quote>, Error> {
+        let info = SymbolInfo::parse(symbol);
+        loop {
+            let api_key = self.keys.active();
+            let query = match (venue, info.exchange) {
+                (Some(venue), _) => format!("symbol={}&exchange={}", symbol, venue),
+                (None, Some(exchange)) => {
+                    format!("symbol={}&exchange={:?}", info.base, exchange)
+                }
+                (None, None) => format!("symbol={}", symbol),
+            };
+            let url = endpoints::url::<E>(&self.regions.selected(), &query, api_key);
+            let response = check_status(reqwest::get(&url).await?)?;
+
+            let data = response.text().await?;
+            return Ok(match endpoints::parse::<E::Response>(&data) {
+                Ok(Ok(response)) => Some(quote(response)),
+                Ok(Err(error)) => {
+                    if let Some(reason) = error.key_error() {
+                        if self.keys.fail_over(reason) {
+                            continue;
+                        }
+                    }
+                    None
+                }
+                Err(e) => {
+                    warn!(symbol, endpoint = E::PATH, error = %e, "Unexpected price response");
+                    None
+                }
+            });
+        }
+    }
+
+    /// One multi-symbol `/price` call. Symbols missing from the response or
+    /// failing on their own come back as `None`.
+    async fn fetch_batch(&self, symbols: &[&String]) -> Result<Vec<Option<Quote>>, Error> {
+        let query = symbols
+            .iter()
+            .map(|s| s.as_str())
+            .collect::<Vec<_>>()
+            .join(",");
+        loop {
+            let url = endpoints::url::<BatchPrice>(
+                &self.regions.selected(),
+                &format!("symbol={}", query),
+                self.keys.active(),
+            );
+            let data = check_status(reqwest::get(&url).await?)?.text().await?;
+            let mut entries = match endpoints::parse::<<BatchPrice as Endpoint>::Response>(&data) {
+                Ok(Ok(entries)) => entries,
+                Ok(Err(error)) => {
+                    if let Some(reason) = error.key_error() {
+                        if self.keys.fail_over(reason) {
+                            continue;
+                        }
+                    }
+                    warn!(symbols = symbols.len(), code = error.code, message = %error.message, "Batch price request failed");
+                    return Ok(vec![None; symbols.len()]);
+                }
+                Err(e) => {
+                    warn!(symbols = symbols.len(), error = %e, "Unexpected batch price response");
+                    return Ok(vec![None; symbols.len()]);
+                }
+            };
+            return Ok(symbols
+                .iter()
+                .map(|symbol| match entries.remove(symbol.as_str()) {
+                    Some(BatchPriceEntry::Price(price)) => Some(Quote::new(price.price)),
+                    Some(BatchPriceEntry::Error(error)) => {
+                        trace!(symbol = %symbol, message = %error.message, "No price in batch");
+                        None
+                    }
+                    None => None,
+                })
+                .collect());
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for TwelveData {
+    fn name(&self) -> &str {
+        "twelvedata"
+    }
+
+    #[instrument(skip(self))]
+    async fn fetch_price(&self, symbol: &str) -> Result<Option<Quote>, Error> {
+        if self.quotes {
+            self.fetch_one::<DailyQuote>(symbol, None, Quote::from)
+                .await
+        } else {
+            self.fetch_one::<Price>(symbol, None, |price| Quote::new(price.price))
+                .await
+        }
+    }
+
+    // Crypto pairs take the exchange by name, as in `exchange=Binance`.
+    async fn fetch_venue_price(&self, symbol: &str, venue: &str) -> Result<Option<Quote>, Error> {
+        self.fetch_one::<Price>(symbol, Some(venue), |price| Quote::new(price.price))
+            .await
+    }
+
+    fn batch_size(&self) -> usize {
+        if self.quotes {
+            1
+        } else {
+            self.batch_size
+        }
+    }
+
+    // Exchange-qualified symbols need their own `exchange` parameter, so
+    // only plain ones share a request. A request that fails fails its own
+    // symbols and the rest keep their prices.
+    #[instrument(skip(self, symbols), fields(symbols = symbols.len()))]
+    async fn fetch_prices(&self, symbols: &[String]) -> Result<Vec<SymbolQuote>, Error> {
+        let mut prices: Vec<SymbolQuote> = vec![Ok(None); symbols.len()];
+        let mut failure = None;
+        let (plain, qualified): (Vec<usize>, Vec<usize>) =
+            (0..symbols.len()).partition(|i| SymbolInfo::parse(&symbols[*i]).exchange.is_none());
+        let mut singles = qualified;
+        for chunk in plain.chunks(self.batch_size()) {
+            if let [i] = chunk {
+                singles.push(*i);
+                continue;
+            }
+            let batch: Vec<&String> = chunk.iter().map(|i| &symbols[*i]).collect();
+            match self.fetch_batch(&batch).await {
+                Ok(batch) => {
+                    for (i, price) in chunk.iter().zip(batch) {
+                        prices[*i] = Ok(price);
+                    }
+                }
+                Err(e) => {
+                    warn!(symbols = chunk.len(), error = %e, "Failed to fetch batch prices");
+                    for i in chunk {
+                        prices[*i] = Err(SymbolError(e.to_string()));
+                    }
+                    failure.get_or_insert(e);
+                }
+            }
+        }
+        for i in singles {
+            prices[i] = match self.fetch_price(&symbols[i]).await {
+                Ok(price) => Ok(price),
+                Err(e) => {
+                    warn!(symbol = %symbols[i], error = %e, "Failed to fetch price");
+                    let error = SymbolError(e.to_string());
+                    failure.get_or_insert(e);
+                    Err(error)
+                }
+            };
+        }
+        settle(prices, failure)
+    }
+
+    async fn fetch_market_state(&self, market: &Markets) -> Result<u64, Error> {
+        Ok(self.fetch_market_hours(market).await?.seconds_until_open)
+    }
+
+    // Holidays come back closed and half days with an early time to close.
+    #[instrument(skip(self))]
+    async fn fetch_market_hours(&self, market: &Markets) -> Result<MarketHours, Error> {
+        let open = MarketHours {
+            seconds_until_open: 0,
+            seconds_until_close: None,
+        };
+        let m = market.to_string();
+        let url = endpoints::url::<MarketStates>(
+            &self.regions.selected(),
+            &format!("exchange={}", market),
+            self.keys.active(),
+        );
+        let response = check_status(reqwest::get(&url).await?)?;
+        let data = response.text().await?;
+        let states = match endpoints::parse::<<MarketStates as Endpoint>::Response>(&data) {
+            Ok(Ok(states)) => states,
+            Ok(Err(error)) => {
+                warn!(market = %m, code = error.code, message = %error.message, "Market state request failed");
+                return Ok(open);
+            }
+            Err(e) => {
+                warn!(market = %m, error = %e, "Unexpected market state response");
+                return Ok(open);
+            }
+        };
+        let Some(state) = states.first() else {
+            return Ok(open);
+        };
+        if state.is_market_open {
+            let seconds_until_close = state.seconds_to_close();
+            trace!(market = %m, ?seconds_until_close, "Market is open");
+            return Ok(MarketHours {
+                seconds_until_close,
+                ..open
+            });
+        }
+        let seconds = state.seconds_to_open();
+        info!(market = %m, seconds, "Market is closed, time to open");
+        Ok(MarketHours {
+            seconds_until_open: seconds,
+            seconds_until_close: None,
+        })
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::CycleContext;
+use crate::metrics;
+use crate::sink::{FetchOutcome, PriceUpdate, Sink};
+
+/// Symbols scoring below this are flagged as degraded.
+pub const DEGRADED_SCORE: f64 = 0.8;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct QualityConfig {
+    pub window_secs: u64,
+    /// A tick moving more than this from the previous one counts as an anomaly.
+    pub anomaly_pct: f64,
+    /// Gaps between successful fetches longer than this start to cost score.
+    pub stale_after_secs: u64,
+}
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        QualityConfig {
+            window_secs: 60 * 60,
+            anomaly_pct: 10.,
+            stale_after_secs: 15 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QualityReport {
+    pub symbol: String,
+    pub score: f64,
+    pub success_rate: f64,
+    pub staleness_secs: i64,
+    pub anomalies: usize,
+    pub fetches: usize,
+}
+
+#[derive(Debug, Default)]
+struct History {
+    fetches: VecDeque<(DateTime<Utc>, FetchOutcome)>,
+    anomalies: VecDeque<DateTime<Utc>>,
+    last_price: Option<f64>,
+    last_attempt: Option<DateTime<Utc>>,
+    last_success: Option<DateTime<Utc>>,
+}
+
+/// Scores each symbol between 0 and 1 from its fetch success rate, how
+/// long it has gone without a price while being polled, and how many ticks
+/// jumped implausibly, all over a rolling window.
+pub struct QualityTracker {
+    config: QualityConfig,
+    history: Mutex<HashMap<String, History>>,
+}
+
+impl QualityTracker {
+    pub fn new(config: QualityConfig) -> Self {
+        QualityTracker {
+            config,
+            history: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Only time spent polling without success counts, so closed markets
+    // don't look stale.
+    fn report(&self, symbol: &str, history: &History) -> QualityReport {
+        let fetches = history.fetches.len();
+        let successes = history
+            .fetches
+            .iter()
+            .filter(|(_, o)| *o == FetchOutcome::Success)
+            .count();
+        let success_rate = if fetches == 0 {
+            1.
+        } else {
+            successes as f64 / fetches as f64
+        };
+        let staleness_secs = match (history.last_attempt, history.last_success) {
+            (Some(attempt), Some(success)) => (attempt - success).num_seconds().max(0),
+            (Some(attempt), None) => history
+                .fetches
+                .front()
+                .map(|(first, _)| (attempt - *first).num_seconds())
+                .unwrap_or_default(),
+            _ => 0,
+        };
+        let stale_after = self.config.stale_after_secs.max(1) as f64;
+        let freshness = if staleness_secs as f64 <= stale_after {
+            1.
+        } else {
+            stale_after / staleness_secs as f64
+        };
+        let anomalies = history.anomalies.len();
+        let cleanliness = 1. - (anomalies as f64 / successes.max(1) as f64).min(1.);
+        QualityReport {
+            symbol: symbol.to_string(),
+            score: success_rate * freshness * cleanliness,
+            success_rate,
+            staleness_secs,
+            anomalies,
+            fetches,
+        }
+    }
+
+    pub fn reports(&self) -> Vec<QualityReport> {
+        let history = self.history.lock().unwrap();
+        let sorted: BTreeMap<_, _> = history.iter().collect();
+        sorted
+            .into_iter()
+            .map(|(symbol, h)| self.report(symbol, h))
+            .collect()
+    }
+
+    fn prune(&self, history: &mut History, now: DateTime<Utc>) {
+        let cutoff = now - Duration::seconds(self.config.window_secs as i64);
+        while history.fetches.front().is_some_and(|(at, _)| *at < cutoff) {
+            history.fetches.pop_front();
+        }
+        while history.anomalies.front().is_some_and(|at| *at < cutoff) {
+            history.anomalies.pop_front();
+        }
+    }
+}
+
+impl Sink for QualityTracker {
+    fn record(&self, update: &PriceUpdate) {
+        let mut history = self.history.lock().unwrap();
+        let history = history.entry(update.symbol.clone()).or_default();
+        if let Some(last) = history.last_price.filter(|p| *p != 0.) {
+            let change = ((update.price - last) / last).abs() * 100.;
+            if change > self.config.anomaly_pct {
+                history.anomalies.push_back(update.timestamp);
+            }
+        }
+        history.last_price = Some(update.price);
+        history.last_success = Some(update.timestamp);
+    }
+
+    fn on_fetch(&self, symbol: &str, outcome: FetchOutcome, at: DateTime<Utc>) {
+        let mut history = self.history.lock().unwrap();
+        let history = history.entry(symbol.to_string()).or_default();
+        history.fetches.push_back((at, outcome));
+        history.last_attempt = Some(at);
+    }
+
+    fn on_cycle(&self, cycle: &CycleContext) {
+        let mut history = self.history.lock().unwrap();
+        for (symbol, h) in history.iter_mut() {
+            self.prune(h, cycle.now);
+            let report = self.report(symbol, h);
+            metrics::update_data_quality(symbol, report.score);
+        }
+    }
+}
@@ -0,0 +1,421 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::history::{Adjustment, History, Resolution};
+use crate::PricePoint;
+
+/// How far back `price(...)` looks for the latest price.
+const LOOKBACK: Duration = Duration::days(1);
+
+#[derive(Debug, Error, PartialEq)]
+pub enum QueryError {
+    #[error("at {at}: {message}")]
+    Syntax { at: usize, message: String },
+    #[error("unknown function `{0}`")]
+    UnknownFunction(String),
+    #[error("`{function}` takes {expected}")]
+    Arguments {
+        function: String,
+        expected: &'static str,
+    },
+    #[error("no prices for {0} in range")]
+    NoData(String),
+    #[error("result is not a number, such as after dividing by zero")]
+    NotFinite,
+    #[error("window reaches past the earliest time there is")]
+    OutOfRange,
+}
+
+/// Reduces the prices in a window to one value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Aggregation {
+    Avg,
+    Min,
+    Max,
+    Sum,
+    Count,
+    Stddev,
+    /// Last minus first.
+    Delta,
+    /// Last relative to first, in percent.
+    PctChange,
+}
+
+impl Aggregation {
+    const ALL: [(&'static str, Aggregation); 8] = [
+        ("avg_over_time", Aggregation::Avg),
+        ("min_over_time", Aggregation::Min),
+        ("max_over_time", Aggregation::Max),
+        ("sum_over_time", Aggregation::Sum),
+        ("count_over_time", Aggregation::Count),
+        ("stddev_over_time", Aggregation::Stddev),
+        ("delta", Aggregation::Delta),
+        ("pct_change", Aggregation::PctChange),
+    ];
+
+    fn named(name: &str) -> Option<Self> {
+        Self::ALL.iter().find(|(n, _)| *n == name).map(|(_, a)| *a)
+    }
+
+    fn apply(self, prices: &[f64]) -> Option<f64> {
+        if self == Aggregation::Count {
+            return Some(prices.len() as f64);
+        }
+        let (first, last) = (*prices.first()?, *prices.last()?);
+        let n = prices.len() as f64;
+        let mean = prices.iter().sum::<f64>() / n;
+        Some(match self {
+            Aggregation::Avg => mean,
+            Aggregation::Min => prices.iter().copied().fold(f64::INFINITY, f64::min),
+            Aggregation::Max => prices.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            Aggregation::Sum => mean * n,
+            Aggregation::Count => n,
+            Aggregation::Stddev => {
+                (prices.iter().map(|p| (p - mean).powi(2)).sum::<f64>() / n).sqrt()
+            }
+            Aggregation::Delta => last - first,
+            Aggregation::PctChange => (last - first) / first * 100.,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    /// The latest price of the symbol.
+    Price(String),
+    OverTime {
+        aggregation: Aggregation,
+        symbol: String,
+        window: Duration,
+    },
+    Negate(Box<Expr>),
+    Binary {
+        op: Op,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Number(f64),
+    Duration(Duration),
+    Open,
+    Close,
+    Comma,
+    Op(char),
+}
+
+fn syntax(at: usize, message: impl Into<String>) -> QueryError {
+    QueryError::Syntax {
+        at,
+        message: message.into(),
+    }
+}
+
+fn unit(c: char) -> Option<i64> {
+    Some(match c {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        'w' => 7 * 86400,
+        _ => return None,
+    })
+}
+
+// Tokens with the offset each starts at.
+fn lex(source: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+    let chars: Vec<(usize, char)> = source.char_indices().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while let Some(&(at, c)) = chars.get(i) {
+        i += 1;
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ',' => Token::Comma,
+            '+' | '-' | '*' | '/' => Token::Op(c),
+            '"' | '\'' => {
+                let start = i;
+                while chars.get(i).is_some_and(|(_, q)| *q != c) {
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    return Err(syntax(at, "unterminated string"));
+                }
+                i += 1;
+                Token::Str(chars[start..i - 1].iter().map(|(_, c)| c).collect())
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let start = i - 1;
+                while chars
+                    .get(i)
+                    .is_some_and(|(_, c)| c.is_ascii_alphanumeric() || *c == '.')
+                {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().map(|(_, c)| c).collect();
+                if text.ends_with(|c: char| c.is_ascii_alphabetic()) {
+                    Token::Duration(duration(&text).ok_or_else(|| {
+                        syntax(
+                            at,
+                            format!("bad duration `{}`, use e.g. 30s, 5m, 1h or 1d", text),
+                        )
+                    })?)
+                } else {
+                    Token::Number(
+                        text.parse()
+                            .map_err(|_| syntax(at, format!("bad number `{}`", text)))?,
+                    )
+                }
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let start = i - 1;
+                while chars
+                    .get(i)
+                    .is_some_and(|(_, c)| c.is_ascii_alphanumeric() || *c == '_')
+                {
+                    i += 1;
+                }
+                Token::Ident(chars[start..i].iter().map(|(_, c)| c).collect())
+            }
+            c => return Err(syntax(at, format!("unexpected `{}`", c))),
+        };
+        tokens.push((at, token));
+    }
+    Ok(tokens)
+}
+
+/// Whole numbers each followed by a unit of `s`, `m`, `h`, `d` or `w`, as
+/// in `1h30m`.
+pub fn duration(text: &str) -> Option<Duration> {
+    let mut total = 0i64;
+    let mut digits = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+        } else {
+            let n: i64 = digits.parse().ok()?;
+            total = total.checked_add(n.checked_mul(unit(c)?)?)?;
+            digits.clear();
+        }
+    }
+    if !digits.is_empty() || total <= 0 {
+        return None;
+    }
+    Duration::try_seconds(total)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(_, t)| t)
+    }
+
+    fn at(&self) -> usize {
+        self.tokens.get(self.next).map_or(self.end, |(at, _)| *at)
+    }
+
+    fn bump(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.next).map(|(_, t)| t.clone());
+        self.next += 1;
+        token
+    }
+
+    fn expect(&mut self, token: Token, what: &str) -> Result<(), QueryError> {
+        let at = self.at();
+        match self.bump() {
+            Some(t) if t == token => Ok(()),
+            _ => Err(syntax(at, format!("expected {}", what))),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.term()?;
+        while let Some(Token::Op(c @ ('+' | '-'))) = self.peek() {
+            let op = if *c == '+' { Op::Add } else { Op::Sub };
+            self.bump();
+            let right = self.term()?;
+            left = Expr::Binary {
+                op,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+        Ok(left)
+    }
+
+    fn term(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.unary()?;
+        while let Some(Token::Op(c @ ('*' | '/'))) = self.peek() {
+            let op = if *c == '*' { Op::Mul } else { Op::Div };
+            self.bump();
+            let right = self.unary()?;
+            left = Expr::Binary {
+                op,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, QueryError> {
+        if self.peek() == Some(&Token::Op('-')) {
+            self.bump();
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, QueryError> {
+        let at = self.at();
+        match self.bump() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Open) => {
+                let expr = self.expr()?;
+                self.expect(Token::Close, "`)`")?;
+                Ok(expr)
+            }
+            Some(Token::Ident(name)) => {
+                self.expect(Token::Open, "`(` after a function name")?;
+                self.call(name)
+            }
+            _ => Err(syntax(at, "expected a number, `(` or a function")),
+        }
+    }
+
+    fn call(&mut self, name: String) -> Result<Expr, QueryError> {
+        if name == "price" {
+            let symbol = self.symbol(&name, "a quoted symbol")?;
+            self.expect(Token::Close, "`)`")?;
+            return Ok(Expr::Price(symbol));
+        }
+        let Some(aggregation) = Aggregation::named(&name) else {
+            return Err(QueryError::UnknownFunction(name));
+        };
+        const EXPECTED: &str = "a price and a window, as in `(price(\"AAPL\"), 1h)`";
+        let ok = matches!(self.bump(), Some(Token::Ident(f)) if f == "price")
+            && self.bump() == Some(Token::Open);
+        if !ok {
+            return Err(QueryError::Arguments {
+                function: name,
+                expected: EXPECTED,
+            });
+        }
+        let symbol = self.symbol(&name, EXPECTED)?;
+        self.expect(Token::Close, "`)`")?;
+        self.expect(Token::Comma, "`,` before the window")?;
+        let window = match self.bump() {
+            Some(Token::Duration(window)) => window,
+            _ => {
+                return Err(QueryError::Arguments {
+                    function: name,
+                    expected: EXPECTED,
+                })
+            }
+        };
+        self.expect(Token::Close, "`)`")?;
+        Ok(Expr::OverTime {
+            aggregation,
+            symbol,
+            window,
+        })
+    }
+
+    fn symbol(&mut self, function: &str, expected: &'static str) -> Result<String, QueryError> {
+        match self.bump() {
+            Some(Token::Str(symbol)) if !symbol.is_empty() => Ok(symbol),
+            _ => Err(QueryError::Arguments {
+                function: function.to_string(),
+                expected,
+            }),
+        }
+    }
+}
+
+/// Parses expressions like `avg_over_time(price("AAPL"), 1h)`: `price` of a
+/// symbol, the functions of [`Aggregation`] over a window of it, numbers,
+/// and `+ - * /` between them.
+pub fn parse(source: &str) -> Result<Expr, QueryError> {
+    let mut parser = Parser {
+        tokens: lex(source)?,
+        next: 0,
+        end: source.len(),
+    };
+    let expr = parser.expr()?;
+    if parser.peek().is_some() {
+        return Err(syntax(parser.at(), "unexpected input after the expression"));
+    }
+    Ok(expr)
+}
+
+// Prices of `symbol` in `(at - window, at]`, oldest first.
+fn window(
+    history: &History,
+    symbol: &str,
+    window: Duration,
+    at: DateTime<Utc>,
+) -> Result<Vec<f64>, QueryError> {
+    let since = at
+        .checked_sub_signed(window)
+        .ok_or(QueryError::OutOfRange)?;
+    Ok(history
+        .query(symbol, since, Utc::now(), Resolution::Auto, Adjustment::Raw)
+        .points
+        .into_iter()
+        .filter(|p: &PricePoint| p.timestamp > since && p.timestamp <= at)
+        .map(|p| p.price)
+        .collect())
+}
+
+/// The value of `expr` at `at`, from the history.
+pub fn eval(expr: &Expr, history: &History, at: DateTime<Utc>) -> Result<f64, QueryError> {
+    let value = match expr {
+        Expr::Number(n) => *n,
+        Expr::Price(symbol) => *window(history, symbol, LOOKBACK, at)?
+            .last()
+            .ok_or_else(|| QueryError::NoData(symbol.clone()))?,
+        Expr::OverTime {
+            aggregation,
+            symbol,
+            window: span,
+        } => aggregation
+            .apply(&window(history, symbol, *span, at)?)
+            .ok_or_else(|| QueryError::NoData(symbol.clone()))?,
+        Expr::Negate(expr) => -eval(expr, history, at)?,
+        Expr::Binary { op, left, right } => {
+            let (left, right) = (eval(left, history, at)?, eval(right, history, at)?);
+            match op {
+                Op::Add => left + right,
+                Op::Sub => left - right,
+                Op::Mul => left * right,
+                Op::Div => left / right,
+            }
+        }
+    };
+    if !value.is_finite() {
+        return Err(QueryError::NotFinite);
+    }
+    Ok(value)
+}
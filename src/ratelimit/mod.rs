@@ -0,0 +1,151 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tracing::{debug, instrument};
+
+use crate::clock::Clock;
+use crate::config::RateLimit;
+use crate::metrics;
+
+/// Requests sent inside one limit's trailing period.
+struct Window {
+    limit: RateLimit,
+    label: String,
+    sent: VecDeque<DateTime<Utc>>,
+}
+
+impl Window {
+    fn period(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.limit.period_secs as i64)
+    }
+
+    fn expire(&mut self, now: DateTime<Utc>) {
+        let start = now - self.period();
+        while self.sent.front().is_some_and(|at| *at <= start) {
+            self.sent.pop_front();
+        }
+    }
+
+    fn remaining(&self) -> u64 {
+        self.limit.requests.saturating_sub(self.sent.len() as u64)
+    }
+
+    /// How long until `cost` more requests fit, zero if they do now.
+    fn wait(&self, cost: u64, now: DateTime<Utc>) -> chrono::Duration {
+        let over = (self.sent.len() as u64 + cost).saturating_sub(self.limit.requests);
+        match over.checked_sub(1) {
+            None => chrono::Duration::zero(),
+            Some(i) => (self.sent[i as usize] + self.period() - now).max(chrono::Duration::zero()),
+        }
+    }
+}
+
+/// Sliding-window limiter enforcing every configured limit at once, e.g. 8
+/// requests a minute and 800 a day. Each limit counts the requests of its
+/// own trailing period, so bursts are allowed up to the limit and never
+/// past it.
+pub struct RateLimiter {
+    windows: Mutex<Vec<Window>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl RateLimiter {
+    /// Limits with a zero period are ignored.
+    pub fn new(limits: &[RateLimit], clock: Arc<dyn Clock>) -> Self {
+        let windows: Vec<Window> = limits
+            .iter()
+            .filter(|l| l.period_secs > 0)
+            .map(|l| Window {
+                limit: *l,
+                label: format!("{}s", l.period_secs),
+                sent: VecDeque::new(),
+            })
+            .collect();
+        for window in &windows {
+            metrics::update_rate_limit_remaining(&window.label, window.remaining());
+        }
+        RateLimiter {
+            windows: Mutex::new(windows),
+            clock,
+        }
+    }
+
+    /// Waits until every limit has room for `cost` requests, then takes it.
+    /// A cost larger than a limit is capped to it, so a big batch waits for
+    /// an empty window rather than forever.
+    #[instrument(skip(self))]
+    pub async fn acquire(&self, cost: u64) {
+        self.acquire_with(cost, |_| {}).await
+    }
+
+    /// [`acquire`](Self::acquire), telling `waiting` how long each wait
+    /// for room will be before it starts.
+    pub async fn acquire_with(&self, cost: u64, waiting: impl Fn(Duration)) {
+        loop {
+            let wait = {
+                let now = self.clock.now();
+                let mut windows = self.windows.lock().unwrap();
+                let mut wait = chrono::Duration::zero();
+                for window in windows.iter_mut() {
+                    window.expire(now);
+                    wait = wait.max(window.wait(cost.min(window.limit.requests), now));
+                }
+                if wait.is_zero() {
+                    for window in windows.iter_mut() {
+                        let cost = cost.min(window.limit.requests);
+                        window.sent.extend(std::iter::repeat_n(now, cost as usize));
+                        metrics::update_rate_limit_remaining(&window.label, window.remaining());
+                    }
+                    return;
+                }
+                wait
+            };
+            debug!(
+                cost,
+                wait_secs = wait.num_seconds(),
+                "Waiting for rate limit"
+            );
+            let wait = wait
+                .to_std()
+                .unwrap_or_default()
+                .max(Duration::from_millis(1));
+            waiting(wait);
+            self.clock.sleep(wait).await;
+        }
+    }
+
+    /// Takes `cost` requests only if every limit has room for them now.
+    pub fn try_acquire(&self, cost: u64) -> bool {
+        let now = self.clock.now();
+        let mut windows = self.windows.lock().unwrap();
+        for window in windows.iter_mut() {
+            window.expire(now);
+        }
+        if windows.iter().any(|w| w.remaining() < cost) {
+            return false;
+        }
+        for window in windows.iter_mut() {
+            window.sent.extend(std::iter::repeat_n(now, cost as usize));
+            metrics::update_rate_limit_remaining(&window.label, window.remaining());
+        }
+        true
+    }
+
+    /// Requests left in each limit's current window, strictest first.
+    pub fn remaining(&self) -> Vec<(RateLimit, u64)> {
+        let now = self.clock.now();
+        let mut windows = self.windows.lock().unwrap();
+        let mut remaining: Vec<(RateLimit, u64)> = windows
+            .iter_mut()
+            .map(|window| {
+                window.expire(now);
+                metrics::update_rate_limit_remaining(&window.label, window.remaining());
+                (window.limit, window.remaining())
+            })
+            .collect();
+        remaining.sort_by_key(|(_, left)| *left);
+        remaining
+    }
+}
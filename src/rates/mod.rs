@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::metrics;
+use crate::sink::{PriceUpdate, Sink};
+use crate::AssetClass;
+
+/// Yield curve spread such as 2s10s, `long` minus `short`, in percentage points.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Spread {
+    pub name: String,
+    pub long: String,
+    pub short: String,
+}
+
+/// Earnings yield of the equity market minus a treasury yield.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EquityRiskPremium {
+    pub yield_symbol: String,
+    /// Percent, e.g. 4.6 for a forward P/E of about 21.7.
+    pub earnings_yield: f64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RatesConfig {
+    pub spreads: Vec<Spread>,
+    pub equity_risk_premium: Option<EquityRiskPremium>,
+}
+
+pub struct RatesTracker {
+    config: RatesConfig,
+    yields: Mutex<HashMap<String, f64>>,
+}
+
+impl RatesTracker {
+    pub fn new(config: RatesConfig) -> Self {
+        RatesTracker {
+            config,
+            yields: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Sink for RatesTracker {
+    fn record(&self, update: &PriceUpdate) {
+        if AssetClass::of(&update.symbol) != AssetClass::Rate {
+            return;
+        }
+        let mut yields = self.yields.lock().unwrap();
+        yields.insert(update.symbol.clone(), update.price);
+
+        for spread in &self.config.spreads {
+            if let (Some(long), Some(short)) = (yields.get(&spread.long), yields.get(&spread.short))
+            {
+                metrics::update_rate_spread(&spread.name, long - short);
+            }
+        }
+        if let Some(erp) = &self.config.equity_risk_premium {
+            if let Some(rate) = yields.get(&erp.yield_symbol) {
+                metrics::update_equity_risk_premium(erp.earnings_yield - rate);
+            }
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+use tracing::error;
+
+use crate::auth::{TokenRecord, TOKENS_FILE};
+use crate::encryption::StorageKey;
+use crate::events;
+use crate::metrics;
+use crate::notes::{SymbolNote, NOTES_FILE};
+use crate::portfolio::{Holding, PORTFOLIO_FILE};
+use crate::toggles::TOGGLES_FILE;
+use crate::Tickers;
+
+/// Copies kept of each state file from before its last saves, newest as
+/// `<file>.1`.
+pub const GENERATIONS: usize = 3;
+
+/// How a state file that wouldn't parse was put right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    /// Replaced by the newest backup that parses.
+    Restored,
+    /// Only its unreadable lines were dropped.
+    Repaired,
+    /// No backup parsed, so it starts over empty.
+    Reset,
+}
+
+impl Outcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::Restored => "restored",
+            Outcome::Repaired => "repaired",
+            Outcome::Reset => "reset",
+        }
+    }
+}
+
+/// A corrupt state file found at startup, moved aside and recovered.
+#[derive(Debug, Clone, Serialize)]
+pub struct Recovery {
+    pub path: PathBuf,
+    /// Where the corrupt file was moved, to look into later.
+    pub quarantined: PathBuf,
+    pub restored_from: Option<PathBuf>,
+    pub outcome: Outcome,
+    pub error: String,
+}
+
+#[derive(Debug, Error)]
+pub enum RecoveryError {
+    /// Not recovered like the rest: starting over would open the API to
+    /// anyone and a backup could bring back revoked tokens.
+    #[error(
+        "API token store {} is corrupt ({error}); fix it, or restore a backup \
+         such as {} knowing tokens revoked since come back with it",
+        path.display(),
+        backup_path(path, 1).display()
+    )]
+    Tokens { path: PathBuf, error: String },
+}
+
+/// Backup `generation` of the file at `path`.
+pub fn backup_path(path: &Path, generation: usize) -> PathBuf {
+    suffixed(path, &generation.to_string())
+}
+
+/// Keeps the file at `path` as its newest backup, shifting the older ones
+/// down and dropping the oldest. Called before a save replaces it.
+pub fn backup(path: &Path) -> std::io::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    for generation in (1..GENERATIONS).rev() {
+        let from = backup_path(path, generation);
+        if from.exists() {
+            std::fs::rename(&from, backup_path(path, generation + 1))?;
+        }
+    }
+    std::fs::copy(path, backup_path(path, 1))?;
+    Ok(())
+}
+
+/// Moves the file at `path` aside as `<file>.corrupt-<unix seconds>`.
+pub fn quarantine(path: &Path) -> std::io::Result<PathBuf> {
+    let to = suffixed(path, &format!("corrupt-{}", Utc::now().timestamp()));
+    std::fs::rename(path, &to)?;
+    Ok(to)
+}
+
+/// Puts the file at `path` right when it doesn't parse as a `T`: it is
+/// quarantined and the newest backup that parses is copied in its place,
+/// or with none the file is left missing so it starts over empty. `None`
+/// when the file is missing or fine.
+pub fn recover<T: DeserializeOwned>(path: &Path) -> Option<Recovery> {
+    let data = std::fs::read(path).ok()?;
+    let error = serde_json::from_slice::<T>(&data).err()?.to_string();
+    let quarantined = match quarantine(path) {
+        Ok(quarantined) => quarantined,
+        Err(e) => {
+            error!(path = %path.display(), error = %e, "Failed to quarantine corrupt state file");
+            return None;
+        }
+    };
+    let restored_from = (1..=GENERATIONS)
+        .map(|generation| backup_path(path, generation))
+        .find(|backup| {
+            std::fs::read(backup).is_ok_and(|data| serde_json::from_slice::<T>(&data).is_ok())
+                && std::fs::copy(backup, path).is_ok()
+        });
+    let outcome = match restored_from {
+        Some(_) => Outcome::Restored,
+        None => Outcome::Reset,
+    };
+    Some(incident(Recovery {
+        path: path.to_path_buf(),
+        quarantined,
+        restored_from,
+        outcome,
+        error,
+    }))
+}
+
+/// Recovers the tickers file at `tickers` and the state files in
+/// `state_dir` that don't parse, before anything reads them. A token store
+/// that doesn't parse is left for an operator and fails the check.
+pub fn check_state(tickers: &Path, state_dir: &Path) -> Result<Vec<Recovery>, RecoveryError> {
+    let tokens = state_dir.join(TOKENS_FILE);
+    if let Ok(data) = std::fs::read(&tokens) {
+        if let Err(e) = serde_json::from_slice::<Vec<TokenRecord>>(&data) {
+            error!(incident = "corrupt_state", path = %tokens.display(), error = %e, "API token store is corrupt");
+            return Err(RecoveryError::Tokens {
+                path: tokens,
+                error: e.to_string(),
+            });
+        }
+    }
+    Ok([
+        recover::<Tickers>(tickers),
+        recover::<BTreeMap<String, Holding>>(&state_dir.join(PORTFOLIO_FILE)),
+        recover::<BTreeMap<String, SymbolNote>>(&state_dir.join(NOTES_FILE)),
+        recover::<BTreeSet<String>>(&state_dir.join(TOGGLES_FILE)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect())
+}
+
+/// Drops the lines of the event log at `path` that don't read back, which
+/// a crash mid-append leaves and the next append glues onto. The whole log
+/// is quarantined first. A log none of whose lines decrypt is left to fail
+/// loading, being under another key rather than damaged.
+pub fn recover_journal(path: &Path, key: Option<&StorageKey>) -> Option<Recovery> {
+    let data = std::fs::read_to_string(path).ok()?;
+    let lines: Vec<&str> = data.lines().filter(|l| !l.trim().is_empty()).collect();
+    let readable: Vec<&str> = lines
+        .iter()
+        .copied()
+        .filter(|line| events::parse_line(line, key).is_ok())
+        .collect();
+    let unreadable = lines.len() - readable.len();
+    if unreadable == 0 || (readable.is_empty() && key.is_some()) {
+        return None;
+    }
+    let quarantined = suffixed(path, &format!("corrupt-{}", Utc::now().timestamp()));
+    let rewritten = std::fs::copy(path, &quarantined).and_then(|_| {
+        let tmp = suffixed(path, "tmp");
+        let mut kept = readable.join("\n");
+        if !kept.is_empty() {
+            kept.push('\n');
+        }
+        std::fs::write(&tmp, kept)?;
+        std::fs::rename(&tmp, path)
+    });
+    if let Err(e) = rewritten {
+        error!(path = %path.display(), error = %e, "Failed to repair event log");
+        return None;
+    }
+    Some(incident(Recovery {
+        path: path.to_path_buf(),
+        quarantined,
+        restored_from: None,
+        outcome: Outcome::Repaired,
+        error: format!("{unreadable} unreadable lines"),
+    }))
+}
+
+// Logs `recovery` as one structured event, for log alerting to match on.
+fn incident(recovery: Recovery) -> Recovery {
+    let file = recovery
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    metrics::update_state_recovery(&file, recovery.outcome.as_str());
+    error!(
+        incident = "corrupt_state",
+        path = %recovery.path.display(),
+        quarantined = %recovery.quarantined.display(),
+        restored_from = recovery.restored_from.as_ref().map(|p| p.display().to_string()),
+        outcome = recovery.outcome.as_str(),
+        error = %recovery.error,
+        "Recovered corrupt state file"
+    );
+    recovery
+}
+
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
@@ -0,0 +1,71 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use notify::{Event, RecursiveMode, Watcher};
+use tokio::sync::{mpsc, watch};
+use tracing::{error, info, warn};
+
+use crate::{bootstrap, Tickers};
+
+/// Editors save in several steps; changes this close together are one
+/// reload.
+const SETTLE: Duration = Duration::from_millis(100);
+
+/// Watches the tickers file at `path` and publishes the watchlist each time
+/// it changes on disk, starting from `current`. A file that won't parse is
+/// logged and the last good watchlist kept.
+///
+/// The directory is watched rather than the file, so saves that replace the
+/// file by renaming over it are seen too.
+pub fn watch_tickers(path: &Path, current: Tickers) -> notify::Result<watch::Receiver<Tickers>> {
+    let (changed_tx, mut changed) = mpsc::unbounded_channel();
+    let name = path.file_name().map(|name| name.to_owned());
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+            Ok(event)
+                if !event.kind.is_access()
+                    && event.paths.iter().any(|p| p.file_name() == name.as_deref()) =>
+            {
+                let _ = changed_tx.send(());
+            }
+            Ok(_) => {}
+            Err(e) => warn!(error = %e, "Tickers file watch failed"),
+        })?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+    let (tx, rx) = watch::channel(current);
+    let path = path.to_path_buf();
+    tokio::spawn(async move {
+        // Watching stops when the watcher is dropped.
+        let _watcher = watcher;
+        info!(path = %path.display(), "Watching tickers file");
+        while changed.recv().await.is_some() {
+            tokio::time::sleep(SETTLE).await;
+            while changed.try_recv().is_ok() {}
+            let tickers = match bootstrap::load_tickers(&path) {
+                Ok(tickers) => tickers,
+                Err(e) => {
+                    error!(error = %e, "Failed to reload tickers, keeping the watchlist");
+                    continue;
+                }
+            };
+            let count = tickers.get_tickers().len();
+            let updated = tx.send_if_modified(|current| {
+                let updated = *current != tickers;
+                *current = tickers;
+                updated
+            });
+            if updated {
+                info!(tickers = count, "Tickers file changed, watchlist reloaded");
+            }
+            if tx.is_closed() {
+                break;
+            }
+        }
+    });
+    Ok(rx)
+}
@@ -0,0 +1,140 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::calendar::{self, Session};
+use crate::StockMarket;
+
+/// Where one day's return ends and the next begins.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReturnConvention {
+    /// Every mark counts toward the calendar day it lands on in the
+    /// timezone of record, extended hours included.
+    #[default]
+    MarkToMarket,
+    /// Days run from one session close of the configured exchange to the
+    /// next, so prints after the close count toward the next day.
+    CloseToClose,
+}
+
+/// Which days a return can be booked on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Settlement {
+    /// Weekends are days of their own, e.g. for crypto.
+    #[default]
+    Calendar,
+    /// Weekend marks settle on the following Monday.
+    TradingDays,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReturnsConfig {
+    pub convention: ReturnConvention,
+    /// IANA name of the timezone days are dated in.
+    pub timezone: String,
+    pub settlement: Settlement,
+}
+
+impl Default for ReturnsConfig {
+    fn default() -> Self {
+        ReturnsConfig {
+            convention: ReturnConvention::default(),
+            timezone: "UTC".into(),
+            settlement: Settlement::default(),
+        }
+    }
+}
+
+/// Assigns timestamps to the trading day of record. Shared by paper P&L,
+/// daily history closes, whole-day candles and benchmark tracking so they
+/// all agree on what "today" is.
+#[derive(Debug, Clone, Copy)]
+pub struct Conventions {
+    convention: ReturnConvention,
+    settlement: Settlement,
+    tz: Tz,
+    session: Session,
+}
+
+impl Default for Conventions {
+    fn default() -> Self {
+        Conventions::new(&ReturnsConfig::default(), StockMarket::NYSE)
+    }
+}
+
+fn is_weekend(date: NaiveDate) -> bool {
+    matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+impl Conventions {
+    /// `exchange` supplies the session close for close-to-close days. An
+    /// unknown timezone falls back to UTC.
+    pub fn new(config: &ReturnsConfig, exchange: StockMarket) -> Self {
+        let tz = config.timezone.parse::<Tz>().unwrap_or_else(|e| {
+            warn!(timezone = %config.timezone, error = %e, "Unknown returns timezone, using UTC");
+            Tz::UTC
+        });
+        Conventions {
+            convention: config.convention,
+            settlement: config.settlement,
+            tz,
+            session: calendar::session(exchange),
+        }
+    }
+
+    pub fn convention(&self) -> ReturnConvention {
+        self.convention
+    }
+
+    /// The trading day a mark taken at `at` counts toward.
+    pub fn day_of(&self, at: DateTime<Utc>) -> NaiveDate {
+        let mut day = match self.convention {
+            ReturnConvention::MarkToMarket => at.with_timezone(&self.tz).date_naive(),
+            ReturnConvention::CloseToClose => {
+                let local = at.with_timezone(&self.session.tz);
+                if local.time() >= self.session.close {
+                    local.date_naive() + Duration::days(1)
+                } else {
+                    local.date_naive()
+                }
+            }
+        };
+        if self.settlement == Settlement::TradingDays {
+            while is_weekend(day) {
+                day += Duration::days(1);
+            }
+        }
+        day
+    }
+
+    /// The first instant counting toward `day`.
+    pub fn day_start(&self, day: NaiveDate) -> DateTime<Utc> {
+        let mut first = day;
+        if self.settlement == Settlement::TradingDays {
+            while is_weekend(first - Duration::days(1)) {
+                first -= Duration::days(1);
+            }
+        }
+        let (tz, date, time) = match self.convention {
+            ReturnConvention::MarkToMarket => (self.tz, first, NaiveTime::MIN),
+            ReturnConvention::CloseToClose => (
+                self.session.tz,
+                first - Duration::days(1),
+                self.session.close,
+            ),
+        };
+        tz.from_local_datetime(&date.and_time(time))
+            .earliest()
+            .map(|start| start.with_timezone(&Utc))
+            .unwrap_or_else(|| date.and_time(time).and_utc())
+    }
+
+    /// Start of the day `at` counts toward.
+    pub fn truncate(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        self.day_start(self.day_of(at))
+    }
+}
@@ -0,0 +1,164 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tracing::{info, warn};
+
+#[derive(Debug, Clone)]
+pub struct ServiceOptions {
+    pub name: String,
+    pub executable: PathBuf,
+    pub config: PathBuf,
+    pub working_dir: PathBuf,
+    pub env_file: PathBuf,
+    /// Install as a per-user unit instead of a system one.
+    pub user: bool,
+}
+
+/// A systemd unit that runs fintek with readiness notification, the
+/// working directory holding the tickers file and state dir, and restarts
+/// on failure.
+pub fn systemd_unit(options: &ServiceOptions) -> String {
+    let wanted_by = if options.user {
+        "default.target"
+    } else {
+        "multi-user.target"
+    };
+    format!(
+        "[Unit]
+Description=fintek price poller
+After=network-online.target
+Wants=network-online.target
+StartLimitIntervalSec=300
+StartLimitBurst=10
+
+[Service]
+Type=notify
+NotifyAccess=main
+ExecStart={exe} --config {config} run
+WorkingDirectory={dir}
+EnvironmentFile=-{env}
+Restart=on-failure
+RestartSec=5
+
+[Install]
+WantedBy={wanted_by}
+",
+        exe = options.executable.display(),
+        config = options.config.display(),
+        dir = options.working_dir.display(),
+        env = options.env_file.display(),
+    )
+}
+
+pub fn unit_path(options: &ServiceOptions) -> PathBuf {
+    let dir = if options.user {
+        let config = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| Path::new(&h).join(".config")))
+            .unwrap_or_else(|| PathBuf::from(".config"));
+        config.join("systemd/user")
+    } else {
+        PathBuf::from("/etc/systemd/system")
+    };
+    dir.join(format!("{}.service", options.name))
+}
+
+/// Writes the unit and reloads systemd. Enabling is left to the operator.
+#[cfg(unix)]
+pub fn install(options: &ServiceOptions) -> std::io::Result<PathBuf> {
+    let path = unit_path(options);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, systemd_unit(options))?;
+    let mut reload = std::process::Command::new("systemctl");
+    if options.user {
+        reload.arg("--user");
+    }
+    let status = reload.arg("daemon-reload").status()?;
+    if !status.success() {
+        warn!(%status, "systemctl daemon-reload failed");
+    }
+    Ok(path)
+}
+
+/// Registers an auto-start service with the service control manager and
+/// restarts it on failure. fintek does not answer service control requests
+/// itself yet, so wrap it with a host such as WinSW if the SCM times it out.
+#[cfg(windows)]
+pub fn install(options: &ServiceOptions) -> std::io::Result<PathBuf> {
+    use std::process::Command;
+
+    let bin_path = format!(
+        "\"{}\" --config \"{}\" run",
+        options.executable.display(),
+        options.config.display()
+    );
+    let status = Command::new("sc.exe")
+        .args([
+            "create",
+            &options.name,
+            "binPath=",
+            &bin_path,
+            "start=",
+            "auto",
+        ])
+        .status()?;
+    if !status.success() {
+        return Err(std::io::Error::other(format!(
+            "sc.exe create failed: {}",
+            status
+        )));
+    }
+    let status = Command::new("sc.exe")
+        .args([
+            "failure",
+            &options.name,
+            "reset=",
+            "300",
+            "actions=",
+            "restart/5000/restart/5000/restart/5000",
+        ])
+        .status()?;
+    if !status.success() {
+        warn!(%status, "sc.exe failure failed");
+    }
+    Ok(options.executable.clone())
+}
+
+/// Sends `state` to the systemd notification socket, if there is one.
+#[cfg(target_os = "linux")]
+pub fn notify(state: &str) {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+    let Some(socket) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    let socket = socket.to_string_lossy().into_owned();
+    let addr = match socket.strip_prefix('@') {
+        Some(name) => SocketAddr::from_abstract_name(name.as_bytes()),
+        None => SocketAddr::from_pathname(&socket),
+    };
+    let result = addr.and_then(|addr| {
+        let datagram = UnixDatagram::unbound()?;
+        datagram.send_to_addr(state.as_bytes(), &addr)
+    });
+    if let Err(e) = result {
+        warn!(error = %e, socket, "Failed to notify systemd");
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn notify(_state: &str) {}
+
+pub fn notify_ready() {
+    info!("Service ready");
+    notify("READY=1");
+}
+
+/// Half the watchdog timeout systemd asks for, if it asks.
+pub fn watchdog_interval() -> Option<Duration> {
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    Some(Duration::from_micros(usec / 2))
+}
@@ -0,0 +1,215 @@
+use std::path::Path;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use thiserror::Error;
+use tokio::fs;
+use toml_edit::{Array, ArrayOfTables, DocumentMut, Item, Table, TableLike, Value};
+use tracing::{info, instrument};
+
+use crate::alerts::{AlertRule, AlertsConfig};
+use crate::indicators::IndicatorsConfig;
+use crate::Tickers;
+
+const PREFIX: &str = "fintek1";
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Error)]
+pub enum ShareError {
+    #[error("not a fintek watchlist export")]
+    Format,
+    #[error("signature does not match, the export was altered or signed with another key")]
+    Signature,
+    #[error("invalid encoding: {0}")]
+    Encoding(#[from] base64::DecodeError),
+    #[error("invalid payload: {0}")]
+    Payload(#[from] serde_json::Error),
+    #[error("invalid config: {0}")]
+    Config(#[from] toml_edit::TomlError),
+    #[error("invalid config: {0}")]
+    Settings(#[from] toml::de::Error),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// What an export carries: the watchlist and the indicator and alert
+/// settings that go with it. Exports from before the settings were shared
+/// import as the watchlist alone.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Shared {
+    pub tickers: Vec<String>,
+    #[serde(default)]
+    pub indicators: Option<IndicatorsConfig>,
+    #[serde(default)]
+    pub alert_rules: Vec<AlertRule>,
+}
+
+impl Shared {
+    pub fn new(tickers: &Tickers, indicators: &IndicatorsConfig, alerts: &AlertsConfig) -> Self {
+        Shared {
+            tickers: tickers.get_tickers().clone(),
+            indicators: Some(indicators.clone()),
+            alert_rules: alerts.rules.clone(),
+        }
+    }
+}
+
+fn mac(key: &[u8], payload: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(PREFIX.as_bytes());
+    mac.update(payload.as_bytes());
+    mac
+}
+
+/// Encodes the watchlist as `fintek1.<payload>.<signature>`, both parts
+/// base64url so the string can be pasted into URLs and chat messages.
+#[instrument(skip_all)]
+pub fn export(shared: &Shared, key: &[u8]) -> String {
+    let json = serde_json::to_vec(shared).expect("Failed to serialize watchlist");
+    let payload = URL_SAFE_NO_PAD.encode(json);
+    let signature = URL_SAFE_NO_PAD.encode(mac(key, &payload).finalize().into_bytes());
+    format!("{}.{}.{}", PREFIX, payload, signature)
+}
+
+#[instrument(skip(key))]
+pub fn import(encoded: &str, key: &[u8]) -> Result<Shared, ShareError> {
+    let mut parts = encoded.trim().split('.');
+    let (Some(PREFIX), Some(payload), Some(signature), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(ShareError::Format);
+    };
+    let signature = URL_SAFE_NO_PAD.decode(signature)?;
+    mac(key, payload)
+        .verify_slice(&signature)
+        .map_err(|_| ShareError::Signature)?;
+    let shared: Shared = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload)?)?;
+    info!(
+        count = shared.tickers.len(),
+        rules = shared.alert_rules.len(),
+        "Imported shared watchlist"
+    );
+    Ok(shared)
+}
+
+pub async fn export_to_file(
+    shared: &Shared,
+    key: &[u8],
+    path: impl AsRef<Path>,
+) -> Result<(), ShareError> {
+    fs::write(path, export(shared, key)).await?;
+    Ok(())
+}
+
+pub async fn import_from_file(key: &[u8], path: impl AsRef<Path>) -> Result<Shared, ShareError> {
+    import(&fs::read_to_string(path).await?, key)
+}
+
+/// Adds the imported symbols that aren't already watched, keeping existing order.
+pub fn merge(tickers: &mut Tickers, imported: &[String]) {
+    let mut merged = tickers.get_tickers().clone();
+    for symbol in imported {
+        if !merged.contains(symbol) {
+            merged.push(symbol.clone());
+        }
+    }
+    tickers.set_tickers(merged);
+}
+
+// The parts of a config file an import touches.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Settings {
+    indicators: IndicatorsConfig,
+    alerts: AlertsConfig,
+}
+
+/// Adds the imported indicator periods and alert rules that `config`, the
+/// text of a config file, doesn't have yet, and returns the edited text with
+/// the names of the rules added. The rest of the file, comments included,
+/// is left as it was.
+pub fn merge_config(config: &str, shared: &Shared) -> Result<(String, Vec<String>), ShareError> {
+    let mut document: DocumentMut = config.parse()?;
+    let current: Settings = toml::from_str(config)?;
+    if let Some(imported) = &shared.indicators {
+        let merged = merge_indicators(current.indicators, imported);
+        let indicators = section(&mut document, "indicators");
+        for (key, item) in table(&merged).iter() {
+            indicators.insert(key, item.clone());
+        }
+    }
+    let names: Vec<String> = current.alerts.rules.iter().map(AlertRule::name).collect();
+    let mut added = vec![];
+    for rule in &shared.alert_rules {
+        let name = rule.name();
+        if names.contains(&name) || added.contains(&name) {
+            continue;
+        }
+        let inline = document.get("alerts").is_some_and(Item::is_inline_table);
+        let rules = section(&mut document, "alerts")
+            .entry("rules")
+            .or_insert(match inline {
+                true => toml_edit::value(Array::new()),
+                false => Item::ArrayOfTables(ArrayOfTables::new()),
+            });
+        match rules {
+            Item::ArrayOfTables(rules) => rules.push(table(rule)),
+            Item::Value(Value::Array(rules)) => rules.push(table(rule).into_inline_table()),
+            _ => unreachable!("alerts.rules parsed as a list"),
+        }
+        added.push(name);
+    }
+    Ok((document.to_string(), added))
+}
+
+// Periods `current` lacks are added and its RSI kept unless it has none.
+// Either side computing for every symbol wins over a list.
+fn merge_indicators(
+    mut current: IndicatorsConfig,
+    imported: &IndicatorsConfig,
+) -> IndicatorsConfig {
+    for period in &imported.sma {
+        if !current.sma.contains(period) {
+            current.sma.push(*period);
+        }
+    }
+    for period in &imported.ema {
+        if !current.ema.contains(period) {
+            current.ema.push(*period);
+        }
+    }
+    if current.rsi == 0 {
+        current.rsi = imported.rsi;
+    }
+    if imported.symbols.is_empty() {
+        current.symbols.clear();
+    } else if !current.symbols.is_empty() {
+        for symbol in &imported.symbols {
+            if !current.symbols.contains(symbol) {
+                current.symbols.push(symbol.clone());
+            }
+        }
+    }
+    current
+}
+
+// The table `key` of `document`, added when missing without a header of
+// its own. It parsed into `Settings`, so it is a table if there at all.
+fn section<'a>(document: &'a mut DocumentMut, key: &str) -> &'a mut dyn TableLike {
+    let mut missing = Table::new();
+    missing.set_implicit(true);
+    document
+        .entry(key)
+        .or_insert(Item::Table(missing))
+        .as_table_like_mut()
+        .expect("settings parsed as tables")
+}
+
+fn table(value: &impl Serialize) -> Table {
+    let text = toml::to_string(value).expect("Failed to serialize settings");
+    let document: DocumentMut = text.parse().expect("toml writes valid toml");
+    document.as_table().clone()
+}
@@ -0,0 +1,236 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use thiserror::Error;
+use tracing::{error, info, instrument};
+
+use crate::clock::Clock;
+use crate::paper::PaperAccount;
+use crate::sink::LatestPrices;
+
+const SHEETS_URL: &str = "https://sheets.googleapis.com/v4/spreadsheets";
+const SCOPE: &str = "https://www.googleapis.com/auth/spreadsheets";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SheetsConfig {
+    pub enabled: bool,
+    pub spreadsheet_id: String,
+    /// Service account key file. Without one, `GOOGLE_ACCESS_TOKEN` is used
+    /// as is, which suits short runs with a token from `gcloud`.
+    pub credentials: Option<PathBuf>,
+    pub prices_sheet: String,
+    pub portfolio_sheet: String,
+    pub interval_secs: u64,
+}
+
+impl Default for SheetsConfig {
+    fn default() -> Self {
+        SheetsConfig {
+            enabled: false,
+            spreadsheet_id: String::new(),
+            credentials: None,
+            prices_sheet: "Prices".into(),
+            portfolio_sheet: "Portfolio".into(),
+            interval_secs: 300,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SheetsError {
+    #[error("failed to read credentials {0}: {1}")]
+    Credentials(PathBuf, String),
+    #[error("no credentials file configured and GOOGLE_ACCESS_TOKEN is not set")]
+    NoCredentials,
+    #[error("failed to sign token request: {0}")]
+    Sign(#[from] jsonwebtoken::errors::Error),
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Google API returned {0}: {1}")]
+    Api(u16, String),
+}
+
+#[derive(Debug, Deserialize)]
+struct ServiceAccount {
+    client_email: String,
+    private_key: String,
+    token_uri: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Claims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+/// Pushes latest prices and the paper portfolio into a Google Sheet, one
+/// tab each, replacing the tab's contents on every run.
+pub struct SheetsSync {
+    config: SheetsConfig,
+    prices: Arc<LatestPrices>,
+    paper: Arc<PaperAccount>,
+    client: reqwest::Client,
+    token: Mutex<Option<(String, DateTime<Utc>)>>,
+}
+
+impl SheetsSync {
+    pub fn new(config: SheetsConfig, prices: Arc<LatestPrices>, paper: Arc<PaperAccount>) -> Self {
+        SheetsSync {
+            config,
+            prices,
+            paper,
+            client: reqwest::Client::new(),
+            token: Mutex::new(None),
+        }
+    }
+
+    async fn access_token(&self, now: DateTime<Utc>) -> Result<String, SheetsError> {
+        let Some(path) = &self.config.credentials else {
+            return std::env::var("GOOGLE_ACCESS_TOKEN").map_err(|_| SheetsError::NoCredentials);
+        };
+        if let Some((token, expires)) = self.token.lock().unwrap().clone() {
+            if expires > now + chrono::Duration::minutes(1) {
+                return Ok(token);
+            }
+        }
+
+        let read_err = |e: String| SheetsError::Credentials(path.clone(), e);
+        let data = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| read_err(e.to_string()))?;
+        let account: ServiceAccount =
+            serde_json::from_str(&data).map_err(|e| read_err(e.to_string()))?;
+        let claims = Claims {
+            iss: &account.client_email,
+            scope: SCOPE,
+            aud: &account.token_uri,
+            iat: now.timestamp(),
+            exp: now.timestamp() + 3600,
+        };
+        let key = EncodingKey::from_rsa_pem(account.private_key.as_bytes())?;
+        let assertion = jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &key)?;
+
+        let response = self
+            .client
+            .post(&account.token_uri)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", &assertion),
+            ])
+            .send()
+            .await?;
+        let v = check(response).await?;
+        let token = v["access_token"].as_str().unwrap_or_default().to_string();
+        let expires = now + chrono::Duration::seconds(v["expires_in"].as_i64().unwrap_or(3600));
+        *self.token.lock().unwrap() = Some((token.clone(), expires));
+        Ok(token)
+    }
+
+    fn rows(&self) -> (Vec<Value>, Vec<Value>) {
+        let mut prices = vec![json!(["symbol", "price", "timestamp"])];
+        for update in self.prices.snapshot() {
+            prices.push(json!([
+                update.symbol,
+                update.price,
+                update.timestamp.to_rfc3339()
+            ]));
+        }
+
+        let portfolio = self.paper.portfolio();
+        let mut holdings = vec![json!([
+            "symbol",
+            "currency",
+            "quantity",
+            "average_cost",
+            "last_price",
+            "market_value",
+            "unrealized_pnl"
+        ])];
+        for p in portfolio.positions {
+            holdings.push(json!([
+                p.symbol,
+                p.currency,
+                p.quantity,
+                p.average_cost,
+                p.last_price,
+                p.market_value,
+                p.unrealized_pnl
+            ]));
+        }
+        holdings.push(json!(["cash", "", "", "", "", portfolio.cash]));
+        holdings.push(json!(["equity", "", "", "", "", portfolio.equity]));
+        (prices, holdings)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn push(&self, now: DateTime<Utc>) -> Result<(), SheetsError> {
+        let token = self.access_token(now).await?;
+        let base = format!("{}/{}/values", SHEETS_URL, self.config.spreadsheet_id);
+        let (prices, holdings) = self.rows();
+        let (prices_len, holdings_len) = (prices.len(), holdings.len());
+
+        // Clear first so rows for dropped symbols don't linger below the data.
+        let ranges = [&self.config.prices_sheet, &self.config.portfolio_sheet];
+        let response = self
+            .client
+            .post(format!("{}:batchClear", base))
+            .bearer_auth(&token)
+            .header("content-type", "application/json")
+            .body(json!({ "ranges": ranges }).to_string())
+            .send()
+            .await?;
+        check(response).await?;
+
+        let body = json!({
+            "valueInputOption": "RAW",
+            "data": [
+                { "range": format!("{}!A1", self.config.prices_sheet), "values": prices },
+                { "range": format!("{}!A1", self.config.portfolio_sheet), "values": holdings },
+            ],
+        });
+        let response = self
+            .client
+            .post(format!("{}:batchUpdate", base))
+            .bearer_auth(&token)
+            .header("content-type", "application/json")
+            .body(body.to_string())
+            .send()
+            .await?;
+        check(response).await?;
+        info!(
+            prices = prices_len - 1,
+            positions = holdings_len - 3,
+            "Synced Google Sheet"
+        );
+        Ok(())
+    }
+
+    pub async fn run(self: Arc<Self>, clock: Arc<dyn Clock>) {
+        loop {
+            clock
+                .sleep(Duration::from_secs(self.config.interval_secs))
+                .await;
+            if let Err(e) = self.push(clock.now()).await {
+                error!(error = %e, "Failed to sync Google Sheet");
+            }
+        }
+    }
+}
+
+async fn check(response: reqwest::Response) -> Result<Value, SheetsError> {
+    let status = response.status();
+    let data = response.text().await?;
+    if !status.is_success() {
+        return Err(SheetsError::Api(status.as_u16(), data));
+    }
+    Ok(serde_json::from_str(&data).unwrap_or_default())
+}
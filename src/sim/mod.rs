@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tracing::{info, instrument};
+
+use crate::clock::{Clock, VirtualClock};
+use crate::config::Config;
+use crate::engine::Engine;
+use crate::provider::MockProvider;
+use crate::sink::{MemorySink, PriceUpdate};
+use crate::{metrics, PricePoint, Tickers};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RecordedTick {
+    pub symbol: String,
+    pub timestamp: DateTime<Utc>,
+    pub price: f64,
+}
+
+/// A day of ticks, stored as one JSON `RecordedTick` per line.
+#[derive(Debug, Default)]
+pub struct Recording {
+    series: BTreeMap<String, Vec<PricePoint>>,
+}
+
+impl Recording {
+    pub async fn load(path: &Path) -> std::io::Result<Self> {
+        let data = fs::read_to_string(path).await?;
+        let mut recording = Recording::default();
+        for (i, line) in data.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let tick: RecordedTick = serde_json::from_str(line).map_err(|e| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("line {}: {}", i + 1, e),
+                )
+            })?;
+            recording.push(tick);
+        }
+        Ok(recording)
+    }
+
+    pub fn push(&mut self, tick: RecordedTick) {
+        self.series
+            .entry(tick.symbol)
+            .or_default()
+            .push(PricePoint {
+                timestamp: tick.timestamp,
+                price: tick.price,
+            });
+    }
+
+    pub fn start(&self) -> Option<DateTime<Utc>> {
+        self.series
+            .values()
+            .flat_map(|points| points.iter().map(|p| p.timestamp))
+            .min()
+    }
+
+    pub fn end(&self) -> Option<DateTime<Utc>> {
+        self.series
+            .values()
+            .flat_map(|points| points.iter().map(|p| p.timestamp))
+            .max()
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SymbolSummary {
+    pub updates: usize,
+    pub first_price: f64,
+    pub last_price: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimulationReport {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub updates: usize,
+    pub symbols: BTreeMap<String, SymbolSummary>,
+    pub metrics: String,
+}
+
+/// Replays `recording` through the engine on a virtual clock. The same
+/// recording and config always produce the same report.
+#[instrument(skip_all)]
+pub async fn simulate(
+    recording: Recording,
+    config: &Config,
+) -> Option<(SimulationReport, Vec<PriceUpdate>)> {
+    let start = recording.start()?;
+    let end = recording.end()?;
+    let symbols: Vec<String> = recording.series.keys().cloned().collect();
+
+    metrics::register_metrics();
+    let clock: Arc<dyn Clock> = Arc::new(VirtualClock::new(start));
+    let provider = Arc::new(MockProvider::new(recording.series, clock.clone()));
+    let memory = Arc::new(MemorySink::default());
+    let engine = Engine::from_config(provider, clock.clone(), config).with_sink(memory.clone());
+
+    engine.run_until(Tickers::new(symbols), Some(end)).await;
+
+    let updates = memory.updates();
+    let mut per_symbol: BTreeMap<String, SymbolSummary> = BTreeMap::new();
+    for update in &updates {
+        let summary = per_symbol.entry(update.symbol.clone()).or_default();
+        if summary.updates == 0 {
+            summary.first_price = update.price;
+        }
+        summary.updates += 1;
+        summary.last_price = update.price;
+    }
+    info!(updates = updates.len(), %start, %end, "Simulation finished");
+
+    let report = SimulationReport {
+        start,
+        end,
+        updates: updates.len(),
+        symbols: per_symbol,
+        metrics: metrics::encode(),
+    };
+    Some((report, updates))
+}
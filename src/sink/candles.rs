@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use chrono::NaiveDate;
+
+use super::{PriceUpdate, Sink};
+use crate::metrics;
+use crate::returns::Conventions;
+
+/// Open, high, low and close of one symbol over one trading day.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DailyCandle {
+    pub session: NaiveDate,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+}
+
+/// Builds today's candle per symbol from every price and exports it,
+/// labelled by session date, so dashboards can draw candles straight from
+/// the gauges. A symbol's previous session is dropped when its next begins.
+#[derive(Debug)]
+pub struct DailyCandles {
+    conventions: Conventions,
+    candles: Mutex<HashMap<String, DailyCandle>>,
+}
+
+impl DailyCandles {
+    /// Days follow `conventions`, as returns and history do.
+    pub fn new(conventions: Conventions) -> Self {
+        DailyCandles {
+            conventions,
+            candles: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Sink for DailyCandles {
+    fn record(&self, update: &PriceUpdate) {
+        let session = self.conventions.day_of(update.timestamp);
+        let price = update.price;
+        let mut candles = self.candles.lock().unwrap();
+        let candle = match candles.get_mut(&update.symbol) {
+            Some(candle) if candle.session == session => {
+                candle.high = candle.high.max(price);
+                candle.low = candle.low.min(price);
+                candle.close = price;
+                *candle
+            }
+            previous => {
+                if let Some(old) = previous.map(|c| c.session) {
+                    // A late tick for a finished session would reopen it.
+                    if old > session {
+                        return;
+                    }
+                    metrics::remove_daily_candle(&update.symbol, old);
+                }
+                let candle = DailyCandle {
+                    session,
+                    open: price,
+                    high: price,
+                    low: price,
+                    close: price,
+                };
+                candles.insert(update.symbol.clone(), candle);
+                candle
+            }
+        };
+        metrics::update_daily_candle(&update.symbol, &candle);
+    }
+}
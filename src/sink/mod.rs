@@ -0,0 +1,162 @@
+pub mod candles;
+pub mod skew;
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use utoipa::ToSchema;
+
+use crate::engine::CycleContext;
+use crate::metrics;
+use crate::provider::DayQuote;
+use crate::symbol::SymbolInfo;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct PriceUpdate {
+    pub symbol: String,
+    pub price: f64,
+    /// When fintek received the price, by the local clock.
+    pub timestamp: DateTime<Utc>,
+    /// When the provider says the price was set, if it says.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_timestamp: Option<DateTime<Utc>>,
+    /// The day's figures, when fetched from a quote endpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub day: Option<DayQuote>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FetchOutcome {
+    Success,
+    Empty,
+    Failure,
+}
+
+pub trait Sink: Send + Sync {
+    fn record(&self, update: &PriceUpdate);
+
+    /// Called for every fetch attempt, successful or not.
+    fn on_fetch(&self, _symbol: &str, _outcome: FetchOutcome, _at: DateTime<Utc>) {}
+
+    fn on_cycle(&self, _cycle: &CycleContext) {}
+
+    /// Rebuild state from a logged update. Sinks with side effects beyond
+    /// their own state should override this.
+    fn replay(&self, update: &PriceUpdate) {
+        self.record(update);
+    }
+
+    /// Scale what the sink keeps of `symbol`'s earlier prices by `factor`,
+    /// as when a continuous future rolls onto a contract trading at another
+    /// level.
+    fn rebase(&self, _symbol: &str, _factor: f64, _at: DateTime<Utc>) {}
+
+    /// What the sink is toggled by at runtime: its type's name in snake
+    /// case, e.g. `metrics_sink`.
+    fn name(&self) -> String {
+        let path = std::any::type_name::<Self>();
+        let ty = path.split('<').next().unwrap_or(path);
+        let ty = ty.rsplit("::").next().unwrap_or(ty);
+        let mut name = String::new();
+        for (i, c) in ty.chars().enumerate() {
+            if c.is_uppercase() && i > 0 {
+                name.push('_');
+            }
+            name.push(c.to_ascii_lowercase());
+        }
+        name
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MetricsSink;
+
+impl Sink for MetricsSink {
+    fn record(&self, update: &PriceUpdate) {
+        let info = SymbolInfo::parse(&update.symbol);
+        metrics::update_stock_price(
+            update.price,
+            &update.symbol,
+            info.asset_class.as_str(),
+            &info.currency(),
+        );
+        metrics::update_last_update(&update.symbol, update.timestamp);
+        if let Some(day) = &update.day {
+            metrics::update_day_quote(&update.symbol, day);
+        }
+    }
+}
+
+/// The most recent update per symbol.
+#[derive(Debug, Default)]
+pub struct LatestPrices {
+    prices: Mutex<BTreeMap<String, PriceUpdate>>,
+    // Woken on every update, for `next_change`.
+    updated: Notify,
+}
+
+impl LatestPrices {
+    /// Sorted by symbol.
+    pub fn snapshot(&self) -> Vec<PriceUpdate> {
+        self.prices.lock().unwrap().values().cloned().collect()
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<PriceUpdate> {
+        self.prices.lock().unwrap().get(symbol).cloned()
+    }
+
+    /// The first update of `symbol` at a price other than `from`, or `None`
+    /// if there is none within `timeout`.
+    pub async fn next_change(
+        &self,
+        symbol: &str,
+        from: Option<f64>,
+        timeout: Duration,
+    ) -> Option<PriceUpdate> {
+        let changed = async {
+            loop {
+                // Registered before looking, so an update in between wakes it.
+                let updated = self.updated.notified();
+                tokio::pin!(updated);
+                updated.as_mut().enable();
+                if let Some(update) = self.get(symbol).filter(|u| Some(u.price) != from) {
+                    return update;
+                }
+                updated.await;
+            }
+        };
+        tokio::time::timeout(timeout, changed).await.ok()
+    }
+}
+
+impl Sink for LatestPrices {
+    fn record(&self, update: &PriceUpdate) {
+        self.prices
+            .lock()
+            .unwrap()
+            .insert(update.symbol.clone(), update.clone());
+        self.updated.notify_waiters();
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    updates: Mutex<Vec<PriceUpdate>>,
+}
+
+impl MemorySink {
+    pub fn updates(&self) -> Vec<PriceUpdate> {
+        self.updates.lock().unwrap().clone()
+    }
+}
+
+impl Sink for MemorySink {
+    fn record(&self, update: &PriceUpdate) {
+        self.updates.lock().unwrap().push(update.clone());
+    }
+}
@@ -0,0 +1,47 @@
+use std::sync::Mutex;
+
+use tracing::warn;
+
+use super::{PriceUpdate, Sink};
+use crate::engine::CycleContext;
+use crate::metrics;
+
+/// A provider timestamp this far ahead of the local clock can't be latency.
+const AHEAD_WARN_SECS: f64 = 5.;
+
+/// Compares provider timestamps with local receive times. Per symbol the
+/// gap is mostly provider lag plus latency, but both are never negative,
+/// so the smallest gap in a cycle bounds how far the host clock is off.
+#[derive(Debug, Default)]
+pub struct SkewTracker {
+    // Smallest gap seen this cycle.
+    smallest: Mutex<Option<f64>>,
+}
+
+impl Sink for SkewTracker {
+    fn record(&self, update: &PriceUpdate) {
+        let Some(provider) = update.provider_timestamp else {
+            return;
+        };
+        let skew = (update.timestamp - provider).num_milliseconds() as f64 / 1000.;
+        metrics::update_price_timestamp_skew(&update.symbol, skew);
+        let mut smallest = self.smallest.lock().unwrap();
+        *smallest = Some(smallest.map_or(skew, |s| s.min(skew)));
+    }
+
+    fn on_cycle(&self, _cycle: &CycleContext) {
+        let Some(skew) = self.smallest.lock().unwrap().take() else {
+            return;
+        };
+        metrics::update_clock_skew(skew);
+        if skew < -AHEAD_WARN_SECS {
+            warn!(
+                skew_secs = skew,
+                "Provider timestamps are ahead of the local clock, check NTP"
+            );
+        }
+    }
+
+    // Restored ticks say nothing about the clock now.
+    fn replay(&self, _update: &PriceUpdate) {}
+}
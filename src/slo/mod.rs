@@ -0,0 +1,135 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::engine::CycleContext;
+use crate::metrics;
+use crate::sink::{PriceUpdate, Sink};
+use crate::AssetClass;
+
+const WINDOWS: &[(&str, i64)] = &[("5m", 5 * 60), ("1h", 60 * 60), ("6h", 6 * 60 * 60)];
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FreshnessObjective {
+    pub asset_class: AssetClass,
+    pub max_staleness_secs: u64,
+    pub target: f64,
+    #[serde(default = "default_market_hours_only")]
+    pub market_hours_only: bool,
+}
+
+fn default_market_hours_only() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    at: DateTime<Utc>,
+    good: usize,
+    total: usize,
+}
+
+/// Samples every watched symbol's staleness at the end of each cycle and
+/// exports compliance and burn rate per asset class over fixed windows.
+pub struct FreshnessTracker {
+    objectives: Vec<FreshnessObjective>,
+    last_update: Mutex<HashMap<String, DateTime<Utc>>>,
+    samples: Mutex<HashMap<AssetClass, VecDeque<Sample>>>,
+}
+
+impl FreshnessTracker {
+    pub fn new(objectives: Vec<FreshnessObjective>) -> Self {
+        for objective in &objectives {
+            metrics::update_slo_objective(
+                objective.asset_class.as_str(),
+                objective.max_staleness_secs as f64,
+                objective.target,
+            );
+        }
+        FreshnessTracker {
+            objectives,
+            last_update: Mutex::new(HashMap::new()),
+            samples: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn evaluate(&self, now: DateTime<Utc>, market_open: bool, symbols: &[String]) {
+        let last_update = self.last_update.lock().unwrap();
+        let mut samples = self.samples.lock().unwrap();
+        let longest = WINDOWS
+            .iter()
+            .map(|(_, secs)| *secs)
+            .max()
+            .unwrap_or_default();
+
+        for objective in &self.objectives {
+            if objective.market_hours_only && !market_open {
+                continue;
+            }
+            let max_staleness = Duration::seconds(objective.max_staleness_secs as i64);
+            let mut sample = Sample {
+                at: now,
+                good: 0,
+                total: 0,
+            };
+            for symbol in symbols {
+                if AssetClass::of(symbol) != objective.asset_class {
+                    continue;
+                }
+                sample.total += 1;
+                if last_update
+                    .get(symbol)
+                    .is_some_and(|at| now - *at <= max_staleness)
+                {
+                    sample.good += 1;
+                }
+            }
+            if sample.total == 0 {
+                continue;
+            }
+
+            let history = samples.entry(objective.asset_class).or_default();
+            history.push_back(sample);
+            while history
+                .front()
+                .is_some_and(|s| now - s.at > Duration::seconds(longest))
+            {
+                history.pop_front();
+            }
+
+            let class = objective.asset_class.as_str();
+            for (window, secs) in WINDOWS {
+                let (good, total) = history
+                    .iter()
+                    .filter(|s| now - s.at <= Duration::seconds(*secs))
+                    .fold((0, 0), |(g, t), s| (g + s.good, t + s.total));
+                let compliance = good as f64 / total as f64;
+                let budget = (1. - objective.target).max(f64::EPSILON);
+                let burn_rate = (1. - compliance) / budget;
+                metrics::update_slo_compliance(class, window, compliance, burn_rate);
+                if *window == "5m" && compliance < objective.target {
+                    warn!(
+                        asset_class = class,
+                        compliance, burn_rate, "Data freshness objective breached"
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl Sink for FreshnessTracker {
+    fn record(&self, update: &PriceUpdate) {
+        self.last_update
+            .lock()
+            .unwrap()
+            .insert(update.symbol.clone(), update.timestamp);
+    }
+
+    fn on_cycle(&self, cycle: &CycleContext) {
+        self.evaluate(cycle.now, cycle.market_open, cycle.symbols);
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Method {
+    #[default]
+    None,
+    /// Median of the last `window` raw ticks.
+    Median,
+    /// One-dimensional Kalman filter with noise relative to the price.
+    Kalman,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SmoothingConfig {
+    pub method: Method,
+    pub window: usize,
+    pub process_noise: f64,
+    pub measurement_noise: f64,
+    /// Symbols to smooth; all when empty.
+    pub symbols: Vec<String>,
+}
+
+impl Default for SmoothingConfig {
+    fn default() -> Self {
+        SmoothingConfig {
+            method: Method::None,
+            window: 5,
+            process_noise: 0.001,
+            measurement_noise: 0.01,
+            symbols: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    window: VecDeque<f64>,
+    estimate: Option<(f64, f64)>,
+}
+
+/// Suppresses single bad ticks before prices reach sinks.
+pub struct Smoother {
+    config: SmoothingConfig,
+    state: Mutex<HashMap<String, State>>,
+}
+
+impl Smoother {
+    pub fn new(config: SmoothingConfig) -> Self {
+        Smoother {
+            config,
+            state: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn apply(&self, symbol: &str, price: f64) -> f64 {
+        if self.config.method == Method::None
+            || (!self.config.symbols.is_empty() && !self.config.symbols.iter().any(|s| s == symbol))
+        {
+            return price;
+        }
+        let mut state = self.state.lock().unwrap();
+        let state = state.entry(symbol.to_string()).or_default();
+        let smoothed = match self.config.method {
+            Method::None => price,
+            Method::Median => {
+                state.window.push_back(price);
+                while state.window.len() > self.config.window.max(1) {
+                    state.window.pop_front();
+                }
+                let mut sorted: Vec<f64> = state.window.iter().copied().collect();
+                sorted.sort_by(f64::total_cmp);
+                let mid = sorted.len() / 2;
+                if sorted.len().is_multiple_of(2) {
+                    (sorted[mid - 1] + sorted[mid]) / 2.
+                } else {
+                    sorted[mid]
+                }
+            }
+            Method::Kalman => {
+                let q = (self.config.process_noise * price).powi(2);
+                let r = (self.config.measurement_noise * price).powi(2);
+                let (x, p) = state.estimate.unwrap_or((price, r));
+                let p = p + q;
+                let gain = p / (p + r);
+                let x = x + gain * (price - x);
+                state.estimate = Some((x, (1. - gain) * p));
+                x
+            }
+        };
+        debug!(symbol, price, smoothed, "Smoothed price");
+        smoothed
+    }
+}
@@ -0,0 +1,374 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{error, info, instrument, warn};
+
+use crate::clock::Clock;
+use crate::metrics;
+use crate::notify::{Notification, NotificationKind, Notifiers, Urgency};
+use crate::{read_tickers, AssetClass};
+
+pub const SOCIAL_FILE: &str = "social.json";
+pub const STOCKTWITS_URL: &str = "https://api.stocktwits.com";
+pub const REDDIT_URL: &str = "https://www.reddit.com";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SocialSource {
+    /// The latest messages of a symbol's stream, with the bullish or bearish
+    /// tags their authors gave them. Only the last 30 are public, so busier
+    /// symbols count at most that many per poll.
+    Stocktwits,
+    /// Posts naming the symbol in the configured subreddits.
+    Reddit,
+}
+
+impl SocialSource {
+    pub fn name(self) -> &'static str {
+        match self {
+            SocialSource::Stocktwits => "stocktwits",
+            SocialSource::Reddit => "reddit",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SocialConfig {
+    /// Nothing is sampled without a source. One request per watched stock
+    /// and source on every poll.
+    pub sources: Vec<SocialSource>,
+    /// Also the window mentions are counted over.
+    pub poll_secs: u64,
+    /// Polls the buzz of a symbol is compared with.
+    pub window: usize,
+    /// Polls needed before spikes alert.
+    pub min_samples: usize,
+    pub spike_zscore: f64,
+    /// Fewer mentions than this never alert, however quiet the symbol was.
+    pub min_mentions: u64,
+    pub subreddits: Vec<String>,
+    pub user_agent: String,
+    pub stocktwits_url: String,
+    pub reddit_url: String,
+}
+
+impl Default for SocialConfig {
+    fn default() -> Self {
+        SocialConfig {
+            sources: vec![],
+            poll_secs: 900,
+            window: 96,
+            min_samples: 12,
+            spike_zscore: 3.,
+            min_mentions: 5,
+            subreddits: vec!["wallstreetbets".into(), "stocks".into(), "investing".into()],
+            user_agent: format!("fintek/{}", env!("CARGO_PKG_VERSION")),
+            stocktwits_url: STOCKTWITS_URL.into(),
+            reddit_url: REDDIT_URL.into(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SocialError {
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error("unexpected response: {0}")]
+    Response(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Deserialize)]
+struct StocktwitsSentiment {
+    basic: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct StocktwitsEntities {
+    sentiment: Option<StocktwitsSentiment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StocktwitsMessage {
+    created_at: DateTime<Utc>,
+    #[serde(default)]
+    entities: StocktwitsEntities,
+}
+
+#[derive(Debug, Deserialize)]
+struct StocktwitsStream {
+    #[serde(default)]
+    messages: Vec<StocktwitsMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RedditPost {
+    created_utc: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct RedditChild {
+    data: RedditPost,
+}
+
+#[derive(Debug, Deserialize)]
+struct RedditListingData {
+    #[serde(default)]
+    children: Vec<RedditChild>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RedditListing {
+    data: RedditListingData,
+}
+
+/// What one source said about a symbol over the last poll.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Sample {
+    pub symbol: String,
+    pub source: SocialSource,
+    pub mentions: u64,
+    /// From -1 when every tagged message is bearish to 1 when all are
+    /// bullish, for sources that tag them.
+    pub sentiment: Option<f64>,
+    /// How unusual the mentions are against the last polls.
+    pub zscore: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Series {
+    mentions: VecDeque<f64>,
+    // Whether the last poll alerted, so a spike alerts once.
+    #[serde(default)]
+    spiking: bool,
+}
+
+// How many standard deviations `x` is above `samples`. Mention counts of
+// quiet symbols hardly vary, so the deviation is at least one mention.
+fn zscore(samples: &VecDeque<f64>, x: f64) -> Option<f64> {
+    if samples.is_empty() {
+        return None;
+    }
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n;
+    Some((x - mean) / variance.sqrt().max(1.))
+}
+
+/// Samples how much the watched stocks are talked about on social sites
+/// and exports each poll's mentions as a z-score of the recent ones, so
+/// buzz compares across symbols, alerting when one spikes. The recent
+/// mentions are saved so a restart keeps its baseline.
+pub struct SocialMonitor {
+    config: SocialConfig,
+    client: reqwest::Client,
+    notifiers: Notifiers,
+    path: PathBuf,
+    // Per `symbol/source`.
+    series: Mutex<BTreeMap<String, Series>>,
+    latest: Mutex<BTreeMap<String, Sample>>,
+}
+
+impl SocialMonitor {
+    /// `None` without sources.
+    pub fn new(config: &SocialConfig, state_dir: &Path, notifiers: Notifiers) -> Option<Self> {
+        if config.sources.is_empty() {
+            return None;
+        }
+        let client = reqwest::Client::builder()
+            .user_agent(config.user_agent.clone())
+            .build()
+            .ok()?;
+        let path = state_dir.join(SOCIAL_FILE);
+        let series = match std::fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+                warn!(path = %path.display(), error = %e, "Ignoring corrupt social state");
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Some(SocialMonitor {
+            config: config.clone(),
+            client,
+            notifiers,
+            path,
+            series: Mutex::new(series),
+            latest: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    /// The last sample of every symbol and source.
+    pub fn latest(&self) -> Vec<Sample> {
+        self.latest.lock().unwrap().values().cloned().collect()
+    }
+
+    async fn get<T: DeserializeOwned>(&self, url: &str) -> Result<T, SocialError> {
+        let response = self.client.get(url).send().await?.error_for_status()?;
+        Ok(serde_json::from_str(&response.text().await?)?)
+    }
+
+    #[instrument(skip(self))]
+    async fn stocktwits(
+        &self,
+        symbol: &str,
+        since: DateTime<Utc>,
+    ) -> Result<(u64, Option<f64>), SocialError> {
+        let url = format!(
+            "{}/api/2/streams/symbol/{}.json",
+            self.config.stocktwits_url.trim_end_matches('/'),
+            symbol
+        );
+        let stream: StocktwitsStream = self.get(&url).await?;
+        let recent: Vec<_> = stream
+            .messages
+            .iter()
+            .filter(|m| m.created_at >= since)
+            .collect();
+        let (mut bullish, mut bearish) = (0., 0.);
+        for message in &recent {
+            match message
+                .entities
+                .sentiment
+                .as_ref()
+                .map(|s| s.basic.as_str())
+            {
+                Some("Bullish") => bullish += 1.,
+                Some("Bearish") => bearish += 1.,
+                _ => {}
+            }
+        }
+        let sentiment = (bullish + bearish > 0.).then(|| (bullish - bearish) / (bullish + bearish));
+        Ok((recent.len() as u64, sentiment))
+    }
+
+    #[instrument(skip(self))]
+    async fn reddit(&self, symbol: &str, since: DateTime<Utc>) -> Result<u64, SocialError> {
+        let url = format!(
+            "{}/r/{}/search.json?q={}&restrict_sr=on&sort=new&t=day&limit=100",
+            self.config.reddit_url.trim_end_matches('/'),
+            self.config.subreddits.join("+"),
+            symbol
+        );
+        let listing: RedditListing = self.get(&url).await?;
+        let since = since.timestamp() as f64;
+        Ok(listing
+            .data
+            .children
+            .iter()
+            .filter(|c| c.data.created_utc >= since)
+            .count() as u64)
+    }
+
+    // Adds the poll's mentions to the series, with the alert when they spike.
+    fn score(&self, sample: &mut Sample) -> Option<Notification> {
+        let key = format!("{}/{}", sample.symbol, sample.source.name());
+        let mut all = self.series.lock().unwrap();
+        let series = all.entry(key).or_default();
+        let mentions = sample.mentions as f64;
+        let usual = series.mentions.iter().sum::<f64>() / series.mentions.len().max(1) as f64;
+        sample.zscore = zscore(&series.mentions, mentions);
+        let spiking = series.mentions.len() >= self.config.min_samples
+            && sample.mentions >= self.config.min_mentions
+            && sample.zscore.is_some_and(|z| z >= self.config.spike_zscore);
+        let alert = spiking && !series.spiking;
+        series.spiking = spiking;
+        series.mentions.push_back(mentions);
+        while series.mentions.len() > self.config.window.max(1) {
+            series.mentions.pop_front();
+        }
+        let z = sample.zscore.filter(|_| alert)?;
+        Some(Notification {
+            title: format!("{} buzz spike on {}", sample.symbol, sample.source.name()),
+            body: format!(
+                "{} mentions of {} in the last {} minutes, {:.1} standard deviations above the usual {:.1}",
+                sample.mentions,
+                sample.symbol,
+                self.config.poll_secs / 60,
+                z,
+                usual
+            ),
+            urgency: Urgency::Normal,
+            kind: NotificationKind::Alert,
+            symbols: vec![sample.symbol.clone()],
+            rule: Some("social_buzz_spike".into()),
+            value: Some(z),
+            labels: BTreeMap::new(),
+        })
+    }
+
+    async fn sample(
+        &self,
+        symbol: &str,
+        source: SocialSource,
+        since: DateTime<Utc>,
+    ) -> Option<Sample> {
+        let result = match source {
+            SocialSource::Stocktwits => self.stocktwits(symbol, since).await,
+            SocialSource::Reddit => self.reddit(symbol, since).await.map(|n| (n, None)),
+        };
+        match result {
+            Ok((mentions, sentiment)) => Some(Sample {
+                symbol: symbol.to_string(),
+                source,
+                mentions,
+                sentiment,
+                zscore: None,
+            }),
+            Err(e) => {
+                warn!(symbol, source = source.name(), error = %e, "Failed to sample social buzz");
+                None
+            }
+        }
+    }
+
+    /// Samples every stock among `symbols` from every source once and saves
+    /// the series.
+    pub async fn poll(&self, symbols: &[String], now: DateTime<Utc>) {
+        let since = now - chrono::Duration::seconds(self.config.poll_secs.max(60) as i64);
+        let mut samples = 0;
+        for symbol in symbols
+            .iter()
+            .filter(|s| AssetClass::of(s) == AssetClass::Stock)
+        {
+            for &source in &self.config.sources {
+                let Some(mut sample) = self.sample(symbol, source, since).await else {
+                    continue;
+                };
+                let alert = self.score(&mut sample);
+                metrics::update_social_sample(&sample);
+                if let Some(notification) = alert {
+                    self.notifiers.notify(&notification).await;
+                }
+                self.latest
+                    .lock()
+                    .unwrap()
+                    .insert(format!("{}/{}", sample.symbol, source.name()), sample);
+                samples += 1;
+            }
+        }
+        let data =
+            serde_json::to_vec_pretty(&*self.series.lock().unwrap()).expect("series serialize");
+        if let Err(e) = std::fs::write(&self.path, data) {
+            error!(path = %self.path.display(), error = %e, "Failed to save social state");
+        }
+        info!(samples, "Sampled social buzz");
+    }
+
+    pub async fn run(self: Arc<Self>, clock: Arc<dyn Clock>) {
+        loop {
+            match read_tickers().await {
+                Ok(tickers) => self.poll(tickers.get_tickers(), clock.now()).await,
+                Err(e) => warn!(error = %e, "Failed to read tickers, skipping social poll"),
+            }
+            clock
+                .sleep(Duration::from_secs(self.config.poll_secs.max(60)))
+                .await;
+        }
+    }
+}
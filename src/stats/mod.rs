@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::clock::Clock;
+use crate::metrics;
+use crate::read_tickers;
+use crate::storage::{DailyBar, DailyStore};
+
+pub const STATS_FILE: &str = "stats.json";
+
+// How often expired stats are looked for.
+const CHECK_SECS: u64 = 15 * 60;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StatsConfig {
+    /// Derives slow-moving statistics from the stored daily bars, which
+    /// needs `storage.path` and the daily pipeline.
+    pub enabled: bool,
+    /// How long derived statistics are used, across restarts, before they
+    /// are derived again.
+    pub ttl_hours: u64,
+    /// Days in the average true range, smoothed the Wilder way.
+    pub atr_days: usize,
+    /// Days in the average volume.
+    pub volume_days: usize,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        StatsConfig {
+            enabled: false,
+            ttl_hours: 24,
+            atr_days: 14,
+            volume_days: 20,
+        }
+    }
+}
+
+/// A symbol's statistics as of its last daily bar. Each is `None` until
+/// there are enough bars for it.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SymbolStats {
+    pub symbol: String,
+    pub computed_at: DateTime<Utc>,
+    pub bars: usize,
+    pub average_volume: Option<f64>,
+    pub atr: Option<f64>,
+    /// Over the last 52 weeks.
+    pub year_high: Option<f64>,
+    pub year_low: Option<f64>,
+}
+
+/// Statistics of `bars`, oldest first, as of `now`.
+pub fn derive(
+    symbol: &str,
+    bars: &[DailyBar],
+    config: &StatsConfig,
+    now: DateTime<Utc>,
+) -> SymbolStats {
+    let volumes: Vec<f64> = bars
+        .iter()
+        .rev()
+        .filter_map(|b| b.volume)
+        .take(config.volume_days)
+        .collect();
+    let average_volume = (config.volume_days > 0 && volumes.len() == config.volume_days)
+        .then(|| volumes.iter().sum::<f64>() / volumes.len() as f64);
+
+    // Seeded with the mean of the first `atr_days` true ranges.
+    let period = config.atr_days;
+    let mut atr: Option<f64> = None;
+    for (i, pair) in bars.windows(2).enumerate() {
+        let (previous, bar) = (&pair[0], &pair[1]);
+        let range = (bar.high - bar.low)
+            .max((bar.high - previous.close).abs())
+            .max((bar.low - previous.close).abs());
+        let n = i + 1;
+        atr = Some(match atr {
+            Some(atr) if n > period => (atr * (period - 1) as f64 + range) / period as f64,
+            _ => (atr.unwrap_or(0.) * (n - 1) as f64 + range) / n as f64,
+        });
+    }
+    let atr = atr.filter(|_| period > 0 && bars.len() > period);
+
+    let year_ago = now.date_naive() - chrono::Duration::weeks(52);
+    let year = bars.iter().filter(|b| b.date > year_ago);
+    let year_high = year.clone().map(|b| b.high).reduce(f64::max);
+    let year_low = year.map(|b| b.low).reduce(f64::min);
+    SymbolStats {
+        symbol: symbol.to_string(),
+        computed_at: now,
+        bars: bars.len(),
+        average_volume,
+        atr,
+        year_high,
+        year_low,
+    }
+}
+
+/// Per-symbol statistics that change once a day at most, derived from a
+/// year of stored bars and saved with the time they were derived. A
+/// restart picks them up from the file instead of scanning the bars again
+/// until they expire.
+pub struct StatsCache {
+    config: StatsConfig,
+    path: PathBuf,
+    stats: RwLock<BTreeMap<String, SymbolStats>>,
+}
+
+impl StatsCache {
+    /// Exports what is saved right away, expired or not, so the gauges are
+    /// there before the first refresh.
+    pub fn new(config: &StatsConfig, state_dir: &Path) -> Self {
+        let path = state_dir.join(STATS_FILE);
+        let stats: BTreeMap<String, SymbolStats> = match std::fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+                warn!(path = %path.display(), error = %e, "Ignoring corrupt symbol stats");
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        stats.values().for_each(metrics::update_symbol_stats);
+        StatsCache {
+            config: config.clone(),
+            path,
+            stats: RwLock::new(stats),
+        }
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<SymbolStats> {
+        self.stats.read().unwrap().get(symbol).cloned()
+    }
+
+    pub fn all(&self) -> Vec<SymbolStats> {
+        self.stats.read().unwrap().values().cloned().collect()
+    }
+
+    fn expired(&self, symbol: &str, now: DateTime<Utc>) -> bool {
+        let ttl = chrono::Duration::hours(self.config.ttl_hours as i64);
+        self.stats
+            .read()
+            .unwrap()
+            .get(symbol)
+            .is_none_or(|stats| stats.computed_at + ttl <= now)
+    }
+
+    /// Derives the stats of `symbols` that are missing or expired, drops
+    /// those of symbols no longer watched and saves the rest.
+    pub async fn refresh(&self, store: &dyn DailyStore, symbols: &[String], now: DateTime<Utc>) {
+        let today = now.date_naive();
+        let from = today - chrono::Duration::weeks(53);
+        let mut derived = 0;
+        for symbol in symbols.iter().filter(|s| self.expired(s, now)) {
+            match store.bars(symbol, from, today).await {
+                Ok(bars) if bars.is_empty() => {}
+                Ok(bars) => {
+                    let stats = derive(symbol, &bars, &self.config, now);
+                    metrics::update_symbol_stats(&stats);
+                    self.stats.write().unwrap().insert(symbol.clone(), stats);
+                    derived += 1;
+                }
+                Err(e) => error!(symbol, error = %e, "Failed to read daily bars for stats"),
+            }
+        }
+        let removed = {
+            let mut stats = self.stats.write().unwrap();
+            let before = stats.len();
+            stats.retain(|symbol, _| symbols.contains(symbol));
+            before - stats.len()
+        };
+        if derived == 0 && removed == 0 {
+            return;
+        }
+        let data = serde_json::to_vec_pretty(&*self.stats.read().unwrap())
+            .expect("symbol stats serialize");
+        let tmp = self.path.with_extension("json.tmp");
+        if let Err(e) = std::fs::write(&tmp, data).and_then(|_| std::fs::rename(&tmp, &self.path)) {
+            error!(path = %self.path.display(), error = %e, "Failed to save symbol stats");
+        }
+        info!(derived, removed, "Refreshed symbol stats");
+    }
+
+    pub async fn run(self: Arc<Self>, store: Arc<dyn DailyStore>, clock: Arc<dyn Clock>) {
+        loop {
+            match read_tickers().await {
+                Ok(tickers) => {
+                    self.refresh(&*store, tickers.get_tickers(), clock.now())
+                        .await
+                }
+                Err(e) => warn!(error = %e, "Failed to read tickers, skipping stats refresh"),
+            }
+            clock.sleep(Duration::from_secs(CHECK_SECS)).await;
+        }
+    }
+}
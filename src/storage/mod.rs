@@ -0,0 +1,308 @@
+pub mod replica;
+pub mod sqlite;
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tracing::{error, info};
+use utoipa::ToSchema;
+
+use self::sqlite::SqliteConfig;
+
+use crate::events::Event;
+use crate::lag;
+use crate::limits::Limits;
+use crate::metrics;
+use crate::toggles::{self, Toggles};
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// SQLite database every fetched price and fired alert is appended to.
+    /// Off when unset.
+    pub path: Option<PathBuf>,
+    /// Read-only copy of it the HTTP API reads from, as a URL like
+    /// `sqlite:///replica/prices.db`, kept in sync by e.g. Litestream.
+    /// Reads it fails go to `path`, which must be set too.
+    pub replica_url: Option<String>,
+    /// Pragmas and pool sizing for both.
+    pub sqlite: SqliteConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct StoredPrice {
+    pub symbol: String,
+    pub price: f64,
+    /// Receive time by the local clock.
+    pub timestamp: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_timestamp: Option<DateTime<Utc>>,
+    /// Name of the provider the price came from.
+    pub source: String,
+}
+
+/// One trading day of a symbol, from the daily pipeline.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct DailyBar {
+    pub symbol: String,
+    pub date: NaiveDate,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    /// Missing for currencies.
+    pub volume: Option<f64>,
+    pub source: String,
+}
+
+/// A symbol's key statistics as of `date`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Fundamentals {
+    pub symbol: String,
+    pub date: NaiveDate,
+    pub market_cap: Option<f64>,
+    pub trailing_pe: Option<f64>,
+    /// Diluted, trailing twelve months.
+    pub eps: Option<f64>,
+    /// Forward annual, as a fraction.
+    pub dividend_yield: Option<f64>,
+    pub beta: Option<f64>,
+}
+
+/// A reported quarter against its consensus estimates.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct EarningsSurprise {
+    pub symbol: String,
+    /// When the results were reported.
+    pub date: NaiveDate,
+    pub eps_estimate: Option<f64>,
+    pub eps_actual: f64,
+    pub revenue_estimate: Option<f64>,
+    pub revenue_actual: Option<f64>,
+}
+
+// How far `actual` beat `estimate`, in percent of the estimate's size.
+fn surprise_percent(actual: f64, estimate: f64) -> Option<f64> {
+    (estimate != 0.).then(|| (actual - estimate) / estimate.abs() * 100.)
+}
+
+impl EarningsSurprise {
+    pub fn eps_percent(&self) -> Option<f64> {
+        surprise_percent(self.eps_actual, self.eps_estimate?)
+    }
+
+    pub fn revenue_percent(&self) -> Option<f64> {
+        surprise_percent(self.revenue_actual?, self.revenue_estimate?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertOutcome {
+    /// Every notifier delivered it.
+    Sent,
+    /// Some notifiers failed.
+    Partial,
+    Failed,
+    /// Another replica sent it.
+    Deduplicated,
+}
+
+impl AlertOutcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AlertOutcome::Sent => "sent",
+            AlertOutcome::Partial => "partial",
+            AlertOutcome::Failed => "failed",
+            AlertOutcome::Deduplicated => "deduplicated",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        [
+            AlertOutcome::Sent,
+            AlertOutcome::Partial,
+            AlertOutcome::Failed,
+            AlertOutcome::Deduplicated,
+        ]
+        .into_iter()
+        .find(|outcome| outcome.as_str() == s)
+    }
+}
+
+/// One fired alert and what became of it.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct AlertRecord {
+    /// What fired it, such as the strategy's name.
+    pub rule: String,
+    pub symbol: Option<String>,
+    /// The symbol's price when it fired, when known.
+    pub value: Option<f64>,
+    pub title: String,
+    pub fired_at: DateTime<Utc>,
+    pub outcome: AlertOutcome,
+    /// Notifiers that failed to deliver it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct RuleCount {
+    pub rule: String,
+    pub fired: u64,
+    /// Times some notifier failed to deliver it.
+    pub failed: u64,
+    pub last_fired: DateTime<Utc>,
+}
+
+/// `alerts` counted per rule, as [`AlertStore::rule_counts`] counts the
+/// whole store, for when only some of them may be seen.
+pub fn count_rules(alerts: &[AlertRecord]) -> Vec<RuleCount> {
+    let mut counts: BTreeMap<&str, RuleCount> = BTreeMap::new();
+    for alert in alerts {
+        let count = counts.entry(&alert.rule).or_insert_with(|| RuleCount {
+            rule: alert.rule.clone(),
+            fired: 0,
+            failed: 0,
+            last_fired: alert.fired_at,
+        });
+        count.fired += 1;
+        if matches!(alert.outcome, AlertOutcome::Failed | AlertOutcome::Partial) {
+            count.failed += 1;
+        }
+        count.last_fired = count.last_fired.max(alert.fired_at);
+    }
+    counts.into_values().collect()
+}
+
+/// A schema migration and whether this store has it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    /// `None` while pending.
+    pub applied_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error(transparent)]
+    Sqlite(#[from] sqlx::Error),
+    #[error("failed to create the store directory: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to migrate the store: {0}")]
+    Migrate(#[from] sqlx::migrate::MigrateError),
+    #[error("a storage key is set but this build can't encrypt the store; rebuild with `--features encryption`")]
+    EncryptionUnavailable,
+}
+
+/// Durable append-only price log, read back by symbol and time range.
+#[async_trait]
+pub trait PriceStore: Send + Sync {
+    async fn append(&self, prices: &[StoredPrice]) -> Result<(), StorageError>;
+
+    /// Prices of `symbol` received in `[from, to)`, oldest first.
+    async fn range(
+        &self,
+        symbol: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<StoredPrice>, StorageError>;
+}
+
+/// Durable log of fired alerts.
+#[async_trait]
+pub trait AlertStore: Send + Sync {
+    async fn record_alert(&self, alert: &AlertRecord) -> Result<(), StorageError>;
+
+    /// Alerts fired since `since`, about `symbol` when given, newest first.
+    async fn alerts(
+        &self,
+        symbol: Option<&str>,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<AlertRecord>, StorageError>;
+
+    /// Alerts fired per rule since `since`, by rule.
+    async fn rule_counts(&self, since: DateTime<Utc>) -> Result<Vec<RuleCount>, StorageError>;
+}
+
+/// Daily bars, fundamentals and earnings surprises, one row per symbol and
+/// day. Writing a day again replaces it, so reruns are harmless.
+#[async_trait]
+pub trait DailyStore: Send + Sync {
+    async fn upsert_bars(&self, bars: &[DailyBar]) -> Result<(), StorageError>;
+
+    /// Bars of `symbol` dated `from` through `to`, oldest first.
+    async fn bars(
+        &self,
+        symbol: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DailyBar>, StorageError>;
+
+    async fn upsert_fundamentals(&self, fundamentals: &[Fundamentals]) -> Result<(), StorageError>;
+
+    /// The latest snapshot of `symbol`.
+    async fn fundamentals(&self, symbol: &str) -> Result<Option<Fundamentals>, StorageError>;
+
+    async fn upsert_surprises(&self, surprises: &[EarningsSurprise]) -> Result<(), StorageError>;
+
+    /// Every stored report of `symbol`, newest first.
+    async fn surprises(&self, symbol: &str) -> Result<Vec<EarningsSurprise>, StorageError>;
+}
+
+/// Appends every price on the bus, writing whatever has queued up since the
+/// last write as one batch so a slow disk doesn't fall further behind.
+/// Prices arriving while storage is toggled off are dropped.
+pub async fn write_prices(
+    store: Arc<dyn PriceStore>,
+    source: String,
+    mut events: UnboundedReceiver<Event>,
+    toggles: Arc<Toggles>,
+    limits: Arc<Limits>,
+) {
+    toggles.register(toggles::STORAGE);
+    info!(source, "Writing prices to the store");
+    let mut batch = vec![];
+    while let Some(event) = events.recv().await {
+        let mut next = Some(event);
+        while let Some(event) = next {
+            if let Event::Price(update) = event {
+                batch.push(StoredPrice {
+                    symbol: update.symbol,
+                    price: update.price,
+                    timestamp: update.timestamp,
+                    provider_timestamp: update.provider_timestamp,
+                    source: source.clone(),
+                });
+            }
+            next = events.try_recv().ok();
+        }
+        if !toggles.enabled(toggles::STORAGE) {
+            batch.clear();
+        }
+        batch.truncate(limits.storable(batch.len(), Utc::now()));
+        if batch.is_empty() {
+            continue;
+        }
+        let oldest = batch.iter().map(|p| p.timestamp).min();
+        lag::behind(lag::STORAGE, oldest);
+        match store.append(&batch).await {
+            Ok(()) => metrics::update_prices_stored(batch.len()),
+            Err(e) => error!(prices = batch.len(), error = %e, "Failed to store prices"),
+        }
+        let now = Utc::now();
+        lag::caught_up(
+            lag::STORAGE,
+            oldest.map_or(0., |at| (now - at).num_milliseconds() as f64 / 1000.),
+        );
+        lag::backlog(lag::STORAGE, events.len());
+        batch.clear();
+    }
+}
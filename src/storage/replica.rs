@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use tracing::warn;
+
+use crate::metrics;
+
+use super::sqlite::SqliteStore;
+use super::{
+    AlertRecord, AlertStore, DailyBar, DailyStore, EarningsSurprise, Fundamentals, PriceStore,
+    RuleCount, StorageError, StoredPrice,
+};
+
+/// Serves reads from a read-only replica and writes to the primary. A read
+/// the replica fails is answered by the primary instead.
+pub struct ReadReplica {
+    replica: SqliteStore,
+    primary: Arc<SqliteStore>,
+}
+
+impl ReadReplica {
+    pub fn new(replica: SqliteStore, primary: Arc<SqliteStore>) -> Self {
+        ReadReplica { replica, primary }
+    }
+
+    fn failed_over(query: &str, error: &StorageError) {
+        warn!(query, error = %error, "Replica read failed, reading from the primary");
+        metrics::update_replica_fallbacks(query);
+    }
+}
+
+#[async_trait]
+impl PriceStore for ReadReplica {
+    async fn append(&self, prices: &[StoredPrice]) -> Result<(), StorageError> {
+        self.primary.append(prices).await
+    }
+
+    async fn range(
+        &self,
+        symbol: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<StoredPrice>, StorageError> {
+        match self.replica.range(symbol, from, to).await {
+            Ok(prices) => Ok(prices),
+            Err(e) => {
+                Self::failed_over("range", &e);
+                self.primary.range(symbol, from, to).await
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AlertStore for ReadReplica {
+    async fn record_alert(&self, alert: &AlertRecord) -> Result<(), StorageError> {
+        self.primary.record_alert(alert).await
+    }
+
+    async fn alerts(
+        &self,
+        symbol: Option<&str>,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<AlertRecord>, StorageError> {
+        match self.replica.alerts(symbol, since).await {
+            Ok(alerts) => Ok(alerts),
+            Err(e) => {
+                Self::failed_over("alerts", &e);
+                self.primary.alerts(symbol, since).await
+            }
+        }
+    }
+
+    async fn rule_counts(&self, since: DateTime<Utc>) -> Result<Vec<RuleCount>, StorageError> {
+        match self.replica.rule_counts(since).await {
+            Ok(counts) => Ok(counts),
+            Err(e) => {
+                Self::failed_over("rule_counts", &e);
+                self.primary.rule_counts(since).await
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl DailyStore for ReadReplica {
+    async fn upsert_bars(&self, bars: &[DailyBar]) -> Result<(), StorageError> {
+        self.primary.upsert_bars(bars).await
+    }
+
+    async fn bars(
+        &self,
+        symbol: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DailyBar>, StorageError> {
+        match self.replica.bars(symbol, from, to).await {
+            Ok(bars) => Ok(bars),
+            Err(e) => {
+                Self::failed_over("bars", &e);
+                self.primary.bars(symbol, from, to).await
+            }
+        }
+    }
+
+    async fn upsert_fundamentals(&self, fundamentals: &[Fundamentals]) -> Result<(), StorageError> {
+        self.primary.upsert_fundamentals(fundamentals).await
+    }
+
+    async fn fundamentals(&self, symbol: &str) -> Result<Option<Fundamentals>, StorageError> {
+        match self.replica.fundamentals(symbol).await {
+            Ok(fundamentals) => Ok(fundamentals),
+            Err(e) => {
+                Self::failed_over("fundamentals", &e);
+                self.primary.fundamentals(symbol).await
+            }
+        }
+    }
+
+    async fn upsert_surprises(&self, surprises: &[EarningsSurprise]) -> Result<(), StorageError> {
+        self.primary.upsert_surprises(surprises).await
+    }
+
+    async fn surprises(&self, symbol: &str) -> Result<Vec<EarningsSurprise>, StorageError> {
+        match self.replica.surprises(symbol).await {
+            Ok(surprises) => Ok(surprises),
+            Err(e) => {
+                Self::failed_over("surprises", &e);
+                self.primary.surprises(symbol).await
+            }
+        }
+    }
+}
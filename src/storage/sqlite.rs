@@ -0,0 +1,504 @@
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::migrate::Migrator;
+use sqlx::sqlite::{
+    SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions, SqliteSynchronous,
+};
+use sqlx::Row;
+use tracing::info;
+
+use crate::encryption::StorageKey;
+
+use super::{
+    AlertOutcome, AlertRecord, AlertStore, DailyBar, DailyStore, EarningsSurprise, Fundamentals,
+    MigrationStatus, PriceStore, RuleCount, StorageError, StoredPrice,
+};
+
+/// The schema's history, from `migrations/`, built into the binary.
+static MIGRATOR: Migrator = sqlx::migrate!();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Synchronous {
+    Off,
+    Normal,
+    Full,
+    Extra,
+}
+
+impl From<Synchronous> for SqliteSynchronous {
+    fn from(synchronous: Synchronous) -> Self {
+        match synchronous {
+            Synchronous::Off => SqliteSynchronous::Off,
+            Synchronous::Normal => SqliteSynchronous::Normal,
+            Synchronous::Full => SqliteSynchronous::Full,
+            Synchronous::Extra => SqliteSynchronous::Extra,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SqliteConfig {
+    /// Write-ahead logging, so API reads don't block on ingest writes.
+    pub wal: bool,
+    /// `normal` is durable across crashes under WAL, if not power loss.
+    pub synchronous: Synchronous,
+    /// How long a connection waits on a locked database before failing
+    /// with SQLITE_BUSY.
+    pub busy_timeout_ms: u64,
+    pub max_connections: u32,
+    /// Kept open while idle.
+    pub min_connections: u32,
+    /// How long a query waits for a free connection.
+    pub acquire_timeout_secs: u64,
+}
+
+impl Default for SqliteConfig {
+    fn default() -> Self {
+        SqliteConfig {
+            wal: true,
+            synchronous: Synchronous::Normal,
+            busy_timeout_ms: 5_000,
+            max_connections: 4,
+            min_connections: 0,
+            acquire_timeout_secs: 30,
+        }
+    }
+}
+
+impl SqliteConfig {
+    // Pragmas that don't need write access, so replicas get them too.
+    fn apply(&self, options: SqliteConnectOptions) -> SqliteConnectOptions {
+        options
+            .busy_timeout(Duration::from_millis(self.busy_timeout_ms))
+            .synchronous(self.synchronous.into())
+    }
+
+    fn pool(&self) -> SqlitePoolOptions {
+        SqlitePoolOptions::new()
+            .max_connections(self.max_connections.max(1))
+            .min_connections(self.min_connections.min(self.max_connections.max(1)))
+            .acquire_timeout(Duration::from_secs(self.acquire_timeout_secs))
+    }
+}
+
+/// Prices and fired alerts in SQLite, each in one table indexed by time,
+/// and the daily pipeline's bars, fundamentals and earnings surprises keyed
+/// by symbol and day.
+pub struct SqliteStore {
+    pool: SqlitePool,
+}
+
+// Fixed-width RFC 3339 in UTC, so timestamps sort as text.
+fn text(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+impl SqliteStore {
+    /// Opens the database at `path`, creating it if missing, and brings its
+    /// schema up to date.
+    pub async fn open(
+        path: &Path,
+        key: Option<&StorageKey>,
+        config: &SqliteConfig,
+    ) -> Result<Self, StorageError> {
+        let store = SqliteStore::connect(path, key, config).await?;
+        store.migrate().await?;
+        Ok(store)
+    }
+
+    /// Opens the database at `path` as it is, creating it if missing. With a
+    /// `key` the file and its journal are encrypted by SQLCipher; an existing
+    /// unencrypted store won't open with one.
+    pub async fn connect(
+        path: &Path,
+        key: Option<&StorageKey>,
+        config: &SqliteConfig,
+    ) -> Result<Self, StorageError> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let journal = if config.wal {
+            SqliteJournalMode::Wal
+        } else {
+            SqliteJournalMode::Delete
+        };
+        let mut options = config.apply(
+            SqliteConnectOptions::new()
+                .filename(path)
+                .create_if_missing(true)
+                .journal_mode(journal),
+        );
+        if let Some(key) = key {
+            if !cfg!(feature = "encryption") {
+                return Err(StorageError::EncryptionUnavailable);
+            }
+            options = options.pragma("key", key.sqlcipher());
+        }
+        let pool = config.pool().connect_with(options).await?;
+        Ok(SqliteStore { pool })
+    }
+
+    /// Opens the replica at `url`, such as `sqlite:///replica/prices.db`,
+    /// read-only and as it is; it is migrated by whatever keeps it in sync.
+    pub async fn replica(
+        url: &str,
+        key: Option<&StorageKey>,
+        config: &SqliteConfig,
+    ) -> Result<Self, StorageError> {
+        let mut options = config.apply(
+            SqliteConnectOptions::from_str(url)?
+                .read_only(true)
+                .create_if_missing(false),
+        );
+        if let Some(key) = key {
+            if !cfg!(feature = "encryption") {
+                return Err(StorageError::EncryptionUnavailable);
+            }
+            options = options.pragma("key", key.sqlcipher());
+        }
+        let pool = config.pool().connect_with(options).await?;
+        Ok(SqliteStore { pool })
+    }
+
+    /// Applies the migrations not yet applied, in order, and returns the
+    /// versions it applied.
+    pub async fn migrate(&self) -> Result<Vec<i64>, StorageError> {
+        let before = self.applied().await?;
+        MIGRATOR.run(&self.pool).await?;
+        let applied: Vec<i64> = MIGRATOR
+            .iter()
+            .map(|m| m.version)
+            .filter(|version| !before.iter().any(|(v, _)| v == version))
+            .collect();
+        if !applied.is_empty() {
+            info!(?applied, "Migrated price store");
+        }
+        Ok(applied)
+    }
+
+    /// Every known migration and when it was applied here.
+    pub async fn migrations(&self) -> Result<Vec<MigrationStatus>, StorageError> {
+        let applied = self.applied().await?;
+        Ok(MIGRATOR
+            .iter()
+            .map(|m| MigrationStatus {
+                version: m.version,
+                description: m.description.to_string(),
+                applied_at: applied
+                    .iter()
+                    .find(|(version, _)| *version == m.version)
+                    .map(|(_, at)| *at),
+            })
+            .collect())
+    }
+
+    // Versions applied so far, none before the first migration ran.
+    async fn applied(&self) -> Result<Vec<(i64, DateTime<Utc>)>, StorageError> {
+        let exists: Option<String> = sqlx::query_scalar(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations'",
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        if exists.is_none() {
+            return Ok(vec![]);
+        }
+        let rows = sqlx::query(
+            "SELECT version, installed_on FROM _sqlx_migrations WHERE success ORDER BY version",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter()
+            .map(|row| Ok((row.try_get("version")?, row.try_get("installed_on")?)))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl PriceStore for SqliteStore {
+    async fn append(&self, prices: &[StoredPrice]) -> Result<(), StorageError> {
+        let mut tx = self.pool.begin().await?;
+        for price in prices {
+            sqlx::query(
+                "INSERT INTO prices (symbol, price, timestamp, provider_timestamp, source) \
+                 VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(&price.symbol)
+            .bind(price.price)
+            .bind(text(price.timestamp))
+            .bind(price.provider_timestamp.map(text))
+            .bind(&price.source)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn range(
+        &self,
+        symbol: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<StoredPrice>, StorageError> {
+        let rows = sqlx::query(
+            "SELECT symbol, price, timestamp, provider_timestamp, source FROM prices \
+             WHERE symbol = ? AND timestamp >= ? AND timestamp < ? ORDER BY timestamp",
+        )
+        .bind(symbol)
+        .bind(text(from))
+        .bind(text(to))
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter()
+            .map(|row| {
+                Ok(StoredPrice {
+                    symbol: row.try_get("symbol")?,
+                    price: row.try_get("price")?,
+                    timestamp: row.try_get("timestamp")?,
+                    provider_timestamp: row.try_get("provider_timestamp")?,
+                    source: row.try_get("source")?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl AlertStore for SqliteStore {
+    async fn record_alert(&self, alert: &AlertRecord) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO alerts (rule, symbol, value, title, fired_at, outcome, failed) \
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&alert.rule)
+        .bind(&alert.symbol)
+        .bind(alert.value)
+        .bind(&alert.title)
+        .bind(text(alert.fired_at))
+        .bind(alert.outcome.as_str())
+        .bind(alert.failed.join(","))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn alerts(
+        &self,
+        symbol: Option<&str>,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<AlertRecord>, StorageError> {
+        let rows = sqlx::query(
+            "SELECT rule, symbol, value, title, fired_at, outcome, failed FROM alerts \
+             WHERE fired_at >= ? AND (? IS NULL OR symbol = ?) ORDER BY fired_at DESC",
+        )
+        .bind(text(since))
+        .bind(symbol)
+        .bind(symbol)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter()
+            .map(|row| {
+                let outcome: String = row.try_get("outcome")?;
+                let failed: String = row.try_get("failed")?;
+                Ok(AlertRecord {
+                    rule: row.try_get("rule")?,
+                    symbol: row.try_get("symbol")?,
+                    value: row.try_get("value")?,
+                    title: row.try_get("title")?,
+                    fired_at: row.try_get("fired_at")?,
+                    outcome: AlertOutcome::parse(&outcome).ok_or_else(|| {
+                        sqlx::Error::ColumnDecode {
+                            index: "outcome".into(),
+                            source: format!("unknown alert outcome {:?}", outcome).into(),
+                        }
+                    })?,
+                    failed: failed
+                        .split(',')
+                        .filter(|name| !name.is_empty())
+                        .map(String::from)
+                        .collect(),
+                })
+            })
+            .collect()
+    }
+
+    async fn rule_counts(&self, since: DateTime<Utc>) -> Result<Vec<RuleCount>, StorageError> {
+        let rows = sqlx::query(
+            "SELECT rule, COUNT(*) AS fired, SUM(outcome IN ('failed', 'partial')) AS failed, \
+             MAX(fired_at) AS last_fired FROM alerts WHERE fired_at >= ? \
+             GROUP BY rule ORDER BY rule",
+        )
+        .bind(text(since))
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter()
+            .map(|row| {
+                let fired: i64 = row.try_get("fired")?;
+                let failed: i64 = row.try_get("failed")?;
+                let last_fired: String = row.try_get("last_fired")?;
+                Ok(RuleCount {
+                    rule: row.try_get("rule")?,
+                    fired: fired as u64,
+                    failed: failed as u64,
+                    last_fired: last_fired.parse().map_err(|e: chrono::ParseError| {
+                        sqlx::Error::ColumnDecode {
+                            index: "last_fired".into(),
+                            source: e.into(),
+                        }
+                    })?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl DailyStore for SqliteStore {
+    async fn upsert_bars(&self, bars: &[DailyBar]) -> Result<(), StorageError> {
+        let mut tx = self.pool.begin().await?;
+        for bar in bars {
+            sqlx::query(
+                "INSERT OR REPLACE INTO daily_bars \
+                 (symbol, date, open, high, low, close, volume, source) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&bar.symbol)
+            .bind(bar.date)
+            .bind(bar.open)
+            .bind(bar.high)
+            .bind(bar.low)
+            .bind(bar.close)
+            .bind(bar.volume)
+            .bind(&bar.source)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn bars(
+        &self,
+        symbol: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DailyBar>, StorageError> {
+        let rows = sqlx::query(
+            "SELECT symbol, date, open, high, low, close, volume, source FROM daily_bars \
+             WHERE symbol = ? AND date >= ? AND date <= ? ORDER BY date",
+        )
+        .bind(symbol)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter()
+            .map(|row| {
+                Ok(DailyBar {
+                    symbol: row.try_get("symbol")?,
+                    date: row.try_get("date")?,
+                    open: row.try_get("open")?,
+                    high: row.try_get("high")?,
+                    low: row.try_get("low")?,
+                    close: row.try_get("close")?,
+                    volume: row.try_get("volume")?,
+                    source: row.try_get("source")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn upsert_fundamentals(&self, fundamentals: &[Fundamentals]) -> Result<(), StorageError> {
+        let mut tx = self.pool.begin().await?;
+        for f in fundamentals {
+            sqlx::query(
+                "INSERT OR REPLACE INTO fundamentals \
+                 (symbol, date, market_cap, trailing_pe, eps, dividend_yield, beta) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&f.symbol)
+            .bind(f.date)
+            .bind(f.market_cap)
+            .bind(f.trailing_pe)
+            .bind(f.eps)
+            .bind(f.dividend_yield)
+            .bind(f.beta)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn fundamentals(&self, symbol: &str) -> Result<Option<Fundamentals>, StorageError> {
+        let row = sqlx::query(
+            "SELECT symbol, date, market_cap, trailing_pe, eps, dividend_yield, beta \
+             FROM fundamentals WHERE symbol = ? ORDER BY date DESC LIMIT 1",
+        )
+        .bind(symbol)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.map(|row| {
+            Ok(Fundamentals {
+                symbol: row.try_get("symbol")?,
+                date: row.try_get("date")?,
+                market_cap: row.try_get("market_cap")?,
+                trailing_pe: row.try_get("trailing_pe")?,
+                eps: row.try_get("eps")?,
+                dividend_yield: row.try_get("dividend_yield")?,
+                beta: row.try_get("beta")?,
+            })
+        })
+        .transpose()
+    }
+
+    async fn upsert_surprises(&self, surprises: &[EarningsSurprise]) -> Result<(), StorageError> {
+        let mut tx = self.pool.begin().await?;
+        for s in surprises {
+            sqlx::query(
+                "INSERT OR REPLACE INTO earnings_surprises \
+                 (symbol, date, eps_estimate, eps_actual, revenue_estimate, revenue_actual) \
+                 VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&s.symbol)
+            .bind(s.date)
+            .bind(s.eps_estimate)
+            .bind(s.eps_actual)
+            .bind(s.revenue_estimate)
+            .bind(s.revenue_actual)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn surprises(&self, symbol: &str) -> Result<Vec<EarningsSurprise>, StorageError> {
+        let rows = sqlx::query(
+            "SELECT symbol, date, eps_estimate, eps_actual, revenue_estimate, revenue_actual \
+             FROM earnings_surprises WHERE symbol = ? ORDER BY date DESC",
+        )
+        .bind(symbol)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter()
+            .map(|row| {
+                Ok(EarningsSurprise {
+                    symbol: row.try_get("symbol")?,
+                    date: row.try_get("date")?,
+                    eps_estimate: row.try_get("eps_estimate")?,
+                    eps_actual: row.try_get("eps_actual")?,
+                    revenue_estimate: row.try_get("revenue_estimate")?,
+                    revenue_actual: row.try_get("revenue_actual")?,
+                })
+            })
+            .collect()
+    }
+}
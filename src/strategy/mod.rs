@@ -0,0 +1,342 @@
+pub mod script;
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+use crate::events::{Event, EventBus};
+use crate::history::{Adjustment, History, Resolution, Series};
+use crate::notify::{Notification, NotificationKind, Notifiers, Urgency};
+use crate::paper::{Order, OrderRequest, PaperAccount, PaperError, Portfolio, Side};
+use crate::returns::Conventions;
+use crate::sink::{PriceUpdate, Sink};
+
+pub use script::ScriptStrategy;
+
+// Signals may trigger further signals; stop a feedback loop after this many rounds.
+const MAX_SIGNAL_ROUNDS: usize = 8;
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Candle {
+    pub symbol: String,
+    pub start: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub ticks: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Signal {
+    /// Name of the strategy that emitted it.
+    pub source: String,
+    pub symbol: String,
+    pub name: String,
+    pub value: f64,
+}
+
+/// User trading logic. Every callback gets a [`Context`] for placing paper
+/// orders, sending notifications and emitting signals to other strategies.
+pub trait Strategy: Send {
+    fn name(&self) -> &str;
+
+    fn on_tick(&mut self, _tick: &PriceUpdate, _ctx: &mut Context) {}
+
+    fn on_candle(&mut self, _candle: &Candle, _ctx: &mut Context) {}
+
+    fn on_signal(&mut self, _signal: &Signal, _ctx: &mut Context) {}
+}
+
+pub struct Context<'a> {
+    pub now: DateTime<Utc>,
+    strategy: &'a str,
+    /// Symbol of the tick, candle or signal being handled.
+    symbol: &'a str,
+    /// Latest price of `symbol`, recorded with its alerts.
+    price: Option<f64>,
+    paper: &'a PaperAccount,
+    history: Option<&'a History>,
+    notifications: Vec<Notification>,
+    signals: Vec<Signal>,
+}
+
+impl Context<'_> {
+    pub fn place(&mut self, request: OrderRequest) -> Result<Order, PaperError> {
+        self.paper.place(request, self.now)
+    }
+
+    pub fn buy(&mut self, symbol: &str, quantity: f64) -> Result<Order, PaperError> {
+        self.market(symbol, Side::Buy, quantity)
+    }
+
+    pub fn sell(&mut self, symbol: &str, quantity: f64) -> Result<Order, PaperError> {
+        self.market(symbol, Side::Sell, quantity)
+    }
+
+    fn market(&mut self, symbol: &str, side: Side, quantity: f64) -> Result<Order, PaperError> {
+        self.place(OrderRequest {
+            symbol: symbol.to_string(),
+            side,
+            quantity,
+            limit_price: None,
+        })
+    }
+
+    pub fn portfolio(&self) -> Portfolio {
+        self.paper.portfolio()
+    }
+
+    /// Recorded prices since `since`, raw or split-adjusted, at the given
+    /// resolution or, with `Auto`, the finest one retained that far back.
+    /// Empty when the engine keeps no history.
+    pub fn history(
+        &self,
+        symbol: &str,
+        since: DateTime<Utc>,
+        resolution: Resolution,
+        adjustment: Adjustment,
+    ) -> Series {
+        match self.history {
+            Some(history) => history.query(symbol, since, self.now, resolution, adjustment),
+            None => Series {
+                resolution,
+                points: vec![],
+            },
+        }
+    }
+
+    pub fn notify(&mut self, title: &str, body: &str, urgency: Urgency) {
+        self.notifications.push(Notification {
+            title: format!("[{}] {}", self.strategy, title),
+            body: body.to_string(),
+            urgency,
+            kind: NotificationKind::Alert,
+            symbols: vec![self.symbol.to_string()],
+            rule: Some(self.strategy.to_string()),
+            value: self.price,
+            labels: BTreeMap::new(),
+        });
+    }
+
+    pub fn signal(&mut self, symbol: &str, name: &str, value: f64) {
+        self.signals.push(Signal {
+            source: self.strategy.to_string(),
+            symbol: symbol.to_string(),
+            name: name.to_string(),
+            value,
+        });
+    }
+}
+
+struct State {
+    strategies: Vec<Box<dyn Strategy>>,
+    candles: HashMap<String, Candle>,
+}
+
+/// A sink that drives registered strategies from the engine's price
+/// updates, aggregating ticks into fixed-width candles.
+pub struct StrategyRunner {
+    paper: Arc<PaperAccount>,
+    history: Option<Arc<History>>,
+    events: EventBus,
+    notifiers: Notifiers,
+    candle_secs: i64,
+    conventions: Conventions,
+    state: Mutex<State>,
+}
+
+impl StrategyRunner {
+    pub fn new(
+        paper: Arc<PaperAccount>,
+        notifiers: Notifiers,
+        events: EventBus,
+        candle_secs: i64,
+    ) -> Self {
+        StrategyRunner {
+            paper,
+            history: None,
+            events,
+            notifiers,
+            candle_secs: candle_secs.max(1),
+            conventions: Conventions::default(),
+            state: Mutex::new(State {
+                strategies: vec![],
+                candles: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn with_history(mut self, history: Arc<History>) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Day-wide candles span trading days of record instead of UTC days.
+    pub fn with_conventions(mut self, conventions: Conventions) -> Self {
+        self.conventions = conventions;
+        self
+    }
+
+    pub fn add(&self, strategy: Box<dyn Strategy>) {
+        self.state.lock().unwrap().strategies.push(strategy);
+    }
+
+    fn bucket(&self, timestamp: DateTime<Utc>) -> DateTime<Utc> {
+        if self.candle_secs == DAY_SECS {
+            return self.conventions.truncate(timestamp);
+        }
+        let secs = timestamp.timestamp();
+        DateTime::from_timestamp(secs - secs.rem_euclid(self.candle_secs), 0).unwrap_or(timestamp)
+    }
+
+    // Closes the running candle when the tick starts a new bucket.
+    fn update_candle(&self, state: &mut State, tick: &PriceUpdate) -> Option<Candle> {
+        let start = self.bucket(tick.timestamp);
+        let fresh = Candle {
+            symbol: tick.symbol.clone(),
+            start,
+            open: tick.price,
+            high: tick.price,
+            low: tick.price,
+            close: tick.price,
+            ticks: 1,
+        };
+        match state.candles.get_mut(&tick.symbol) {
+            Some(candle) if candle.start == start => {
+                candle.high = candle.high.max(tick.price);
+                candle.low = candle.low.min(tick.price);
+                candle.close = tick.price;
+                candle.ticks += 1;
+                None
+            }
+            Some(candle) => Some(std::mem::replace(candle, fresh)),
+            None => {
+                state.candles.insert(tick.symbol.clone(), fresh);
+                None
+            }
+        }
+    }
+
+    fn dispatch<F>(
+        &self,
+        strategies: &mut [Box<dyn Strategy>],
+        symbol: &str,
+        now: DateTime<Utc>,
+        mut call: F,
+    ) where
+        F: FnMut(&mut dyn Strategy, &mut Context),
+    {
+        let mut notifications = vec![];
+        let mut signals = vec![];
+        for strategy in strategies.iter_mut() {
+            let name = strategy.name().to_string();
+            let mut ctx = self.context(&name, symbol, now);
+            call(strategy.as_mut(), &mut ctx);
+            notifications.append(&mut ctx.notifications);
+            signals.append(&mut ctx.signals);
+        }
+
+        let mut rounds = 0;
+        while !signals.is_empty() {
+            rounds += 1;
+            if rounds > MAX_SIGNAL_ROUNDS {
+                warn!(
+                    pending = signals.len(),
+                    "Dropping strategy signals after too many rounds"
+                );
+                break;
+            }
+            let mut next = vec![];
+            for signal in &signals {
+                self.events.publish(Event::Signal {
+                    at: now,
+                    signal: signal.clone(),
+                });
+                for strategy in strategies.iter_mut() {
+                    if strategy.name() == signal.source {
+                        continue;
+                    }
+                    let name = strategy.name().to_string();
+                    let mut ctx = self.context(&name, &signal.symbol, now);
+                    strategy.on_signal(signal, &mut ctx);
+                    notifications.append(&mut ctx.notifications);
+                    next.append(&mut ctx.signals);
+                }
+            }
+            signals = next;
+        }
+
+        if notifications.is_empty() {
+            return;
+        }
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                let notifiers = self.notifiers.clone();
+                handle.spawn(async move {
+                    for notification in &notifications {
+                        notifiers.notify(notification).await;
+                    }
+                });
+            }
+            Err(_) => error!("No runtime to send strategy notifications on"),
+        }
+    }
+
+    fn context<'a>(
+        &'a self,
+        strategy: &'a str,
+        symbol: &'a str,
+        now: DateTime<Utc>,
+    ) -> Context<'a> {
+        Context {
+            now,
+            strategy,
+            symbol,
+            price: self
+                .state
+                .lock()
+                .unwrap()
+                .candles
+                .get(symbol)
+                .map(|candle| candle.close),
+            paper: &self.paper,
+            history: self.history.as_deref(),
+            notifications: vec![],
+            signals: vec![],
+        }
+    }
+}
+
+impl Sink for StrategyRunner {
+    // Strategies only see live data; replay restores the candles.
+    fn replay(&self, update: &PriceUpdate) {
+        let mut state = self.state.lock().unwrap();
+        self.update_candle(&mut state, update);
+    }
+
+    fn record(&self, update: &PriceUpdate) {
+        let mut state = self.state.lock().unwrap();
+        let closed = self.update_candle(&mut state, update);
+        let mut strategies = std::mem::take(&mut state.strategies);
+        drop(state);
+
+        let now = update.timestamp;
+        self.dispatch(&mut strategies, &update.symbol, now, |s, ctx| {
+            s.on_tick(update, ctx)
+        });
+        if let Some(candle) = closed {
+            self.dispatch(&mut strategies, &candle.symbol, now, |s, ctx| {
+                s.on_candle(&candle, ctx)
+            });
+        }
+
+        let mut state = self.state.lock().unwrap();
+        strategies.append(&mut state.strategies);
+        state.strategies = strategies;
+    }
+}
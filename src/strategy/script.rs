@@ -0,0 +1,204 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use rhai::{CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+use tracing::{error, info, warn};
+
+use super::{Candle, Context, Signal, Strategy};
+use crate::market::MarketContext;
+use crate::notify::Urgency;
+use crate::sink::PriceUpdate;
+
+// How often the script file is checked for edits.
+const RELOAD_CHECK: Duration = Duration::from_secs(1);
+
+enum Action {
+    Buy(String, f64),
+    Sell(String, f64),
+    Notify(String, String),
+    Signal(String, String, f64),
+}
+
+/// A strategy or alert written in Rhai. The script may define `on_tick`,
+/// `on_candle` and `on_signal`, each taking a map, keep state on `this`,
+/// and call `buy`, `sell`, `notify` and `signal`, and `market` with
+/// market-wide indicators on. Edits to the file are
+/// picked up without a restart; a script that fails to compile keeps the
+/// previous version running.
+pub struct ScriptStrategy {
+    name: String,
+    path: PathBuf,
+    engine: Engine,
+    ast: Option<AST>,
+    modified: Option<SystemTime>,
+    checked: Instant,
+    state: Dynamic,
+    actions: Arc<Mutex<Vec<Action>>>,
+}
+
+impl ScriptStrategy {
+    pub fn load(path: &Path) -> Self {
+        let actions = Arc::new(Mutex::new(vec![]));
+        let mut engine = Engine::new();
+        let queue = actions.clone();
+        engine.register_fn("buy", move |symbol: &str, quantity: f64| {
+            queue
+                .lock()
+                .unwrap()
+                .push(Action::Buy(symbol.to_string(), quantity))
+        });
+        let queue = actions.clone();
+        engine.register_fn("sell", move |symbol: &str, quantity: f64| {
+            queue
+                .lock()
+                .unwrap()
+                .push(Action::Sell(symbol.to_string(), quantity))
+        });
+        let queue = actions.clone();
+        engine.register_fn("notify", move |title: &str, body: &str| {
+            queue
+                .lock()
+                .unwrap()
+                .push(Action::Notify(title.to_string(), body.to_string()))
+        });
+        let queue = actions.clone();
+        engine.register_fn("signal", move |symbol: &str, name: &str, value: f64| {
+            queue
+                .lock()
+                .unwrap()
+                .push(Action::Signal(symbol.to_string(), name.to_string(), value))
+        });
+
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+        let mut strategy = ScriptStrategy {
+            name,
+            path: path.to_path_buf(),
+            engine,
+            ast: None,
+            modified: None,
+            checked: Instant::now(),
+            state: Dynamic::from_map(Map::new()),
+            actions,
+        };
+        strategy.reload();
+        strategy
+    }
+
+    /// Lets the script read market-wide indicators with `market(name)`,
+    /// which is `()` until the indicator is known.
+    pub fn with_market(mut self, market: Arc<MarketContext>) -> Self {
+        self.engine
+            .register_fn("market", move |name: &str| match market.get(name) {
+                Some(value) => Dynamic::from_float(value),
+                None => Dynamic::UNIT,
+            });
+        self
+    }
+
+    fn reload(&mut self) {
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .ok();
+        if self.ast.is_some() && modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+        let source = match std::fs::read_to_string(&self.path) {
+            Ok(source) => source,
+            Err(e) => {
+                error!(path = %self.path.display(), error = %e, "Failed to read script");
+                return;
+            }
+        };
+        match self.engine.compile(&source) {
+            Ok(ast) => {
+                info!(path = %self.path.display(), "Loaded script");
+                self.ast = Some(ast);
+            }
+            Err(e) => error!(path = %self.path.display(), error = %e, "Failed to compile script"),
+        }
+    }
+
+    fn call(&mut self, function: &str, arg: Map, ctx: &mut Context) {
+        if self.checked.elapsed() >= RELOAD_CHECK {
+            self.checked = Instant::now();
+            self.reload();
+        }
+        let Some(ast) = &self.ast else {
+            return;
+        };
+        if !ast.iter_functions().any(|f| f.name == function) {
+            return;
+        }
+
+        let options = CallFnOptions::new().bind_this_ptr(&mut self.state);
+        let result = self.engine.call_fn_with_options::<Dynamic>(
+            options,
+            &mut Scope::new(),
+            ast,
+            function,
+            (Dynamic::from_map(arg),),
+        );
+        if let Err(e) = result {
+            warn!(script = %self.name, function, error = %e, "Script failed");
+        }
+
+        let actions = std::mem::take(&mut *self.actions.lock().unwrap());
+        for action in actions {
+            let result = match action {
+                Action::Buy(symbol, quantity) => ctx.buy(&symbol, quantity).map(drop),
+                Action::Sell(symbol, quantity) => ctx.sell(&symbol, quantity).map(drop),
+                Action::Notify(title, body) => {
+                    ctx.notify(&title, &body, Urgency::Normal);
+                    Ok(())
+                }
+                Action::Signal(symbol, name, value) => {
+                    ctx.signal(&symbol, &name, value);
+                    Ok(())
+                }
+            };
+            if let Err(e) = result {
+                warn!(script = %self.name, error = %e, "Script order rejected");
+            }
+        }
+    }
+}
+
+impl Strategy for ScriptStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn on_tick(&mut self, tick: &PriceUpdate, ctx: &mut Context) {
+        let mut map = Map::new();
+        map.insert("symbol".into(), tick.symbol.clone().into());
+        map.insert("price".into(), tick.price.into());
+        map.insert("timestamp".into(), tick.timestamp.timestamp().into());
+        self.call("on_tick", map, ctx);
+    }
+
+    fn on_candle(&mut self, candle: &Candle, ctx: &mut Context) {
+        let mut map = Map::new();
+        map.insert("symbol".into(), candle.symbol.clone().into());
+        map.insert("start".into(), candle.start.timestamp().into());
+        map.insert("open".into(), candle.open.into());
+        map.insert("high".into(), candle.high.into());
+        map.insert("low".into(), candle.low.into());
+        map.insert("close".into(), candle.close.into());
+        map.insert("ticks".into(), (candle.ticks as i64).into());
+        self.call("on_candle", map, ctx);
+    }
+
+    fn on_signal(&mut self, signal: &Signal, ctx: &mut Context) {
+        let mut map = Map::new();
+        map.insert("source".into(), signal.source.clone().into());
+        map.insert("symbol".into(), signal.symbol.clone().into());
+        map.insert("name".into(), signal.name.clone().into());
+        map.insert("value".into(), signal.value.into());
+        self.call("on_signal", map, ctx);
+    }
+}
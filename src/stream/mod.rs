@@ -0,0 +1,254 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use thiserror::Error;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, info, instrument, warn};
+
+use crate::clock::Clock;
+use crate::engine::Engine;
+use crate::metrics;
+use crate::provider::{number, Quote};
+use crate::symbol::SymbolInfo;
+
+pub const TWELVEDATA_WS_URL: &str = "wss://ws.twelvedata.com/v1/quotes/price";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StreamConfig {
+    /// Stream prices over Twelve Data's WebSocket, polling only what the
+    /// stream doesn't cover.
+    pub enabled: bool,
+    pub url: String,
+    /// Keepalive interval, also how often subscriptions follow the watchlist.
+    pub heartbeat_secs: u64,
+    /// A subscribed symbol without a tick for this long is polled again.
+    pub stale_secs: u64,
+    /// Longest wait between reconnects, which back off exponentially.
+    pub max_backoff_secs: u64,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        StreamConfig {
+            enabled: false,
+            url: TWELVEDATA_WS_URL.into(),
+            heartbeat_secs: 10,
+            stale_secs: 60,
+            max_backoff_secs: 60,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+enum StreamError {
+    #[error(transparent)]
+    Socket(#[from] tokio_tungstenite::tungstenite::Error),
+    #[error("closed by the server")]
+    Closed,
+}
+
+#[derive(Debug, Deserialize)]
+struct Subscription {
+    symbol: String,
+    exchange: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+enum StreamEvent {
+    Price {
+        symbol: String,
+        exchange: Option<String>,
+        #[serde(deserialize_with = "number::deserialize")]
+        price: f64,
+        /// Unix seconds.
+        timestamp: Option<i64>,
+    },
+    SubscribeStatus {
+        #[serde(default)]
+        success: Vec<Subscription>,
+        #[serde(default)]
+        fails: Vec<Subscription>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+// Name on the socket: exchange listings as `BASE:EXCHANGE`.
+fn stream_symbol(symbol: &str) -> String {
+    let info = SymbolInfo::parse(symbol);
+    match info.exchange {
+        Some(exchange) => format!("{}:{:?}", info.base, exchange),
+        None => symbol.to_string(),
+    }
+}
+
+/// Pushes prices from Twelve Data's WebSocket into the engine. Symbols
+/// that tick are skipped by polling; when the socket drops, or a symbol
+/// goes quiet for [`StreamConfig::stale_secs`], REST polling picks it up
+/// until the stream is back.
+pub struct PriceStream {
+    config: StreamConfig,
+    clock: Arc<dyn Clock>,
+    // Last tick or subscription confirmation per watched symbol.
+    live: Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+impl PriceStream {
+    pub fn new(config: StreamConfig, clock: Arc<dyn Clock>) -> Self {
+        PriceStream {
+            config,
+            clock,
+            live: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_live(&self, symbol: &str) -> bool {
+        let stale = chrono::Duration::seconds(self.config.stale_secs as i64);
+        self.live
+            .lock()
+            .unwrap()
+            .get(symbol)
+            .is_some_and(|at| self.clock.now() - *at < stale)
+    }
+
+    fn mark(&self, symbol: &str) {
+        let mut live = self.live.lock().unwrap();
+        live.insert(symbol.to_string(), self.clock.now());
+        metrics::update_stream_symbols(live.len());
+    }
+
+    fn drop_all(&self) {
+        self.live.lock().unwrap().clear();
+        metrics::update_stream_symbols(0);
+    }
+
+    /// Connects and reconnects forever.
+    pub async fn run(self: Arc<Self>, engine: Arc<Engine>, api_key: String) {
+        let mut backoff = 1;
+        loop {
+            let started = self.clock.now();
+            let result = self.session(&engine, &api_key).await;
+            self.drop_all();
+            metrics::update_stream_connected(false);
+            if let Err(e) = result {
+                warn!(error = %e, "Price stream dropped, polling until it reconnects");
+            }
+            // A session that lasted resets the backoff.
+            if self.clock.now() - started > chrono::Duration::seconds(60) {
+                backoff = 1;
+            }
+            self.clock.sleep(Duration::from_secs(backoff)).await;
+            backoff = (backoff * 2).min(self.config.max_backoff_secs.max(1));
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn session(&self, engine: &Engine, api_key: &str) -> Result<(), StreamError> {
+        let url = format!("{}?apikey={}", self.config.url, api_key);
+        let (socket, _) = tokio_tungstenite::connect_async(url).await?;
+        info!(url = %self.config.url, "Price stream connected");
+        metrics::update_stream_connected(true);
+        let (mut tx, mut rx) = socket.split();
+        // Socket name to watched symbol, for what's subscribed.
+        let mut subscribed: HashMap<String, String> = HashMap::new();
+        let mut heartbeat =
+            tokio::time::interval(Duration::from_secs(self.config.heartbeat_secs.max(1)));
+        loop {
+            tokio::select! {
+                _ = heartbeat.tick() => {
+                    let watched: BTreeSet<String> = engine.watched().into_iter().collect();
+                    let added: Vec<String> = watched
+                        .iter()
+                        .filter(|s| !subscribed.values().any(|w| w == *s))
+                        .cloned()
+                        .collect();
+                    let removed: Vec<String> = subscribed
+                        .iter()
+                        .filter(|(_, s)| !watched.contains(*s))
+                        .map(|(name, _)| name.clone())
+                        .collect();
+                    if !added.is_empty() {
+                        let names: Vec<String> = added.iter().map(|s| stream_symbol(s)).collect();
+                        let message = json!({"action": "subscribe", "params": {"symbols": names.join(",")}});
+                        tx.send(Message::Text(message.to_string())).await?;
+                        subscribed.extend(names.into_iter().zip(added));
+                    }
+                    if !removed.is_empty() {
+                        let message = json!({"action": "unsubscribe", "params": {"symbols": removed.join(",")}});
+                        tx.send(Message::Text(message.to_string())).await?;
+                        let mut live = self.live.lock().unwrap();
+                        for name in &removed {
+                            if let Some(symbol) = subscribed.remove(name) {
+                                live.remove(&symbol);
+                            }
+                        }
+                    }
+                    tx.send(Message::Text(json!({"action": "heartbeat"}).to_string())).await?;
+                }
+                message = rx.next() => match message {
+                    Some(Ok(Message::Text(text))) => self.handle(&text, &subscribed, engine),
+                    Some(Ok(Message::Close(_))) | None => return Err(StreamError::Closed),
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(e.into()),
+                },
+            }
+        }
+    }
+
+    fn handle(&self, text: &str, subscribed: &HashMap<String, String>, engine: &Engine) {
+        let resolve = |symbol: &str, exchange: &Option<String>| {
+            exchange
+                .as_ref()
+                .and_then(|e| subscribed.get(&format!("{}:{}", symbol, e)))
+                .or_else(|| subscribed.get(symbol))
+                .cloned()
+        };
+        match serde_json::from_str::<StreamEvent>(text) {
+            Ok(StreamEvent::Price {
+                symbol,
+                exchange,
+                price,
+                timestamp,
+            }) => {
+                let Some(watched) = resolve(&symbol, &exchange) else {
+                    debug!(symbol, "Tick for a symbol no longer subscribed");
+                    return;
+                };
+                self.mark(&watched);
+                engine.ingest(
+                    &watched,
+                    Quote {
+                        price,
+                        timestamp: timestamp.and_then(|t| DateTime::from_timestamp(t, 0)),
+                        day: None,
+                    },
+                );
+            }
+            Ok(StreamEvent::SubscribeStatus { success, fails }) => {
+                for s in &success {
+                    if let Some(watched) = resolve(&s.symbol, &s.exchange) {
+                        self.mark(&watched);
+                    }
+                }
+                if !fails.is_empty() {
+                    let symbols: Vec<&str> = fails.iter().map(|s| s.symbol.as_str()).collect();
+                    warn!(?symbols, "Stream refused symbols, they stay on polling");
+                }
+                info!(
+                    subscribed = success.len(),
+                    failed = fails.len(),
+                    "Price stream subscribed"
+                );
+            }
+            Ok(StreamEvent::Other) => {}
+            Err(e) => debug!(error = %e, "Unexpected stream message"),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::any::Any;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use tokio::task::JoinHandle;
+use tracing::{error, warn};
+
+use crate::metrics;
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// A run this long counts as healthy and resets the backoff.
+const HEALTHY_RUN: Duration = Duration::from_secs(300);
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("non-string panic payload")
+}
+
+/// Runs a long-lived task built by `factory`, restarting it with
+/// exponential backoff whenever it panics or returns. Every restart is
+/// logged and counted in `task_restarts_total`.
+pub fn spawn<F, Fut>(name: &'static str, factory: F) -> JoinHandle<()>
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut backoff = MIN_BACKOFF;
+        loop {
+            let started = Instant::now();
+            let reason = match tokio::spawn(factory()).await {
+                Ok(()) => {
+                    warn!(task = name, "Supervised task exited");
+                    "exit"
+                }
+                Err(e) if e.is_panic() => {
+                    let payload = e.into_panic();
+                    error!(
+                        task = name,
+                        panic = panic_message(payload.as_ref()),
+                        "Supervised task panicked"
+                    );
+                    "panic"
+                }
+                Err(_) => return,
+            };
+            if started.elapsed() >= HEALTHY_RUN {
+                backoff = MIN_BACKOFF;
+            }
+            metrics::update_task_restart(name, reason);
+            warn!(
+                task = name,
+                backoff_secs = backoff.as_secs(),
+                "Restarting task"
+            );
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    })
+}
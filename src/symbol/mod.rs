@@ -0,0 +1,173 @@
+use crate::{AssetClass, StockMarket};
+
+const SUFFIXES: &[(&str, StockMarket)] = &[
+    ("L", StockMarket::LSE),
+    ("DE", StockMarket::XETR),
+    ("F", StockMarket::XETR),
+    ("PA", StockMarket::Euronext),
+    ("AS", StockMarket::Euronext),
+    ("BR", StockMarket::Euronext),
+    ("LS", StockMarket::Euronext),
+    ("MI", StockMarket::Euronext),
+    ("SW", StockMarket::SIX),
+    ("T", StockMarket::JPX),
+    ("HK", StockMarket::HKEX),
+];
+
+// Exchange codes (ISO 10383) to suffixes; US venues take none.
+const MICS: &[(&str, &str)] = &[
+    ("XNYS", ""),
+    ("XNAS", ""),
+    ("ARCX", ""),
+    ("BATS", ""),
+    ("XASE", ""),
+    ("XLON", "L"),
+    ("XETR", "DE"),
+    ("XFRA", "F"),
+    ("XPAR", "PA"),
+    ("XAMS", "AS"),
+    ("XBRU", "BR"),
+    ("XLIS", "LS"),
+    ("XMIL", "MI"),
+    ("XSWX", "SW"),
+    ("XJPX", "T"),
+    ("XTKS", "T"),
+    ("XHKG", "HK"),
+];
+
+/// The ticker for `base` listed on the exchange with code `mic`, `None`
+/// for exchanges fintek doesn't follow.
+pub fn listing(base: &str, mic: &str) -> Option<String> {
+    let (_, suffix) = MICS.iter().find(|(code, _)| *code == mic)?;
+    Some(if suffix.is_empty() {
+        base.to_string()
+    } else {
+        format!("{}.{}", base, suffix)
+    })
+}
+
+/// The exchange with code `mic`, US venues besides Nasdaq counting as the
+/// NYSE, whose hours they keep.
+pub fn mic_exchange(mic: &str) -> Option<StockMarket> {
+    let (_, suffix) = MICS.iter().find(|(code, _)| *code == mic)?;
+    Some(match *suffix {
+        "" if mic == "XNAS" => StockMarket::NASDAQ,
+        "" => StockMarket::NYSE,
+        suffix => SUFFIXES.iter().find(|(s, _)| *s == suffix)?.1,
+    })
+}
+
+/// True for a known exchange suffix, or none at all.
+pub fn is_supported(symbol: &str) -> bool {
+    match symbol.rsplit_once('.') {
+        Some((_, suffix)) => SUFFIXES.iter().any(|(s, _)| *s == suffix),
+        None => true,
+    }
+}
+
+const INDEXES: &[(&str, StockMarket)] = &[
+    ("SPX", StockMarket::NYSE),
+    ("DJI", StockMarket::NYSE),
+    ("RUT", StockMarket::NYSE),
+    ("VIX", StockMarket::NYSE),
+    ("NDX", StockMarket::NASDAQ),
+    ("IXIC", StockMarket::NASDAQ),
+    ("FTSE", StockMarket::LSE),
+    ("DAX", StockMarket::XETR),
+    ("CAC", StockMarket::Euronext),
+    ("SMI", StockMarket::SIX),
+    ("N225", StockMarket::JPX),
+    ("HSI", StockMarket::HKEX),
+];
+
+/// The exchange whose hours an index follows.
+pub fn index_exchange(symbol: &str) -> Option<StockMarket> {
+    INDEXES
+        .iter()
+        .find(|(index, _)| *index == symbol)
+        .map(|(_, exchange)| *exchange)
+}
+
+/// Government bond yields quoted as country and tenor, like `US10Y` or `DE2Y`.
+pub fn is_yield(symbol: &str) -> bool {
+    let Some(tenor) = symbol.strip_suffix('Y') else {
+        return false;
+    };
+    let (country, years) = tenor.split_at(tenor.len().min(2));
+    country.len() == 2
+        && country.chars().all(|c| c.is_ascii_uppercase())
+        && !years.is_empty()
+        && years.chars().all(|c| c.is_ascii_digit())
+}
+
+/// A ticker split into the provider symbol and the exchange implied by a
+/// Yahoo-style suffix, so `VOD.L` is `VOD` on the LSE and `7203.T` is `7203`
+/// in Tokyo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolInfo {
+    pub base: String,
+    pub exchange: Option<StockMarket>,
+    pub asset_class: AssetClass,
+}
+
+impl SymbolInfo {
+    pub fn parse(symbol: &str) -> Self {
+        let asset_class = AssetClass::of(symbol);
+        if let Some(exchange) = index_exchange(symbol) {
+            return SymbolInfo {
+                base: symbol.to_string(),
+                exchange: Some(exchange),
+                asset_class,
+            };
+        }
+        if let Some((base, suffix)) = symbol.rsplit_once('.') {
+            if let Some((_, exchange)) = SUFFIXES.iter().find(|(s, _)| *s == suffix) {
+                return SymbolInfo {
+                    base: base.to_string(),
+                    exchange: Some(*exchange),
+                    asset_class,
+                };
+            }
+        }
+        SymbolInfo {
+            base: symbol.to_string(),
+            exchange: crate::tagged_exchange(symbol),
+            asset_class,
+        }
+    }
+
+    /// The currency prices are quoted in: the exchange's for listings, the
+    /// quote side for pairs. Yields are in percent and indexes in points.
+    pub fn currency(&self) -> String {
+        match self.asset_class {
+            AssetClass::Rate => "percent".into(),
+            AssetClass::Index => "points".into(),
+            AssetClass::Forex | AssetClass::Crypto => self
+                .base
+                .rsplit_once('/')
+                .map(|(_, quote)| quote.to_string())
+                .unwrap_or_else(|| "USD".into()),
+            _ => self.exchange.map(currency).unwrap_or("USD").into(),
+        }
+    }
+}
+
+pub fn currency(exchange: StockMarket) -> &'static str {
+    match exchange {
+        StockMarket::NYSE | StockMarket::NASDAQ => "USD",
+        // London quotes most equities in pence
+        StockMarket::LSE => "GBX",
+        StockMarket::XETR | StockMarket::Euronext => "EUR",
+        StockMarket::SIX => "CHF",
+        StockMarket::JPX => "JPY",
+        StockMarket::HKEX => "HKD",
+    }
+}
+
+pub fn region(exchange: StockMarket) -> &'static str {
+    match exchange {
+        StockMarket::NYSE | StockMarket::NASDAQ => "North America",
+        StockMarket::LSE | StockMarket::XETR | StockMarket::Euronext | StockMarket::SIX => "Europe",
+        StockMarket::JPX | StockMarket::HKEX => "Asia",
+    }
+}
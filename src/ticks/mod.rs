@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+use tracing::{info, warn};
+
+use crate::engine::Engine;
+use crate::provider::Quote;
+
+/// One line of a tick feed. Recordings, and the price events of
+/// `--events-stdout`, read as is.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Tick {
+    pub symbol: String,
+    pub price: f64,
+    /// When the price was quoted. Taken as received when missing.
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+/// Feeds each JSON line of `input` to `engine` as a fetched price, so
+/// indicators, alerts and metrics see it as they would a provider's, until
+/// the input ends. Lines that aren't ticks are skipped. Returns the ticks
+/// fed.
+pub async fn feed<R: AsyncBufRead + Unpin>(engine: &Engine, input: R) -> usize {
+    let mut lines = input.lines();
+    let (mut fed, mut skipped, mut number) = (0, 0, 0);
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(e) => {
+                warn!(error = %e, "Failed to read ticks, stopping");
+                break;
+            }
+        };
+        number += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Tick>(&line) {
+            Ok(tick) => {
+                engine.ingest(
+                    &tick.symbol,
+                    Quote {
+                        price: tick.price,
+                        timestamp: tick.timestamp,
+                        day: None,
+                    },
+                );
+                fed += 1;
+            }
+            Err(e) => {
+                skipped += 1;
+                warn!(line = number, error = %e, "Skipping unreadable tick");
+            }
+        }
+    }
+    info!(fed, skipped, "Tick input ended");
+    fed
+}
@@ -0,0 +1,201 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc, Weekday};
+use serde::Serialize;
+use tracing::{error, info, warn};
+
+use crate::engine::Engine;
+use crate::history::{Adjustment, History, Resolution};
+use crate::metrics;
+use crate::{AssetClass, PricePoint};
+
+use super::{fetch_days, TimeSeriesConfig};
+
+/// Days still missing from a symbol's daily closes after a repair.
+#[derive(Debug, Clone, Serialize)]
+pub struct Gaps {
+    pub symbol: String,
+    pub missing: Vec<NaiveDate>,
+    /// Filled by the last scan.
+    pub repaired: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GapReport {
+    pub scanned_at: Option<DateTime<Utc>>,
+    /// Only symbols with gaps left.
+    pub symbols: Vec<Gaps>,
+}
+
+/// Finds trading days missing from the stored daily closes, such as from
+/// downtime, and fetches them again through the rate limiter. Days the
+/// provider has no candle for either, like exchange holidays, aren't asked
+/// for again and stay in the report.
+pub struct GapRepair {
+    config: TimeSeriesConfig,
+    history: Arc<History>,
+    unfillable: Mutex<HashMap<String, BTreeSet<NaiveDate>>>,
+    report: RwLock<GapReport>,
+}
+
+impl GapRepair {
+    pub fn new(config: &TimeSeriesConfig, history: Arc<History>) -> Self {
+        GapRepair {
+            config: config.clone(),
+            history,
+            unfillable: Mutex::new(HashMap::new()),
+            report: RwLock::new(GapReport::default()),
+        }
+    }
+
+    pub fn report(&self) -> GapReport {
+        self.report.read().unwrap().clone()
+    }
+
+    /// Days within the lookback, from the first stored close up to the
+    /// open day, that have no close. Weekends count for crypto only.
+    pub fn missing(&self, symbol: &str, now: DateTime<Utc>) -> Vec<NaiveDate> {
+        let conventions = self.history.conventions();
+        let closes = self
+            .history
+            .query(
+                symbol,
+                DateTime::<Utc>::MIN_UTC,
+                now,
+                Resolution::Day,
+                Adjustment::Raw,
+            )
+            .points;
+        let Some(first) = closes.first() else {
+            return vec![];
+        };
+        let stored: BTreeSet<NaiveDate> = closes
+            .iter()
+            .map(|p| conventions.day_of(p.timestamp))
+            .collect();
+        let today = conventions.day_of(now);
+        let lookback = chrono::Duration::try_days(self.config.gap_lookback_days.max(0));
+        let start = lookback
+            .and_then(|d| today.checked_sub_signed(d))
+            .unwrap_or(today)
+            .max(conventions.day_of(first.timestamp));
+        let every_day = AssetClass::of(symbol) == AssetClass::Crypto;
+        start
+            .iter_days()
+            .take_while(|day| *day < today)
+            .filter(|day| every_day || !matches!(day.weekday(), Weekday::Sat | Weekday::Sun))
+            // Days the conventions never assign a close to.
+            .filter(|day| conventions.day_of(conventions.day_start(*day)) == *day)
+            .filter(|day| !stored.contains(day))
+            .collect()
+    }
+
+    // Fetches the span of the days worth asking for in one request.
+    // Returns how many were filled.
+    async fn repair(
+        &self,
+        engine: &Engine,
+        symbol: &str,
+        missing: &[NaiveDate],
+        base_url: &str,
+        api_key: &str,
+    ) -> usize {
+        let missing: Vec<NaiveDate> = {
+            let unfillable = self.unfillable.lock().unwrap();
+            let skipped = unfillable.get(symbol);
+            missing
+                .iter()
+                .copied()
+                .filter(|day| skipped.is_none_or(|s| !s.contains(day)))
+                .collect()
+        };
+        let (Some(start), Some(end)) = (missing.first(), missing.last()) else {
+            return 0;
+        };
+        engine.limiter().acquire(1).await;
+        let candles = match fetch_days(base_url, symbol, *start, *end, api_key).await {
+            Ok(Some(candles)) => candles,
+            Ok(None) => return 0,
+            Err(e) => {
+                warn!(symbol, error = %e, "Failed to fetch missing days");
+                return 0;
+            }
+        };
+        let conventions = self.history.conventions();
+        let closes: Vec<PricePoint> = candles
+            .iter()
+            .filter(|c| missing.contains(&conventions.day_of(c.timestamp)))
+            .map(|c| PricePoint {
+                timestamp: c.timestamp,
+                price: c.close,
+            })
+            .collect();
+        let filled: BTreeSet<NaiveDate> = closes
+            .iter()
+            .map(|p| conventions.day_of(p.timestamp))
+            .collect();
+        self.unfillable
+            .lock()
+            .unwrap()
+            .entry(symbol.to_string())
+            .or_default()
+            .extend(missing.iter().filter(|day| !filled.contains(day)));
+        match self.history.fill_days(symbol, &closes) {
+            Ok(added) => added,
+            Err(e) => {
+                error!(symbol, error = %e, "Failed to write repaired closes");
+                0
+            }
+        }
+    }
+
+    /// Scans and repairs every symbol once, replacing the report.
+    pub async fn scan(&self, engine: &Engine, symbols: &[String], base_url: &str, api_key: &str) {
+        let mut report = vec![];
+        let mut total = 0;
+        for symbol in symbols {
+            let missing = self.missing(symbol, engine.clock().now());
+            let repaired = self
+                .repair(engine, symbol, &missing, base_url, api_key)
+                .await;
+            total += repaired;
+            metrics::update_gaps_repaired(repaired);
+            let missing = self.missing(symbol, engine.clock().now());
+            metrics::update_history_gaps(symbol, missing.len());
+            if !missing.is_empty() {
+                report.push(Gaps {
+                    symbol: symbol.clone(),
+                    missing,
+                    repaired,
+                });
+            }
+        }
+        let remaining: usize = report.iter().map(|gaps| gaps.missing.len()).sum();
+        info!(repaired = total, remaining, "Scanned history for gaps");
+        *self.report.write().unwrap() = GapReport {
+            scanned_at: Some(engine.clock().now()),
+            symbols: report,
+        };
+    }
+
+    pub async fn run(self: Arc<Self>, engine: Arc<Engine>, base_url: String, api_key: String) {
+        loop {
+            // Everything the engine polls, pinned and namespace symbols
+            // included. Empty until its first cycle, so wait for that.
+            let symbols = engine.watched();
+            if symbols.is_empty() {
+                engine.clock().sleep(Duration::from_secs(10)).await;
+                continue;
+            }
+            self.scan(&engine, &symbols, &base_url, &api_key).await;
+            engine
+                .clock()
+                .sleep(Duration::from_secs(
+                    self.config.gap_scan_hours.max(1) * 3600,
+                ))
+                .await;
+        }
+    }
+}
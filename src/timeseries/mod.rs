@@ -0,0 +1,230 @@
+pub mod gaps;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use reqwest::Error;
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument, warn};
+
+use crate::engine::Engine;
+use crate::history::Adjustment;
+use crate::provider::endpoints::{self, Endpoint, TimeSeriesValue};
+use crate::sink::PriceUpdate;
+
+/// Most candles one request returns.
+pub const MAX_OUTPUT_SIZE: u32 = 5000;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TimeSeriesConfig {
+    /// Fetch candles for every ticker on startup and feed their closes to
+    /// history and the indicators, one request per symbol.
+    pub backfill: bool,
+    /// Bar size, e.g. `1min`, `1h` or `1day`.
+    pub interval: String,
+    pub output_size: u32,
+    /// Look for trading days missing from the stored daily closes and
+    /// fetch them again.
+    pub repair_gaps: bool,
+    /// How far back gaps are looked for.
+    pub gap_lookback_days: i64,
+    pub gap_scan_hours: u64,
+}
+
+impl Default for TimeSeriesConfig {
+    fn default() -> Self {
+        TimeSeriesConfig {
+            backfill: false,
+            interval: "1day".into(),
+            output_size: 30,
+            repair_gaps: false,
+            gap_lookback_days: 30,
+            gap_scan_hours: 24,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Candle {
+    /// Start of the bar.
+    pub timestamp: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    /// Zero where the provider has none, e.g. forex.
+    pub volume: f64,
+}
+
+fn timestamp(datetime: &str, tz: Tz) -> Option<DateTime<Utc>> {
+    let local = NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(datetime, "%Y-%m-%d")
+                .ok()?
+                .and_hms_opt(0, 0, 0)
+        })?;
+    tz.from_local_datetime(&local)
+        .earliest()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+fn candle(value: &TimeSeriesValue, tz: Tz) -> Option<Candle> {
+    Some(Candle {
+        timestamp: timestamp(&value.datetime, tz)?,
+        open: value.open,
+        high: value.high,
+        low: value.low,
+        close: value.close,
+        volume: value.volume,
+    })
+}
+
+/// Up to `output_size` candles of `interval` for `symbol`, oldest first.
+#[instrument(skip(api_key))]
+pub async fn fetch_candles(
+    base_url: &str,
+    symbol: &str,
+    interval: &str,
+    output_size: u32,
+    api_key: &str,
+) -> Result<Vec<Candle>, Error> {
+    let query = format!(
+        "symbol={}&interval={}&outputsize={}",
+        symbol,
+        interval,
+        output_size.clamp(1, MAX_OUTPUT_SIZE)
+    );
+    Ok(fetch(base_url, symbol, &query, api_key)
+        .await?
+        .unwrap_or_default())
+}
+
+/// Daily candles from `start` through `end`, oldest first. `None` when the
+/// provider turned the request down, as opposed to having no candles.
+#[instrument(skip(api_key))]
+pub async fn fetch_days(
+    base_url: &str,
+    symbol: &str,
+    start: NaiveDate,
+    end: NaiveDate,
+    api_key: &str,
+) -> Result<Option<Vec<Candle>>, Error> {
+    // The end date is exclusive.
+    let query = format!(
+        "symbol={}&interval=1day&start_date={}&end_date={}&outputsize={}",
+        symbol,
+        start,
+        end.succ_opt().unwrap_or(end),
+        MAX_OUTPUT_SIZE
+    );
+    fetch(base_url, symbol, &query, api_key).await
+}
+
+async fn fetch(
+    base_url: &str,
+    symbol: &str,
+    query: &str,
+    api_key: &str,
+) -> Result<Option<Vec<Candle>>, Error> {
+    let url = endpoints::url::<endpoints::TimeSeries>(base_url, query, api_key);
+    let data = reqwest::get(&url).await?.text().await?;
+    let response = match endpoints::parse::<<endpoints::TimeSeries as Endpoint>::Response>(&data) {
+        Ok(Ok(response)) => response,
+        Ok(Err(error)) => {
+            warn!(symbol, code = error.code, message = %error.message, "Time series request failed");
+            return Ok(None);
+        }
+        Err(e) => {
+            warn!(symbol, error = %e, "Unexpected time series response");
+            return Ok(None);
+        }
+    };
+    let tz = response
+        .meta
+        .exchange_timezone
+        .and_then(|tz| tz.parse::<Tz>().ok())
+        .unwrap_or(Tz::UTC);
+    let mut candles: Vec<Candle> = response
+        .values
+        .iter()
+        .filter_map(|v| candle(v, tz))
+        .collect();
+    candles.sort_by_key(|c| c.timestamp);
+    Ok(Some(candles))
+}
+
+/// Mean close of the last `period` candles.
+pub fn sma(candles: &[Candle], period: usize) -> Option<f64> {
+    if period == 0 || candles.len() < period {
+        return None;
+    }
+    let window = &candles[candles.len() - period..];
+    Some(window.iter().map(|c| c.close).sum::<f64>() / period as f64)
+}
+
+/// Exponential moving average of the closes, seeded with the SMA of the
+/// first `period` candles.
+pub fn ema(candles: &[Candle], period: usize) -> Option<f64> {
+    let seed = sma(&candles[..period.min(candles.len())], period)?;
+    let alpha = 2. / (period as f64 + 1.);
+    Some(
+        candles[period..]
+            .iter()
+            .fold(seed, |ema, c| ema + alpha * (c.close - ema)),
+    )
+}
+
+/// Backfills every symbol without any history yet, so one restored from
+/// the event log isn't given points older than the ones it holds.
+pub async fn backfill(
+    engine: &Engine,
+    config: &TimeSeriesConfig,
+    base_url: &str,
+    api_key: &str,
+    symbols: &[String],
+) {
+    for symbol in symbols {
+        let known = engine.history().is_some_and(|history| {
+            !history
+                .since(symbol, DateTime::<Utc>::MIN_UTC, Adjustment::Raw)
+                .is_empty()
+        });
+        if known {
+            continue;
+        }
+        engine.limiter().acquire(1).await;
+        let candles = match fetch_candles(
+            base_url,
+            symbol,
+            &config.interval,
+            config.output_size,
+            api_key,
+        )
+        .await
+        {
+            Ok(candles) => candles,
+            Err(e) => {
+                warn!(symbol, error = %e, "Failed to backfill");
+                continue;
+            }
+        };
+        let updates: Vec<PriceUpdate> = candles
+            .iter()
+            .map(|c| PriceUpdate {
+                symbol: symbol.clone(),
+                price: c.close,
+                timestamp: c.timestamp,
+                provider_timestamp: None,
+                day: None,
+            })
+            .collect();
+        engine.backfill(&updates);
+        info!(
+            symbol,
+            candles = candles.len(),
+            sma = ?sma(&candles, candles.len().min(20)),
+            "Backfilled price history"
+        );
+    }
+}
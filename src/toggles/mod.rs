@@ -0,0 +1,106 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::{metrics, recovery};
+
+pub const TOGGLES_FILE: &str = "toggles.json";
+
+/// Price writes to the store, toggled next to the sinks.
+pub const STORAGE: &str = "storage";
+
+#[derive(Debug, Error)]
+pub enum ToggleError {
+    #[error("toggles: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("toggles: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("no subsystem {0:?}")]
+    NotFound(String),
+}
+
+#[derive(Default)]
+struct State {
+    known: BTreeSet<String>,
+    disabled: BTreeSet<String>,
+}
+
+/// Subsystems switched off at runtime, such as a sink shedding load during
+/// an incident. The switched-off set survives restarts in the state
+/// directory.
+pub struct Toggles {
+    path: PathBuf,
+    state: RwLock<State>,
+}
+
+impl Toggles {
+    /// A broken file leaves everything on.
+    pub fn load(state_dir: &Path) -> Self {
+        let path = state_dir.join(TOGGLES_FILE);
+        let disabled = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                warn!(path = %path.display(), error = %e, "Ignoring corrupt toggles");
+                BTreeSet::new()
+            }),
+            Err(_) => BTreeSet::new(),
+        };
+        if !disabled.is_empty() {
+            warn!(disabled = ?disabled, "Subsystems switched off");
+        }
+        Toggles {
+            path,
+            state: RwLock::new(State {
+                known: BTreeSet::new(),
+                disabled,
+            }),
+        }
+    }
+
+    /// Makes `name` toggleable.
+    pub fn register(&self, name: &str) {
+        let mut state = self.state.write().unwrap();
+        state.known.insert(name.to_string());
+        metrics::update_feature_enabled(name, !state.disabled.contains(name));
+    }
+
+    pub fn enabled(&self, name: &str) -> bool {
+        !self.state.read().unwrap().disabled.contains(name)
+    }
+
+    /// Every registered subsystem and whether it is on.
+    pub fn list(&self) -> BTreeMap<String, bool> {
+        let state = self.state.read().unwrap();
+        state
+            .known
+            .iter()
+            .map(|name| (name.clone(), !state.disabled.contains(name)))
+            .collect()
+    }
+
+    pub fn set(&self, name: &str, enabled: bool) -> Result<(), ToggleError> {
+        let mut state = self.state.write().unwrap();
+        if !state.known.contains(name) {
+            return Err(ToggleError::NotFound(name.to_string()));
+        }
+        let mut disabled = state.disabled.clone();
+        if enabled {
+            disabled.remove(name);
+        } else {
+            disabled.insert(name.to_string());
+        }
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&disabled)?)?;
+        recovery::backup(&self.path)?;
+        std::fs::rename(&tmp, &self.path)?;
+        state.disabled = disabled;
+        info!(name, enabled, "Toggled subsystem");
+        metrics::update_feature_enabled(name, enabled);
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::engine::CycleContext;
+use crate::metrics;
+use crate::paper::PaperAccount;
+use crate::returns::Conventions;
+use crate::sink::{PriceUpdate, Sink};
+
+const TRACKING_FILE: &str = "tracking.json";
+const TRADING_DAYS: f64 = 252.;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TrackingConfig {
+    /// Symbol the paper portfolio is measured against, polled alongside the
+    /// watchlist. Tracking is off without one.
+    pub benchmark: Option<String>,
+    /// Daily returns in the rolling window, a quarter of trading by default.
+    pub window_days: usize,
+}
+
+impl Default for TrackingConfig {
+    fn default() -> Self {
+        TrackingConfig {
+            benchmark: None,
+            window_days: 63,
+        }
+    }
+}
+
+/// Closing paper equity and benchmark price of one day.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+pub struct DailyMark {
+    pub equity: Option<f64>,
+    pub benchmark: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackingReport {
+    pub benchmark: String,
+    /// Daily return pairs the figures are computed from.
+    pub days: usize,
+    /// Annualized portfolio and benchmark returns.
+    pub portfolio_return: f64,
+    pub benchmark_return: f64,
+    /// Annualized standard deviation of the daily active return.
+    pub tracking_error: f64,
+    /// Annualized active return over the tracking error.
+    pub information_ratio: Option<f64>,
+}
+
+/// Keeps a daily mark of paper equity and the benchmark in
+/// `<state_dir>/tracking.json`, and derives rolling tracking error and
+/// information ratio from their returns.
+pub struct BenchmarkTracker {
+    config: TrackingConfig,
+    benchmark: String,
+    paper: Arc<PaperAccount>,
+    path: PathBuf,
+    marks: Mutex<BTreeMap<NaiveDate, DailyMark>>,
+    conventions: Conventions,
+}
+
+impl BenchmarkTracker {
+    /// `None` when no benchmark is configured.
+    pub fn new(
+        config: &TrackingConfig,
+        paper: Arc<PaperAccount>,
+        state_dir: &Path,
+    ) -> Option<Self> {
+        let benchmark = config.benchmark.clone()?;
+        let path = state_dir.join(TRACKING_FILE);
+        let marks = std::fs::read(&path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        Some(BenchmarkTracker {
+            config: config.clone(),
+            benchmark,
+            paper,
+            path,
+            marks: Mutex::new(marks),
+            conventions: Conventions::default(),
+        })
+    }
+
+    /// Dates marks by the trading day of record.
+    pub fn with_conventions(mut self, conventions: Conventions) -> Self {
+        self.conventions = conventions;
+        self
+    }
+
+    pub fn benchmark(&self) -> &str {
+        &self.benchmark
+    }
+
+    fn save(&self, marks: &BTreeMap<NaiveDate, DailyMark>) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(marks)?)?;
+        std::fs::rename(&tmp, &self.path)
+    }
+
+    // The last mark of a day stands as its close.
+    fn mark(&self, date: NaiveDate, update: impl FnOnce(&mut DailyMark)) {
+        let mut marks = self.marks.lock().unwrap();
+        update(marks.entry(date).or_default());
+    }
+
+    /// Daily portfolio and benchmark returns over the window, oldest first.
+    pub fn returns(&self) -> Vec<(f64, f64)> {
+        let marks = self.marks.lock().unwrap();
+        let closes: Vec<(f64, f64)> = marks
+            .values()
+            .filter_map(|m| Some((m.equity?, m.benchmark?)))
+            .collect();
+        let mut returns: Vec<(f64, f64)> = closes
+            .windows(2)
+            .filter(|w| w[0].0 > 0. && w[0].1 > 0.)
+            .map(|w| (w[1].0 / w[0].0 - 1., w[1].1 / w[0].1 - 1.))
+            .collect();
+        let skip = returns.len().saturating_sub(self.config.window_days);
+        returns.drain(..skip);
+        returns
+    }
+
+    /// `None` until there are two daily returns to compare.
+    pub fn report(&self) -> Option<TrackingReport> {
+        let returns = self.returns();
+        if returns.len() < 2 {
+            return None;
+        }
+        let n = returns.len() as f64;
+        let mean = |values: &mut dyn Iterator<Item = f64>| values.sum::<f64>() / n;
+        let portfolio = mean(&mut returns.iter().map(|r| r.0));
+        let benchmark = mean(&mut returns.iter().map(|r| r.1));
+        let active = portfolio - benchmark;
+        let variance = returns
+            .iter()
+            .map(|(p, b)| (p - b - active).powi(2))
+            .sum::<f64>()
+            / (n - 1.);
+        let tracking_error = variance.sqrt() * TRADING_DAYS.sqrt();
+        Some(TrackingReport {
+            benchmark: self.benchmark.clone(),
+            days: returns.len(),
+            portfolio_return: portfolio * TRADING_DAYS,
+            benchmark_return: benchmark * TRADING_DAYS,
+            tracking_error,
+            information_ratio: (tracking_error > 0.)
+                .then(|| active * TRADING_DAYS / tracking_error),
+        })
+    }
+}
+
+impl Sink for BenchmarkTracker {
+    fn record(&self, update: &PriceUpdate) {
+        if update.symbol == self.benchmark {
+            self.mark(self.conventions.day_of(update.timestamp), |m| {
+                m.benchmark = Some(update.price)
+            });
+        }
+    }
+
+    fn on_cycle(&self, cycle: &CycleContext) {
+        let equity = self.paper.portfolio().equity;
+        self.mark(self.conventions.day_of(cycle.now), |m| {
+            m.equity = Some(equity)
+        });
+        // Once a cycle, so a restart keeps the latest close of the day.
+        if let Err(e) = self.save(&self.marks.lock().unwrap()) {
+            error!(path = %self.path.display(), error = %e, "Failed to save tracking marks");
+        }
+        if let Some(report) = self.report() {
+            metrics::update_tracking(
+                &report.benchmark,
+                report.tracking_error,
+                report.information_ratio,
+            );
+        }
+    }
+}
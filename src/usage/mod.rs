@@ -0,0 +1,379 @@
+use chrono::NaiveTime;
+use serde::Serialize;
+
+use crate::calendar::{self, Session};
+use crate::config::{Config, RateLimit};
+use crate::listings::Consolidator;
+use crate::priority::{self, CycleBudget, OverBudget};
+use crate::provider::ProviderKind;
+use crate::symbol::SymbolInfo;
+use crate::AssetClass;
+
+const DAY_SECS: f64 = 24. * 60. * 60.;
+// Globex trades 23 hours a day.
+const COMMODITY_SECS: f64 = 23. * 60. * 60.;
+
+/// Which API keys are around, since some jobs only run with one.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Keys {
+    /// Keys in `API_KEYS` besides the active one.
+    pub standbys: usize,
+    /// `FINNHUB_API_KEY` is set.
+    pub finnhub: bool,
+}
+
+/// One kind of traffic and what it costs on a trading day.
+#[derive(Debug, Clone, Serialize)]
+pub struct Source {
+    pub provider: &'static str,
+    pub name: &'static str,
+    pub requests_per_day: f64,
+    pub credits_per_day: f64,
+    /// Requests sent back to back when the job runs, bypassing the limiter.
+    pub burst: u64,
+    // How often a burst repeats, or the span steady traffic spreads over.
+    #[serde(skip)]
+    span_secs: f64,
+}
+
+impl Source {
+    /// Requests landing in the busiest window of `period_secs`.
+    fn in_window(&self, period_secs: f64) -> f64 {
+        if self.burst > 0 {
+            self.burst as f64 * (period_secs / self.span_secs).ceil().max(1.)
+        } else {
+            self.credits_per_day * (period_secs / self.span_secs).min(1.)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LimitCheck {
+    pub requests: u64,
+    pub period_secs: u64,
+    /// Credits expected in the busiest window of the period.
+    pub estimated: f64,
+    pub exceeded: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageEstimate {
+    pub symbols: usize,
+    pub sources: Vec<Source>,
+    /// Checked against the account the price provider bills.
+    pub limits: Vec<LimitCheck>,
+    /// Against `priority.max_cycle_secs`, when set.
+    pub cycle: Option<CycleBudget>,
+    /// Whether an over-budget cycle keeps fintek from starting.
+    pub cycle_fails: bool,
+    pub warnings: Vec<String>,
+}
+
+impl UsageEstimate {
+    pub fn exceeded(&self) -> bool {
+        self.limits.iter().any(|l| l.exceeded)
+            || (self.cycle_fails && self.cycle.as_ref().is_some_and(CycleBudget::exceeded))
+    }
+}
+
+fn secs_of_day(time: NaiveTime) -> f64 {
+    (time - NaiveTime::MIN).num_seconds() as f64
+}
+
+fn session_secs(session: &Session) -> f64 {
+    let lunch = session
+        .lunch
+        .map(|(start, end)| secs_of_day(end) - secs_of_day(start))
+        .unwrap_or_default();
+    secs_of_day(session.close) - secs_of_day(session.open) - lunch
+}
+
+// Most requests `limit` lets through over `secs`.
+fn capacity(limit: &RateLimit, secs: f64) -> f64 {
+    limit.requests as f64 * (secs / limit.period_secs as f64).max(1.)
+}
+
+/// `symbols` and those the config polls whatever the tickers file says:
+/// listings, futures contracts, option underlyings and the benchmark.
+pub fn polled_symbols(config: &Config, symbols: &[String]) -> Vec<String> {
+    let mut symbols = symbols.to_vec();
+    let pinned = Consolidator::new(config.listings.clone())
+        .symbols()
+        .into_iter()
+        .chain(
+            config
+                .futures
+                .continuous
+                .iter()
+                .flat_map(|f| f.contracts.iter().map(|c| c.symbol.clone())),
+        )
+        .chain(config.options.symbols.clone())
+        .chain(config.tracking.benchmark.clone());
+    for symbol in pinned {
+        if !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
+    }
+    symbols
+}
+
+/// Estimated API traffic for `symbols` under `config` on a trading day,
+/// with every session assumed to overlap, which makes it an upper bound.
+/// Pinned symbols are added here, see [`polled_symbols`].
+pub fn estimate(config: &Config, symbols: &[String], keys: Keys) -> UsageEstimate {
+    let symbols = polled_symbols(config, symbols);
+    let primary_secs = session_secs(&calendar::session(config.exchange));
+    let open_secs = |symbol: &str| {
+        let info = SymbolInfo::parse(symbol);
+        match info.exchange {
+            _ if info.asset_class == AssetClass::Commodity => COMMODITY_SECS,
+            Some(exchange) => session_secs(&calendar::session(exchange)),
+            None => primary_secs,
+        }
+    };
+    let stocks = symbols
+        .iter()
+        .filter(|s| AssetClass::of(s) == AssetClass::Stock)
+        .count() as u64;
+    let limits: Vec<RateLimit> = config
+        .rate_limits
+        .iter()
+        .filter(|l| l.period_secs > 0)
+        .copied()
+        .collect();
+    let mut warnings = vec![];
+
+    let price_provider = match config.provider.kind {
+        ProviderKind::TwelveData => "twelvedata",
+        ProviderKind::Finnhub => "finnhub",
+        ProviderKind::Rest => "rest",
+    };
+    let batch = match config.provider.kind {
+        ProviderKind::TwelveData => config.provider.batch_size.max(1),
+        ProviderKind::Finnhub | ProviderKind::Rest => 1,
+    } as f64;
+    let interval = config.priority.interval_secs as f64;
+    let longest = symbols
+        .iter()
+        .map(|s| open_secs(s))
+        .fold(primary_secs, f64::max);
+    let (credits, cycles, cycle_span) = if interval > 0. {
+        let credits: f64 = symbols.iter().map(|s| open_secs(s) / interval).sum();
+        (credits, primary_secs / interval, primary_secs)
+    } else {
+        // Everything is due every cycle, so the limiter sets the pace and
+        // each cycle's market state lookup takes its share.
+        let budget = limits
+            .iter()
+            .map(|l| capacity(l, longest))
+            .fold(f64::INFINITY, f64::min);
+        if budget.is_finite() {
+            warnings.push(
+                "priority.interval_secs is 0, so prices use the whole rate budget; \
+                 set an interval to leave room for other jobs"
+                    .to_string(),
+            );
+        } else {
+            warnings.push(
+                "priority.interval_secs is 0 and no rate limit is configured, \
+                 polling is unbounded"
+                    .to_string(),
+            );
+        }
+        let per_cycle = symbols.len() as f64 + 1.;
+        (
+            budget * symbols.len() as f64 / per_cycle,
+            budget / per_cycle,
+            longest,
+        )
+    };
+    let credits = if credits.is_finite() { credits } else { 0. };
+    let cycles = if cycles.is_finite() { cycles } else { 0. };
+
+    let mut sources = vec![
+        Source {
+            provider: price_provider,
+            name: "prices",
+            requests_per_day: (credits / batch).ceil(),
+            credits_per_day: credits.ceil(),
+            burst: 0,
+            span_secs: longest,
+        },
+        Source {
+            provider: price_provider,
+            name: "market state",
+            requests_per_day: cycles.ceil(),
+            credits_per_day: cycles.ceil(),
+            burst: 0,
+            span_secs: cycle_span,
+        },
+    ];
+    let mut job = |provider, name, burst: u64, refresh_secs: f64| {
+        if burst == 0 || refresh_secs <= 0. {
+            return;
+        }
+        let per_day = burst as f64 * (DAY_SECS / refresh_secs).max(1.);
+        sources.push(Source {
+            provider,
+            name,
+            requests_per_day: per_day,
+            credits_per_day: per_day,
+            burst,
+            span_secs: refresh_secs,
+        });
+    };
+    job(
+        "twelvedata",
+        "splits",
+        stocks,
+        config.history.split_refresh_hours as f64 * 3600.,
+    );
+    // Calendars moved into the daily run are counted with it.
+    let daily_calendars = config.daily.enabled && config.daily.calendars;
+    if config.corporate.enabled && !daily_calendars {
+        job(
+            "twelvedata",
+            "corporate events",
+            2 * stocks,
+            config.corporate.refresh_hours as f64 * 3600.,
+        );
+    }
+    if config.daily.enabled {
+        let mut run = symbols.len() as u64;
+        if config.daily.fundamentals {
+            run += stocks;
+        }
+        if daily_calendars && config.corporate.enabled {
+            run += 2 * stocks;
+        }
+        job(
+            "twelvedata",
+            "daily pipeline",
+            run.min(config.daily.budget),
+            DAY_SECS,
+        );
+    }
+    if config.movers.enabled {
+        job("twelvedata", "market movers", 2, DAY_SECS);
+    }
+    if config.analysts.enabled {
+        job(
+            "twelvedata",
+            "analyst consensus",
+            2 * stocks,
+            config.analysts.refresh_hours.max(1) as f64 * 3600.,
+        );
+    }
+    if !config.options.symbols.is_empty() {
+        // The expirations, then a chain per expiration.
+        let per_symbol = 1 + config.options.expirations.max(1) as u64;
+        job(
+            "twelvedata",
+            "options chains",
+            per_symbol * config.options.symbols.len() as u64,
+            config.options.refresh_secs.max(60) as f64,
+        );
+    }
+    if config.provider.kind == ProviderKind::TwelveData {
+        job(
+            "twelvedata",
+            "key probes",
+            keys.standbys as u64,
+            config.keys.probe_interval_secs as f64,
+        );
+    }
+    if keys.finnhub {
+        job("finnhub", "economic calendar", 1, 60. * 60.);
+    }
+    if config.edgar.enabled {
+        // The company index once, then a request per stock each poll.
+        job(
+            "sec",
+            "EDGAR filings",
+            stocks,
+            config.edgar.poll_secs.max(60) as f64,
+        );
+    }
+    if config.market.fear_greed {
+        job(
+            "alternative.me",
+            "fear & greed index",
+            1,
+            config.market.refresh_secs.max(60) as f64,
+        );
+    }
+    for source in &config.social.sources {
+        job(
+            source.name(),
+            "social buzz",
+            stocks,
+            config.social.poll_secs.max(60) as f64,
+        );
+    }
+
+    if config.timeseries.backfill {
+        // Once per start and through the limiter, so never a burst.
+        sources.push(Source {
+            provider: "twelvedata",
+            name: "backfill",
+            requests_per_day: symbols.len() as f64,
+            credits_per_day: symbols.len() as f64,
+            burst: 0,
+            span_secs: DAY_SECS,
+        });
+    }
+    if config.timeseries.repair_gaps {
+        // At most one request per symbol a scan, only for symbols with gaps.
+        let scans = 24. / config.timeseries.gap_scan_hours.max(1) as f64;
+        sources.push(Source {
+            provider: "twelvedata",
+            name: "gap repair",
+            requests_per_day: symbols.len() as f64 * scans,
+            credits_per_day: symbols.len() as f64 * scans,
+            burst: 0,
+            span_secs: DAY_SECS,
+        });
+    }
+
+    let limits: Vec<LimitCheck> = limits
+        .iter()
+        .map(|limit| {
+            let estimated: f64 = sources
+                .iter()
+                .filter(|s| s.provider == price_provider)
+                .map(|s| s.in_window(limit.period_secs as f64))
+                .sum();
+            LimitCheck {
+                requests: limit.requests,
+                period_secs: limit.period_secs,
+                estimated: estimated.ceil(),
+                exceeded: estimated > limit.requests as f64 + 0.5,
+            }
+        })
+        .collect();
+    for source in sources.iter().filter(|s| s.provider == price_provider) {
+        if let Some(limit) = config
+            .rate_limits
+            .iter()
+            .find(|l| l.period_secs > 0 && source.burst > l.requests)
+        {
+            warnings.push(format!(
+                "{} sends {} requests at once, over the limit of {} per {}s",
+                source.name, source.burst, limit.requests, limit.period_secs
+            ));
+        }
+    }
+    let cycle = priority::cycle_budget(
+        symbols.len(),
+        &config.priority,
+        priority::budget(&config.rate_limits),
+    );
+    UsageEstimate {
+        symbols: symbols.len(),
+        sources,
+        limits,
+        cycle,
+        cycle_fails: config.priority.over_budget == OverBudget::Fail,
+        warnings,
+    }
+}
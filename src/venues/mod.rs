@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::engine::Engine;
+use crate::metrics;
+use crate::sink::{PriceUpdate, Sink};
+use crate::AssetClass;
+
+/// What trading a crypto pair on one exchange costs on top of the mid.
+/// Providers only report the mid, so the spread is the venue's usual one.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Venue {
+    pub name: String,
+    /// Pairs traded there; every crypto pair when empty.
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    /// Taker fee, in percent of the notional.
+    #[serde(default)]
+    pub fee_percent: f64,
+    /// Ask minus bid, in percent of the mid.
+    #[serde(default)]
+    pub spread_percent: f64,
+    /// The provider's name for the exchange, e.g. `Binance`, to poll the
+    /// venue's own price of each pair. Priced off the feed's mid without.
+    #[serde(default)]
+    pub exchange: Option<String>,
+    /// How often the venue's prices are polled, with an `exchange`.
+    #[serde(default = "default_interval")]
+    pub interval_secs: u64,
+}
+
+fn default_interval() -> u64 {
+    60
+}
+
+impl Venue {
+    fn trades(&self, symbol: &str) -> bool {
+        if self.symbols.is_empty() {
+            AssetClass::of(symbol) == AssetClass::Crypto
+        } else {
+            self.symbols.iter().any(|s| s == symbol)
+        }
+    }
+
+    /// Paid per unit to buy and received per unit to sell at `mid`.
+    pub fn effective(&self, mid: f64) -> (f64, f64) {
+        let half_spread = self.spread_percent / 200.;
+        let fee = self.fee_percent / 100.;
+        (
+            mid * (1. + half_spread) * (1. + fee),
+            mid * (1. - half_spread) * (1. - fee),
+        )
+    }
+
+    /// The bid and ask quoted around `mid`, before fees.
+    pub fn quote(&self, mid: f64) -> (f64, f64) {
+        let half_spread = self.spread_percent / 200.;
+        (mid * (1. - half_spread), mid * (1. + half_spread))
+    }
+
+    // A polled price missing a few rounds no longer counts as the venue's.
+    fn max_age(&self) -> Option<Duration> {
+        self.exchange
+            .as_ref()
+            .map(|_| Duration::seconds(3 * self.interval_secs.max(1) as i64))
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    // Mid per symbol and venue index, with when it was seen.
+    mids: HashMap<(String, usize), (f64, DateTime<Utc>)>,
+    // Venues exported as best bid and ask per symbol.
+    best: HashMap<String, [Option<String>; 2]>,
+}
+
+/// Exports what buying and selling each crypto pair would cost on each
+/// configured venue, next to the mid in `stock_price`, and the best bid
+/// and ask across venues with the venue quoting each.
+pub struct VenuePricer {
+    venues: Vec<Venue>,
+    state: Mutex<State>,
+}
+
+impl VenuePricer {
+    /// `None` without venues.
+    pub fn new(venues: &[Venue]) -> Option<Self> {
+        if venues.is_empty() {
+            return None;
+        }
+        Some(VenuePricer {
+            venues: venues.to_vec(),
+            state: Mutex::new(State::default()),
+        })
+    }
+
+    /// Whether any venue is polled for its own prices.
+    pub fn polls(&self) -> bool {
+        self.venues.iter().any(|v| v.exchange.is_some())
+    }
+
+    fn observe(&self, symbol: &str, venue: usize, mid: f64, at: DateTime<Utc>) {
+        let mut state = self.state.lock().unwrap();
+        state.mids.insert((symbol.to_string(), venue), (mid, at));
+        let (buy, sell) = self.venues[venue].effective(mid);
+        metrics::update_effective_price(symbol, &self.venues[venue].name, buy, sell);
+        self.aggregate(&mut state, symbol, at);
+    }
+
+    // Re-exports the best bid and ask of `symbol` over the venues with a
+    // fresh price, and the mid between them.
+    fn aggregate(&self, state: &mut State, symbol: &str, now: DateTime<Utc>) {
+        let mut bid: Option<(&str, f64)> = None;
+        let mut ask: Option<(&str, f64)> = None;
+        for (i, venue) in self.venues.iter().enumerate() {
+            let Some((mid, at)) = state.mids.get(&(symbol.to_string(), i)) else {
+                continue;
+            };
+            if venue.max_age().is_some_and(|age| now - *at > age) {
+                continue;
+            }
+            let (b, a) = venue.quote(*mid);
+            if bid.is_none_or(|(_, best)| b > best) {
+                bid = Some((&venue.name, b));
+            }
+            if ask.is_none_or(|(_, best)| a < best) {
+                ask = Some((&venue.name, a));
+            }
+        }
+        let exported = state.best.entry(symbol.to_string()).or_default();
+        for (previous, (side, best)) in exported.iter_mut().zip([("bid", bid), ("ask", ask)]) {
+            metrics::update_best_price(symbol, side, previous.as_deref(), best);
+            *previous = best.map(|(venue, _)| venue.to_string());
+        }
+        metrics::update_consolidated_mid(symbol, bid.zip(ask).map(|((_, b), (_, a))| (b + a) / 2.));
+    }
+
+    /// Polls the venues with an `exchange` for their own price of the pairs
+    /// they trade, on the main provider's credits.
+    pub async fn run(self: Arc<Self>, engine: Arc<Engine>) {
+        let clock = engine.clock().clone();
+        let polled: Vec<usize> = (0..self.venues.len())
+            .filter(|i| self.venues[*i].exchange.is_some())
+            .collect();
+        let names: Vec<&str> = polled
+            .iter()
+            .map(|i| self.venues[*i].name.as_str())
+            .collect();
+        info!(venues = ?names, "Polling venue prices");
+        let mut due = vec![clock.now(); self.venues.len()];
+        loop {
+            for &i in &polled {
+                let venue = &self.venues[i];
+                if due[i] > clock.now() {
+                    continue;
+                }
+                let exchange = venue.exchange.as_deref().unwrap_or_default();
+                let symbols: Vec<String> = if venue.symbols.is_empty() {
+                    engine.watched()
+                } else {
+                    venue.symbols.clone()
+                };
+                for symbol in symbols.iter().filter(|s| venue.trades(s)) {
+                    engine.limiter().acquire(1).await;
+                    match engine.provider().fetch_venue_price(symbol, exchange).await {
+                        Ok(Some(quote)) => self.observe(symbol, i, quote.price, clock.now()),
+                        Ok(None) => debug!(symbol, venue = %venue.name, "No venue price"),
+                        Err(e) => {
+                            warn!(symbol, venue = %venue.name, error = %e, "Venue price fetch failed")
+                        }
+                    }
+                }
+                due[i] = clock.now() + Duration::seconds(venue.interval_secs.max(1) as i64);
+            }
+            let next = polled
+                .iter()
+                .map(|i| due[*i])
+                .min()
+                .unwrap_or_else(|| clock.now());
+            clock
+                .sleep((next - clock.now()).to_std().unwrap_or_default())
+                .await;
+        }
+    }
+}
+
+impl Sink for VenuePricer {
+    fn record(&self, update: &PriceUpdate) {
+        for (i, venue) in self.venues.iter().enumerate() {
+            if venue.exchange.is_none() && venue.trades(&update.symbol) {
+                self.observe(&update.symbol, i, update.price, update.timestamp);
+            }
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use serde::Serialize;
+
+/// What this binary was built from, stamped in by the build script.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git_sha: &'static str,
+    pub rustc: &'static str,
+    pub build_date: &'static str,
+}
+
+pub const BUILD: BuildInfo = BuildInfo {
+    version: env!("CARGO_PKG_VERSION"),
+    git_sha: env!("FINTEK_GIT_SHA"),
+    rustc: env!("FINTEK_RUSTC"),
+    build_date: env!("FINTEK_BUILD_DATE"),
+};
@@ -0,0 +1,91 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::clock::Clock;
+use crate::metrics;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    pub enabled: bool,
+    /// How long a cycle may run past its scheduled sleeps before the poll
+    /// loop counts as stuck and is restarted.
+    pub stall_secs: u64,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        WatchdogConfig {
+            enabled: true,
+            stall_secs: 600,
+        }
+    }
+}
+
+/// Deadline the poll loop re-arms as it goes. Crossing it means a cycle
+/// hung, e.g. on an await that never resolves.
+pub struct Watchdog {
+    stall: Duration,
+    clock: Arc<dyn Clock>,
+    deadline: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl Watchdog {
+    /// `None` when disabled.
+    pub fn new(config: &WatchdogConfig, clock: Arc<dyn Clock>) -> Option<Self> {
+        if !config.enabled || config.stall_secs == 0 {
+            return None;
+        }
+        Some(Watchdog {
+            stall: Duration::from_secs(config.stall_secs),
+            clock,
+            deadline: Mutex::new(None),
+        })
+    }
+
+    /// Expects the loop to check in again within `idle`, an intended sleep,
+    /// plus the stall bound. No deadline past what dates can hold.
+    pub fn arm(&self, idle: Duration) {
+        let deadline = chrono::Duration::from_std(idle.saturating_add(self.stall))
+            .ok()
+            .and_then(|bound| self.clock.now().checked_add_signed(bound));
+        *self.deadline.lock().unwrap() = deadline;
+    }
+
+    /// Like [`arm`](Self::arm), but only ever moves the deadline later, for
+    /// waits that may overlap others in flight.
+    pub fn extend(&self, idle: Duration) {
+        let later = chrono::Duration::from_std(idle.saturating_add(self.stall))
+            .ok()
+            .and_then(|bound| self.clock.now().checked_add_signed(bound));
+        let mut deadline = self.deadline.lock().unwrap();
+        if let (Some(current), Some(later)) = (*deadline, later) {
+            *deadline = Some(current.max(later));
+        } else if later.is_none() {
+            *deadline = None;
+        }
+    }
+
+    fn overdue(&self) -> bool {
+        let now = self.clock.now();
+        self.deadline.lock().unwrap().is_some_and(|d| now > d)
+    }
+
+    /// Resolves once the deadline passes, counting the trip and disarming
+    /// until the loop re-arms.
+    pub async fn tripped(&self) {
+        while !self.overdue() {
+            self.clock.sleep(Duration::from_secs(1)).await;
+        }
+        *self.deadline.lock().unwrap() = None;
+        error!(
+            stall_secs = self.stall.as_secs(),
+            "Poll loop missed its deadline"
+        );
+        metrics::update_watchdog_trip();
+    }
+}
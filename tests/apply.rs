@@ -0,0 +1,85 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{TimeZone, Utc};
+use fintek::clock::VirtualClock;
+use fintek::engine::apply::{Applier, ApplyConfig, ChangeOutcome, ChangeSource};
+use fintek::events::{Event, EventBus};
+use fintek::limits::{Limits, LimitsConfig};
+use fintek::sink::{FetchOutcome, Sink};
+use fintek::Tickers;
+
+fn outcomes(events: &mut tokio::sync::mpsc::UnboundedReceiver<Event>) -> Vec<ChangeOutcome> {
+    let mut outcomes = vec![];
+    while let Ok(event) = events.try_recv() {
+        if let Event::ConfigChange { outcome, .. } = event {
+            outcomes.push(outcome);
+        }
+    }
+    outcomes
+}
+
+#[tokio::test]
+async fn rolls_back_when_fetches_fail_and_confirms_otherwise() {
+    let dir = std::env::temp_dir().join(format!("fintek-apply-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    fintek::set_tickers_path(&dir.join("tickers.json"));
+    let start = Utc.with_ymd_and_hms(2026, 10, 15, 14, 30, 0).unwrap();
+    let clock = Arc::new(VirtualClock::new(start));
+    let bus = EventBus::default();
+    let mut events = bus.subscribe();
+    let config = ApplyConfig {
+        grace_secs: 60,
+        max_failures: 3,
+    };
+    let applier = Applier::new(&config, clock.clone(), bus);
+    let limits = Limits::new(&LimitsConfig {
+        max_symbols: 3,
+        ..LimitsConfig::default()
+    });
+
+    let good = Tickers::new(vec!["AAPL".into()]);
+    let mut tickers = good.clone();
+    let bad = Tickers::new(vec!["AAPL".into(), "NOPE".into()]);
+    applier
+        .stage(&mut tickers, bad.clone(), ChangeSource::Reload, &limits)
+        .unwrap();
+    assert_eq!(tickers, bad);
+    for _ in 0..3 {
+        applier.on_fetch("NOPE", FetchOutcome::Failure, start);
+    }
+    applier.check(&mut tickers).await;
+    assert_eq!(tickers, good);
+    assert_eq!(
+        fintek::read_tickers().await.unwrap().get_tickers(),
+        good.get_tickers()
+    );
+    assert_eq!(
+        outcomes(&mut events),
+        [ChangeOutcome::Applied, ChangeOutcome::RolledBack]
+    );
+
+    let too_many = Tickers::new(vec!["A".into(), "B".into(), "C".into(), "D".into()]);
+    assert!(applier
+        .stage(&mut tickers, too_many, ChangeSource::Api, &limits)
+        .is_err());
+    assert_eq!(tickers, good);
+
+    let fine = Tickers::new(vec!["AAPL".into(), "MSFT".into()]);
+    applier
+        .stage(&mut tickers, fine.clone(), ChangeSource::Api, &limits)
+        .unwrap();
+    applier.on_fetch("MSFT", FetchOutcome::Success, start);
+    clock.advance(Duration::from_secs(61));
+    applier.check(&mut tickers).await;
+    assert_eq!(tickers, fine);
+    assert_eq!(
+        outcomes(&mut events),
+        [
+            ChangeOutcome::Rejected,
+            ChangeOutcome::Applied,
+            ChangeOutcome::Confirmed
+        ]
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
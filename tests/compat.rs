@@ -0,0 +1,71 @@
+#![allow(deprecated)]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{TimeZone, Utc};
+use fintek::clock::VirtualClock;
+use fintek::config::RateLimit;
+use fintek::provider::{Provider, TwelveData};
+use fintek::ratelimit::RateLimiter;
+use fintek::{calculate_sleep_duration, should_sleep, Markets, StockMarket};
+
+// Whether fetching once every `pause` seconds always finds room in a
+// limiter enforcing `limits`.
+fn fits(limits: [(u64, u64); 2], pause: u64) -> bool {
+    let limits = limits.map(|(requests, period_secs)| RateLimit {
+        requests,
+        period_secs,
+    });
+    let start = Utc.with_ymd_and_hms(2024, 3, 4, 0, 0, 0).unwrap();
+    let clock = Arc::new(VirtualClock::new(start));
+    let limiter = RateLimiter::new(&limits, clock.clone());
+    (0..1_001).all(|_| {
+        let room = limiter.try_acquire(1);
+        clock.advance(Duration::from_secs(pause));
+        room
+    })
+}
+
+#[test]
+fn sleep_duration_is_the_pace_the_rate_limiter_allows() {
+    assert_eq!(calculate_sleep_duration(0, 8, 60, 800, 86_400), None);
+    assert_eq!(calculate_sleep_duration(8, 0, 60, 800, 86_400), None);
+    assert_eq!(calculate_sleep_duration(8, 8, 0, 800, 0), Some(0));
+    for (r1, p1, r2, p2) in [
+        (8, 60, 800, 86_400),
+        (8, 60, 800, 23_400),
+        (8, 60, 100_000, 86_400),
+        (5, 1, 1_000, 3_600),
+    ] {
+        let pause = calculate_sleep_duration(8, r1, p1, r2, p2).unwrap();
+        assert!(fits([(r1, p1), (r2, p2)], pause), "{:?}", (r1, p1, r2, p2));
+        assert!(
+            !fits([(r1, p1), (r2, p2)], pause - 1),
+            "{:?}",
+            (r1, p1, r2, p2)
+        );
+    }
+}
+
+// Hits the network, so only with `FINTEK_CONTRACT_TESTS` set, as
+// tests/contract.rs.
+#[tokio::test]
+async fn should_sleep_is_the_twelvedata_market_state() {
+    if std::env::var_os("FINTEK_CONTRACT_TESTS").is_none() {
+        eprintln!("FINTEK_CONTRACT_TESTS not set, skipping should_sleep against Twelve Data");
+        return;
+    }
+    let key = std::env::var("API_KEY").unwrap_or_else(|_| "demo".into());
+    let provider = TwelveData::new(&key);
+    let market = || Markets::Stock(StockMarket::NYSE);
+
+    let expected = provider.fetch_market_state(&market()).await.unwrap();
+    let shimmed = should_sleep(market(), &key).await.unwrap();
+    assert!(
+        shimmed.abs_diff(expected) <= 5,
+        "{} vs {}",
+        shimmed,
+        expected
+    );
+}
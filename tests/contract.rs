@@ -0,0 +1,33 @@
+//! Checks the typed Twelve Data responses against the live API. Hits the
+//! network, so it only runs with `FINTEK_CONTRACT_TESTS` set, e.g. nightly:
+//!
+//! ```text
+//! FINTEK_CONTRACT_TESTS=1 API_KEY=... cargo test --test contract -- --nocapture
+//! ```
+//!
+//! Without `API_KEY` it uses Twelve Data's `demo` key, which covers AAPL.
+//! `FINTEK_CONTRACT_URL` points it at a sandbox or mirror instead.
+
+use fintek::provider::contract;
+use fintek::provider::TWELVEDATA_URL;
+
+#[tokio::test]
+async fn twelvedata_contract() {
+    if std::env::var_os("FINTEK_CONTRACT_TESTS").is_none() {
+        eprintln!("FINTEK_CONTRACT_TESTS not set, skipping provider contract checks");
+        return;
+    }
+    let base = std::env::var("FINTEK_CONTRACT_URL").unwrap_or_else(|_| TWELVEDATA_URL.into());
+    let key = std::env::var("API_KEY").unwrap_or_else(|_| "demo".into());
+
+    let reports = contract::check_all(&base, &key).await;
+    for report in &reports {
+        print!("{}", report);
+    }
+    let failed: Vec<_> = reports
+        .iter()
+        .filter(|r| !r.passed())
+        .map(|r| r.endpoint)
+        .collect();
+    assert!(failed.is_empty(), "contract broken for {:?}", failed);
+}
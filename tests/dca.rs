@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, TimeZone, Utc};
+use fintek::dca::{self, DcaPlan, Schedule};
+use fintek::PricePoint;
+
+fn daily(from: (i32, u32, u32), days: i64, price: f64) -> Vec<PricePoint> {
+    let start = Utc
+        .with_ymd_and_hms(from.0, from.1, from.2, 15, 0, 0)
+        .unwrap();
+    (0..days)
+        .map(|day| PricePoint {
+            timestamp: start + Duration::days(day),
+            price,
+        })
+        .collect()
+}
+
+fn plan(symbol: &str, schedule: Schedule) -> DcaPlan {
+    DcaPlan {
+        symbol: symbol.into(),
+        amount: 100.,
+        schedule,
+    }
+}
+
+// Jan 31, Feb 28 and Mar 31, not Mar 28: each month counts from the start.
+#[test]
+fn monthly_purchases_keep_the_start_day() {
+    let mut history = daily((2025, 1, 31), 60, 10.);
+    // Priced apart so the purchase dates show in the cost.
+    for point in &mut history {
+        if point.timestamp.format("%m-%d").to_string() == "03-31" {
+            point.price = 20.;
+        }
+    }
+    let report = dca::simulate(&plan("AAPL", Schedule::Monthly), &history).unwrap();
+    assert_eq!(report.purchases, 3);
+    assert!((report.shares - 25.).abs() < 1e-9);
+}
+
+#[test]
+fn plans_run_over_their_own_symbol() {
+    let histories = HashMap::from([
+        ("AAPL".to_string(), daily((2025, 1, 1), 30, 10.)),
+        ("MSFT".to_string(), {
+            let mut history = daily((2025, 1, 1), 30, 10.);
+            history.last_mut().unwrap().price = 20.;
+            history
+        }),
+    ]);
+    let plans = [
+        plan("AAPL", Schedule::Weekly),
+        plan("MSFT", Schedule::Weekly),
+        plan("NVDA", Schedule::Weekly),
+    ];
+    let reports = dca::compare(&plans, &histories);
+    let symbols: Vec<&str> = reports.iter().map(|r| r.symbol.as_str()).collect();
+    assert_eq!(symbols, ["MSFT", "AAPL"]);
+    assert_eq!(reports[1].total_return, 0.);
+}
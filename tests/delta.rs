@@ -0,0 +1,75 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use fintek::history::delta::{decode, encode, DeltaError};
+use fintek::PricePoint;
+
+fn ticks(start: DateTime<Utc>, step: Duration, prices: &[f64]) -> Vec<PricePoint> {
+    prices
+        .iter()
+        .enumerate()
+        .map(|(i, &price)| PricePoint {
+            timestamp: start + step * i as i32,
+            price,
+        })
+        .collect()
+}
+
+#[test]
+fn round_trips_exactly() {
+    let start =
+        Utc.with_ymd_and_hms(2026, 10, 15, 14, 30, 0).unwrap() + Duration::nanoseconds(123_456_789);
+    let blocks = [
+        ticks(
+            start,
+            Duration::seconds(1),
+            &[67_012.5, 67_013.25, 67_001.0],
+        ),
+        ticks(
+            start,
+            Duration::milliseconds(250),
+            &[0.000_031_2, 0.000_031_4],
+        ),
+        ticks(start, Duration::seconds(5), &[1.0 / 3.0, f64::MAX, -2.5]),
+        ticks(start, Duration::seconds(-1), &[100.0, 99.99]),
+        vec![],
+    ];
+    let mut data = vec![];
+    for block in &blocks {
+        encode(block, &mut data);
+    }
+    assert_eq!(decode(&data).unwrap(), blocks.concat());
+}
+
+#[test]
+fn far_smaller_than_json() {
+    let start =
+        Utc.with_ymd_and_hms(2026, 10, 15, 0, 0, 0).unwrap() + Duration::nanoseconds(987_654_321);
+    let prices: Vec<f64> = (0..3600)
+        .map(|i| 67_000.0 + ((i * 37) % 200) as f64 / 100.0)
+        .collect();
+    let points = ticks(start, Duration::seconds(1), &prices);
+    let mut data = vec![];
+    encode(&points, &mut data);
+    let json: usize = points
+        .iter()
+        .map(|p| serde_json::to_string(p).unwrap().len() + 1)
+        .sum();
+    assert!(
+        json / data.len() >= 5,
+        "{} bytes against {}",
+        data.len(),
+        json
+    );
+}
+
+#[test]
+fn stops_at_a_cut_block() {
+    let start = Utc.with_ymd_and_hms(2026, 10, 15, 0, 0, 0).unwrap();
+    let mut data = vec![];
+    encode(&ticks(start, Duration::seconds(1), &[1.5, 1.75]), &mut data);
+    let whole = data.len();
+    encode(&ticks(start, Duration::seconds(1), &[2.5, 2.75]), &mut data);
+    data.truncate(data.len() - 1);
+    assert!(matches!(decode(&data), Err(DeltaError::Truncated(_))));
+    assert_eq!(decode(&data[..whole]).unwrap().len(), 2);
+    assert_eq!(decode(&[0, 1, 2]), Err(DeltaError::Magic(0)));
+}
@@ -0,0 +1,45 @@
+use chrono::Utc;
+use fintek::events::{Event, EventBus};
+use fintek::sink::PriceUpdate;
+
+fn price(price: f64) -> Event {
+    Event::Price(PriceUpdate {
+        symbol: "AAPL".into(),
+        price,
+        timestamp: Utc::now(),
+        provider_timestamp: None,
+        day: None,
+    })
+}
+
+#[test]
+fn drops_what_a_bounded_reader_has_no_room_for() {
+    let bus = EventBus::default();
+    let mut all = bus.subscribe();
+    let mut bounded = bus.subscribe_bounded(2);
+    for i in 0..5 {
+        bus.publish(price(i as f64));
+    }
+    let prices = |events: &mut Vec<Event>| -> Vec<f64> {
+        events
+            .drain(..)
+            .filter_map(|e| match e {
+                Event::Price(update) => Some(update.price),
+                _ => None,
+            })
+            .collect()
+    };
+    let mut read = vec![];
+    while let Ok(event) = bounded.try_recv() {
+        read.push(event);
+    }
+    assert_eq!(prices(&mut read), [0., 1.]);
+    while let Ok(event) = all.try_recv() {
+        read.push(event);
+    }
+    assert_eq!(prices(&mut read), [0., 1., 2., 3., 4.]);
+
+    // Room again once read.
+    bus.publish(price(5.));
+    assert!(bounded.try_recv().is_ok());
+}
@@ -0,0 +1,43 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use chrono::{TimeZone, Utc};
+use fintek::clock::VirtualClock;
+use fintek::feed::AlertFeed;
+use fintek::notify::{Notification, NotificationKind, Notifier, Urgency};
+
+fn alert(symbol: &str) -> Notification {
+    Notification {
+        title: format!("{} crossed", symbol),
+        body: String::new(),
+        urgency: Urgency::Normal,
+        kind: NotificationKind::Alert,
+        symbols: vec![symbol.into()],
+        rule: None,
+        value: None,
+        labels: BTreeMap::new(),
+    }
+}
+
+#[tokio::test]
+async fn gives_alerts_of_the_same_time_their_own_ids() {
+    let clock = Arc::new(VirtualClock::new(
+        Utc.with_ymd_and_hms(2026, 10, 15, 14, 30, 0).unwrap(),
+    ));
+    let feed = AlertFeed::new(clock);
+    for symbol in ["AAPL", "AAPL", "MSFT"] {
+        feed.notify(&alert(symbol)).await.unwrap();
+    }
+
+    let atom = feed.to_atom(None);
+    let ids: BTreeSet<&str> = atom
+        .lines()
+        .filter(|l| l.trim().starts_with("<id>urn:fintek:alert:"))
+        .collect();
+    assert_eq!(ids.len(), 3);
+
+    let only = BTreeSet::from(["MSFT".to_string()]);
+    let atom = feed.to_atom(Some(&only));
+    assert_eq!(atom.matches("<entry>").count(), 1);
+    assert!(atom.contains("MSFT crossed"));
+}
@@ -0,0 +1,34 @@
+use async_trait::async_trait;
+use fintek::provider::{Provider, Quote};
+use fintek::Markets;
+
+// Prices everything but `BAD`, which can't be reached.
+struct Flaky;
+
+#[async_trait]
+impl Provider for Flaky {
+    fn name(&self) -> &str {
+        "flaky"
+    }
+
+    async fn fetch_price(&self, symbol: &str) -> Result<Option<Quote>, reqwest::Error> {
+        if symbol == "BAD" {
+            reqwest::get("http://127.0.0.1:1/").await?;
+        }
+        Ok(Some(Quote::new(1.)))
+    }
+
+    async fn fetch_market_state(&self, _market: &Markets) -> Result<u64, reqwest::Error> {
+        Ok(0)
+    }
+}
+
+#[tokio::test]
+async fn fails_symbols_on_their_own_and_the_call_only_when_all_do() {
+    let symbols = ["AAPL".to_string(), "BAD".to_string()];
+    let quotes = Flaky.fetch_prices(&symbols).await.unwrap();
+    assert!(matches!(quotes[0], Ok(Some(_))));
+    assert!(quotes[1].is_err());
+
+    assert!(Flaky.fetch_prices(&symbols[1..]).await.is_err());
+}
@@ -0,0 +1,17 @@
+use chrono::{Duration, TimeZone, Utc};
+use fintek::lag;
+
+#[test]
+fn keeps_falling_behind_while_stuck_on_overdue_work() {
+    let subsystem = "test_writer";
+    let now = Utc.with_ymd_and_hms(2026, 10, 15, 14, 30, 0).unwrap();
+    assert_eq!(lag::lag(subsystem, now), None);
+
+    lag::behind(subsystem, Some(now - Duration::seconds(2)));
+    assert_eq!(lag::lag(subsystem, now), Some(2.));
+    // Nothing finishes, so the lag grows with the clock.
+    assert_eq!(lag::lag(subsystem, now + Duration::seconds(30)), Some(32.));
+
+    lag::caught_up(subsystem, 3.);
+    assert_eq!(lag::lag(subsystem, now + Duration::minutes(10)), Some(3.));
+}
@@ -0,0 +1,97 @@
+use fintek::provider::endpoints::{DividendsResponse, PriceResponse};
+use fintek::provider::number::{parse, NumberLocale};
+
+#[test]
+fn plain_numbers() {
+    assert_eq!(parse("1234.5", NumberLocale::Auto), Some(1234.5));
+    assert_eq!(parse("  42 ", NumberLocale::Auto), Some(42.));
+    assert_eq!(parse("+0.25", NumberLocale::Auto), Some(0.25));
+    assert_eq!(parse("-3", NumberLocale::Auto), Some(-3.));
+    assert_eq!(parse(".5", NumberLocale::Auto), Some(0.5));
+    assert_eq!(parse(",5", NumberLocale::Auto), Some(0.5));
+    assert_eq!(parse("1.5e3", NumberLocale::Auto), Some(1500.));
+    assert_eq!(parse("2E-2", NumberLocale::Auto), Some(0.02));
+}
+
+#[test]
+fn grouping_and_decimal_marks() {
+    assert_eq!(parse("1,234.56", NumberLocale::Auto), Some(1234.56));
+    assert_eq!(parse("1.234,56", NumberLocale::Auto), Some(1234.56));
+    assert_eq!(parse("1,234,567", NumberLocale::Auto), Some(1234567.));
+    assert_eq!(parse("1.234.567", NumberLocale::Auto), Some(1234567.));
+    assert_eq!(parse("1 234,5", NumberLocale::Auto), Some(1234.5));
+    assert_eq!(parse("1\u{a0}234,5", NumberLocale::Auto), Some(1234.5));
+    assert_eq!(parse("1\u{202f}234,5", NumberLocale::Auto), Some(1234.5));
+    assert_eq!(parse("1'234.5", NumberLocale::Auto), Some(1234.5));
+    assert_eq!(parse("1_000", NumberLocale::Auto), Some(1000.));
+}
+
+#[test]
+fn ambiguous_lone_separator() {
+    // A lone dot is always decimal, a lone comma only groups before three digits.
+    assert_eq!(parse("1.234", NumberLocale::Auto), Some(1.234));
+    assert_eq!(parse("1,234", NumberLocale::Auto), Some(1234.));
+    assert_eq!(parse("0,125", NumberLocale::Auto), Some(0.125));
+    assert_eq!(parse("12,5", NumberLocale::Auto), Some(12.5));
+    assert_eq!(parse("1,2345", NumberLocale::Auto), Some(1.2345));
+}
+
+#[test]
+fn explicit_locales() {
+    assert_eq!(parse("1,234", NumberLocale::En), Some(1234.));
+    assert_eq!(parse("1,234", NumberLocale::De), Some(1.234));
+    assert_eq!(parse("1.234", NumberLocale::De), Some(1234.));
+    assert_eq!(parse("1.234,5", NumberLocale::De), Some(1234.5));
+    assert_eq!(parse("1 234,5", NumberLocale::Fr), Some(1234.5));
+    assert_eq!(parse("1'234.5", NumberLocale::Ch), Some(1234.5));
+    assert_eq!(parse("1.234,5", NumberLocale::En), None);
+}
+
+#[test]
+fn signs_and_accounting_negatives() {
+    assert_eq!(parse("(1,234.50)", NumberLocale::Auto), Some(-1234.5));
+    assert_eq!(parse("\u{2212}7.5", NumberLocale::Auto), Some(-7.5));
+    assert_eq!(parse("- 2", NumberLocale::Auto), Some(-2.));
+    assert_eq!(parse("-1.2%", NumberLocale::Auto), Some(-1.2));
+    assert_eq!(parse("3,5 %", NumberLocale::Auto), Some(3.5));
+}
+
+#[test]
+fn rejects_garbage() {
+    for text in [
+        "",
+        " ",
+        "-",
+        "abc",
+        "1.2.3,4,5",
+        "1,,",
+        "1e",
+        "1e+",
+        "NaN",
+        "inf",
+        "1..2",
+        "$12",
+        "12,5.3",
+        "1e400",
+        "1,23,456",
+        "12,34.5",
+    ] {
+        assert_eq!(parse(text, NumberLocale::Auto), None, "{:?}", text);
+    }
+}
+
+#[test]
+fn deserializes_numbers_and_strings() {
+    let price: PriceResponse = serde_json::from_str(r#"{"price": "1,234.50"}"#).unwrap();
+    assert_eq!(price.price, 1234.5);
+    let price: PriceResponse = serde_json::from_str(r#"{"price": 12.5}"#).unwrap();
+    assert_eq!(price.price, 12.5);
+    assert!(serde_json::from_str::<PriceResponse>(r#"{"price": "n/a"}"#).is_err());
+
+    let dividends: DividendsResponse = serde_json::from_str(
+        r#"{"dividends": [{"ex_date": "2024-02-09", "amount": "0,24"}, {"ex_date": "2024-05-10", "amount": 0.25}]}"#,
+    )
+    .unwrap();
+    let amounts: Vec<f64> = dividends.dividends.iter().map(|d| d.amount).collect();
+    assert_eq!(amounts, [0.24, 0.25]);
+}
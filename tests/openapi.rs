@@ -0,0 +1,9 @@
+use fintek::api::openapi::ApiDoc;
+use utoipa::OpenApi;
+
+#[test]
+fn documents_the_graphql_endpoint() {
+    let doc = ApiDoc::openapi();
+    let graphql = &doc.paths.paths["/api/v1/graphql"];
+    assert!(graphql.post.is_some());
+}
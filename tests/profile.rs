@@ -0,0 +1,61 @@
+use chrono::{Duration, TimeZone, Utc};
+use fintek::calendar;
+use fintek::profile::{build, ProfileConfig};
+use fintek::timeseries::Candle;
+use fintek::StockMarket;
+
+fn candle(minutes: i64, low: f64, high: f64, volume: f64) -> Candle {
+    let open = Utc.with_ymd_and_hms(2026, 10, 15, 13, 30, 0).unwrap();
+    Candle {
+        timestamp: open + Duration::minutes(minutes),
+        open: low,
+        high,
+        low,
+        close: high,
+        volume,
+    }
+}
+
+#[test]
+fn finds_the_point_of_control_and_value_area_of_the_last_session() {
+    let session = calendar::session(StockMarket::NYSE);
+    let config = ProfileConfig {
+        buckets: 10,
+        ..ProfileConfig::default()
+    };
+    let candles = [
+        // The day before, left out.
+        candle(-24 * 60, 50., 60., 1e9),
+        candle(0, 100., 110., 100.),
+        candle(5, 103., 104., 500.),
+        candle(10, 104., 105., 300.),
+        candle(15, 102., 103., 100.),
+    ];
+    let now = Utc::now();
+    let profile = build("AAPL", &candles, &session, &config, now).unwrap();
+
+    assert_eq!(profile.candles, 4);
+    assert_eq!(profile.volume, 1000.);
+    assert_eq!(profile.levels.len(), 10);
+    assert_eq!(profile.levels[3].volume, 510.);
+    assert_eq!(profile.point_of_control, 103.5);
+    // 510 + 310 takes the value area past 700.
+    assert_eq!(profile.value_area_low, 103.);
+    assert_eq!(profile.value_area_high, 105.);
+    let total: f64 = profile.levels.iter().map(|l| l.volume).sum();
+    assert!((total - 1000.).abs() < 1e-9);
+}
+
+#[test]
+fn has_no_profile_without_volume() {
+    let session = calendar::session(StockMarket::NYSE);
+    let candles = [candle(0, 1.1, 1.2, 0.)];
+    let profile = build(
+        "EUR/USD",
+        &candles,
+        &session,
+        &ProfileConfig::default(),
+        Utc::now(),
+    );
+    assert!(profile.is_none());
+}
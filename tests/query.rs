@@ -0,0 +1,25 @@
+use chrono::{DateTime, Utc};
+use fintek::history::{History, HistoryConfig};
+use fintek::query::{self, QueryError};
+
+#[test]
+fn rejects_windows_too_large_for_a_duration() {
+    assert!(query::duration("9999999999999999s").is_none());
+    assert!(query::parse("avg_over_time(price(\"AAPL\"), 9999999999999999s)").is_err());
+}
+
+#[test]
+fn reports_windows_reaching_past_the_earliest_time() {
+    let dir = std::env::temp_dir().join(format!("fintek-query-{}", std::process::id()));
+    let history = History::new(&HistoryConfig::default(), &dir);
+    let expr = query::parse("avg_over_time(price(\"AAPL\"), 1000w)").unwrap();
+    assert_eq!(
+        query::eval(&expr, &history, DateTime::<Utc>::MIN_UTC),
+        Err(QueryError::OutOfRange)
+    );
+    let price = query::parse("price(\"AAPL\")").unwrap();
+    assert_eq!(
+        query::eval(&price, &history, DateTime::<Utc>::MIN_UTC),
+        Err(QueryError::OutOfRange)
+    );
+}
@@ -0,0 +1,19 @@
+use fintek::auth::{TokenStore, TOKENS_FILE};
+use fintek::recovery;
+
+#[test]
+fn keeps_the_api_locked_when_the_token_store_is_corrupt() {
+    let dir = std::env::temp_dir().join(format!("fintek-recovery-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let tokens = dir.join(TOKENS_FILE);
+    std::fs::write(&tokens, "[{\"name\": \"ci\", \"sco").unwrap();
+
+    assert!(recovery::check_state(&dir.join("tickers.json"), &dir).is_err());
+    // Left for an operator, not reset or restored.
+    assert!(tokens.exists());
+    assert!(!TokenStore::new(&dir).is_open());
+
+    std::fs::write(&tokens, "[]").unwrap();
+    assert!(recovery::check_state(&dir.join("tickers.json"), &dir).is_ok());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
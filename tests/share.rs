@@ -0,0 +1,75 @@
+use fintek::alerts::{AlertsConfig, Condition};
+use fintek::indicators::IndicatorsConfig;
+use fintek::share::{self, ShareError, Shared};
+use fintek::Tickers;
+
+const CONFIG: &str = r#"# watched from home
+[indicators]
+sma = [20]
+rsi = 0
+
+[[alerts.rules]]
+symbol = "AAPL"
+when = "above"
+price = 200.0
+"#;
+
+fn shared() -> Shared {
+    let alerts: AlertsConfig = toml::from_str(
+        r#"
+        [[rules]]
+        symbol = "AAPL"
+        when = "above"
+        price = 200.0
+
+        [[rules]]
+        symbol = "MSFT"
+        when = "move"
+        percent = -5.0
+        minutes = 30
+        "#,
+    )
+    .unwrap();
+    let indicators = IndicatorsConfig {
+        sma: vec![20, 50],
+        ema: vec![12],
+        rsi: 14,
+        symbols: vec![],
+    };
+    Shared::new(
+        &Tickers::new(vec!["AAPL".into(), "MSFT".into()]),
+        &indicators,
+        &alerts,
+    )
+}
+
+#[test]
+fn exports_carry_indicators_and_alert_rules() {
+    let encoded = share::export(&shared(), b"key");
+    let imported = share::import(&encoded, b"key").unwrap();
+    assert_eq!(imported.tickers, ["AAPL", "MSFT"]);
+    assert_eq!(imported.indicators.unwrap().sma, [20, 50]);
+    assert_eq!(imported.alert_rules.len(), 2);
+    assert_eq!(
+        imported.alert_rules[1].condition,
+        Condition::Move {
+            percent: -5.0,
+            minutes: 30
+        }
+    );
+    assert!(matches!(
+        share::import(&encoded, b"other key"),
+        Err(ShareError::Signature)
+    ));
+}
+
+#[test]
+fn imports_add_only_what_the_config_lacks() {
+    let (merged, added) = share::merge_config(CONFIG, &shared()).unwrap();
+    assert_eq!(added, ["MSFT -5% in 30m"]);
+    assert!(merged.starts_with("# watched from home"));
+    let merged: toml::Value = toml::from_str(&merged).unwrap();
+    assert_eq!(merged["indicators"]["sma"], toml::Value::from(vec![20, 50]));
+    assert_eq!(merged["indicators"]["rsi"], toml::Value::from(14));
+    assert_eq!(merged["alerts"]["rules"].as_array().unwrap().len(), 2);
+}
@@ -0,0 +1,79 @@
+use chrono::{TimeZone, Utc};
+use fintek::provider::{Signer, SigningConfig, SigningScheme};
+use reqwest::{Method, Request};
+
+fn signer(scheme: SigningScheme, secret: &str) -> Signer {
+    let config = SigningConfig {
+        scheme,
+        ..SigningConfig::default()
+    };
+    Signer::new(
+        &config,
+        Some("key".into()),
+        Some(secret.into()),
+        Some("passphrase".into()),
+    )
+    .unwrap()
+    .unwrap()
+}
+
+// The worked example from Binance's API documentation.
+#[test]
+fn signs_binance_queries() {
+    let signer = signer(
+        SigningScheme::Binance,
+        "NhqPtmdSJYdKjVHjA7PZj4Mge3R5YNiP1e3UZjInClVN65XAbvqqM6A7H5fATj0j",
+    );
+    let url = "https://api.binance.com/api/v3/order?symbol=LTCBTC&side=BUY&type=LIMIT\
+               &timeInForce=GTC&quantity=1&price=0.1";
+    let mut request = Request::new(Method::POST, url.parse().unwrap());
+    signer.sign(
+        &mut request,
+        Utc.timestamp_millis_opt(1_499_827_319_559).unwrap(),
+    );
+    assert_eq!(
+        request.url().query(),
+        Some(
+            "symbol=LTCBTC&side=BUY&type=LIMIT&timeInForce=GTC&quantity=1&price=0.1\
+             &recvWindow=5000&timestamp=1499827319559\
+             &signature=c8db56825ae71d6d79447849e617115f4a920fa2acdcab2b053c4b2838bd6b71"
+        )
+    );
+    assert_eq!(request.headers()["x-mbx-apikey"], "key");
+}
+
+#[test]
+fn gives_every_request_its_own_nonce() {
+    let signer = signer(SigningScheme::Hmac, "secret");
+    let now = Utc::now();
+    let signed: Vec<Request> = (0..2)
+        .map(|_| {
+            let mut request =
+                Request::new(Method::GET, "https://example.com/balance".parse().unwrap());
+            signer.sign(&mut request, now);
+            request
+        })
+        .collect();
+    let header = |i: usize, name: &str| signed[i].headers()[name].clone();
+    assert_ne!(header(0, "x-nonce"), header(1, "x-nonce"));
+    assert_ne!(header(0, "x-signature"), header(1, "x-signature"));
+    assert_eq!(header(0, "x-timestamp"), header(1, "x-timestamp"));
+}
+
+#[test]
+fn needs_a_base64_secret_for_coinbase() {
+    let config = SigningConfig {
+        scheme: SigningScheme::Coinbase,
+        ..SigningConfig::default()
+    };
+    let signer = |secret: &str| {
+        Signer::new(
+            &config,
+            Some("key".into()),
+            Some(secret.into()),
+            Some("passphrase".into()),
+        )
+    };
+    assert!(signer("not base64!").is_err());
+    assert!(signer("c2VjcmV0").unwrap().is_some());
+}
@@ -0,0 +1,33 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{TimeZone, Utc};
+use fintek::clock::{Clock, VirtualClock};
+use fintek::config::RateLimit;
+use fintek::ratelimit::RateLimiter;
+use fintek::watchdog::{Watchdog, WatchdogConfig};
+
+#[tokio::test]
+async fn waits_for_rate_limit_credits_are_not_stalls() {
+    let start = Utc.with_ymd_and_hms(2026, 10, 15, 14, 30, 0).unwrap();
+    let clock = Arc::new(VirtualClock::new(start));
+    let config = WatchdogConfig {
+        enabled: true,
+        stall_secs: 10,
+    };
+    let watchdog = Watchdog::new(&config, clock.clone()).unwrap();
+    let limit = RateLimit {
+        requests: 1,
+        period_secs: 60,
+    };
+    let limiter = RateLimiter::new(&[limit], clock.clone());
+
+    watchdog.arm(Duration::ZERO);
+    limiter.acquire(1).await;
+    limiter.acquire_with(1, |wait| watchdog.extend(wait)).await;
+    assert!(clock.now() >= start + chrono::Duration::seconds(60));
+
+    // Due the stall bound after the minute spent waiting, not before.
+    watchdog.tripped().await;
+    assert!(clock.now() > start + chrono::Duration::seconds(70));
+}